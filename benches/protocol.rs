@@ -0,0 +1,99 @@
+//! Times the hottest protocol operations, VarInts and packet framing, with
+//! both the `Protocol` trait and the slice based fast paths.
+//!
+//! Usage: `cargo bench --bench protocol`
+
+extern crate hematite_server as hem;
+
+use std::io::Cursor;
+use std::time::Instant;
+
+use hem::packet::play::clientbound::{ChunkData, Packet};
+use hem::packet::{read_frame, PacketRead, PacketWrite, Protocol};
+use hem::types::{Chunk, ChunkColumn, Var};
+
+/// Runs `f` `iters` times, printing how long each run took on average.
+/// What `f` returns is summed and printed so that none of it is optimized
+/// away.
+fn bench<F: FnMut() -> usize>(name: &str, iters: u32, mut f: F) {
+    // Warm up
+    for _ in 0..iters / 10 {
+        f();
+    }
+    let start = Instant::now();
+    let mut sum = 0usize;
+    for _ in 0..iters {
+        sum = sum.wrapping_add(f());
+    }
+    let elapsed = start.elapsed();
+    let nanos = elapsed.as_secs() * 1_000_000_000 + elapsed.subsec_nanos() as u64;
+    println!("{:<32} {:>12} ns/iter ({})", name, nanos / iters as u64, sum);
+}
+
+/// Values spread over every VarInt length.
+fn values() -> Vec<i32> {
+    (0..1024).map(|i: i32| i.wrapping_mul(0x9e37_79b9u32 as i32) >> (i % 32)).collect()
+}
+
+fn main() {
+    let values = values();
+    let mut encoded = vec![];
+    for value in &values {
+        <Var<i32> as Protocol>::proto_encode(value, &mut encoded).unwrap();
+    }
+
+    bench("var_i32 encode, Protocol", 10_000, || {
+        let mut dst = Vec::with_capacity(values.len() * Var::<i32>::MAX_LEN);
+        for value in &values {
+            <Var<i32> as Protocol>::proto_encode(value, &mut dst).unwrap();
+        }
+        dst.len()
+    });
+    bench("var_i32 encode, encode_into", 10_000, || {
+        let mut dst = vec![0; values.len() * Var::<i32>::MAX_LEN];
+        let mut len = 0;
+        for value in &values {
+            len += Var::<i32>::encode_into(*value, &mut dst[len..]);
+        }
+        len
+    });
+    bench("var_i32 decode, Protocol", 10_000, || {
+        let mut src = Cursor::new(&encoded[..]);
+        let mut sum = 0usize;
+        for _ in 0..values.len() {
+            sum = sum.wrapping_add(<Var<i32> as Protocol>::proto_decode(&mut src).unwrap() as usize);
+        }
+        sum
+    });
+    bench("var_i32 decode, decode_from", 10_000, || {
+        let mut pos = 0;
+        let mut sum = 0usize;
+        for _ in 0..values.len() {
+            let (value, len) = Var::<i32>::decode_from(&encoded[pos..]).unwrap();
+            pos += len;
+            sum = sum.wrapping_add(value as usize);
+        }
+        sum
+    });
+
+    // A full column, the biggest packet sent often
+    let column = ChunkColumn { chunks: (0..16).map(|_| Chunk::new(1 << 4, 0xff)).collect(), biomes: Some([1; 256]) };
+    let packet = ChunkData { x: 3, z: -7, continuous: true, mask: column.mask(), chunk_data: column.encode().unwrap() };
+    let mut frame = vec![];
+    packet.write(&mut frame).unwrap();
+
+    bench("chunk data write", 1_000, || {
+        let mut dst = vec![];
+        packet.write(&mut dst).unwrap();
+        dst.len()
+    });
+    bench("chunk data read_frame", 1_000, || {
+        read_frame(&mut Cursor::new(&frame[..])).unwrap().len()
+    });
+    bench("chunk data read", 1_000, || {
+        match Packet::read(&mut Cursor::new(&frame[..])).unwrap() {
+            Packet::ChunkData(chunk) => chunk.chunk_data.len(),
+            _ => unreachable!()
+        }
+    });
+}
@@ -0,0 +1,163 @@
+//! `#[derive(Protocol)]` for structs made of `Protocol` types, encoded one
+//! field after the other in the order they're declared.
+//!
+//! Fields are encoded as their own type, which must then be its own `Clean`
+//! type. Fields whose wire type is another one say so with
+//! `#[protocol(Type)]`:
+//!
+//! ```ignore
+//! #[derive(Debug, Protocol)]
+//! pub struct Stat {
+//!     pub name: String,
+//!     #[protocol(Var<i32>)]
+//!     pub value: i32
+//! }
+//! ```
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, Spacing, TokenStream, TokenTree};
+
+/// A field of the struct, with the type it's encoded as.
+struct Field {
+    name: String,
+    wire_type: String
+}
+
+#[proc_macro_derive(Protocol, attributes(protocol))]
+pub fn derive_protocol(input: TokenStream) -> TokenStream {
+    let code = match parse_struct(input) {
+        Ok((name, fields)) => expand(&name, &fields),
+        Err(err) => format!("compile_error!({:?});", err)
+    };
+    code.parse().unwrap()
+}
+
+/// The name and fields of the struct `input` declares.
+fn parse_struct(input: TokenStream) -> Result<(String, Vec<Field>), String> {
+    let mut tokens = input.into_iter();
+    // Attributes and visibility come first
+    loop {
+        match tokens.next() {
+            Some(TokenTree::Ident(ref ident)) if ident.to_string() == "struct" => break,
+            Some(TokenTree::Ident(ref ident)) if ident.to_string() == "enum" || ident.to_string() == "union" => {
+                return Err("#[derive(Protocol)] only works on structs".to_string());
+            }
+            Some(_) => {}
+            None => return Err("expected a struct".to_string())
+        }
+    }
+    let name = match tokens.next() {
+        Some(TokenTree::Ident(ident)) => ident.to_string(),
+        _ => return Err("expected the name of the struct".to_string())
+    };
+    match tokens.next() {
+        Some(TokenTree::Group(ref body)) if body.delimiter() == Delimiter::Brace => {
+            Ok((name, parse_fields(body.stream())?))
+        }
+        Some(TokenTree::Punct(ref punct)) if punct.as_char() == ';' => Ok((name, vec![])),
+        Some(TokenTree::Punct(ref punct)) if punct.as_char() == '<' => {
+            Err("#[derive(Protocol)] doesn't support generic structs".to_string())
+        }
+        _ => Err("#[derive(Protocol)] needs named fields".to_string())
+    }
+}
+
+/// Fields declared in the braces of a struct.
+fn parse_fields(body: TokenStream) -> Result<Vec<Field>, String> {
+    // Fields are split by commas, but not those between type parameters,
+    // which aren't grouped like those between parentheses
+    let mut declarations = vec![vec![]];
+    let mut depth = 0;
+    let mut arrow = false;
+    for token in body {
+        if let TokenTree::Punct(ref punct) = token {
+            match punct.as_char() {
+                ',' if depth == 0 => {
+                    declarations.push(vec![]);
+                    continue;
+                }
+                '<' => depth += 1,
+                '>' if !arrow => depth -= 1,
+                _ => {}
+            }
+        }
+        arrow = match token {
+            TokenTree::Punct(ref punct) => punct.as_char() == '-' && punct.spacing() == Spacing::Joint,
+            _ => false
+        };
+        declarations.last_mut().unwrap().push(token);
+    }
+    declarations.into_iter().filter(|tokens| !tokens.is_empty()).map(parse_field).collect()
+}
+
+fn parse_field(tokens: Vec<TokenTree>) -> Result<Field, String> {
+    let mut wire_type = None;
+    let mut tokens = tokens.into_iter().peekable();
+    let name = loop {
+        match tokens.next() {
+            // An attribute, maybe #[protocol(Type)]
+            Some(TokenTree::Punct(ref punct)) if punct.as_char() == '#' => {
+                if let Some(TokenTree::Group(attr)) = tokens.next() {
+                    let mut attr = attr.stream().into_iter();
+                    match (attr.next(), attr.next()) {
+                        (Some(TokenTree::Ident(ref ident)), Some(TokenTree::Group(ref ty))) if ident.to_string() == "protocol" => {
+                            wire_type = Some(ty.stream().to_string());
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            // Visibility, maybe `pub(crate)`
+            Some(TokenTree::Ident(ref ident)) if ident.to_string() == "pub" => {
+                if let Some(TokenTree::Group(group)) = tokens.peek() {
+                    if group.delimiter() == Delimiter::Parenthesis {
+                        tokens.next();
+                    }
+                }
+            }
+            Some(TokenTree::Ident(ident)) => break ident.to_string(),
+            _ => return Err("#[derive(Protocol)] needs named fields".to_string())
+        }
+    };
+    match tokens.next() {
+        Some(TokenTree::Punct(ref punct)) if punct.as_char() == ':' => {}
+        _ => return Err(format!("expected the type of field {}", name))
+    }
+    let ty = tokens.collect::<TokenStream>().to_string();
+    Ok(Field { name, wire_type: wire_type.unwrap_or(ty) })
+}
+
+fn expand(name: &str, fields: &[Field]) -> String {
+    let protocol = "::hematite_server::packet::Protocol";
+    let mut len = String::from("0");
+    let mut encode = String::new();
+    let mut decode = String::new();
+    for field in fields {
+        len.push_str(&format!(" + <{} as {}>::proto_len(&value.{})", field.wire_type, protocol, field.name));
+        encode.push_str(&format!("<{} as {}>::proto_encode(&value.{}, dst)?;\n", field.wire_type, protocol, field.name));
+        decode.push_str(&format!("{}: <{} as {}>::proto_decode(src)?,\n", field.name, field.wire_type, protocol));
+    }
+    // Without fields, the arguments aren't used
+    let (value, dst, src) = if fields.is_empty() { ("_", "_", "_") } else { ("value", "dst", "src") };
+    format!("
+        impl {protocol} for {name} {{
+            type Clean = {name};
+
+            fn proto_len({value}: &{name}) -> usize {{
+                {len}
+            }}
+
+            fn proto_encode({value}: &{name}, {dst}: &mut dyn std::io::Write) -> std::io::Result<()> {{
+                {encode}
+                Ok(())
+            }}
+
+            fn proto_decode({src}: &mut dyn std::io::Read) -> std::io::Result<{name}> {{
+                Ok({name} {{
+                    {decode}
+                }})
+            }}
+        }}
+    ", protocol = protocol, name = name, value = value, dst = dst, src = src, len = len, encode = encode, decode = decode)
+}
@@ -0,0 +1,44 @@
+//! Shows the status of a Minecraft server, like the server list does.
+//!
+//! Usage: `cargo run --example ping --no-default-features --features slp-client -- <host> [port]`
+
+extern crate hematite_server as hem;
+
+use std::env;
+use std::process;
+
+use hem::proto::slp;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let host = match args.next() {
+        Some(host) => host,
+        None => {
+            println!("Usage: ping <host> [port]");
+            process::exit(2);
+        }
+    };
+    // Without a port, the server's SRV record is looked up like vanilla does
+    let port = match args.next().map(|port| port.parse()) {
+        Some(Ok(port)) => Some(port),
+        Some(Err(_)) => {
+            println!("Invalid port");
+            process::exit(2);
+        }
+        None => None
+    };
+    match slp::query(&host, port) {
+        Ok((response, elapsed)) => {
+            println!("{} ({}, protocol {})", response.description, response.version.name, response.version.protocol);
+            println!("{}/{} players online", response.players.online, response.players.max);
+            for sample in response.players.sample.unwrap_or_else(Vec::new) {
+                println!("  {}", sample.name);
+            }
+            println!("Ping: {} ms", elapsed);
+        }
+        Err(err) => {
+            println!("Couldn't reach {}: {}", host, err);
+            process::exit(1);
+        }
+    }
+}
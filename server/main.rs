@@ -2,10 +2,16 @@ extern crate hematite_server as hem;
 #[macro_use]
 extern crate log;
 
+use std::env;
+use std::io::{self, BufRead};
 use std::net::TcpListener;
+use std::path::Path;
+use std::process;
 use std::sync::Arc;
 use std::thread;
 
+use hem::mca;
+use hem::proto::properties::Properties;
 use hem::vanilla::Server;
 
 use log::{Level, LevelFilter, Metadata, Record, SetLoggerError};
@@ -34,28 +40,83 @@ fn init_logger() -> Result<(), SetLoggerError> {
     Ok(())
 }
 
+/// Checks the region files of the world, rewriting them compactly if `fix`
+/// is set, then exits. The server mustn't be running.
+fn check_regions(fix: bool) -> ! {
+    let path = Path::new("server.properties");
+    let props = if path.exists() {
+        Properties::load(path).expect("failed reading server.properties")
+    } else {
+        Properties::default()
+    };
+    let reports = match mca::check_world(Path::new(&props.level_name), fix) {
+        Ok(reports) => reports,
+        Err(err) => {
+            error!("failed checking regions of {}: {}", props.level_name, err);
+            process::exit(1);
+        }
+    };
+    let mut lost = 0;
+    for (path, report) in reports {
+        info!("{}: {} chunks, {} wasted sectors", path.display(), report.chunks, report.wasted_sectors);
+        for ((x, z), problem) in report.problems {
+            warn!("{}: chunk {}, {}: {:?}", path.display(), x, z, problem);
+        }
+        for (x, z) in report.lost.iter().cloned() {
+            error!("{}: chunk {}, {} can't be recovered{}", path.display(), x, z, if fix { " and was dropped" } else { "" });
+        }
+        lost += report.lost.len();
+    }
+    process::exit(if lost > 0 && !fix { 1 } else { 0 });
+}
+
+/// Reads commands typed in the console: `save-all` saves the worlds and
+/// `stop` saves them and exits.
+fn start_console(server: Arc<Server>) {
+    thread::Builder::new().name("Console".to_string()).spawn(move || {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            match line.as_ref().map(|line| line.trim()) {
+                Ok("stop") => server.stop(),
+                Ok("save-all") => {
+                    if let Err(err) = server.save_all() {
+                        error!("failed saving the worlds: {}", err);
+                    }
+                }
+                Ok("") => {}
+                Ok(other) => info!("unknown console command {:?}, try save-all or stop", other),
+                Err(_) => break
+            }
+        }
+    }).expect("failed starting console");
+}
+
 fn main () {
     init_logger().expect("failed to initialize logger");
 
     info!("hematite server");
 
+    match env::args().nth(1).as_ref().map(|arg| &arg[..]) {
+        Some("--check-regions") => check_regions(false),
+        Some("--repair-regions") => check_regions(true),
+        _ => {}
+    }
+
     let server = Server::new().expect("failed new server");
 
     let listener = TcpListener::bind(&(server.addr(), server.port())).expect("failed tcp bind");
     // NOTE(toqueteos): As soon as we need &mut server reference this won't work
     let server_ref = Arc::new(server);
-    // Accept connections and process them, spawning a new tasks for each one
+    server_ref.install_crash_handler();
+    server_ref.start_ticking().expect("failed starting tick threads");
+    server_ref.start_watchdog().expect("failed starting watchdog");
+    server_ref.start_backups().expect("failed starting backups");
+    server_ref.start_autosave().expect("failed starting autosave");
+    start_console(server_ref.clone());
+    // Accept connections and process them, each on a thread of its own
     for conn in listener.incoming() {
         match conn {
-            Ok(conn) => {
-                let srv = server_ref.clone();
-                thread::spawn(move|| {
-                    match srv.handle(conn) {
-                        Ok(_) => {}
-                        Err(err) => info!("{}", err)
-                    }
-                });
-            }
+            Ok(conn) => Server::accept(&server_ref, conn),
             Err(e) => info!("Connection error {:?}", e)
         }
     }
@@ -0,0 +1,297 @@
+//! Block properties.
+//!
+//! Blocks are identified by their numeric id, without metadata.
+
+use item::{self, Tool};
+use types::Slot;
+
+pub const AIR: u16 = 0;
+pub const STONE: u16 = 1;
+pub const BED: u16 = 26;
+pub const TNT: u16 = 46;
+pub const FIRE: u16 = 51;
+pub const FURNACE: u16 = 61;
+pub const LIT_FURNACE: u16 = 62;
+pub const STANDING_SIGN: u16 = 63;
+pub const WALL_SIGN: u16 = 68;
+pub const BREWING_STAND: u16 = 117;
+pub const ANVIL: u16 = 145;
+
+/// Blast resistance of block `id`, as listed on the wiki. Vanilla divides
+/// these by 5 when working out how far an explosion reaches.
+pub fn blast_resistance(id: u16) -> f32 {
+    match id {
+        // Plants, redstone components, torches, fire, carpets...
+        0 | 6 | 31 | 32 | 36 | 37 | 38 | 39 | 40 | 46 | 50 | 51 | 55 | 59 | 75 | 76 | 83 | 90 | 93 | 94 |
+        104 | 105 | 111 | 115 | 131 | 132 | 140 | 141 | 142 | 149 | 150 | 165 | 175 => 0.0,
+        78 | 171 => 0.5,
+        18 | 26 | 80 | 99 | 100 | 106 | 151 | 161 | 178 => 1.0,
+        20 | 89 | 95 | 102 | 123 | 124 | 160 | 169 => 1.5,
+        65 | 81 | 87 => 2.0,
+        3 | 12 | 29 | 33 | 34 | 69 | 70 | 72 | 77 | 79 | 88 | 92 | 117 | 143 | 147 | 148 | 170 | 174 => 2.5,
+        2 | 13 | 19 | 60 | 82 | 110 => 3.0,
+        27 | 28 | 66 | 157 => 3.5,
+        97 => 3.75,
+        24 | 25 | 35 | 128 | 155 | 156 | 179 | 180 => 4.0,
+        63 | 68 | 86 | 91 | 103 | 144 | 176 | 177 => 5.0,
+        47 => 7.5,
+        17 | 118 | 162 => 10.0,
+        54 | 58 | 146 => 12.5,
+        5 | 14 | 15 | 16 | 21 | 22 | 53 | 56 | 64 | 73 | 74 | 85 | 96 | 107 | 125 | 126 | 127 | 129 | 134 |
+        135 | 136 | 138 | 153 | 163 | 164 | 183 | 184 | 185 | 186 | 187 | 188 | 189 | 190 | 191 | 192 | 193 |
+        194 | 195 | 196 | 197 => 15.0,
+        23 | 61 | 62 | 158 => 17.5,
+        30 => 20.0,
+        159 | 172 => 21.0,
+        154 => 24.0,
+        52 | 71 | 167 => 25.0,
+        121 | 122 => 45.0,
+        130 => 3000.0,
+        49 | 116 | 145 => 6000.0,
+        7 | 119 | 120 | 137 | 166 => 18000000.0,
+        // Water and lava
+        8 | 9 | 10 | 11 => 500.0,
+        // Stone, cobblestone, bricks, metal blocks, slabs and stairs of those
+        _ => 30.0
+    }
+}
+
+/// Hardness of block `id`, as listed on the wiki, `None` for blocks which
+/// can't be broken.
+pub fn hardness(id: u16) -> Option<f32> {
+    Some(match id {
+        // Plants, redstone components, torches, fire...
+        0 | 6 | 31 | 32 | 37 | 38 | 39 | 40 | 46 | 50 | 51 | 55 | 59 | 75 | 76 | 83 | 93 | 94 | 104 | 105 | 111 |
+        115 | 131 | 132 | 140 | 141 | 142 | 149 | 150 | 165 | 175 => 0.0,
+        78 | 171 => 0.1,
+        18 | 26 | 80 | 99 | 100 | 106 | 127 | 151 | 161 | 178 => 0.2,
+        20 | 89 | 95 | 102 | 123 | 124 | 160 | 169 => 0.3,
+        65 | 81 | 87 => 0.4,
+        3 | 12 | 29 | 33 | 34 | 69 | 70 | 72 | 77 | 79 | 88 | 92 | 117 | 143 | 147 | 148 | 170 | 174 => 0.5,
+        2 | 13 | 19 | 60 | 82 | 110 => 0.6,
+        27 | 28 | 66 | 157 => 0.7,
+        97 => 0.75,
+        24 | 25 | 35 | 128 | 155 | 156 | 179 | 180 => 0.8,
+        63 | 68 | 86 | 91 | 103 | 144 | 176 | 177 => 1.0,
+        159 | 172 => 1.25,
+        1 | 47 | 98 | 109 | 168 => 1.5,
+        4 | 5 | 17 | 43 | 44 | 45 | 48 | 53 | 67 | 84 | 85 | 107 | 108 | 112 | 113 | 114 | 118 | 125 | 126 | 134 |
+        135 | 136 | 139 | 162 | 163 | 164 | 181 | 182 | 183 | 184 | 185 | 186 | 187 | 188 | 189 | 190 | 191 | 192 => 2.0,
+        54 | 58 | 146 => 2.5,
+        14 | 15 | 16 | 21 | 22 | 41 | 56 | 64 | 73 | 74 | 96 | 121 | 122 | 129 | 138 | 153 | 154 | 193 | 194 | 195 |
+        196 | 197 => 3.0,
+        23 | 61 | 62 | 158 => 3.5,
+        30 => 4.0,
+        42 | 52 | 57 | 71 | 101 | 116 | 133 | 145 | 152 | 167 | 173 => 5.0,
+        130 => 22.5,
+        49 => 50.0,
+        // Bedrock, liquids, portals, command blocks, barriers...
+        _ => return None
+    })
+}
+
+/// Kind of tool which digs block `id` faster than anything else.
+pub fn preferred_tool(id: u16) -> Option<Tool> {
+    match id {
+        // Stone, ores, bricks, metal blocks...
+        1 | 4 | 14 | 15 | 16 | 21 | 22 | 23 | 24 | 41 | 42 | 43 | 44 | 45 | 48 | 49 | 52 | 56 | 57 | 61 | 62 | 67 |
+        70 | 71 | 73 | 74 | 79 | 87 | 98 | 101 | 108 | 109 | 112 | 113 | 114 | 116 | 117 | 118 | 121 | 128 | 129 |
+        130 | 133 | 139 | 145 | 147 | 148 | 152 | 153 | 154 | 155 | 156 | 158 | 159 | 167 | 168 | 172 | 173 |
+        174 | 179 | 180 | 181 | 182 => Some(Tool::Pickaxe),
+        2 | 3 | 12 | 13 | 60 | 78 | 80 | 82 | 88 | 110 => Some(Tool::Shovel),
+        // Anything wooden, pumpkins and melons
+        5 | 17 | 25 | 47 | 53 | 54 | 58 | 63 | 64 | 65 | 68 | 72 | 84 | 85 | 86 | 91 | 96 | 99 | 100 | 103 | 107 |
+        125 | 126 | 127 | 134 | 135 | 136 | 143 | 146 | 151 | 162 | 163 | 164 | 176 | 177 | 178 | 183 | 184 | 185 |
+        186 | 187 | 188 | 189 | 190 | 191 | 192 | 193 | 194 | 195 | 196 | 197 => Some(Tool::Axe),
+        30 => Some(Tool::Sword),
+        _ => None
+    }
+}
+
+/// Harvest level of the pickaxe block `id` needs to drop anything, `None`
+/// if it doesn't need one.
+pub fn harvest_level(id: u16) -> Option<u8> {
+    match id {
+        49 => Some(3),
+        14 | 41 | 56 | 57 | 73 | 74 | 129 | 133 => Some(2),
+        15 | 21 | 22 | 42 => Some(1),
+        // Ice breaks without dropping anything anyway
+        79 | 174 => None,
+        _ if preferred_tool(id) == Some(Tool::Pickaxe) => Some(0),
+        _ => None
+    }
+}
+
+/// Whether block `id` drops anything when dug with `held`.
+pub fn can_harvest(id: u16, held: Option<&Slot>) -> bool {
+    let tool = held.and_then(|stack| item::tool(stack.id()));
+    match id {
+        30 => match tool {
+            Some((Tool::Shears, _, _)) | Some((Tool::Sword, _, _)) => true,
+            _ => false
+        },
+        // Snow
+        78 | 80 => match tool {
+            Some((Tool::Shovel, _, _)) => true,
+            _ => false
+        },
+        _ => match (harvest_level(id), tool) {
+            (None, _) => true,
+            (Some(needed), Some((Tool::Pickaxe, level, _))) => level >= needed,
+            (Some(_), _) => false
+        }
+    }
+}
+
+/// What else than the held item changes how fast a player digs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DigConditions {
+    /// Amplifier of the haste effect, if the player has it.
+    pub haste: Option<u8>,
+    pub mining_fatigue: Option<u8>,
+    /// Whether the player's head is under water.
+    pub underwater: bool,
+    /// Whether the player's helmet has Aqua Affinity, which makes up for
+    /// being under water.
+    pub aqua_affinity: bool,
+    pub on_ground: bool
+}
+
+/// Share of block `id` a player digs each tick while holding `held`, 1 or
+/// more when it breaks at once and 0 when it can't be broken. Vanilla lets
+/// blocks break once 70% dug.
+pub fn dig_speed(id: u16, held: Option<&Slot>, conditions: &DigConditions) -> f32 {
+    let hardness = match hardness(id) {
+        Some(hardness) => hardness,
+        None => return 0.0
+    };
+    if hardness <= 0.0 {
+        return 1.0;
+    }
+    let mut speed = held.map_or(1.0, |stack| tool_speed(id, stack));
+    if speed > 1.0 {
+        let efficiency = held.map_or(0, |stack| stack.enchantment_level(item::EFFICIENCY));
+        if efficiency > 0 {
+            speed += (efficiency * efficiency + 1) as f32;
+        }
+    }
+    if let Some(amplifier) = conditions.haste {
+        speed *= 1.0 + (amplifier as f32 + 1.0) * 0.2;
+    }
+    if let Some(amplifier) = conditions.mining_fatigue {
+        speed *= match amplifier {
+            0 => 0.3,
+            1 => 0.09,
+            2 => 0.0027,
+            _ => 0.00081
+        };
+    }
+    if conditions.underwater && !conditions.aqua_affinity {
+        speed /= 5.0;
+    }
+    if !conditions.on_ground {
+        speed /= 5.0;
+    }
+    speed / hardness / if can_harvest(id, held) { 30.0 } else { 100.0 }
+}
+
+/// How many times faster than bare hands `stack` digs block `id`.
+fn tool_speed(id: u16, stack: &Slot) -> f32 {
+    match item::tool(stack.id()) {
+        Some((Tool::Sword, _, _)) => match id {
+            30 => 15.0,
+            // Plants, vines, leaves, pumpkins and melons
+            6 | 18 | 31 | 32 | 37 | 38 | 39 | 40 | 59 | 83 | 86 | 91 | 103 | 104 | 105 | 106 | 111 | 141 | 142 | 161 |
+            175 => 1.5,
+            _ => 1.0
+        },
+        Some((Tool::Shears, _, _)) => match id {
+            18 | 30 | 161 => 15.0,
+            35 => 5.0,
+            _ => 1.0
+        },
+        Some((kind, _, efficiency)) if preferred_tool(id) == Some(kind) => efficiency,
+        _ => 1.0
+    }
+}
+
+pub fn is_rail(id: u16) -> bool {
+    match id {
+        27 | 28 | 66 | 157 => true,
+        _ => false
+    }
+}
+
+/// Light level block `id` gives off.
+pub fn light_emitted(id: u16) -> u8 {
+    match id {
+        10 | 11 | 51 | 89 | 91 | 119 | 124 | 138 | 169 => 15,
+        50 => 14,
+        62 => 13,
+        90 => 11,
+        76 | 94 => 7,
+        39 | 117 | 122 | 130 => 1,
+        _ => 0
+    }
+}
+
+/// Blocks which hurt whoever walks into them: lava, fire and cactus.
+pub fn is_harmful(id: u16) -> bool {
+    match id {
+        10 | 11 | FIRE | 81 => true,
+        _ => false
+    }
+}
+
+/// Whether block `id` stops explosions from reaching entities and can hold
+/// fire on top.
+pub fn is_solid(id: u16) -> bool {
+    match id {
+        0 | 6 | 8 | 9 | 10 | 11 | 27 | 28 | 30 | 31 | 32 | 36 | 37 | 38 | 39 | 40 | 50 | 51 | 55 | 59 | 63 |
+        65 | 66 | 68 | 69 | 70 | 72 | 75 | 76 | 77 | 78 | 83 | 90 | 93 | 94 | 104 | 105 | 106 | 111 | 115 |
+        119 | 131 | 132 | 141 | 142 | 143 | 147 | 148 | 157 | 171 | 175 | 176 | 177 => false,
+        _ => true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resistance() {
+        assert_eq!(blast_resistance(AIR), 0.0);
+        assert_eq!(blast_resistance(1), 30.0);
+        assert_eq!(blast_resistance(49), 6000.0);
+        assert_eq!(blast_resistance(185), 15.0);
+    }
+
+    #[test]
+    fn dig_speeds() {
+        use item;
+        use types::Slot;
+
+        let standing = DigConditions { haste: None, mining_fatigue: None, underwater: false, aqua_affinity: false, on_ground: true };
+        // Stone takes 7.5 seconds by hand, and drops nothing
+        assert!((1.0 / dig_speed(1, None, &standing) - 150.0).abs() < 1e-3);
+        let pickaxe = Slot::new(278, 1, 0);
+        assert!(can_harvest(1, Some(&pickaxe)));
+        assert!((dig_speed(1, Some(&pickaxe), &standing) - 8.0 / 1.5 / 30.0).abs() < 1e-6);
+        // Iron pickaxes aren't good enough for obsidian
+        assert!(!can_harvest(49, Some(&Slot::new(257, 1, 0))));
+        let swimming = DigConditions { underwater: true, on_ground: false, ..standing };
+        assert!((dig_speed(1, Some(&pickaxe), &swimming) * 25.0 - dig_speed(1, Some(&pickaxe), &standing)).abs() < 1e-6);
+        assert_eq!(dig_speed(7, Some(&pickaxe), &standing), 0.0);
+        assert_eq!(dig_speed(item::ITEM_FRAME, None, &standing), 0.0);
+        assert!(dig_speed(50, None, &standing) >= 1.0);
+    }
+
+    #[test]
+    fn solid() {
+        assert!(!is_solid(AIR));
+        assert!(!is_solid(FIRE));
+        assert!(is_solid(1));
+        assert_eq!(light_emitted(50), 14);
+        assert_eq!(light_emitted(1), 0);
+    }
+}
@@ -0,0 +1,162 @@
+//! Brewing stands.
+
+use item;
+use potion;
+use types::Slot;
+
+/// Window slot of the ingredient, the three potion slots come before it.
+pub const INGREDIENT_SLOT: usize = 3;
+
+/// Number of brewing stand slots, the player's inventory comes after them.
+pub const SIZE: usize = 4;
+
+/// Ticks it takes to brew.
+pub const BREW_TICKS: i32 = 400;
+
+#[derive(Clone, Debug)]
+pub struct BrewingStand {
+    slots: [Option<Slot>; 4],
+    /// Ticks left before brewing is done, 0 when not brewing.
+    brew_ticks: i32,
+    /// Ingredient brewing started with, swapping it out stops brewing.
+    ingredient: Option<u16>
+}
+
+impl BrewingStand {
+    pub fn new() -> BrewingStand {
+        BrewingStand {
+            slots: [None, None, None, None],
+            brew_ticks: 0,
+            ingredient: None
+        }
+    }
+
+    pub fn brewing(&self) -> bool { self.brew_ticks > 0 }
+
+    /// Whether ticking the brewing stand can change anything.
+    pub fn active(&self) -> bool {
+        self.brewing() || self.can_brew()
+    }
+
+    pub fn items(&self) -> Vec<Option<Slot>> {
+        self.slots.to_vec()
+    }
+
+    pub fn slot_mut(&mut self, index: usize) -> Option<&mut Option<Slot>> {
+        self.slots.get_mut(index)
+    }
+
+    /// Whether players can put `stack` in slot `index`.
+    pub fn accepts(&self, index: usize, stack: &Slot) -> bool {
+        match index {
+            INGREDIENT_SLOT => potion::is_ingredient(stack.id(), stack.damage()),
+            0 | 1 | 2 => stack.id() == item::POTION || stack.id() == item::GLASS_BOTTLE,
+            _ => false
+        }
+    }
+
+    /// Window properties, the brewing time left.
+    pub fn properties(&self) -> [i16; 1] {
+        [self.brew_ticks as i16]
+    }
+
+    fn can_brew(&self) -> bool {
+        let ingredient = match self.slots[INGREDIENT_SLOT] {
+            Some(ref ingredient) => ingredient,
+            None => return false
+        };
+        self.slots[..INGREDIENT_SLOT].iter().any(|slot| match *slot {
+            Some(ref stack) if stack.id() == item::POTION => {
+                potion::brew(stack.damage(), ingredient.id(), ingredient.damage()).is_some()
+            }
+            _ => false
+        })
+    }
+
+    /// Brews one tick forward. Returns whether any slot changed.
+    pub fn tick(&mut self) -> bool {
+        let ingredient = self.slots[INGREDIENT_SLOT].as_ref().map(|stack| stack.id());
+        if self.brew_ticks > 0 {
+            if !self.can_brew() || ingredient != self.ingredient {
+                self.brew_ticks = 0;
+                return false;
+            }
+            self.brew_ticks -= 1;
+            if self.brew_ticks == 0 {
+                self.brew();
+                return true;
+            }
+        } else if self.can_brew() {
+            self.brew_ticks = BREW_TICKS;
+            self.ingredient = ingredient;
+        }
+        false
+    }
+
+    /// Applies the ingredient to every potion, using it up.
+    fn brew(&mut self) {
+        let (id, damage) = match self.slots[INGREDIENT_SLOT] {
+            Some(ref ingredient) => (ingredient.id(), ingredient.damage()),
+            None => return
+        };
+        for slot in self.slots[..INGREDIENT_SLOT].iter_mut() {
+            let brewed = match *slot {
+                Some(ref stack) if stack.id() == item::POTION => potion::brew(stack.damage(), id, damage),
+                _ => None
+            };
+            if let Some(brewed) = brewed {
+                *slot = Some(potion::potion_item(brewed));
+            }
+        }
+        self.slots[INGREDIENT_SLOT] = match self.slots[INGREDIENT_SLOT].take() {
+            Some(ref ingredient) if ingredient.count() > 1 => {
+                let mut left = ingredient.clone();
+                left.set_count(ingredient.count() - 1);
+                Some(left)
+            }
+            _ => None
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use item;
+    use potion;
+    use types::Slot;
+
+    #[test]
+    fn brews_awkward_potions() {
+        let mut stand = BrewingStand::new();
+        *stand.slot_mut(0).unwrap() = Some(Slot::new(item::POTION, 1, potion::WATER));
+        *stand.slot_mut(2).unwrap() = Some(Slot::new(item::POTION, 1, potion::WATER));
+        *stand.slot_mut(INGREDIENT_SLOT).unwrap() = Some(Slot::new(potion::NETHER_WART, 2, 0));
+        assert!(!stand.tick());
+        assert!(stand.brewing());
+        let mut ticks = 0;
+        while !stand.tick() {
+            ticks += 1;
+        }
+        assert_eq!(ticks, BREW_TICKS - 1);
+        let items = stand.items();
+        assert_eq!(items[0].as_ref().map(|stack| stack.damage()), Some(potion::AWKWARD));
+        assert!(items[1].is_none());
+        assert_eq!(items[INGREDIENT_SLOT].as_ref().map(|stack| stack.count()), Some(1));
+        // Nether wart doesn't do anything more to awkward potions
+        assert!(!stand.active());
+    }
+
+    #[test]
+    fn removing_ingredient_stops() {
+        let mut stand = BrewingStand::new();
+        *stand.slot_mut(0).unwrap() = Some(Slot::new(item::POTION, 1, potion::WATER));
+        *stand.slot_mut(INGREDIENT_SLOT).unwrap() = Some(Slot::new(potion::NETHER_WART, 1, 0));
+        stand.tick();
+        *stand.slot_mut(INGREDIENT_SLOT).unwrap() = None;
+        stand.tick();
+        assert!(!stand.brewing());
+        assert!(!stand.accepts(INGREDIENT_SLOT, &Slot::new(1, 1, 0)));
+    }
+}
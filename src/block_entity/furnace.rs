@@ -0,0 +1,259 @@
+//! Furnaces, along with what they burn and what they smelt.
+
+use item;
+use types::Slot;
+
+pub const INPUT_SLOT: usize = 0;
+pub const FUEL_SLOT: usize = 1;
+pub const OUTPUT_SLOT: usize = 2;
+
+/// Number of furnace slots, the player's inventory comes after them.
+pub const SIZE: usize = 3;
+
+/// Ticks it takes to smelt one item.
+pub const COOK_TICKS: i32 = 200;
+
+#[derive(Clone, Debug)]
+pub struct Furnace {
+    slots: [Option<Slot>; 3],
+    /// Ticks left before the fuel item being burnt runs out.
+    burn_ticks: i32,
+    /// How long that fuel item burns in total.
+    burn_total: i32,
+    /// Progress smelting the current input item.
+    cook_ticks: i32,
+    /// Experience earned by smelting, handed out when the output is taken.
+    experience: f32
+}
+
+impl Furnace {
+    pub fn new() -> Furnace {
+        Furnace {
+            slots: [None, None, None],
+            burn_ticks: 0,
+            burn_total: 0,
+            cook_ticks: 0,
+            experience: 0.0
+        }
+    }
+
+    pub fn burning(&self) -> bool { self.burn_ticks > 0 }
+
+    /// Whether ticking the furnace can change anything.
+    pub fn active(&self) -> bool {
+        self.burning() || self.cook_ticks > 0 || self.can_smelt()
+    }
+
+    pub fn items(&self) -> Vec<Option<Slot>> {
+        self.slots.to_vec()
+    }
+
+    pub fn slot_mut(&mut self, index: usize) -> Option<&mut Option<Slot>> {
+        self.slots.get_mut(index)
+    }
+
+    /// Whether players can put `stack` in slot `index`.
+    pub fn accepts(&self, index: usize, stack: &Slot) -> bool {
+        match index {
+            INPUT_SLOT => true,
+            FUEL_SLOT => fuel_ticks(stack.id()).is_some(),
+            _ => false
+        }
+    }
+
+    /// Window properties, in order: fuel left, fuel burning time, smelting
+    /// progress and smelting time.
+    pub fn properties(&self) -> [i16; 4] {
+        [self.burn_ticks as i16, self.burn_total as i16, self.cook_ticks as i16, COOK_TICKS as i16]
+    }
+
+    /// Takes the experience earned so far, as whole points. Fractions are
+    /// kept for later.
+    pub fn take_experience(&mut self) -> i32 {
+        let points = self.experience.floor();
+        self.experience -= points;
+        points as i32
+    }
+
+    fn can_smelt(&self) -> bool {
+        let (id, damage) = match self.slots[INPUT_SLOT] {
+            Some(ref input) => match smelt(input.id(), input.damage()) {
+                Some((id, damage, _)) => (id, damage),
+                None => return false
+            },
+            None => return false
+        };
+        match self.slots[OUTPUT_SLOT] {
+            Some(ref output) => {
+                output.can_stack_with(&Slot::new(id, 1, damage)) && output.count() < output.max_stack_size()
+            }
+            None => true
+        }
+    }
+
+    /// Burns fuel and smelts one tick forward. Returns whether any slot
+    /// changed.
+    pub fn tick(&mut self) -> bool {
+        let mut changed = false;
+        if self.burn_ticks > 0 {
+            self.burn_ticks -= 1;
+        }
+        let can_smelt = self.can_smelt();
+        if can_smelt && self.burn_ticks == 0 {
+            if let Some(ticks) = self.slots[FUEL_SLOT].as_ref().and_then(|fuel| fuel_ticks(fuel.id())) {
+                self.burn_ticks = ticks;
+                self.burn_total = ticks;
+                self.slots[FUEL_SLOT] = match self.slots[FUEL_SLOT].take() {
+                    Some(ref fuel) if fuel.count() > 1 => {
+                        let mut left = fuel.clone();
+                        left.set_count(fuel.count() - 1);
+                        Some(left)
+                    }
+                    // Lava leaves its bucket behind
+                    Some(ref fuel) if fuel.id() == item::LAVA_BUCKET => Some(Slot::new(item::BUCKET, 1, 0)),
+                    _ => None
+                };
+                changed = true;
+            }
+        }
+        if self.burn_ticks == 0 {
+            // Progress is slowly lost once the fire is out
+            self.cook_ticks = (self.cook_ticks - 2).max(0);
+        } else if can_smelt {
+            self.cook_ticks += 1;
+            if self.cook_ticks >= COOK_TICKS {
+                self.cook_ticks = 0;
+                self.smelt_one();
+                changed = true;
+            }
+        } else {
+            self.cook_ticks = 0;
+        }
+        changed
+    }
+
+    fn smelt_one(&mut self) {
+        let input = match self.slots[INPUT_SLOT].take() {
+            Some(input) => input,
+            None => return
+        };
+        if let Some((id, damage, experience)) = smelt(input.id(), input.damage()) {
+            self.slots[OUTPUT_SLOT] = Some(match self.slots[OUTPUT_SLOT].take() {
+                Some(mut output) => {
+                    let count = output.count() + 1;
+                    output.set_count(count);
+                    output
+                }
+                None => Slot::new(id, 1, damage)
+            });
+            self.experience += experience;
+        }
+        if input.count() > 1 {
+            let mut left = input;
+            let count = left.count() - 1;
+            left.set_count(count);
+            self.slots[INPUT_SLOT] = Some(left);
+        }
+    }
+}
+
+/// Ticks item `id` keeps a furnace burning, `None` if it isn't fuel.
+pub fn fuel_ticks(id: u16) -> Option<i32> {
+    match id {
+        item::LAVA_BUCKET => Some(20000),
+        // Block of coal
+        173 => Some(16000),
+        // Blaze rod
+        369 => Some(2400),
+        item::COAL => Some(1600),
+        // Logs, planks and anything made of wood
+        5 | 17 | 25 | 47 | 53 | 54 | 58 | 72 | 84 | 85 | 96 | 99 | 100 | 107 | 134 | 135 | 136 | 146 | 151 | 162 |
+        163 | 164 | 183 | 184 | 185 | 186 | 187 | 188 | 189 | 190 | 191 | 192 => Some(300),
+        // Wooden tools
+        268 | 269 | 270 | 271 | 290 => Some(200),
+        // Wooden slabs
+        126 => Some(150),
+        // Saplings and sticks
+        6 | 280 => Some(100),
+        _ => None
+    }
+}
+
+/// What item `id` with the given `damage` smelts into, as the resulting
+/// item id and damage, along with the experience it gives.
+pub fn smelt(id: u16, damage: i16) -> Option<(u16, i16, f32)> {
+    match (id, damage) {
+        // Ores
+        (14, _) => Some((266, 0, 1.0)),
+        (15, _) => Some((item::IRON_INGOT, 0, 0.7)),
+        (16, _) => Some((item::COAL, 0, 0.1)),
+        (21, _) => Some((351, 4, 0.2)),
+        (56, _) => Some((264, 0, 1.0)),
+        (73, _) => Some((331, 0, 0.7)),
+        (129, _) => Some((388, 0, 1.0)),
+        (153, _) => Some((406, 0, 0.2)),
+        // Blocks
+        (4, _) => Some((1, 0, 0.1)),
+        (12, _) => Some((20, 0, 0.1)),
+        (17, _) | (162, _) => Some((item::COAL, 1, 0.15)),
+        (19, 1) => Some((19, 0, 0.15)),
+        (81, _) => Some((351, 2, 0.2)),
+        (82, _) => Some((172, 0, 0.35)),
+        (87, _) => Some((405, 0, 0.1)),
+        (98, 0) => Some((98, 2, 0.1)),
+        (337, _) => Some((336, 0, 0.3)),
+        // Food
+        (319, _) => Some((320, 0, 0.35)),
+        (349, 0) | (349, 1) => Some((350, damage, 0.35)),
+        (363, _) => Some((364, 0, 0.35)),
+        (365, _) => Some((366, 0, 0.35)),
+        (392, _) => Some((393, 0, 0.35)),
+        (411, _) => Some((412, 0, 0.35)),
+        (423, _) => Some((424, 0, 0.35)),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use item;
+    use types::Slot;
+
+    #[test]
+    fn smelts_iron() {
+        let mut furnace = Furnace::new();
+        *furnace.slot_mut(INPUT_SLOT).unwrap() = Some(Slot::new(15, 2, 0));
+        *furnace.slot_mut(FUEL_SLOT).unwrap() = Some(Slot::new(item::COAL, 1, 0));
+        assert!(furnace.tick());
+        assert!(furnace.burning());
+        assert!(furnace.items()[FUEL_SLOT].is_none());
+        for _ in 0..2 * COOK_TICKS {
+            furnace.tick();
+        }
+        let output = furnace.items()[OUTPUT_SLOT].clone().unwrap();
+        assert_eq!((output.id(), output.count()), (item::IRON_INGOT, 2));
+        assert!(furnace.items()[INPUT_SLOT].is_none());
+        assert_eq!(furnace.take_experience(), 1);
+        assert_eq!(furnace.take_experience(), 0);
+    }
+
+    #[test]
+    fn lava_leaves_bucket() {
+        let mut furnace = Furnace::new();
+        *furnace.slot_mut(INPUT_SLOT).unwrap() = Some(Slot::new(12, 1, 0));
+        *furnace.slot_mut(FUEL_SLOT).unwrap() = Some(Slot::new(item::LAVA_BUCKET, 1, 0));
+        furnace.tick();
+        assert_eq!(furnace.items()[FUEL_SLOT].as_ref().map(|stack| stack.id()), Some(item::BUCKET));
+        assert!(!furnace.accepts(FUEL_SLOT, &Slot::new(12, 1, 0)));
+    }
+
+    #[test]
+    fn no_fuel() {
+        let mut furnace = Furnace::new();
+        *furnace.slot_mut(INPUT_SLOT).unwrap() = Some(Slot::new(12, 1, 0));
+        assert!(!furnace.tick());
+        assert!(!furnace.burning());
+    }
+}
@@ -0,0 +1,99 @@
+//! Block entities, the state kept for blocks which do more than sit there.
+
+use block;
+use types::Slot;
+
+pub use self::brewing_stand::BrewingStand;
+pub use self::furnace::Furnace;
+pub use self::sign::Sign;
+
+pub mod brewing_stand;
+pub mod furnace;
+pub mod sign;
+
+#[derive(Clone, Debug)]
+pub enum BlockEntity {
+    Furnace(Furnace),
+    BrewingStand(BrewingStand),
+    Sign(Sign)
+}
+
+impl BlockEntity {
+    /// Fresh block entity for block `id`, if it needs one.
+    pub fn new(id: u16) -> Option<BlockEntity> {
+        match id {
+            block::FURNACE | block::LIT_FURNACE => Some(BlockEntity::Furnace(Furnace::new())),
+            block::BREWING_STAND => Some(BlockEntity::BrewingStand(BrewingStand::new())),
+            block::STANDING_SIGN | block::WALL_SIGN => Some(BlockEntity::Sign(Sign::new())),
+            _ => None
+        }
+    }
+
+    /// Whether this belongs with block `id`, once the block is replaced the
+    /// block entity goes away.
+    pub fn belongs_to(&self, id: u16) -> bool {
+        match *self {
+            BlockEntity::Furnace(_) => id == block::FURNACE || id == block::LIT_FURNACE,
+            BlockEntity::BrewingStand(_) => id == block::BREWING_STAND,
+            BlockEntity::Sign(_) => id == block::STANDING_SIGN || id == block::WALL_SIGN
+        }
+    }
+
+    /// Whether ticking can change anything.
+    pub fn active(&self) -> bool {
+        match *self {
+            BlockEntity::Furnace(ref furnace) => furnace.active(),
+            BlockEntity::BrewingStand(ref stand) => stand.active(),
+            BlockEntity::Sign(_) => false
+        }
+    }
+
+    /// Moves one tick forward, returns whether any slot changed.
+    pub fn tick(&mut self) -> bool {
+        match *self {
+            BlockEntity::Furnace(ref mut furnace) => furnace.tick(),
+            BlockEntity::BrewingStand(ref mut stand) => stand.tick(),
+            BlockEntity::Sign(_) => false
+        }
+    }
+
+    /// Content of the slots, in window order.
+    pub fn items(&self) -> Vec<Option<Slot>> {
+        match *self {
+            BlockEntity::Furnace(ref furnace) => furnace.items(),
+            BlockEntity::BrewingStand(ref stand) => stand.items(),
+            BlockEntity::Sign(_) => vec![]
+        }
+    }
+
+    pub fn slot_mut(&mut self, index: usize) -> Option<&mut Option<Slot>> {
+        match *self {
+            BlockEntity::Furnace(ref mut furnace) => furnace.slot_mut(index),
+            BlockEntity::BrewingStand(ref mut stand) => stand.slot_mut(index),
+            BlockEntity::Sign(_) => None
+        }
+    }
+
+    /// Whether players can put `stack` in slot `index`.
+    pub fn accepts(&self, index: usize, stack: &Slot) -> bool {
+        match *self {
+            BlockEntity::Furnace(ref furnace) => furnace.accepts(index, stack),
+            BlockEntity::BrewingStand(ref stand) => stand.accepts(index, stack),
+            BlockEntity::Sign(_) => false
+        }
+    }
+
+    /// Values of the window properties, in order.
+    pub fn properties(&self) -> Vec<i16> {
+        match *self {
+            BlockEntity::Furnace(ref furnace) => furnace.properties().to_vec(),
+            BlockEntity::BrewingStand(ref stand) => stand.properties().to_vec(),
+            BlockEntity::Sign(_) => vec![]
+        }
+    }
+
+    /// Items kept inside, spilled out when the block is broken.
+    pub fn into_items(self) -> Vec<Slot> {
+        self.items().into_iter().filter_map(|slot| slot).collect()
+    }
+}
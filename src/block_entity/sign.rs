@@ -0,0 +1,113 @@
+//! Signs, whose text players write once.
+
+use types::{ChatJson, Message};
+
+use uuid::Uuid;
+
+/// Lines of text on a sign.
+pub const LINES: usize = 4;
+
+/// Most characters a line keeps, as many of the narrowest ones as fit in a
+/// sign's 90 pixels.
+pub const MAX_LINE_LENGTH: usize = 45;
+
+#[derive(Clone, Debug)]
+pub struct Sign {
+    lines: [String; LINES],
+    /// Who may write the sign, until they do.
+    editor: Option<Uuid>
+}
+
+impl Sign {
+    pub fn new() -> Sign {
+        Sign { lines: Default::default(), editor: None }
+    }
+
+    pub fn lines(&self) -> &[String; LINES] { &self.lines }
+
+    pub fn editor(&self) -> Option<&Uuid> { self.editor.as_ref() }
+
+    /// Lets player `uuid` write the sign, once.
+    pub fn set_editor(&mut self, uuid: Uuid) {
+        self.editor = Some(uuid);
+    }
+
+    /// Writes `lines` sent by player `uuid` on the sign, as plain text.
+    /// Returns whether they were allowed to.
+    pub fn write(&mut self, uuid: &Uuid, lines: [&ChatJson; LINES]) -> bool {
+        if self.editor.as_ref() != Some(uuid) {
+            return false;
+        }
+        self.editor = None;
+        for (line, text) in self.lines.iter_mut().zip(lines.iter()) {
+            *line = sanitize_line(text);
+        }
+        true
+    }
+}
+
+/// The plain text of sign line `line`, cut to `MAX_LINE_LENGTH`.
+///
+/// Clients only ever send text, anything else could make signs run
+/// commands or show what their writer picked when clicked or hovered, so
+/// events, formatting codes, translations, scores and selectors are all
+/// dropped.
+pub fn sanitize_line(line: &ChatJson) -> String {
+    let mut text = String::new();
+    plain_text(line, &mut text);
+    let mut clean = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // Formatting code, which takes the character after it
+            '\u{a7}' => { chars.next(); }
+            c if c.is_control() => {}
+            c => clean.push(c)
+        }
+    }
+    clean.chars().take(MAX_LINE_LENGTH).collect()
+}
+
+fn plain_text(line: &ChatJson, text: &mut String) {
+    if let Message::PlainText(ref msg) = line.msg {
+        text.push_str(msg);
+    }
+    for extra in &line.extra {
+        plain_text(extra, text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use types::{ChatJson, ClickEvent, HoverEvent};
+
+    use uuid::Uuid;
+
+    #[test]
+    fn sanitizing() {
+        let mut line = ChatJson::from("\u{a7}cRed\n ");
+        line.click_event = Some(ClickEvent::RunCommand("/op Steve".to_string()));
+        line.hover_event = Some(HoverEvent::Text("Click me".to_string()));
+        line.extra.push(ChatJson::from("text"));
+        line.extra.push(ChatJson::translate("chat.type.admin", vec![ChatJson::from("hidden")]));
+        assert_eq!(sanitize_line(&line), "Red text");
+        assert_eq!(sanitize_line(&ChatJson::from("a".repeat(100))).len(), MAX_LINE_LENGTH);
+    }
+
+    #[test]
+    fn writing() {
+        let (steve, alex) = (Uuid::new_v4(), Uuid::new_v4());
+        let text = ChatJson::from("Hello");
+        let empty = ChatJson::from("");
+        let mut sign = Sign::new();
+        assert!(!sign.write(&steve, [&text, &empty, &empty, &empty]));
+        sign.set_editor(steve.clone());
+        assert!(!sign.write(&alex, [&text, &empty, &empty, &empty]));
+        assert!(sign.write(&steve, [&text, &empty, &empty, &empty]));
+        assert_eq!(sign.lines()[0], "Hello");
+        // Written once and for all
+        assert!(!sign.write(&steve, [&empty, &empty, &empty, &empty]));
+    }
+}
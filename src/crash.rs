@@ -0,0 +1,190 @@
+//! Crash reports, written when any server thread panics.
+//!
+//! A panicking thread leaves poisoned locks behind, which would bring down
+//! the tick loop sooner or later, so the server saves what it can and exits
+//! instead of limping on. Connection threads are the exception: one player
+//! can send something that makes theirs panic, and dropping their
+//! connection is enough.
+
+use std::backtrace::Backtrace;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::thread;
+
+use world::World;
+
+use time;
+
+/// Name of the threads handling connections, whose panics aren't fatal.
+pub const CONNECTION_THREAD: &'static str = "Connection";
+
+/// Directory crash reports are saved in.
+pub const REPORT_DIR: &'static str = "crash-reports";
+
+/// What went wrong, as told by the panic hook.
+#[derive(Clone, Debug)]
+pub struct Crash {
+    pub message: String,
+    /// File and line which panicked.
+    pub location: Option<String>,
+    pub thread: String,
+    pub backtrace: String
+}
+
+/// Writes a crash report whenever a thread panics, and unless it was
+/// handling a connection saves `worlds` and exits.
+pub fn install(worlds: Vec<Arc<World>>) {
+    panic::set_hook(Box::new(move |info| {
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => match info.payload().downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => "Box<Any>".to_string()
+            }
+        };
+        let crash = Crash {
+            message: message,
+            location: info.location().map(|location| format!("{}:{}", location.file(), location.line())),
+            thread: thread::current().name().unwrap_or("<unnamed>").to_string(),
+            backtrace: Backtrace::force_capture().to_string()
+        };
+        on_panic(&crash, &worlds, Path::new(REPORT_DIR), |crash, worlds| crash_and_exit(crash, worlds));
+    }));
+}
+
+/// Whether `crash` should bring the server down.
+pub fn is_fatal(crash: &Crash) -> bool {
+    crash.thread != CONNECTION_THREAD
+}
+
+/// Handles `crash`: fatal ones `exit`, others only get their report saved
+/// in `dir`, the thread unwinding and its connection being dropped.
+fn on_panic<F>(crash: &Crash, worlds: &[Arc<World>], dir: &Path, exit: F) where F: FnOnce(&Crash, &[Arc<World>]) {
+    if is_fatal(crash) {
+        return exit(crash, worlds);
+    }
+    let text = report(crash, worlds);
+    match write_report_in(dir, &text) {
+        Ok(path) => error!("connection thread panicked, dropping it, report saved to {}", path.display()),
+        Err(err) => error!("connection thread panicked, dropping it, couldn't save report ({}):\n{}", err, text)
+    }
+}
+
+/// Writes a crash report for `crash`, saves `worlds` as far as they can be
+/// and exits.
+pub fn crash_and_exit(crash: &Crash, worlds: &[Arc<World>]) -> ! {
+    let text = report(crash, worlds);
+    match write_report(&text) {
+        Ok(path) => error!("server crashed, report saved to {}", path.display()),
+        Err(err) => error!("server crashed, couldn't save report ({}):\n{}", err, text)
+    }
+    for world in worlds {
+        match world.try_save_config() {
+            Some(Ok(())) => {}
+            Some(Err(err)) => error!("failed saving world: {}", err),
+            None => error!("world is locked up, not saved")
+        }
+        match world.try_save_chunks() {
+            Some(Ok(_)) => {}
+            Some(Err(err)) => error!("failed saving chunks: {}", err),
+            None => error!("chunks are locked up, not saved")
+        }
+    }
+    process::exit(1)
+}
+
+/// Text of the crash report for `crash`, with the state of `worlds`.
+pub fn report(crash: &Crash, worlds: &[Arc<World>]) -> String {
+    let mut text = String::new();
+    text.push_str("---- Minecraft Crash Report ----\n");
+    text.push_str(&format!("Time: {}\n", time::strftime("%Y-%m-%d %H:%M:%S", &time::now()).unwrap_or_default()));
+    text.push_str(&format!("Description: {}\n\n", crash.message));
+    text.push_str(&format!("Thread: {}\n", crash.thread));
+    if let Some(ref location) = crash.location {
+        text.push_str(&format!("Location: {}\n", location));
+    }
+    text.push_str(&format!("\n{}\n", crash.backtrace));
+    for (index, world) in worlds.iter().enumerate() {
+        text.push_str(&format!("\n-- World {} --\n", index));
+        // Whatever crashed may be holding these locks
+        match world.try_player_count() {
+            Some(count) => text.push_str(&format!("Players: {}\n", count)),
+            None => text.push_str("Players: ~~LOCKED~~\n")
+        }
+        match world.try_recent_packets() {
+            Some(packets) => {
+                text.push_str("Recent packets:\n");
+                for packet in packets {
+                    text.push_str(&format!("\t{}\n", packet));
+                }
+            }
+            None => text.push_str("Recent packets: ~~LOCKED~~\n")
+        }
+    }
+    text
+}
+
+/// Saves crash report `text` in the crash-reports directory.
+pub fn write_report(text: &str) -> io::Result<PathBuf> {
+    write_report_in(Path::new(REPORT_DIR), text)
+}
+
+fn write_report_in(dir: &Path, text: &str) -> io::Result<PathBuf> {
+    try!(fs::create_dir_all(dir));
+    let name = format!("crash-{}-server.txt", time::strftime("%Y-%m-%d_%H.%M.%S", &time::now()).unwrap_or_default());
+    let path = dir.join(name);
+    let mut file = try!(File::create(&path));
+    try!(file.write_all(text.as_bytes()));
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_text() {
+        let crash = Crash {
+            message: "index out of bounds".to_string(),
+            location: Some("src/world/mod.rs:10".to_string()),
+            thread: "tick world".to_string(),
+            backtrace: "stack backtrace:".to_string()
+        };
+        let text = report(&crash, &[]);
+        assert!(text.contains("Description: index out of bounds"));
+        assert!(text.contains("Thread: tick world"));
+        assert!(text.contains("Location: src/world/mod.rs:10"));
+    }
+
+    #[test]
+    fn connection_panics_arent_fatal() {
+        use std::cell::Cell;
+        use std::env;
+        use std::fs;
+        use std::path::Path;
+
+        let dir = env::temp_dir().join(format!("hematite-crash-{}", ::std::process::id()));
+        let mut crash = Crash {
+            message: "attempt to subtract with overflow".to_string(),
+            location: None,
+            thread: CONNECTION_THREAD.to_string(),
+            backtrace: String::new()
+        };
+        let exited = Cell::new(false);
+        super::on_panic(&crash, &[], &dir, |_, _| exited.set(true));
+        assert!(!exited.get());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+
+        for thread in &["tick world", "Server Watchdog", "<unnamed>"] {
+            crash.thread = thread.to_string();
+            super::on_panic(&crash, &[], Path::new("unused"), |_, _| exited.set(true));
+            assert!(exited.get());
+            exited.set(false);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Encryption of connections in online mode.
+//!
+//! The server sends its RSA public key, which the client encrypts a shared
+//! secret with. Both then encrypt everything they send with AES/CFB8, the
+//! secret being both key and IV, and tell the session server about the
+//! login with a hash of the secret and the key.
+//!
+//! Reference: http://wiki.vg/Protocol_Encryption
+
+use std::io::prelude::*;
+use std::io;
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use openssl::error::ErrorStack;
+use openssl::pkey::Private;
+use openssl::rsa::{Padding, Rsa};
+use openssl::sha::Sha1;
+use openssl::symm::{Cipher, Crypter, Mode};
+
+/// Size of the server's key, the same as vanilla's.
+pub const KEY_BITS: u32 = 1024;
+
+/// Length of the shared secret clients pick.
+pub const SECRET_LEN: usize = 16;
+
+fn openssl_error(err: ErrorStack) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err)
+}
+
+/// The server's RSA key pair, made on start.
+pub struct KeyPair {
+    rsa: Rsa<Private>,
+    /// The public key as sent in EncryptionRequest, X.509 encoded.
+    public_der: Vec<u8>
+}
+
+impl KeyPair {
+    pub fn generate() -> io::Result<KeyPair> {
+        let rsa = try!(Rsa::generate(KEY_BITS).map_err(openssl_error));
+        let public_der = try!(rsa.public_key_to_der().map_err(openssl_error));
+        Ok(KeyPair { rsa: rsa, public_der: public_der })
+    }
+
+    pub fn public_der(&self) -> &[u8] { &self.public_der }
+
+    /// Decrypts `data` a client encrypted with the public key.
+    pub fn decrypt(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut decrypted = vec![0; self.rsa.size() as usize];
+        let len = try!(self.rsa.private_decrypt(data, &mut decrypted, Padding::PKCS1)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "couldn't decrypt with the server key")));
+        decrypted.truncate(len);
+        Ok(decrypted)
+    }
+}
+
+/// The hash the client and server both give the session server, SHA-1 of
+/// `server_id`, `secret` and `public_der`, written as a signed number in
+/// hexadecimal like Java's `BigInteger::toString(16)` does.
+pub fn server_hash(server_id: &str, secret: &[u8], public_der: &[u8]) -> String {
+    let mut sha1 = Sha1::new();
+    sha1.update(server_id.as_bytes());
+    sha1.update(secret);
+    sha1.update(public_der);
+    let mut digest = sha1.finish();
+    let negative = digest[0] & 0x80 != 0;
+    if negative {
+        // Two's complement
+        let mut carry = true;
+        for byte in digest.iter_mut().rev() {
+            *byte = !*byte;
+            if carry {
+                let (sum, overflow) = byte.overflowing_add(1);
+                *byte = sum;
+                carry = overflow;
+            }
+        }
+    }
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let hex = hex.trim_start_matches('0');
+    format!("{}{}", if negative { "-" } else { "" }, if hex.is_empty() { "0" } else { hex })
+}
+
+fn cipher(mode: Mode, secret: &[u8]) -> io::Result<Crypter> {
+    if secret.len() != SECRET_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("shared secret is {} bytes long", secret.len())));
+    }
+    Crypter::new(Cipher::aes_128_cfb8(), mode, secret, Some(secret)).map_err(openssl_error)
+}
+
+/// Runs `data` through `crypter` in place.
+fn apply(crypter: &mut Crypter, data: &mut [u8]) -> io::Result<()> {
+    // CFB8 works a byte at a time, nothing is held back
+    let mut out = vec![0; data.len() + 1];
+    let len = try!(crypter.update(data, &mut out).map_err(openssl_error));
+    data.copy_from_slice(&out[..len]);
+    Ok(())
+}
+
+/// A connection, encrypted from when `encrypt` is called. Clones share the
+/// ciphers, whose state follows every byte sent or received, so whatever
+/// is written to any of them goes out in order.
+pub struct SymmStream {
+    stream: TcpStream,
+    encrypter: Arc<Mutex<Option<Crypter>>>,
+    decrypter: Arc<Mutex<Option<Crypter>>>
+}
+
+impl SymmStream {
+    /// `stream`, not encrypted yet.
+    pub fn new(stream: TcpStream) -> SymmStream {
+        SymmStream { stream: stream, encrypter: Arc::new(Mutex::new(None)), decrypter: Arc::new(Mutex::new(None)) }
+    }
+
+    /// Encrypts everything sent and received from now on with `secret`.
+    pub fn encrypt(&self, secret: &[u8]) -> io::Result<()> {
+        let encrypter = try!(cipher(Mode::Encrypt, secret));
+        let decrypter = try!(cipher(Mode::Decrypt, secret));
+        *self.encrypter.lock().unwrap() = Some(encrypter);
+        *self.decrypter.lock().unwrap() = Some(decrypter);
+        Ok(())
+    }
+
+    pub fn try_clone(&self) -> io::Result<SymmStream> {
+        Ok(SymmStream {
+            stream: try!(self.stream.try_clone()),
+            encrypter: self.encrypter.clone(),
+            decrypter: self.decrypter.clone()
+        })
+    }
+
+    /// The connection underneath, whose bytes are encrypted.
+    pub fn get_ref(&self) -> &TcpStream { &self.stream }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(timeout)
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.stream.shutdown(how)
+    }
+}
+
+impl Read for SymmStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Not locked while waiting for data, only one thread reads anyway
+        let len = try!(self.stream.read(buf));
+        if let Some(ref mut decrypter) = *self.decrypter.lock().unwrap() {
+            try!(apply(decrypter, &mut buf[..len]));
+        }
+        Ok(len)
+    }
+}
+
+impl Write for SymmStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Locked until written, the next bytes are encrypted following these
+        match *self.encrypter.lock().unwrap() {
+            Some(ref mut encrypter) => {
+                let mut data = buf.to_vec();
+                try!(apply(encrypter, &mut data));
+                try!(self.stream.write_all(&data));
+                Ok(buf.len())
+            }
+            None => self.stream.write(buf)
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::{TcpListener, TcpStream};
+
+    use openssl::rsa::{Padding, Rsa};
+
+    #[test]
+    fn hashes() {
+        // Examples from wiki.vg, hashing just the name
+        assert_eq!(server_hash("Notch", &[], &[]), "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48");
+        assert_eq!(server_hash("jeb_", &[], &[]), "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1");
+        assert_eq!(server_hash("simon", &[], &[]), "88e16a1019277b15d58faf0541e11910eb756f6");
+    }
+
+    #[test]
+    fn key_pair() {
+        let key = KeyPair::generate().unwrap();
+        let public = Rsa::public_key_from_der(key.public_der()).unwrap();
+        let mut encrypted = vec![0; public.size() as usize];
+        let len = public.public_encrypt(&[7; SECRET_LEN], &mut encrypted, Padding::PKCS1).unwrap();
+        assert_eq!(key.decrypt(&encrypted[..len]).unwrap(), vec![7; SECRET_LEN]);
+        assert!(key.decrypt(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn encrypted_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = SymmStream::new(TcpStream::connect(listener.local_addr().unwrap()).unwrap());
+        let mut server = SymmStream::new(listener.accept().unwrap().0);
+        let secret = [0x2a; SECRET_LEN];
+        assert!(client.encrypt(&secret[..4]).is_err());
+        client.encrypt(&secret).unwrap();
+        server.encrypt(&secret).unwrap();
+
+        client.write_all(b"hello").unwrap();
+        // Clones carry on where the others left off
+        client.try_clone().unwrap().write_all(b" world").unwrap();
+        let mut received = [0; 11];
+        server.read_exact(&mut received).unwrap();
+        assert_eq!(&received, b"hello world");
+
+        // What goes over the wire isn't the plain text
+        let mut raw = server.get_ref().try_clone().unwrap();
+        client.write_all(b"hello").unwrap();
+        let mut sent = [0; 5];
+        raw.read_exact(&mut sent).unwrap();
+        assert!(&sent != b"hello");
+    }
+}
@@ -0,0 +1,123 @@
+//! What hurts players, and the death messages it ends up in.
+
+use message;
+use types::ChatJson;
+
+/// Ticks after hurting a player during which someone still gets the credit
+/// for their death.
+pub const KILL_CREDIT_TICKS: i64 = 100;
+
+/// Someone who hurt a player.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attacker {
+    pub entity_id: i32,
+    pub name: String
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DamageSource {
+    Generic,
+    Explosion,
+    Fall,
+    OutOfWorld,
+    Starve,
+    /// An arrow, with whoever shot it.
+    Arrow(Option<Attacker>),
+    /// A player hitting them.
+    Player(Attacker),
+    /// A mob hitting them.
+    Mob(Attacker)
+}
+
+impl DamageSource {
+    /// Exhaustion taking damage costs, damage which goes through armor
+    /// doesn't tire.
+    pub fn exhaustion(&self) -> f32 {
+        match *self {
+            DamageSource::Generic | DamageSource::Fall | DamageSource::OutOfWorld | DamageSource::Starve => 0.0,
+            DamageSource::Explosion | DamageSource::Arrow(_) | DamageSource::Player(_) | DamageSource::Mob(_) => 0.3
+        }
+    }
+
+    /// Who's to blame, if anyone.
+    pub fn attacker(&self) -> Option<&Attacker> {
+        match *self {
+            DamageSource::Arrow(ref shooter) => shooter.as_ref(),
+            DamageSource::Player(ref attacker) | DamageSource::Mob(ref attacker) => Some(attacker),
+            _ => None
+        }
+    }
+}
+
+/// A death message, as the translation key vanilla clients know and the
+/// names filling it in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeathMessage {
+    pub key: &'static str,
+    pub with: Vec<String>
+}
+
+impl DeathMessage {
+    /// Message for `victim` dying of `source`, `killer` being whoever hurt
+    /// them last if it wasn't long ago.
+    pub fn new(victim: &str, source: &DamageSource, killer: Option<&Attacker>) -> DeathMessage {
+        let killer = killer.map(|killer| &killer.name[..]);
+        let (key, other) = match *source {
+            // Vanilla names the arrow itself when nobody shot it
+            DamageSource::Arrow(ref shooter) => {
+                ("death.attack.arrow", Some(shooter.as_ref().map_or("Arrow", |shooter| &shooter.name[..])))
+            }
+            DamageSource::Player(ref attacker) => ("death.attack.player", Some(&attacker.name[..])),
+            DamageSource::Mob(ref attacker) => ("death.attack.mob", Some(&attacker.name[..])),
+            DamageSource::Explosion if killer.is_some() => ("death.attack.explosion.player", killer),
+            DamageSource::Explosion => ("death.attack.explosion", None),
+            DamageSource::Fall if killer.is_some() => ("death.fell.assist", killer),
+            DamageSource::Fall => ("death.attack.fall", None),
+            DamageSource::OutOfWorld => ("death.attack.outOfWorld", None),
+            DamageSource::Starve => ("death.attack.starve", None),
+            DamageSource::Generic => ("death.attack.generic", None)
+        };
+        let mut with = vec![victim.to_string()];
+        with.extend(other.map(|name| name.to_string()));
+        DeathMessage { key: key, with: with }
+    }
+
+    /// The message for chat, which clients translate themselves.
+    pub fn to_chat(&self) -> ChatJson {
+        ChatJson::translate(self.key, self.with.iter().map(|name| ChatJson::from(&name[..])).collect())
+    }
+
+    /// The message in English, for the log and CombatEvent.
+    pub fn to_text(&self) -> String {
+        let template = match self.key {
+            "death.attack.arrow" => "%1$s was shot by %2$s",
+            "death.attack.player" | "death.attack.mob" => "%1$s was slain by %2$s",
+            "death.attack.explosion.player" => "%1$s was blown up by %2$s",
+            "death.attack.explosion" => "%1$s blew up",
+            "death.fell.assist" => "%1$s was doomed to fall by %2$s",
+            "death.attack.fall" => "%1$s hit the ground too hard",
+            "death.attack.outOfWorld" => "%1$s fell out of the world",
+            "death.attack.starve" => "%1$s starved to death",
+            _ => "%1$s died"
+        };
+        message::fill(template, &self.with)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn death_messages() {
+        let alex = Attacker { entity_id: 2, name: "Alex".to_string() };
+        let shot = DeathMessage::new("Steve", &DamageSource::Arrow(Some(alex.clone())), None);
+        assert_eq!(shot.key, "death.attack.arrow");
+        assert_eq!(shot.to_text(), "Steve was shot by Alex");
+        // Alex gets the credit for pushing Steve into TNT
+        let blown = DeathMessage::new("Steve", &DamageSource::Explosion, Some(&alex));
+        assert_eq!(blown.to_text(), "Steve was blown up by Alex");
+        assert_eq!(DeathMessage::new("Steve", &DamageSource::Starve, Some(&alex)).to_text(), "Steve starved to death");
+        assert_eq!(DeathMessage::new("Steve", &DamageSource::Arrow(None), None).with, vec!["Steve", "Arrow"]);
+    }
+}
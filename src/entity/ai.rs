@@ -0,0 +1,488 @@
+//! Goal based AI for mobs.
+//!
+//! A mob's brain holds goals by priority. Each tick the first goal which
+//! wants to run steers the mob, straight towards where it's going as long as
+//! there's no wall higher than a step or drop deeper than `MAX_DROP` in the
+//! way. Goals chasing players follow paths from `navigation` instead, which
+//! are searched within a budget shared by all mobs in a tick.
+
+use std::cmp::Ordering;
+
+use block;
+use entity::Entity;
+use entity::navigation::{self, Path};
+
+use rand::Rng;
+
+/// Deepest drop mobs walk off.
+pub const MAX_DROP: i32 = 3;
+
+/// Upwards velocity of a mob stepping up a block.
+pub const STEP_VELOCITY: f64 = 0.42;
+
+/// Ticks between two hits of a melee attack.
+pub const ATTACK_COOLDOWN: i32 = 20;
+
+/// Reach of a melee attack, in blocks.
+pub const ATTACK_REACH: f64 = 2.0;
+
+/// Ticks a path is followed before searching it again.
+pub const REPATH_TICKS: i32 = 20;
+
+/// How far in blocks a player may get from the end of a path before it's
+/// searched again.
+const PATH_SLACK: f64 = 1.5;
+
+/// Something a mob may want to do.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Goal {
+    /// Walk to random spots nearby once in a while.
+    Wander { speed: f64 },
+    /// Turn to face players coming within `range` blocks.
+    LookAtPlayer { range: f64 },
+    /// Run off in a random direction for a while after getting hurt.
+    Panic { speed: f64 },
+    /// Follow players within `range` blocks holding item `item`.
+    Follow { item: u16, range: f64, speed: f64 },
+    /// Chase the nearest player within `range` blocks and hit them.
+    MeleeAttack { range: f64, damage: f32, speed: f64 }
+}
+
+/// A player, as mobs see them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target {
+    pub entity_id: i32,
+    pub position: [f64; 3],
+    /// Id of the item in their hand.
+    pub held_item: Option<u16>,
+    /// Whether they can be attacked, creative and spectator players can't.
+    pub attackable: bool
+}
+
+/// Something a mob did which the world has to carry out.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    /// Hit player `target` for `damage`.
+    Attack { target: i32, damage: f32 }
+}
+
+/// Goals of a mob and how it's getting on with them.
+#[derive(Clone, Debug)]
+pub struct Brain {
+    /// By priority, lowest first.
+    goals: Vec<(u8, Goal)>,
+    /// Index into `goals` of the goal which ran last.
+    running: Option<usize>,
+    /// Where the running goal is taking the mob.
+    destination: Option<[f64; 3]>,
+    /// Ticks left for the running goal.
+    timer: i32,
+    /// Ticks left to panic.
+    panic: i32,
+    cooldown: i32,
+    /// Path the running goal is following, kept between ticks.
+    path: Option<Path>,
+    /// Ticks left before `path` is searched again.
+    repath: i32
+}
+
+impl Brain {
+    /// A brain without any goals, the mob stands still.
+    pub fn new() -> Brain {
+        Brain { goals: vec![], running: None, destination: None, timer: 0, panic: 0, cooldown: 0, path: None, repath: 0 }
+    }
+
+    /// Adds `goal`, taking over from goals with a higher `priority`.
+    pub fn with(mut self, priority: u8, goal: Goal) -> Brain {
+        let index = self.goals.iter().position(|&(other, _)| other > priority).unwrap_or(self.goals.len());
+        self.goals.insert(index, (priority, goal));
+        self
+    }
+
+    /// Animals: wander, look at players and run when hurt.
+    pub fn passive() -> Brain {
+        Brain::new()
+            .with(1, Goal::Panic { speed: 0.2 })
+            .with(6, Goal::Wander { speed: 0.1 })
+            .with(7, Goal::LookAtPlayer { range: 6.0 })
+    }
+
+    /// Monsters: go for players nearby, and wander otherwise.
+    pub fn hostile(damage: f32) -> Brain {
+        Brain::new()
+            .with(2, Goal::MeleeAttack { range: 16.0, damage: damage, speed: 0.15 })
+            .with(7, Goal::Wander { speed: 0.1 })
+            .with(8, Goal::LookAtPlayer { range: 8.0 })
+    }
+
+    pub fn goals(&self) -> &[(u8, Goal)] {
+        &self.goals
+    }
+
+    /// The goal running, if any.
+    pub fn running(&self) -> Option<&Goal> {
+        self.running.map(|index| &self.goals[index].1)
+    }
+
+    /// Makes the mob panic for a while, as when hurt.
+    pub fn alarm(&mut self) {
+        self.panic = 100;
+        self.destination = None;
+        self.path = None;
+    }
+
+    /// The path being followed, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_ref()
+    }
+
+    /// Runs the first goal which wants to, steering `entity`. Path searches
+    /// look at no more than `budget` blocks, which is lowered by as many as
+    /// they do.
+    pub fn tick<F, R>(&mut self, entity: &mut Entity, targets: &[Target], block_at: &F, rng: &mut R, budget: &mut usize) -> Option<Action>
+        where F: Fn([i32; 3]) -> u16, R: Rng
+    {
+        self.panic = (self.panic - 1).max(0);
+        self.cooldown = (self.cooldown - 1).max(0);
+        for index in 0..self.goals.len() {
+            if self.running != Some(index) && !self.should_start(index, entity, targets, rng) {
+                continue;
+            }
+            if self.running != Some(index) {
+                self.running = Some(index);
+                self.destination = None;
+                self.timer = 0;
+                self.path = None;
+            }
+            let goal = self.goals[index].1.clone();
+            match self.run(&goal, entity, targets, block_at, rng, budget) {
+                Some(result) => return result,
+                // Done, let the next goal have a go
+                None => self.running = None
+            }
+        }
+        stop(entity);
+        None
+    }
+
+    /// Whether goal `index`, which isn't running, wants to.
+    fn should_start<R: Rng>(&self, index: usize, entity: &Entity, targets: &[Target], rng: &mut R) -> bool {
+        match self.goals[index].1 {
+            Goal::Panic { .. } => self.panic > 0,
+            Goal::Wander { .. } => rng.gen_range(0, 120) == 0,
+            Goal::LookAtPlayer { range } => nearest(entity, targets, range, |_| true).is_some() && rng.gen_range(0, 50) == 0,
+            Goal::Follow { item, range, .. } => nearest(entity, targets, range, |target| target.held_item == Some(item)).is_some(),
+            Goal::MeleeAttack { range, .. } => nearest(entity, targets, range, |target| target.attackable).is_some()
+        }
+    }
+
+    /// Runs `goal` for a tick. Returns `None` once it's done, or what it
+    /// wants the world to do.
+    fn run<F, R>(&mut self, goal: &Goal, entity: &mut Entity, targets: &[Target], block_at: &F, rng: &mut R, budget: &mut usize) -> Option<Option<Action>>
+        where F: Fn([i32; 3]) -> u16, R: Rng
+    {
+        match *goal {
+            Goal::Panic { speed } => {
+                if self.panic == 0 {
+                    return None;
+                }
+                let destination = match self.destination {
+                    Some(destination) => destination,
+                    None => random_spot(entity.position, 5.0, rng)
+                };
+                // Pick another way out when this one's blocked or reached
+                self.destination = if steer(entity, destination, speed, block_at) { Some(destination) } else { None };
+                Some(None)
+            }
+            Goal::Wander { speed } => {
+                if self.destination.is_none() {
+                    self.destination = Some(random_spot(entity.position, 10.0, rng));
+                    self.timer = 200;
+                }
+                self.timer -= 1;
+                let destination = self.destination.unwrap();
+                if self.timer <= 0 || !steer(entity, destination, speed, block_at) {
+                    return None;
+                }
+                Some(None)
+            }
+            Goal::LookAtPlayer { range } => {
+                if self.timer == 0 {
+                    self.timer = 40 + rng.gen_range(0, 40);
+                }
+                self.timer -= 1;
+                match nearest(entity, targets, range, |_| true) {
+                    Some(target) if self.timer > 0 => {
+                        stop(entity);
+                        look_at(entity, target.position);
+                        Some(None)
+                    }
+                    _ => None
+                }
+            }
+            Goal::Follow { item, range, speed } => {
+                let target = match nearest(entity, targets, range, |target| target.held_item == Some(item)) {
+                    Some(target) => target,
+                    None => return None
+                };
+                if distance(entity.position, target.position) > 2.5 {
+                    self.chase(entity, target.position, speed, block_at, budget);
+                } else {
+                    stop(entity);
+                }
+                look_at(entity, target.position);
+                Some(None)
+            }
+            Goal::MeleeAttack { range, damage, speed } => {
+                let target = match nearest(entity, targets, range, |target| target.attackable) {
+                    Some(target) => target,
+                    None => return None
+                };
+                look_at(entity, target.position);
+                if distance(entity.position, target.position) > ATTACK_REACH {
+                    self.chase(entity, target.position, speed, block_at, budget);
+                    look_at(entity, target.position);
+                    return Some(None);
+                }
+                stop(entity);
+                if self.cooldown > 0 {
+                    return Some(None);
+                }
+                self.cooldown = ATTACK_COOLDOWN;
+                Some(Some(Action::Attack { target: target.entity_id, damage: damage }))
+            }
+        }
+    }
+
+    /// Steers `entity` along a path to `to`, searching it again every
+    /// `REPATH_TICKS` or once `to` moved away from its end. Goes straight
+    /// for `to` when there's no path and no budget left to search one.
+    fn chase<F: Fn([i32; 3]) -> u16>(&mut self, entity: &mut Entity, to: [f64; 3], speed: f64, block_at: &F, budget: &mut usize) {
+        let goal = block_of(to);
+        self.repath -= 1;
+        let stale = match self.path.as_ref().and_then(Path::end) {
+            Some(end) => self.repath <= 0 || distance(center_of(end), center_of(goal)) > PATH_SLACK,
+            None => true
+        };
+        if stale && *budget > 0 {
+            let limit = navigation::MAX_SEARCH.min(*budget);
+            let (path, searched) = navigation::find_path(block_of(entity.position), goal, block_at, limit);
+            *budget -= searched;
+            self.path = path;
+            self.repath = REPATH_TICKS;
+        }
+        let next = match self.path {
+            Some(ref mut path) => {
+                // Close enough to the block walked to, on to the next
+                if path.next().map_or(false, |next| horizontal_distance(entity.position, center_of(next)) < 0.5) {
+                    path.advance();
+                }
+                path.next()
+            }
+            None => None
+        };
+        match next {
+            Some(next) => {
+                if !steer(entity, center_of(next), speed, block_at) {
+                    self.path = None;
+                }
+            }
+            None => {
+                self.path = None;
+                steer(entity, to, speed, block_at);
+            }
+        }
+    }
+}
+
+/// The nearest of `targets` within `range` blocks of `entity` for which
+/// `filter` holds.
+fn nearest<'a, P: Fn(&Target) -> bool>(entity: &Entity, targets: &'a [Target], range: f64, filter: P) -> Option<&'a Target> {
+    targets.iter()
+        .filter(|target| filter(target) && distance(entity.position, target.position) <= range)
+        .min_by(|a, b| {
+            let (a, b) = (distance(entity.position, a.position), distance(entity.position, b.position));
+            a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+        })
+}
+
+fn distance(a: [f64; 3], b: [f64; 3]) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+fn horizontal_distance(a: [f64; 3], b: [f64; 3]) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+/// The block `position` is in.
+fn block_of(position: [f64; 3]) -> [i32; 3] {
+    [position[0].floor() as i32, position[1].floor() as i32, position[2].floor() as i32]
+}
+
+/// Where a mob stands in the middle of block `location`.
+fn center_of(location: [i32; 3]) -> [f64; 3] {
+    [location[0] as f64 + 0.5, location[1] as f64, location[2] as f64 + 0.5]
+}
+
+/// A spot at most `radius` blocks away from `from` on both axes.
+fn random_spot<R: Rng>(from: [f64; 3], radius: f64, rng: &mut R) -> [f64; 3] {
+    [from[0] + rng.gen_range(-radius, radius), from[1], from[2] + rng.gen_range(-radius, radius)]
+}
+
+/// Stops walking, letting friction do the rest.
+fn stop(entity: &mut Entity) {
+    entity.velocity[0] = 0.0;
+    entity.velocity[2] = 0.0;
+}
+
+/// Turns `entity` towards `position`, about where eyes are.
+fn look_at(entity: &mut Entity, position: [f64; 3]) {
+    let (dx, dz) = (position[0] - entity.position[0], position[2] - entity.position[2]);
+    let dy = position[1] - entity.position[1];
+    entity.yaw = (-dx).atan2(dz).to_degrees() as f32;
+    entity.pitch = -dy.atan2((dx * dx + dz * dz).sqrt()).to_degrees() as f32;
+}
+
+/// Sets `entity` walking straight for `to` at `speed` blocks per tick,
+/// stepping up single blocks. Returns whether it's on its way, rather than
+/// there already or stopped by a wall or drop.
+pub fn steer<F: Fn([i32; 3]) -> u16>(entity: &mut Entity, to: [f64; 3], speed: f64, block_at: &F) -> bool {
+    let solid = |location: [i32; 3]| block::is_solid(block_at(location) >> 4);
+    let (dx, dz) = (to[0] - entity.position[0], to[2] - entity.position[2]);
+    let horizontal = (dx * dx + dz * dz).sqrt();
+    if horizontal < 0.5 {
+        stop(entity);
+        return false;
+    }
+    let (nx, nz) = (dx / horizontal, dz / horizontal);
+    let feet = entity.position[1].floor() as i32;
+    let ahead = |y: i32| [(entity.position[0] + nx * 0.8).floor() as i32, y, (entity.position[2] + nz * 0.8).floor() as i32];
+    let here = [entity.position[0].floor() as i32, feet, entity.position[2].floor() as i32];
+    let mut jump = false;
+    if solid(ahead(feet)) {
+        // A step needs room for the mob above it
+        if solid(ahead(feet + 1)) || solid(ahead(feet + 2)) || solid([here[0], feet + 2, here[2]]) {
+            stop(entity);
+            return false;
+        }
+        jump = true;
+    } else if solid(ahead(feet + 1)) {
+        stop(entity);
+        return false;
+    } else if (1..MAX_DROP + 2).all(|depth| !solid(ahead(feet - depth))) {
+        stop(entity);
+        return false;
+    }
+    entity.velocity[0] = nx * speed;
+    entity.velocity[2] = nz * speed;
+    if jump && entity.on_ground {
+        entity.velocity[1] = STEP_VELOCITY;
+        entity.on_ground = false;
+    }
+    entity.yaw = (-dx).atan2(dz).to_degrees() as f32;
+    entity.pitch = 0.0;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use entity::{Entity, EntityKind, Horse};
+
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn ground(location: [i32; 3]) -> u16 {
+        // Floor at 63, a step at x = 3, a wall at x = 6 and a pit at z < -2
+        let solid = location[1] < 64 && location[2] >= -2
+            || location[0] == 3 && location[1] == 64
+            || location[0] == 6 && location[1] < 70;
+        if solid { 1 << 4 } else { 0 }
+    }
+
+    fn horse() -> Entity {
+        let mut entity = Entity::new(1, EntityKind::Horse(Horse::random()), [0.5, 64.0, 0.5]);
+        entity.on_ground = true;
+        entity
+    }
+
+    #[test]
+    fn steering() {
+        let mut entity = horse();
+        assert!(steer(&mut entity, [10.5, 64.0, 0.5], 0.1, &ground));
+        assert!(entity.velocity[0] > 0.0);
+        // Steps up onto the block at x = 3, stops at the wall
+        let mut stepped = false;
+        for _ in 0..200 {
+            if !steer(&mut entity, [10.5, 64.0, 0.5], 0.1, &ground) {
+                break;
+            }
+            entity.tick(ground);
+            stepped |= entity.position[1] >= 65.0;
+        }
+        assert!(stepped);
+        assert!(entity.position[0] < 6.0);
+        // Won't walk into the pit
+        let mut entity = horse();
+        entity.position = [0.5, 64.0, -1.5];
+        assert!(!steer(&mut entity, [0.5, 64.0, -10.0], 0.1, &ground));
+    }
+
+    #[test]
+    fn goals() {
+        let mut rng = StdRng::from_seed([7; 32]);
+        let mut budget = navigation::TICK_BUDGET;
+        let mut entity = horse();
+        let mut brain = Brain::hostile(3.0).with(1, Goal::Panic { speed: 0.2 });
+        assert_eq!(brain.goals()[0].0, 1);
+        let target = Target { entity_id: 9, position: [1.5, 64.0, 0.5], held_item: None, attackable: true };
+        let action = brain.tick(&mut entity, &[target.clone()], &ground, &mut rng, &mut budget);
+        assert_eq!(action, Some(Action::Attack { target: 9, damage: 3.0 }));
+        // Cooling down
+        assert_eq!(brain.tick(&mut entity, &[target.clone()], &ground, &mut rng, &mut budget), None);
+        // Panicking beats attacking
+        brain.alarm();
+        brain.tick(&mut entity, &[target.clone()], &ground, &mut rng, &mut budget);
+        assert_eq!(brain.running(), Some(&Goal::Panic { speed: 0.2 }));
+        // Creative players are left alone
+        let creative = Target { attackable: false, ..target };
+        let mut brain = Brain::hostile(3.0);
+        for _ in 0..20 {
+            assert_eq!(brain.tick(&mut entity, &[creative.clone()], &ground, &mut rng, &mut budget), None);
+        }
+    }
+
+    #[test]
+    fn chasing() {
+        // A wall at x = 3 with a gap at z = 4
+        let walled = |location: [i32; 3]| -> u16 {
+            let solid = location[1] < 64 || location[0] == 3 && location[2] != 4 && location[1] < 70;
+            if solid { 1 << 4 } else { 0 }
+        };
+        let mut rng = StdRng::from_seed([7; 32]);
+        let mut entity = horse();
+        let mut brain = Brain::new().with(1, Goal::MeleeAttack { range: 16.0, damage: 1.0, speed: 0.2 });
+        let target = Target { entity_id: 9, position: [6.5, 64.0, 0.5], held_item: None, attackable: true };
+        // Out of budget the mob heads straight for the wall
+        let mut budget = 0;
+        brain.tick(&mut entity, &[target.clone()], &walled, &mut rng, &mut budget);
+        assert!(brain.path().is_none());
+        let mut budget = navigation::TICK_BUDGET;
+        brain.tick(&mut entity, &[target.clone()], &walled, &mut rng, &mut budget);
+        assert!(budget < navigation::TICK_BUDGET);
+        let path = brain.path().unwrap().clone();
+        assert!(path.complete);
+        assert!(path.blocks.contains(&[3, 64, 4]));
+        // The path is kept while the target stays put
+        let left = budget;
+        brain.tick(&mut entity, &[target.clone()], &walled, &mut rng, &mut budget);
+        assert_eq!(budget, left);
+        // And takes the mob through the gap
+        for _ in 0..200 {
+            brain.tick(&mut entity, &[target.clone()], &walled, &mut rng, &mut budget);
+            entity.tick(&walled);
+        }
+        assert!(entity.position[0] > 4.0);
+    }
+}
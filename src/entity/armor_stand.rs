@@ -0,0 +1,156 @@
+//! Armor stands, wearing armor in a pose.
+
+use item;
+use types::{EntityMetadata as Metadata, MetadataEntry, Slot};
+use types::metadata_index::armor_stand;
+
+/// Equipment slot of the held item, as numbered in EntityEquipment. Boots,
+/// leggings, chestplate and helmet follow.
+pub const HELD_SLOT: usize = 0;
+
+/// Number of equipment slots.
+pub const EQUIPMENT_SLOTS: usize = 5;
+
+/// Rotations of the body parts in degrees, around the x, y then z axis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pose {
+    pub head: [f32; 3],
+    pub body: [f32; 3],
+    pub left_arm: [f32; 3],
+    pub right_arm: [f32; 3],
+    pub left_leg: [f32; 3],
+    pub right_leg: [f32; 3]
+}
+
+impl Default for Pose {
+    /// The pose armor stands are placed in.
+    fn default() -> Pose {
+        Pose {
+            head: [0.0, 0.0, 0.0],
+            body: [0.0, 0.0, 0.0],
+            left_arm: [-10.0, 0.0, -10.0],
+            right_arm: [-15.0, 0.0, 10.0],
+            left_leg: [-1.0, 0.0, -1.0],
+            right_leg: [1.0, 0.0, 1.0]
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ArmorStand {
+    equipment: [Option<Slot>; EQUIPMENT_SLOTS],
+    pub pose: Pose,
+    pub small: bool,
+    pub no_gravity: bool,
+    /// Only armor stands showing their arms can hold an item.
+    pub arms: bool,
+    pub base_plate: bool
+}
+
+impl ArmorStand {
+    pub fn new() -> ArmorStand {
+        ArmorStand {
+            equipment: [None, None, None, None, None],
+            pose: Pose::default(),
+            small: false,
+            no_gravity: false,
+            arms: false,
+            base_plate: true
+        }
+    }
+
+    /// Item in equipment slot `slot`.
+    pub fn equipment(&self, slot: usize) -> Option<&Slot> {
+        self.equipment.get(slot).and_then(|stack| stack.as_ref())
+    }
+
+    /// Equipment slot `stack` goes in when a player puts it on the stand,
+    /// `None` if it can't.
+    pub fn slot_for(&self, stack: &Slot) -> Option<usize> {
+        match item::armor_slot(stack.id()) {
+            // Helmets are slot 4, boots slot 1
+            Some(armor) => Some(4 - armor),
+            None if self.arms => Some(HELD_SLOT),
+            None => None
+        }
+    }
+
+    /// Equipment slot at `height` above the stand's feet, where an empty
+    /// handed player clicked it, `None` when there's nothing to take there.
+    pub fn slot_at(&self, height: f32) -> Option<usize> {
+        // Small stands are half the height
+        let (height, small) = if self.small { (height * 2.0, 1.0) } else { (height, 0.0) };
+        let slot = if height >= 0.1 && height < 0.1 + 0.45 + small * 0.35 && self.equipment[1].is_some() {
+            1
+        } else if height >= 0.9 + small * 0.3 && height < 0.9 + 0.7 + small * 0.3 && self.equipment[3].is_some() {
+            3
+        } else if height >= 0.4 && height < 0.4 + 0.8 + small * 0.2 && self.equipment[2].is_some() {
+            2
+        } else if height >= 1.6 && self.equipment[4].is_some() {
+            4
+        } else if self.equipment[HELD_SLOT].is_some() {
+            HELD_SLOT
+        } else {
+            return None;
+        };
+        Some(slot)
+    }
+
+    /// Puts `stack` in equipment slot `slot`, returns what was there.
+    pub fn swap(&mut self, slot: usize, stack: Option<Slot>) -> Option<Slot> {
+        match self.equipment.get_mut(slot) {
+            Some(equipment) => ::std::mem::replace(equipment, stack),
+            None => stack
+        }
+    }
+
+    /// Takes all the equipment off, when the stand gets broken.
+    pub fn take_equipment(&mut self) -> Vec<Slot> {
+        self.equipment.iter_mut().filter_map(|stack| stack.take()).collect()
+    }
+
+    pub fn metadata(&self, metadata: &mut Metadata) {
+        let mut flags = 0;
+        if self.small {
+            flags |= 0x01;
+        }
+        if self.no_gravity {
+            flags |= 0x02;
+        }
+        if self.arms {
+            flags |= 0x04;
+        }
+        if !self.base_plate {
+            flags |= 0x08;
+        }
+        metadata.set(armor_stand::FLAGS, MetadataEntry::Byte(flags));
+        metadata.set(armor_stand::HEAD, MetadataEntry::Float3(self.pose.head));
+        metadata.set(armor_stand::BODY, MetadataEntry::Float3(self.pose.body));
+        metadata.set(armor_stand::LEFT_ARM, MetadataEntry::Float3(self.pose.left_arm));
+        metadata.set(armor_stand::RIGHT_ARM, MetadataEntry::Float3(self.pose.right_arm));
+        metadata.set(armor_stand::LEFT_LEG, MetadataEntry::Float3(self.pose.left_leg));
+        metadata.set(armor_stand::RIGHT_LEG, MetadataEntry::Float3(self.pose.right_leg));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use types::Slot;
+
+    #[test]
+    fn equip() {
+        let mut stand = ArmorStand::new();
+        let helmet = Slot::new(310, 1, 0);
+        assert_eq!(stand.slot_for(&helmet), Some(4));
+        assert_eq!(stand.slot_for(&Slot::new(317, 1, 0)), Some(1));
+        // Swords need arms
+        assert_eq!(stand.slot_for(&Slot::new(276, 1, 0)), None);
+        assert!(stand.swap(4, Some(helmet)).is_none());
+        assert_eq!(stand.slot_at(1.8), Some(4));
+        assert_eq!(stand.slot_at(0.2), None);
+        assert_eq!(stand.take_equipment().len(), 1);
+        assert!(stand.equipment(4).is_none());
+    }
+}
@@ -0,0 +1,228 @@
+//! Horses, donkeys and mules.
+
+use item;
+use types::{EntityMetadata as Metadata, MetadataEntry, Slot, hyphenated};
+use types::metadata_index::horse;
+
+use rand::{self, Rng};
+use uuid::Uuid;
+
+/// Window slot holding the saddle.
+pub const SADDLE_SLOT: usize = 0;
+
+/// Window slot holding the armor, only plain horses can wear some.
+pub const ARMOR_SLOT: usize = 1;
+
+/// Slots added to the window by the chest of a donkey or mule.
+pub const CHEST_SIZE: usize = 15;
+
+/// Speed added, in blocks per tick, each time a rider pushes forward.
+pub const SPEED: f64 = 0.1;
+
+/// Upwards velocity of a jump charged all the way.
+pub const JUMP_VELOCITY: f64 = 0.6;
+
+/// Horse type, sent in the metadata.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HorseKind {
+    Horse = 0,
+    Donkey = 1,
+    Mule = 2,
+    Zombie = 3,
+    Skeleton = 4
+}
+
+impl HorseKind {
+    /// Name shown as the title of the inventory window.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            HorseKind::Horse => "Horse",
+            HorseKind::Donkey => "Donkey",
+            HorseKind::Mule => "Mule",
+            HorseKind::Zombie => "Zombie Horse",
+            HorseKind::Skeleton => "Skeleton Horse"
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Horse {
+    pub kind: HorseKind,
+    /// Color in the low byte and markings in the next one, only plain horses
+    /// show it.
+    pub variant: i32,
+    /// Player the horse has been tamed by.
+    pub owner: Option<Uuid>,
+    /// Grows with each failed taming attempt, making the next one likelier
+    /// to succeed.
+    temper: i32,
+    saddle: Option<Slot>,
+    armor: Option<Slot>,
+    /// Chest slots of a donkey or mule carrying one.
+    chest: Option<Vec<Option<Slot>>>
+}
+
+impl Horse {
+    pub fn new(kind: HorseKind, variant: i32) -> Horse {
+        Horse {
+            kind: kind,
+            variant: variant,
+            owner: None,
+            temper: 0,
+            saddle: None,
+            armor: None,
+            chest: None
+        }
+    }
+
+    /// A wild horse like the ones spawn eggs give, once in a while a donkey.
+    pub fn random() -> Horse {
+        let mut rng = rand::thread_rng();
+        if rng.gen_range(0, 10) == 0 {
+            return Horse::new(HorseKind::Donkey, 0);
+        }
+        let color = rng.gen_range(0, 7);
+        let markings = rng.gen_range(0, 5);
+        Horse::new(HorseKind::Horse, color | markings << 8)
+    }
+
+    pub fn tame(&self) -> bool { self.owner.is_some() }
+    pub fn saddled(&self) -> bool { self.saddle.is_some() }
+
+    /// Tries to tame the horse for `owner`, as vanilla does each time a
+    /// player gets on a wild horse. Returns whether it worked.
+    pub fn try_tame(&mut self, owner: Uuid) -> bool {
+        if self.tame() {
+            return true;
+        }
+        if rand::thread_rng().gen_range(0, 100) < self.temper {
+            self.owner = Some(owner);
+            true
+        } else {
+            self.temper = (self.temper + 5).min(100);
+            false
+        }
+    }
+
+    /// Puts a saddle or, on a donkey or mule, a chest on the horse straight
+    /// from a player's hand. Returns whether `stack` was used.
+    pub fn equip(&mut self, stack: &Slot) -> bool {
+        match stack.id() {
+            item::SADDLE if self.saddle.is_none() => {
+                let mut saddle = stack.clone();
+                saddle.set_count(1);
+                self.saddle = Some(saddle);
+                true
+            }
+            item::CHEST if self.chest.is_none() && self.can_carry_chest() => {
+                self.chest = Some(vec![None; CHEST_SIZE]);
+                true
+            }
+            _ => false
+        }
+    }
+
+    pub fn can_carry_chest(&self) -> bool {
+        self.kind == HorseKind::Donkey || self.kind == HorseKind::Mule
+    }
+
+    /// Number of slots in the horse's part of its inventory window.
+    pub fn window_size(&self) -> usize {
+        2 + self.chest.as_ref().map_or(0, |chest| chest.len())
+    }
+
+    /// Contents of the horse's part of its inventory window.
+    pub fn items(&self) -> Vec<Option<Slot>> {
+        let mut items = vec![self.saddle.clone(), self.armor.clone()];
+        if let Some(ref chest) = self.chest {
+            items.extend(chest.iter().cloned());
+        }
+        items
+    }
+
+    /// Window slot `index`, `None` when out of range.
+    pub fn slot_mut(&mut self, index: usize) -> Option<&mut Option<Slot>> {
+        match index {
+            SADDLE_SLOT => Some(&mut self.saddle),
+            ARMOR_SLOT => Some(&mut self.armor),
+            _ => self.chest.as_mut().and_then(|chest| chest.get_mut(index - 2))
+        }
+    }
+
+    /// Whether `stack` can go in window slot `index`.
+    pub fn accepts(&self, index: usize, stack: &Slot) -> bool {
+        match index {
+            SADDLE_SLOT => stack.id() == item::SADDLE,
+            ARMOR_SLOT => self.kind == HorseKind::Horse && armor_type(stack.id()) != 0,
+            _ => index < self.window_size()
+        }
+    }
+
+    pub fn metadata(&self, metadata: &mut Metadata) {
+        let mut flags = 0;
+        if self.tame() {
+            flags |= 0x02;
+        }
+        if self.saddled() {
+            flags |= 0x04;
+        }
+        if self.chest.is_some() {
+            flags |= 0x08;
+        }
+        metadata.set(horse::FLAGS, MetadataEntry::Int(flags));
+        metadata.set(horse::KIND, MetadataEntry::Byte(self.kind as u8));
+        metadata.set(horse::VARIANT, MetadataEntry::Int(self.variant));
+        let owner = self.owner.map_or(String::new(), |owner| hyphenated(&owner));
+        metadata.set(horse::OWNER, MetadataEntry::String(owner));
+        metadata.set(horse::ARMOR, MetadataEntry::Int(self.armor.as_ref().map_or(0, |armor| armor_type(armor.id()))));
+    }
+}
+
+/// Armor type sent in the metadata for armor item `id`, 0 if it isn't horse
+/// armor.
+pub fn armor_type(id: u16) -> i32 {
+    match id {
+        item::IRON_HORSE_ARMOR => 1,
+        item::GOLDEN_HORSE_ARMOR => 2,
+        item::DIAMOND_HORSE_ARMOR => 3,
+        _ => 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use item;
+    use types::Slot;
+
+    #[test]
+    fn slots() {
+        let mut donkey = Horse::new(HorseKind::Donkey, 0);
+        assert_eq!(donkey.window_size(), 2);
+        assert!(donkey.slot_mut(2).is_none());
+        assert!(!donkey.accepts(ARMOR_SLOT, &Slot::new(item::IRON_HORSE_ARMOR, 1, 0)));
+        assert!(donkey.equip(&Slot::new(item::CHEST, 1, 0)));
+        assert!(!donkey.equip(&Slot::new(item::CHEST, 1, 0)));
+        assert_eq!(donkey.window_size(), 2 + CHEST_SIZE);
+        *donkey.slot_mut(16).unwrap() = Some(Slot::new(1, 64, 0));
+        assert_eq!(donkey.items()[16].as_ref().map(|stack| stack.id()), Some(1));
+
+        let horse = Horse::new(HorseKind::Horse, 0);
+        assert!(horse.accepts(ARMOR_SLOT, &Slot::new(item::DIAMOND_HORSE_ARMOR, 1, 0)));
+        assert!(!horse.accepts(SADDLE_SLOT, &Slot::new(item::DIAMOND_HORSE_ARMOR, 1, 0)));
+    }
+
+    #[test]
+    fn taming_gets_easier() {
+        let mut horse = Horse::new(HorseKind::Horse, 0);
+        let owner = Uuid::new_v4();
+        let mut attempts = 0;
+        while !horse.try_tame(owner) {
+            attempts += 1;
+        }
+        // Temper reaches 100 after 20 failures, which always succeeds
+        assert!(attempts <= 20);
+        assert_eq!(horse.owner, Some(owner));
+    }
+}
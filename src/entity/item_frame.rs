@@ -0,0 +1,99 @@
+//! Item frames, hanging on the side of a block and showing off an item.
+
+use types::{EntityMetadata as Metadata, MetadataEntry, Slot};
+use types::metadata_index::item_frame;
+use types::consts::BlockFace;
+
+/// Number of ways an item can be turned in a frame, 45° apart.
+pub const ROTATIONS: u8 = 8;
+
+#[derive(Clone, Debug)]
+pub struct ItemFrame {
+    /// Face of the supporting block the frame hangs on, always a
+    /// horizontal one.
+    pub facing: BlockFace,
+    item: Option<Slot>,
+    /// Steps of 45° the item is turned by clockwise.
+    rotation: u8
+}
+
+impl ItemFrame {
+    /// A frame hanging on the `facing` side of a block, `None` for the top
+    /// and bottom faces.
+    pub fn new(facing: BlockFace) -> Option<ItemFrame> {
+        match facing {
+            BlockFace::North | BlockFace::South | BlockFace::West | BlockFace::East => {
+                Some(ItemFrame { facing: facing, item: None, rotation: 0 })
+            }
+            BlockFace::Up | BlockFace::Down | BlockFace::Special => None
+        }
+    }
+
+    pub fn item(&self) -> Option<&Slot> { self.item.as_ref() }
+    pub fn rotation(&self) -> u8 { self.rotation }
+
+    /// Direction sent as the object data of SpawnObject: 0 for south, then
+    /// clockwise.
+    pub fn direction(&self) -> i32 {
+        match self.facing {
+            BlockFace::South => 0,
+            BlockFace::West => 1,
+            BlockFace::North => 2,
+            _ => 3
+        }
+    }
+
+    /// Right click with `held`: an empty frame takes one of it, otherwise
+    /// the item in the frame is turned. Returns whether `held` was used.
+    pub fn interact(&mut self, held: Option<&Slot>) -> bool {
+        if self.item.is_some() {
+            self.rotation = (self.rotation + 1) % ROTATIONS;
+            return false;
+        }
+        match held {
+            Some(stack) => {
+                let mut item = stack.clone();
+                item.set_count(1);
+                self.item = Some(item);
+                self.rotation = 0;
+                true
+            }
+            None => false
+        }
+    }
+
+    /// Takes the item out of the frame, when it's hit.
+    pub fn take_item(&mut self) -> Option<Slot> {
+        self.rotation = 0;
+        self.item.take()
+    }
+
+    pub fn metadata(&self, metadata: &mut Metadata) {
+        metadata.set(item_frame::ITEM, MetadataEntry::Slot(self.item.clone()));
+        metadata.set(item_frame::ROTATION, MetadataEntry::Byte(self.rotation));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use types::Slot;
+    use types::consts::BlockFace;
+
+    #[test]
+    fn place_and_rotate() {
+        assert!(ItemFrame::new(BlockFace::Up).is_none());
+        let mut frame = ItemFrame::new(BlockFace::West).unwrap();
+        assert_eq!(frame.direction(), 1);
+        assert!(!frame.interact(None));
+        assert!(frame.interact(Some(&Slot::new(276, 5, 0))));
+        assert_eq!(frame.item().map(|item| item.count()), Some(1));
+        for _ in 0..ROTATIONS + 1 {
+            assert!(!frame.interact(Some(&Slot::new(1, 1, 0))));
+        }
+        assert_eq!(frame.rotation(), 1);
+        assert_eq!(frame.take_item().map(|item| item.id()), Some(276));
+        assert_eq!(frame.rotation(), 0);
+    }
+}
@@ -0,0 +1,426 @@
+//! Non-player entities.
+
+use block;
+use packet::PacketWrite;
+use packet::play::clientbound::{EntityEquipment, EntityMetadata, EntityTeleport, EntityVelocity, SpawnExperienceOrb, SpawnMob};
+use packet::play::clientbound::SpawnObject;
+use types::consts::{MinecartKind, MobKind, ObjectKind};
+use types::{EntityMetadata as Metadata, ObjectData, Slot};
+
+use rand::Rng;
+
+pub use self::ai::{Action, Brain, Goal, Target};
+pub use self::armor_stand::ArmorStand;
+pub use self::horse::{Horse, HorseKind};
+pub use self::item_frame::ItemFrame;
+pub use self::monster::{Monster, MonsterKind};
+
+pub mod ai;
+pub mod armor_stand;
+pub mod horse;
+pub mod item_frame;
+pub mod monster;
+pub mod navigation;
+pub mod sanitize;
+
+/// An entity living in a world.
+#[derive(Clone, Debug)]
+pub struct Entity {
+    pub id: i32,
+    pub kind: EntityKind,
+    pub position: [f64; 3],
+    /// Velocity in blocks per tick.
+    pub velocity: [f64; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    pub on_ground: bool,
+    /// Entity id of the player riding this entity.
+    pub passenger: Option<i32>,
+    /// Ticks since the entity spawned.
+    pub age: i32,
+    /// Set once the entity is gone, it's then dropped on the next tick.
+    pub removed: bool,
+    /// Goals of a mob, `None` for entities which don't think.
+    pub brain: Option<Brain>
+}
+
+#[derive(Clone, Debug)]
+pub enum EntityKind {
+    /// A dropped item stack.
+    Item(Slot),
+    /// Experience worth this many points, collected by walking into it.
+    ExperienceOrb(i16),
+    /// Lit TNT, `fuse` is the number of ticks left before it explodes.
+    PrimedTnt { fuse: i32 },
+    /// An arrow, snowball or egg, `stuck` once an arrow lands in a block and
+    /// `pickup` if players can then collect it.
+    Projectile { kind: ObjectKind, shooter: i32, stuck: bool, pickup: bool },
+    /// A boat or an empty minecart, which players can ride.
+    Vehicle(ObjectKind),
+    /// A horse, donkey or mule.
+    Horse(Horse),
+    /// An item frame, positioned in the middle of the block it fills.
+    ItemFrame(ItemFrame),
+    ArmorStand(ArmorStand),
+    /// A zombie, skeleton or spider.
+    Monster(Monster)
+}
+
+/// Fuse of TNT lit by a player, in ticks.
+pub const TNT_FUSE: i32 = 80;
+
+/// Power of a TNT explosion.
+pub const TNT_POWER: f32 = 4.0;
+
+impl Entity {
+    pub fn new(id: i32, kind: EntityKind, position: [f64; 3]) -> Entity {
+        Entity {
+            id: id,
+            kind: kind,
+            position: position,
+            velocity: [0.0; 3],
+            yaw: 0.0,
+            pitch: 0.0,
+            on_ground: false,
+            passenger: None,
+            age: 0,
+            removed: false,
+            brain: None
+        }
+    }
+
+    /// Name of the entity, as in death messages.
+    pub fn name(&self) -> &'static str {
+        match self.kind {
+            EntityKind::Horse(ref horse) => horse.kind.name(),
+            EntityKind::Monster(ref monster) => monster.kind.name(),
+            EntityKind::Item(_) => "Item",
+            EntityKind::ExperienceOrb(_) => "Experience Orb",
+            EntityKind::PrimedTnt { .. } => "Primed TNT",
+            EntityKind::Projectile { kind: ObjectKind::Arrow, .. } => "Arrow",
+            EntityKind::Projectile { .. } => "Projectile",
+            EntityKind::Vehicle(ObjectKind::Boat) => "Boat",
+            EntityKind::Vehicle(_) => "Minecart",
+            EntityKind::ItemFrame(_) => "Item Frame",
+            EntityKind::ArmorStand(_) => "Armor Stand"
+        }
+    }
+
+    /// Lets the entity's brain steer it for a tick, unless it has none or
+    /// someone's riding it. Path searches share `budget` with other mobs.
+    /// Returns what it wants done.
+    pub fn think<F: Fn([i32; 3]) -> u16, R: Rng>(&mut self, targets: &[Target], block_at: &F, rng: &mut R, budget: &mut usize) -> Option<Action> {
+        if self.passenger.is_some() || self.removed {
+            return None;
+        }
+        let mut brain = match self.brain.take() {
+            Some(brain) => brain,
+            None => return None
+        };
+        let action = brain.tick(self, targets, block_at, rng, budget);
+        self.brain = Some(brain);
+        action
+    }
+
+    /// Kind sent in SpawnObject for this entity, `None` for mobs and
+    /// experience orbs.
+    pub fn object_kind(&self) -> Option<ObjectKind> {
+        match self.kind {
+            EntityKind::Item(_) => Some(ObjectKind::ItemStack),
+            EntityKind::PrimedTnt { .. } => Some(ObjectKind::ActivatedTnt),
+            EntityKind::Projectile { kind, .. } | EntityKind::Vehicle(kind) => Some(kind),
+            EntityKind::ItemFrame(_) => Some(ObjectKind::ItemFrame),
+            EntityKind::ArmorStand(_) => Some(ObjectKind::ArmorStand),
+            EntityKind::Horse(_) | EntityKind::Monster(_) | EntityKind::ExperienceOrb(_) => None
+        }
+    }
+
+    /// Kind sent in SpawnMob for this entity, `None` for objects.
+    pub fn mob_kind(&self) -> Option<MobKind> {
+        match self.kind {
+            EntityKind::Horse(_) => Some(MobKind::Horse),
+            EntityKind::Monster(ref monster) => Some(monster.kind.mob_kind()),
+            _ => None
+        }
+    }
+
+    /// Bounding box size as `[width, height]` in blocks.
+    pub fn size(&self) -> [f64; 2] {
+        let size = match (self.object_kind(), self.mob_kind()) {
+            (Some(kind), _) => kind.size(),
+            (None, Some(kind)) => kind.size(),
+            (None, None) => [0.25, 0.25]
+        };
+        [size[0] as f64, size[1] as f64]
+    }
+
+    /// Whether players can ride this entity.
+    pub fn rideable(&self) -> bool {
+        match self.kind {
+            EntityKind::Vehicle(_) | EntityKind::Horse(_) => true,
+            _ => false
+        }
+    }
+
+    pub fn metadata(&self) -> Metadata {
+        let mut metadata = Metadata::new();
+        match self.kind {
+            EntityKind::Item(ref stack) => { metadata.set_item(stack.clone()); }
+            EntityKind::Horse(ref horse) => horse.metadata(&mut metadata),
+            EntityKind::ItemFrame(ref frame) => frame.metadata(&mut metadata),
+            EntityKind::ArmorStand(ref stand) => stand.metadata(&mut metadata),
+            EntityKind::Monster(ref monster) => monster.metadata(&mut metadata),
+            EntityKind::PrimedTnt { .. } | EntityKind::Projectile { .. } | EntityKind::Vehicle(_) | EntityKind::ExperienceOrb(_) => {}
+        }
+        metadata
+    }
+
+    /// Height of a passenger's feet above this entity's position.
+    pub fn seat_height(&self) -> f64 {
+        match self.kind {
+            EntityKind::Vehicle(ObjectKind::Boat) => -0.3 - 0.35,
+            _ => self.size()[1] - 0.35
+        }
+    }
+
+    /// Whether this is a projectile which hasn't hit anything yet.
+    pub fn in_flight(&self) -> bool {
+        match self.kind {
+            EntityKind::Projectile { stuck, .. } => !stuck && !self.removed,
+            _ => false
+        }
+    }
+
+    /// Moves the entity one tick forward, falling until it lands on a solid
+    /// block, `block_at` gives the block (`id << 4 | metadata`) at a
+    /// position. Returns whether the entity moved.
+    pub fn tick<F: Fn([i32; 3]) -> u16>(&mut self, block_at: F) -> bool {
+        let floats = match self.kind {
+            EntityKind::Vehicle(ObjectKind::Boat) => true,
+            _ => false
+        };
+        let is_solid = |location: [i32; 3]| {
+            let id = block_at(location) >> 4;
+            block::is_solid(id) || (floats && (id == 8 || id == 9))
+        };
+        self.age += 1;
+        match self.kind {
+            EntityKind::Projectile { stuck: true, .. } => return false,
+            // Frames hang on their block, and some stands float
+            EntityKind::ItemFrame(_) => return false,
+            EntityKind::ArmorStand(ref stand) if stand.no_gravity => return false,
+            EntityKind::Projectile { kind, .. } => return self.fly(kind, is_solid),
+            _ => {}
+        }
+        if self.on_ground && self.velocity == [0.0; 3] {
+            return false;
+        }
+        self.velocity[1] -= 0.04;
+        let mut position = [self.position[0] + self.velocity[0],
+                            self.position[1] + self.velocity[1],
+                            self.position[2] + self.velocity[2]];
+        let below = [position[0].floor() as i32, position[1].floor() as i32, position[2].floor() as i32];
+        self.on_ground = self.velocity[1] <= 0.0 && is_solid(below);
+        if self.on_ground {
+            position[1] = below[1] as f64 + 1.0;
+            self.velocity[1] = 0.0;
+        }
+        self.position = position;
+        for v in self.velocity.iter_mut() {
+            *v *= 0.98;
+        }
+        if self.on_ground {
+            self.velocity[0] *= 0.7;
+            self.velocity[2] *= 0.7;
+            // Stop sliding once too slow to notice
+            if self.velocity[0].abs() < 0.005 && self.velocity[2].abs() < 0.005 {
+                self.velocity = [0.0; 3];
+            }
+        }
+        true
+    }
+
+    /// Projectile movement: no friction with the ground, arrows stick into
+    /// the first block they hit while anything else breaks.
+    fn fly<F: Fn([i32; 3]) -> bool>(&mut self, kind: ObjectKind, is_solid: F) -> bool {
+        let speed = (self.velocity[0].powi(2) + self.velocity[1].powi(2) + self.velocity[2].powi(2)).sqrt();
+        let steps = (speed / 0.1).ceil().max(1.0) as i32;
+        for i in 1..steps + 1 {
+            let t = i as f64 / steps as f64;
+            let point = [self.position[0] + self.velocity[0] * t,
+                         self.position[1] + self.velocity[1] * t,
+                         self.position[2] + self.velocity[2] * t];
+            if is_solid([point[0].floor() as i32, point[1].floor() as i32, point[2].floor() as i32]) {
+                self.position = point;
+                self.velocity = [0.0; 3];
+                match self.kind {
+                    EntityKind::Projectile { kind: ObjectKind::Arrow, ref mut stuck, .. } => *stuck = true,
+                    _ => self.removed = true
+                }
+                return true;
+            }
+        }
+        for n in 0..3 {
+            self.position[n] += self.velocity[n];
+            self.velocity[n] *= 0.99;
+        }
+        self.velocity[1] -= if kind == ObjectKind::Arrow { 0.05 } else { 0.03 };
+        // Point the way it's flying
+        let horizontal = (self.velocity[0].powi(2) + self.velocity[2].powi(2)).sqrt();
+        self.yaw = self.velocity[0].atan2(self.velocity[2]).to_degrees() as f32;
+        self.pitch = self.velocity[1].atan2(horizontal).to_degrees() as f32;
+        true
+    }
+
+    /// Packet moving this entity to its current position.
+    pub fn teleport_packet(&self) -> EntityTeleport {
+        EntityTeleport {
+            entity_id: self.id,
+            position: fixed_point(self.position),
+            yaw: angle(self.yaw),
+            pitch: angle(self.pitch),
+            on_ground: self.on_ground
+        }
+    }
+
+    /// Packets telling a client about this entity.
+    pub fn spawn_packets(&self) -> Vec<Box<PacketWrite>> {
+        if let Some(kind) = self.mob_kind() {
+            return vec![Box::new(SpawnMob {
+                entity_id: self.id,
+                type_: kind,
+                position: fixed_point(self.position),
+                yaw: angle(self.yaw),
+                pitch: angle(self.pitch),
+                head_pitch: angle(self.pitch),
+                velocity: velocity(self.velocity),
+                metadata: self.metadata()
+            })];
+        }
+        if let EntityKind::ExperienceOrb(count) = self.kind {
+            return vec![Box::new(SpawnExperienceOrb { entity_id: self.id, position: fixed_point(self.position), count: count })];
+        }
+        let (kind, data) = match self.kind {
+            EntityKind::Horse(_) | EntityKind::Monster(_) => unreachable!("mobs are spawned with SpawnMob"),
+            EntityKind::ExperienceOrb(_) => unreachable!("experience orbs have their own packet"),
+            EntityKind::Item(_) => (ObjectKind::ItemStack, ObjectData::item_stack(velocity(self.velocity))),
+            EntityKind::PrimedTnt { .. } => (ObjectKind::ActivatedTnt, ObjectData::empty()),
+            EntityKind::Vehicle(ObjectKind::Boat) => (ObjectKind::Boat, ObjectData::empty()),
+            EntityKind::Vehicle(kind) => (kind, ObjectData::minecart(MinecartKind::Empty)),
+            EntityKind::Projectile { kind, shooter, .. } => (kind, ObjectData::projectile(shooter, velocity(self.velocity))),
+            EntityKind::ItemFrame(ref frame) => (ObjectKind::ItemFrame, ObjectData::item_frame(frame.direction())),
+            EntityKind::ArmorStand(_) => (ObjectKind::ArmorStand, ObjectData::empty())
+        };
+        let mut packets: Vec<Box<PacketWrite>> = vec![
+            Box::new(SpawnObject {
+                entity_id: self.id,
+                type_: kind,
+                position: fixed_point(self.position),
+                pitch: angle(self.pitch),
+                yaw: angle(self.yaw),
+                data: data
+            }),
+            Box::new(EntityMetadata { entity_id: self.id, metadata: self.metadata() })
+        ];
+        if data.velocity.is_none() && self.velocity != [0.0; 3] {
+            packets.push(Box::new(EntityVelocity { entity_id: self.id, velocity: velocity(self.velocity) }));
+        }
+        if let EntityKind::ArmorStand(ref stand) = self.kind {
+            for slot in 0..armor_stand::EQUIPMENT_SLOTS {
+                if let Some(stack) = stand.equipment(slot) {
+                    packets.push(Box::new(EntityEquipment { entity_id: self.id, slot: slot as i16, item: Some(stack.clone()) }));
+                }
+            }
+        }
+        packets
+    }
+}
+
+/// Whether the segment from `from` to `to` goes through the box between
+/// `min` and `max`.
+pub fn segment_hits(from: [f64; 3], to: [f64; 3], min: [f64; 3], max: [f64; 3]) -> bool {
+    let (mut enter, mut exit) = (0.0f64, 1.0f64);
+    for n in 0..3 {
+        let delta = to[n] - from[n];
+        if delta.abs() < 1.0e-9 {
+            if from[n] < min[n] || from[n] > max[n] {
+                return false;
+            }
+            continue;
+        }
+        let a = (min[n] - from[n]) / delta;
+        let b = (max[n] - from[n]) / delta;
+        enter = enter.max(a.min(b));
+        exit = exit.min(a.max(b));
+        if enter > exit {
+            return false;
+        }
+    }
+    true
+}
+
+/// Converts a position to the 5 bits fraction fixed point format.
+pub fn fixed_point(position: [f64; 3]) -> [i32; 3] {
+    [(position[0] * 32.0).floor() as i32, (position[1] * 32.0).floor() as i32, (position[2] * 32.0).floor() as i32]
+}
+
+/// Converts an angle in degrees to steps of 1/256 of a full turn.
+pub fn angle(degrees: f32) -> u8 {
+    (degrees * 256.0 / 360.0) as i32 as u8
+}
+
+/// Converts a velocity in blocks per tick to units of 1/8000 block per tick,
+/// clamped like vanilla does to 3.9 blocks per tick.
+pub fn velocity(velocity: [f64; 3]) -> [i16; 3] {
+    let clamp = |v: f64| (v.max(-3.9).min(3.9) * 8000.0) as i16;
+    [clamp(velocity[0]), clamp(velocity[1]), clamp(velocity[2])]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_and_lands() {
+        let mut entity = Entity::new(1, EntityKind::PrimedTnt { fuse: TNT_FUSE }, [0.5, 66.0, 0.5]);
+        let ground = |location: [i32; 3]| if location[1] < 64 { 1 << 4 } else { 0 };
+        let mut ticks = 0;
+        while !entity.on_ground {
+            assert!(entity.tick(&ground));
+            ticks += 1;
+            assert!(ticks < 100);
+        }
+        assert_eq!(entity.position[1], 64.0);
+        assert!(!entity.tick(&ground));
+    }
+
+    #[test]
+    fn arrow_sticks() {
+        let kind = EntityKind::Projectile { kind: ObjectKind::Arrow, shooter: 1, stuck: false, pickup: true };
+        let mut arrow = Entity::new(2, kind, [0.5, 65.0, 0.5]);
+        arrow.velocity = [0.0, -2.0, 0.0];
+        assert!(arrow.tick(|location: [i32; 3]| if location[1] < 64 { 1 << 4 } else { 0 }));
+        assert!(!arrow.in_flight());
+        assert!(!arrow.removed);
+        assert!(arrow.position[1] < 64.0 && arrow.position[1] > 63.8);
+    }
+
+    #[test]
+    fn boat_floats() {
+        let mut boat = Entity::new(3, EntityKind::Vehicle(ObjectKind::Boat), [0.5, 64.5, 0.5]);
+        let water = |location: [i32; 3]| if location[1] < 64 { 9 << 4 } else { 0 };
+        for _ in 0..20 {
+            boat.tick(&water);
+        }
+        assert!(boat.on_ground);
+        assert_eq!(boat.position[1], 64.0);
+    }
+
+    #[test]
+    fn segment() {
+        let (min, max) = ([-0.3, 64.0, -0.3], [0.3, 65.8, 0.3]);
+        assert!(segment_hits([-2.0, 65.0, 0.0], [2.0, 65.0, 0.0], min, max));
+        assert!(!segment_hits([-2.0, 65.0, 0.0], [-1.0, 65.0, 0.0], min, max));
+        assert!(!segment_hits([-2.0, 67.0, 0.0], [2.0, 67.0, 0.0], min, max));
+    }
+}
@@ -0,0 +1,141 @@
+//! Hostile mobs, spawned in the dark.
+
+use types::consts::MobKind;
+use types::{EntityMetadata as Metadata, metadata_flags};
+
+/// Ticks an undead mob burns for once it's set on fire by sunlight.
+pub const BURN_TICKS: i32 = 160;
+
+/// Damage fire does, once a second.
+pub const FIRE_DAMAGE: f32 = 1.0;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MonsterKind {
+    Zombie,
+    Skeleton,
+    Spider
+}
+
+/// Monsters spawning in the dark, equally likely.
+pub const SPAWNING: [MonsterKind; 3] = [MonsterKind::Zombie, MonsterKind::Skeleton, MonsterKind::Spider];
+
+impl MonsterKind {
+    pub fn from_mob_kind(kind: MobKind) -> Option<MonsterKind> {
+        match kind {
+            MobKind::Zombie => Some(MonsterKind::Zombie),
+            MobKind::Skeleton => Some(MonsterKind::Skeleton),
+            MobKind::Spider => Some(MonsterKind::Spider),
+            _ => None
+        }
+    }
+
+    pub fn mob_kind(&self) -> MobKind {
+        match *self {
+            MonsterKind::Zombie => MobKind::Zombie,
+            MonsterKind::Skeleton => MobKind::Skeleton,
+            MonsterKind::Spider => MobKind::Spider
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            MonsterKind::Zombie => "Zombie",
+            MonsterKind::Skeleton => "Skeleton",
+            MonsterKind::Spider => "Spider"
+        }
+    }
+
+    pub fn max_health(&self) -> f32 {
+        match *self {
+            MonsterKind::Zombie | MonsterKind::Skeleton => 20.0,
+            MonsterKind::Spider => 16.0
+        }
+    }
+
+    /// Damage of a hit, on normal difficulty. Skeletons don't shoot yet, so
+    /// they hit instead.
+    pub fn attack_damage(&self) -> f32 {
+        match *self {
+            MonsterKind::Zombie => 3.0,
+            MonsterKind::Skeleton | MonsterKind::Spider => 2.0
+        }
+    }
+
+    /// Whether sunlight sets the monster on fire.
+    pub fn undead(&self) -> bool {
+        *self != MonsterKind::Spider
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Monster {
+    pub kind: MonsterKind,
+    pub health: f32,
+    /// Ticks left burning.
+    pub fire: i32
+}
+
+impl Monster {
+    pub fn new(kind: MonsterKind) -> Monster {
+        Monster { kind: kind, health: kind.max_health(), fire: 0 }
+    }
+
+    pub fn on_fire(&self) -> bool {
+        self.fire > 0
+    }
+
+    pub fn dead(&self) -> bool {
+        self.health <= 0.0
+    }
+
+    /// Burns the monster for a tick. Undead ones catch fire standing in
+    /// `sunlight`, and water puts any out. Returns the damage taken.
+    pub fn tick_fire(&mut self, sunlight: bool, in_water: bool) -> f32 {
+        if in_water {
+            self.fire = 0;
+            return 0.0;
+        }
+        if sunlight && self.kind.undead() && self.fire == 0 {
+            self.fire = BURN_TICKS;
+        }
+        if self.fire == 0 {
+            return 0.0;
+        }
+        self.fire -= 1;
+        if self.fire % 20 != 19 {
+            return 0.0;
+        }
+        self.health = (self.health - FIRE_DAMAGE).max(0.0);
+        FIRE_DAMAGE
+    }
+
+    pub fn metadata(&self, metadata: &mut Metadata) {
+        metadata.set_flags(if self.on_fire() { metadata_flags::ON_FIRE } else { 0 }).set_health(self.health);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burning() {
+        let mut zombie = Monster::new(MonsterKind::Zombie);
+        let mut damage = 0.0;
+        for _ in 0..100 {
+            damage += zombie.tick_fire(true, false);
+        }
+        assert!(zombie.on_fire());
+        assert_eq!(damage, 5.0);
+        assert_eq!(zombie.health, 15.0);
+        // Burning on in the shade, until jumping in water
+        zombie.tick_fire(false, false);
+        assert!(zombie.on_fire());
+        assert_eq!(zombie.tick_fire(false, true), 0.0);
+        assert!(!zombie.on_fire());
+
+        let mut spider = Monster::new(MonsterKind::Spider);
+        assert_eq!(spider.tick_fire(true, false), 0.0);
+        assert!(!spider.on_fire());
+    }
+}
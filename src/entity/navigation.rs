@@ -0,0 +1,229 @@
+//! A* paths over the block grid, for mobs whose way isn't a straight line.
+//!
+//! Paths go from block to block along the four horizontal directions,
+//! stepping up single blocks and dropping down at most `ai::MAX_DROP`. Water
+//! costs extra, lava, fire and cacti are never walked into.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use block;
+use entity::ai::MAX_DROP;
+
+/// Most blocks a single search looks at before giving up.
+pub const MAX_SEARCH: usize = 400;
+
+/// Blocks mobs may look at, all searches together, in a tick.
+pub const TICK_BUDGET: usize = 2000;
+
+/// Cost of walking to the next block, others are relative to it.
+const WALK: u32 = 10;
+const STEP_UP: u32 = 10;
+const DROP: u32 = 5;
+const WATER: u32 = 80;
+
+/// Blocks from where a mob stands to where it's going, start excluded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    pub blocks: Vec<[i32; 3]>,
+    /// Whether the path reaches where the mob was going, rather than just
+    /// getting closest.
+    pub complete: bool
+}
+
+impl Path {
+    /// The block being walked to, `None` once there.
+    pub fn next(&self) -> Option<[i32; 3]> {
+        self.blocks.first().cloned()
+    }
+
+    /// Moves on to the next block.
+    pub fn advance(&mut self) {
+        if !self.blocks.is_empty() {
+            self.blocks.remove(0);
+        }
+    }
+
+    /// Where the path ends.
+    pub fn end(&self) -> Option<[i32; 3]> {
+        self.blocks.last().cloned()
+    }
+}
+
+fn is_water(id: u16) -> bool {
+    id == 8 || id == 9
+}
+
+/// Cost of standing at `feet`, `None` if a mob can't.
+fn standing_cost<F: Fn([i32; 3]) -> u16>(feet: [i32; 3], block_at: &F) -> Option<u32> {
+    let id = |location: [i32; 3]| block_at(location) >> 4;
+    let (below, body, head) = (id([feet[0], feet[1] - 1, feet[2]]), id(feet), id([feet[0], feet[1] + 1, feet[2]]));
+    if block::is_solid(body) || block::is_solid(head) || block::is_harmful(body) || block::is_harmful(head) || block::is_harmful(below) {
+        return None;
+    }
+    if is_water(body) {
+        // Swimming works anywhere, but mobs would rather not
+        return Some(WATER);
+    }
+    if block::is_solid(below) { Some(0) } else { None }
+}
+
+/// Blocks a mob can get to from `from` in one move, with what that costs.
+fn neighbors<F: Fn([i32; 3]) -> u16>(from: [i32; 3], block_at: &F) -> Vec<([i32; 3], u32)> {
+    let mut neighbors = vec![];
+    let headroom = !block::is_solid(block_at([from[0], from[1] + 2, from[2]]) >> 4);
+    for &(dx, dz) in &[(1, 0), (-1, 0), (0, 1), (0, -1)] {
+        let level = [from[0] + dx, from[1], from[2] + dz];
+        if let Some(cost) = standing_cost(level, block_at) {
+            neighbors.push((level, WALK + cost));
+            continue;
+        }
+        if block::is_solid(block_at(level) >> 4) {
+            let up = [level[0], level[1] + 1, level[2]];
+            if headroom {
+                if let Some(cost) = standing_cost(up, block_at) {
+                    neighbors.push((up, WALK + STEP_UP + cost));
+                }
+            }
+            continue;
+        }
+        // Nothing to stand on, look for ground below
+        for depth in 1..MAX_DROP + 1 {
+            let down = [level[0], level[1] - depth, level[2]];
+            if block::is_solid(block_at(down) >> 4) {
+                break;
+            }
+            if let Some(cost) = standing_cost(down, block_at) {
+                neighbors.push((down, WALK + DROP * depth as u32 + cost));
+                break;
+            }
+        }
+    }
+    neighbors
+}
+
+fn estimate(from: [i32; 3], to: [i32; 3]) -> u32 {
+    (((from[0] - to[0]).abs() + (from[1] - to[1]).abs() + (from[2] - to[2]).abs()) as u32) * WALK
+}
+
+#[derive(Eq, PartialEq)]
+struct Open {
+    /// Cost so far plus the estimate of what's left.
+    total: u32,
+    location: [i32; 3]
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Open) -> Ordering {
+        // Cheapest first out of the max-heap
+        other.total.cmp(&self.total).then_with(|| self.location.cmp(&other.location))
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Open) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Finds the cheapest path from block `from` to block `to`, looking at no
+/// more than `limit` blocks. When `to` can't be reached that way, the path
+/// leads to the block found closest to it instead. Returns the path and how
+/// many blocks were looked at, the path being `None` if the mob can't move
+/// at all.
+pub fn find_path<F: Fn([i32; 3]) -> u16>(from: [i32; 3], to: [i32; 3], block_at: &F, limit: usize) -> (Option<Path>, usize) {
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<[i32; 3], [i32; 3]> = HashMap::new();
+    let mut costs: HashMap<[i32; 3], u32> = HashMap::new();
+    open.push(Open { total: estimate(from, to), location: from });
+    costs.insert(from, 0);
+    let mut closest = (estimate(from, to), from);
+    let mut searched = 0;
+    while let Some(Open { location, .. }) = open.pop() {
+        if location == to {
+            closest = (0, to);
+            break;
+        }
+        if searched >= limit {
+            break;
+        }
+        searched += 1;
+        let cost = costs[&location];
+        for (next, step) in neighbors(location, block_at) {
+            let next_cost = cost + step;
+            if costs.get(&next).map_or(false, |&known| known <= next_cost) {
+                continue;
+            }
+            costs.insert(next, next_cost);
+            came_from.insert(next, location);
+            let left = estimate(next, to);
+            if left < closest.0 {
+                closest = (left, next);
+            }
+            open.push(Open { total: next_cost + left, location: next });
+        }
+    }
+    let end = closest.1;
+    if end == from {
+        return (None, searched);
+    }
+    let mut blocks = vec![end];
+    while let Some(&previous) = came_from.get(blocks.last().unwrap()) {
+        if previous == from {
+            break;
+        }
+        blocks.push(previous);
+    }
+    blocks.reverse();
+    (Some(Path { blocks: blocks, complete: end == to }), searched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Floor at y = 63, a wall along x = 3 with a gap at z = 5, water at
+    /// x = 1 z < 0 and lava at x = -1.
+    fn ground(location: [i32; 3]) -> u16 {
+        let [x, y, z] = location;
+        let id = if y < 63 {
+            1
+        } else if y == 63 && x == -1 {
+            11
+        } else if y == 63 {
+            if x == 1 && z < 0 { 9 } else { 1 }
+        } else if x == 3 && z != 5 && y < 67 {
+            1
+        } else {
+            0
+        };
+        id << 4
+    }
+
+    #[test]
+    fn around_the_wall() {
+        let (path, searched) = find_path([0, 64, 0], [6, 64, 0], &ground, MAX_SEARCH);
+        let path = path.unwrap();
+        assert!(path.complete);
+        assert!(searched <= MAX_SEARCH);
+        assert!(path.blocks.contains(&[3, 64, 5]));
+        assert_eq!(path.end(), Some([6, 64, 0]));
+        // Every move is to a neighboring block
+        let mut previous = [0, 64, 0];
+        for &block in &path.blocks {
+            assert_eq!((block[0] - previous[0]).abs() + (block[2] - previous[2]).abs(), 1);
+            previous = block;
+        }
+    }
+
+    #[test]
+    fn out_of_reach() {
+        // Too few blocks to find the gap, the path gets as close as it can
+        let (path, searched) = find_path([0, 64, 0], [6, 64, 0], &ground, 5);
+        assert_eq!(searched, 5);
+        assert!(!path.unwrap().complete);
+        // Nothing but lava west
+        let (path, _) = find_path([0, 64, 0], [-5, 64, 0], &ground, MAX_SEARCH);
+        assert!(path.map_or(true, |path| path.blocks.iter().all(|block| block[0] >= 0)));
+    }
+}
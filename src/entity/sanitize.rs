@@ -0,0 +1,127 @@
+//! Cleaning up entity NBT before it goes out in UpdateEntityNbt.
+//!
+//! Entity NBT may hold things clients put there, such as item names and
+//! book pages, so it's trimmed to what clients need and to sane sizes.
+
+use std::collections::HashMap;
+use std::io;
+
+use types::MAX_NBT_SIZE;
+
+use nbt;
+
+/// Entity tags clients are told about, anything else is dropped.
+pub const ENTITY_TAGS: &'static [&'static str] = &[
+    "AbsorptionAmount", "ActiveEffects", "Age", "Attributes", "CustomName", "CustomNameVisible",
+    "Equipment", "Health", "HealF", "Invulnerable", "NoAI", "Silent"
+];
+
+/// Deepest nesting of lists and compounds kept.
+pub const MAX_DEPTH: usize = 16;
+
+/// Longest string kept, in bytes.
+pub const MAX_STRING_LENGTH: usize = 32767;
+
+/// Most elements kept in a list or array, the rest are dropped.
+pub const MAX_ELEMENTS: usize = 1024;
+
+/// `entries` with unexpected tags removed, and overly nested or long values
+/// removed or cut short.
+pub fn sanitize(entries: HashMap<String, nbt::Value>) -> HashMap<String, nbt::Value> {
+    entries.into_iter().filter(|&(ref name, _)| ENTITY_TAGS.contains(&&name[..])).filter_map(|(name, value)| {
+        sanitize_value(value, 1).map(|value| (name, value))
+    }).collect()
+}
+
+/// A blob holding `entries` sanitized, ready for UpdateEntityNbt. Fails if
+/// it's still too large for a packet.
+pub fn entity_nbt(entries: HashMap<String, nbt::Value>) -> io::Result<nbt::Blob> {
+    let mut blob = nbt::Blob::new("".to_string());
+    for (name, value) in sanitize(entries) {
+        try!(blob.insert(name, value));
+    }
+    if blob.len() > MAX_NBT_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "entity NBT too large"));
+    }
+    Ok(blob)
+}
+
+fn sanitize_value(value: nbt::Value, depth: usize) -> Option<nbt::Value> {
+    match value {
+        nbt::Value::String(string) => {
+            if string.len() > MAX_STRING_LENGTH {
+                return None;
+            }
+            Some(nbt::Value::String(string))
+        }
+        nbt::Value::ByteArray(mut bytes) => {
+            bytes.truncate(MAX_ELEMENTS);
+            Some(nbt::Value::ByteArray(bytes))
+        }
+        nbt::Value::IntArray(mut ints) => {
+            ints.truncate(MAX_ELEMENTS);
+            Some(nbt::Value::IntArray(ints))
+        }
+        nbt::Value::List(values) => {
+            if depth >= MAX_DEPTH {
+                return None;
+            }
+            let values = values.into_iter().take(MAX_ELEMENTS).filter_map(|value| sanitize_value(value, depth + 1));
+            Some(nbt::Value::List(values.collect()))
+        }
+        nbt::Value::Compound(entries) => {
+            if depth >= MAX_DEPTH {
+                return None;
+            }
+            let entries = entries.into_iter().filter_map(|(name, value)| {
+                sanitize_value(value, depth + 1).map(|value| (name, value))
+            });
+            Some(nbt::Value::Compound(entries.collect()))
+        }
+        value => Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use nbt;
+
+    #[test]
+    fn strips_tags() {
+        let mut entries = HashMap::new();
+        entries.insert("CustomName".to_string(), nbt::Value::String("Bob".to_string()));
+        entries.insert("Pos".to_string(), nbt::Value::List(vec![nbt::Value::Double(0.0)]));
+        entries.insert("Silent".to_string(), nbt::Value::String("x".repeat(MAX_STRING_LENGTH + 1)));
+        let sanitized = sanitize(entries);
+        assert_eq!(sanitized.len(), 1);
+        assert_eq!(sanitized.get("CustomName"), Some(&nbt::Value::String("Bob".to_string())));
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let mut value = nbt::Value::Int(0);
+        for _ in 0..MAX_DEPTH + 2 {
+            value = nbt::Value::List(vec![value]);
+        }
+        let mut entries = HashMap::new();
+        entries.insert("Attributes".to_string(), value);
+        entries.insert("Equipment".to_string(), nbt::Value::IntArray(vec![0; MAX_ELEMENTS * 2]));
+        let sanitized = sanitize(entries);
+        let mut depth = 0;
+        let mut value = sanitized.get("Attributes").unwrap();
+        while let nbt::Value::List(ref values) = *value {
+            depth += 1;
+            match values.first() {
+                Some(inner) => value = inner,
+                None => break
+            }
+        }
+        assert_eq!(depth, MAX_DEPTH - 1);
+        assert_eq!(sanitized.get("Equipment").map(|value| value.len()), Some(4 + 4 * MAX_ELEMENTS));
+        assert!(entity_nbt(sanitized).is_ok());
+    }
+}
@@ -0,0 +1,132 @@
+//! Server events, for server logic and embedders to react to.
+
+use std::sync::RwLock;
+
+use packet::AnyPacket;
+use player::Player;
+use types::consts::ResourcePackResult;
+use world::WorldConfig;
+
+/// Something which happened on the server.
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// A client answered a ResourcePackSend.
+    ResourcePackStatus { player: &'a Player, result: ResourcePackResult },
+    /// A world's settings were changed, its border already follows them.
+    WorldConfigChanged { old: &'a WorldConfig, new: &'a WorldConfig },
+    /// A player joined the server, and is in a world.
+    PlayerJoin { player: &'a Player },
+    /// A player left the server.
+    PlayerQuit { player: &'a Player },
+    /// A player didn't move or chat for afk-timeout minutes.
+    AfkEnter { player: &'a Player },
+    /// A player away from keyboard moved or chatted again.
+    AfkLeave { player: &'a Player },
+    /// A player's chat message got through the chat filters, and is about
+    /// to be broadcast.
+    Chat { player: &'a Player, message: &'a str },
+    /// A player sent a packet registered with `World::register_packet`.
+    CustomPacket { player: &'a Player, packet: &'a AnyPacket }
+}
+
+/// What a chat filter wants done with a message.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FilterAction {
+    Allow,
+    /// Nobody sees the message.
+    Cancel,
+    /// Nobody sees the message, and the sender can't chat anymore.
+    Mute
+}
+
+/// Checks chat messages before they're broadcast, e.g. to censor words or
+/// stop spam.
+pub trait ChatFilter {
+    /// Decides about `message` sent by `player`, possibly changing it.
+    fn filter(&self, player: &Player, message: &mut String) -> FilterAction;
+}
+
+/// Lets everything through.
+pub struct PassThrough;
+
+impl ChatFilter for PassThrough {
+    fn filter(&self, _: &Player, _: &mut String) -> FilterAction {
+        FilterAction::Allow
+    }
+}
+
+/// Dispatches events to registered listeners, in registration order.
+pub struct EventBus {
+    listeners: RwLock<Vec<Box<Fn(&Event) + Send + Sync>>>,
+    chat_filters: RwLock<Vec<Box<ChatFilter + Send + Sync>>>
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        EventBus { listeners: RwLock::new(vec![]), chat_filters: RwLock::new(vec![]) }
+    }
+
+    /// Registers `filter` to check chat messages, after those registered
+    /// before it.
+    pub fn add_chat_filter<F>(&self, filter: F) where F: ChatFilter + Send + Sync + 'static {
+        self.chat_filters.write().unwrap().push(Box::new(filter));
+    }
+
+    /// Runs `message` from `player` through the chat filters, stopping at
+    /// the first which doesn't allow it.
+    pub fn filter_chat(&self, player: &Player, message: &mut String) -> FilterAction {
+        for filter in self.chat_filters.read().unwrap().iter() {
+            match filter.filter(player, message) {
+                FilterAction::Allow => {}
+                action => return action
+            }
+        }
+        FilterAction::Allow
+    }
+
+    /// Registers `listener` to be called for every event.
+    pub fn subscribe<F>(&self, listener: F) where F: Fn(&Event) + Send + Sync + 'static {
+        self.listeners.write().unwrap().push(Box::new(listener));
+    }
+
+    pub fn emit(&self, event: &Event) {
+        for listener in self.listeners.read().unwrap().iter() {
+            listener(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use player::Player;
+
+    use uuid::Uuid;
+
+    struct Censor;
+
+    impl ChatFilter for Censor {
+        fn filter(&self, _: &Player, message: &mut String) -> FilterAction {
+            if message.contains("spam") {
+                return FilterAction::Mute;
+            }
+            *message = message.replace("darn", "****");
+            FilterAction::Allow
+        }
+    }
+
+    #[test]
+    fn chat_filters() {
+        let events = EventBus::new();
+        events.add_chat_filter(PassThrough);
+        events.add_chat_filter(Censor);
+        let player = Player::new(Uuid::new_v4(), "Steve".to_string(), Box::new(io::sink()));
+        let mut message = "darn creepers".to_string();
+        assert_eq!(events.filter_chat(&player, &mut message), FilterAction::Allow);
+        assert_eq!(message, "**** creepers");
+        assert_eq!(events.filter_chat(&player, &mut "spam spam".to_string()), FilterAction::Mute);
+    }
+}
@@ -0,0 +1,186 @@
+//! Item properties.
+//!
+//! Items are identified by their numeric id, block ids double as the id of
+//! the corresponding item.
+
+pub const CHEST: u16 = 54;
+pub const BOWL: u16 = 281;
+pub const MUSHROOM_STEW: u16 = 282;
+pub const GOLDEN_APPLE: u16 = 322;
+pub const FLINT_AND_STEEL: u16 = 259;
+pub const BOW: u16 = 261;
+pub const ARROW: u16 = 262;
+pub const COAL: u16 = 263;
+pub const IRON_INGOT: u16 = 265;
+pub const BUCKET: u16 = 325;
+pub const LAVA_BUCKET: u16 = 327;
+pub const MINECART: u16 = 328;
+pub const SADDLE: u16 = 329;
+pub const SNOWBALL: u16 = 332;
+pub const BOAT: u16 = 333;
+pub const MILK_BUCKET: u16 = 335;
+pub const EGG: u16 = 344;
+pub const POTION: u16 = 373;
+pub const GLASS_BOTTLE: u16 = 374;
+pub const SPAWN_EGG: u16 = 383;
+pub const FIRE_CHARGE: u16 = 385;
+pub const ITEM_FRAME: u16 = 389;
+pub const RABBIT_STEW: u16 = 413;
+pub const ARMOR_STAND: u16 = 416;
+pub const IRON_HORSE_ARMOR: u16 = 417;
+pub const GOLDEN_HORSE_ARMOR: u16 = 418;
+pub const DIAMOND_HORSE_ARMOR: u16 = 419;
+
+/// Ticks it takes to eat food or drink.
+pub const USE_TICKS: i64 = 32;
+
+/// Enchantment ids, as found in the `ench` tag of items.
+pub const AQUA_AFFINITY: i16 = 6;
+pub const EFFICIENCY: i16 = 32;
+
+/// Kinds of tools, each digging some blocks faster.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Tool {
+    Pickaxe,
+    Axe,
+    Shovel,
+    Sword,
+    Shears
+}
+
+/// Most items of kind `id` a single slot can hold.
+pub fn max_stack_size(id: u16) -> u8 {
+    match id {
+        _ if max_damage(id).is_some() => 1,
+        // Snowballs, signs, buckets, eggs, ender pearls, armor stands and banners
+        SNOWBALL | 323 | BUCKET | EGG | 368 | ARMOR_STAND | 425 => 16,
+        // Potions, saddles, boats, minecarts, beds, cake, horse armor, music discs...
+        POTION | SADDLE | BOAT | MINECART | 342 | 343 | 407 | 408 | 355 | 354 | 282 | 326 | LAVA_BUCKET | 335 | 387 |
+        IRON_HORSE_ARMOR | GOLDEN_HORSE_ARMOR | DIAMOND_HORSE_ARMOR | 2256 | 2257 | 2258 | 2259 | 2260 | 2261 |
+        2262 | 2263 | 2264 | 2265 | 2266 | 2267 => 1,
+        _ => 64
+    }
+}
+
+/// Number of uses a tool survives, `None` for items which don't wear out.
+pub fn max_damage(id: u16) -> Option<i16> {
+    match id {
+        268 | 269 | 270 | 271 | 290 => Some(59),
+        272 | 273 | 274 | 275 | 291 => Some(131),
+        256 | 257 | 258 | 267 | 292 => Some(250),
+        276 | 277 | 278 | 279 | 293 => Some(1561),
+        283 | 284 | 285 | 286 | 294 => Some(32),
+        FLINT_AND_STEEL | 346 => Some(64),
+        BOW => Some(384),
+        359 => Some(238),
+        398 => Some(25),
+        // Helmets, chestplates, leggings then boots
+        298 => Some(55), 299 => Some(80), 300 => Some(75), 301 => Some(65),
+        302 | 306 => Some(165), 303 | 307 => Some(240), 304 | 308 => Some(225), 305 | 309 => Some(195),
+        310 => Some(363), 311 => Some(528), 312 => Some(495), 313 => Some(429),
+        314 => Some(77), 315 => Some(112), 316 => Some(105), 317 => Some(91),
+        _ => None
+    }
+}
+
+/// Item which repairs item `id` in an anvil.
+pub fn repair_material(id: u16) -> Option<u16> {
+    match id {
+        // Planks
+        268 | 269 | 270 | 271 | 290 => Some(5),
+        // Cobblestone
+        272 | 273 | 274 | 275 | 291 => Some(4),
+        // Leather
+        298 | 299 | 300 | 301 => Some(334),
+        // Iron ingots, chain armor included
+        256 | 257 | 258 | 267 | 292 | 302 | 303 | 304 | 305 | 306 | 307 | 308 | 309 => Some(IRON_INGOT),
+        // Diamonds
+        276 | 277 | 278 | 279 | 293 | 310 | 311 | 312 | 313 => Some(264),
+        // Gold ingots
+        283 | 284 | 285 | 286 | 294 | 314 | 315 | 316 | 317 => Some(266),
+        _ => None
+    }
+}
+
+/// Armor slot item `id` goes in, from 0 for the helmet to 3 for the boots.
+/// Pumpkins and mob heads can be worn too.
+pub fn armor_slot(id: u16) -> Option<usize> {
+    match id {
+        _ if id >= 298 && id <= 317 => Some((id - 298) as usize % 4),
+        86 | 397 => Some(0),
+        _ => None
+    }
+}
+
+/// Kind of tool item `id` is, with its harvest level from 0 for wood and gold
+/// to 3 for diamond, and how much faster it digs the blocks it's made for.
+pub fn tool(id: u16) -> Option<(Tool, u8, f32)> {
+    let kind = match id {
+        256 | 269 | 273 | 277 | 284 => Tool::Shovel,
+        257 | 270 | 274 | 278 | 285 => Tool::Pickaxe,
+        258 | 271 | 275 | 279 | 286 => Tool::Axe,
+        267 | 268 | 272 | 276 | 283 => Tool::Sword,
+        359 => return Some((Tool::Shears, 0, 1.0)),
+        _ => return None
+    };
+    let (level, efficiency) = match id {
+        // Wood, stone, iron, diamond then gold
+        268 | 269 | 270 | 271 => (0, 2.0),
+        272 | 273 | 274 | 275 => (1, 4.0),
+        256 | 257 | 258 | 267 => (2, 6.0),
+        276 | 277 | 278 | 279 => (3, 8.0),
+        _ => (0, 12.0)
+    };
+    Some((kind, level, efficiency))
+}
+
+/// Food points item `id` gives when eaten, and its saturation modifier.
+/// `None` for items which can't be eaten.
+pub fn food(id: u16) -> Option<(i32, f32)> {
+    Some(match id {
+        // Apples, stew, bread and porkchops
+        260 => (4, 0.3),
+        MUSHROOM_STEW => (6, 0.6),
+        297 => (5, 0.6),
+        319 => (3, 0.3),
+        320 => (8, 0.8),
+        GOLDEN_APPLE => (4, 1.2),
+        // Fish, cookies and melon
+        349 => (2, 0.1),
+        350 => (5, 0.6),
+        357 => (2, 0.1),
+        360 => (2, 0.3),
+        // Beef, chicken, rotten flesh and spider eyes
+        363 => (3, 0.3),
+        364 => (8, 0.8),
+        365 => (2, 0.3),
+        366 => (6, 0.6),
+        367 => (4, 0.1),
+        375 => (2, 0.8),
+        // Carrots, potatoes, golden carrots and pumpkin pie
+        391 => (3, 0.6),
+        392 => (1, 0.3),
+        393 => (5, 0.6),
+        394 => (2, 0.3),
+        396 => (6, 1.2),
+        400 => (8, 0.3),
+        // Rabbit and mutton
+        411 => (3, 0.3),
+        412 => (5, 0.6),
+        RABBIT_STEW => (10, 0.6),
+        423 => (2, 0.3),
+        424 => (6, 0.8),
+        _ => return None
+    })
+}
+
+/// What's left in hand after eating or drinking item `id`.
+pub fn leftover(id: u16) -> Option<u16> {
+    match id {
+        MUSHROOM_STEW | RABBIT_STEW => Some(BOWL),
+        MILK_BUCKET => Some(BUCKET),
+        POTION => Some(GLASS_BOTTLE),
+        _ => None
+    }
+}
+
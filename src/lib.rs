@@ -1,3 +1,13 @@
+//! A Minecraft 1.8.9 server, usable on its own or embedded.
+//!
+//! Embedders should start from `prelude`, which gathers the server, worlds,
+//! players, events and packets and is kept stable. The other modules are
+//! public for the server binary and the curious, not promised to stay put.
+//!
+//! Tools which only query servers can leave out the default `server`
+//! feature and enable `slp-client`, which keeps the handshake and status
+//! packets, `proto::slp` and what they're made of.
+
 #![cfg_attr(test, deny(missing_docs, warnings))]
 #![forbid(unused_variables)]
 // #![feature(associated_type_defaults)]
@@ -5,20 +15,42 @@
 
 extern crate byteorder;
 extern crate flate2;
+#[cfg_attr(feature = "server", macro_use)]
+extern crate hematite_server_derive;
 #[macro_use]
 extern crate log;
 pub extern crate nbt;
 extern crate num;
+#[cfg(feature = "server")] extern crate openssl;
 extern crate rand;
 extern crate regex;
 extern crate rustc_serialize;
 extern crate time;
 extern crate uuid;
 
+// Lets code derived with #[derive(Protocol)] name the crate the same way in
+// here as outside it
+extern crate self as hematite_server;
+
+#[cfg(feature = "server")] pub mod block;
+#[cfg(feature = "server")] pub mod block_entity;
 pub mod consts;
+#[cfg(feature = "server")] pub mod crash;
+#[cfg(feature = "server")] pub mod crypto;
+#[cfg(feature = "server")] pub mod damage;
+#[cfg(feature = "server")] pub mod entity;
+#[cfg(feature = "server")] pub mod events;
+pub mod item;
+#[cfg(feature = "server")] pub mod mca;
+#[cfg(feature = "server")] pub mod message;
 pub mod packet;
+#[cfg(feature = "server")] pub mod permissions;
+#[cfg(feature = "server")] pub mod player;
+#[cfg(feature = "server")] pub mod potion;
+#[cfg(feature = "server")] pub mod prelude;
 pub mod proto;
 pub mod types;
 mod util;
-pub mod vanilla;
-pub mod world;
+#[cfg(feature = "server")] pub mod vanilla;
+#[cfg(feature = "server")] pub mod window;
+#[cfg(feature = "server")] pub mod world;
@@ -0,0 +1,570 @@
+//! Region files (.mca), each holding up to 32x32 chunk columns.
+//!
+//! A region file starts with two 4KiB tables of 1024 entries, one per chunk:
+//! where the chunk is (its first 4KiB sector and how many sectors it takes)
+//! and when it was last saved. Chunks are compressed NBT, each preceded by
+//! its length and compression scheme.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+use types::{Chunk, ChunkColumn};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use nbt;
+
+/// Size of the sectors region files are made of.
+pub const SECTOR_BYTES: usize = 4096;
+
+/// Compression schemes of chunks.
+pub const GZIP: u8 = 1;
+pub const ZLIB: u8 = 2;
+
+/// Sectors taken by the location and timestamp tables.
+const HEADER_SECTORS: usize = 2;
+
+/// A chunk as stored in a region file, still compressed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawChunk {
+    pub compression: u8,
+    pub data: Vec<u8>,
+    /// When the chunk was last saved, in seconds since the epoch.
+    pub timestamp: u32
+}
+
+impl RawChunk {
+    /// Decompresses and parses the chunk.
+    pub fn nbt(&self) -> io::Result<nbt::Blob> {
+        let mut src = Cursor::new(&self.data[..]);
+        match self.compression {
+            GZIP => Ok(try!(nbt::Blob::from_gzip(&mut src))),
+            ZLIB => Ok(try!(nbt::Blob::from_zlib(&mut src))),
+            other => Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown chunk compression {}", other)))
+        }
+    }
+
+    /// Compresses chunk column `x`, `z` the way vanilla saves chunks, with
+    /// zlib. `last_update` is the world's age in ticks. Entities and block
+    /// entities aren't saved, the lists are written empty.
+    pub fn from_column(x: i32, z: i32, column: &ChunkColumn, last_update: i64, timestamp: u32) -> io::Result<RawChunk> {
+        let mut level = HashMap::new();
+        level.insert("xPos".to_string(), nbt::Value::Int(x));
+        level.insert("zPos".to_string(), nbt::Value::Int(z));
+        level.insert("LastUpdate".to_string(), nbt::Value::Long(last_update));
+        // Generated whole, there's nothing left for vanilla to populate
+        level.insert("TerrainPopulated".to_string(), nbt::Value::Byte(1));
+        level.insert("HeightMap".to_string(), nbt::Value::IntArray(height_map(column)));
+        level.insert("Entities".to_string(), nbt::Value::List(vec![]));
+        level.insert("TileEntities".to_string(), nbt::Value::List(vec![]));
+        let sections = column.chunks.iter().enumerate().map(|(y, chunk)| write_section(y as i8, chunk)).collect();
+        level.insert("Sections".to_string(), nbt::Value::List(sections));
+        if let Some(ref biomes) = column.biomes {
+            level.insert("Biomes".to_string(), nbt::Value::ByteArray(biomes.iter().map(|&biome| biome as i8).collect()));
+        }
+        let mut blob = nbt::Blob::new("".to_string());
+        try!(blob.insert("Level".to_string(), nbt::Value::Compound(level)));
+        let mut data = vec![];
+        try!(blob.write_zlib(&mut data));
+        Ok(RawChunk { compression: ZLIB, data: data, timestamp: timestamp })
+    }
+
+    /// The chunk's blocks, light and biomes. Sections missing below the
+    /// highest one are filled with air, so they're contiguous.
+    pub fn column(&self) -> io::Result<ChunkColumn> {
+        let mut src: Box<Read> = match self.compression {
+            GZIP => Box::new(try!(GzDecoder::new(Cursor::new(&self.data[..])))),
+            ZLIB => Box::new(ZlibDecoder::new(Cursor::new(&self.data[..]))),
+            other => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown chunk compression {}", other)))
+        };
+        let root = try!(nbt::Value::read_header(&mut src).and_then(|(id, _)| nbt::Value::from_reader(id, &mut src)));
+        let level = match root {
+            nbt::Value::Compound(mut root) => match root.remove("Level") {
+                Some(nbt::Value::Compound(level)) => level,
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "chunk has no Level compound"))
+            },
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "chunk root isn't a compound"))
+        };
+        let mut chunks = vec![];
+        if let Some(&nbt::Value::List(ref sections)) = level.get("Sections") {
+            for section in sections {
+                let section = match *section {
+                    nbt::Value::Compound(ref section) => section,
+                    _ => continue
+                };
+                let y = match section.get("Y") {
+                    Some(&nbt::Value::Byte(y)) if y >= 0 && y < 16 => y as usize,
+                    _ => continue
+                };
+                while chunks.len() <= y {
+                    chunks.push(Chunk::new(0, 0xff));
+                }
+                chunks[y] = try!(read_section(section));
+            }
+        }
+        let biomes = match level.get("Biomes") {
+            Some(&nbt::Value::ByteArray(ref biomes)) if biomes.len() == 256 => {
+                let mut array = [0; 256];
+                for (biome, &value) in array.iter_mut().zip(biomes) {
+                    *biome = value as u8;
+                }
+                Some(array)
+            }
+            _ => None
+        };
+        Ok(ChunkColumn { chunks: chunks, biomes: biomes })
+    }
+}
+
+/// Height of the highest block in each column of `column`, plus one, by
+/// `z * 16 + x`. 0 where there's only air.
+fn height_map(column: &ChunkColumn) -> Vec<i32> {
+    let top = column.chunks.len() * 16;
+    let mut heights = Vec::with_capacity(256);
+    for z in 0..16 {
+        for x in 0..16 {
+            let height = (0..top).rev().find(|&y| column.block(x, y, z) != 0).map_or(0, |y| y + 1);
+            heights.push(height as i32);
+        }
+    }
+    heights
+}
+
+/// A section as saved in `Level.Sections`, `Add` only written when some
+/// block needs it.
+fn write_section(y: i8, chunk: &Chunk) -> nbt::Value {
+    fn bytes(values: &[u8]) -> nbt::Value {
+        nbt::Value::ByteArray(values.iter().map(|&value| value as i8).collect())
+    }
+
+    let mut blocks = Vec::with_capacity(4096);
+    let mut add = vec![0u8; 2048];
+    let mut data = vec![0u8; 2048];
+    for (index, &block) in chunk.blocks.iter().enumerate() {
+        let id = block >> 4;
+        blocks.push(id as u8);
+        add[index >> 1] |= ((id >> 8) as u8 & 0xf) << ((index & 1) * 4);
+        data[index >> 1] |= (block as u8 & 0xf) << ((index & 1) * 4);
+    }
+    let mut section = HashMap::new();
+    section.insert("Y".to_string(), nbt::Value::Byte(y));
+    section.insert("Blocks".to_string(), bytes(&blocks));
+    if add.iter().any(|&byte| byte != 0) {
+        section.insert("Add".to_string(), bytes(&add));
+    }
+    section.insert("Data".to_string(), bytes(&data));
+    section.insert("BlockLight".to_string(), bytes(&chunk.block_light));
+    if let Some(ref sky_light) = chunk.sky_light {
+        section.insert("SkyLight".to_string(), bytes(sky_light));
+    }
+    nbt::Value::Compound(section)
+}
+
+/// Reads a section's blocks and light, as saved in `Level.Sections`.
+fn read_section(section: &HashMap<String, nbt::Value>) -> io::Result<Chunk> {
+    fn bytes<'a>(section: &'a HashMap<String, nbt::Value>, name: &str, len: usize) -> io::Result<Option<&'a [i8]>> {
+        match section.get(name) {
+            Some(&nbt::Value::ByteArray(ref bytes)) if bytes.len() == len => Ok(Some(bytes)),
+            Some(_) => Err(io::Error::new(io::ErrorKind::InvalidData, format!("bad {} in chunk section", name))),
+            None => Ok(None)
+        }
+    }
+    // Even indices are in the low half of a byte
+    fn nibble(array: &[i8], index: usize) -> u8 {
+        (array[index >> 1] as u8 >> ((index & 1) * 4)) & 0xf
+    }
+
+    let mut chunk = Chunk::new(0, 0xff);
+    let blocks = match try!(bytes(section, "Blocks", 4096)) {
+        Some(blocks) => blocks,
+        None => return Err(io::Error::new(io::ErrorKind::InvalidData, "chunk section has no Blocks"))
+    };
+    let add = try!(bytes(section, "Add", 2048));
+    let data = try!(bytes(section, "Data", 2048));
+    for (index, block) in chunk.blocks.iter_mut().enumerate() {
+        let high = add.map_or(0, |add| nibble(add, index) as u16);
+        let meta = data.map_or(0, |data| nibble(data, index) as u16);
+        *block = (high << 8 | blocks[index] as u8 as u16) << 4 | meta;
+    }
+    if let Some(light) = try!(bytes(section, "BlockLight", 2048)) {
+        for (byte, &value) in chunk.block_light.iter_mut().zip(light) {
+            *byte = value as u8;
+        }
+    }
+    if let (Some(sky), Some(light)) = (chunk.sky_light.as_mut(), try!(bytes(section, "SkyLight", 2048))) {
+        for (byte, &value) in sky.iter_mut().zip(light) {
+            *byte = value as u8;
+        }
+    }
+    Ok(chunk)
+}
+
+/// What's wrong with a chunk of a region file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    /// Its sectors start inside the header.
+    InHeader,
+    /// Its sectors run past the end of the file.
+    PastEnd,
+    /// It shares sectors with the chunk at `other`.
+    Overlaps { other: (i32, i32) },
+    /// Its length doesn't fit the sectors it was given.
+    BadLength(u32),
+    UnknownCompression(u8),
+    /// It doesn't decompress to NBT.
+    Corrupt(String)
+}
+
+/// Result of checking a region file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    /// Chunks which could be read.
+    pub chunks: usize,
+    /// Sectors past the header not holding any of those chunks.
+    pub wasted_sectors: usize,
+    /// Problems found, by chunk position within the region.
+    pub problems: Vec<((i32, i32), Problem)>,
+    /// Chunks which couldn't be read, and are dropped when rewriting.
+    pub lost: Vec<(i32, i32)>
+}
+
+impl Report {
+    /// Whether the file is fine as it is.
+    pub fn is_clean(&self) -> bool {
+        self.problems.is_empty() && self.wasted_sectors == 0
+    }
+}
+
+/// A region file held in memory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct McaFile {
+    /// By `x + z * 32`.
+    chunks: Vec<Option<RawChunk>>
+}
+
+impl McaFile {
+    pub fn new() -> McaFile {
+        McaFile { chunks: vec![None; 1024] }
+    }
+
+    /// Reads a region file, failing on the first unreadable chunk. `check`
+    /// salvages what it can instead.
+    pub fn read(src: &mut Read) -> io::Result<McaFile> {
+        let mut data = vec![];
+        try!(src.read_to_end(&mut data));
+        let (file, report) = try!(check(&data));
+        let lost = report.lost;
+        match report.problems.into_iter().find(|&(chunk, _)| lost.contains(&chunk)) {
+            Some(((x, z), problem)) => Err(io::Error::new(io::ErrorKind::InvalidData, format!("chunk {}, {}: {:?}", x, z, problem))),
+            None => Ok(file)
+        }
+    }
+
+    /// Chunk `x`, `z` of the region, coordinates taken modulo 32.
+    pub fn chunk(&self, x: i32, z: i32) -> Option<&RawChunk> {
+        self.chunks[index(x, z)].as_ref()
+    }
+
+    pub fn set_chunk(&mut self, x: i32, z: i32, chunk: Option<RawChunk>) {
+        self.chunks[index(x, z)] = chunk;
+    }
+
+    /// Number of chunks in the region.
+    pub fn len(&self) -> usize {
+        self.chunks.iter().filter(|chunk| chunk.is_some()).count()
+    }
+
+    /// Writes the region with its chunks packed one after another, wasting
+    /// no sectors.
+    pub fn write(&self, dst: &mut Write) -> io::Result<()> {
+        let mut locations = Vec::with_capacity(SECTOR_BYTES);
+        let mut timestamps = Vec::with_capacity(SECTOR_BYTES);
+        let mut sectors = vec![];
+        let mut next = HEADER_SECTORS;
+        for chunk in &self.chunks {
+            let chunk = match *chunk {
+                Some(ref chunk) => chunk,
+                None => {
+                    try!(locations.write_u32::<BigEndian>(0));
+                    try!(timestamps.write_u32::<BigEndian>(0));
+                    continue;
+                }
+            };
+            let start = sectors.len();
+            try!(sectors.write_u32::<BigEndian>(chunk.data.len() as u32 + 1));
+            sectors.push(chunk.compression);
+            sectors.extend_from_slice(&chunk.data);
+            let count = (sectors.len() - start + SECTOR_BYTES - 1) / SECTOR_BYTES;
+            if count > 255 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "chunk too big for a region file"));
+            }
+            sectors.resize(start + count * SECTOR_BYTES, 0);
+            try!(locations.write_u32::<BigEndian>((next as u32) << 8 | count as u32));
+            try!(timestamps.write_u32::<BigEndian>(chunk.timestamp));
+            next += count;
+        }
+        try!(dst.write_all(&locations));
+        try!(dst.write_all(&timestamps));
+        dst.write_all(&sectors)
+    }
+}
+
+fn index(x: i32, z: i32) -> usize {
+    ((x & 31) + (z & 31) * 32) as usize
+}
+
+fn position(index: usize) -> (i32, i32) {
+    ((index % 32) as i32, (index / 32) as i32)
+}
+
+/// Reads the chunks of region file contents `data` which can be read, and
+/// reports what's wrong with the others. Fails only when the header itself
+/// is missing.
+pub fn check(data: &[u8]) -> io::Result<(McaFile, Report)> {
+    let mut file = McaFile::new();
+    let mut report = Report::default();
+    if data.is_empty() {
+        return Ok((file, report));
+    }
+    if data.len() < HEADER_SECTORS * SECTOR_BYTES {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "region file shorter than its header"));
+    }
+    let file_sectors = (data.len() + SECTOR_BYTES - 1) / SECTOR_BYTES;
+    let mut header = Cursor::new(data);
+    let mut entries = Vec::with_capacity(1024);
+    for _ in 0..1024 {
+        let location = try!(header.read_u32::<BigEndian>());
+        entries.push(((location >> 8) as usize, (location & 0xff) as usize));
+    }
+    let mut timestamps = Vec::with_capacity(1024);
+    for _ in 0..1024 {
+        timestamps.push(try!(header.read_u32::<BigEndian>()));
+    }
+
+    // Which chunk claimed each sector first, to tell overlaps
+    let mut owners: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut used = vec![false; file_sectors];
+    for (index, &(offset, count)) in entries.iter().enumerate() {
+        if offset == 0 && count == 0 {
+            continue;
+        }
+        let problem = if offset < HEADER_SECTORS {
+            Some(Problem::InHeader)
+        } else if count == 0 || offset + count > file_sectors {
+            Some(Problem::PastEnd)
+        } else {
+            for sector in offset..offset + count {
+                if let Some(&other) = owners.get(&sector) {
+                    report.problems.push((position(index), Problem::Overlaps { other: position(other) }));
+                    break;
+                }
+            }
+            for sector in offset..offset + count {
+                owners.entry(sector).or_insert(index);
+            }
+            match read_chunk(data, offset, count, timestamps[index]) {
+                Ok(chunk) => {
+                    for sector in offset..offset + count {
+                        used[sector] = true;
+                    }
+                    file.chunks[index] = Some(chunk);
+                    report.chunks += 1;
+                    None
+                }
+                Err(problem) => Some(problem)
+            }
+        };
+        if let Some(problem) = problem {
+            report.problems.push((position(index), problem));
+            report.lost.push(position(index));
+        }
+    }
+    report.wasted_sectors = used[HEADER_SECTORS..].iter().filter(|&&used| !used).count();
+    Ok((file, report))
+}
+
+fn read_chunk(data: &[u8], offset: usize, count: usize, timestamp: u32) -> Result<RawChunk, Problem> {
+    let sectors = &data[offset * SECTOR_BYTES..data.len().min((offset + count) * SECTOR_BYTES)];
+    let length = Cursor::new(sectors).read_u32::<BigEndian>().unwrap_or(0);
+    if length == 0 || length as usize + 4 > sectors.len() {
+        return Err(Problem::BadLength(length));
+    }
+    let compression = sectors[4];
+    if compression != GZIP && compression != ZLIB {
+        return Err(Problem::UnknownCompression(compression));
+    }
+    let chunk = RawChunk { compression: compression, data: sectors[5..4 + length as usize].to_vec(), timestamp: timestamp };
+    match chunk.nbt() {
+        Ok(_) => Ok(chunk),
+        Err(err) => Err(Problem::Corrupt(err.to_string()))
+    }
+}
+
+/// Checks every region file of the world in directory `world`, rewriting
+/// them compactly if `fix` is set. Chunks which can't be read are dropped
+/// on rewrite. Meant for worlds no server is running.
+pub fn check_world(world: &Path, fix: bool) -> io::Result<Vec<(PathBuf, Report)>> {
+    let dir = world.join("region");
+    let mut paths = vec![];
+    for entry in try!(fs::read_dir(&dir)) {
+        let path = try!(entry).path();
+        if path.extension().map_or(false, |ext| ext == "mca") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    let mut reports = vec![];
+    for path in paths {
+        let mut data = vec![];
+        try!(try!(File::open(&path)).read_to_end(&mut data));
+        let (file, report) = try!(check(&data).map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err))));
+        if fix && !report.is_clean() {
+            // Written aside first so a failure leaves the original alone
+            let tmp = path.with_extension("mca.tmp");
+            try!(file.write(&mut try!(File::create(&tmp))));
+            try!(fs::rename(&tmp, &path));
+        }
+        reports.push((path, report));
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use byteorder::{BigEndian, WriteBytesExt};
+    use nbt;
+
+    fn chunk(x: i32) -> RawChunk {
+        let mut blob = nbt::Blob::new("".to_string());
+        blob.insert("xPos".to_string(), nbt::Value::Int(x)).unwrap();
+        let mut data = vec![];
+        blob.write_zlib(&mut data).unwrap();
+        RawChunk { compression: ZLIB, data: data, timestamp: 1445000000 }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut file = McaFile::new();
+        file.set_chunk(0, 0, Some(chunk(0)));
+        file.set_chunk(-1, 3, Some(chunk(31)));
+        let mut data = vec![];
+        file.write(&mut data).unwrap();
+        assert_eq!(data.len(), 4 * SECTOR_BYTES);
+        let (read, report) = check(&data).unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.chunks, 2);
+        assert_eq!(read, file);
+        assert_eq!(read.chunk(31, 3).unwrap().nbt().unwrap(), chunk(31).nbt().unwrap());
+        assert_eq!(McaFile::read(&mut &data[..]).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn column() {
+        use std::collections::HashMap;
+
+        let mut blocks = vec![0i8; 4096];
+        let mut data = vec![0i8; 2048];
+        let mut add = vec![0i8; 2048];
+        // Stone at 0, 0, 0, wool with data 14 at 1, 2, 3 and block 257 at
+        // 0, 0, 1
+        blocks[0] = 1;
+        blocks[2 << 8 | 3 << 4 | 1] = 35;
+        data[(2 << 8 | 3 << 4 | 1) >> 1] = 14 << 4;
+        blocks[1 << 4] = 1;
+        add[(1 << 4) >> 1] = 1;
+        let mut section = HashMap::new();
+        section.insert("Y".to_string(), nbt::Value::Byte(1));
+        section.insert("Blocks".to_string(), nbt::Value::ByteArray(blocks));
+        section.insert("Data".to_string(), nbt::Value::ByteArray(data));
+        section.insert("Add".to_string(), nbt::Value::ByteArray(add));
+        section.insert("SkyLight".to_string(), nbt::Value::ByteArray(vec![0x77; 2048]));
+        let mut level = HashMap::new();
+        level.insert("Sections".to_string(), nbt::Value::List(vec![nbt::Value::Compound(section)]));
+        level.insert("Biomes".to_string(), nbt::Value::ByteArray(vec![4; 256]));
+        let mut blob = nbt::Blob::new("".to_string());
+        blob.insert("Level".to_string(), nbt::Value::Compound(level)).unwrap();
+        let mut data = vec![];
+        blob.write_zlib(&mut data).unwrap();
+
+        let column = RawChunk { compression: ZLIB, data: data, timestamp: 0 }.column().unwrap();
+        assert_eq!(column.chunks.len(), 2);
+        assert_eq!(column.chunks[0].blocks[0], 0);
+        let section = &column.chunks[1];
+        assert_eq!(section.blocks[0], 1 << 4);
+        assert_eq!(section.blocks[2 << 8 | 3 << 4 | 1], 35 << 4 | 14);
+        assert_eq!(section.blocks[1 << 4], 257 << 4);
+        assert_eq!(section.sky_light.unwrap()[0], 0x77);
+        assert_eq!(column.biomes.unwrap()[255], 4);
+    }
+
+    #[test]
+    fn saved_columns() {
+        use types::{Chunk, ChunkColumn};
+
+        let mut chunk = Chunk::new(1 << 4, 0xf);
+        chunk.blocks[5] = 35 << 4 | 14;
+        chunk.blocks[4095] = 257 << 4 | 3;
+        chunk.block_light[7] = 0x5a;
+        let column = ChunkColumn { chunks: vec![Chunk::new(0, 0xff), chunk], biomes: Some([7; 256]) };
+        let mut file = McaFile::new();
+        file.set_chunk(-3, 40, Some(RawChunk::from_column(-3, 40, &column, 24000, 1445000000).unwrap()));
+        let mut data = vec![];
+        file.write(&mut data).unwrap();
+        assert_eq!(data.len() % SECTOR_BYTES, 0);
+
+        let read = McaFile::read(&mut &data[..]).unwrap();
+        let raw = read.chunk(-3, 40).unwrap();
+        assert_eq!(raw.compression, ZLIB);
+        assert_eq!(raw.timestamp, 1445000000);
+        let loaded = raw.column().unwrap();
+        assert_eq!(loaded.chunks.len(), 2);
+        for (loaded, saved) in loaded.chunks.iter().zip(&column.chunks) {
+            assert_eq!(&loaded.blocks[..], &saved.blocks[..]);
+            assert_eq!(&loaded.block_light[..], &saved.block_light[..]);
+            assert_eq!(&loaded.sky_light.unwrap()[..], &saved.sky_light.unwrap()[..]);
+        }
+        assert_eq!(&loaded.biomes.unwrap()[..], &column.biomes.unwrap()[..]);
+
+        // What vanilla expects next to the blocks
+        let level = match raw.nbt().unwrap()["Level"] {
+            nbt::Value::Compound(ref level) => level.clone(),
+            ref other => panic!("Level is {:?}", other)
+        };
+        assert_eq!(level["LastUpdate"], nbt::Value::Long(24000));
+        assert_eq!(level["TerrainPopulated"], nbt::Value::Byte(1));
+        assert_eq!(level["HeightMap"], nbt::Value::IntArray(vec![32; 256]));
+        assert_eq!(level["Entities"], nbt::Value::List(vec![]));
+        assert_eq!(level["TileEntities"], nbt::Value::List(vec![]));
+    }
+
+    #[test]
+    fn damaged() {
+        let mut file = McaFile::new();
+        file.set_chunk(0, 0, Some(chunk(0)));
+        file.set_chunk(1, 0, Some(chunk(1)));
+        file.set_chunk(2, 0, Some(chunk(2)));
+        let mut data = vec![];
+        file.write(&mut data).unwrap();
+        // Chunk 1 points at chunk 0's sector, chunk 2 is garbage, and a
+        // sector nobody uses is left at the end
+        (&mut data[4..8]).write_u32::<BigEndian>(2 << 8 | 1).unwrap();
+        data[4 * SECTOR_BYTES + 4] = 9;
+        data.extend(vec![0; SECTOR_BYTES]);
+        let (salvaged, report) = check(&data).unwrap();
+        assert_eq!(report.chunks, 2);
+        assert_eq!(report.lost, vec![(2, 0)]);
+        assert_eq!(report.wasted_sectors, 3);
+        assert_eq!(report.problems[0], ((1, 0), Problem::Overlaps { other: (0, 0) }));
+        assert!(McaFile::read(&mut &data[..]).is_err());
+
+        let mut rewritten = vec![];
+        salvaged.write(&mut rewritten).unwrap();
+        let (_, report) = check(&rewritten).unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.chunks, 2);
+    }
+}
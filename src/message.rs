@@ -0,0 +1,184 @@
+//! Messages the server sends players, as vanilla translation keys so clients
+//! show them in their own language. Messages vanilla has no key for are sent
+//! as plain text instead, in English.
+
+use types::ChatJson;
+use types::consts::Color;
+
+/// A message vanilla clients know, with its English text for the log and
+/// wherever a translation can't be used.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Translation {
+    pub key: &'static str,
+    /// English text, `%s` or `%1$s` where the arguments go.
+    pub text: &'static str
+}
+
+macro_rules! translations {
+    ($($(#[$attr:meta])* $name:ident = $key:expr, $text:expr;)*) => {
+        $($(#[$attr])* pub const $name: Translation = Translation { key: $key, text: $text };)*
+    }
+}
+
+translations! {
+    NO_PERMISSION = "commands.generic.permission", "You do not have permission to use this command.";
+    UNKNOWN_COMMAND = "commands.generic.notFound", "Unknown command. Try /help for a list of commands";
+    PLAYER_NOT_FOUND = "commands.generic.player.notFound", "That player cannot be found";
+    /// Takes the usage of the command.
+    USAGE = "commands.generic.usage", "Usage: %s";
+    MESSAGE_SELF = "commands.message.sameTarget", "You can't send a private message to yourself!";
+    /// Takes the recipient and the message.
+    MESSAGE_OUTGOING = "commands.message.display.outgoing", "You whisper to %s: %s";
+    /// Takes the sender and the message.
+    MESSAGE_INCOMING = "commands.message.display.incoming", "%s whispers to you: %s";
+    DEBUG_START = "commands.debug.start", "Started debug profiling";
+    DEBUG_ALREADY_STARTED = "commands.debug.alreadyStarted", "Can't start profiling, it's already started!";
+    DEBUG_NOT_STARTED = "commands.debug.notStarted", "Can't stop profiling when we haven't started yet!";
+    /// Takes the seconds and ticks profiled.
+    DEBUG_STOP = "commands.debug.stop", "Stopped debug profiling after %s seconds (%s ticks)";
+    NO_SLEEP = "tile.bed.noSleep", "You can only sleep at night";
+    NOT_SAFE = "tile.bed.notSafe", "You may not rest now, there are monsters nearby";
+    /// Takes the player's name and the coordinates.
+    SPAWN_POINT_SET = "commands.spawnpoint.success", "Set %s's spawn point to (%s, %s, %s)";
+    /// Takes what was given instead of a number.
+    INVALID_NUMBER = "commands.generic.num.invalid", "'%s' is not a valid number";
+    /// Takes the number and the smallest one allowed.
+    NUMBER_TOO_SMALL = "commands.generic.num.tooSmall", "The number you have entered (%s) is too small, it must be at least %s";
+    /// Takes the number and the biggest one allowed.
+    NUMBER_TOO_BIG = "commands.generic.num.tooBig", "The number you have entered (%s) is too big, it must be at most %s";
+    /// Takes the page and the number of pages.
+    HELP_HEADER = "commands.help.header", "--- Showing help page %s of %s (/help <page>) ---";
+    /// Takes the new time.
+    TIME_SET = "commands.time.set", "Set the time to %s";
+    /// Takes the ticks added.
+    TIME_ADDED = "commands.time.added", "Added %s to the time";
+    WEATHER_CLEAR = "commands.weather.clear", "Changing to clear weather";
+    WEATHER_RAIN = "commands.weather.rain", "Changing to rainy weather";
+    WEATHER_THUNDER = "commands.weather.thunder", "Changing to rain and thunder";
+    BED_MISSING = "tile.bed.notValid", "Your home bed was missing or obstructed";
+    SPAM = "disconnect.spam", "Kicked for spamming";
+    TIMEOUT = "disconnect.timeout", "Timed out";
+    /// Takes the player's name.
+    JOINED = "multiplayer.player.joined", "%s joined the game";
+    /// Takes the player's name.
+    LEFT = "multiplayer.player.left", "%s left the game";
+}
+
+impl Translation {
+    /// The message for chat, `with` filled in by clients.
+    pub fn to_chat<S: AsRef<str>>(&self, with: &[S]) -> ChatJson {
+        ChatJson::translate(self.key, with.iter().map(|arg| ChatJson::from(arg.as_ref())).collect())
+    }
+
+    /// The message in English, `with` filled in.
+    pub fn to_text<S: AsRef<str>>(&self, with: &[S]) -> String {
+        fill(self.text, with)
+    }
+}
+
+impl From<Translation> for ChatJson {
+    /// The message for chat, for those without arguments.
+    fn from(translation: Translation) -> ChatJson {
+        ChatJson::translate(translation.key, vec![])
+    }
+}
+
+/// Why idle players get kicked, vanilla has no key for it.
+pub const IDLE: &'static str = "You have been idle for too long!";
+
+/// Why players the session server doesn't vouch for are refused in online
+/// mode, untranslated like vanilla.
+pub const UNVERIFIED: &'static str = "Failed to verify username!";
+
+/// How players joining or leaving are announced, as set by the join-message
+/// and quit-message properties.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Announcement {
+    Hidden,
+    /// Vanilla's message, which clients translate.
+    Vanilla(Translation),
+    /// Plain text, `%s` standing for the player's name.
+    Custom(String)
+}
+
+impl Announcement {
+    /// Announcement for property value `format`: `vanilla`'s message for
+    /// "vanilla", none for an empty value, else a custom format.
+    pub fn parse(format: &str, vanilla: Translation) -> Announcement {
+        match format.trim() {
+            "" => Announcement::Hidden,
+            "vanilla" => Announcement::Vanilla(vanilla),
+            format => Announcement::Custom(format.to_string())
+        }
+    }
+
+    /// The announcement about player `name`, yellow like vanilla's.
+    pub fn to_chat(&self, name: &str) -> Option<ChatJson> {
+        let mut message = match *self {
+            Announcement::Hidden => return None,
+            Announcement::Vanilla(translation) => translation.to_chat(&[name]),
+            Announcement::Custom(ref format) => ChatJson::from(fill(format, &[name]))
+        };
+        message.color = Some(Color::Yellow);
+        Some(message)
+    }
+}
+
+/// Fills in translation `template` with `with`, taken in order for `%s`
+/// and by position for `%1$s`, as clients do.
+pub fn fill<S: AsRef<str>>(template: &str, with: &[S]) -> String {
+    let mut text = String::new();
+    let mut next = 0;
+    let mut rest = template;
+    while let Some(start) = rest.find('%') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        if rest.starts_with('%') {
+            text.push('%');
+            rest = &rest[1..];
+            continue;
+        }
+        let digits = rest.find(|c: char| !c.is_digit(10)).unwrap_or(rest.len());
+        let index = if rest[digits..].starts_with("$s") && digits > 0 {
+            let index = rest[..digits].parse::<usize>().ok().map(|n| n - 1);
+            rest = &rest[digits + 2..];
+            index
+        } else if rest.starts_with('s') {
+            rest = &rest[1..];
+            next += 1;
+            Some(next - 1)
+        } else {
+            text.push('%');
+            continue;
+        };
+        text.push_str(index.and_then(|index| with.get(index)).map_or("", |arg| arg.as_ref()));
+    }
+    text.push_str(rest);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translations() {
+        assert_eq!(USAGE.to_text(&["/msg <player> <message>"]), "Usage: /msg <player> <message>");
+        assert_eq!(MESSAGE_INCOMING.to_text(&["Steve", "hi"]), "Steve whispers to you: hi");
+        assert_eq!(fill("%2$s, then %1$s: 100%%", &["a", "b"]), "b, then a: 100%");
+        assert_eq!(fill("%s and %s", &["a"]), "a and ");
+        let with = vec![ChatJson::from("10"), ChatJson::from("200")];
+        assert_eq!(DEBUG_STOP.to_chat(&["10", "200"]), ChatJson::translate("commands.debug.stop", with));
+        assert_eq!(ChatJson::from(SPAM), ChatJson::translate("disconnect.spam", vec![]));
+    }
+
+    #[test]
+    fn announcements() {
+        assert_eq!(Announcement::parse("", JOINED).to_chat("Steve"), None);
+        let vanilla = Announcement::parse("vanilla", JOINED).to_chat("Steve").unwrap();
+        assert_eq!(vanilla.color, Some(Color::Yellow));
+        assert_eq!(vanilla.msg, JOINED.to_chat(&["Steve"]).msg);
+        let custom = Announcement::parse("Welcome back, %s!", JOINED).to_chat("Steve").unwrap();
+        assert_eq!(custom.msg, ChatJson::from("Welcome back, Steve!").msg);
+    }
+}
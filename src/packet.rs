@@ -2,8 +2,13 @@
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
+use std::any::Any;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 use std::io;
 use std::io::prelude::*;
+use std::sync::Arc;
 
 use types::Var;
 
@@ -26,8 +31,35 @@ pub trait PacketWrite {
     /// **TODO:** add support for compression.
     fn write(&self, dst: &mut Write) -> io::Result<()> {
         let len = self.inner_len();
-        try!(<Var<i32> as Protocol>::proto_encode(&(len as i32), dst));
-        self.inner_encode(dst)
+        // Framed in memory, so the packet goes out in one write. The
+        // length is right-aligned in the room left for it.
+        let start = Var::<i32>::MAX_LEN - <Var<i32> as Protocol>::proto_len(&(len as i32));
+        let mut frame = Vec::with_capacity(Var::<i32>::MAX_LEN + len);
+        frame.resize(Var::<i32>::MAX_LEN, 0);
+        Var::<i32>::encode_into(len as i32, &mut frame[start..]);
+        try!(self.inner_encode(&mut frame));
+        dst.write_all(&frame[start..])
+    }
+}
+
+/// A packet encoded ahead of time, id included, to write the same bytes to
+/// many players without encoding it again for each.
+#[derive(Clone, Debug)]
+pub struct Encoded(pub Arc<Vec<u8>>);
+
+impl Encoded {
+    pub fn new<P: PacketWrite + ?Sized>(packet: &P) -> io::Result<Encoded> {
+        let mut body = Vec::with_capacity(packet.inner_len());
+        try!(packet.inner_encode(&mut body));
+        Ok(Encoded(Arc::new(body)))
+    }
+}
+
+impl PacketWrite for Encoded {
+    fn inner_len(&self) -> usize { self.0.len() }
+
+    fn inner_encode(&self, dst: &mut Write) -> io::Result<()> {
+        dst.write_all(&self.0)
     }
 }
 
@@ -39,8 +71,134 @@ pub trait PacketRead: Sized {
     ///
     /// **TODO:** add support for compression.
     fn read<R: Read>(src: &mut R) -> io::Result<Self> {
-        let proto_len = try!(<Var<i32> as Protocol>::proto_decode(src));
-        Self::inner_decode(&mut src.take(proto_len as u64))
+        let frame = try!(read_frame(src));
+        Self::inner_decode(&mut &frame[..])
+    }
+
+    /// Reads a new packet like `read`, but skips over packets whose id is
+    /// unknown instead of failing on them.
+    fn read_skipping_unknown<R: Read>(src: &mut R) -> io::Result<Self> {
+        loop {
+            let frame = try!(read_frame(src));
+            match Self::inner_decode(&mut &frame[..]) {
+                Err(ref err) if unknown_id(err).is_some() => {
+                    debug!("skipped packet {:#04x}, {} bytes long", unknown_id(err).unwrap(), frame.len());
+                }
+                result => return result
+            }
+        }
+    }
+}
+
+/// Longest frame `read_frame` accepts, the most a 3 byte VarInt holds like
+/// vanilla's limit.
+pub const MAX_FRAME_LEN: i32 = (1 << 21) - 1;
+
+/// Reads the length prefixed frame of a packet, the packet id and its
+/// fields, whole so that decoding them reads from memory.
+pub fn read_frame<R: Read>(src: &mut R) -> io::Result<Vec<u8>> {
+    let proto_len = try!(<Var<i32> as Protocol>::proto_decode(src));
+    if proto_len < 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "negative packet length"));
+    }
+    if proto_len > MAX_FRAME_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("packet is {} bytes long, over the {} limit", proto_len, MAX_FRAME_LEN)));
+    }
+    // Grown as data comes rather than trusting the length up front
+    let mut frame = vec![];
+    try!(Read::take(src, proto_len as u64).read_to_end(&mut frame));
+    if frame.len() < proto_len as usize {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "packet cut short"));
+    }
+    Ok(frame)
+}
+
+/// Error decoding a packet whose id isn't one of its namespace, wrapped in
+/// an `io::Error`.
+#[derive(Debug)]
+pub struct UnknownId(pub i32);
+
+impl fmt::Display for UnknownId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown packet id {:#04x}", self.0)
+    }
+}
+
+impl Error for UnknownId {
+    fn description(&self) -> &str { "unknown packet id" }
+}
+
+/// The id of the packet `err` failed on, when it failed for not knowing it.
+pub fn unknown_id(err: &io::Error) -> Option<i32> {
+    err.get_ref().and_then(|inner| inner.downcast_ref::<UnknownId>()).map(|&UnknownId(id)| id)
+}
+
+/// A packet of any type, for packets only known at runtime, e.g. those of
+/// plugin channels.
+pub trait AnyPacket: PacketWrite + fmt::Debug + Send {
+    fn as_any(&self) -> &Any;
+}
+
+impl<P: PacketWrite + fmt::Debug + Send + Any> AnyPacket for P {
+    fn as_any(&self) -> &Any { self }
+}
+
+impl AnyPacket {
+    /// The packet as a `P`, if that's what it is.
+    pub fn downcast_ref<P: Any>(&self) -> Option<&P> {
+        self.as_any().downcast_ref()
+    }
+}
+
+/// Decodes the body of a packet, after its id.
+pub type Decoder = fn(&mut Read) -> io::Result<Box<AnyPacket>>;
+
+fn decode_boxed<P: Protocol<Clean = P> + AnyPacket + 'static>(src: &mut Read) -> io::Result<Box<AnyPacket>> {
+    <P as Protocol>::proto_decode(src).map(|packet| Box::new(packet) as Box<AnyPacket>)
+}
+
+/// Packets of one ID namespace decided at runtime, unlike those of the
+/// `packets!` tables, so more can be added without changing them. Each
+/// table's `registry()` starts with its packets.
+pub struct Registry {
+    decoders: HashMap<i32, Decoder>
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry { decoders: HashMap::new() }
+    }
+
+    /// Decodes packets with `id` as `P` from now on. Returns whether
+    /// another type was registered for it.
+    pub fn register<P: Protocol<Clean = P> + AnyPacket + 'static>(&mut self, id: i32) -> bool {
+        self.register_decoder(id, decode_boxed::<P>)
+    }
+
+    /// Decodes packets with `id` with `decoder` from now on. Returns
+    /// whether another one was registered for it.
+    pub fn register_decoder(&mut self, id: i32, decoder: Decoder) -> bool {
+        self.decoders.insert(id, decoder).is_some()
+    }
+
+    pub fn contains(&self, id: i32) -> bool {
+        self.decoders.contains_key(&id)
+    }
+
+    /// Decodes a packet, id included, failing with `UnknownId` for those
+    /// not registered.
+    pub fn inner_decode(&self, src: &mut Read) -> io::Result<Box<AnyPacket>> {
+        let id = try!(<Var<i32> as Protocol>::proto_decode(src));
+        match self.decoders.get(&id) {
+            Some(decoder) => decoder(src),
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, UnknownId(id)))
+        }
+    }
+
+    /// Reads a new packet from a reader, including length.
+    pub fn read<R: Read>(&self, src: &mut R) -> io::Result<Box<AnyPacket>> {
+        let frame = try!(read_frame(src));
+        self.inner_decode(&mut &frame[..])
     }
 }
 
@@ -60,32 +218,116 @@ mod prelude {
     pub use std::io;
     pub use std::io::prelude::*;
 
+    #[cfg(feature = "server")]
     pub use nbt;
 
+    #[cfg(feature = "server")]
     pub use uuid::Uuid;
 
-    pub use packet::{BlockChangeRecord, ChunkMeta, Protocol, PacketRead, PacketWrite, Stat, NextState};
+    pub use packet::{read_frame, Protocol, PacketRead, PacketWrite, Registry, NextState, UnknownId};
     pub use proto::slp;
-    pub use types::{Arr, BlockPos, ChunkColumn, Slot, UuidString, Var};
+    pub use types::Var;
+    #[cfg(feature = "server")]
+    pub use packet::{BlockChangeRecord, ChunkMeta, Stat};
+    #[cfg(feature = "server")]
+    pub use types::{Arr, BlockPos, Bytes, ChunkColumn, CombatEvent, EntityUseAction, MapData, MapIcon, ObjectData, ObjectiveAction, PlayerListAction, ScoreAction, Slot, TeamAction, TitleAction, UuidString, WorldBorderAction};
+    #[cfg(feature = "server")]
+    pub use types::ChatJson as Chat;
+    #[cfg(feature = "server")]
+    pub use types::EntityMetadata as Metadata;
+    #[cfg(feature = "server")]
     pub use types::consts::*;
 }
 
+/// Declares the packets of one ID namespace, as `id => Name { fields }`.
+///
+/// Ids are those of `consts::PROTO_VERSION`. Where a later protocol version
+/// gives a packet another id, it's annotated as
+/// `id, since version => other_id => Name { fields }`, latest version last,
+/// which `Name::id` and `Packet::read_version` go by.
 macro_rules! packets {
-    ($($id:expr => $name:ident { $($packet:tt)* })*) => {
+    ($($id:expr $(, since $version:expr => $version_id:expr)* => $name:ident { $($packet:tt)* })*) => {
         use packet::prelude::*;
 
         $(proto_struct!{ $name { $($packet)* } })*
 
+        $(impl $name {
+            /// The id of the packet in protocol `version`.
+            pub fn id(version: i32) -> i32 {
+                let ids: &[(i32, i32)] = &[$(($version, $version_id)),*];
+                ids.iter().rev().find(|&&(since, _)| version >= since).map_or($id, |&(_, id)| id)
+            }
+        })*
+
         #[derive(Debug)]
         pub enum Packet {
             $($name($name)),*
         }
 
+        /// The packets above by name and id, each with a function decoding
+        /// a body of it then writing it back whole, for tests to go
+        /// through them all.
+        #[cfg(test)]
+        #[allow(dead_code)]
+        pub fn table() -> Vec<(&'static str, i32, fn(&[u8]) -> io::Result<Vec<u8>>)> {
+            vec![$((stringify!($name), $id, {
+                fn rewrite(body: &[u8]) -> io::Result<Vec<u8>> {
+                    let packet = try!(<$name as Protocol>::proto_decode(&mut &body[..]));
+                    let mut dst = vec![];
+                    try!(packet.write(&mut dst));
+                    Ok(dst)
+                }
+                rewrite as fn(&[u8]) -> io::Result<Vec<u8>>
+            })),*]
+        }
+
+        /// The packets above, for more to be added to.
+        pub fn registry() -> Registry {
+            let mut registry = Registry::new();
+            $(registry.register::<$name>($id);)*
+            registry
+        }
+
+        impl Packet {
+            /// The id of the packet in protocol `version`.
+            pub fn id(&self, version: i32) -> i32 {
+                match *self {
+                    $(Packet::$name(_) => $name::id(version),)*
+                }
+            }
+
+            /// Reads a new packet of protocol `version` from a reader,
+            /// including length.
+            pub fn read_version<R: Read>(version: i32, src: &mut R) -> io::Result<Packet> {
+                let frame = try!(read_frame(src));
+                let (id, id_len) = try!(Var::<i32>::decode_from(&frame));
+                let mut src = &frame[id_len..];
+                $(if id == $name::id(version) {
+                    return <$name as Protocol>::proto_decode(&mut src).map(Packet::$name);
+                })*
+                Err(io::Error::new(io::ErrorKind::InvalidInput, UnknownId(id)))
+            }
+        }
+
         impl PacketRead for Packet {
             fn inner_decode(src: &mut Read) -> io::Result<Self> {
                 match try!(<Var<i32> as Protocol>::proto_decode(src)) {
                     $($id => <$name as Protocol>::proto_decode(src).map(Packet::$name),)*
-                    _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "unknown packet id"))
+                    id => Err(io::Error::new(io::ErrorKind::InvalidInput, UnknownId(id)))
+                }
+            }
+        }
+
+        impl PacketWrite for Packet {
+            fn inner_len(&self) -> usize {
+                match *self {
+                    $(Packet::$name(ref packet) => packet.inner_len(),)*
+                }
+            }
+
+            fn inner_encode(&self, dst: &mut Write) -> io::Result<()> {
+                match *self {
+                    $(Packet::$name(ref packet) => packet.inner_encode(dst),)*
                 }
             }
         }
@@ -185,6 +427,42 @@ macro_rules! proto_struct {
             }
         }
     };
+    // Structs ending with fields which are only there when a predicate on
+    // the fields before them holds, e.g. `entity_id: i32 where kind == 1`,
+    // `None` when it doesn't.
+    ($name:ident { $($fname:ident: $fty:ty),+; $($cname:ident: $cty:ty where $cond:expr),+ }) => {
+        #[derive(Debug)]
+        pub struct $name {
+            $(pub $fname: <$fty as Protocol>::Clean,)*
+            $(pub $cname: Option<<$cty as Protocol>::Clean>),*
+        }
+
+        impl Protocol for $name {
+            type Clean = Self;
+
+            fn proto_len(value: &$name) -> usize {
+                0 $(+ <$fty as Protocol>::proto_len(&value.$fname))*
+                  $(+ value.$cname.as_ref().map_or(0, <$cty as Protocol>::proto_len))*
+            }
+
+            fn proto_encode(value: &$name, dst: &mut Write) -> io::Result<()> {
+                $(try!(<$fty as Protocol>::proto_encode(&value.$fname, dst));)*
+                $(if let Some(ref $cname) = value.$cname {
+                    try!(<$cty as Protocol>::proto_encode($cname, dst));
+                })*
+                Ok(())
+            }
+
+            fn proto_decode(src: &mut Read) -> io::Result<$name> {
+                $(let $fname = try!(<$fty as Protocol>::proto_decode(src));)*
+                $(let $cname = if $cond { Some(try!(<$cty as Protocol>::proto_decode(src))) } else { None };)*
+                Ok($name {
+                    $($fname: $fname,)*
+                    $($cname: $cname),*
+                })
+            }
+        }
+    };
     // Custom encode/decode structs.
     ($name:ident { $($fname:ident: $fty:ty),+; $impl_struct:item }) => {
         #[derive(Debug)]
@@ -196,12 +474,6 @@ macro_rules! proto_struct {
     }
 }
 
-macro_rules! proto_structs {
-    ($($name:ident { $($fields:tt)+ })+) => {
-        $(proto_struct!($name { $($fields)* });)*
-    }
-}
-
 impl_protocol!(i8,  1, write_i8,  read_i8);
 impl_protocol!(u8,  1, write_u8,  read_u8);
 impl_protocol!(i16, 2, write_i16, read_i16);
@@ -291,23 +563,29 @@ impl Protocol for NextState {
     }
 }
 
-proto_structs! {
-    BlockChangeRecord {
-        xz: u8,
-        y: u8,
-        block_id: Var<i32>
-    }
+#[cfg(feature = "server")]
+#[derive(Debug, Protocol)]
+pub struct BlockChangeRecord {
+    pub xz: u8,
+    pub y: u8,
+    #[protocol(Var<i32>)]
+    pub block_id: i32
+}
 
-    ChunkMeta {
-        x: i32,
-        z: i32,
-        mask: u16
-    }
+#[cfg(feature = "server")]
+#[derive(Debug, Protocol)]
+pub struct ChunkMeta {
+    pub x: i32,
+    pub z: i32,
+    pub mask: u16
+}
 
-    Stat {
-        name: String,
-        value: Var<i32>
-    }
+#[cfg(feature = "server")]
+#[derive(Debug, Protocol)]
+pub struct Stat {
+    pub name: String,
+    #[protocol(Var<i32>)]
+    pub value: i32
 }
 
 pub mod handshake {
@@ -315,25 +593,54 @@ pub mod handshake {
         0x00 => Handshake { proto_version: Var<i32>, server_address: String, server_port: u16, next_state: NextState }
     }
 }
+#[cfg(feature = "server")]
 pub mod play {
     pub mod clientbound { packets! {
         0x00 => KeepAlive { keep_alive_id: Var<i32> }
-        0x01 => JoinGame { entity_id: i32, gamemode: u8, dimension: Dimension, difficulty: u8, max_players: u8, level_type: String, reduced_debug_info: bool }
-        // 0x02 => ChatMessage { data: Chat, position: i8 }
+        0x01 => JoinGame { entity_id: i32, gamemode: GameModeFlags, dimension: Dimension, difficulty: Difficulty, max_players: u8, level_type: String, reduced_debug_info: bool }
+        0x02 => ChatMessage { data: Chat, position: ChatPosition }
         0x03 => TimeUpdate { world_age: i64, time_of_day: i64 }
         0x04 => EntityEquipment { entity_id: Var<i32>, slot: i16, item: Option<Slot> }
         0x05 => WorldSpawn { location: BlockPos }
         0x06 => UpdateHealth { health: f32, food: Var<i32>, saturation: f32 }
-        0x07 => Respawn { dimension: Dimension, difficulty: u8, gamemode: u8, level_type: String }
+        0x07 => Respawn { dimension: Dimension, difficulty: Difficulty, gamemode: GameMode, level_type: String;
+            impl Protocol for Respawn {
+                type Clean = Self;
+                fn proto_len(this: &Self) -> usize {
+                    // Unlike in JoinGame, the dimension is an int here
+                    4 + 1 + 1 + <String as Protocol>::proto_len(&this.level_type)
+                }
+                fn proto_encode(this: &Self, dst: &mut Write) -> io::Result<()> {
+                    try!(<i32 as Protocol>::proto_encode(&(this.dimension as i32), dst));
+                    try!(<Difficulty as Protocol>::proto_encode(&this.difficulty, dst));
+                    try!(<GameMode as Protocol>::proto_encode(&this.gamemode, dst));
+                    <String as Protocol>::proto_encode(&this.level_type, dst)
+                }
+                fn proto_decode(src: &mut Read) -> io::Result<Respawn> {
+                    let dimension = match try!(<i32 as Protocol>::proto_decode(src)) {
+                        -1 => Dimension::Nether,
+                        0 => Dimension::Overworld,
+                        1 => Dimension::End,
+                        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid dimension"))
+                    };
+                    Ok(Respawn {
+                        dimension: dimension,
+                        difficulty: try!(<Difficulty as Protocol>::proto_decode(src)),
+                        gamemode: try!(<GameMode as Protocol>::proto_decode(src)),
+                        level_type: try!(<String as Protocol>::proto_decode(src))
+                    })
+                }
+            }
+        }
         0x08 => PlayerPositionAndLook { position: [f64; 3], yaw: f32, pitch: f32, flags: i8 }
         0x09 => HeldItemChange { slot: i8 }
         0x0a => UseBed { entity_id: Var<i32>, location: BlockPos }
-        0x0b => Animation { entity_id: Var<i32>, animation: u8 }
-        // 0x0c => SpawnPlayer { entity_id: Var<i32>, player_uuid: Uuid, position: [i32; 3], yaw: u8, pitch: u8, current_item: i16, metadata: Metadata }
+        0x0b => Animation { entity_id: Var<i32>, animation: AnimationKind }
+        0x0c => SpawnPlayer { entity_id: Var<i32>, player_uuid: Uuid, position: [i32; 3], yaw: u8, pitch: u8, current_item: i16, metadata: Metadata }
         0x0d => CollectItem { collected_eid: Var<i32>, collector_eid: Var<i32> }
-        // 0x0e => SpawnObject { entity_id: Var<i32>, type_: i8, position: [i32; 3], pitch: u8, yaw: u8, data: ObjectData }
-        // 0x0f => SpawnMob { entity_id: Var<i32>, type_: u8, position: [i32; 3], yaw: u8, pitch: u8, head_pitch: u8, velocity: [i16; 3], metadata: Metadata }
-        0x10 => SpawnPainting { entity_id: Var<i32>, title: String, location: BlockPos, direction: u8 }
+        0x0e => SpawnObject { entity_id: Var<i32>, type_: ObjectKind, position: [i32; 3], pitch: u8, yaw: u8, data: ObjectData }
+        0x0f => SpawnMob { entity_id: Var<i32>, type_: MobKind, position: [i32; 3], yaw: u8, pitch: u8, head_pitch: u8, velocity: [i16; 3], metadata: Metadata }
+        0x10 => SpawnPainting { entity_id: Var<i32>, title: PaintingKind, location: BlockPos, direction: HorizontalFace }
         0x11 => SpawnExperienceOrb { entity_id: Var<i32>, position: [i32; 3], count: i16 }
         0x12 => EntityVelocity { entity_id: Var<i32>, velocity: [i16; 3] }
         0x13 => DestroyEntities { entity_ids: Arr<Var<i32>, Var<i32>> }
@@ -343,14 +650,14 @@ pub mod play {
         0x17 => EntityLookAndRelativeMove { entity_id: Var<i32>, delta: [i8; 3], yaw: u8, pitch: u8, on_ground: bool }
         0x18 => EntityTeleport { entity_id: Var<i32>, position: [i32; 3], yaw: u8, pitch: u8, on_ground: bool }
         0x19 => EntityHeadLook { entity_id: Var<i32>, head_yaw: u8 }
-        0x1A => EntityStatus { entity_id: i32, entity_status: i8 }
+        0x1A => EntityStatus { entity_id: i32, entity_status: EntityStatusKind }
         0x1B => AttachEntity { riding_eid: i32, vehicle_eid: i32, leash: bool }
-        // 0x1C => EntityMetadata { entity_id: Var<i32>, metadata: Metadata }
+        0x1C => EntityMetadata { entity_id: Var<i32>, metadata: Metadata }
         0x1D => EntityEffect { entity_id: Var<i32>, effect_id: i8, amplifier: i8, duration: Var<i32>, hide_particles: bool }
         0x1E => RemoveEntityEffect { entity_id: Var<i32>, effect_id: i8 }
         0x1F => SetExperience { xp_bar: f32, level: Var<i32>, xp_total: Var<i32> }
         // 0x20 => EntityProperties { entity_id: Var<i32>, properties: Arr<i32, Property> }
-        0x21 => ChunkData { x: i32, z: i32, continuous: bool, mask: u16, chunk_data: Arr<Var<i32>, u8> }
+        0x21 => ChunkData { x: i32, z: i32, continuous: bool, mask: u16, chunk_data: Bytes<Var<i32>> }
         0x22 => MultiBlockChange { chunk_x: i32, chunk_z: i32, records: Arr<Var<i32>, BlockChangeRecord> }
         0x23 => BlockChange { location: BlockPos, block_id: Var<i32> }
         0x24 => BlockAction { location: BlockPos, byte1: u8, byte2: u8, block_type: Var<i32> }
@@ -381,19 +688,18 @@ pub mod play {
                 fn proto_decode(src: &mut Read) -> io::Result<ChunkDataBulk> {
                     let sky_light_sent = try!(<bool as Protocol>::proto_decode(src));
                     let columns = try!(<Var<i32> as Protocol>::proto_decode(src));
-                    let mut chunk_meta = Vec::with_capacity(columns as usize);
-                    for cm in &mut chunk_meta {
-                        *cm = try!(<ChunkMeta as Protocol>::proto_decode(src));
+                    if columns < 0 {
+                        return Err(io::Error::new(io::ErrorKind::InvalidInput, "negative column count"));
                     }
+                    let chunk_meta = try!((0..columns).map(|_| <ChunkMeta as Protocol>::proto_decode(src)).collect::<io::Result<Vec<_>>>());
                     // Read all encoded ChunkColumns, buffer size starts at 4KB, probably will get bigger
                     let mut data = Vec::with_capacity(1 << 12);
                     try!(src.read_to_end(&mut data));
                     let mut src = io::Cursor::new(data);
-                    let mut chunk_data = Vec::with_capacity(columns as usize);
-                    for (cd, cm) in chunk_data.iter_mut().zip(chunk_meta.iter()) {
+                    let chunk_data = try!(chunk_meta.iter().map(|cm| {
                         // chunk_data, mask, continuous, sky_light
-                        *cd = try!(ChunkColumn::decode(&mut src, cm.mask, true, true));
-                    }
+                        ChunkColumn::decode(&mut src, cm.mask, true, sky_light_sent)
+                    }).collect::<io::Result<Vec<_>>>());
                     Ok(ChunkDataBulk{
                         sky_light_sent: sky_light_sent,
                         chunk_meta: chunk_meta,
@@ -405,27 +711,63 @@ pub mod play {
         0x27 => Explosion { position: [f32; 3], radius: f32, records: Arr<i32, [i8; 3]>, player_motion: [f32; 3] }
         0x28 => Effect { effect_id: i32, location: BlockPos, data: i32, disable_relative_volume: bool }
         0x29 => SoundEffect { name: String, position: [i32; 3], volume: f32, pitch: u8 }
-        // 0x2a => Particle { particle_id: i32, long_distance: bool, position: [f32; 3], offset: [f32; 3], particle_data: f32, particle_count: i32, data: Vec<i32>; impl Protocol for Particle { ... } } // PROBLEM: length of data depends on particle_id
+        0x2a => Particle { particle_id: ParticleId, long_distance: bool, position: [f32; 3], offset: [f32; 3], particle_data: f32, particle_count: i32, data: Vec<i32>;
+            impl Protocol for Particle {
+                type Clean = Self;
+                fn proto_len(this: &Self) -> usize {
+                    // How much data there is depends on the particle
+                    4 + 1 + 12 + 12 + 4 + 4 + this.data.iter().map(<Var<i32> as Protocol>::proto_len).fold(0, |acc, item| acc + item)
+                }
+                fn proto_encode(this: &Self, dst: &mut Write) -> io::Result<()> {
+                    if this.data.len() != this.particle_id.data_len() {
+                        return Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("{:?} takes {} data, not {}", this.particle_id, this.particle_id.data_len(), this.data.len())[..]));
+                    }
+                    try!(<ParticleId as Protocol>::proto_encode(&this.particle_id, dst));
+                    try!(<bool as Protocol>::proto_encode(&this.long_distance, dst));
+                    try!(<[f32; 3] as Protocol>::proto_encode(&this.position, dst));
+                    try!(<[f32; 3] as Protocol>::proto_encode(&this.offset, dst));
+                    try!(<f32 as Protocol>::proto_encode(&this.particle_data, dst));
+                    try!(<i32 as Protocol>::proto_encode(&this.particle_count, dst));
+                    for value in &this.data {
+                        try!(<Var<i32> as Protocol>::proto_encode(value, dst));
+                    }
+                    Ok(())
+                }
+                fn proto_decode(src: &mut Read) -> io::Result<Particle> {
+                    let particle_id = try!(<ParticleId as Protocol>::proto_decode(src));
+                    Ok(Particle {
+                        particle_id: particle_id,
+                        long_distance: try!(<bool as Protocol>::proto_decode(src)),
+                        position: try!(<[f32; 3] as Protocol>::proto_decode(src)),
+                        offset: try!(<[f32; 3] as Protocol>::proto_decode(src)),
+                        particle_data: try!(<f32 as Protocol>::proto_decode(src)),
+                        particle_count: try!(<i32 as Protocol>::proto_decode(src)),
+                        data: try!((0..particle_id.data_len()).map(|_| <Var<i32> as Protocol>::proto_decode(src)).collect::<io::Result<_>>())
+                    })
+                }
+            }
+        }
         0x2b => ChangeGameState { reason: u8, value: f32 }
         0x2c => SpawnGlobalEntity { entity_id: Var<i32>, type_: i8, position: [i32; 3] }
-        // 0x2d => OpenWindow { window_id: u8, window_type: String, window_title: Chat, slots: u8, entity_id: Option<i32>; impl Protocol for OpenWindow { ... } } // PROBLEM: entity_id depends on window_type
+        // Only horse windows say which entity they belong to
+        0x2d => OpenWindow { window_id: u8, window_type: String, window_title: Chat, slots: u8; entity_id: i32 where window_type == "EntityHorse" }
         0x2e => CloseWindow { window_id: u8 }
         0x2f => SetSlot { window_id: u8, slot: i16, data: Option<Slot> }
         0x30 => WindowItems { window_id: u8, slots: Arr<i16, Option<Slot>> }
         0x31 => WindowProperty { window_id: u8, property: i16, value: i16 }
         0x32 => ConfirmTransaction { window_id: u8, action_number: i16, accepted: bool }
-        // 0x33 => UpdateSign { location: BlockPos, line0: Chat, line1: Chat, line2: Chat, line3: Chat }
-        // 0x34 => UpdateMap { map_id: Var<i32>, scale: i8, icons: Arr<Var<i32>, MapIcon>, data: MapData } // MapData is a quirky format holding optional pixel data for an arbitrary rectangle on the map
+        0x33 => UpdateSign { location: BlockPos, line0: Chat, line1: Chat, line2: Chat, line3: Chat }
+        0x34 => UpdateMap { map_id: Var<i32>, scale: i8, icons: Arr<Var<i32>, MapIcon>, data: MapData }
         // 0x35 => UpdateBlockEntity { location: [i32; 3], action: u8, nbt_data: Nbt; impl Protocol for UpdateBlockEntity { ... } } // PROBLEM: nbt_data is omitted entirely if it encodes an empty NBT tag
         0x36 => SignEditorOpen { location: BlockPos }
         0x37 => Statistics { stats: Arr<Var<i32>, Stat> }
-        // 0x38 => UpdatePlayerList { action: Var<i32>, players: Arr<Var<i32>, PlayerListItem>; impl Protocol for UpdatePlayerList { ... } } // PROBLEM: suructure of `players` elements depends on `action`
+        0x38 => UpdatePlayerList { action: PlayerListAction }
         0x39 => PlayerAbilities { flags: i8, flying_speed: f32, walking_speed: f32 }
         0x3a => TabComplete { matches: Arr<Var<i32>, String> }
-        // 0x3b => ScoreboardObjective { objective_name: String, mode: ObjectiveAction }
-        // 0x3c => UpdateScore { score_name: String, action: ScoreAction }
+        0x3b => ScoreboardObjective { objective_name: String, mode: ObjectiveAction }
+        0x3c => UpdateScore { score_name: String, action: ScoreAction }
         0x3d => DisplayScoreboard { position: i8, score_name: String }
-        // 0x3e => UpdateTeam { team_name: String, action: TeamAction }
+        0x3e => UpdateTeam { team_name: String, action: TeamAction }
         0x3f => PluginMessage { channel: String, data: Vec<u8>;
             impl Protocol for PluginMessage {
                 type Clean = Self;
@@ -445,41 +787,41 @@ pub mod play {
                 }
             }
         }
-        // 0x40 => Disconnect { reason: Chat }
-        0x41 => ServerDifficulty { difficulty: u8 }
-        // 0x42 => PlayCombatEvent { event: CombatEvent }
+        0x40 => Disconnect { reason: Chat }
+        0x41 => ServerDifficulty { difficulty: Difficulty }
+        0x42 => PlayCombatEvent { event: CombatEvent }
         0x43 => Camera { camera_id: Var<i32> }
-        // 0x44 => WorldBorder { action: WorldBorderAction }
-        // 0x45 => Title { action: TitleAction }
+        0x44 => WorldBorder { action: WorldBorderAction }
+        0x45 => Title { action: TitleAction }
         0x46 => SetCompression { threshold: Var<i32> }
-        // 0x47 => PlayerListHeaderFooter { header: Chat, footer: Chat }
+        0x47 => PlayerListHeaderFooter { header: Chat, footer: Chat }
         0x48 => ResourcePackSend { url: String, hash: String }
         0x49 => UpdateEntityNbt { entity_id: Var<i32>, tag: nbt::Blob }
     } }
     pub mod serverbound { packets! {
         0x00 => KeepAlive { keep_alive_id: i32 }
         0x01 => ChatMessage { message: String }
-        // 0x02 => UseEntity { target_eid: i32, use_type: EntityUseAction }
+        0x02 => UseEntity { target_eid: Var<i32>, use_type: EntityUseAction }
         0x03 => PlayerIdle { on_ground: bool }
         0x04 => PlayerPosition { position: [f64; 3], on_ground: bool }
         0x05 => PlayerLook { yaw: f32, pitch: f32, on_ground: bool }
         0x06 => PlayerPositionAndLook { position: [f64; 3], yaw: f32, pitch: f32, on_ground: bool }
-        0x07 => PlayerDigging { status: i8, location: BlockPos, face: i8 }
-        0x08 => PlayerBlockPlacement { location: BlockPos, direction: i8, held_item: Option<Slot>, cursor: [i8; 3] }
+        0x07 => PlayerDigging { status: DiggingStatus, location: BlockPos, face: BlockFace }
+        0x08 => PlayerBlockPlacement { location: BlockPos, direction: BlockFace, held_item: Option<Slot>, cursor: [i8; 3] }
         0x09 => HeldItemChange { slot: i16 }
         0x0a => Animation {}
-        0x0b => EntityAction { entity_id: Var<i32>, action_id: Var<i32>, jump_boost: Var<i32> }
+        0x0b => EntityAction { entity_id: Var<i32>, action: EntityActionKind, jump_boost: Var<i32> }
         0x0c => SteerVehicle { sideways: f32, forward: f32, flags: u8 }
         0x0d => CloseWindow { window_id: u8 }
         0x0e => ClickWindow { window_id: u8, slot: i16, button: i8, action_number: i16, mode: i8, clicked_item: Option<Slot> }
         0x0f => ConfirmTransaction { window_id: u8, action_number: i16, accepted: bool }
         0x10 => CreativeInventoryAction { slot: i16, clicked_item: Option<Slot> }
         0x11 => EnchantItem { window_id: u8, enchantment: i8 }
-        // 0x12 => UpdateSign { location: BlockPos, line0: Chat, line1: Chat, line2: Chat, line3: Chat }
+        0x12 => UpdateSign { location: BlockPos, line0: Chat, line1: Chat, line2: Chat, line3: Chat }
         0x13 => PlayerAbilities { flags: i8, flying_speed: f32, walking_speed: f32 }
         0x14 => TabComplete { text: String, looking_at: Option<i64> }
-        0x15 => ClientSettings { locale: String, view_distance: i8, chat_mode: i8, chat_colors: bool, displayed_skin_parts: u8 }
-        0x16 => ClientStatus { action_id: Var<i32> }
+        0x15 => ClientSettings { locale: String, view_distance: i8, chat_mode: ChatMode, chat_colors: bool, displayed_skin_parts: u8 }
+        0x16 => ClientStatus { action: ClientStatusAction }
         0x17 => PluginMessage { channel: String, data: Vec<u8>;
             impl Protocol for PluginMessage {
                 type Clean = Self;
@@ -500,7 +842,7 @@ pub mod play {
             }
         }
         0x18 => Spectate { target_player: Uuid }
-        0x19 => ResourcePackStatus { hash: String, result: Var<i32> }
+        0x19 => ResourcePackStatus { hash: String, result: ResourcePackResult }
     } }
 }
 pub mod status {
@@ -513,15 +855,238 @@ pub mod status {
         0x01 => Ping { time: i64 }
     } }
 }
+#[cfg(feature = "server")]
 pub mod login {
     pub mod clientbound { packets! {
-        // 0x00 => Disconnect { reason: Chat }
-        0x01 => EncryptionRequest { server_id: String, pubkey: Arr<Var<i32>, u8>, verify_token: Arr<Var<i32>, u8> }
+        0x00 => Disconnect { reason: Chat }
+        0x01 => EncryptionRequest { server_id: String, pubkey: Bytes<Var<i32>>, verify_token: Bytes<Var<i32>> }
         0x02 => LoginSuccess { uuid: UuidString, username: String }
         0x03 => SetCompression { threshold: Var<i32> }
     } }
     pub mod serverbound { packets! {
         0x00 => LoginStart { name: String }
-        0x01 => EncryptionResponse { shared_secret: Arr<Var<i32>, u8>, verify_token: Arr<Var<i32>, u8> }
+        0x01 => EncryptionResponse { shared_secret: Bytes<Var<i32>>, verify_token: Bytes<Var<i32>> }
+    } }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use std::collections::HashSet;
+    use std::io;
+    use std::io::Cursor;
+    use std::iter;
+
+    use packet::{PacketRead, PacketWrite};
+    use types::Var;
+
+    use rand::Rng;
+
+    mod versioned { packets! {
+        0x00 => Ping { id: Var<i32> }
+        0x01, since 100 => 0x05, since 200 => 0x02 => Move { kind: u8, amount: i16; extra: i32 where kind == 1, more: u8 where extra == Some(2) }
     } }
+
+    #[test]
+    fn derived() {
+        use packet::{Protocol, Stat};
+
+        let stat = Stat { name: "stat.jump".to_string(), value: 300 };
+        let mut dst = vec![];
+        <Stat as Protocol>::proto_encode(&stat, &mut dst).unwrap();
+        assert_eq!(dst, b"\x09stat.jump\xac\x02");
+        assert_eq!(<Stat as Protocol>::proto_len(&stat), dst.len());
+        let decoded = <Stat as Protocol>::proto_decode(&mut Cursor::new(dst)).unwrap();
+        assert_eq!((decoded.name, decoded.value), (stat.name, stat.value));
+    }
+
+    #[test]
+    fn unknown_ids() {
+        use packet::play::serverbound::Packet;
+        use packet::unknown_id;
+
+        // An unknown 0x1f with 3 bytes of data, then HeldItemChange
+        let bytes = vec![4, 0x1f, 1, 2, 3, 3, 0x09, 0, 4];
+        let err = Packet::read(&mut Cursor::new(bytes.clone())).unwrap_err();
+        assert_eq!(unknown_id(&err), Some(0x1f));
+        match Packet::read_skipping_unknown(&mut Cursor::new(bytes)).unwrap() {
+            Packet::HeldItemChange(change) => assert_eq!(change.slot, 4),
+            packet => panic!("read {:?}", packet)
+        }
+        // Other errors aren't skipped
+        let err = Packet::read_skipping_unknown(&mut Cursor::new(vec![2, 0x09, 0])).unwrap_err();
+        assert_eq!(unknown_id(&err), None);
+    }
+
+    #[test]
+    fn frame_limit() {
+        use packet::{read_frame, Protocol, MAX_FRAME_LEN};
+
+        let mut src = vec![];
+        <Var<i32> as Protocol>::proto_encode(&MAX_FRAME_LEN, &mut src).unwrap();
+        assert_eq!(src, [0xff, 0xff, 0x7f]);
+        // Cut short, but the length itself is fine
+        assert_eq!(read_frame(&mut Cursor::new(src)).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+        // Refused before reading any of the frame
+        let mut src = vec![];
+        <Var<i32> as Protocol>::proto_encode(&(MAX_FRAME_LEN + 1), &mut src).unwrap();
+        src.extend(iter::repeat(0).take(16));
+        let mut cursor = Cursor::new(src);
+        assert_eq!(read_frame(&mut cursor).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(cursor.position(), 4);
+    }
+
+    #[test]
+    fn registries() {
+        use packet::play::serverbound::{self, HeldItemChange};
+        use packet::unknown_id;
+        use self::versioned::{Move, Ping};
+
+        let mut registry = serverbound::registry();
+        assert!(registry.contains(0x09));
+        let packet = registry.read(&mut Cursor::new(vec![3, 0x09, 0, 4])).unwrap();
+        assert_eq!(packet.downcast_ref::<HeldItemChange>().map(|change| change.slot), Some(4));
+        assert!(packet.downcast_ref::<Move>().is_none());
+
+        // Packets are added and replaced at runtime
+        let err = registry.read(&mut Cursor::new(vec![4, 0x30, 0, 0, 3])).unwrap_err();
+        assert_eq!(unknown_id(&err), Some(0x30));
+        assert!(!registry.register::<Move>(0x30));
+        let packet = registry.read(&mut Cursor::new(vec![4, 0x30, 0, 0, 3])).unwrap();
+        assert_eq!(packet.downcast_ref::<Move>().map(|packet| packet.amount), Some(3));
+        assert!(registry.register::<Ping>(0x09));
+        let packet = registry.read(&mut Cursor::new(vec![2, 0x09, 7])).unwrap();
+        assert_eq!(packet.downcast_ref::<Ping>().map(|ping| ping.id), Some(7));
+        assert!(self::versioned::registry().contains(0x01));
+
+        // Packets write their own id
+        let mut dst = vec![];
+        packet.write(&mut dst).unwrap();
+        assert_eq!(dst, [2, 0x00, 7]);
+    }
+
+    #[test]
+    fn versioned_ids() {
+        use self::versioned::{Move, Packet, Ping};
+
+        assert_eq!(Ping::id(47), 0x00);
+        assert_eq!((Move::id(47), Move::id(100), Move::id(150), Move::id(200)), (0x01, 0x05, 0x05, 0x02));
+        let mut src = Cursor::new(vec![5, 0x05, 0, 0, 3, 9]);
+        match Packet::read_version(100, &mut src).unwrap() {
+            ref packet @ Packet::Move(_) => assert_eq!(packet.id(100), 0x05),
+            packet => panic!("read {:?}", packet)
+        }
+        assert!(Packet::read_version(47, &mut Cursor::new(vec![2, 0x05, 0])).is_err());
+    }
+
+    type Table = Vec<(&'static str, i32, fn(&[u8]) -> io::Result<Vec<u8>>)>;
+
+    /// Bytes valid as some field type, for bodies to be made of so that
+    /// every kind of packet decodes from one sooner or later: strings,
+    /// an empty slot, the end of metadata, an empty NBT compound, and JSON
+    /// for chat and the server status.
+    const TOKENS: &'static [&'static [u8]] = &[
+        b"\x00", b"\x01", b"\x02", b"\x7f", b"\xff\xff",
+        b"\x02\"\"",
+        b"\x05Kebab",
+        b"\x24069a79f4-44e9-4726-a5be-fca90e38aaf5",
+        b"\x0a\x00\x00\x00",
+        b"\x71{\"description\":\"\",\"favicon\":null,\"players\":{\"max\":0,\"online\":0,\"sample\":null},\"version\":{\"name\":\"\",\"protocol\":0}}"
+    ];
+
+    /// Bodies to build packets from: zeros, zeros leading into a token
+    /// over and over, then random runs of tokens and bytes.
+    fn bodies<'a, R: Rng>(rng: &'a mut R) -> Box<Iterator<Item = Vec<u8>> + 'a> {
+        let zeros = iter::once(vec![0; 64]);
+        let repeated = (0..16).flat_map(|zeros| TOKENS.iter().map(move |token| {
+            let mut body = vec![0; zeros];
+            for _ in 0..8 {
+                body.extend(*token);
+            }
+            body
+        }));
+        let fuzzed = (0..2000).map(move |_| {
+            let mut body = vec![];
+            for _ in 0..rng.gen_range(0, 16) {
+                if rng.gen_weighted_bool(4) {
+                    body.push(rng.gen());
+                } else {
+                    body.extend(*rng.choose(TOKENS).unwrap());
+                }
+            }
+            body
+        });
+        Box::new(zeros.chain(repeated).chain(fuzzed))
+    }
+
+    /// Builds every packet of `table` from whichever `bodies` it can,
+    /// checking its length prefix and id, and that reading it back as a `P`
+    /// writes the same bytes. Returns those it couldn't build at all.
+    fn check_table<P: PacketRead + PacketWrite>(table: Table) -> Vec<&'static str> {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        let mut rng = StdRng::from_seed([3; 32]);
+        let mut ids = HashSet::new();
+        let mut unbuilt = vec![];
+        for (name, id, rewrite) in table {
+            assert!(ids.insert(id), "{} reuses id {:#04x}", name, id);
+            let frames: Vec<_> = bodies(&mut rng).filter_map(|body| rewrite(&body).ok()).collect();
+            if frames.is_empty() {
+                unbuilt.push(name);
+            }
+            for frame in frames {
+                let (len, len_len) = Var::<i32>::decode_from(&frame).unwrap();
+                assert_eq!(len as usize, frame.len() - len_len, "{} has the wrong length", name);
+                assert_eq!(Var::<i32>::decode_from(&frame[len_len..]).unwrap().0, id, "{} has the wrong id", name);
+                let mut dst = vec![];
+                P::read(&mut Cursor::new(frame.clone())).unwrap().write(&mut dst).unwrap();
+                assert_eq!(dst, frame, "{} reads back as another packet", name);
+            }
+        }
+        unbuilt
+    }
+
+    #[test]
+    fn every_packet() {
+        use packet::{handshake, login, play, status};
+
+        let mut unbuilt = check_table::<handshake::Packet>(handshake::table());
+        unbuilt.extend(check_table::<status::clientbound::Packet>(status::clientbound::table()));
+        unbuilt.extend(check_table::<status::serverbound::Packet>(status::serverbound::table()));
+        unbuilt.extend(check_table::<login::clientbound::Packet>(login::clientbound::table()));
+        unbuilt.extend(check_table::<login::serverbound::Packet>(login::serverbound::table()));
+        unbuilt.extend(check_table::<play::clientbound::Packet>(play::clientbound::table()));
+        unbuilt.extend(check_table::<play::serverbound::Packet>(play::serverbound::table()));
+        assert_eq!(unbuilt, Vec::<&str>::new());
+    }
+
+    #[test]
+    fn conditional_fields() {
+        use packet::play::clientbound::{OpenWindow, Packet};
+        use self::versioned::Move;
+        use types::ChatJson;
+
+        let horse = OpenWindow {
+            window_id: 1,
+            window_type: "EntityHorse".to_string(),
+            window_title: ChatJson::from("Horse"),
+            slots: 2,
+            entity_id: Some(42)
+        };
+        let mut dst = vec![];
+        horse.write(&mut dst).unwrap();
+        match Packet::read(&mut Cursor::new(dst)).unwrap() {
+            Packet::OpenWindow(window) => assert_eq!(window.entity_id, Some(42)),
+            packet => panic!("read {:?}", packet)
+        }
+
+        let mut dst = vec![];
+        Move { kind: 1, amount: 3, extra: Some(2), more: Some(7) }.write(&mut dst).unwrap();
+        assert_eq!(dst, [9, 0x01, 1, 0, 3, 0, 0, 0, 2, 7]);
+        match self::versioned::Packet::read(&mut Cursor::new(vec![4, 0x01, 0, 0, 3])).unwrap() {
+            self::versioned::Packet::Move(packet) => assert_eq!((packet.extra, packet.more), (None, None)),
+            packet => panic!("read {:?}", packet)
+        }
+    }
 }
@@ -0,0 +1,96 @@
+//! Permission nodes, for finer control than op levels.
+
+use player::Player;
+use world::command::COMMANDS;
+
+/// Lets players break and place blocks near the world spawn.
+pub const SPAWN_PROTECTION_BYPASS: &'static str = "minecraft.spawnprotection.bypass";
+
+/// Lets players place and edit command blocks.
+pub const COMMAND_BLOCK: &'static str = "minecraft.commandblock";
+
+/// Node needed to run command `name`.
+pub fn command_node(name: &str) -> String {
+    format!("minecraft.command.{}", name)
+}
+
+/// Decides what players may do.
+pub trait PermissionProvider {
+    fn has_permission(&self, player: &Player, node: &str) -> bool;
+}
+
+/// Grants nodes by op level, the longest prefix matching a node deciding
+/// the level it needs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpLevels {
+    prefixes: Vec<(String, i32)>,
+    /// Level needed for nodes no prefix matches.
+    default_level: i32
+}
+
+impl OpLevels {
+    pub fn new(default_level: i32) -> OpLevels {
+        OpLevels { prefixes: vec![], default_level: default_level }
+    }
+
+    /// Makes nodes starting with `prefix` need op level `level`.
+    pub fn with(mut self, prefix: &str, level: i32) -> OpLevels {
+        self.prefixes.retain(|&(ref other, _)| other != prefix);
+        self.prefixes.push((prefix.to_string(), level));
+        self
+    }
+
+    /// Vanilla's levels: commands need the level they're registered with,
+    /// command blocks 2 and anything else 4. Spawn protection only keeps
+    /// out non-ops, and nobody when there are no ops at all.
+    pub fn vanilla(has_ops: bool) -> OpLevels {
+        let mut levels = OpLevels::new(4)
+            .with(SPAWN_PROTECTION_BYPASS, if has_ops { 1 } else { 0 })
+            .with(COMMAND_BLOCK, 2);
+        for command in COMMANDS {
+            levels = levels.with(&command_node(command.name), command.level);
+        }
+        levels
+    }
+
+    /// Op level needed for `node`.
+    pub fn level_needed(&self, node: &str) -> i32 {
+        self.prefixes.iter()
+            .filter(|&&(ref prefix, _)| node.starts_with(&prefix[..]))
+            .max_by_key(|&&(ref prefix, _)| prefix.len())
+            .map_or(self.default_level, |&(_, level)| level)
+    }
+}
+
+impl PermissionProvider for OpLevels {
+    fn has_permission(&self, player: &Player, node: &str) -> bool {
+        player.op_level() >= self.level_needed(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use player::Player;
+
+    use uuid::Uuid;
+
+    #[test]
+    fn op_levels() {
+        let levels = OpLevels::vanilla(true).with("plugin.", 0).with("plugin.admin", 3);
+        assert_eq!(levels.level_needed(&command_node("debug")), 3);
+        assert_eq!(levels.level_needed(&command_node("msg")), 0);
+        assert_eq!(levels.level_needed("plugin.admin.ban"), 3);
+        assert_eq!(levels.level_needed("plugin.home"), 0);
+        assert_eq!(levels.level_needed("unknown"), 4);
+
+        let mut player = Player::new(Uuid::new_v4(), "Steve".to_string(), Box::new(io::sink()));
+        assert!(!levels.has_permission(&player, SPAWN_PROTECTION_BYPASS));
+        assert!(OpLevels::vanilla(false).has_permission(&player, SPAWN_PROTECTION_BYPASS));
+        player.set_op_level(2);
+        assert!(levels.has_permission(&player, COMMAND_BLOCK));
+    }
+}
@@ -0,0 +1,1242 @@
+//! Players connected to the server.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::mem;
+use std::io::{self, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use damage::{self, Attacker, DamageSource};
+use entity;
+use item;
+use packet::{PacketWrite, Stat};
+use packet::play::clientbound::SpawnPlayer;
+use potion::Effect;
+use types::{ChatJson, EntityMetadata, PlayerListEntry, ProfileProperty, Slot, hyphenated, metadata_flags};
+use types::consts::{BlockFace, ChatMode, ChatPosition, Difficulty, GameMode, ResourcePackResult};
+use window::{Drag, Window, WindowKind};
+use world::chunk_stream::ChunkView;
+use world::keep_alive::KeepAlive;
+
+use uuid::Uuid;
+
+/// Number of slots in the player inventory window, crafting and armor
+/// slots included.
+pub const INVENTORY_SIZE: usize = 45;
+
+/// Inventory window index of the first hotbar slot.
+pub const HOTBAR_START: usize = 36;
+
+/// Inventory window index of the helmet slot, followed by the other armor
+/// slots.
+pub const ARMOR_START: usize = 5;
+
+/// Inventory window index of the first main inventory slot, right after
+/// crafting and armor.
+pub const MAIN_START: usize = 9;
+
+/// Height of a standing player's eyes above their feet.
+pub const EYE_HEIGHT: f64 = 1.62;
+
+/// Health of a player who just joined or respawned, in half hearts.
+pub const MAX_HEALTH: f32 = 20.0;
+
+/// Players can't sprint with this much food or less.
+pub const SPRINT_FOOD: i32 = 6;
+
+/// Players regenerate health with at least this much food.
+pub const REGEN_FOOD: i32 = 18;
+
+/// Ticks between hearts regenerated or lost to starvation.
+const FOOD_TICKS: u32 = 80;
+
+/// Ticks a player must have slept for the night to be skipped.
+pub const SLEEP_TICKS: i64 = 100;
+
+/// Flags of PlayerAbilities.
+pub const INVULNERABLE: i8 = 0x01;
+pub const FLYING: i8 = 0x02;
+pub const ALLOW_FLYING: i8 = 0x04;
+pub const CREATIVE_MODE: i8 = 0x08;
+
+/// A block being dug in survival, between StartedDigging and
+/// FinishedDigging.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Digging {
+    pub location: [i32; 3],
+    pub face: BlockFace,
+    /// World age when digging started.
+    pub started: i64,
+    /// Share of the block dug each tick, see `block::dig_speed`.
+    pub speed: f32
+}
+
+impl Digging {
+    /// Ticks spent digging so far.
+    pub fn ticks(&self, world_age: i64) -> i64 {
+        world_age - self.started
+    }
+}
+
+/// Where a player respawns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpawnPoint {
+    pub location: [i32; 3],
+    /// Whether it was set with /spawnpoint rather than by sleeping, and
+    /// holds without a bed there.
+    pub forced: bool
+}
+
+/// A player as the world and their connection share them, locked to be
+/// read or changed.
+pub type PlayerHandle = Arc<Mutex<Player>>;
+
+/// A logged in player.
+pub struct Player {
+    uuid: Uuid,
+    name: String,
+    entity_id: i32,
+    gamemode: GameMode,
+    /// Op level from ops.json, 0 for regular players.
+    op_level: i32,
+    chat_mode: ChatMode,
+    /// Muted players can't chat.
+    muted: bool,
+    /// Name of whoever the player last sent or got a private message from.
+    reply_to: Option<String>,
+    /// Whether the player sees everyone's private messages.
+    social_spy: bool,
+    position: [f64; 3],
+    yaw: f32,
+    pitch: f32,
+    on_ground: bool,
+    /// Position and look other players were last sent, in their wire
+    /// units, `None` until the player's been spawned for them.
+    shown: Option<([i32; 3], u8, u8)>,
+    health: f32,
+    /// What hurt the player last.
+    last_damage: Option<DamageSource>,
+    /// Who hurt the player last, with the world age they did.
+    last_attacker: Option<(Attacker, i64)>,
+    food: i32,
+    saturation: f32,
+    /// Grows as the player exerts themselves, every 4 points use up some
+    /// saturation or food.
+    exhaustion: f32,
+    /// Ticks towards the next heart regenerated or lost to starvation.
+    food_timer: u32,
+    level: i32,
+    /// Progress towards the next level, from 0 to 1.
+    experience: f32,
+    total_experience: i32,
+    inventory: Vec<Option<Slot>>,
+    held_slot: usize,
+    /// Status effects, with the world age they wear off at.
+    effects: Vec<(Effect, i64)>,
+    digging: Option<Digging>,
+    using_item: Option<i64>,
+    vehicle: Option<i32>,
+    sneaking: bool,
+    sprinting: bool,
+    flying: bool,
+    /// Head of the bed the player sleeps in, with the world age when they
+    /// lay down.
+    bed: Option<([i32; 3], i64)>,
+    spawn_point: Option<SpawnPoint>,
+    window: Option<Window>,
+    last_window_id: u8,
+    cursor: Option<Slot>,
+    drag: Option<Drag>,
+    /// Window id and action number of the last rejected click, until the
+    /// client owns up to it.
+    rejected_click: Option<(u8, i16)>,
+    resource_pack: Option<ResourcePackResult>,
+    /// Client brand sent on MC|Brand, e.g. `vanilla`.
+    brand: Option<String>,
+    stats: BTreeMap<String, i32>,
+    /// Started by the world once the player has joined.
+    keep_alive: Option<KeepAlive>,
+    /// Chunks sent to the client, set by the world the player is in.
+    chunk_view: Option<ChunkView>,
+    /// When the player last moved, looked around or chatted.
+    last_active: Instant,
+    /// Whether the player's been idle long enough to be away from keyboard.
+    afk: bool,
+    /// Shown in the tab list instead of the player's name.
+    list_name: Option<ChatJson>,
+    /// Their skin and cape, from the session server in online mode.
+    properties: Vec<ProfileProperty>,
+    stream: Box<Write + Send>,
+    /// The connection `stream` writes to, to close it from outside the
+    /// thread reading from it.
+    socket: Option<TcpStream>
+}
+
+impl Player {
+    /// Creates a player whose packets get written to `stream`.
+    pub fn new(uuid: Uuid, name: String, stream: Box<Write + Send>) -> Player {
+        Player {
+            uuid: uuid,
+            name: name,
+            entity_id: 0,
+            gamemode: GameMode::Survival,
+            op_level: 0,
+            chat_mode: ChatMode::Enabled,
+            muted: false,
+            reply_to: None,
+            social_spy: false,
+            position: [0.0, 64.0, 0.0],
+            yaw: 0.0,
+            pitch: 0.0,
+            on_ground: false,
+            shown: None,
+            health: MAX_HEALTH,
+            last_damage: None,
+            last_attacker: None,
+            food: 20,
+            saturation: 5.0,
+            exhaustion: 0.0,
+            food_timer: 0,
+            level: 0,
+            experience: 0.0,
+            total_experience: 0,
+            inventory: vec![None; INVENTORY_SIZE],
+            held_slot: 0,
+            effects: vec![],
+            digging: None,
+            using_item: None,
+            vehicle: None,
+            sneaking: false,
+            sprinting: false,
+            flying: false,
+            bed: None,
+            spawn_point: None,
+            window: None,
+            last_window_id: 0,
+            cursor: None,
+            drag: None,
+            rejected_click: None,
+            resource_pack: None,
+            brand: None,
+            stats: BTreeMap::new(),
+            keep_alive: None,
+            chunk_view: None,
+            last_active: Instant::now(),
+            afk: false,
+            list_name: None,
+            properties: vec![],
+            stream: stream,
+            socket: None
+        }
+    }
+
+    pub fn uuid(&self) -> &Uuid { &self.uuid }
+    pub fn name(&self) -> &str { &self.name }
+
+    /// Entity id given by the world the player is in, 0 before joining.
+    pub fn entity_id(&self) -> i32 { self.entity_id }
+
+    pub fn set_entity_id(&mut self, entity_id: i32) {
+        self.entity_id = entity_id;
+    }
+
+    /// Writes `packet` to this player's connection and flushes it.
+    pub fn send<P: PacketWrite + ?Sized>(&mut self, packet: &P) -> io::Result<()> {
+        try!(packet.write(&mut self.stream));
+        self.stream.flush()
+    }
+
+    /// Sends `message` to be displayed at `position`, unless the client's
+    /// chat mode hides messages there.
+    pub fn send_chat(&mut self, message: ChatJson, position: ChatPosition) -> io::Result<()> {
+        use packet::play::clientbound::ChatMessage;
+
+        if !self.chat_mode.accepts(position) {
+            return Ok(());
+        }
+        self.send(&ChatMessage { data: message, position: position })
+    }
+
+    /// Sends a system message, shown in chat unless chat is hidden.
+    pub fn send_message<T: Into<ChatJson>>(&mut self, message: T) -> io::Result<()> {
+        self.send_chat(message.into(), ChatPosition::System)
+    }
+
+    /// Shows `text` above the hotbar.
+    pub fn send_action_bar(&mut self, text: &str) -> io::Result<()> {
+        self.send_chat(ChatJson::from(text), ChatPosition::ActionBar)
+    }
+
+    pub fn keep_alive_mut(&mut self) -> Option<&mut KeepAlive> { self.keep_alive.as_mut() }
+
+    /// Latency in milliseconds measured by the last keep-alive answered, 0
+    /// until there's been one.
+    pub fn ping(&self) -> i32 {
+        match self.keep_alive.as_ref().and_then(|keep_alive| keep_alive.latency()) {
+            Some(latency) => (latency.as_secs() * 1000 + latency.subsec_nanos() as u64 / 1000000) as i32,
+            None => 0
+        }
+    }
+
+    /// How this player shows in the tab list.
+    pub fn list_entry(&self) -> PlayerListEntry {
+        PlayerListEntry {
+            uuid: self.uuid.clone(),
+            name: self.name.clone(),
+            properties: self.properties.clone(),
+            gamemode: self.gamemode,
+            ping: self.ping(),
+            display_name: self.list_name.clone()
+        }
+    }
+
+    pub fn list_name(&self) -> Option<&ChatJson> { self.list_name.as_ref() }
+
+    pub fn set_list_name(&mut self, name: Option<ChatJson>) {
+        self.list_name = name;
+    }
+
+    pub fn properties(&self) -> &[ProfileProperty] { &self.properties }
+
+    pub fn set_properties(&mut self, properties: Vec<ProfileProperty>) {
+        self.properties = properties;
+    }
+
+    /// How long the player's been idle as of `now`.
+    pub fn idle_for(&self, now: Instant) -> Duration {
+        if now > self.last_active { now.duration_since(self.last_active) } else { Duration::from_secs(0) }
+    }
+
+    /// Records the player doing something at `now`, returning whether they
+    /// were away from keyboard until then.
+    pub fn mark_active(&mut self, now: Instant) -> bool {
+        self.last_active = now;
+        mem::replace(&mut self.afk, false)
+    }
+
+    pub fn afk(&self) -> bool { self.afk }
+
+    pub fn set_afk(&mut self, afk: bool) {
+        self.afk = afk;
+    }
+
+    pub fn set_keep_alive(&mut self, keep_alive: KeepAlive) {
+        self.keep_alive = Some(keep_alive);
+    }
+
+    pub fn chunk_view(&self) -> Option<&ChunkView> { self.chunk_view.as_ref() }
+
+    pub fn chunk_view_mut(&mut self) -> Option<&mut ChunkView> { self.chunk_view.as_mut() }
+
+    pub fn set_chunk_view(&mut self, view: ChunkView) {
+        self.chunk_view = Some(view);
+    }
+
+    pub fn set_socket(&mut self, socket: TcpStream) {
+        self.socket = Some(socket);
+    }
+
+    /// Closes the player's connection, so that whoever reads from it finds
+    /// it ended.
+    pub fn disconnect(&mut self) -> io::Result<()> {
+        match self.socket {
+            Some(ref socket) => socket.shutdown(Shutdown::Both),
+            None => Ok(())
+        }
+    }
+
+    /// Tells the client why it's being disconnected, the connection is
+    /// closed by whoever reads from it.
+    pub fn kick<T: Into<ChatJson>>(&mut self, reason: T) -> io::Result<()> {
+        use packet::play::clientbound::Disconnect;
+
+        try!(self.send(&Disconnect { reason: reason.into() }));
+        info!("Kicked {}", self.name);
+        Ok(())
+    }
+
+    pub fn op_level(&self) -> i32 { self.op_level }
+
+    pub fn set_op_level(&mut self, level: i32) {
+        self.op_level = level;
+    }
+
+    pub fn chat_mode(&self) -> ChatMode { self.chat_mode }
+
+    pub fn set_chat_mode(&mut self, chat_mode: ChatMode) {
+        self.chat_mode = chat_mode;
+    }
+
+    pub fn muted(&self) -> bool { self.muted }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    pub fn reply_to(&self) -> Option<&str> { self.reply_to.as_ref().map(|name| &name[..]) }
+
+    pub fn set_reply_to(&mut self, name: Option<String>) {
+        self.reply_to = name;
+    }
+
+    pub fn social_spy(&self) -> bool { self.social_spy }
+
+    pub fn set_social_spy(&mut self, social_spy: bool) {
+        self.social_spy = social_spy;
+    }
+
+    pub fn gamemode(&self) -> GameMode { self.gamemode }
+
+    /// Sets the game mode, spectators always flying and only creative
+    /// players keeping on.
+    pub fn set_gamemode(&mut self, gamemode: GameMode) {
+        self.gamemode = gamemode;
+        self.flying = match gamemode {
+            GameMode::Spectator => true,
+            GameMode::Creative => self.flying,
+            _ => false
+        };
+    }
+
+    pub fn flying(&self) -> bool { self.flying }
+
+    /// Starts or stops flying, as the client says. Returns whether the
+    /// game mode allows it.
+    pub fn set_flying(&mut self, flying: bool) -> bool {
+        if (flying && self.abilities() & ALLOW_FLYING == 0) || (!flying && self.gamemode == GameMode::Spectator) {
+            return false;
+        }
+        self.flying = flying;
+        true
+    }
+
+    /// PlayerAbilities flags of the player's game mode.
+    pub fn abilities(&self) -> i8 {
+        let flags = match self.gamemode {
+            GameMode::Creative => INVULNERABLE | ALLOW_FLYING | CREATIVE_MODE,
+            GameMode::Spectator => INVULNERABLE | ALLOW_FLYING,
+            _ => 0
+        };
+        if self.flying { flags | FLYING } else { flags }
+    }
+
+    /// Tells the client what it may do in the player's game mode.
+    pub fn send_abilities(&mut self) -> io::Result<()> {
+        use packet::play::clientbound::PlayerAbilities;
+
+        let flags = self.abilities();
+        self.send(&PlayerAbilities { flags: flags, flying_speed: 0.05, walking_speed: 0.1 })
+    }
+
+    /// Position of the player's feet.
+    pub fn position(&self) -> [f64; 3] { self.position }
+    pub fn yaw(&self) -> f32 { self.yaw }
+    pub fn pitch(&self) -> f32 { self.pitch }
+    pub fn on_ground(&self) -> bool { self.on_ground }
+
+    pub fn eye_position(&self) -> [f64; 3] {
+        [self.position[0], self.position[1] + EYE_HEIGHT, self.position[2]]
+    }
+
+    /// Unit vector pointing where the player is looking.
+    pub fn look(&self) -> [f64; 3] {
+        let yaw = (self.yaw as f64).to_radians();
+        let pitch = (self.pitch as f64).to_radians();
+        [-yaw.sin() * pitch.cos(), -pitch.sin(), yaw.cos() * pitch.cos()]
+    }
+
+    pub fn set_position(&mut self, position: [f64; 3], on_ground: bool) {
+        self.position = position;
+        self.on_ground = on_ground;
+    }
+
+    /// Moves the player where their client says they went, which tires
+    /// them when sprinting or jumping.
+    pub fn walk(&mut self, position: [f64; 3], on_ground: bool) {
+        let (dx, dy, dz) = (position[0] - self.position[0], position[1] - self.position[1], position[2] - self.position[2]);
+        if self.on_ground && !on_ground && dy > 0.0 {
+            let exhaustion = if self.sprinting { 0.8 } else { 0.2 };
+            self.add_exhaustion(exhaustion);
+        } else if on_ground && self.sprinting {
+            let distance = (dx * dx + dz * dz).sqrt() as f32;
+            self.add_exhaustion(0.1 * distance);
+        }
+        self.set_position(position, on_ground);
+    }
+
+    pub fn set_look(&mut self, yaw: f32, pitch: f32, on_ground: bool) {
+        self.yaw = yaw;
+        self.pitch = pitch;
+        self.on_ground = on_ground;
+    }
+
+    pub fn health(&self) -> f32 { self.health }
+    pub fn dead(&self) -> bool { self.health <= 0.0 }
+    pub fn food(&self) -> i32 { self.food }
+    pub fn saturation(&self) -> f32 { self.saturation }
+
+    /// Tells the client about its current health and food.
+    pub fn send_health(&mut self) -> io::Result<()> {
+        use packet::play::clientbound::UpdateHealth;
+
+        let (health, food, saturation) = (self.health, self.food, self.saturation);
+        self.send(&UpdateHealth { health: health, food: food, saturation: saturation })
+    }
+
+    /// Sets health, clamped between 0 and `MAX_HEALTH`.
+    pub fn set_health(&mut self, health: f32) -> io::Result<()> {
+        self.health = health.max(0.0).min(MAX_HEALTH);
+        self.send_health()
+    }
+
+    /// Takes `amount` half hearts of damage from `source` at world age
+    /// `world_age`, returns whether it killed the player. Creative and
+    /// spectator players are invulnerable.
+    pub fn damage(&mut self, amount: f32, source: DamageSource, world_age: i64) -> io::Result<bool> {
+        match self.gamemode {
+            GameMode::Creative | GameMode::Spectator => Ok(false),
+            _ if self.health <= 0.0 => Ok(false),
+            _ => {
+                if let Some(attacker) = source.attacker() {
+                    self.last_attacker = Some((attacker.clone(), world_age));
+                }
+                self.add_exhaustion(source.exhaustion());
+                self.last_damage = Some(source);
+                let health = self.health - amount;
+                try!(self.set_health(health));
+                Ok(self.health <= 0.0)
+            }
+        }
+    }
+
+    /// Tires the player, unless they can't take damage anyway.
+    pub fn add_exhaustion(&mut self, exhaustion: f32) {
+        if self.gamemode == GameMode::Survival || self.gamemode == GameMode::Adventure {
+            self.exhaustion = (self.exhaustion + exhaustion).min(40.0);
+        }
+    }
+
+    /// Ticks the food bar: exhaustion uses up saturation then food, full
+    /// players regenerate when `regenerate` is set and starving ones get
+    /// hurt. Returns whether the player should take a half heart of
+    /// starvation damage.
+    pub fn tick_food(&mut self, regenerate: bool, difficulty: Difficulty) -> io::Result<bool> {
+        if self.gamemode == GameMode::Creative || self.gamemode == GameMode::Spectator || self.health <= 0.0 {
+            return Ok(false);
+        }
+        let mut changed = false;
+        if self.exhaustion > 4.0 {
+            self.exhaustion -= 4.0;
+            if self.saturation > 0.0 {
+                self.saturation = (self.saturation - 1.0).max(0.0);
+                changed = true;
+            } else if difficulty != Difficulty::Peaceful {
+                self.food = (self.food - 1).max(0);
+                changed = true;
+            }
+        }
+        if self.sprinting && self.food <= SPRINT_FOOD {
+            self.sprinting = false;
+        }
+        let mut starve = false;
+        if regenerate && self.food >= REGEN_FOOD && self.health < MAX_HEALTH {
+            self.food_timer += 1;
+            if self.food_timer >= FOOD_TICKS {
+                self.food_timer = 0;
+                self.health = (self.health + 1.0).min(MAX_HEALTH);
+                self.add_exhaustion(3.0);
+                changed = true;
+            }
+        } else if self.food <= 0 {
+            self.food_timer += 1;
+            if self.food_timer >= FOOD_TICKS {
+                self.food_timer = 0;
+                // Starving stops at 5 hearts on easy and half a heart on normal
+                starve = match difficulty {
+                    Difficulty::Hard => true,
+                    Difficulty::Normal => self.health > 1.0,
+                    _ => self.health > 10.0
+                };
+            }
+        } else {
+            self.food_timer = 0;
+        }
+        if changed {
+            try!(self.send_health());
+        }
+        Ok(starve)
+    }
+
+    /// What hurt the player last.
+    pub fn last_damage(&self) -> Option<&DamageSource> { self.last_damage.as_ref() }
+
+    /// Who gets the credit if the player dies at world age `world_age`.
+    pub fn killer(&self, world_age: i64) -> Option<&Attacker> {
+        match self.last_attacker {
+            Some((ref attacker, hurt)) if world_age - hurt <= damage::KILL_CREDIT_TICKS => Some(attacker),
+            _ => None
+        }
+    }
+
+    /// Experience level.
+    pub fn level(&self) -> i32 { self.level }
+
+    pub fn send_experience(&mut self) -> io::Result<()> {
+        use packet::play::clientbound::SetExperience;
+
+        let packet = SetExperience { xp_bar: self.experience, level: self.level, xp_total: self.total_experience };
+        self.send(&packet)
+    }
+
+    /// Gives the player experience `points`, levelling up as they add up.
+    pub fn add_experience(&mut self, points: i32) -> io::Result<()> {
+        if points <= 0 {
+            return Ok(());
+        }
+        self.total_experience += points;
+        self.experience += points as f32 / level_cost(self.level) as f32;
+        while self.experience >= 1.0 {
+            // Carry over what's left to the next level
+            let left = (self.experience - 1.0) * level_cost(self.level) as f32;
+            self.level += 1;
+            self.experience = left / level_cost(self.level) as f32;
+        }
+        self.send_experience()
+    }
+
+    /// Takes all the player's experience away, returning the points they
+    /// drop on death: 7 per level, 100 at most.
+    pub fn take_experience(&mut self) -> io::Result<i32> {
+        let dropped = (self.level * 7).min(100);
+        self.level = 0;
+        self.experience = 0.0;
+        self.total_experience = 0;
+        try!(self.send_experience());
+        Ok(dropped)
+    }
+
+    /// Takes `levels` levels away, e.g. to pay for using an anvil.
+    pub fn remove_levels(&mut self, levels: i32) -> io::Result<()> {
+        self.level -= levels;
+        if self.level < 0 {
+            self.level = 0;
+            self.experience = 0.0;
+            self.total_experience = 0;
+        }
+        self.send_experience()
+    }
+
+    /// Empties the inventory, armor and crafting grid included, returning
+    /// what was in it.
+    pub fn take_inventory(&mut self) -> Vec<Slot> {
+        self.inventory.iter_mut().filter_map(|slot| slot.take()).collect()
+    }
+
+    /// Item in the inventory window slot `index`.
+    pub fn slot(&self, index: usize) -> Option<&Slot> {
+        self.inventory.get(index).and_then(|slot| slot.as_ref())
+    }
+
+    /// Sets the inventory window slot `index`, out of range indices are
+    /// ignored.
+    pub fn set_slot(&mut self, index: usize, item: Option<Slot>) {
+        if let Some(slot) = self.inventory.get_mut(index) {
+            *slot = item;
+        }
+    }
+
+    /// Selected hotbar slot, from 0 to 8.
+    pub fn held_slot(&self) -> usize { self.held_slot }
+
+    pub fn set_held_slot(&mut self, held_slot: usize) {
+        if held_slot < 9 {
+            self.held_slot = held_slot;
+        }
+    }
+
+    pub fn held_item(&self) -> Option<&Slot> {
+        self.slot(HOTBAR_START + self.held_slot)
+    }
+
+    pub fn set_held_item(&mut self, item: Option<Slot>) {
+        let index = HOTBAR_START + self.held_slot;
+        self.set_slot(index, item);
+    }
+
+    /// Wears the held tool down by one use, breaking it once used up.
+    pub fn wear_held_item(&mut self) {
+        if self.gamemode == GameMode::Creative {
+            return;
+        }
+        let worn = match self.held_item() {
+            Some(stack) => match item::max_damage(stack.id()) {
+                Some(max) if stack.damage() >= max => None,
+                Some(_) => {
+                    let mut stack = stack.clone();
+                    let damage = stack.damage() + 1;
+                    stack.set_damage(damage);
+                    Some(stack)
+                }
+                None => return
+            },
+            None => return
+        };
+        self.set_held_item(worn);
+    }
+
+    /// Puts `stack` in the inventory, hotbar first, topping up existing
+    /// stacks before using empty slots. Returns what didn't fit.
+    pub fn add_item(&mut self, mut stack: Slot) -> io::Result<Option<Slot>> {
+        let order: Vec<usize> = (HOTBAR_START..INVENTORY_SIZE).chain(MAIN_START..HOTBAR_START).collect();
+        let mut changed = vec![];
+        for &index in &order {
+            if let Some(ref mut existing) = self.inventory[index] {
+                if existing.merge(&mut stack, 64) > 0 {
+                    changed.push(index);
+                }
+            }
+            if stack.count() == 0 {
+                break;
+            }
+        }
+        if stack.count() > 0 {
+            for &index in &order {
+                if self.inventory[index].is_none() {
+                    self.inventory[index] = Some(stack.clone());
+                    changed.push(index);
+                    stack.set_count(0);
+                    break;
+                }
+            }
+        }
+        for index in changed {
+            try!(self.send_slot(index));
+        }
+        Ok(stack.non_empty())
+    }
+
+    pub fn has_item(&self, id: u16) -> bool {
+        self.inventory.iter().any(|slot| slot.as_ref().map_or(false, |stack| stack.id() == id))
+    }
+
+    /// Removes one item with the given id from the inventory, hotbar first.
+    /// Returns whether there was one.
+    pub fn take_item(&mut self, id: u16) -> io::Result<bool> {
+        let order: Vec<usize> = (HOTBAR_START..INVENTORY_SIZE).chain(MAIN_START..HOTBAR_START).collect();
+        for index in order {
+            let left = match self.inventory[index] {
+                Some(ref stack) if stack.id() == id => stack.with_count(stack.count() - 1).non_empty(),
+                _ => continue
+            };
+            self.inventory[index] = left;
+            try!(self.send_slot(index));
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Tells the client what's in the inventory window slot `index`.
+    pub fn send_slot(&mut self, index: usize) -> io::Result<()> {
+        use packet::play::clientbound::SetSlot;
+
+        let data = self.inventory[index].clone();
+        self.send(&SetSlot { window_id: 0, slot: index as i16, data: data })
+    }
+
+    /// Removes one item, or the whole stack, from the selected hotbar slot.
+    pub fn take_held_item(&mut self, whole_stack: bool) -> Option<Slot> {
+        let index = HOTBAR_START + self.held_slot;
+        let (taken, left) = match self.inventory[index].take() {
+            Some(mut stack) if !whole_stack && stack.count() > 1 => {
+                let taken = stack.split(1);
+                (Some(taken), Some(stack))
+            }
+            stack => (stack, None)
+        };
+        self.inventory[index] = left;
+        taken
+    }
+
+    /// Status effect `id`, if the player has it at world age `world_age`.
+    pub fn effect(&self, id: u8, world_age: i64) -> Option<&Effect> {
+        self.effects.iter().find(|&&(ref effect, ends)| effect.id == id && ends > world_age).map(|&(ref effect, _)| effect)
+    }
+
+    /// Gives the player `effect` from world age `world_age` on, replacing
+    /// any effect of the same kind.
+    pub fn add_effect(&mut self, effect: Effect, world_age: i64) {
+        self.effects.retain(|&(ref other, ends)| other.id != effect.id && ends > world_age);
+        self.effects.push((effect, world_age + effect.duration as i64));
+    }
+
+    /// Block currently being dug, if any.
+    pub fn digging(&self) -> Option<&Digging> { self.digging.as_ref() }
+
+    pub fn start_digging(&mut self, location: [i32; 3], face: BlockFace, world_age: i64, speed: f32) {
+        self.digging = Some(Digging { location: location, face: face, started: world_age, speed: speed });
+    }
+
+    /// Stops digging, returning the block which was being dug.
+    pub fn stop_digging(&mut self) -> Option<Digging> {
+        self.digging.take()
+    }
+
+    /// World age when the player started using their held item, e.g.
+    /// drawing a bow.
+    pub fn using_item(&self) -> Option<i64> { self.using_item }
+
+    /// Whether the player may eat item `id`: only when hungry, save for
+    /// golden apples, and never when they can't get hurt.
+    pub fn can_eat(&self, id: u16) -> bool {
+        if item::food(id).is_none() || self.abilities() & INVULNERABLE != 0 {
+            return false;
+        }
+        id == item::GOLDEN_APPLE || self.food < 20
+    }
+
+    /// Fills the food bar with `food` points, and saturation by as much
+    /// times `saturation` times 2, never past the food level.
+    pub fn eat(&mut self, food: i32, saturation: f32) {
+        self.food = (self.food + food).min(20);
+        self.saturation = (self.saturation + food as f32 * saturation * 2.0).min(self.food as f32);
+    }
+
+    pub fn clear_effects(&mut self) {
+        self.effects.clear();
+    }
+
+    /// Bits of `metadata_flags` others see the player with.
+    pub fn metadata_flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.sneaking {
+            flags |= metadata_flags::CROUCHED;
+        }
+        if self.sprinting {
+            flags |= metadata_flags::SPRINTING;
+        }
+        if self.using_item.is_some() {
+            flags |= metadata_flags::USING_ITEM;
+        }
+        flags
+    }
+
+    /// Spawns the player for other players.
+    pub fn spawn_packet(&mut self) -> SpawnPlayer {
+        let (position, yaw, pitch) = (entity::fixed_point(self.position), entity::angle(self.yaw), entity::angle(self.pitch));
+        self.shown = Some((position, yaw, pitch));
+        let mut metadata = EntityMetadata::new();
+        metadata.set_flags(self.metadata_flags());
+        SpawnPlayer {
+            entity_id: self.entity_id,
+            player_uuid: self.uuid.clone(),
+            position: position,
+            yaw: yaw,
+            pitch: pitch,
+            current_item: self.held_item().map_or(0, |stack| stack.id() as i16),
+            metadata: metadata
+        }
+    }
+
+    /// Moves the player for other players if they've moved or looked
+    /// around since they were last shown.
+    pub fn movement_packets(&mut self) -> Vec<Box<PacketWrite + Send>> {
+        use packet::play::clientbound::{EntityHeadLook, EntityTeleport};
+
+        let (position, yaw, pitch) = (entity::fixed_point(self.position), entity::angle(self.yaw), entity::angle(self.pitch));
+        match self.shown {
+            Some(shown) if shown != (position, yaw, pitch) => {}
+            _ => return vec![]
+        }
+        self.shown = Some((position, yaw, pitch));
+        vec![
+            Box::new(EntityTeleport { entity_id: self.entity_id, position: position, yaw: yaw, pitch: pitch, on_ground: self.on_ground }),
+            Box::new(EntityHeadLook { entity_id: self.entity_id, head_yaw: yaw })
+        ]
+    }
+
+    pub fn start_using_item(&mut self, world_age: i64) {
+        self.using_item = Some(world_age);
+    }
+
+    /// Stops using the held item, returning when it started.
+    pub fn stop_using_item(&mut self) -> Option<i64> {
+        self.using_item.take()
+    }
+
+    /// Entity id of what the player is riding.
+    pub fn vehicle(&self) -> Option<i32> { self.vehicle }
+
+    pub fn set_vehicle(&mut self, vehicle: Option<i32>) {
+        self.vehicle = vehicle;
+    }
+
+    pub fn take_vehicle(&mut self) -> Option<i32> {
+        self.vehicle.take()
+    }
+
+    pub fn sneaking(&self) -> bool { self.sneaking }
+
+    pub fn set_sneaking(&mut self, sneaking: bool) {
+        self.sneaking = sneaking;
+    }
+
+    pub fn sprinting(&self) -> bool { self.sprinting }
+
+    /// Starts sprinting if the player has more than `SPRINT_FOOD`, returns
+    /// whether they could.
+    pub fn start_sprinting(&mut self) -> bool {
+        self.sprinting = self.food > SPRINT_FOOD || self.gamemode == GameMode::Creative;
+        self.sprinting
+    }
+
+    pub fn stop_sprinting(&mut self) {
+        self.sprinting = false;
+    }
+
+    /// Head of the bed the player sleeps in.
+    pub fn bed(&self) -> Option<[i32; 3]> {
+        self.bed.map(|(location, _)| location)
+    }
+
+    pub fn sleep(&mut self, bed: [i32; 3], world_age: i64) {
+        self.bed = Some((bed, world_age));
+    }
+
+    /// Gets out of bed, returning where it was.
+    pub fn wake(&mut self) -> Option<[i32; 3]> {
+        self.bed.take().map(|(location, _)| location)
+    }
+
+    /// Whether the player has slept for `SLEEP_TICKS` by world age
+    /// `world_age`.
+    pub fn slept(&self, world_age: i64) -> bool {
+        self.bed.map_or(false, |(_, since)| world_age - since >= SLEEP_TICKS)
+    }
+
+    pub fn spawn_point(&self) -> Option<SpawnPoint> { self.spawn_point }
+
+    pub fn set_spawn_point(&mut self, spawn_point: Option<SpawnPoint>) {
+        self.spawn_point = spawn_point;
+    }
+
+    /// Brings the player back to life, with full health and food and
+    /// nothing left of how they died.
+    pub fn respawn(&mut self) {
+        self.health = MAX_HEALTH;
+        self.food = 20;
+        self.saturation = 5.0;
+        self.exhaustion = 0.0;
+        self.food_timer = 0;
+        self.effects.clear();
+        self.last_damage = None;
+        self.last_attacker = None;
+        self.digging = None;
+        self.using_item = None;
+        self.bed = None;
+    }
+
+    /// Window open on top of the player's inventory.
+    pub fn window(&self) -> Option<&Window> { self.window.as_ref() }
+
+    pub fn window_mut(&mut self) -> Option<&mut Window> { self.window.as_mut() }
+
+    /// Id of the open window, 0 for the player's inventory when none is.
+    pub fn window_id(&self) -> u8 {
+        self.window.as_ref().map_or(0, |window| window.id)
+    }
+
+    /// Opens a window of the given `kind`, replacing any open one, and
+    /// returns its id.
+    pub fn open_window(&mut self, kind: WindowKind) -> u8 {
+        // Ids go from 1 to 100 like vanilla's, 0 is the player's inventory
+        self.last_window_id = self.last_window_id % 100 + 1;
+        self.window = Some(Window { id: self.last_window_id, kind: kind });
+        self.drag = None;
+        self.rejected_click = None;
+        self.last_window_id
+    }
+
+    pub fn close_window(&mut self) -> Option<Window> {
+        self.drag = None;
+        self.rejected_click = None;
+        self.window.take()
+    }
+
+    /// Item picked up with the cursor in an open window.
+    pub fn cursor(&self) -> Option<&Slot> { self.cursor.as_ref() }
+
+    pub fn set_cursor(&mut self, cursor: Option<Slot>) {
+        self.cursor = cursor;
+    }
+
+    pub fn take_cursor(&mut self) -> Option<Slot> {
+        self.cursor.take()
+    }
+
+    /// Drag going on in the open window, between ClickWindow mode 5 clicks.
+    pub fn take_drag(&mut self) -> Option<Drag> {
+        self.drag.take()
+    }
+
+    pub fn set_drag(&mut self, drag: Option<Drag>) {
+        self.drag = drag;
+    }
+
+    /// Whether clicks in window `window_id` are ignored, because the client
+    /// still has to acknowledge a rejected one.
+    pub fn awaiting_apology(&self, window_id: u8) -> bool {
+        self.rejected_click.map_or(false, |(id, _)| id == window_id)
+    }
+
+    /// Remembers that click `action_number` in window `window_id` was
+    /// rejected, until the client sends it back in a ConfirmTransaction.
+    pub fn reject_click(&mut self, window_id: u8, action_number: i16) {
+        self.rejected_click = Some((window_id, action_number));
+    }
+
+    /// Takes the client's ConfirmTransaction for a rejected click, returns
+    /// whether it was the one waited for.
+    pub fn apologize(&mut self, window_id: u8, action_number: i16) -> bool {
+        if self.rejected_click == Some((window_id, action_number)) {
+            self.rejected_click = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Latest resource pack status reported by the client, `None` if the
+    /// client never answered a ResourcePackSend.
+    pub fn resource_pack(&self) -> Option<ResourcePackResult> { self.resource_pack }
+
+    pub fn set_resource_pack(&mut self, result: ResourcePackResult) {
+        self.resource_pack = Some(result);
+    }
+
+    pub fn brand(&self) -> Option<&str> { self.brand.as_ref().map(|brand| &brand[..]) }
+
+    pub fn set_brand(&mut self, brand: String) {
+        self.brand = Some(brand);
+    }
+
+    /// Value of the statistic `name`, e.g. `stat.jump`.
+    pub fn stat(&self, name: &str) -> i32 {
+        self.stats.get(name).cloned().unwrap_or(0)
+    }
+
+    pub fn increment_stat(&mut self, name: &str, amount: i32) {
+        *self.stats.entry(name.to_string()).or_insert(0) += amount;
+    }
+
+    /// All statistics with a value, as sent in the Statistics packet.
+    pub fn stats(&self) -> Vec<Stat> {
+        self.stats.iter().map(|(name, value)| Stat { name: name.clone(), value: *value }).collect()
+    }
+}
+
+/// Experience points it takes to go from `level` to the next one.
+pub fn level_cost(level: i32) -> i32 {
+    if level >= 30 {
+        112 + (level - 30) * 9
+    } else if level >= 15 {
+        37 + (level - 15) * 5
+    } else {
+        7 + level * 2
+    }
+}
+
+impl fmt::Debug for Player {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Player name={} uuid={}", self.name, hyphenated(&self.uuid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use uuid::Uuid;
+
+    #[test]
+    fn sleeping() {
+        let mut player = Player::new(Uuid::new_v4(), "Steve".to_string(), Box::new(io::sink()));
+        assert!(!player.slept(0));
+        player.sleep([1, 64, 2], 1000);
+        assert_eq!(player.bed(), Some([1, 64, 2]));
+        assert!(!player.slept(1000 + SLEEP_TICKS - 1));
+        assert!(player.slept(1000 + SLEEP_TICKS));
+        assert_eq!(player.wake(), Some([1, 64, 2]));
+        assert!(!player.slept(1000 + SLEEP_TICKS));
+    }
+
+    #[test]
+    fn idling() {
+        let mut player = Player::new(Uuid::new_v4(), "Steve".to_string(), Box::new(io::sink()));
+        let now = Instant::now();
+        assert!(!player.mark_active(now));
+        assert_eq!(player.idle_for(now + Duration::from_secs(90)), Duration::from_secs(90));
+        player.set_afk(true);
+        player.set_list_name(Some(ChatJson::from("[AFK] Steve")));
+        assert_eq!(player.list_entry().display_name, Some(ChatJson::from("[AFK] Steve")));
+        assert!(player.mark_active(now + Duration::from_secs(100)));
+        assert!(!player.afk());
+        assert_eq!(player.idle_for(now + Duration::from_secs(90)), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn respawning() {
+        let mut player = Player::new(Uuid::new_v4(), "Steve".to_string(), Box::new(io::sink()));
+        assert!(player.damage(30.0, DamageSource::Starve, 0).unwrap());
+        assert!(player.dead());
+        player.set_spawn_point(Some(SpawnPoint { location: [1, 64, 2], forced: false }));
+        player.respawn();
+        assert!(!player.dead() && player.last_damage().is_none());
+        assert_eq!(player.spawn_point().map(|spawn_point| spawn_point.location), Some([1, 64, 2]));
+    }
+
+    #[test]
+    fn experience() {
+        let mut player = Player::new(Uuid::new_v4(), "Steve".to_string(), Box::new(io::sink()));
+        player.add_experience(7 + 9 + 5).unwrap();
+        assert_eq!(player.level(), 2);
+        assert!((player.experience - 5.0 / 11.0).abs() < 1.0e-5);
+        player.remove_levels(3).unwrap();
+        assert_eq!((player.level(), player.total_experience), (0, 0));
+        player.add_experience(1000).unwrap();
+        assert_eq!(player.take_experience().unwrap(), 100);
+        assert_eq!((player.level(), player.total_experience), (0, 0));
+    }
+
+    #[test]
+    fn rejected_click() {
+        let mut player = Player::new(Uuid::new_v4(), "Steve".to_string(), Box::new(io::sink()));
+        player.reject_click(0, 12);
+        assert!(player.awaiting_apology(0));
+        assert!(!player.apologize(0, 11));
+        assert!(player.apologize(0, 12));
+        assert!(!player.awaiting_apology(0));
+        // Opening another window forgets about it
+        player.reject_click(0, 13);
+        let id = player.open_window(WindowKind::Horse(1));
+        assert!(!player.awaiting_apology(0));
+        assert_eq!(player.window_id(), id);
+        player.close_window();
+        assert_eq!(player.window_id(), 0);
+    }
+
+    #[test]
+    fn kill_credit() {
+        use damage::{Attacker, DamageSource};
+
+        let mut player = Player::new(Uuid::new_v4(), "Steve".to_string(), Box::new(io::sink()));
+        let alex = Attacker { entity_id: 2, name: "Alex".to_string() };
+        assert!(!player.damage(5.0, DamageSource::Arrow(Some(alex.clone())), 100).unwrap());
+        assert!(player.damage(15.0, DamageSource::Fall, 150).unwrap());
+        assert_eq!(player.killer(150), Some(&alex));
+        assert_eq!(player.killer(201), None);
+        assert_eq!(player.last_damage(), Some(&DamageSource::Fall));
+        // Dead players don't die again
+        assert!(!player.damage(1.0, DamageSource::Generic, 151).unwrap());
+    }
+
+    #[test]
+    fn hunger() {
+        use types::consts::Difficulty;
+
+        let mut player = Player::new(Uuid::new_v4(), "Steve".to_string(), Box::new(io::sink()));
+        player.health = 10.0;
+        for _ in 0..FOOD_TICKS {
+            assert!(!player.tick_food(true, Difficulty::Normal).unwrap());
+        }
+        assert_eq!(player.health, 11.0);
+        // Healing is tiring, and the first 4 points of exhaustion eat into saturation
+        player.add_exhaustion(1.5);
+        player.tick_food(false, Difficulty::Normal).unwrap();
+        assert_eq!((player.food, player.saturation), (20, 4.0));
+
+        player.food = SPRINT_FOOD;
+        assert!(!player.start_sprinting());
+        player.food = 0;
+        let starved = (0..FOOD_TICKS).filter(|_| player.tick_food(true, Difficulty::Easy).unwrap()).count();
+        assert_eq!(starved, 1);
+        player.health = 10.0;
+        assert!(!(0..FOOD_TICKS).any(|_| player.tick_food(true, Difficulty::Easy).unwrap()));
+    }
+
+    #[test]
+    fn exhaustion() {
+        let mut player = Player::new(Uuid::new_v4(), "Steve".to_string(), Box::new(io::sink()));
+        player.set_position([0.0, 64.0, 0.0], true);
+        player.walk([0.0, 64.4, 0.0], false);
+        assert!((player.exhaustion - 0.2).abs() < 1e-6);
+        player.walk([0.0, 64.0, 0.0], true);
+        player.start_sprinting();
+        player.walk([3.0, 64.0, 4.0], true);
+        assert!((player.exhaustion - 0.7).abs() < 1e-6);
+        player.set_gamemode(GameMode::Creative);
+        player.add_exhaustion(1.0);
+        assert!((player.exhaustion - 0.7).abs() < 1e-6);
+    }
+
+    #[test]
+    fn effects() {
+        use potion::{self, Effect};
+
+        let mut player = Player::new(Uuid::new_v4(), "Steve".to_string(), Box::new(io::sink()));
+        player.add_effect(Effect { id: potion::HASTE, amplifier: 0, duration: 100 }, 1000);
+        assert_eq!(player.effect(potion::HASTE, 1099).map(|effect| effect.amplifier), Some(0));
+        assert!(player.effect(potion::HASTE, 1100).is_none());
+        player.add_effect(Effect { id: potion::HASTE, amplifier: 1, duration: 100 }, 1050);
+        assert_eq!(player.effect(potion::HASTE, 1100).map(|effect| effect.amplifier), Some(1));
+        assert_eq!(player.effects.len(), 1);
+    }
+
+    #[test]
+    fn abilities() {
+        let mut player = Player::new(Uuid::new_v4(), "Steve".to_string(), Box::new(io::sink()));
+        assert_eq!(player.abilities(), 0);
+        assert!(!player.set_flying(true));
+        player.set_gamemode(GameMode::Creative);
+        assert!(player.set_flying(true));
+        assert_eq!(player.abilities(), INVULNERABLE | FLYING | ALLOW_FLYING | CREATIVE_MODE);
+        player.set_gamemode(GameMode::Survival);
+        assert!(!player.flying());
+        player.set_gamemode(GameMode::Spectator);
+        assert!(player.flying() && !player.set_flying(false));
+    }
+
+    #[test]
+    fn shown_to_others() {
+        let mut player = Player::new(Uuid::new_v4(), "Steve".to_string(), Box::new(io::sink()));
+        player.set_entity_id(7);
+        // Nothing to move before being spawned
+        assert!(player.movement_packets().is_empty());
+        player.set_position([1.5, 64.0, -2.0], true);
+        let spawn = player.spawn_packet();
+        assert_eq!((spawn.entity_id, spawn.position, spawn.current_item), (7, [48, 2048, -64], 0));
+        assert!(player.movement_packets().is_empty());
+        player.set_look(90.0, 0.0, true);
+        assert_eq!(player.movement_packets().len(), 2);
+        assert!(player.movement_packets().is_empty());
+    }
+
+    #[test]
+    fn eating() {
+        let mut player = Player::new(Uuid::new_v4(), "Steve".to_string(), Box::new(io::sink()));
+        // Full players can only eat golden apples
+        assert!(!player.can_eat(297) && player.can_eat(item::GOLDEN_APPLE));
+        player.food = 15;
+        player.saturation = 0.0;
+        assert!(player.can_eat(297) && !player.can_eat(item::BOW));
+        player.eat(8, 0.8);
+        assert_eq!(player.food, 20);
+        assert!((player.saturation - 12.8).abs() < 1e-5);
+        assert!(!player.can_eat(297) && player.can_eat(item::GOLDEN_APPLE));
+        player.set_gamemode(GameMode::Creative);
+        assert!(!player.can_eat(item::GOLDEN_APPLE));
+    }
+}
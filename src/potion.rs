@@ -0,0 +1,212 @@
+//! Potions and brewing.
+//!
+//! Potions are told apart by their damage value: the low 4 bits pick the
+//! effect, bit 5 makes it level II, bit 6 extends it, bit 13 marks potions
+//! which can be drunk and bit 14 splash potions.
+
+use std::collections::HashMap;
+
+use item;
+use types::Slot;
+
+use nbt;
+
+pub const WATER: i16 = 0;
+pub const AWKWARD: i16 = 16;
+pub const THICK: i16 = 32;
+pub const MUNDANE: i16 = 64;
+
+const LEVEL_II: i16 = 0x20;
+const EXTENDED: i16 = 0x40;
+const DRINKABLE: i16 = 0x2000;
+const SPLASH: i16 = 0x4000;
+
+/// Status effect ids.
+pub const HASTE: u8 = 3;
+pub const MINING_FATIGUE: u8 = 4;
+
+pub const NETHER_WART: u16 = 372;
+pub const GLOWSTONE_DUST: u16 = 348;
+pub const REDSTONE: u16 = 331;
+pub const FERMENTED_SPIDER_EYE: u16 = 376;
+pub const GUNPOWDER: u16 = 289;
+
+/// A status effect given by a potion.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Effect {
+    pub id: u8,
+    pub amplifier: u8,
+    /// In ticks.
+    pub duration: i32
+}
+
+/// Whether potion `potion` is thrown rather than drunk.
+pub fn is_splash(potion: i16) -> bool {
+    potion & SPLASH != 0
+}
+
+/// Whether item `id` with the given `damage` can go in the ingredient slot
+/// of a brewing stand.
+pub fn is_ingredient(id: u16, damage: i16) -> bool {
+    match id {
+        NETHER_WART | GLOWSTONE_DUST | REDSTONE | FERMENTED_SPIDER_EYE | GUNPOWDER => true,
+        _ => base_effect(id, damage).is_some()
+    }
+}
+
+/// Effect bits given to an awkward potion by ingredient `id`.
+fn base_effect(id: u16, damage: i16) -> Option<i16> {
+    match (id, damage) {
+        // Ghast tear, regeneration
+        (370, _) => Some(1),
+        // Sugar, swiftness
+        (353, _) => Some(2),
+        // Magma cream, fire resistance
+        (378, _) => Some(3),
+        // Spider eye, poison
+        (375, _) => Some(4),
+        // Glistering melon, healing
+        (382, _) => Some(5),
+        // Golden carrot, night vision
+        (396, _) => Some(6),
+        // Blaze powder, strength
+        (377, _) => Some(9),
+        // Rabbit's foot, leaping
+        (414, _) => Some(11),
+        // Pufferfish, water breathing
+        (349, 3) => Some(13),
+        _ => None
+    }
+}
+
+/// What brewing the potion `potion` with ingredient `id` gives, `None` if
+/// the ingredient has no effect on it.
+pub fn brew(potion: i16, id: u16, damage: i16) -> Option<i16> {
+    let effect = potion & 0xf;
+    let result = match id {
+        GUNPOWDER if potion & SPLASH == 0 && potion & DRINKABLE != 0 => potion & !DRINKABLE | SPLASH,
+        NETHER_WART if potion == WATER => AWKWARD,
+        REDSTONE if potion == WATER => MUNDANE,
+        GLOWSTONE_DUST if potion == WATER => THICK,
+        FERMENTED_SPIDER_EYE if potion == WATER => DRINKABLE | 8,
+        // Instant effects can't last longer
+        REDSTONE if effect != 0 && effect != 5 && effect != 12 => potion & !LEVEL_II | EXTENDED,
+        GLOWSTONE_DUST if effect != 0 && strengthens(effect) => potion & !EXTENDED | LEVEL_II,
+        FERMENTED_SPIDER_EYE if effect != 0 => match corrupt(effect) {
+            Some(corrupted) => potion & !0xf | corrupted,
+            None => return None
+        },
+        _ if potion == AWKWARD => match base_effect(id, damage) {
+            Some(effect) => DRINKABLE | effect,
+            None => return None
+        },
+        _ => return None
+    };
+    if result == potion { None } else { Some(result) }
+}
+
+/// Whether effect bits `effect` come in level II.
+fn strengthens(effect: i16) -> bool {
+    match effect {
+        1 | 2 | 4 | 5 | 9 | 11 | 12 => true,
+        _ => false
+    }
+}
+
+/// Effect bits fermented spider eyes turn `effect` into.
+fn corrupt(effect: i16) -> Option<i16> {
+    match effect {
+        // Swiftness, leaping and fire resistance make slowness
+        2 | 3 | 11 => Some(10),
+        // Healing and poison make harming
+        4 | 5 => Some(12),
+        // Night vision makes invisibility
+        6 => Some(14),
+        // Regeneration and strength make weakness
+        1 | 9 => Some(8),
+        _ => None
+    }
+}
+
+/// Effects of the potion with damage value `potion`.
+pub fn effects(potion: i16) -> Vec<Effect> {
+    let long = |normal: i32, extended: i32| if potion & EXTENDED != 0 { extended } else { normal };
+    let (id, duration) = match potion & 0xf {
+        1 => (10, if potion & LEVEL_II != 0 { 440 } else { long(900, 2400) }),
+        2 => (1, if potion & LEVEL_II != 0 { 1800 } else { long(3600, 9600) }),
+        3 => (12, long(3600, 9600)),
+        4 => (19, if potion & LEVEL_II != 0 { 432 } else { long(900, 2400) }),
+        5 => (6, 1),
+        6 => (16, long(3600, 9600)),
+        8 => (18, long(1800, 4800)),
+        9 => (5, if potion & LEVEL_II != 0 { 1800 } else { long(3600, 9600) }),
+        10 => (2, long(1800, 4800)),
+        11 => (8, if potion & LEVEL_II != 0 { 1800 } else { long(3600, 9600) }),
+        12 => (7, 1),
+        13 => (13, long(3600, 9600)),
+        14 => (14, long(3600, 9600)),
+        _ => return vec![]
+    };
+    let amplifier = if potion & LEVEL_II != 0 { 1 } else { 0 };
+    vec![Effect { id: id, amplifier: amplifier, duration: duration }]
+}
+
+/// A potion item with damage value `potion`, its effects spelled out in
+/// its CustomPotionEffects tag.
+pub fn potion_item(potion: i16) -> Slot {
+    let mut stack = Slot::new(item::POTION, 1, potion);
+    let effects: Vec<nbt::Value> = effects(potion).into_iter().map(|effect| {
+        let mut entries = HashMap::new();
+        entries.insert("Id".to_string(), nbt::Value::Byte(effect.id as i8));
+        entries.insert("Amplifier".to_string(), nbt::Value::Byte(effect.amplifier as i8));
+        entries.insert("Duration".to_string(), nbt::Value::Int(effect.duration));
+        entries.insert("Ambient".to_string(), nbt::Value::Byte(0));
+        entries.insert("ShowParticles".to_string(), nbt::Value::Byte(1));
+        nbt::Value::Compound(entries)
+    }).collect();
+    if !effects.is_empty() {
+        let mut entries = HashMap::new();
+        entries.insert("CustomPotionEffects".to_string(), nbt::Value::List(effects));
+        stack.set_tag_entries(entries);
+    }
+    stack
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use nbt;
+
+    #[test]
+    fn swiftness() {
+        let awkward = brew(WATER, NETHER_WART, 0).unwrap();
+        assert_eq!(awkward, AWKWARD);
+        let swiftness = brew(awkward, 353, 0).unwrap();
+        assert_eq!(swiftness, 8194);
+        assert_eq!(effects(swiftness), vec![Effect { id: 1, amplifier: 0, duration: 3600 }]);
+        let extended = brew(swiftness, REDSTONE, 0).unwrap();
+        assert_eq!(effects(extended)[0].duration, 9600);
+        let strong = brew(extended, GLOWSTONE_DUST, 0).unwrap();
+        assert_eq!(effects(strong), vec![Effect { id: 1, amplifier: 1, duration: 1800 }]);
+        assert_eq!(brew(swiftness, FERMENTED_SPIDER_EYE, 0), Some(8202));
+        assert_eq!(brew(swiftness, GUNPOWDER, 0), Some(16386));
+    }
+
+    #[test]
+    fn no_change() {
+        assert_eq!(brew(WATER, 353, 0), None);
+        assert_eq!(brew(8197, REDSTONE, 0), None);
+        assert!(!is_ingredient(1, 0));
+    }
+
+    #[test]
+    fn item_tag() {
+        let potion = potion_item(8194);
+        match potion.tag_entries().get("CustomPotionEffects") {
+            Some(&nbt::Value::List(ref effects)) => assert_eq!(effects.len(), 1),
+            other => panic!("unexpected tag {:?}", other)
+        }
+        assert!(potion_item(WATER).tag().is_none());
+    }
+}
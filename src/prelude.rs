@@ -0,0 +1,21 @@
+//! What embedding hematite takes, in one import:
+//!
+//! ```ignore
+//! extern crate hematite_server;
+//!
+//! use hematite_server::prelude::*;
+//! ```
+//!
+//! Everything here stays where it is between minor versions. Paths deeper
+//! in the crate are how hematite is put together, and may move.
+
+pub use events::{ChatFilter, Event, EventBus, FilterAction};
+pub use packet::{AnyPacket, PacketRead, PacketWrite, Protocol, Registry};
+pub use packet::play::{clientbound, serverbound};
+pub use permissions::{OpLevels, PermissionProvider};
+pub use player::{Player, PlayerHandle};
+pub use proto::properties::Properties;
+pub use types::{ChatJson, Slot};
+pub use types::consts::{Color, Difficulty, Dimension, GameMode};
+pub use vanilla::{Server, ServerBuilder};
+pub use world::{World, WorldConfig, WorldHandle, WorldRandom};
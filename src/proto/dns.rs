@@ -0,0 +1,258 @@
+//! Server address resolution, like the vanilla client does it.
+//!
+//! A server given without a port may point to where it really runs with a
+//! `_minecraft._tcp` SRV record, looked up here with a bare-bones DNS query
+//! to the system's nameserver before falling back to the usual host
+//! resolution.
+//!
+//! Reference: https://tools.ietf.org/html/rfc2782
+
+use std::fs::File;
+use std::io::ErrorKind::InvalidData;
+use std::io::prelude::*;
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+use byteorder::{BigEndian, ByteOrder};
+use rand;
+
+/// Port servers listen on unless told otherwise.
+pub const DEFAULT_PORT: u16 = 25565;
+
+/// How long a nameserver gets to answer before the SRV lookup is given up.
+pub const TIMEOUT_MS: u64 = 3000;
+
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+
+/// A `_minecraft._tcp` SRV record, where the server really runs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Srv {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: String
+}
+
+/// Where to connect to for the server at `host`, with `port` if one was
+/// given: the hostname and port to greet the server with in the handshake,
+/// and the addresses they resolve to.
+///
+/// Like vanilla, SRV records are only looked up without a port or an IP
+/// address, and failing to find one isn't an error.
+pub fn resolve(host: &str, port: Option<u16>) -> io::Result<(String, u16, Vec<SocketAddr>)> {
+    let (host, port) = match port {
+        Some(port) => (host.to_string(), port),
+        None if host.parse::<IpAddr>().is_ok() => (host.to_string(), DEFAULT_PORT),
+        None => match lookup_srv(host) {
+            Ok(Some(srv)) => {
+                debug!("{} is served from {}:{}", host, srv.target, srv.port);
+                (srv.target, srv.port)
+            }
+            Ok(None) => (host.to_string(), DEFAULT_PORT),
+            Err(err) => {
+                debug!("couldn't look up SRV record of {}: {}", host, err);
+                (host.to_string(), DEFAULT_PORT)
+            }
+        }
+    };
+    let addrs: Vec<_> = try!((&host[..], port).to_socket_addrs()).collect();
+    Ok((host, port, addrs))
+}
+
+/// Connects to the server at `host`, trying each address it resolves to in
+/// turn, returning the stream along with the hostname and port to send in
+/// the handshake.
+pub fn connect(host: &str, port: Option<u16>) -> io::Result<(TcpStream, String, u16)> {
+    let (host, port, addrs) = try!(resolve(host, port));
+    let stream = try!(TcpStream::connect(&addrs[..]));
+    Ok((stream, host, port))
+}
+
+/// The `_minecraft._tcp` SRV record of `domain`, the one to try first if it
+/// has several, `None` if it has none.
+pub fn lookup_srv(domain: &str) -> io::Result<Option<Srv>> {
+    let nameserver = match try!(nameserver()) {
+        Some(nameserver) => nameserver,
+        None => return Ok(None)
+    };
+    let socket = try!(UdpSocket::bind(if nameserver.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }));
+    try!(socket.set_read_timeout(Some(Duration::from_millis(TIMEOUT_MS))));
+    try!(socket.connect(nameserver));
+    let id = rand::random::<u16>();
+    try!(socket.send(&try!(srv_query(id, &format!("_minecraft._tcp.{}", domain)))));
+    let mut buf = [0; 512];
+    let len = try!(socket.recv(&mut buf));
+    let mut records = try!(parse_srv_response(id, &buf[..len]));
+    // Lowest priority first, then heaviest
+    records.sort_by(|a, b| a.priority.cmp(&b.priority).then(b.weight.cmp(&a.weight)));
+    Ok(records.into_iter().next())
+}
+
+/// The first nameserver in `/etc/resolv.conf`, if any.
+fn nameserver() -> io::Result<Option<SocketAddr>> {
+    let mut conf = String::new();
+    match File::open("/etc/resolv.conf") {
+        Ok(mut file) => { try!(file.read_to_string(&mut conf)); }
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err)
+    }
+    Ok(conf.lines().filter_map(|line| {
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("nameserver"), Some(addr)) => addr.parse::<IpAddr>().ok().map(|ip| SocketAddr::new(ip, 53)),
+            _ => None
+        }
+    }).next())
+}
+
+/// A recursive query for the SRV records of `name`.
+fn srv_query(id: u16, name: &str) -> io::Result<Vec<u8>> {
+    let mut query = vec![0; 12];
+    BigEndian::write_u16(&mut query[0..2], id);
+    // Recursion desired, one question
+    BigEndian::write_u16(&mut query[2..4], 0x0100);
+    BigEndian::write_u16(&mut query[4..6], 1);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("invalid domain name {:?}", name)[..]));
+        }
+        query.push(label.len() as u8);
+        query.extend(label.bytes());
+    }
+    query.push(0);
+    let mut tail = [0; 4];
+    BigEndian::write_u16(&mut tail[0..2], TYPE_SRV);
+    BigEndian::write_u16(&mut tail[2..4], CLASS_IN);
+    query.extend(tail.iter());
+    Ok(query)
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(InvalidData, &format!("invalid DNS response: {}", what)[..])
+}
+
+/// Reads a `u16` at `pos` in `msg`.
+fn read_u16(msg: &[u8], pos: usize) -> io::Result<u16> {
+    if pos + 2 > msg.len() {
+        return Err(invalid("truncated"));
+    }
+    Ok(BigEndian::read_u16(&msg[pos..pos + 2]))
+}
+
+/// Reads the possibly compressed name at `pos` in `msg`, returning it and
+/// where it ends.
+fn read_name(msg: &[u8], pos: usize) -> io::Result<(String, usize)> {
+    let mut labels = vec![];
+    let (mut pos, mut end) = (pos, None);
+    // Names can't take more steps than there are bytes, unless pointers loop
+    for _ in 0..msg.len() {
+        let len = *try!(msg.get(pos).ok_or(invalid("truncated"))) as usize;
+        if len == 0 {
+            return Ok((labels.join("."), end.unwrap_or(pos + 1)));
+        }
+        if len & 0xc0 == 0xc0 {
+            let pointer = (try!(read_u16(msg, pos)) & 0x3fff) as usize;
+            if end.is_none() {
+                end = Some(pos + 2);
+            }
+            pos = pointer;
+            continue;
+        }
+        let label = try!(msg.get(pos + 1..pos + 1 + len).ok_or(invalid("truncated")));
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        pos += 1 + len;
+    }
+    Err(invalid("name pointer loop"))
+}
+
+/// The SRV records answering query `id` in `msg`.
+fn parse_srv_response(id: u16, msg: &[u8]) -> io::Result<Vec<Srv>> {
+    if try!(read_u16(msg, 0)) != id {
+        return Err(invalid("answer to another query"));
+    }
+    let flags = try!(read_u16(msg, 2));
+    match flags & 0xf {
+        0 => {}
+        // No such domain
+        3 => return Ok(vec![]),
+        rcode => return Err(invalid(&format!("error code {}", rcode)))
+    }
+    let questions = try!(read_u16(msg, 4));
+    let answers = try!(read_u16(msg, 6));
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = try!(read_name(msg, pos)).1 + 4;
+    }
+    let mut records = vec![];
+    for _ in 0..answers {
+        pos = try!(read_name(msg, pos)).1;
+        let kind = try!(read_u16(msg, pos));
+        let class = try!(read_u16(msg, pos + 2));
+        let len = try!(read_u16(msg, pos + 8)) as usize;
+        let data = pos + 10;
+        if data + len > msg.len() {
+            return Err(invalid("truncated"));
+        }
+        if kind == TYPE_SRV && class == CLASS_IN {
+            records.push(Srv {
+                priority: try!(read_u16(msg, data)),
+                weight: try!(read_u16(msg, data + 2)),
+                port: try!(read_u16(msg, data + 4)),
+                target: try!(read_name(msg, data + 6)).0
+            });
+        }
+        pos = data + len;
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::{parse_srv_response, srv_query};
+
+    #[test]
+    fn srv_answers() {
+        let query = srv_query(0x1234, "_minecraft._tcp.example.com").unwrap();
+        assert_eq!(&query[..6], &[0x12, 0x34, 0x01, 0x00, 0x00, 0x01]);
+        assert_eq!(&query[12..23], b"\x0a_minecraft");
+        assert_eq!(&query[query.len() - 5..], &[0, 0, 33, 0, 1]);
+
+        let mut response = query.clone();
+        response[2] = 0x81;
+        response[3] = 0x80;
+        response[7] = 2;
+        // Answers naming the question by pointer, the second one's target
+        // ending in example.com from the question too
+        response.extend(&[0xc0, 12, 0, 33, 0, 1, 0, 0, 1, 0x2c, 0, 22, 0, 10, 0, 5, 0x63, 0xdd]);
+        response.extend(b"\x02mc\x07example\x03com\x00");
+        response.extend(&[0xc0, 12, 0, 33, 0, 1, 0, 0, 1, 0x2c, 0, 13, 0, 5, 0, 0, 0x63, 0xde]);
+        response.extend(b"\x04play\xc0\x1c");
+        let records = parse_srv_response(0x1234, &response).unwrap();
+        assert_eq!(records, vec![
+            Srv { priority: 10, weight: 5, port: 25565, target: "mc.example.com".to_string() },
+            Srv { priority: 5, weight: 0, port: 25566, target: "play.example.com".to_string() }
+        ]);
+
+        // Not ours, unknown domains and garbage
+        assert!(parse_srv_response(0x4321, &response).is_err());
+        response[3] = 0x83;
+        assert_eq!(parse_srv_response(0x1234, &response).unwrap(), vec![]);
+        response[3] = 0x80;
+        response.truncate(response.len() - 3);
+        assert!(parse_srv_response(0x1234, &response).is_err());
+        assert!(srv_query(1, "bad..name").is_err());
+    }
+
+    #[test]
+    fn addresses_skip_srv() {
+        let (host, port, addrs) = resolve("127.0.0.1", None).unwrap();
+        assert_eq!((&host[..], port), ("127.0.0.1", DEFAULT_PORT));
+        assert_eq!(addrs, vec!["127.0.0.1:25565".parse().unwrap()]);
+        let (_, port, addrs) = resolve("::1", Some(25566)).unwrap();
+        assert_eq!(port, 25566);
+        assert_eq!(addrs, vec!["[::1]:25566".parse().unwrap()]);
+    }
+}
@@ -0,0 +1,196 @@
+//! Server icons shown in the server list.
+//!
+//! Icons are 64x64 PNG files dropped in a directory, sent in turn, one per
+//! status request. The directory is looked at again on each request, so
+//! icons can be added, changed or removed while the server runs.
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use byteorder::{BigEndian, ReadBytesExt};
+use rustc_serialize::base64::{ToBase64, STANDARD};
+
+/// Directory icons are read from.
+pub const ICONS_DIR: &'static str = "icons";
+
+/// Icon used when the icons directory is missing or has no icons.
+pub const DEFAULT_ICON: &'static str = "assets/favicon.png";
+
+/// Width and height icons must have.
+pub const SIZE: u32 = 64;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+/// Width and height of the PNG image `data`, fails if it isn't one.
+pub fn png_size(data: &[u8]) -> io::Result<(u32, u32)> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "not a PNG image");
+    if data.len() < 24 || data[..8] != PNG_SIGNATURE || &data[12..16] != b"IHDR" {
+        return Err(invalid());
+    }
+    let mut header = Cursor::new(&data[16..24]);
+    let width = try!(header.read_u32::<BigEndian>());
+    let height = try!(header.read_u32::<BigEndian>());
+    Ok((width, height))
+}
+
+/// PNG image `data` as sent in the status response.
+pub fn data_url(data: &[u8]) -> String {
+    format!("data:image/png;base64,{}", data.to_base64(STANDARD))
+}
+
+/// Files of an icons directory as last read: names, sizes and modification
+/// times, which change whenever an icon does.
+type Listing = Vec<(PathBuf, u64, Option<SystemTime>)>;
+
+struct State {
+    listing: Option<Listing>,
+    /// Data URLs of the icons read.
+    icons: Vec<String>,
+    next: usize
+}
+
+/// Icons of a directory, sent in turn.
+pub struct Favicons {
+    dir: PathBuf,
+    fallback: PathBuf,
+    state: Mutex<State>
+}
+
+impl Favicons {
+    /// Icons in `dir`, or the one at `fallback` if there are none.
+    pub fn new<P: Into<PathBuf>, Q: Into<PathBuf>>(dir: P, fallback: Q) -> Favicons {
+        Favicons {
+            dir: dir.into(),
+            fallback: fallback.into(),
+            state: Mutex::new(State { listing: None, icons: vec![], next: 0 })
+        }
+    }
+
+    /// The next icon to send, reading the directory again first if anything
+    /// changed in it. `None` if there's no icon at all.
+    pub fn next(&self) -> Option<String> {
+        let mut state = self.state.lock().unwrap();
+        let listing = listing(&self.dir);
+        if state.listing.as_ref() != Some(&listing) {
+            state.icons = self.read(&listing);
+            state.listing = Some(listing);
+            state.next = 0;
+        }
+        if state.icons.is_empty() {
+            return None;
+        }
+        let index = state.next % state.icons.len();
+        state.next = index + 1;
+        Some(state.icons[index].clone())
+    }
+
+    /// Icons of `listing`, the fallback one if there are none. Files which
+    /// aren't 64x64 PNG images are skipped.
+    fn read(&self, listing: &Listing) -> Vec<String> {
+        let mut icons = vec![];
+        for &(ref path, _, _) in listing {
+            match read_icon(path) {
+                Ok(icon) => icons.push(icon),
+                Err(err) => warn!("skipping icon {}: {}", path.display(), err)
+            }
+        }
+        if icons.is_empty() {
+            match read_icon(&self.fallback) {
+                Ok(icon) => icons.push(icon),
+                Err(err) => debug!("no server icon at {}: {}", self.fallback.display(), err)
+            }
+        }
+        info!("loaded {} server icon(s)", icons.len());
+        icons
+    }
+}
+
+/// The PNG files of `dir`, sorted by name, none if it can't be read.
+fn listing(dir: &Path) -> Listing {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![]
+    };
+    let mut listing: Listing = entries.filter_map(|entry| entry.ok()).filter_map(|entry| {
+        let path = entry.path();
+        if path.extension().map_or(true, |extension| extension != "png") {
+            return None;
+        }
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => return None
+        };
+        if !metadata.is_file() {
+            return None;
+        }
+        Some((path, metadata.len(), metadata.modified().ok()))
+    }).collect();
+    listing.sort_by(|a, b| a.0.cmp(&b.0));
+    listing
+}
+
+fn read_icon(path: &Path) -> io::Result<String> {
+    let mut data = vec![];
+    try!(try!(File::open(path)).read_to_end(&mut data));
+    let (width, height) = try!(png_size(&data));
+    if width != SIZE || height != SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("icon is {}x{}, not {}x{}", width, height, SIZE, SIZE)));
+    }
+    Ok(data_url(&data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+
+    use byteorder::{BigEndian, WriteBytesExt};
+
+    /// The start of a PNG image `width` by `height`, `tag` apart, enough
+    /// for the checks done.
+    fn png(width: u32, height: u32, tag: u8) -> Vec<u8> {
+        let mut data = PNG_SIGNATURE.to_vec();
+        data.write_u32::<BigEndian>(13).unwrap();
+        data.extend_from_slice(b"IHDR");
+        data.write_u32::<BigEndian>(width).unwrap();
+        data.write_u32::<BigEndian>(height).unwrap();
+        data.push(tag);
+        data
+    }
+
+    #[test]
+    fn rotation() {
+        let dir = env::temp_dir().join("hematite_favicons");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, data: Vec<u8>| File::create(dir.join(name)).unwrap().write_all(&data).unwrap();
+        write("a.png", png(64, 64, 1));
+        write("b.png", png(64, 64, 2));
+        write("big.png", png(128, 128, 3));
+        write("notes.txt", vec![]);
+
+        let favicons = Favicons::new(dir.clone(), dir.join("missing.png"));
+        let (a, b) = (data_url(&png(64, 64, 1)), data_url(&png(64, 64, 2)));
+        assert_eq!(favicons.next(), Some(a.clone()));
+        assert_eq!(favicons.next(), Some(b.clone()));
+        assert_eq!(favicons.next(), Some(a.clone()));
+        // Picked up without restarting
+        write("c.png", png(64, 64, 4));
+        let icons: Vec<_> = (0..3).map(|_| favicons.next().unwrap()).collect();
+        assert!(icons.contains(&data_url(&png(64, 64, 4))));
+        fs::remove_file(dir.join("a.png")).unwrap();
+        fs::remove_file(dir.join("b.png")).unwrap();
+        fs::remove_file(dir.join("c.png")).unwrap();
+        assert_eq!(favicons.next(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(png_size(b"GIF89a").is_err());
+    }
+}
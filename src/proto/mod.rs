@@ -1,4 +1,9 @@
-//! MC Protocols.
-
-pub mod properties;
-pub mod slp;
\ No newline at end of file
+//! MC Protocols.
+
+pub mod dns;
+pub mod favicon;
+pub mod mutes;
+pub mod ops;
+pub mod properties;
+#[cfg(feature = "server")] pub mod session;
+pub mod slp;
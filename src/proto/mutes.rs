@@ -0,0 +1,103 @@
+//! Parse and write muted.json files, listing players who can't chat.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use rustc_serialize::json::{Json, ToJson};
+
+/// Muted players, saved to a file whenever they change.
+pub struct MuteList {
+    /// `None` when mutes are only kept in memory.
+    path: Option<PathBuf>,
+    /// Lowercase player names.
+    names: Mutex<BTreeSet<String>>
+}
+
+impl MuteList {
+    /// Mutes listed in the file at `path`, which doesn't need to exist yet.
+    pub fn load(path: &Path) -> io::Result<MuteList> {
+        let names = match File::open(path) {
+            Ok(file) => try!(parse(&mut BufReader::new(file))),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => BTreeSet::new(),
+            Err(err) => return Err(err)
+        };
+        Ok(MuteList { path: Some(path.to_path_buf()), names: Mutex::new(names) })
+    }
+
+    /// An empty list which is never saved.
+    pub fn in_memory() -> MuteList {
+        MuteList { path: None, names: Mutex::new(BTreeSet::new()) }
+    }
+
+    pub fn is_muted(&self, name: &str) -> bool {
+        self.names.lock().unwrap().contains(&name.to_lowercase())
+    }
+
+    /// Mutes or unmutes player `name`, saving the list when it changed.
+    pub fn set_muted(&self, name: &str, muted: bool) -> io::Result<()> {
+        let mut names = self.names.lock().unwrap();
+        let changed = if muted { names.insert(name.to_lowercase()) } else { names.remove(&name.to_lowercase()) };
+        match self.path {
+            Some(ref path) if changed => write(&mut try!(File::create(path)), &names),
+            _ => Ok(())
+        }
+    }
+}
+
+/// Lowercase names from muted.json content, a list of `{"name": ...}`.
+pub fn parse(src: &mut Read) -> io::Result<BTreeSet<String>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let json = try!(Json::from_reader(src).map_err(|_| invalid("invalid muted.json")));
+    let entries = match json {
+        Json::Array(entries) => entries,
+        _ => return Err(invalid("muted.json isn't a list"))
+    };
+    let mut names = BTreeSet::new();
+    for entry in entries {
+        match entry.find("name").and_then(|name| name.as_string()) {
+            Some(name) => names.insert(name.to_lowercase()),
+            None => return Err(invalid("muted.json entry without a name"))
+        };
+    }
+    Ok(names)
+}
+
+pub fn write(dst: &mut Write, names: &BTreeSet<String>) -> io::Result<()> {
+    let entries: Vec<Json> = names.iter().map(|name| {
+        let mut entry = BTreeMap::new();
+        entry.insert("name".to_string(), name.to_json());
+        Json::Object(entry)
+    }).collect();
+    write!(dst, "{}", Json::Array(entries).pretty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeSet;
+    use std::io;
+
+    #[test]
+    fn roundtrip() {
+        let names: BTreeSet<String> = vec!["notch".to_string(), "jeb_".to_string()].into_iter().collect();
+        let mut dst = vec![];
+        write(&mut dst, &names).unwrap();
+        assert_eq!(parse(&mut io::Cursor::new(dst)).unwrap(), names);
+        let json = br#"[{"name": "Dinnerbone"}]"#;
+        assert!(parse(&mut io::Cursor::new(&json[..])).unwrap().contains("dinnerbone"));
+        assert!(parse(&mut io::Cursor::new(&b"[{}]"[..])).is_err());
+    }
+
+    #[test]
+    fn in_memory() {
+        let mutes = MuteList::in_memory();
+        mutes.set_muted("Notch", true).unwrap();
+        assert!(mutes.is_muted("notch"));
+        mutes.set_muted("notch", false).unwrap();
+        assert!(!mutes.is_muted("Notch"));
+    }
+}
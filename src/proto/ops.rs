@@ -0,0 +1,52 @@
+//! Parse ops.json files, listing server operators.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use rustc_serialize::json::Json;
+
+/// Op levels from the ops.json file at `path`, by lowercase player name.
+pub fn load(path: &Path) -> io::Result<HashMap<String, i32>> {
+    parse(&mut BufReader::new(try!(File::open(path))))
+}
+
+/// Op levels from vanilla ops.json content, by lowercase player name.
+pub fn parse(src: &mut Read) -> io::Result<HashMap<String, i32>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let json = try!(Json::from_reader(src).map_err(|_| invalid("invalid ops.json")));
+    let entries = match json {
+        Json::Array(entries) => entries,
+        _ => return Err(invalid("ops.json isn't a list"))
+    };
+    let mut ops = HashMap::new();
+    for entry in entries {
+        let name = match entry.find("name").and_then(|name| name.as_string()) {
+            Some(name) => name.to_lowercase(),
+            None => return Err(invalid("ops.json entry without a name"))
+        };
+        let level = entry.find("level").and_then(|level| level.as_i64()).unwrap_or(4);
+        ops.insert(name, level as i32);
+    }
+    Ok(ops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    #[test]
+    fn vanilla_ops() {
+        let json = br#"[
+            {"uuid": "069a79f4-44e9-4726-a5be-fca90e38aaf5", "name": "Notch", "level": 4},
+            {"uuid": "853c80ef-3c37-49fd-aa49-938b674adae6", "name": "jeb_", "level": 2}
+        ]"#;
+        let ops = parse(&mut io::Cursor::new(&json[..])).unwrap();
+        assert_eq!(ops.get("notch"), Some(&4));
+        assert_eq!(ops.get("jeb_"), Some(&2));
+        assert!(parse(&mut io::Cursor::new(&b"{}"[..])).is_err());
+    }
+}
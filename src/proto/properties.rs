@@ -162,9 +162,14 @@ macro_rules! server_properties_impl {
 }
 
 server_properties_impl! {
+    { afk_list_name, "afk-list-name", String, "%s (AFK)".to_string() }
+    { afk_timeout, "afk-timeout", i32, 0 }
     { allow_flight, "allow-flight", bool, false }
     { allow_nether, "allow-nether", bool, true }
     { announce_player_achievements, "announce-player-achievements", bool, true }
+    { backup_directory, "backup-directory", String, "backups".to_string() }
+    { backup_interval, "backup-interval", i32, 0 }
+    { backups_kept, "backups-kept", i32, 10 }
     { difficulty, "difficulty", i32, 1 }
     { enable_query, "enable-query", bool, false }
     { enable_rcon, "enable-rcon", bool, false }
@@ -174,11 +179,19 @@ server_properties_impl! {
     { generate_structures, "generate-structures", bool, true }
     { generator_settings, "generator-settings", String, "".to_string() }
     { hardcore, "hardcore", bool, false }
+    { join_message, "join-message", String, "vanilla".to_string() }
+    { keep_alive_interval, "keep-alive-interval", i32, 15 }
+    { keep_alive_timeout, "keep-alive-timeout", i32, 30 }
     { level_name, "level-name", String, "world".to_string() }
     { level_seed, "level-seed", String, "".to_string() }
+    { level_storage, "level-storage", String, "disk".to_string() }
     { level_type, "level-type", String, "DEFAULT".to_string() }
+    { login_timeout, "login-timeout", i32, 30 }
     { max_build_height, "max-build-height", i32, 256 }
+    { max_connections, "max-connections", i32, 0 }
+    { max_pending_connections, "max-pending-connections", i32, 64 }
     { max_players, "max-players", i32, 20 }
+    { max_status_connections_per_ip, "max-status-connections-per-ip", i32, 4 }
     { max_tick_time, "max-tick-time", i32, 60000 }
     { max_world_size, "max-world-size", i32, 29999984 }
     { motd, "motd", String, "A Minecraft Server".to_string() }
@@ -188,17 +201,27 @@ server_properties_impl! {
     { player_idle_timeout, "player-idle-timeout", i32, 0 }
     { pvp, "pvp", bool, true }
     { query_port, "query.port", i32, 25565 }
+    { quit_message, "quit-message", String, "vanilla".to_string() }
+    { rate_limit_chat, "rate-limit-chat", i32, 20 }
+    { rate_limit_movement, "rate-limit-movement", i32, 60 }
     { rcon_password, "rcon.password", String, "".to_string() }
     { rcon_port, "rcon.port", i32, 25575 }
     { resource_pack, "resource-pack", String, "".to_string() }
     { resource_pack_hash, "resource-pack-hash", String, "".to_string() }
+    { server_brand, "server-brand", String, "hematite".to_string() }
     { server_ip, "server-ip", String, "".to_string() }
     { server_port, "server-port", u16, 25565 }
+    { simulation_distance, "simulation-distance", i32, 10 }
+    { skip_unknown_packets, "skip-unknown-packets", bool, true }
     { snooper_enabled, "snooper-enabled", bool, true }
     { spawn_animals, "spawn-animals", bool, true }
     { spawn_monsters, "spawn-monsters", bool, true }
     { spawn_npcs, "spawn-npcs", bool, true }
     { spawn_protection, "spawn-protection", i32, 16 }
+    { status_sample, "status-sample", String, "random".to_string() }
+    { status_sample_messages, "status-sample-messages", String, "".to_string() }
+    { status_sample_size, "status-sample-size", i32, 12 }
+    { status_timeout, "status-timeout", i32, 5 }
     { use_native_transport, "use-native-transport", bool, true }
     { view_distance, "view-distance", i32, 10 }
     { white_list, "white-list", bool, false }
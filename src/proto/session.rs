@@ -0,0 +1,168 @@
+//! Asking Mojang's session server whether players logging in are who they
+//! say, for online mode.
+//!
+//! Before sending the shared secret, the client tells the session server
+//! it's joining the server with the hash from `crypto::server_hash`. The
+//! server then asks whether the player joined with that same hash, getting
+//! their profile back if so.
+//!
+//! Reference: http://wiki.vg/Protocol_Encryption#Server
+
+use std::io::ErrorKind::InvalidData;
+use std::io::prelude::*;
+use std::io;
+use std::net::TcpStream;
+use std::str;
+use std::time::Duration;
+
+use openssl::ssl::{SslConnector, SslMethod};
+use rustc_serialize::json::Json;
+use uuid::Uuid;
+
+use types::ProfileProperty;
+
+pub const HOST: &'static str = "sessionserver.mojang.com";
+
+/// How long the session server gets to answer.
+pub const TIMEOUT_MS: u64 = 10000;
+
+/// A player's profile, as the session server has it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub id: Uuid,
+    pub name: String,
+    /// Their skin and cape, signed.
+    pub properties: Vec<ProfileProperty>
+}
+
+/// The profile of player `name` if they joined the server whose hash is
+/// `server_hash`, `None` if they didn't.
+pub fn has_joined(name: &str, server_hash: &str) -> io::Result<Option<Profile>> {
+    let path = format!("/session/minecraft/hasJoined?username={}&serverId={}", encode(name), encode(server_hash));
+    let response = try!(get(HOST, &path));
+    parse_response(&response)
+}
+
+/// Percent-encodes `value` for a query string.
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for &byte in value.as_bytes() {
+        match byte {
+            b'A'...b'Z' | b'a'...b'z' | b'0'...b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte))
+        }
+    }
+    encoded
+}
+
+/// The whole response to an HTTPS GET of `path` on `host`.
+fn get(host: &str, path: &str) -> io::Result<Vec<u8>> {
+    let stream = try!(TcpStream::connect((host, 443)));
+    let timeout = Some(Duration::from_millis(TIMEOUT_MS));
+    try!(stream.set_read_timeout(timeout));
+    try!(stream.set_write_timeout(timeout));
+    let connector = try!(SslConnector::builder(SslMethod::tls()).map_err(|err| io::Error::new(io::ErrorKind::Other, err))).build();
+    let mut stream = try!(connector.connect(host, stream).map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string())));
+    try!(write!(stream, "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: hematite\r\nConnection: close\r\n\r\n", path, host));
+    let mut response = vec![];
+    match stream.read_to_end(&mut response) {
+        Ok(_) => Ok(response),
+        // Servers may hang up without closing TLS, the response being all
+        // there anyway
+        Err(err) => match split(&response) {
+            Some((head, body)) if content_length(head).map_or(false, |len| body.len() >= len) => Ok(response),
+            _ => Err(err)
+        }
+    }
+}
+
+/// Headers and body of HTTP response `response`.
+fn split(response: &[u8]) -> Option<(&str, &[u8])> {
+    let end = match response.windows(4).position(|window| window == b"\r\n\r\n") {
+        Some(end) => end,
+        None => return None
+    };
+    str::from_utf8(&response[..end]).ok().map(|head| (head, &response[end + 4..]))
+}
+
+fn content_length(head: &str) -> Option<usize> {
+    head.lines().skip(1).filter_map(|line| {
+        let mut parts = line.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) if name.trim().eq_ignore_ascii_case("content-length") => value.trim().parse().ok(),
+            _ => None
+        }
+    }).next()
+}
+
+/// Reads the session server's answer: a profile when it's 200, nothing to
+/// say when it's 204.
+fn parse_response(response: &[u8]) -> io::Result<Option<Profile>> {
+    let (head, body) = try!(split(response).ok_or(io::Error::new(InvalidData, "session server sent a malformed response")));
+    let status = head.lines().next().and_then(|line| line.split(' ').nth(1)).and_then(|status| status.parse::<u16>().ok());
+    match status {
+        Some(200) => {
+            let body = try!(str::from_utf8(body).map_err(|_| io::Error::new(InvalidData, "session server sent invalid UTF-8")));
+            parse_profile(body).map(Some)
+        }
+        Some(204) => Ok(None),
+        Some(status) => Err(io::Error::new(io::ErrorKind::Other, format!("session server answered {}", status))),
+        None => Err(io::Error::new(InvalidData, "session server sent a malformed status line"))
+    }
+}
+
+fn parse_profile(body: &str) -> io::Result<Profile> {
+    fn invalid() -> io::Error {
+        io::Error::new(InvalidData, "session server sent an invalid profile")
+    }
+
+    let json = try!(Json::from_str(body).map_err(|_| invalid()));
+    let id = try!(json.find("id").and_then(|id| id.as_string()).and_then(|id| Uuid::parse_str(id).ok()).ok_or(invalid()));
+    let name = try!(json.find("name").and_then(|name| name.as_string()).ok_or(invalid()));
+    let mut properties = vec![];
+    if let Some(list) = json.find("properties").and_then(|properties| properties.as_array()) {
+        for property in list {
+            let name = try!(property.find("name").and_then(|name| name.as_string()).ok_or(invalid()));
+            let value = try!(property.find("value").and_then(|value| value.as_string()).ok_or(invalid()));
+            let signature = property.find("signature").and_then(|signature| signature.as_string());
+            properties.push(ProfileProperty {
+                name: name.to_string(),
+                value: value.to_string(),
+                signature: signature.map(|signature| signature.to_string())
+            });
+        }
+    }
+    Ok(Profile { id: id, name: name.to_string(), properties: properties })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use types::ProfileProperty;
+
+    use uuid::Uuid;
+
+    #[test]
+    fn query() {
+        assert_eq!(encode("jeb_"), "jeb_");
+        assert_eq!(encode("-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"), "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1");
+        assert_eq!(encode("a b&c"), "a%20b%26c");
+    }
+
+    #[test]
+    fn responses() {
+        let joined = b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n\
+            {\"id\":\"069a79f444e94726a5befca90e38aaf5\",\"name\":\"Notch\",\
+            \"properties\":[{\"name\":\"textures\",\"value\":\"e30=\",\"signature\":\"c2ln\"}]}";
+        assert_eq!(parse_response(joined).unwrap(), Some(Profile {
+            id: Uuid::parse_str("069a79f4-44e9-4726-a5be-fca90e38aaf5").unwrap(),
+            name: "Notch".to_string(),
+            properties: vec![ProfileProperty { name: "textures".to_string(), value: "e30=".to_string(), signature: Some("c2ln".to_string()) }]
+        }));
+        assert_eq!(parse_response(b"HTTP/1.1 204 No Content\r\n\r\n").unwrap(), None);
+        assert!(parse_response(b"HTTP/1.1 500 Internal Server Error\r\n\r\n").is_err());
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\n\r\n{\"name\":\"Notch\"}").is_err());
+        assert!(parse_response(b"garbage").is_err());
+    }
+}
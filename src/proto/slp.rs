@@ -2,20 +2,25 @@
 //!
 //! Reference: http://wiki.vg/Server_List_Ping
 
-use std::fs::File;
 use std::io::ErrorKind::InvalidInput;
 use std::io::prelude::*;
 use std::io;
 use std::net::TcpStream;
 use std::ops::Sub; // Sub for Timespec
-use std::path::Path;
 
 use consts;
 use packet::{PacketRead, PacketWrite, Protocol};
+use proto::dns;
+use proto::properties::Properties;
+use types::hyphenated;
 
-use rustc_serialize::base64::{ToBase64, STANDARD};
+use rand::Rng;
 use rustc_serialize::json;
 use time;
+use uuid::Uuid;
+
+/// Id given to sample entries which aren't players.
+pub const NO_ID: &'static str = "00000000-0000-0000-0000-000000000000";
 
 #[derive(Debug, RustcDecodable, RustcEncodable)]
 pub struct Description {
@@ -35,6 +40,51 @@ pub struct Sample {
     pub id: String,
 }
 
+/// What the list shown when hovering the player count holds, as set by the
+/// status-sample properties.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SampleMode {
+    /// Nothing, only the count is shown.
+    Hidden,
+    /// At most this many of the players online, picked at random like
+    /// vanilla does.
+    Random(usize),
+    /// Lines of text instead of players.
+    Custom(Vec<String>)
+}
+
+impl SampleMode {
+    /// Mode set by status-sample: `random`, `hidden` or `custom`, which
+    /// shows status-sample-messages split on `|`. Unknown modes hide the
+    /// sample, erring on the side of privacy.
+    pub fn of(props: &Properties) -> SampleMode {
+        match &props.status_sample[..] {
+            "random" => SampleMode::Random(props.status_sample_size.max(0) as usize),
+            "custom" => SampleMode::Custom(props.status_sample_messages.split('|').map(|line| line.to_string()).collect()),
+            "hidden" => SampleMode::Hidden,
+            mode => {
+                warn!("unknown status-sample {:?}, hiding the sample", mode);
+                SampleMode::Hidden
+            }
+        }
+    }
+
+    /// The sample to send with players `online` given by name and id,
+    /// `None` if there's nothing to show.
+    pub fn sample<R: Rng>(&self, online: &[(String, Uuid)], rng: &mut R) -> Option<Vec<Sample>> {
+        let sample: Vec<_> = match *self {
+            SampleMode::Hidden => vec![],
+            SampleMode::Random(size) => {
+                let mut online = online.to_vec();
+                rng.shuffle(&mut online);
+                online.into_iter().take(size).map(|(name, id)| Sample { name: name, id: hyphenated(&id) }).collect()
+            }
+            SampleMode::Custom(ref lines) => lines.iter().map(|line| Sample { name: line.clone(), id: NO_ID.to_string() }).collect()
+        };
+        if sample.is_empty() { None } else { Some(sample) }
+    }
+}
+
 #[derive(Debug, RustcDecodable, RustcEncodable)]
 pub struct Version {
     pub name: String,
@@ -71,8 +121,9 @@ impl Protocol for Response {
 // FIXME(toqueteos): This is yelling to be a method of a Server struct or
 // something more useful. We need the Handshake's `next_state` field in order
 // to perform login for a player.
-/// Server-side Server List response
-pub fn response(stream: &mut TcpStream) -> io::Result<()> {
+/// Server-side Server List response, showing message `motd`, telling about
+/// `players` and showing icon `favicon`, a data URL.
+pub fn response(stream: &mut TcpStream, motd: &str, players: Players, favicon: Option<String>) -> io::Result<()> {
     use packet::status::serverbound::Packet::{self, StatusRequest};
     use packet::status::clientbound::StatusResponse;
 
@@ -80,10 +131,6 @@ pub fn response(stream: &mut TcpStream) -> io::Result<()> {
     match try!(Packet::read(stream)) {
         StatusRequest(_) => {
             // S->C: Status Response packet
-            let mut file = try!(File::open(&Path::new("assets/favicon.png")));
-            let mut contents = Vec::new();
-            try!(file.read_to_end(&mut contents));
-            let favicon = contents.to_base64(STANDARD);
             // FIXME(toqueteos): Micro-optimization? We could totally drop JSON
             // encoding and just replace player values (online & max) with format! all
             // other values are static.
@@ -92,15 +139,9 @@ pub fn response(stream: &mut TcpStream) -> io::Result<()> {
                     name: consts::VERSION.to_string(),
                     protocol: consts::PROTO_VERSION,
                 },
-                players: Players{
-                    // FIXME(toqueteos): This is value should be a internal counter of server
-                    online: 0,
-                    // FIXME(toqueteos): This is value read from server.properties file
-                    max: 20,
-                    sample: None
-                },
-                description: "With custom favicons! Woot :D".to_string(),
-                favicon: Some(format!("data:image/png;base64,{:}", favicon)),
+                players: players,
+                description: motd.to_string(),
+                favicon: favicon,
             };
             try!(StatusResponse { response: resp }.write(stream));
             Ok(())
@@ -109,22 +150,57 @@ pub fn response(stream: &mut TcpStream) -> io::Result<()> {
     }
 }
 
-/// Server-side pong response, optional
-pub fn pong(stream: &mut TcpStream) -> io::Result<()> {
+/// Server-side pong response, optional: clients may hang up or stop
+/// answering instead of pinging, which isn't an error. Returns whether they
+/// pinged.
+pub fn pong(stream: &mut TcpStream) -> io::Result<bool> {
     use packet::status::clientbound::Pong;
     use packet::status::serverbound::Packet::{self, Ping};
 
     // C->S: Ping packet
-    match try!(Packet::read(stream)) {
-        Ping(ping) => {
+    match Packet::read(stream) {
+        Ok(Ping(ping)) => {
             // S->C: Pong packet
             try!(Pong { time: ping.time }.write(stream));
-            Ok(())
+            Ok(true)
         }
-        wrong_packet => Err(io::Error::new(InvalidInput, &format!("Invalid packet read, expecting C->S Ping packet, got {:?}", wrong_packet)[..]))
+        Ok(wrong_packet) => Err(io::Error::new(InvalidInput, &format!("Invalid packet read, expecting C->S Ping packet, got {:?}", wrong_packet)[..])),
+        Err(ref err) if skipped_ping(err) => Ok(false),
+        Err(err) => Err(err)
     }
 }
 
+/// Whether reading a ping failed for the client being gone or timing out.
+fn skipped_ping(err: &io::Error) -> bool {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::WouldBlock
+        | io::ErrorKind::TimedOut => true,
+        _ => false
+    }
+}
+
+/// Asks the server at `host` for its status like the server list does,
+/// returning its response and how many milliseconds a ping took. Without a
+/// `port`, the server's SRV record tells where to ask, see `proto::dns`.
+pub fn query(host: &str, port: Option<u16>) -> io::Result<(Response, i64)> {
+    use packet::NextState;
+    use packet::handshake::Handshake;
+
+    let (mut stream, host, port) = try!(dns::connect(host, port));
+    try!(Handshake {
+        proto_version: consts::PROTO_VERSION,
+        server_address: host,
+        server_port: port,
+        next_state: NextState::Status
+    }.write(&mut stream));
+    let response = try!(request(&mut stream));
+    let elapsed = try!(ping(&mut stream));
+    Ok((response, elapsed))
+}
+
 /// Client-side Server List request
 pub fn request(stream: &mut TcpStream) -> io::Result<Response> {
     use packet::status::serverbound::StatusRequest;
@@ -176,6 +252,60 @@ mod tests {
 
     use packet::handshake::Handshake;
     use packet::{PacketWrite, NextState};
+    use proto::properties::Properties;
+
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use uuid::Uuid;
+
+    #[test]
+    fn sample_modes() {
+        let mut rng = StdRng::from_seed([2; 32]);
+        let online: Vec<_> = (0..20).map(|n| (format!("player{}", n), Uuid::new_v4())).collect();
+        let mut props = Properties::default();
+        assert_eq!(SampleMode::of(&props), SampleMode::Random(12));
+        let sample = SampleMode::of(&props).sample(&online, &mut rng).unwrap();
+        assert_eq!(sample.len(), 12);
+        assert!(sample.iter().all(|entry| online.iter().any(|&(ref name, id)| *name == entry.name && hyphenated(&id) == entry.id)));
+        assert!(SampleMode::of(&props).sample(&[], &mut rng).is_none());
+
+        props.status_sample = "hidden".to_string();
+        assert!(SampleMode::of(&props).sample(&online, &mut rng).is_none());
+        props.status_sample = "secret".to_string();
+        assert_eq!(SampleMode::of(&props), SampleMode::Hidden);
+
+        props.status_sample = "custom".to_string();
+        props.status_sample_messages = "Welcome!|Join us".to_string();
+        let sample = SampleMode::of(&props).sample(&online, &mut rng).unwrap();
+        assert_eq!(sample.iter().map(|entry| &entry.name[..]).collect::<Vec<_>>(), vec!["Welcome!", "Join us"]);
+        assert_eq!(sample[0].id, NO_ID);
+    }
+
+    #[test]
+    fn skipped_ping() {
+        use std::net::TcpListener;
+        use std::thread;
+        use std::time::Duration;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Hanging up or going quiet instead of pinging is fine
+        drop(TcpStream::connect(addr).unwrap());
+        let (mut stream, _) = listener.accept().unwrap();
+        assert!(!pong(&mut stream).unwrap());
+        let quiet = TcpStream::connect(addr).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+        assert!(!pong(&mut stream).unwrap());
+        drop(quiet);
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        let ponged = thread::spawn(move || pong(&mut stream).unwrap());
+        assert!(ping(&mut client).unwrap() >= 0);
+        assert!(ponged.join().unwrap());
+    }
 
     #[test]
     #[cfg(vanilla_server_required)]
@@ -39,6 +39,38 @@ impl<L: Protocol, T: Protocol> Protocol for Arr<L, T> where L::Clean: NumCast {
     }
 }
 
+/// Length-prefixed array of bytes, like `Arr<L, u8>` but read and written
+/// in one go rather than byte by byte, which matters for chunk data.
+pub struct Bytes<L>(PhantomData<fn() -> L>);
+
+impl<L: Protocol> Protocol for Bytes<L> where L::Clean: NumCast {
+    type Clean = Vec<u8>;
+
+    fn proto_len(value: &Vec<u8>) -> usize {
+        <L as Protocol>::proto_len(&(<<L as Protocol>::Clean as NumCast>::from(value.len()).unwrap())) + value.len()
+    }
+
+    fn proto_encode(value: &Vec<u8>, dst: &mut Write) -> io::Result<()> {
+        let len = try!(<L::Clean as NumCast>::from(value.len()).ok_or(io::Error::new(io::ErrorKind::InvalidInput, "could not convert length of vector to Array length type")));
+        try!(<L as Protocol>::proto_encode(&len, dst));
+        dst.write_all(value)
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<Vec<u8>> {
+        let len = try!(
+                       try!(<L as Protocol>::proto_decode(src))
+                       .to_usize()
+                       .ok_or(io::Error::new(io::ErrorKind::InvalidInput, "could not read length of vector from Array length type"))
+        );
+        let mut value = vec![];
+        try!(src.take(len as u64).read_to_end(&mut value));
+        if value.len() < len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "array cut short"));
+        }
+        Ok(value)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -91,4 +123,17 @@ mod tests {
         let value = <Arr<i32, i32> as Protocol>::proto_decode(&mut src).unwrap();
         assert_eq!(arr, value);
     }
+
+    #[test]
+    fn bytes_like_arr() {
+        let value = vec![1u8, 2, 0xff];
+        let mut bytes = Vec::new();
+        <Bytes<Var<i32>> as Protocol>::proto_encode(&value, &mut bytes).unwrap();
+        let mut arr = Vec::new();
+        <Arr<Var<i32>, u8> as Protocol>::proto_encode(&value, &mut arr).unwrap();
+        assert_eq!(bytes, arr);
+        assert_eq!(<Bytes<Var<i32>> as Protocol>::proto_len(&value), bytes.len());
+        assert_eq!(<Bytes<Var<i32>> as Protocol>::proto_decode(&mut &bytes[..]).unwrap(), value);
+        assert!(<Bytes<Var<i32>> as Protocol>::proto_decode(&mut &bytes[..3]).is_err());
+    }
 }
@@ -1,10 +1,12 @@
 use std::collections::{BTreeMap, BTreeSet};
 use std::io;
+use std::io::prelude::*;
 use std::str::FromStr;
 
 use rustc_serialize::{Encodable, Encoder};
 use rustc_serialize::json::{self, Json, ToJson};
 
+use packet::Protocol;
 use types::EntitySelector;
 use types::consts::Color;
 use types::selector;
@@ -96,6 +98,11 @@ macro_rules! type_check {
 }
 
 impl ChatJson {
+    /// A message clients translate themselves, filling in `with`.
+    pub fn translate(key: &str, with: Vec<ChatJson>) -> ChatJson {
+        ChatJson { msg: Message::Translatable(key.to_string(), with), ..ChatJson::from("") }
+    }
+
     pub fn from_reader(src: &mut io::Read) -> Result<ChatJson, ChatJsonError> {
         let json = try!(Json::from_reader(src));
         ChatJson::from_json(json)
@@ -325,6 +332,27 @@ impl Encodable for ChatJson {
     }
 }
 
+/// Chat components are sent as JSON strings.
+impl Protocol for ChatJson {
+    type Clean = ChatJson;
+
+    fn proto_len(value: &ChatJson) -> usize {
+        <String as Protocol>::proto_len(&value.to_json().to_string())
+    }
+
+    fn proto_encode(value: &ChatJson, dst: &mut Write) -> io::Result<()> {
+        <String as Protocol>::proto_encode(&value.to_json().to_string(), dst)
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<ChatJson> {
+        let s = try!(<String as Protocol>::proto_decode(src));
+        ChatJson::from_reader(&mut s.as_bytes()).map_err(|err| match err {
+            ChatJsonError::IoError(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidInput, &format!("invalid chat: {:?}", err)[..])
+        })
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Message {
     PlainText(String),
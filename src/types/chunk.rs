@@ -7,6 +7,7 @@ use std::io::{self, Cursor};
 use packet::Protocol;
 
 /// ChunkColumn is a set of 0-16 chunks, up to 16x256x16 blocks.
+#[derive(Clone)]
 pub struct ChunkColumn {
     pub chunks: Vec<Chunk>,
     pub biomes: Option<[u8; 256]>
@@ -21,6 +22,37 @@ impl ChunkColumn {
         };
         chunks + biomes
     }
+    /// Primary bit mask of this column, assuming sections are stored
+    /// contiguously from the bottom of the world.
+    pub fn mask(&self) -> u16 {
+        ((1u32 << self.chunks.len()) - 1) as u16
+    }
+
+    /// Block at column relative coordinates, as `id << 4 | metadata`.
+    ///
+    /// Sections are assumed to be stored contiguously from the bottom of the
+    /// world, blocks above the topmost section are air.
+    pub fn block(&self, x: usize, y: usize, z: usize) -> u16 {
+        match self.chunks.get(y >> 4) {
+            Some(chunk) => chunk.blocks[(y & 0xf) << 8 | (z & 0xf) << 4 | (x & 0xf)],
+            None => 0
+        }
+    }
+
+    /// Sets a block at column relative coordinates, adding empty sections
+    /// on top as needed, with sky light if the sections below have it.
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, block: u16) {
+        let sky_light = self.chunks.first().map_or(true, |chunk| chunk.sky_light.is_some());
+        while self.chunks.len() <= y >> 4 {
+            let mut chunk = Chunk::new(0, 0xff);
+            if !sky_light {
+                chunk.sky_light = None;
+            }
+            self.chunks.push(chunk);
+        }
+        self.chunks[y >> 4].blocks[(y & 0xf) << 8 | (z & 0xf) << 4 | (x & 0xf)] = block;
+    }
+
     pub fn encode(&self) -> io::Result<Vec<u8>> {
         use byteorder::{LittleEndian, WriteBytesExt};
 
@@ -98,6 +130,7 @@ impl fmt::Debug for ChunkColumn {
 /// Chunk is a group of 16x16x16 blocks.
 ///
 /// `block_light`, `sky_light` are nibble arrays (4bit values)
+#[derive(Clone)]
 pub struct Chunk {
     pub blocks: [u16; 4096],
     pub block_light: [u8; 2048],
@@ -0,0 +1,112 @@
+//! MC Protocol Combat Event, the body of the PlayCombatEvent packet.
+
+use std::io;
+use std::io::prelude::*;
+
+use packet::Protocol;
+use types::Var;
+
+/// Combat Event
+///
+/// `duration` is in ticks, and `entity_id` is the opponent, -1 if there's
+/// none.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CombatEvent {
+    EnterCombat,
+    EndCombat { duration: i32, entity_id: i32 },
+    /// Vanilla clients only pass the plain text `message` on to Twitch.
+    EntityDead { player_id: i32, entity_id: i32, message: String }
+}
+
+impl CombatEvent {
+    fn id(&self) -> i32 {
+        match *self {
+            CombatEvent::EnterCombat => 0,
+            CombatEvent::EndCombat { .. } => 1,
+            CombatEvent::EntityDead { .. } => 2
+        }
+    }
+}
+
+impl Protocol for CombatEvent {
+    type Clean = CombatEvent;
+
+    fn proto_len(value: &CombatEvent) -> usize {
+        <Var<i32> as Protocol>::proto_len(&value.id()) + match *value {
+            CombatEvent::EnterCombat => 0,
+            CombatEvent::EndCombat { duration, .. } => <Var<i32> as Protocol>::proto_len(&duration) + 4,
+            CombatEvent::EntityDead { player_id, ref message, .. } => {
+                <Var<i32> as Protocol>::proto_len(&player_id) + 4 + <String as Protocol>::proto_len(message)
+            }
+        }
+    }
+
+    fn proto_encode(value: &CombatEvent, dst: &mut Write) -> io::Result<()> {
+        try!(<Var<i32> as Protocol>::proto_encode(&value.id(), dst));
+        match *value {
+            CombatEvent::EnterCombat => {}
+            CombatEvent::EndCombat { duration, entity_id } => {
+                try!(<Var<i32> as Protocol>::proto_encode(&duration, dst));
+                try!(<i32 as Protocol>::proto_encode(&entity_id, dst));
+            }
+            CombatEvent::EntityDead { player_id, entity_id, ref message } => {
+                try!(<Var<i32> as Protocol>::proto_encode(&player_id, dst));
+                try!(<i32 as Protocol>::proto_encode(&entity_id, dst));
+                try!(<String as Protocol>::proto_encode(message, dst));
+            }
+        }
+        Ok(())
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<CombatEvent> {
+        Ok(match try!(<Var<i32> as Protocol>::proto_decode(src)) {
+            0 => CombatEvent::EnterCombat,
+            1 => CombatEvent::EndCombat {
+                duration: try!(<Var<i32> as Protocol>::proto_decode(src)),
+                entity_id: try!(<i32 as Protocol>::proto_decode(src))
+            },
+            2 => CombatEvent::EntityDead {
+                player_id: try!(<Var<i32> as Protocol>::proto_decode(src)),
+                entity_id: try!(<i32 as Protocol>::proto_decode(src)),
+                message: try!(<String as Protocol>::proto_decode(src))
+            },
+            n => return Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("invalid combat event {}", n)[..]))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use packet::Protocol;
+
+    fn roundtrip(value: CombatEvent) {
+        let mut dst = Vec::new();
+        <CombatEvent as Protocol>::proto_encode(&value, &mut dst).unwrap();
+        assert_eq!(dst.len(), <CombatEvent as Protocol>::proto_len(&value));
+        let mut src = io::Cursor::new(dst);
+        assert_eq!(<CombatEvent as Protocol>::proto_decode(&mut src).unwrap(), value);
+        assert_eq!(src.position() as usize, src.get_ref().len());
+    }
+
+    #[test]
+    fn entity_dead_roundtrip() {
+        roundtrip(CombatEvent::EntityDead { player_id: 12, entity_id: -1, message: "Steve blew up".to_string() });
+    }
+
+    #[test]
+    fn events_roundtrip() {
+        roundtrip(CombatEvent::EnterCombat);
+        roundtrip(CombatEvent::EndCombat { duration: 200, entity_id: 35 });
+        roundtrip(CombatEvent::EndCombat { duration: 0, entity_id: -1 });
+    }
+
+    #[test]
+    fn invalid_event() {
+        let mut src = io::Cursor::new(vec![3]);
+        assert!(<CombatEvent as Protocol>::proto_decode(&mut src).is_err());
+    }
+}
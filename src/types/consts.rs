@@ -5,19 +5,24 @@ use std::io;
 use std::str::FromStr;
 
 use packet::Protocol;
+use types::Var;
 
 use num::FromPrimitive;
 use rustc_serialize::json::{Json, ToJson};
 
 macro_rules! enum_protocol_impl {
     ($name:ty, $repr:ty, $dec_repr:ident) => {
+        enum_protocol_impl!($name, $repr, $repr, $dec_repr);
+    };
+    // Enums whose wire representation is not a primitive, e.g. `Var<i32>`.
+    ($name:ty, $repr:ty, $clean:ty, $dec_repr:ident) => {
         impl Protocol for $name {
             type Clean = $name;
 
-            fn proto_len(value: &$name) -> usize { <$repr as Protocol>::proto_len(&(*value as $repr)) }
+            fn proto_len(value: &$name) -> usize { <$repr as Protocol>::proto_len(&(*value as $clean)) }
 
             fn proto_encode(value: &$name, dst: &mut Write) -> io::Result<()> {
-                let repr = *value as $repr;
+                let repr = *value as $clean;
                 try!(<$repr as Protocol>::proto_encode(&repr, dst));
                 Ok(())
             }
@@ -62,6 +67,1036 @@ impl FromPrimitive for Dimension {
     }
 }
 
+enum_protocol_impl!(Difficulty, u8, from_u8);
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Difficulty {
+    Peaceful = 0,
+    Easy = 1,
+    Normal = 2,
+    Hard = 3
+}
+
+impl FromPrimitive for Difficulty {
+    fn from_i64(n: i64) -> Option<Difficulty> {
+        if n < 0 { None } else { Difficulty::from_u64(n as u64) }
+    }
+
+    fn from_u64(n: u64) -> Option<Difficulty> {
+        match n {
+            0 => Some(Difficulty::Peaceful),
+            1 => Some(Difficulty::Easy),
+            2 => Some(Difficulty::Normal),
+            3 => Some(Difficulty::Hard),
+            _ => None
+        }
+    }
+}
+
+enum_protocol_impl!(GameMode, u8, from_u8);
+
+/// Game mode as sent in `Respawn`, which never carries the hardcore flag.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GameMode {
+    Survival = 0,
+    Creative = 1,
+    Adventure = 2,
+    Spectator = 3
+}
+
+impl FromPrimitive for GameMode {
+    fn from_i64(n: i64) -> Option<GameMode> {
+        if n < 0 { None } else { GameMode::from_u64(n as u64) }
+    }
+
+    fn from_u64(n: u64) -> Option<GameMode> {
+        match n {
+            0 => Some(GameMode::Survival),
+            1 => Some(GameMode::Creative),
+            2 => Some(GameMode::Adventure),
+            3 => Some(GameMode::Spectator),
+            _ => None
+        }
+    }
+}
+
+/// Game mode as sent in `JoinGame`, where bit 3 (`0x8`) is the hardcore flag.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct GameModeFlags {
+    pub gamemode: GameMode,
+    pub hardcore: bool
+}
+
+impl Protocol for GameModeFlags {
+    type Clean = GameModeFlags;
+
+    fn proto_len(_: &GameModeFlags) -> usize { 1 }
+
+    fn proto_encode(value: &GameModeFlags, dst: &mut Write) -> io::Result<()> {
+        let hardcore = if value.hardcore { 0x8 } else { 0 };
+        <u8 as Protocol>::proto_encode(&(value.gamemode as u8 | hardcore), dst)
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<GameModeFlags> {
+        let value = try!(<u8 as Protocol>::proto_decode(src));
+        match GameMode::from_u8(value & !0x8) {
+            Some(gamemode) => Ok(GameModeFlags { gamemode: gamemode, hardcore: value & 0x8 != 0 }),
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("invalid gamemode: {}", value)[..]))
+        }
+    }
+}
+
+enum_protocol_impl!(BlockFace, i8, from_i8);
+
+/// Face of a block, as sent in PlayerDigging and PlayerBlockPlacement.
+#[repr(i8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlockFace {
+    /// Sent when no face applies, e.g. when using the held item in the air.
+    Special = -1,
+    Down = 0,
+    Up = 1,
+    North = 2,
+    South = 3,
+    West = 4,
+    East = 5
+}
+
+impl BlockFace {
+    /// Unit vector pointing out of this face.
+    pub fn offset(&self) -> [i32; 3] {
+        match *self {
+            BlockFace::Special => [0, 0, 0],
+            BlockFace::Down => [0, -1, 0],
+            BlockFace::Up => [0, 1, 0],
+            BlockFace::North => [0, 0, -1],
+            BlockFace::South => [0, 0, 1],
+            BlockFace::West => [-1, 0, 0],
+            BlockFace::East => [1, 0, 0]
+        }
+    }
+
+    /// Position of the block adjacent to `pos` through this face, which is
+    /// where a block placed against this face ends up.
+    pub fn relative(&self, pos: [i32; 3]) -> [i32; 3] {
+        let offset = self.offset();
+        [pos[0] + offset[0], pos[1] + offset[1], pos[2] + offset[2]]
+    }
+
+    pub fn opposite(&self) -> BlockFace {
+        match *self {
+            BlockFace::Special => BlockFace::Special,
+            BlockFace::Down => BlockFace::Up,
+            BlockFace::Up => BlockFace::Down,
+            BlockFace::North => BlockFace::South,
+            BlockFace::South => BlockFace::North,
+            BlockFace::West => BlockFace::East,
+            BlockFace::East => BlockFace::West
+        }
+    }
+}
+
+impl BlockFace {
+    /// Rotates a horizontal face a quarter turn counterclockwise, seen from
+    /// above. Vertical faces are returned unchanged.
+    pub fn rotate_ccw(&self) -> BlockFace {
+        match *self {
+            BlockFace::North => BlockFace::West,
+            BlockFace::West => BlockFace::South,
+            BlockFace::South => BlockFace::East,
+            BlockFace::East => BlockFace::North,
+            face => face
+        }
+    }
+}
+
+impl FromPrimitive for BlockFace {
+    fn from_i64(n: i64) -> Option<BlockFace> {
+        match n {
+            -1 => Some(BlockFace::Special),
+            0 => Some(BlockFace::Down),
+            1 => Some(BlockFace::Up),
+            2 => Some(BlockFace::North),
+            3 => Some(BlockFace::South),
+            4 => Some(BlockFace::West),
+            5 => Some(BlockFace::East),
+            _ => None
+        }
+    }
+
+    fn from_u64(n: u64) -> Option<BlockFace> {
+        BlockFace::from_i64(n as i64)
+    }
+}
+
+/// Horizontal `BlockFace` encoded as an unsigned byte, as sent in
+/// SpawnPainting.
+///
+/// Values are `0` for south, `1` for west, `2` for north and `3` for east.
+pub struct HorizontalFace;
+
+impl Protocol for HorizontalFace {
+    type Clean = BlockFace;
+
+    fn proto_len(_: &BlockFace) -> usize { 1 }
+
+    fn proto_encode(value: &BlockFace, dst: &mut Write) -> io::Result<()> {
+        let index = match *value {
+            BlockFace::South => 0,
+            BlockFace::West => 1,
+            BlockFace::North => 2,
+            BlockFace::East => 3,
+            face => return Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("{:?} is not a horizontal face", face)[..]))
+        };
+        <u8 as Protocol>::proto_encode(&index, dst)
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<BlockFace> {
+        match try!(<u8 as Protocol>::proto_decode(src)) {
+            0 => Ok(BlockFace::South),
+            1 => Ok(BlockFace::West),
+            2 => Ok(BlockFace::North),
+            3 => Ok(BlockFace::East),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid horizontal face"))
+        }
+    }
+}
+
+/// Painting motives, sent by title in SpawnPainting.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PaintingKind {
+    Kebab,
+    Aztec,
+    Alban,
+    Aztec2,
+    Bomb,
+    Plant,
+    Wasteland,
+    Pool,
+    Courbet,
+    Sea,
+    Sunset,
+    Creebet,
+    Wanderer,
+    Graham,
+    Match,
+    Bust,
+    Stage,
+    Void,
+    SkullAndRoses,
+    Wither,
+    Fighters,
+    Pointer,
+    Pigscene,
+    BurningSkull,
+    Skeleton,
+    DonkeyKong
+}
+
+/// Every painting motive, in vanilla registry order.
+pub const PAINTINGS: [PaintingKind; 26] = [
+    PaintingKind::Kebab, PaintingKind::Aztec, PaintingKind::Alban, PaintingKind::Aztec2,
+    PaintingKind::Bomb, PaintingKind::Plant, PaintingKind::Wasteland, PaintingKind::Pool,
+    PaintingKind::Courbet, PaintingKind::Sea, PaintingKind::Sunset, PaintingKind::Creebet,
+    PaintingKind::Wanderer, PaintingKind::Graham, PaintingKind::Match, PaintingKind::Bust,
+    PaintingKind::Stage, PaintingKind::Void, PaintingKind::SkullAndRoses, PaintingKind::Wither,
+    PaintingKind::Fighters, PaintingKind::Pointer, PaintingKind::Pigscene, PaintingKind::BurningSkull,
+    PaintingKind::Skeleton, PaintingKind::DonkeyKong
+];
+
+impl PaintingKind {
+    /// Size of the painting as `[width, height]` in blocks.
+    pub fn size(&self) -> [i32; 2] {
+        match *self {
+            PaintingKind::Kebab
+            | PaintingKind::Aztec
+            | PaintingKind::Alban
+            | PaintingKind::Aztec2
+            | PaintingKind::Bomb
+            | PaintingKind::Plant
+            | PaintingKind::Wasteland => [1, 1],
+            PaintingKind::Pool
+            | PaintingKind::Courbet
+            | PaintingKind::Sea
+            | PaintingKind::Sunset
+            | PaintingKind::Creebet => [2, 1],
+            PaintingKind::Wanderer
+            | PaintingKind::Graham => [1, 2],
+            PaintingKind::Match
+            | PaintingKind::Bust
+            | PaintingKind::Stage
+            | PaintingKind::Void
+            | PaintingKind::SkullAndRoses
+            | PaintingKind::Wither => [2, 2],
+            PaintingKind::Fighters => [4, 2],
+            PaintingKind::Pointer
+            | PaintingKind::Pigscene
+            | PaintingKind::BurningSkull => [4, 4],
+            PaintingKind::Skeleton
+            | PaintingKind::DonkeyKong => [4, 3]
+        }
+    }
+
+    /// Blocks covered by a painting hanging at `location` and facing away
+    /// from its wall towards `facing`.
+    ///
+    /// Paintings are centered on `location`, paintings with an even size
+    /// extend one block further to the left and upwards.
+    pub fn covered_blocks(&self, location: [i32; 3], facing: BlockFace) -> Vec<[i32; 3]> {
+        let [width, height] = self.size();
+        let left = facing.rotate_ccw().offset();
+        let mut blocks = Vec::with_capacity((width * height) as usize);
+        for i in -((width - 1) / 2)..(width - (width - 1) / 2) {
+            for j in -((height - 1) / 2)..(height - (height - 1) / 2) {
+                blocks.push([location[0] + left[0] * i, location[1] + j, location[2] + left[2] * i]);
+            }
+        }
+        blocks
+    }
+
+    /// Bounding box of a painting hanging at `location`, as minimum and
+    /// maximum corners.
+    pub fn bounding_box(&self, location: [i32; 3], facing: BlockFace) -> ([f64; 3], [f64; 3]) {
+        let blocks = self.covered_blocks(location, facing);
+        let mut min = [::std::f64::MAX; 3];
+        let mut max = [::std::f64::MIN; 3];
+        for block in &blocks {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(block[axis] as f64);
+                max[axis] = max[axis].max(block[axis] as f64 + 1.0);
+            }
+        }
+        // Paintings are 1/16th of a block thick, flat against their wall.
+        let back = facing.opposite().offset();
+        for axis in 0..3 {
+            if back[axis] < 0 {
+                max[axis] = min[axis] + 0.0625;
+            } else if back[axis] > 0 {
+                min[axis] = max[axis] - 0.0625;
+            }
+        }
+        (min, max)
+    }
+
+    /// Whether the painting fits at `location`: every covered block must be
+    /// free and every block behind it must be solid.
+    pub fn can_place<F, G>(&self, location: [i32; 3], facing: BlockFace, is_free: F, is_solid: G) -> bool
+        where F: Fn([i32; 3]) -> bool, G: Fn([i32; 3]) -> bool {
+        match facing {
+            BlockFace::Special | BlockFace::Up | BlockFace::Down => return false,
+            _ => {}
+        }
+        self.covered_blocks(location, facing).into_iter().all(|block| {
+            is_free(block) && is_solid(facing.opposite().relative(block))
+        })
+    }
+}
+
+impl AsRef<str> for PaintingKind {
+    fn as_ref(&self) -> &str {
+        match *self {
+            PaintingKind::Kebab => "Kebab",
+            PaintingKind::Aztec => "Aztec",
+            PaintingKind::Alban => "Alban",
+            PaintingKind::Aztec2 => "Aztec2",
+            PaintingKind::Bomb => "Bomb",
+            PaintingKind::Plant => "Plant",
+            PaintingKind::Wasteland => "Wasteland",
+            PaintingKind::Pool => "Pool",
+            PaintingKind::Courbet => "Courbet",
+            PaintingKind::Sea => "Sea",
+            PaintingKind::Sunset => "Sunset",
+            PaintingKind::Creebet => "Creebet",
+            PaintingKind::Wanderer => "Wanderer",
+            PaintingKind::Graham => "Graham",
+            PaintingKind::Match => "Match",
+            PaintingKind::Bust => "Bust",
+            PaintingKind::Stage => "Stage",
+            PaintingKind::Void => "Void",
+            PaintingKind::SkullAndRoses => "SkullAndRoses",
+            PaintingKind::Wither => "Wither",
+            PaintingKind::Fighters => "Fighters",
+            PaintingKind::Pointer => "Pointer",
+            PaintingKind::Pigscene => "Pigscene",
+            PaintingKind::BurningSkull => "BurningSkull",
+            PaintingKind::Skeleton => "Skeleton",
+            PaintingKind::DonkeyKong => "DonkeyKong"
+        }
+    }
+}
+
+impl FromStr for PaintingKind {
+    type Err = ();
+
+    fn from_str(string: &str) -> Result<PaintingKind, ()> {
+        PAINTINGS.iter().find(|kind| kind.as_ref() == string).cloned().ok_or(())
+    }
+}
+
+/// Painting title, validated against the painting registry.
+impl Protocol for PaintingKind {
+    type Clean = PaintingKind;
+
+    fn proto_len(value: &PaintingKind) -> usize {
+        <String as Protocol>::proto_len(&value.as_ref().to_string())
+    }
+
+    fn proto_encode(value: &PaintingKind, dst: &mut Write) -> io::Result<()> {
+        <String as Protocol>::proto_encode(&value.as_ref().to_string(), dst)
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<PaintingKind> {
+        let title = try!(<String as Protocol>::proto_decode(src));
+        PaintingKind::from_str(&title).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, &format!("unknown painting: {}", title)[..]))
+    }
+}
+
+enum_protocol_impl!(ObjectKind, i8, from_i8);
+
+/// Kinds of object entities, as sent in SpawnObject.
+#[repr(i8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ObjectKind {
+    Boat = 1,
+    ItemStack = 2,
+    Minecart = 10,
+    ActivatedTnt = 50,
+    EnderCrystal = 51,
+    Arrow = 60,
+    Snowball = 61,
+    Egg = 62,
+    Fireball = 63,
+    FireCharge = 64,
+    ThrownEnderpearl = 65,
+    WitherSkull = 66,
+    FallingBlock = 70,
+    ItemFrame = 71,
+    EyeOfEnder = 72,
+    ThrownPotion = 73,
+    FallingDragonEgg = 74,
+    ThrownExpBottle = 75,
+    FireworkRocket = 76,
+    LeashKnot = 77,
+    ArmorStand = 78,
+    FishingFloat = 90
+}
+
+impl ObjectKind {
+    /// Whether `ObjectData` for this kind holds the entity id of the shooter.
+    pub fn is_projectile(&self) -> bool {
+        match *self {
+            ObjectKind::Arrow
+            | ObjectKind::Snowball
+            | ObjectKind::Egg
+            | ObjectKind::Fireball
+            | ObjectKind::FireCharge
+            | ObjectKind::ThrownEnderpearl
+            | ObjectKind::WitherSkull
+            | ObjectKind::ThrownPotion
+            | ObjectKind::ThrownExpBottle
+            | ObjectKind::FishingFloat => true,
+            _ => false
+        }
+    }
+
+    /// Bounding box size as `[width, height]` in blocks.
+    pub fn size(&self) -> [f32; 2] {
+        match *self {
+            ObjectKind::Boat => [1.5, 0.6],
+            ObjectKind::Minecart => [0.98, 0.7],
+            ObjectKind::ActivatedTnt | ObjectKind::FallingBlock | ObjectKind::FallingDragonEgg => [0.98, 0.98],
+            ObjectKind::EnderCrystal => [2.0, 2.0],
+            ObjectKind::Fireball => [1.0, 1.0],
+            ObjectKind::ItemFrame | ObjectKind::LeashKnot => [0.5, 0.5],
+            ObjectKind::ArmorStand => [0.5, 1.975],
+            ObjectKind::FireCharge | ObjectKind::WitherSkull => [0.3125, 0.3125],
+            _ => [0.25, 0.25]
+        }
+    }
+}
+
+impl FromPrimitive for ObjectKind {
+    fn from_i64(n: i64) -> Option<ObjectKind> {
+        match n {
+            1 => Some(ObjectKind::Boat),
+            2 => Some(ObjectKind::ItemStack),
+            10 => Some(ObjectKind::Minecart),
+            50 => Some(ObjectKind::ActivatedTnt),
+            51 => Some(ObjectKind::EnderCrystal),
+            60 => Some(ObjectKind::Arrow),
+            61 => Some(ObjectKind::Snowball),
+            62 => Some(ObjectKind::Egg),
+            63 => Some(ObjectKind::Fireball),
+            64 => Some(ObjectKind::FireCharge),
+            65 => Some(ObjectKind::ThrownEnderpearl),
+            66 => Some(ObjectKind::WitherSkull),
+            70 => Some(ObjectKind::FallingBlock),
+            71 => Some(ObjectKind::ItemFrame),
+            72 => Some(ObjectKind::EyeOfEnder),
+            73 => Some(ObjectKind::ThrownPotion),
+            74 => Some(ObjectKind::FallingDragonEgg),
+            75 => Some(ObjectKind::ThrownExpBottle),
+            76 => Some(ObjectKind::FireworkRocket),
+            77 => Some(ObjectKind::LeashKnot),
+            78 => Some(ObjectKind::ArmorStand),
+            90 => Some(ObjectKind::FishingFloat),
+            _ => None
+        }
+    }
+
+    fn from_u64(n: u64) -> Option<ObjectKind> {
+        ObjectKind::from_i64(n as i64)
+    }
+}
+
+enum_protocol_impl!(MobKind, u8, from_u8);
+
+/// Kinds of mob entities, as sent in SpawnMob. Spawn eggs use these as
+/// their damage value.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MobKind {
+    Creeper = 50,
+    Skeleton = 51,
+    Spider = 52,
+    Giant = 53,
+    Zombie = 54,
+    Slime = 55,
+    Ghast = 56,
+    PigZombie = 57,
+    Enderman = 58,
+    CaveSpider = 59,
+    Silverfish = 60,
+    Blaze = 61,
+    MagmaCube = 62,
+    EnderDragon = 63,
+    Wither = 64,
+    Bat = 65,
+    Witch = 66,
+    Endermite = 67,
+    Guardian = 68,
+    Pig = 90,
+    Sheep = 91,
+    Cow = 92,
+    Chicken = 93,
+    Squid = 94,
+    Wolf = 95,
+    Mooshroom = 96,
+    SnowGolem = 97,
+    Ocelot = 98,
+    IronGolem = 99,
+    Horse = 100,
+    Rabbit = 101,
+    Villager = 120
+}
+
+impl MobKind {
+    /// Bounding box size as `[width, height]` in blocks, slimes and magma
+    /// cubes are given at their smallest.
+    pub fn size(&self) -> [f32; 2] {
+        match *self {
+            MobKind::Creeper | MobKind::Blaze => [0.6, 1.8],
+            MobKind::Skeleton | MobKind::Zombie | MobKind::PigZombie | MobKind::Witch | MobKind::Villager => [0.6, 1.95],
+            MobKind::Spider => [1.4, 0.9],
+            MobKind::Giant => [3.6, 10.8],
+            MobKind::Slime | MobKind::MagmaCube => [0.51, 0.51],
+            MobKind::Ghast => [4.0, 4.0],
+            MobKind::Enderman => [0.6, 2.9],
+            MobKind::CaveSpider => [0.7, 0.5],
+            MobKind::Silverfish | MobKind::Endermite => [0.4, 0.3],
+            MobKind::EnderDragon => [16.0, 8.0],
+            MobKind::Wither => [0.9, 3.5],
+            MobKind::Bat => [0.5, 0.9],
+            MobKind::Guardian => [0.85, 0.85],
+            MobKind::Pig => [0.9, 0.9],
+            MobKind::Sheep | MobKind::Cow | MobKind::Mooshroom => [0.9, 1.3],
+            MobKind::Chicken => [0.4, 0.7],
+            MobKind::Squid => [0.95, 0.95],
+            MobKind::Wolf => [0.6, 0.8],
+            MobKind::SnowGolem => [0.7, 1.9],
+            MobKind::Ocelot => [0.6, 0.7],
+            MobKind::IronGolem => [1.4, 2.9],
+            MobKind::Horse => [1.4, 1.6],
+            MobKind::Rabbit => [0.6, 0.7]
+        }
+    }
+}
+
+impl FromPrimitive for MobKind {
+    fn from_i64(n: i64) -> Option<MobKind> {
+        match n {
+            50 => Some(MobKind::Creeper),
+            51 => Some(MobKind::Skeleton),
+            52 => Some(MobKind::Spider),
+            53 => Some(MobKind::Giant),
+            54 => Some(MobKind::Zombie),
+            55 => Some(MobKind::Slime),
+            56 => Some(MobKind::Ghast),
+            57 => Some(MobKind::PigZombie),
+            58 => Some(MobKind::Enderman),
+            59 => Some(MobKind::CaveSpider),
+            60 => Some(MobKind::Silverfish),
+            61 => Some(MobKind::Blaze),
+            62 => Some(MobKind::MagmaCube),
+            63 => Some(MobKind::EnderDragon),
+            64 => Some(MobKind::Wither),
+            65 => Some(MobKind::Bat),
+            66 => Some(MobKind::Witch),
+            67 => Some(MobKind::Endermite),
+            68 => Some(MobKind::Guardian),
+            90 => Some(MobKind::Pig),
+            91 => Some(MobKind::Sheep),
+            92 => Some(MobKind::Cow),
+            93 => Some(MobKind::Chicken),
+            94 => Some(MobKind::Squid),
+            95 => Some(MobKind::Wolf),
+            96 => Some(MobKind::Mooshroom),
+            97 => Some(MobKind::SnowGolem),
+            98 => Some(MobKind::Ocelot),
+            99 => Some(MobKind::IronGolem),
+            100 => Some(MobKind::Horse),
+            101 => Some(MobKind::Rabbit),
+            120 => Some(MobKind::Villager),
+            _ => None
+        }
+    }
+
+    fn from_u64(n: u64) -> Option<MobKind> {
+        MobKind::from_i64(n as i64)
+    }
+}
+
+/// Minecart variants, sent as the `ObjectData` of `ObjectKind::Minecart`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MinecartKind {
+    Empty = 0,
+    Chest = 1,
+    Furnace = 2,
+    Tnt = 3,
+    Spawner = 4,
+    Hopper = 5,
+    CommandBlock = 6
+}
+
+enum_protocol_impl!(ResourcePackResult, Var<i32>, i32, from_i32);
+
+/// Resource pack status reported by the client in ResourcePackStatus.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResourcePackResult {
+    SuccessfullyLoaded = 0,
+    Declined = 1,
+    FailedDownload = 2,
+    Accepted = 3
+}
+
+impl FromPrimitive for ResourcePackResult {
+    fn from_i64(n: i64) -> Option<ResourcePackResult> {
+        match n {
+            0 => Some(ResourcePackResult::SuccessfullyLoaded),
+            1 => Some(ResourcePackResult::Declined),
+            2 => Some(ResourcePackResult::FailedDownload),
+            3 => Some(ResourcePackResult::Accepted),
+            _ => None
+        }
+    }
+
+    fn from_u64(n: u64) -> Option<ResourcePackResult> {
+        ResourcePackResult::from_i64(n as i64)
+    }
+}
+
+enum_protocol_impl!(ClientStatusAction, Var<i32>, i32, from_i32);
+
+/// Action sent by the client in ClientStatus.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClientStatusAction {
+    PerformRespawn = 0,
+    RequestStats = 1,
+    TakingInventoryAchievement = 2
+}
+
+impl FromPrimitive for ClientStatusAction {
+    fn from_i64(n: i64) -> Option<ClientStatusAction> {
+        match n {
+            0 => Some(ClientStatusAction::PerformRespawn),
+            1 => Some(ClientStatusAction::RequestStats),
+            2 => Some(ClientStatusAction::TakingInventoryAchievement),
+            _ => None
+        }
+    }
+
+    fn from_u64(n: u64) -> Option<ClientStatusAction> {
+        ClientStatusAction::from_i64(n as i64)
+    }
+}
+
+enum_protocol_impl!(DiggingStatus, i8, from_i8);
+
+/// Status sent by the client in PlayerDigging.
+#[repr(i8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DiggingStatus {
+    StartedDigging = 0,
+    CancelledDigging = 1,
+    FinishedDigging = 2,
+    DropItemStack = 3,
+    DropItem = 4,
+    /// Releasing a bow or finishing to eat or drink.
+    ShootArrowFinishEating = 5
+}
+
+impl FromPrimitive for DiggingStatus {
+    fn from_i64(n: i64) -> Option<DiggingStatus> {
+        match n {
+            0 => Some(DiggingStatus::StartedDigging),
+            1 => Some(DiggingStatus::CancelledDigging),
+            2 => Some(DiggingStatus::FinishedDigging),
+            3 => Some(DiggingStatus::DropItemStack),
+            4 => Some(DiggingStatus::DropItem),
+            5 => Some(DiggingStatus::ShootArrowFinishEating),
+            _ => None
+        }
+    }
+
+    fn from_u64(n: u64) -> Option<DiggingStatus> {
+        DiggingStatus::from_i64(n as i64)
+    }
+}
+
+enum_protocol_impl!(ChatPosition, i8, from_i8);
+
+/// Where a clientbound ChatMessage is displayed.
+#[repr(i8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ChatPosition {
+    Chat = 0,
+    /// Command feedback and other server messages.
+    System = 1,
+    /// Above the hotbar.
+    ActionBar = 2
+}
+
+impl FromPrimitive for ChatPosition {
+    fn from_i64(n: i64) -> Option<ChatPosition> {
+        match n {
+            0 => Some(ChatPosition::Chat),
+            1 => Some(ChatPosition::System),
+            2 => Some(ChatPosition::ActionBar),
+            _ => None
+        }
+    }
+
+    fn from_u64(n: u64) -> Option<ChatPosition> {
+        ChatPosition::from_i64(n as i64)
+    }
+}
+
+enum_protocol_impl!(ChatMode, i8, from_i8);
+
+/// Chat visibility chosen by the client in ClientSettings.
+#[repr(i8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ChatMode {
+    Enabled = 0,
+    CommandsOnly = 1,
+    Hidden = 2
+}
+
+impl ChatMode {
+    /// Whether a client in this mode wants messages shown at `position`.
+    pub fn accepts(&self, position: ChatPosition) -> bool {
+        match (*self, position) {
+            (_, ChatPosition::ActionBar) => true,
+            (ChatMode::Enabled, _) => true,
+            (ChatMode::CommandsOnly, ChatPosition::System) => true,
+            _ => false
+        }
+    }
+}
+
+impl FromPrimitive for ChatMode {
+    fn from_i64(n: i64) -> Option<ChatMode> {
+        match n {
+            0 => Some(ChatMode::Enabled),
+            1 => Some(ChatMode::CommandsOnly),
+            2 => Some(ChatMode::Hidden),
+            _ => None
+        }
+    }
+
+    fn from_u64(n: u64) -> Option<ChatMode> {
+        ChatMode::from_i64(n as i64)
+    }
+}
+
+enum_protocol_impl!(AnimationKind, u8, from_u8);
+
+/// Animation played by the clientbound Animation packet.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AnimationKind {
+    SwingArm = 0,
+    TakeDamage = 1,
+    LeaveBed = 2,
+    EatFood = 3,
+    CriticalEffect = 4,
+    MagicCriticalEffect = 5
+}
+
+impl FromPrimitive for AnimationKind {
+    fn from_i64(n: i64) -> Option<AnimationKind> {
+        if n < 0 { None } else { AnimationKind::from_u64(n as u64) }
+    }
+
+    fn from_u64(n: u64) -> Option<AnimationKind> {
+        match n {
+            0 => Some(AnimationKind::SwingArm),
+            1 => Some(AnimationKind::TakeDamage),
+            2 => Some(AnimationKind::LeaveBed),
+            3 => Some(AnimationKind::EatFood),
+            4 => Some(AnimationKind::CriticalEffect),
+            5 => Some(AnimationKind::MagicCriticalEffect),
+            _ => None
+        }
+    }
+}
+
+enum_protocol_impl!(EntityStatusKind, i8, from_i8);
+
+/// Status codes sent in EntityStatus, the entity kinds each applies to are
+/// noted where it's not every living entity.
+#[repr(i8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EntityStatusKind {
+    Hurt = 2,
+    Dead = 3,
+    /// Iron golems.
+    ThrowingArms = 4,
+    /// Wolves, ocelots and horses.
+    TamingFailed = 6,
+    /// Wolves, ocelots and horses.
+    TamingSucceeded = 7,
+    /// Wolves.
+    ShakingWater = 8,
+    /// Players, sent to the eating player only.
+    EatingAccepted = 9,
+    /// Sheep, also used by TNT minecarts for the ignite sound.
+    EatingGrass = 10,
+    /// Iron golems.
+    HandingRose = 11,
+    /// Villagers.
+    Mating = 12,
+    /// Villagers.
+    Angry = 13,
+    /// Villagers.
+    Happy = 14,
+    /// Witches.
+    MagicParticles = 15,
+    /// Zombies.
+    ConvertingToVillager = 16,
+    /// Firework rockets.
+    FireworkExploding = 17,
+    /// Animals.
+    InLove = 18,
+    /// Squids.
+    ResetRotation = 19,
+    ExplosionParticle = 20,
+    /// Guardians.
+    GuardianSound = 21,
+    /// Players, sent to the player themself.
+    EnableReducedDebug = 22,
+    /// Players, sent to the player themself.
+    DisableReducedDebug = 23
+}
+
+impl FromPrimitive for EntityStatusKind {
+    fn from_i64(n: i64) -> Option<EntityStatusKind> {
+        match n {
+            2 => Some(EntityStatusKind::Hurt),
+            3 => Some(EntityStatusKind::Dead),
+            4 => Some(EntityStatusKind::ThrowingArms),
+            6 => Some(EntityStatusKind::TamingFailed),
+            7 => Some(EntityStatusKind::TamingSucceeded),
+            8 => Some(EntityStatusKind::ShakingWater),
+            9 => Some(EntityStatusKind::EatingAccepted),
+            10 => Some(EntityStatusKind::EatingGrass),
+            11 => Some(EntityStatusKind::HandingRose),
+            12 => Some(EntityStatusKind::Mating),
+            13 => Some(EntityStatusKind::Angry),
+            14 => Some(EntityStatusKind::Happy),
+            15 => Some(EntityStatusKind::MagicParticles),
+            16 => Some(EntityStatusKind::ConvertingToVillager),
+            17 => Some(EntityStatusKind::FireworkExploding),
+            18 => Some(EntityStatusKind::InLove),
+            19 => Some(EntityStatusKind::ResetRotation),
+            20 => Some(EntityStatusKind::ExplosionParticle),
+            21 => Some(EntityStatusKind::GuardianSound),
+            22 => Some(EntityStatusKind::EnableReducedDebug),
+            23 => Some(EntityStatusKind::DisableReducedDebug),
+            _ => None
+        }
+    }
+
+    fn from_u64(n: u64) -> Option<EntityStatusKind> {
+        EntityStatusKind::from_i64(n as i64)
+    }
+}
+
+enum_protocol_impl!(EntityActionKind, Var<i32>, i32, from_i32);
+
+/// Action sent by the client in EntityAction.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EntityActionKind {
+    StartSneaking = 0,
+    StopSneaking = 1,
+    LeaveBed = 2,
+    StartSprinting = 3,
+    StopSprinting = 4,
+    JumpWithHorse = 5,
+    OpenRiddenHorseInventory = 6
+}
+
+impl FromPrimitive for EntityActionKind {
+    fn from_i64(n: i64) -> Option<EntityActionKind> {
+        match n {
+            0 => Some(EntityActionKind::StartSneaking),
+            1 => Some(EntityActionKind::StopSneaking),
+            2 => Some(EntityActionKind::LeaveBed),
+            3 => Some(EntityActionKind::StartSprinting),
+            4 => Some(EntityActionKind::StopSprinting),
+            5 => Some(EntityActionKind::JumpWithHorse),
+            6 => Some(EntityActionKind::OpenRiddenHorseInventory),
+            _ => None
+        }
+    }
+
+    fn from_u64(n: u64) -> Option<EntityActionKind> {
+        EntityActionKind::from_i64(n as i64)
+    }
+}
+
+enum_protocol_impl!(ParticleId, i32, from_i32);
+
+/// Particle shown by the Particle packet.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParticleId {
+    Explode = 0,
+    LargeExplode = 1,
+    HugeExplosion = 2,
+    FireworksSpark = 3,
+    Bubble = 4,
+    Splash = 5,
+    Wake = 6,
+    Suspended = 7,
+    DepthSuspend = 8,
+    Crit = 9,
+    MagicCrit = 10,
+    Smoke = 11,
+    LargeSmoke = 12,
+    Spell = 13,
+    InstantSpell = 14,
+    MobSpell = 15,
+    MobSpellAmbient = 16,
+    WitchMagic = 17,
+    DripWater = 18,
+    DripLava = 19,
+    AngryVillager = 20,
+    HappyVillager = 21,
+    TownAura = 22,
+    Note = 23,
+    Portal = 24,
+    EnchantmentTable = 25,
+    Flame = 26,
+    Lava = 27,
+    Footstep = 28,
+    Cloud = 29,
+    RedDust = 30,
+    SnowballPoof = 31,
+    SnowShovel = 32,
+    Slime = 33,
+    Heart = 34,
+    Barrier = 35,
+    IconCrack = 36,
+    BlockCrack = 37,
+    BlockDust = 38,
+    Droplet = 39,
+    Take = 40,
+    MobAppearance = 41
+}
+
+impl ParticleId {
+    /// How many VarInts of data come with the particle: an item id and
+    /// damage for `IconCrack`, and a block id with its metadata shifted
+    /// left 12 bits for `BlockCrack` and `BlockDust`.
+    pub fn data_len(&self) -> usize {
+        match *self {
+            ParticleId::IconCrack => 2,
+            ParticleId::BlockCrack | ParticleId::BlockDust => 1,
+            _ => 0
+        }
+    }
+}
+
+impl FromPrimitive for ParticleId {
+    fn from_i64(n: i64) -> Option<ParticleId> {
+        match n {
+            0 => Some(ParticleId::Explode),
+            1 => Some(ParticleId::LargeExplode),
+            2 => Some(ParticleId::HugeExplosion),
+            3 => Some(ParticleId::FireworksSpark),
+            4 => Some(ParticleId::Bubble),
+            5 => Some(ParticleId::Splash),
+            6 => Some(ParticleId::Wake),
+            7 => Some(ParticleId::Suspended),
+            8 => Some(ParticleId::DepthSuspend),
+            9 => Some(ParticleId::Crit),
+            10 => Some(ParticleId::MagicCrit),
+            11 => Some(ParticleId::Smoke),
+            12 => Some(ParticleId::LargeSmoke),
+            13 => Some(ParticleId::Spell),
+            14 => Some(ParticleId::InstantSpell),
+            15 => Some(ParticleId::MobSpell),
+            16 => Some(ParticleId::MobSpellAmbient),
+            17 => Some(ParticleId::WitchMagic),
+            18 => Some(ParticleId::DripWater),
+            19 => Some(ParticleId::DripLava),
+            20 => Some(ParticleId::AngryVillager),
+            21 => Some(ParticleId::HappyVillager),
+            22 => Some(ParticleId::TownAura),
+            23 => Some(ParticleId::Note),
+            24 => Some(ParticleId::Portal),
+            25 => Some(ParticleId::EnchantmentTable),
+            26 => Some(ParticleId::Flame),
+            27 => Some(ParticleId::Lava),
+            28 => Some(ParticleId::Footstep),
+            29 => Some(ParticleId::Cloud),
+            30 => Some(ParticleId::RedDust),
+            31 => Some(ParticleId::SnowballPoof),
+            32 => Some(ParticleId::SnowShovel),
+            33 => Some(ParticleId::Slime),
+            34 => Some(ParticleId::Heart),
+            35 => Some(ParticleId::Barrier),
+            36 => Some(ParticleId::IconCrack),
+            37 => Some(ParticleId::BlockCrack),
+            38 => Some(ParticleId::BlockDust),
+            39 => Some(ParticleId::Droplet),
+            40 => Some(ParticleId::Take),
+            41 => Some(ParticleId::MobAppearance),
+            _ => None
+        }
+    }
+
+    fn from_u64(n: u64) -> Option<ParticleId> {
+        ParticleId::from_i64(n as i64)
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Color {
     Black       = 0x0,
@@ -136,3 +1171,119 @@ impl ToJson for Color {
         self.as_ref().to_json()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use packet::Protocol;
+
+    #[test]
+    fn gamemode_flags_hardcore() {
+        let value = GameModeFlags { gamemode: GameMode::Adventure, hardcore: true };
+        let mut dst = Vec::new();
+        <GameModeFlags as Protocol>::proto_encode(&value, &mut dst).unwrap();
+        assert_eq!(&dst, &[0x0a]);
+        let mut src = io::Cursor::new(dst);
+        assert_eq!(<GameModeFlags as Protocol>::proto_decode(&mut src).unwrap(), value);
+    }
+
+    #[test]
+    fn gamemode_invalid() {
+        let mut src = io::Cursor::new(vec![0x04]);
+        assert!(<GameMode as Protocol>::proto_decode(&mut src).is_err());
+    }
+
+    #[test]
+    fn particle_data() {
+        let mut src = io::Cursor::new(vec![0, 0, 0, 36]);
+        let particle = <ParticleId as Protocol>::proto_decode(&mut src).unwrap();
+        assert_eq!((particle, particle.data_len()), (ParticleId::IconCrack, 2));
+        assert_eq!((ParticleId::BlockDust.data_len(), ParticleId::Heart.data_len()), (1, 0));
+        let mut src = io::Cursor::new(vec![0, 0, 0, 42]);
+        assert!(<ParticleId as Protocol>::proto_decode(&mut src).is_err());
+    }
+
+    #[test]
+    fn block_face_special() {
+        let mut src = io::Cursor::new(vec![0xff]);
+        let face = <BlockFace as Protocol>::proto_decode(&mut src).unwrap();
+        assert_eq!(face, BlockFace::Special);
+        assert_eq!(face.relative([1, 2, 3]), [1, 2, 3]);
+        assert_eq!(BlockFace::West.relative([1, 2, 3]), [0, 2, 3]);
+    }
+
+    #[test]
+    fn horizontal_face() {
+        let mut dst = Vec::new();
+        <HorizontalFace as Protocol>::proto_encode(&BlockFace::North, &mut dst).unwrap();
+        assert_eq!(&dst, &[2]);
+        assert!(<HorizontalFace as Protocol>::proto_encode(&BlockFace::Up, &mut dst).is_err());
+    }
+
+    #[test]
+    fn painting_title() {
+        let mut dst = Vec::new();
+        <PaintingKind as Protocol>::proto_encode(&PaintingKind::SkullAndRoses, &mut dst).unwrap();
+        let value = <PaintingKind as Protocol>::proto_decode(&mut io::Cursor::new(dst)).unwrap();
+        assert_eq!(value, PaintingKind::SkullAndRoses);
+
+        let mut dst = Vec::new();
+        <String as Protocol>::proto_encode(&"Mona Lisa".to_string(), &mut dst).unwrap();
+        assert!(<PaintingKind as Protocol>::proto_decode(&mut io::Cursor::new(dst)).is_err());
+    }
+
+    #[test]
+    fn painting_covered_blocks() {
+        assert_eq!(PaintingKind::Kebab.covered_blocks([0, 64, 0], BlockFace::South), vec![[0, 64, 0]]);
+        // Facing south, left is east (+x).
+        assert_eq!(PaintingKind::Pool.covered_blocks([0, 64, 0], BlockFace::South), vec![[0, 64, 0], [1, 64, 0]]);
+        let blocks = PaintingKind::Fighters.covered_blocks([0, 64, 0], BlockFace::North);
+        assert_eq!(blocks.len(), 8);
+        assert!(blocks.contains(&[1, 64, 0]) && blocks.contains(&[-2, 65, 0]));
+        let (min, max) = PaintingKind::Kebab.bounding_box([0, 64, 0], BlockFace::South);
+        assert_eq!((min, max), ([0.0, 64.0, 0.0], [1.0, 65.0, 0.0625]));
+    }
+
+    #[test]
+    fn object_kind_decode() {
+        let mut src = io::Cursor::new(vec![70]);
+        assert_eq!(<ObjectKind as Protocol>::proto_decode(&mut src).unwrap(), ObjectKind::FallingBlock);
+        let mut src = io::Cursor::new(vec![3]);
+        assert!(<ObjectKind as Protocol>::proto_decode(&mut src).is_err());
+    }
+
+    #[test]
+    fn resource_pack_result_varint() {
+        let mut dst = Vec::new();
+        <ResourcePackResult as Protocol>::proto_encode(&ResourcePackResult::Accepted, &mut dst).unwrap();
+        assert_eq!(&dst, &[3]);
+        let mut src = io::Cursor::new(vec![1]);
+        assert_eq!(<ResourcePackResult as Protocol>::proto_decode(&mut src).unwrap(), ResourcePackResult::Declined);
+    }
+
+    #[test]
+    fn chat_mode_accepts() {
+        assert!(ChatMode::Enabled.accepts(ChatPosition::Chat));
+        assert!(!ChatMode::CommandsOnly.accepts(ChatPosition::Chat));
+        assert!(ChatMode::CommandsOnly.accepts(ChatPosition::System));
+        assert!(!ChatMode::Hidden.accepts(ChatPosition::System));
+        assert!(ChatMode::Hidden.accepts(ChatPosition::ActionBar));
+    }
+
+    #[test]
+    fn difficulty_decode() {
+        let mut src = io::Cursor::new(vec![0x03]);
+        assert_eq!(<Difficulty as Protocol>::proto_decode(&mut src).unwrap(), Difficulty::Hard);
+    }
+
+    #[test]
+    fn entity_status_decode() {
+        let mut src = io::Cursor::new(vec![3]);
+        assert_eq!(<EntityStatusKind as Protocol>::proto_decode(&mut src).unwrap(), EntityStatusKind::Dead);
+        let mut src = io::Cursor::new(vec![5]);
+        assert!(<EntityStatusKind as Protocol>::proto_decode(&mut src).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 //! MC Protocol Metadata data type.
 
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::io::prelude::*;
 use std::io;
 
@@ -13,16 +13,17 @@ use types::Slot;
 /// will be the health item.
 ///
 /// The entity metadata format is quirky dictionary format, where the key and
-/// the value's type are packed in a single byte.
+/// the value's type are packed in a single byte. Entries are sent in index
+/// order, so the same metadata always encodes the same.
 ///
 /// Note that entity metadata is a totally distinct concept from block
 /// metadata.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct EntityMetadata {
-    dict: HashMap<u8, Entry>
+    dict: BTreeMap<u8, Entry>
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Entry {
     Byte(u8),
     Short(i16),
@@ -36,8 +37,192 @@ pub enum Entry {
 
 impl EntityMetadata {
     pub fn new() -> EntityMetadata {
-        EntityMetadata { dict: HashMap::new() }
+        EntityMetadata { dict: BTreeMap::new() }
     }
+
+    /// Sets the entry at `index`, only the lower 5 bits of `index` are used.
+    pub fn set(&mut self, index: u8, entry: Entry) {
+        self.dict.insert(index & 0x1f, entry);
+    }
+
+    pub fn get(&self, index: u8) -> Option<&Entry> {
+        self.dict.get(&index)
+    }
+
+    /// Sets the `flags` bits of every entity.
+    pub fn set_flags(&mut self, flags: u8) -> &mut EntityMetadata {
+        self.set(index::entity::FLAGS, Entry::Byte(flags));
+        self
+    }
+
+    /// Sets the air left of an entity, 300 when it isn't under water.
+    pub fn set_air(&mut self, air: i16) -> &mut EntityMetadata {
+        self.set(index::entity::AIR, Entry::Short(air));
+        self
+    }
+
+    /// Sets the name of a living entity, shown when looked at.
+    pub fn set_custom_name(&mut self, name: &str) -> &mut EntityMetadata {
+        self.set(index::living::CUSTOM_NAME, Entry::String(name.to_string()));
+        self
+    }
+
+    /// Sets whether a living entity's name shows even when not looked at.
+    pub fn set_always_show_nametag(&mut self, always: bool) -> &mut EntityMetadata {
+        self.set(index::living::ALWAYS_SHOW_NAMETAG, Entry::Byte(always as u8));
+        self
+    }
+
+    pub fn set_health(&mut self, health: f32) -> &mut EntityMetadata {
+        self.set(index::living::HEALTH, Entry::Float(health));
+        self
+    }
+
+    /// Sets the color of the potion particles around a living entity, 0
+    /// for none.
+    pub fn set_potion_color(&mut self, color: i32) -> &mut EntityMetadata {
+        self.set(index::living::POTION_COLOR, Entry::Int(color));
+        self
+    }
+
+    /// Sets the number of arrows stuck in a living entity.
+    pub fn set_arrows(&mut self, arrows: u8) -> &mut EntityMetadata {
+        self.set(index::living::ARROWS, Entry::Byte(arrows));
+        self
+    }
+
+    /// Sets whether a mob stands still instead of moving on its own.
+    pub fn set_no_ai(&mut self, no_ai: bool) -> &mut EntityMetadata {
+        self.set(index::living::NO_AI, Entry::Byte(no_ai as u8));
+        self
+    }
+
+    /// Sets the age of an animal or villager, negative for children.
+    pub fn set_age(&mut self, age: i8) -> &mut EntityMetadata {
+        self.set(index::ageable::AGE, Entry::Byte(age as u8));
+        self
+    }
+
+    /// Sets the stack shown by a dropped item.
+    pub fn set_item(&mut self, stack: Slot) -> &mut EntityMetadata {
+        self.set(index::item::ITEM, Entry::Slot(Some(stack)));
+        self
+    }
+}
+
+/// Indices of metadata entries in 1.8, by the kind of entity they're for.
+/// Entities have the entries of every kind they belong to, e.g. zombies
+/// have those of `entity`, `living` and `zombie`.
+pub mod index {
+    pub mod entity {
+        /// Byte of `flags`.
+        pub const FLAGS: u8 = 0;
+        /// Short.
+        pub const AIR: u8 = 1;
+    }
+
+    pub mod living {
+        /// String.
+        pub const CUSTOM_NAME: u8 = 2;
+        /// Byte, 1 if true.
+        pub const ALWAYS_SHOW_NAMETAG: u8 = 3;
+        /// Float.
+        pub const HEALTH: u8 = 6;
+        /// Int, a RGB color.
+        pub const POTION_COLOR: u8 = 7;
+        /// Byte, 1 if true.
+        pub const POTION_AMBIENT: u8 = 8;
+        /// Byte.
+        pub const ARROWS: u8 = 9;
+        /// Byte, 1 if true.
+        pub const NO_AI: u8 = 15;
+    }
+
+    pub mod ageable {
+        /// Byte, negative for children.
+        pub const AGE: u8 = 12;
+    }
+
+    pub mod human {
+        /// Byte of which skin parts show.
+        pub const SKIN_PARTS: u8 = 10;
+        /// Float.
+        pub const ABSORPTION: u8 = 17;
+        /// Int.
+        pub const SCORE: u8 = 18;
+    }
+
+    pub mod item {
+        /// Slot.
+        pub const ITEM: u8 = 10;
+    }
+
+    pub mod item_frame {
+        /// Slot.
+        pub const ITEM: u8 = 8;
+        /// Byte, in eighths of a turn.
+        pub const ROTATION: u8 = 9;
+    }
+
+    pub mod armor_stand {
+        /// Byte: 0x01 small, 0x02 no gravity, 0x04 arms, 0x08 no base plate.
+        pub const FLAGS: u8 = 10;
+        /// Float3 rotations in degrees, one per body part.
+        pub const HEAD: u8 = 11;
+        pub const BODY: u8 = 12;
+        pub const LEFT_ARM: u8 = 13;
+        pub const RIGHT_ARM: u8 = 14;
+        pub const LEFT_LEG: u8 = 15;
+        pub const RIGHT_LEG: u8 = 16;
+    }
+
+    pub mod horse {
+        /// Int: 0x02 tame, 0x04 saddled, 0x08 chest.
+        pub const FLAGS: u8 = 16;
+        /// Byte.
+        pub const KIND: u8 = 19;
+        /// Int, color and markings.
+        pub const VARIANT: u8 = 20;
+        /// String, the owner's UUID.
+        pub const OWNER: u8 = 21;
+        /// Int, the armor worn.
+        pub const ARMOR: u8 = 22;
+    }
+
+    pub mod zombie {
+        /// Byte, 1 if true.
+        pub const CHILD: u8 = 12;
+        /// Byte, 1 if true.
+        pub const VILLAGER: u8 = 13;
+        /// Byte, 1 if true.
+        pub const CONVERTING: u8 = 14;
+    }
+
+    pub mod creeper {
+        /// Byte, 1 when about to explode, -1 otherwise.
+        pub const STATE: u8 = 16;
+        /// Byte, 1 if true.
+        pub const POWERED: u8 = 17;
+    }
+
+    pub mod enderman {
+        /// Short, the id of the carried block.
+        pub const CARRIED: u8 = 16;
+        /// Byte, its metadata.
+        pub const CARRIED_DATA: u8 = 17;
+        /// Byte, 1 if true.
+        pub const SCREAMING: u8 = 18;
+    }
+}
+
+/// Bits of the entry at `index::entity::FLAGS`.
+pub mod flags {
+    pub const ON_FIRE: u8 = 0x01;
+    pub const CROUCHED: u8 = 0x02;
+    pub const SPRINTING: u8 = 0x08;
+    /// Eating, drinking or blocking.
+    pub const USING_ITEM: u8 = 0x10;
+    pub const INVISIBLE: u8 = 0x20;
 }
 
 impl Protocol for EntityMetadata {
@@ -55,7 +240,8 @@ impl Protocol for EntityMetadata {
                 | &Entry::Float3(_) => 12,
             }
         }
-        value.dict.values().map(entry_len).fold(0, |acc, item| acc + item)
+        // Each entry is prefixed by its key, the dictionary ends with 0x7f
+        value.dict.values().map(|entry| 1 + entry_len(entry)).fold(1, |acc, item| acc + item)
     }
     fn proto_encode(value: &EntityMetadata, dst: &mut Write) -> io::Result<()> {
         fn key(k: u8, idx: u8) -> u8 {
@@ -101,7 +287,7 @@ impl Protocol for EntityMetadata {
         Ok(())
     }
     fn proto_decode(src: &mut Read) -> io::Result<EntityMetadata> {
-        let mut dict = HashMap::new();
+        let mut dict = BTreeMap::new();
         loop {
             let item = try!(<u8 as Protocol>::proto_decode(src));
             if item == 0x7F {
@@ -127,3 +313,39 @@ impl Protocol for EntityMetadata {
         Ok(EntityMetadata{ dict: dict })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    use packet::Protocol;
+
+    #[test]
+    fn typed_entries() {
+        let mut metadata = EntityMetadata::new();
+        metadata.set_flags(flags::ON_FIRE | flags::INVISIBLE).set_custom_name("Grumm").set_always_show_nametag(true).set_health(12.5);
+        match metadata.get(index::entity::FLAGS) {
+            Some(&Entry::Byte(0x21)) => {}
+            entry => panic!("flags are {:?}", entry)
+        }
+        match metadata.get(index::living::CUSTOM_NAME) {
+            Some(&Entry::String(ref name)) => assert_eq!(name, "Grumm"),
+            entry => panic!("custom name is {:?}", entry)
+        }
+        match metadata.get(index::living::HEALTH) {
+            Some(&Entry::Float(health)) => assert_eq!(health, 12.5),
+            entry => panic!("health is {:?}", entry)
+        }
+
+        let mut dst = vec![];
+        <EntityMetadata as Protocol>::proto_encode(&metadata, &mut dst).unwrap();
+        assert_eq!(dst.len(), <EntityMetadata as Protocol>::proto_len(&metadata));
+        let decoded = <EntityMetadata as Protocol>::proto_decode(&mut Cursor::new(dst)).unwrap();
+        match decoded.get(index::living::ALWAYS_SHOW_NAMETAG) {
+            Some(&Entry::Byte(1)) => {}
+            entry => panic!("always show nametag is {:?}", entry)
+        }
+    }
+}
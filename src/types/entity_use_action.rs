@@ -0,0 +1,90 @@
+//! MC Protocol Entity Use action, the tail of the UseEntity packet.
+
+use std::io;
+use std::io::prelude::*;
+
+use packet::Protocol;
+use types::Var;
+
+/// Entity Use action
+///
+/// `InteractAt` carries the point on the target's bounding box the player
+/// clicked, relative to the target's position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EntityUseAction {
+    Interact,
+    Attack,
+    InteractAt { target: [f32; 3] }
+}
+
+impl EntityUseAction {
+    fn id(&self) -> i32 {
+        match *self {
+            EntityUseAction::Interact => 0,
+            EntityUseAction::Attack => 1,
+            EntityUseAction::InteractAt { .. } => 2
+        }
+    }
+}
+
+impl Protocol for EntityUseAction {
+    type Clean = EntityUseAction;
+
+    fn proto_len(value: &EntityUseAction) -> usize {
+        <Var<i32> as Protocol>::proto_len(&value.id()) + match *value {
+            EntityUseAction::InteractAt { .. } => 12,
+            _ => 0
+        }
+    }
+
+    fn proto_encode(value: &EntityUseAction, dst: &mut Write) -> io::Result<()> {
+        try!(<Var<i32> as Protocol>::proto_encode(&value.id(), dst));
+        if let EntityUseAction::InteractAt { ref target } = *value {
+            try!(<[f32; 3] as Protocol>::proto_encode(target, dst));
+        }
+        Ok(())
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<EntityUseAction> {
+        Ok(match try!(<Var<i32> as Protocol>::proto_decode(src)) {
+            0 => EntityUseAction::Interact,
+            1 => EntityUseAction::Attack,
+            2 => EntityUseAction::InteractAt { target: try!(<[f32; 3] as Protocol>::proto_decode(src)) },
+            n => return Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("invalid entity use action {}", n)[..]))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use packet::Protocol;
+
+    #[test]
+    fn interact_at_roundtrip() {
+        let value = EntityUseAction::InteractAt { target: [0.5, 1.25, -0.5] };
+        let mut dst = Vec::new();
+        <EntityUseAction as Protocol>::proto_encode(&value, &mut dst).unwrap();
+        assert_eq!(dst.len(), <EntityUseAction as Protocol>::proto_len(&value));
+        let mut src = io::Cursor::new(dst);
+        assert_eq!(<EntityUseAction as Protocol>::proto_decode(&mut src).unwrap(), value);
+    }
+
+    #[test]
+    fn attack() {
+        let mut src = io::Cursor::new(vec![1]);
+        assert_eq!(<EntityUseAction as Protocol>::proto_decode(&mut src).unwrap(), EntityUseAction::Attack);
+    }
+
+    #[test]
+    fn invalid_action() {
+        let mut src = io::Cursor::new(vec![3]);
+        assert!(<EntityUseAction as Protocol>::proto_decode(&mut src).is_err());
+        // Where the player clicked is required
+        let mut src = io::Cursor::new(vec![2, 0x3f, 0, 0, 0]);
+        assert!(<EntityUseAction as Protocol>::proto_decode(&mut src).is_err());
+    }
+}
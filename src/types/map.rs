@@ -0,0 +1,161 @@
+//! MC Protocol map icons and pixel data, sent in the UpdateMap packet.
+
+use std::io;
+use std::io::prelude::*;
+
+use packet::Protocol;
+use types::{Bytes, Var};
+
+/// Side of a map in pixels.
+pub const MAP_SIZE: usize = 128;
+
+/// Icon shown on a map, such as a player's arrow.
+///
+/// `direction` is in sixteenths of a full turn clockwise from south,
+/// `kind` picks the icon's sprite, both 0 to 15, and `x` and `z` are
+/// where it is in half pixels from the map's center.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MapIcon {
+    pub direction: u8,
+    pub kind: u8,
+    pub x: i8,
+    pub z: i8
+}
+
+impl Protocol for MapIcon {
+    type Clean = MapIcon;
+
+    fn proto_len(_: &MapIcon) -> usize { 3 }
+
+    fn proto_encode(value: &MapIcon, dst: &mut Write) -> io::Result<()> {
+        try!(<u8 as Protocol>::proto_encode(&((value.direction & 0xf) << 4 | value.kind & 0xf), dst));
+        try!(<i8 as Protocol>::proto_encode(&value.x, dst));
+        <i8 as Protocol>::proto_encode(&value.z, dst)
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<MapIcon> {
+        let direction_and_kind = try!(<u8 as Protocol>::proto_decode(src));
+        Ok(MapIcon {
+            direction: direction_and_kind >> 4,
+            kind: direction_and_kind & 0xf,
+            x: try!(<i8 as Protocol>::proto_decode(src)),
+            z: try!(<i8 as Protocol>::proto_decode(src))
+        })
+    }
+}
+
+/// Pixels of the `columns` by `rows` rectangle of a map with its top left
+/// corner at `x`, `z`, as color ids row after row.
+///
+/// No columns means the packet only updates icons, and then nothing else
+/// is sent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapData {
+    pub columns: u8,
+    pub rows: u8,
+    pub x: u8,
+    pub z: u8,
+    pub data: Vec<u8>
+}
+
+impl MapData {
+    /// No pixels at all.
+    pub fn empty() -> MapData {
+        MapData { columns: 0, rows: 0, x: 0, z: 0, data: vec![] }
+    }
+
+    /// Every pixel of a map, `colors` holding all `MAP_SIZE` rows.
+    pub fn full(colors: Vec<u8>) -> MapData {
+        MapData { columns: MAP_SIZE as u8, rows: MAP_SIZE as u8, x: 0, z: 0, data: colors }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns == 0
+    }
+}
+
+impl Protocol for MapData {
+    type Clean = MapData;
+
+    fn proto_len(value: &MapData) -> usize {
+        if value.is_empty() {
+            return 1;
+        }
+        4 + <Bytes<Var<i32>> as Protocol>::proto_len(&value.data)
+    }
+
+    fn proto_encode(value: &MapData, dst: &mut Write) -> io::Result<()> {
+        try!(<u8 as Protocol>::proto_encode(&value.columns, dst));
+        if value.is_empty() {
+            return Ok(());
+        }
+        if value.data.len() != value.columns as usize * value.rows as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("{} bytes of map data for {}x{} pixels", value.data.len(), value.columns, value.rows)[..]));
+        }
+        try!(<u8 as Protocol>::proto_encode(&value.rows, dst));
+        try!(<u8 as Protocol>::proto_encode(&value.x, dst));
+        try!(<u8 as Protocol>::proto_encode(&value.z, dst));
+        <Bytes<Var<i32>> as Protocol>::proto_encode(&value.data, dst)
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<MapData> {
+        let columns = try!(<u8 as Protocol>::proto_decode(src));
+        if columns == 0 {
+            return Ok(MapData::empty());
+        }
+        let value = MapData {
+            columns: columns,
+            rows: try!(<u8 as Protocol>::proto_decode(src)),
+            x: try!(<u8 as Protocol>::proto_decode(src)),
+            z: try!(<u8 as Protocol>::proto_decode(src)),
+            data: try!(<Bytes<Var<i32>> as Protocol>::proto_decode(src))
+        };
+        if value.data.len() != columns as usize * value.rows as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("{} bytes of map data for {}x{} pixels", value.data.len(), columns, value.rows)[..]));
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use packet::Protocol;
+
+    fn roundtrip(value: MapData) -> Vec<u8> {
+        let mut dst = Vec::new();
+        <MapData as Protocol>::proto_encode(&value, &mut dst).unwrap();
+        assert_eq!(dst.len(), <MapData as Protocol>::proto_len(&value));
+        let mut src = io::Cursor::new(dst.clone());
+        assert_eq!(<MapData as Protocol>::proto_decode(&mut src).unwrap(), value);
+        dst
+    }
+
+    #[test]
+    fn icons() {
+        let icon = MapIcon { direction: 12, kind: 1, x: -128, z: 40 };
+        let mut dst = Vec::new();
+        <MapIcon as Protocol>::proto_encode(&icon, &mut dst).unwrap();
+        assert_eq!(dst, vec![0xc1, 0x80, 40]);
+        let mut src = io::Cursor::new(dst);
+        assert_eq!(<MapIcon as Protocol>::proto_decode(&mut src).unwrap(), icon);
+    }
+
+    #[test]
+    fn pixel_data() {
+        assert_eq!(roundtrip(MapData::empty()), vec![0]);
+        let patch = MapData { columns: 2, rows: 3, x: 10, z: 126, data: vec![4, 5, 6, 7, 8, 9] };
+        assert_eq!(roundtrip(patch), vec![2, 3, 10, 126, 6, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(roundtrip(MapData::full(vec![34; MAP_SIZE * MAP_SIZE])).len(), 4 + 3 + MAP_SIZE * MAP_SIZE);
+
+        // The rectangle and its pixels must agree
+        let mut dst = Vec::new();
+        let short = MapData { columns: 2, rows: 2, x: 0, z: 0, data: vec![1] };
+        assert!(<MapData as Protocol>::proto_encode(&short, &mut dst).is_err());
+        let mut src = io::Cursor::new(vec![2, 2, 0, 0, 1, 1]);
+        assert!(<MapData as Protocol>::proto_decode(&mut src).is_err());
+    }
+}
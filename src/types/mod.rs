@@ -1,22 +1,40 @@
 mod arr;
 mod chat;
+mod combat_event;
 pub mod consts;
 mod chunk;
 mod entity_metadata;
+mod entity_use_action;
+mod map;
 mod nbt;
+mod object_data;
+mod player_list;
 mod pos;
+mod scoreboard;
 mod selector;
 mod slot;
 mod string;
+mod title;
 mod uuid;
 mod varnum;
+mod world_border;
 
-pub use self::arr::Arr;
-pub use self::chat::ChatJson;
+pub use self::arr::{Arr, Bytes};
+pub use self::chat::{ChatJson, ClickEvent, Format, HoverEvent, Message};
+pub use self::combat_event::CombatEvent;
 pub use self::chunk::{Chunk, ChunkColumn};
-pub use self::entity_metadata::EntityMetadata;
+pub use self::entity_metadata::{EntityMetadata, Entry as MetadataEntry};
+pub use self::entity_metadata::{flags as metadata_flags, index as metadata_index};
+pub use self::entity_use_action::EntityUseAction;
+pub use self::map::{MapData, MapIcon, MAP_SIZE};
+pub use self::nbt::MAX_NBT_SIZE;
+pub use self::object_data::ObjectData;
+pub use self::player_list::{PlayerListAction, PlayerListEntry, ProfileProperty};
 pub use self::pos::BlockPos;
+pub use self::scoreboard::{ObjectiveAction, ObjectiveType, ScoreAction, TeamAction, TeamInfo};
 pub use self::selector::EntitySelector;
 pub use self::slot::Slot;
-pub use self::uuid::UuidString;
+pub use self::title::TitleAction;
+pub use self::uuid::{hyphenated, offline_uuid, UuidString};
 pub use self::varnum::Var;
+pub use self::world_border::WorldBorderAction;
@@ -1,11 +1,14 @@
 //! A protocol implementation for `nbt::Blob`s.
 
-use std::io;
+use std::io::{self, Read};
 
 use nbt;
 
 use packet::Protocol;
 
+/// Largest encoded NBT accepted or sent in a single packet, vanilla's limit.
+pub const MAX_NBT_SIZE: usize = 2097152;
+
 impl Protocol for nbt::Blob {
     type Clean = nbt::Blob;
 
@@ -14,10 +17,35 @@ impl Protocol for nbt::Blob {
     }
 
     fn proto_encode(value: &nbt::Blob, dst: &mut io::Write) -> io::Result<()> {
+        if value.len() > MAX_NBT_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "NBT too large to send"));
+        }
         Ok(try!(value.write(dst)))
     }
 
     fn proto_decode(src: &mut io::Read) -> io::Result<nbt::Blob> {
-        Ok(try!(nbt::Blob::from_reader(src)))
+        // Oversized NBT runs out of input and fails to decode
+        Ok(try!(nbt::Blob::from_reader(&mut src.take(MAX_NBT_SIZE as u64))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use nbt;
+
+    use packet::Protocol;
+
+    #[test]
+    fn size_cap() {
+        let mut blob = nbt::Blob::new("".to_string());
+        blob.insert("Data".to_string(), nbt::Value::ByteArray(vec![0; MAX_NBT_SIZE])).unwrap();
+        let mut dst = vec![];
+        assert!(<nbt::Blob as Protocol>::proto_encode(&blob, &mut dst).is_err());
+        blob.write(&mut dst).unwrap();
+        assert!(<nbt::Blob as Protocol>::proto_decode(&mut io::Cursor::new(dst)).is_err());
     }
 }
@@ -0,0 +1,117 @@
+//! MC Protocol Object Data type, the tail of the SpawnObject packet.
+
+use std::io;
+use std::io::prelude::*;
+
+use packet::Protocol;
+use types::consts::MinecartKind;
+
+/// Object Data
+///
+/// The meaning of `data` depends on the kind of object being spawned (see
+/// `ObjectKind`). Velocity is only present on the wire when `data` is greater
+/// than zero, it is dropped otherwise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ObjectData {
+    pub data: i32,
+    pub velocity: Option<[i16; 3]>
+}
+
+impl ObjectData {
+    /// Object data for kinds which don't use the `data` field.
+    pub fn empty() -> ObjectData {
+        ObjectData { data: 0, velocity: None }
+    }
+
+    /// Arrows, fireballs, snowballs, eggs and other projectiles carry the
+    /// entity id of their shooter.
+    pub fn projectile(shooter_eid: i32, velocity: [i16; 3]) -> ObjectData {
+        ObjectData { data: shooter_eid, velocity: Some(velocity) }
+    }
+
+    /// Falling blocks carry the block id with the block metadata in the
+    /// upper bits.
+    pub fn falling_block(block_id: u16, metadata: u8, velocity: [i16; 3]) -> ObjectData {
+        ObjectData { data: block_id as i32 | (metadata as i32) << 12, velocity: Some(velocity) }
+    }
+
+    /// Minecarts carry their variant.
+    pub fn minecart(kind: MinecartKind) -> ObjectData {
+        let data = kind as i32;
+        ObjectData { data: data, velocity: if data > 0 { Some([0; 3]) } else { None } }
+    }
+
+    /// Item frames carry the direction they face, 0 for south then
+    /// clockwise. Velocity is sent along when it isn't 0, like vanilla does.
+    pub fn item_frame(direction: i32) -> ObjectData {
+        ObjectData { data: direction, velocity: if direction > 0 { Some([0; 3]) } else { None } }
+    }
+
+    /// Item stacks use a non zero `data` so that velocity gets sent, the
+    /// actual item is sent afterwards as entity metadata.
+    pub fn item_stack(velocity: [i16; 3]) -> ObjectData {
+        ObjectData { data: 1, velocity: Some(velocity) }
+    }
+
+    fn has_velocity(&self) -> bool {
+        self.data > 0
+    }
+}
+
+impl Protocol for ObjectData {
+    type Clean = ObjectData;
+
+    fn proto_len(value: &ObjectData) -> usize {
+        if value.has_velocity() { 4 + 6 } else { 4 }
+    }
+
+    fn proto_encode(value: &ObjectData, dst: &mut Write) -> io::Result<()> {
+        try!(<i32 as Protocol>::proto_encode(&value.data, dst));
+        if value.has_velocity() {
+            try!(<[i16; 3] as Protocol>::proto_encode(&value.velocity.unwrap_or([0; 3]), dst));
+        }
+        Ok(())
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<ObjectData> {
+        let mut value = ObjectData { data: try!(<i32 as Protocol>::proto_decode(src)), velocity: None };
+        if value.has_velocity() {
+            value.velocity = Some(try!(<[i16; 3] as Protocol>::proto_decode(src)));
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use packet::Protocol;
+
+    #[test]
+    fn object_data_without_velocity() {
+        let mut dst = Vec::new();
+        <ObjectData as Protocol>::proto_encode(&ObjectData { data: 0, velocity: Some([1, 2, 3]) }, &mut dst).unwrap();
+        assert_eq!(&dst, &[0, 0, 0, 0]);
+        let value = <ObjectData as Protocol>::proto_decode(&mut io::Cursor::new(dst)).unwrap();
+        assert_eq!(value, ObjectData::empty());
+    }
+
+    #[test]
+    fn object_data_with_velocity() {
+        let value = ObjectData::projectile(7, [1, -1, 0]);
+        let mut dst = Vec::new();
+        <ObjectData as Protocol>::proto_encode(&value, &mut dst).unwrap();
+        assert_eq!(&dst, &[0, 0, 0, 7, 0, 1, 0xff, 0xff, 0, 0]);
+        assert_eq!(dst.len(), <ObjectData as Protocol>::proto_len(&value));
+        assert_eq!(<ObjectData as Protocol>::proto_decode(&mut io::Cursor::new(dst)).unwrap(), value);
+    }
+
+    #[test]
+    fn object_data_falling_block() {
+        // Red wool
+        assert_eq!(ObjectData::falling_block(35, 14, [0; 3]).data, 35 | 14 << 12);
+    }
+}
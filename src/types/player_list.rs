@@ -0,0 +1,229 @@
+//! MC Protocol Player List action, the body of the UpdatePlayerList packet.
+
+use std::io;
+use std::io::prelude::*;
+
+use packet::Protocol;
+use types::{Arr, ChatJson, Var};
+use types::consts::GameMode;
+
+use num::FromPrimitive;
+use uuid::Uuid;
+
+/// A property of a player's profile, such as their skin in `textures`,
+/// signed by the session server when `signature` is given.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProfileProperty {
+    pub name: String,
+    pub value: String,
+    pub signature: Option<String>
+}
+
+impl Protocol for ProfileProperty {
+    type Clean = ProfileProperty;
+
+    fn proto_len(value: &ProfileProperty) -> usize {
+        <String as Protocol>::proto_len(&value.name)
+            + <String as Protocol>::proto_len(&value.value)
+            + <Option<String> as Protocol>::proto_len(&value.signature)
+    }
+
+    fn proto_encode(value: &ProfileProperty, dst: &mut Write) -> io::Result<()> {
+        try!(<String as Protocol>::proto_encode(&value.name, dst));
+        try!(<String as Protocol>::proto_encode(&value.value, dst));
+        <Option<String> as Protocol>::proto_encode(&value.signature, dst)
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<ProfileProperty> {
+        Ok(ProfileProperty {
+            name: try!(<String as Protocol>::proto_decode(src)),
+            value: try!(<String as Protocol>::proto_decode(src)),
+            signature: try!(<Option<String> as Protocol>::proto_decode(src))
+        })
+    }
+}
+
+/// A player added to the list, `ping` is their latency in milliseconds and
+/// `display_name` replaces `name` in the list when given.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayerListEntry {
+    pub uuid: Uuid,
+    pub name: String,
+    pub properties: Vec<ProfileProperty>,
+    pub gamemode: GameMode,
+    pub ping: i32,
+    pub display_name: Option<ChatJson>
+}
+
+/// Player List action, applied to each of the players it lists.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlayerListAction {
+    AddPlayer(Vec<PlayerListEntry>),
+    UpdateGamemode(Vec<(Uuid, GameMode)>),
+    UpdateLatency(Vec<(Uuid, i32)>),
+    UpdateDisplayName(Vec<(Uuid, Option<ChatJson>)>),
+    RemovePlayer(Vec<Uuid>)
+}
+
+impl PlayerListAction {
+    fn id(&self) -> i32 {
+        match *self {
+            PlayerListAction::AddPlayer(_) => 0,
+            PlayerListAction::UpdateGamemode(_) => 1,
+            PlayerListAction::UpdateLatency(_) => 2,
+            PlayerListAction::UpdateDisplayName(_) => 3,
+            PlayerListAction::RemovePlayer(_) => 4
+        }
+    }
+
+    fn count(&self) -> usize {
+        match *self {
+            PlayerListAction::AddPlayer(ref entries) => entries.len(),
+            PlayerListAction::UpdateGamemode(ref players) => players.len(),
+            PlayerListAction::UpdateLatency(ref players) => players.len(),
+            PlayerListAction::UpdateDisplayName(ref players) => players.len(),
+            PlayerListAction::RemovePlayer(ref players) => players.len()
+        }
+    }
+}
+
+/// Game modes are VarInts here rather than the usual byte.
+fn gamemode_len(gamemode: GameMode) -> usize {
+    <Var<i32> as Protocol>::proto_len(&(gamemode as i32))
+}
+
+fn encode_gamemode(gamemode: GameMode, dst: &mut Write) -> io::Result<()> {
+    <Var<i32> as Protocol>::proto_encode(&(gamemode as i32), dst)
+}
+
+fn decode_gamemode(src: &mut Read) -> io::Result<GameMode> {
+    let value = try!(<Var<i32> as Protocol>::proto_decode(src));
+    GameMode::from_i32(value).ok_or(io::Error::new(io::ErrorKind::InvalidInput, &format!("invalid game mode {}", value)[..]))
+}
+
+impl Protocol for PlayerListAction {
+    type Clean = PlayerListAction;
+
+    fn proto_len(value: &PlayerListAction) -> usize {
+        let players = match *value {
+            PlayerListAction::AddPlayer(ref entries) => entries.iter().map(|entry| {
+                <String as Protocol>::proto_len(&entry.name)
+                    + <Arr<Var<i32>, ProfileProperty> as Protocol>::proto_len(&entry.properties)
+                    + gamemode_len(entry.gamemode)
+                    + <Var<i32> as Protocol>::proto_len(&entry.ping)
+                    + <Option<ChatJson> as Protocol>::proto_len(&entry.display_name)
+            }).sum(),
+            PlayerListAction::UpdateGamemode(ref players) => players.iter().map(|&(_, gamemode)| gamemode_len(gamemode)).sum(),
+            PlayerListAction::UpdateLatency(ref players) => players.iter().map(|&(_, ping)| <Var<i32> as Protocol>::proto_len(&ping)).sum(),
+            PlayerListAction::UpdateDisplayName(ref players) => {
+                players.iter().map(|&(_, ref display_name)| <Option<ChatJson> as Protocol>::proto_len(display_name)).sum()
+            }
+            PlayerListAction::RemovePlayer(_) => 0
+        };
+        <Var<i32> as Protocol>::proto_len(&value.id())
+            + <Var<i32> as Protocol>::proto_len(&(value.count() as i32))
+            + 16 * value.count()
+            + players
+    }
+
+    fn proto_encode(value: &PlayerListAction, dst: &mut Write) -> io::Result<()> {
+        try!(<Var<i32> as Protocol>::proto_encode(&value.id(), dst));
+        try!(<Var<i32> as Protocol>::proto_encode(&(value.count() as i32), dst));
+        match *value {
+            PlayerListAction::AddPlayer(ref entries) => for entry in entries {
+                try!(<Uuid as Protocol>::proto_encode(&entry.uuid, dst));
+                try!(<String as Protocol>::proto_encode(&entry.name, dst));
+                try!(<Arr<Var<i32>, ProfileProperty> as Protocol>::proto_encode(&entry.properties, dst));
+                try!(encode_gamemode(entry.gamemode, dst));
+                try!(<Var<i32> as Protocol>::proto_encode(&entry.ping, dst));
+                try!(<Option<ChatJson> as Protocol>::proto_encode(&entry.display_name, dst));
+            },
+            PlayerListAction::UpdateGamemode(ref players) => for &(ref uuid, gamemode) in players {
+                try!(<Uuid as Protocol>::proto_encode(uuid, dst));
+                try!(encode_gamemode(gamemode, dst));
+            },
+            PlayerListAction::UpdateLatency(ref players) => for &(ref uuid, ping) in players {
+                try!(<Uuid as Protocol>::proto_encode(uuid, dst));
+                try!(<Var<i32> as Protocol>::proto_encode(&ping, dst));
+            },
+            PlayerListAction::UpdateDisplayName(ref players) => for &(ref uuid, ref display_name) in players {
+                try!(<Uuid as Protocol>::proto_encode(uuid, dst));
+                try!(<Option<ChatJson> as Protocol>::proto_encode(display_name, dst));
+            },
+            PlayerListAction::RemovePlayer(ref players) => for uuid in players {
+                try!(<Uuid as Protocol>::proto_encode(uuid, dst));
+            }
+        }
+        Ok(())
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<PlayerListAction> {
+        let id = try!(<Var<i32> as Protocol>::proto_decode(src));
+        let count = try!(<Var<i32> as Protocol>::proto_decode(src));
+        if count < 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("invalid player count {}", count)[..]));
+        }
+        let players = 0..count;
+        Ok(match id {
+            0 => PlayerListAction::AddPlayer(try!(players.map(|_| Ok(PlayerListEntry {
+                uuid: try!(<Uuid as Protocol>::proto_decode(src)),
+                name: try!(<String as Protocol>::proto_decode(src)),
+                properties: try!(<Arr<Var<i32>, ProfileProperty> as Protocol>::proto_decode(src)),
+                gamemode: try!(decode_gamemode(src)),
+                ping: try!(<Var<i32> as Protocol>::proto_decode(src)),
+                display_name: try!(<Option<ChatJson> as Protocol>::proto_decode(src))
+            })).collect::<io::Result<_>>())),
+            1 => PlayerListAction::UpdateGamemode(try!(players.map(|_| {
+                Ok((try!(<Uuid as Protocol>::proto_decode(src)), try!(decode_gamemode(src))))
+            }).collect::<io::Result<_>>())),
+            2 => PlayerListAction::UpdateLatency(try!(players.map(|_| {
+                Ok((try!(<Uuid as Protocol>::proto_decode(src)), try!(<Var<i32> as Protocol>::proto_decode(src))))
+            }).collect::<io::Result<_>>())),
+            3 => PlayerListAction::UpdateDisplayName(try!(players.map(|_| {
+                Ok((try!(<Uuid as Protocol>::proto_decode(src)), try!(<Option<ChatJson> as Protocol>::proto_decode(src))))
+            }).collect::<io::Result<_>>())),
+            4 => PlayerListAction::RemovePlayer(try!(players.map(|_| <Uuid as Protocol>::proto_decode(src)).collect::<io::Result<_>>())),
+            n => return Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("invalid player list action {}", n)[..]))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+    use std::str::FromStr;
+
+    use packet::Protocol;
+    use types::ChatJson;
+    use types::consts::GameMode;
+
+    use uuid::Uuid;
+
+    fn roundtrip(value: PlayerListAction) {
+        let mut dst = Vec::new();
+        <PlayerListAction as Protocol>::proto_encode(&value, &mut dst).unwrap();
+        assert_eq!(dst.len(), <PlayerListAction as Protocol>::proto_len(&value));
+        let mut src = io::Cursor::new(dst);
+        assert_eq!(<PlayerListAction as Protocol>::proto_decode(&mut src).unwrap(), value);
+        assert_eq!(src.position() as usize, src.get_ref().len());
+    }
+
+    #[test]
+    fn actions_roundtrip() {
+        let uuid = Uuid::from_str("069a79f4-44e9-4726-a5be-fca90e38aaf5").unwrap();
+        roundtrip(PlayerListAction::AddPlayer(vec![PlayerListEntry {
+            uuid: uuid.clone(),
+            name: "Notch".to_string(),
+            properties: vec![ProfileProperty { name: "textures".to_string(), value: "e30=".to_string(), signature: None }],
+            gamemode: GameMode::Creative,
+            ping: 150,
+            display_name: Some(ChatJson::from("The Notch"))
+        }]));
+        roundtrip(PlayerListAction::UpdateGamemode(vec![(uuid.clone(), GameMode::Spectator)]));
+        roundtrip(PlayerListAction::UpdateLatency(vec![(uuid.clone(), 1000), (Uuid::nil(), 0)]));
+        roundtrip(PlayerListAction::UpdateDisplayName(vec![(uuid.clone(), None)]));
+        roundtrip(PlayerListAction::RemovePlayer(vec![uuid]));
+    }
+}
@@ -0,0 +1,323 @@
+//! MC Protocol Scoreboard actions, the bodies of the ScoreboardObjective,
+//! UpdateScore and UpdateTeam packets.
+
+use std::io;
+use std::io::prelude::*;
+
+use packet::Protocol;
+use types::{Arr, Var};
+
+/// How an objective's scores are shown.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ObjectiveType {
+    Integer,
+    Hearts
+}
+
+impl ObjectiveType {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ObjectiveType::Integer => "integer",
+            ObjectiveType::Hearts => "hearts"
+        }
+    }
+}
+
+impl Protocol for ObjectiveType {
+    type Clean = ObjectiveType;
+
+    fn proto_len(value: &ObjectiveType) -> usize {
+        <String as Protocol>::proto_len(&value.as_str().to_string())
+    }
+
+    fn proto_encode(value: &ObjectiveType, dst: &mut Write) -> io::Result<()> {
+        <String as Protocol>::proto_encode(&value.as_str().to_string(), dst)
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<ObjectiveType> {
+        match &try!(<String as Protocol>::proto_decode(src))[..] {
+            "integer" => Ok(ObjectiveType::Integer),
+            "hearts" => Ok(ObjectiveType::Hearts),
+            kind => Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("invalid objective type {:?}", kind)[..]))
+        }
+    }
+}
+
+/// Scoreboard Objective action, `value` being the objective's display name.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObjectiveAction {
+    Create { value: String, kind: ObjectiveType },
+    Remove,
+    Update { value: String, kind: ObjectiveType }
+}
+
+impl ObjectiveAction {
+    fn id(&self) -> i8 {
+        match *self {
+            ObjectiveAction::Create { .. } => 0,
+            ObjectiveAction::Remove => 1,
+            ObjectiveAction::Update { .. } => 2
+        }
+    }
+}
+
+impl Protocol for ObjectiveAction {
+    type Clean = ObjectiveAction;
+
+    fn proto_len(value: &ObjectiveAction) -> usize {
+        1 + match *value {
+            ObjectiveAction::Create { ref value, kind } | ObjectiveAction::Update { ref value, kind } => {
+                <String as Protocol>::proto_len(value) + <ObjectiveType as Protocol>::proto_len(&kind)
+            }
+            ObjectiveAction::Remove => 0
+        }
+    }
+
+    fn proto_encode(value: &ObjectiveAction, dst: &mut Write) -> io::Result<()> {
+        try!(<i8 as Protocol>::proto_encode(&value.id(), dst));
+        match *value {
+            ObjectiveAction::Create { ref value, kind } | ObjectiveAction::Update { ref value, kind } => {
+                try!(<String as Protocol>::proto_encode(value, dst));
+                try!(<ObjectiveType as Protocol>::proto_encode(&kind, dst));
+            }
+            ObjectiveAction::Remove => {}
+        }
+        Ok(())
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<ObjectiveAction> {
+        Ok(match try!(<i8 as Protocol>::proto_decode(src)) {
+            0 => ObjectiveAction::Create {
+                value: try!(<String as Protocol>::proto_decode(src)),
+                kind: try!(<ObjectiveType as Protocol>::proto_decode(src))
+            },
+            1 => ObjectiveAction::Remove,
+            2 => ObjectiveAction::Update {
+                value: try!(<String as Protocol>::proto_decode(src)),
+                kind: try!(<ObjectiveType as Protocol>::proto_decode(src))
+            },
+            n => return Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("invalid objective action {}", n)[..]))
+        })
+    }
+}
+
+/// Update Score action, on the score of the entity named in the packet.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScoreAction {
+    Update { objective_name: String, value: i32 },
+    Remove { objective_name: String }
+}
+
+impl Protocol for ScoreAction {
+    type Clean = ScoreAction;
+
+    fn proto_len(value: &ScoreAction) -> usize {
+        1 + match *value {
+            ScoreAction::Update { ref objective_name, value } => {
+                <String as Protocol>::proto_len(objective_name) + <Var<i32> as Protocol>::proto_len(&value)
+            }
+            ScoreAction::Remove { ref objective_name } => <String as Protocol>::proto_len(objective_name)
+        }
+    }
+
+    fn proto_encode(value: &ScoreAction, dst: &mut Write) -> io::Result<()> {
+        match *value {
+            ScoreAction::Update { ref objective_name, value } => {
+                try!(<i8 as Protocol>::proto_encode(&0, dst));
+                try!(<String as Protocol>::proto_encode(objective_name, dst));
+                try!(<Var<i32> as Protocol>::proto_encode(&value, dst));
+            }
+            ScoreAction::Remove { ref objective_name } => {
+                try!(<i8 as Protocol>::proto_encode(&1, dst));
+                try!(<String as Protocol>::proto_encode(objective_name, dst));
+            }
+        }
+        Ok(())
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<ScoreAction> {
+        Ok(match try!(<i8 as Protocol>::proto_decode(src)) {
+            0 => ScoreAction::Update {
+                objective_name: try!(<String as Protocol>::proto_decode(src)),
+                value: try!(<Var<i32> as Protocol>::proto_decode(src))
+            },
+            1 => ScoreAction::Remove { objective_name: try!(<String as Protocol>::proto_decode(src)) },
+            n => return Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("invalid score action {}", n)[..]))
+        })
+    }
+}
+
+/// What a team looks like to its members and everyone else.
+///
+/// `friendly_flags` has bit 0x01 set for friendly fire and 0x02 for
+/// members seeing invisible teammates, `name_tag_visibility` is one of
+/// `always`, `never`, `hideForOtherTeams` and `hideForOwnTeam`, and `color`
+/// is a chat color id, -1 for none.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TeamInfo {
+    pub display_name: String,
+    pub prefix: String,
+    pub suffix: String,
+    pub friendly_flags: i8,
+    pub name_tag_visibility: String,
+    pub color: i8
+}
+
+impl Protocol for TeamInfo {
+    type Clean = TeamInfo;
+
+    fn proto_len(value: &TeamInfo) -> usize {
+        <String as Protocol>::proto_len(&value.display_name)
+            + <String as Protocol>::proto_len(&value.prefix)
+            + <String as Protocol>::proto_len(&value.suffix)
+            + 1
+            + <String as Protocol>::proto_len(&value.name_tag_visibility)
+            + 1
+    }
+
+    fn proto_encode(value: &TeamInfo, dst: &mut Write) -> io::Result<()> {
+        try!(<String as Protocol>::proto_encode(&value.display_name, dst));
+        try!(<String as Protocol>::proto_encode(&value.prefix, dst));
+        try!(<String as Protocol>::proto_encode(&value.suffix, dst));
+        try!(<i8 as Protocol>::proto_encode(&value.friendly_flags, dst));
+        try!(<String as Protocol>::proto_encode(&value.name_tag_visibility, dst));
+        <i8 as Protocol>::proto_encode(&value.color, dst)
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<TeamInfo> {
+        Ok(TeamInfo {
+            display_name: try!(<String as Protocol>::proto_decode(src)),
+            prefix: try!(<String as Protocol>::proto_decode(src)),
+            suffix: try!(<String as Protocol>::proto_decode(src)),
+            friendly_flags: try!(<i8 as Protocol>::proto_decode(src)),
+            name_tag_visibility: try!(<String as Protocol>::proto_decode(src)),
+            color: try!(<i8 as Protocol>::proto_decode(src))
+        })
+    }
+}
+
+/// Update Team action, players being listed by name.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TeamAction {
+    Create { info: TeamInfo, players: Vec<String> },
+    Remove,
+    UpdateInfo(TeamInfo),
+    AddPlayers(Vec<String>),
+    RemovePlayers(Vec<String>)
+}
+
+impl TeamAction {
+    fn id(&self) -> i8 {
+        match *self {
+            TeamAction::Create { .. } => 0,
+            TeamAction::Remove => 1,
+            TeamAction::UpdateInfo(_) => 2,
+            TeamAction::AddPlayers(_) => 3,
+            TeamAction::RemovePlayers(_) => 4
+        }
+    }
+}
+
+impl Protocol for TeamAction {
+    type Clean = TeamAction;
+
+    fn proto_len(value: &TeamAction) -> usize {
+        1 + match *value {
+            TeamAction::Create { ref info, ref players } => {
+                <TeamInfo as Protocol>::proto_len(info) + <Arr<Var<i32>, String> as Protocol>::proto_len(players)
+            }
+            TeamAction::Remove => 0,
+            TeamAction::UpdateInfo(ref info) => <TeamInfo as Protocol>::proto_len(info),
+            TeamAction::AddPlayers(ref players) | TeamAction::RemovePlayers(ref players) => {
+                <Arr<Var<i32>, String> as Protocol>::proto_len(players)
+            }
+        }
+    }
+
+    fn proto_encode(value: &TeamAction, dst: &mut Write) -> io::Result<()> {
+        try!(<i8 as Protocol>::proto_encode(&value.id(), dst));
+        match *value {
+            TeamAction::Create { ref info, ref players } => {
+                try!(<TeamInfo as Protocol>::proto_encode(info, dst));
+                try!(<Arr<Var<i32>, String> as Protocol>::proto_encode(players, dst));
+            }
+            TeamAction::Remove => {}
+            TeamAction::UpdateInfo(ref info) => {
+                try!(<TeamInfo as Protocol>::proto_encode(info, dst));
+            }
+            TeamAction::AddPlayers(ref players) | TeamAction::RemovePlayers(ref players) => {
+                try!(<Arr<Var<i32>, String> as Protocol>::proto_encode(players, dst));
+            }
+        }
+        Ok(())
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<TeamAction> {
+        Ok(match try!(<i8 as Protocol>::proto_decode(src)) {
+            0 => TeamAction::Create {
+                info: try!(<TeamInfo as Protocol>::proto_decode(src)),
+                players: try!(<Arr<Var<i32>, String> as Protocol>::proto_decode(src))
+            },
+            1 => TeamAction::Remove,
+            2 => TeamAction::UpdateInfo(try!(<TeamInfo as Protocol>::proto_decode(src))),
+            3 => TeamAction::AddPlayers(try!(<Arr<Var<i32>, String> as Protocol>::proto_decode(src))),
+            4 => TeamAction::RemovePlayers(try!(<Arr<Var<i32>, String> as Protocol>::proto_decode(src))),
+            n => return Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("invalid team action {}", n)[..]))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fmt::Debug;
+    use std::io;
+
+    use packet::Protocol;
+
+    fn roundtrip<T: Protocol<Clean = T> + Debug + PartialEq>(value: T) {
+        let mut dst = Vec::new();
+        <T as Protocol>::proto_encode(&value, &mut dst).unwrap();
+        assert_eq!(dst.len(), <T as Protocol>::proto_len(&value));
+        let mut src = io::Cursor::new(dst);
+        assert_eq!(<T as Protocol>::proto_decode(&mut src).unwrap(), value);
+        assert_eq!(src.position() as usize, src.get_ref().len());
+    }
+
+    #[test]
+    fn objective_actions() {
+        roundtrip(ObjectiveAction::Create { value: "Deaths".to_string(), kind: ObjectiveType::Integer });
+        roundtrip(ObjectiveAction::Remove);
+        roundtrip(ObjectiveAction::Update { value: "Health".to_string(), kind: ObjectiveType::Hearts });
+        let mut src = io::Cursor::new(b"\x00\x01a\x05stars".to_vec());
+        assert!(<ObjectiveAction as Protocol>::proto_decode(&mut src).is_err());
+    }
+
+    #[test]
+    fn score_actions() {
+        roundtrip(ScoreAction::Update { objective_name: "Deaths".to_string(), value: 300 });
+        roundtrip(ScoreAction::Remove { objective_name: String::new() });
+        let mut src = io::Cursor::new(vec![2, 0]);
+        assert!(<ScoreAction as Protocol>::proto_decode(&mut src).is_err());
+    }
+
+    #[test]
+    fn team_actions() {
+        let info = TeamInfo {
+            display_name: "Red Team".to_string(),
+            prefix: "\u{a7}c".to_string(),
+            suffix: "\u{a7}r".to_string(),
+            friendly_flags: 0x03,
+            name_tag_visibility: "hideForOtherTeams".to_string(),
+            color: 12
+        };
+        roundtrip(TeamAction::Create { info: info.clone(), players: vec!["Steve".to_string(), "Alex".to_string()] });
+        roundtrip(TeamAction::Remove);
+        roundtrip(TeamAction::UpdateInfo(info));
+        roundtrip(TeamAction::AddPlayers(vec!["Notch".to_string()]));
+        roundtrip(TeamAction::RemovePlayers(vec![]));
+        let mut src = io::Cursor::new(vec![5]);
+        assert!(<TeamAction as Protocol>::proto_decode(&mut src).is_err());
+    }
+}
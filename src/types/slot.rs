@@ -1,18 +1,181 @@
 //! Minecraft item stack (inventory slot) data type
 
+use std::collections::HashMap;
 use std::io;
 use std::io::prelude::*;
 
 use nbt;
 
+use item;
 use packet::Protocol;
 
-#[derive(Debug)]
+/// Stacks are equal when they hold as many of the same item, with the
+/// same damage and NBT tag.
+#[derive(Clone, Debug, PartialEq)]
 pub struct Slot {
     id: u16,
     count: u8,
     damage: i16,
-    tag: nbt::Blob
+    tag: Option<nbt::Blob>
+}
+
+impl Slot {
+    /// Creates an item stack without NBT data.
+    pub fn new(id: u16, count: u8, damage: i16) -> Slot {
+        Slot { id: id, count: count, damage: damage, tag: None }
+    }
+
+    pub fn id(&self) -> u16 { self.id }
+    pub fn count(&self) -> u8 { self.count }
+    pub fn damage(&self) -> i16 { self.damage }
+    pub fn tag(&self) -> Option<&nbt::Blob> { self.tag.as_ref() }
+
+    /// Whether `other` can be merged into this stack, ignoring how many
+    /// items each holds.
+    pub fn can_stack_with(&self, other: &Slot) -> bool {
+        self.id == other.id && self.damage == other.damage && self.tag == other.tag
+    }
+
+    /// Most items of this kind a slot holds, from the item registry.
+    pub fn max_stack_size(&self) -> u8 {
+        item::max_stack_size(self.id)
+    }
+
+    /// A copy of this stack holding `count` items.
+    pub fn with_count(&self, count: u8) -> Slot {
+        Slot { count: count, ..self.clone() }
+    }
+
+    /// Takes up to `count` items off this stack, leaving the rest here.
+    pub fn split(&mut self, count: u8) -> Slot {
+        let taken = count.min(self.count);
+        self.count -= taken;
+        self.with_count(taken)
+    }
+
+    /// Moves items from `other` onto this stack, until it holds `limit`
+    /// items or as many as the item stacks to. Nothing moves if the two
+    /// don't stack. Returns how many items moved.
+    pub fn merge(&mut self, other: &mut Slot, limit: u8) -> u8 {
+        if !self.can_stack_with(other) {
+            return 0;
+        }
+        let room = limit.min(self.max_stack_size()).saturating_sub(self.count);
+        let moved = room.min(other.count);
+        self.count += moved;
+        other.count -= moved;
+        moved
+    }
+
+    /// This stack, or `None` when it's run out of items.
+    pub fn non_empty(self) -> Option<Slot> {
+        if self.count > 0 { Some(self) } else { None }
+    }
+
+    pub fn set_count(&mut self, count: u8) {
+        self.count = count;
+    }
+
+    pub fn set_damage(&mut self, damage: i16) {
+        self.damage = damage;
+    }
+
+    pub fn set_tag(&mut self, tag: Option<nbt::Blob>) {
+        self.tag = tag;
+    }
+
+    /// Top level entries of the NBT tag.
+    pub fn tag_entries(&self) -> HashMap<String, nbt::Value> {
+        let tag = match self.tag {
+            Some(ref tag) => tag,
+            None => return HashMap::new()
+        };
+        // Blobs can't be looked into without risking a panic, go through
+        // their encoding instead
+        let mut data = vec![];
+        if tag.write(&mut data).is_err() {
+            return HashMap::new();
+        }
+        let mut src = io::Cursor::new(data);
+        match nbt::Value::read_header(&mut src).and_then(|(id, _)| nbt::Value::from_reader(id, &mut src)) {
+            Ok(nbt::Value::Compound(entries)) => entries,
+            _ => HashMap::new()
+        }
+    }
+
+    /// Replaces the NBT tag with one holding `entries`, none at all if
+    /// there aren't any.
+    pub fn set_tag_entries(&mut self, entries: HashMap<String, nbt::Value>) {
+        if entries.is_empty() {
+            self.tag = None;
+            return;
+        }
+        let mut tag = nbt::Blob::new("".to_string());
+        for (name, value) in entries {
+            // Entries read back from a tag are well formed
+            let _ = tag.insert(name, value);
+        }
+        self.tag = Some(tag);
+    }
+
+    /// Name given to the item in an anvil.
+    pub fn custom_name(&self) -> Option<String> {
+        match self.tag_entries().get("display") {
+            Some(&nbt::Value::Compound(ref display)) => match display.get("Name") {
+                Some(&nbt::Value::String(ref name)) => Some(name.clone()),
+                _ => None
+            },
+            _ => None
+        }
+    }
+
+    pub fn set_custom_name(&mut self, name: Option<String>) {
+        let mut entries = self.tag_entries();
+        let mut display = match entries.remove("display") {
+            Some(nbt::Value::Compound(display)) => display,
+            _ => HashMap::new()
+        };
+        match name {
+            Some(name) => { display.insert("Name".to_string(), nbt::Value::String(name)); }
+            None => { display.remove("Name"); }
+        }
+        if !display.is_empty() {
+            entries.insert("display".to_string(), nbt::Value::Compound(display));
+        }
+        self.set_tag_entries(entries);
+    }
+
+    /// Extra levels anvils charge for working on this item again.
+    pub fn repair_cost(&self) -> i32 {
+        match self.tag_entries().get("RepairCost") {
+            Some(&nbt::Value::Int(cost)) => cost,
+            _ => 0
+        }
+    }
+
+    pub fn set_repair_cost(&mut self, cost: i32) {
+        let mut entries = self.tag_entries();
+        entries.insert("RepairCost".to_string(), nbt::Value::Int(cost));
+        self.set_tag_entries(entries);
+    }
+
+    /// Level of enchantment `id` on this item, 0 when it doesn't have it.
+    pub fn enchantment_level(&self, id: i16) -> i16 {
+        let enchantments = match self.tag_entries().remove("ench") {
+            Some(nbt::Value::List(enchantments)) => enchantments,
+            _ => return 0
+        };
+        for enchantment in enchantments {
+            if let nbt::Value::Compound(enchantment) = enchantment {
+                if enchantment.get("id") == Some(&nbt::Value::Short(id)) {
+                    if let Some(&nbt::Value::Short(level)) = enchantment.get("lvl") {
+                        return level;
+                    }
+                }
+            }
+        }
+        0
+    }
 }
 
 impl Protocol for Option<Slot> {
@@ -20,7 +183,10 @@ impl Protocol for Option<Slot> {
 
     fn proto_len(value: &Option<Slot>) -> usize {
         match *value {
-            Some(ref slot) => 2 + 1 + 2 + <nbt::Blob as Protocol>::proto_len(&slot.tag), // id, count, damage, tag
+            Some(ref slot) => 2 + 1 + 2 + match slot.tag { // id, count, damage, tag
+                Some(ref tag) => <nbt::Blob as Protocol>::proto_len(tag),
+                None => 1
+            },
             None => 2
         }
     }
@@ -31,7 +197,11 @@ impl Protocol for Option<Slot> {
                 try!(<i16 as Protocol>::proto_encode(&(id as i16), dst));
                 try!(<u8 as Protocol>::proto_encode(&count, dst));
                 try!(<i16 as Protocol>::proto_encode(&damage, dst));
-                try!(<nbt::Blob as Protocol>::proto_encode(tag, dst));
+                match *tag {
+                    Some(ref tag) => try!(<nbt::Blob as Protocol>::proto_encode(tag, dst)),
+                    // A lone TAG_End marks the absence of NBT data
+                    None => try!(<u8 as Protocol>::proto_encode(&0, dst))
+                }
             }
             None => { try!(<i16 as Protocol>::proto_encode(&-1, dst)) }
         }
@@ -43,12 +213,94 @@ impl Protocol for Option<Slot> {
         Ok(if id == -1 {
             None
         } else {
+            let count = try!(<u8 as Protocol>::proto_decode(src));
+            let damage = try!(<i16 as Protocol>::proto_decode(src));
+            let tag_type = try!(<u8 as Protocol>::proto_decode(src));
+            let tag = if tag_type == 0 {
+                None
+            } else {
+                let header = [tag_type];
+                Some(try!(<nbt::Blob as Protocol>::proto_decode(&mut (&header[..]).chain(src))))
+            };
             Some(Slot {
                 id: id as u16,
-                count: try!(<u8 as Protocol>::proto_decode(src)),
-                damage: try!(<i16 as Protocol>::proto_decode(src)),
-                tag: try!(<nbt::Blob as Protocol>::proto_decode(src))
+                count: count,
+                damage: damage,
+                tag: tag
             })
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use nbt;
+
+    use packet::Protocol;
+
+    #[test]
+    fn slot_without_tag() {
+        let slot = Some(Slot::new(1, 64, 0));
+        let mut dst = Vec::new();
+        <Option<Slot> as Protocol>::proto_encode(&slot, &mut dst).unwrap();
+        assert_eq!(&dst, &[0, 1, 64, 0, 0, 0]);
+        assert_eq!(dst.len(), <Option<Slot> as Protocol>::proto_len(&slot));
+        let value = <Option<Slot> as Protocol>::proto_decode(&mut io::Cursor::new(dst)).unwrap().unwrap();
+        assert_eq!((value.id(), value.count(), value.tag().is_none()), (1, 64, true));
+    }
+
+    #[test]
+    fn slot_with_tag() {
+        let mut tag = nbt::Blob::new("".to_string());
+        tag.insert("Unbreakable".to_string(), 1i8).unwrap();
+        let mut slot = Slot::new(276, 1, 0);
+        slot.set_tag(Some(tag.clone()));
+        let mut dst = Vec::new();
+        <Option<Slot> as Protocol>::proto_encode(&Some(slot), &mut dst).unwrap();
+        let value = <Option<Slot> as Protocol>::proto_decode(&mut io::Cursor::new(dst)).unwrap().unwrap();
+        assert_eq!(value.tag(), Some(&tag));
+    }
+
+    #[test]
+    fn custom_name() {
+        let mut slot = Slot::new(276, 1, 0);
+        assert_eq!(slot.custom_name(), None);
+        slot.set_custom_name(Some("Sting".to_string()));
+        slot.set_repair_cost(1);
+        assert_eq!(slot.custom_name(), Some("Sting".to_string()));
+        assert_eq!(slot.repair_cost(), 1);
+        slot.set_custom_name(None);
+        assert_eq!(slot.custom_name(), None);
+        assert_eq!(slot.tag_entries().len(), 1);
+    }
+
+    #[test]
+    fn stacking() {
+        let mut stack = Slot::new(1, 40, 0);
+        assert_eq!(stack.max_stack_size(), 64);
+        assert_eq!(Slot::new(256, 1, 0).max_stack_size(), 1);
+        assert!(stack == Slot::new(1, 40, 0));
+        assert!(stack != Slot::new(1, 41, 0));
+
+        let mut named = Slot::new(1, 30, 0);
+        named.set_custom_name(Some("Rock".to_string()));
+        assert!(!stack.can_stack_with(&named) && stack != named.with_count(40));
+        assert_eq!(stack.merge(&mut named, 64), 0);
+
+        let mut other = Slot::new(1, 30, 0);
+        assert_eq!(stack.merge(&mut other, 64), 24);
+        assert_eq!((stack.count(), other.count()), (64, 6));
+        let mut small = Slot::new(1, 2, 0);
+        assert_eq!(small.merge(&mut other, 4), 2);
+        assert_eq!((small.count(), other.count()), (4, 4));
+
+        let taken = stack.split(60);
+        assert_eq!((taken.count(), stack.count()), (60, 4));
+        assert_eq!(stack.split(10).count(), 4);
+        assert!(stack.non_empty().is_none());
+    }
+}
@@ -25,8 +25,30 @@ impl Protocol for String {
 
     fn proto_decode(src: &mut Read) -> io::Result<String> {
         let len: i32 = try!(<Var<i32> as Protocol>::proto_decode(src));
-        let mut s = vec![0u8; len as usize];
-        try!(src.read_exact(&mut s));
+        if len < 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("negative string length {}", len)[..]));
+        }
+        // Grown as data comes rather than trusting the length up front
+        let mut s = vec![];
+        try!(src.take(len as u64).read_to_end(&mut s));
+        if s.len() < len as usize {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "string cut short"));
+        }
         String::from_utf8(s).map_err(|utf8_err| io::Error::new(io::ErrorKind::InvalidInput, &format!("UTF-8 error: {}", utf8_err.utf8_error().description())[..]))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use packet::Protocol;
+
+    #[test]
+    fn bad_lengths() {
+        let mut src = io::Cursor::new(vec![5, b'a', b'b', b'c']);
+        assert_eq!(<String as Protocol>::proto_decode(&mut src).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        let mut src = io::Cursor::new(vec![0xff, 0xff, 0xff, 0xff, 0x0f]);
+        assert!(<String as Protocol>::proto_decode(&mut src).is_err());
+    }
+}
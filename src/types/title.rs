@@ -0,0 +1,110 @@
+//! MC Protocol Title action, the body of the Title packet.
+
+use std::io;
+use std::io::prelude::*;
+
+use packet::Protocol;
+use types::{ChatJson, Var};
+
+/// Title action
+///
+/// Times are in ticks. Setting the title shows it along with whichever
+/// subtitle was set before, `Hide` clears the screen and `Reset` also
+/// forgets the texts and times.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TitleAction {
+    SetTitle(ChatJson),
+    SetSubtitle(ChatJson),
+    SetTimes { fade_in: i32, stay: i32, fade_out: i32 },
+    Hide,
+    Reset
+}
+
+impl TitleAction {
+    fn id(&self) -> i32 {
+        match *self {
+            TitleAction::SetTitle(_) => 0,
+            TitleAction::SetSubtitle(_) => 1,
+            TitleAction::SetTimes { .. } => 2,
+            TitleAction::Hide => 3,
+            TitleAction::Reset => 4
+        }
+    }
+}
+
+impl Protocol for TitleAction {
+    type Clean = TitleAction;
+
+    fn proto_len(value: &TitleAction) -> usize {
+        <Var<i32> as Protocol>::proto_len(&value.id()) + match *value {
+            TitleAction::SetTitle(ref text) | TitleAction::SetSubtitle(ref text) => <ChatJson as Protocol>::proto_len(text),
+            TitleAction::SetTimes { .. } => 12,
+            TitleAction::Hide | TitleAction::Reset => 0
+        }
+    }
+
+    fn proto_encode(value: &TitleAction, dst: &mut Write) -> io::Result<()> {
+        try!(<Var<i32> as Protocol>::proto_encode(&value.id(), dst));
+        match *value {
+            TitleAction::SetTitle(ref text) | TitleAction::SetSubtitle(ref text) => {
+                try!(<ChatJson as Protocol>::proto_encode(text, dst));
+            }
+            TitleAction::SetTimes { fade_in, stay, fade_out } => {
+                try!(<i32 as Protocol>::proto_encode(&fade_in, dst));
+                try!(<i32 as Protocol>::proto_encode(&stay, dst));
+                try!(<i32 as Protocol>::proto_encode(&fade_out, dst));
+            }
+            TitleAction::Hide | TitleAction::Reset => {}
+        }
+        Ok(())
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<TitleAction> {
+        Ok(match try!(<Var<i32> as Protocol>::proto_decode(src)) {
+            0 => TitleAction::SetTitle(try!(<ChatJson as Protocol>::proto_decode(src))),
+            1 => TitleAction::SetSubtitle(try!(<ChatJson as Protocol>::proto_decode(src))),
+            2 => TitleAction::SetTimes {
+                fade_in: try!(<i32 as Protocol>::proto_decode(src)),
+                stay: try!(<i32 as Protocol>::proto_decode(src)),
+                fade_out: try!(<i32 as Protocol>::proto_decode(src))
+            },
+            3 => TitleAction::Hide,
+            4 => TitleAction::Reset,
+            n => return Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("invalid title action {}", n)[..]))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use packet::Protocol;
+    use types::ChatJson;
+
+    fn roundtrip(value: TitleAction) {
+        let mut dst = Vec::new();
+        <TitleAction as Protocol>::proto_encode(&value, &mut dst).unwrap();
+        assert_eq!(dst.len(), <TitleAction as Protocol>::proto_len(&value));
+        let mut src = io::Cursor::new(dst);
+        assert_eq!(<TitleAction as Protocol>::proto_decode(&mut src).unwrap(), value);
+        assert_eq!(src.position() as usize, src.get_ref().len());
+    }
+
+    #[test]
+    fn actions_roundtrip() {
+        roundtrip(TitleAction::SetTitle(ChatJson::from("Welcome")));
+        roundtrip(TitleAction::SetSubtitle(ChatJson::from("to the server")));
+        roundtrip(TitleAction::SetTimes { fade_in: 10, stay: 70, fade_out: 20 });
+        roundtrip(TitleAction::Hide);
+        roundtrip(TitleAction::Reset);
+    }
+
+    #[test]
+    fn invalid_action() {
+        let mut src = io::Cursor::new(vec![5]);
+        assert!(<TitleAction as Protocol>::proto_decode(&mut src).is_err());
+    }
+}
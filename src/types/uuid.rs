@@ -9,6 +9,80 @@ use packet::Protocol;
 
 use uuid::{ParseError, Uuid};
 
+/// `uuid` in its usual hyphenated form, e.g.
+/// "069a79f4-44e9-4726-a5be-fca90e38aaf5".
+///
+/// `Uuid::to_hyphenated_string` swaps the halves of the UUID, so it's built
+/// from the simple form instead.
+pub fn hyphenated(uuid: &Uuid) -> String {
+    let simple = uuid.to_simple_string();
+    format!("{}-{}-{}-{}-{}", &simple[..8], &simple[8..12], &simple[12..16], &simple[16..20], &simple[20..])
+}
+
+/// UUID of player `name` when the server isn't in online mode, the same
+/// as vanilla's so their saved data is theirs again when they come back:
+/// the version 3 UUID of "OfflinePlayer:<name>".
+pub fn offline_uuid(name: &str) -> Uuid {
+    let mut bytes = md5(format!("OfflinePlayer:{}", name).as_bytes());
+    bytes[6] = (bytes[6] & 0x0f) | 0x30;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    Uuid::from_bytes(&bytes).unwrap()
+}
+
+/// MD5 digest of `data`, which version 3 UUIDs are made from.
+fn md5(data: &[u8]) -> [u8; 16] {
+    const SHIFTS: [u32; 64] = [
+        7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22,
+        5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20,
+        4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23,
+        6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21
+    ];
+    let constants: Vec<u32> = (0..64).map(|i| ((i as f64 + 1.0).sin().abs() * 4294967296.0) as u32).collect();
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    let bits = (data.len() as u64).wrapping_mul(8);
+    for i in 0..8 {
+        message.push((bits >> (8 * i)) as u8);
+    }
+
+    let mut state = [0x67452301u32, 0xefcdab89, 0x98badcfe, 0x10325476];
+    for block in message.chunks(64) {
+        let words: Vec<u32> = block.chunks(4)
+            .map(|word| word[0] as u32 | (word[1] as u32) << 8 | (word[2] as u32) << 16 | (word[3] as u32) << 24)
+            .collect();
+        let (mut a, mut b, mut c, mut d) = (state[0], state[1], state[2], state[3]);
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16)
+            };
+            let rotated = a.wrapping_add(f).wrapping_add(constants[i]).wrapping_add(words[g]).rotate_left(SHIFTS[i]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+        state[0] = state[0].wrapping_add(a);
+        state[1] = state[1].wrapping_add(b);
+        state[2] = state[2].wrapping_add(c);
+        state[3] = state[3].wrapping_add(d);
+    }
+
+    let mut digest = [0u8; 16];
+    for (i, word) in state.iter().enumerate() {
+        for j in 0..4 {
+            digest[4 * i + j] = (word >> (8 * j)) as u8;
+        }
+    }
+    digest
+}
+
 /// UUID read/write wrapper.
 impl Protocol for Uuid {
     type Clean = Uuid;
@@ -31,11 +105,11 @@ impl Protocol for UuidString {
     type Clean = Uuid;
 
     fn proto_len(value: &Uuid) -> usize {
-        <String as Protocol>::proto_len(&value.to_hyphenated_string())
+        <String as Protocol>::proto_len(&hyphenated(value))
     }
 
     fn proto_encode(value: &Uuid, dst: &mut Write) -> io::Result<()> {
-        <String as Protocol>::proto_encode(&value.to_hyphenated_string(), dst)
+        <String as Protocol>::proto_encode(&hyphenated(value), dst)
     }
 
     fn proto_decode(src: &mut Read) -> io::Result<Uuid> {
@@ -49,3 +123,34 @@ impl Protocol for UuidString {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+    use std::str::FromStr;
+
+    use packet::Protocol;
+
+    use uuid::Uuid;
+
+    #[test]
+    fn hyphenated_string() {
+        let uuid = Uuid::from_str("069a79f4-44e9-4726-a5be-fca90e38aaf5").unwrap();
+        assert_eq!(hyphenated(&uuid), "069a79f4-44e9-4726-a5be-fca90e38aaf5");
+        let mut dst = vec![];
+        <UuidString as Protocol>::proto_encode(&uuid, &mut dst).unwrap();
+        assert_eq!(dst.len(), <UuidString as Protocol>::proto_len(&uuid));
+        assert_eq!(<UuidString as Protocol>::proto_decode(&mut Cursor::new(dst)).unwrap(), uuid);
+    }
+
+    #[test]
+    fn offline() {
+        assert_eq!(super::md5(b"The quick brown fox jumps over the lazy dog").to_vec(),
+                   vec![0x9e, 0x10, 0x7d, 0x9d, 0x37, 0x2b, 0xb6, 0x82, 0x6b, 0xd8, 0x1d, 0x35, 0x42, 0xa4, 0x19, 0xd6]);
+        assert_eq!(hyphenated(&offline_uuid("Notch")), "b50ad385-829d-3141-a216-7e7d7539ba7f");
+        assert_eq!(offline_uuid("Notch"), offline_uuid("Notch"));
+        assert!(offline_uuid("Notch") != offline_uuid("jeb_"));
+    }
+}
@@ -11,6 +11,43 @@ use packet::Protocol;
 /// Protocol Buffer varint.
 pub struct Var<T>(PhantomData<T>);
 
+impl Var<i32> {
+    /// Most bytes a `Var<i32>` takes.
+    pub const MAX_LEN: usize = 5;
+
+    /// Writes `value` at the start of `dst`, returning how many bytes it
+    /// took. Panics when `dst` is too short, `MAX_LEN` bytes always being
+    /// enough.
+    pub fn encode_into(value: i32, dst: &mut [u8]) -> usize {
+        let mut temp = value as u32;
+        let mut len = 0;
+        while temp & !0x7f != 0 {
+            dst[len] = (temp & 0x7f) as u8 | 0x80;
+            temp >>= 7;
+            len += 1;
+        }
+        dst[len] = temp as u8;
+        len + 1
+    }
+
+    /// Reads the `Var<i32>` at the start of `src`, returning it and how
+    /// many bytes it took.
+    pub fn decode_from(src: &[u8]) -> io::Result<(i32, usize)> {
+        let mut x = 0u32;
+        for (i, &b) in src.iter().take(Var::<i32>::MAX_LEN).enumerate() {
+            x |= ((b & 0x7f) as u32) << (7 * i);
+            if b & 0x80 == 0 {
+                return Ok((x as i32, i + 1));
+            }
+        }
+        if src.len() < Var::<i32>::MAX_LEN {
+            Err(io::Error::new(io::ErrorKind::UnexpectedEof, "VarInt cut short"))
+        } else {
+            Err(io::Error::new(io::ErrorKind::InvalidInput, "VarInt too big"))
+        }
+    }
+}
+
 impl Protocol for Var<i32> {
     type Clean = i32;
 
@@ -27,16 +64,10 @@ impl Protocol for Var<i32> {
 
     /// Writes `value` as a VarInt into `dst`, it can be up to 5 bytes.
     fn proto_encode(value: &i32, dst: &mut Write) -> io::Result<()> {
-        let mut temp = *value as u32;
-        loop {
-            if (temp & !0x7fu32) == 0 {
-                try!(dst.write_u8(temp as u8));
-                return Ok(());
-            } else {
-                try!(dst.write_u8(((temp & 0x7F) | 0x80) as u8));
-                temp >>= 7;
-            }
-        }
+        // One write rather than one per byte
+        let mut buf = [0; 5];
+        let len = Var::<i32>::encode_into(*value, &mut buf);
+        dst.write_all(&buf[..len])
     }
 
     /// Reads up to 5 bytes from `src`, until a valid `Var<i32>` is found.
@@ -172,6 +203,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn varint_slices() {
+        let tests = varint_tests();
+        for test in &tests {
+            let mut buf = [0; 5];
+            let len = Var::<i32>::encode_into(test.value, &mut buf);
+            assert_eq!(&buf[..len], &test.bytes[..]);
+            let mut src = test.bytes.clone();
+            src.push(0x2a);
+            assert_eq!(Var::<i32>::decode_from(&src).unwrap(), (test.value, test.bytes.len()));
+        }
+        for &value in &[i32::min_value(), i32::max_value(), 1 << 14, -300] {
+            let mut buf = [0; 5];
+            let len = Var::<i32>::encode_into(value, &mut buf);
+            assert_eq!(len, <Var<i32> as Protocol>::proto_len(&value));
+            assert_eq!(Var::<i32>::decode_from(&buf[..len]).unwrap(), (value, len));
+        }
+        assert_eq!(Var::<i32>::decode_from(&[0xac]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(Var::<i32>::decode_from(&[0xff; 6]).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
     #[test]
     fn varlong_read() {
         let tests = varlong_tests();
@@ -0,0 +1,173 @@
+//! MC Protocol World Border action, the body of the WorldBorder packet.
+
+use std::io;
+use std::io::prelude::*;
+
+use packet::Protocol;
+use types::Var;
+
+/// World Border action
+///
+/// Diameters are in blocks, `speed` is the time in milliseconds a resize
+/// takes and `warning_time` is in seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorldBorderAction {
+    SetSize { diameter: f64 },
+    LerpSize { old_diameter: f64, new_diameter: f64, speed: i64 },
+    SetCenter { x: f64, z: f64 },
+    Initialize {
+        x: f64,
+        z: f64,
+        old_diameter: f64,
+        new_diameter: f64,
+        speed: i64,
+        portal_teleport_boundary: i32,
+        warning_time: i32,
+        warning_blocks: i32
+    },
+    SetWarningTime { warning_time: i32 },
+    SetWarningBlocks { warning_blocks: i32 }
+}
+
+impl WorldBorderAction {
+    fn id(&self) -> i32 {
+        match *self {
+            WorldBorderAction::SetSize { .. } => 0,
+            WorldBorderAction::LerpSize { .. } => 1,
+            WorldBorderAction::SetCenter { .. } => 2,
+            WorldBorderAction::Initialize { .. } => 3,
+            WorldBorderAction::SetWarningTime { .. } => 4,
+            WorldBorderAction::SetWarningBlocks { .. } => 5
+        }
+    }
+}
+
+impl Protocol for WorldBorderAction {
+    type Clean = WorldBorderAction;
+
+    fn proto_len(value: &WorldBorderAction) -> usize {
+        <Var<i32> as Protocol>::proto_len(&value.id()) + match *value {
+            WorldBorderAction::SetSize { .. } => 8,
+            WorldBorderAction::LerpSize { speed, .. } => 16 + <Var<i64> as Protocol>::proto_len(&speed),
+            WorldBorderAction::SetCenter { .. } => 16,
+            WorldBorderAction::Initialize { speed, portal_teleport_boundary, warning_time, warning_blocks, .. } => {
+                32 + <Var<i64> as Protocol>::proto_len(&speed)
+                   + <Var<i32> as Protocol>::proto_len(&portal_teleport_boundary)
+                   + <Var<i32> as Protocol>::proto_len(&warning_time)
+                   + <Var<i32> as Protocol>::proto_len(&warning_blocks)
+            }
+            WorldBorderAction::SetWarningTime { warning_time } => <Var<i32> as Protocol>::proto_len(&warning_time),
+            WorldBorderAction::SetWarningBlocks { warning_blocks } => <Var<i32> as Protocol>::proto_len(&warning_blocks)
+        }
+    }
+
+    fn proto_encode(value: &WorldBorderAction, dst: &mut Write) -> io::Result<()> {
+        try!(<Var<i32> as Protocol>::proto_encode(&value.id(), dst));
+        match *value {
+            WorldBorderAction::SetSize { diameter } => {
+                try!(<f64 as Protocol>::proto_encode(&diameter, dst));
+            }
+            WorldBorderAction::LerpSize { old_diameter, new_diameter, speed } => {
+                try!(<f64 as Protocol>::proto_encode(&old_diameter, dst));
+                try!(<f64 as Protocol>::proto_encode(&new_diameter, dst));
+                try!(<Var<i64> as Protocol>::proto_encode(&speed, dst));
+            }
+            WorldBorderAction::SetCenter { x, z } => {
+                try!(<f64 as Protocol>::proto_encode(&x, dst));
+                try!(<f64 as Protocol>::proto_encode(&z, dst));
+            }
+            WorldBorderAction::Initialize { x, z, old_diameter, new_diameter, speed, portal_teleport_boundary, warning_time, warning_blocks } => {
+                try!(<f64 as Protocol>::proto_encode(&x, dst));
+                try!(<f64 as Protocol>::proto_encode(&z, dst));
+                try!(<f64 as Protocol>::proto_encode(&old_diameter, dst));
+                try!(<f64 as Protocol>::proto_encode(&new_diameter, dst));
+                try!(<Var<i64> as Protocol>::proto_encode(&speed, dst));
+                try!(<Var<i32> as Protocol>::proto_encode(&portal_teleport_boundary, dst));
+                try!(<Var<i32> as Protocol>::proto_encode(&warning_time, dst));
+                try!(<Var<i32> as Protocol>::proto_encode(&warning_blocks, dst));
+            }
+            WorldBorderAction::SetWarningTime { warning_time } => {
+                try!(<Var<i32> as Protocol>::proto_encode(&warning_time, dst));
+            }
+            WorldBorderAction::SetWarningBlocks { warning_blocks } => {
+                try!(<Var<i32> as Protocol>::proto_encode(&warning_blocks, dst));
+            }
+        }
+        Ok(())
+    }
+
+    fn proto_decode(src: &mut Read) -> io::Result<WorldBorderAction> {
+        Ok(match try!(<Var<i32> as Protocol>::proto_decode(src)) {
+            0 => WorldBorderAction::SetSize { diameter: try!(<f64 as Protocol>::proto_decode(src)) },
+            1 => WorldBorderAction::LerpSize {
+                old_diameter: try!(<f64 as Protocol>::proto_decode(src)),
+                new_diameter: try!(<f64 as Protocol>::proto_decode(src)),
+                speed: try!(<Var<i64> as Protocol>::proto_decode(src))
+            },
+            2 => WorldBorderAction::SetCenter {
+                x: try!(<f64 as Protocol>::proto_decode(src)),
+                z: try!(<f64 as Protocol>::proto_decode(src))
+            },
+            3 => WorldBorderAction::Initialize {
+                x: try!(<f64 as Protocol>::proto_decode(src)),
+                z: try!(<f64 as Protocol>::proto_decode(src)),
+                old_diameter: try!(<f64 as Protocol>::proto_decode(src)),
+                new_diameter: try!(<f64 as Protocol>::proto_decode(src)),
+                speed: try!(<Var<i64> as Protocol>::proto_decode(src)),
+                portal_teleport_boundary: try!(<Var<i32> as Protocol>::proto_decode(src)),
+                warning_time: try!(<Var<i32> as Protocol>::proto_decode(src)),
+                warning_blocks: try!(<Var<i32> as Protocol>::proto_decode(src))
+            },
+            4 => WorldBorderAction::SetWarningTime { warning_time: try!(<Var<i32> as Protocol>::proto_decode(src)) },
+            5 => WorldBorderAction::SetWarningBlocks { warning_blocks: try!(<Var<i32> as Protocol>::proto_decode(src)) },
+            n => return Err(io::Error::new(io::ErrorKind::InvalidInput, &format!("invalid world border action {}", n)[..]))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use packet::Protocol;
+
+    fn roundtrip(value: WorldBorderAction) {
+        let mut dst = Vec::new();
+        <WorldBorderAction as Protocol>::proto_encode(&value, &mut dst).unwrap();
+        assert_eq!(dst.len(), <WorldBorderAction as Protocol>::proto_len(&value));
+        let mut src = io::Cursor::new(dst);
+        assert_eq!(<WorldBorderAction as Protocol>::proto_decode(&mut src).unwrap(), value);
+        assert_eq!(src.position() as usize, src.get_ref().len());
+    }
+
+    #[test]
+    fn initialize_roundtrip() {
+        roundtrip(WorldBorderAction::Initialize {
+            x: 0.5,
+            z: -10.0,
+            old_diameter: 200.0,
+            new_diameter: 100.0,
+            speed: 60000,
+            portal_teleport_boundary: 29999984,
+            warning_time: 15,
+            warning_blocks: 5
+        });
+    }
+
+    #[test]
+    fn actions_roundtrip() {
+        roundtrip(WorldBorderAction::SetSize { diameter: 60000000.0 });
+        roundtrip(WorldBorderAction::LerpSize { old_diameter: 100.0, new_diameter: 50.5, speed: 1 << 40 });
+        roundtrip(WorldBorderAction::SetCenter { x: -128.0, z: 64.25 });
+        roundtrip(WorldBorderAction::SetWarningTime { warning_time: 300 });
+        roundtrip(WorldBorderAction::SetWarningBlocks { warning_blocks: 0 });
+    }
+
+    #[test]
+    fn invalid_action() {
+        let mut src = io::Cursor::new(vec![6]);
+        assert!(<WorldBorderAction as Protocol>::proto_decode(&mut src).is_err());
+    }
+}
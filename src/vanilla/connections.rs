@@ -0,0 +1,139 @@
+//! Limit on the connections being handled at once, each taking a thread.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts connections from accept to close, so a flood of them can't use up
+/// the server's threads.
+pub struct ConnectionLimit {
+    count: Arc<AtomicUsize>,
+    max: usize
+}
+
+impl ConnectionLimit {
+    /// Lets at most `max` connections be handled at once.
+    pub fn new(max: usize) -> ConnectionLimit {
+        ConnectionLimit { count: Arc::new(AtomicUsize::new(0)), max: max }
+    }
+
+    pub fn count(&self) -> usize { self.count.load(Ordering::SeqCst) }
+
+    pub fn max(&self) -> usize { self.max }
+
+    /// Counts a new connection until the returned slot is dropped, `None`
+    /// if there are too many already. The slot can go to the connection's
+    /// thread.
+    pub fn acquire(&self) -> Option<ConnectionSlot> {
+        let previous = self.count.fetch_add(1, Ordering::SeqCst);
+        if previous >= self.max {
+            self.count.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(ConnectionSlot { count: self.count.clone() })
+    }
+}
+
+/// A connection being handled, which stops counting once dropped.
+pub struct ConnectionSlot {
+    count: Arc<AtomicUsize>
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.count.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Counts connections by the address they come from, so no one address
+/// can take up all of some kind of connection, e.g. status connections of
+/// server list scanners.
+pub struct AddressLimit {
+    counts: Mutex<HashMap<IpAddr, usize>>,
+    max: usize
+}
+
+impl AddressLimit {
+    /// Lets at most `max` connections from each address be handled at once.
+    pub fn new(max: usize) -> AddressLimit {
+        AddressLimit { counts: Mutex::new(HashMap::new()), max: max }
+    }
+
+    pub fn count(&self, addr: &IpAddr) -> usize {
+        self.counts.lock().unwrap().get(addr).cloned().unwrap_or(0)
+    }
+
+    pub fn max(&self) -> usize { self.max }
+
+    /// Counts a new connection from `addr` until the returned guard is
+    /// dropped, `None` if there are too many from there already.
+    pub fn enter(&self, addr: IpAddr) -> Option<AddressSlot> {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(addr).or_insert(0);
+        if *count >= self.max {
+            return None;
+        }
+        *count += 1;
+        Some(AddressSlot { limit: self, addr: addr })
+    }
+}
+
+/// A connection from an address, which stops counting once dropped.
+pub struct AddressSlot<'a> {
+    limit: &'a AddressLimit,
+    addr: IpAddr
+}
+
+impl<'a> Drop for AddressSlot<'a> {
+    fn drop(&mut self) {
+        let mut counts = self.limit.counts.lock().unwrap();
+        let left = match counts.get_mut(&self.addr) {
+            Some(count) => {
+                *count -= 1;
+                *count
+            }
+            None => return
+        };
+        // Addresses don't stay around once their connections are gone
+        if left == 0 {
+            counts.remove(&self.addr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    #[test]
+    fn cap() {
+        let limit = ConnectionLimit::new(2);
+        let first = limit.acquire().unwrap();
+        let second = limit.acquire();
+        assert!(second.is_some());
+        assert!(limit.acquire().is_none());
+        thread::spawn(move || drop(first)).join().unwrap();
+        assert_eq!(limit.count(), 1);
+        assert!(limit.acquire().is_some());
+        assert_eq!(limit.count(), 1);
+    }
+
+    #[test]
+    fn per_address() {
+        let limit = AddressLimit::new(2);
+        let (scanner, player) = ("203.0.113.7".parse().unwrap(), "198.51.100.1".parse().unwrap());
+        let first = limit.enter(scanner);
+        let second = limit.enter(scanner);
+        assert!(first.is_some() && second.is_some());
+        assert!(limit.enter(scanner).is_none());
+        assert!(limit.enter(player).is_some());
+        drop(first);
+        assert_eq!(limit.count(&scanner), 1);
+        drop(second);
+        assert_eq!(limit.count(&scanner), 0);
+        assert!(limit.counts.lock().unwrap().is_empty());
+    }
+}
@@ -1,5 +1,8 @@
 //! Vanilla MC components.
 
+pub mod connections;
+pub mod pending;
 pub mod server;
+pub mod watchdog;
 
-pub use self::server::Server;
+pub use self::server::{Server, ServerBuilder};
@@ -0,0 +1,59 @@
+//! Bookkeeping of connections which haven't logged in yet.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts connections still in the handshake, status or login state, so
+/// clients which never get to play can't pile up.
+pub struct PendingConnections {
+    count: AtomicUsize,
+    max: usize
+}
+
+impl PendingConnections {
+    /// Lets at most `max` connections be pending at once.
+    pub fn new(max: usize) -> PendingConnections {
+        PendingConnections { count: AtomicUsize::new(0), max: max }
+    }
+
+    pub fn count(&self) -> usize { self.count.load(Ordering::SeqCst) }
+
+    /// Counts a new connection until the returned guard is dropped, `None`
+    /// if there are too many already.
+    pub fn enter(&self) -> Option<Pending> {
+        let previous = self.count.fetch_add(1, Ordering::SeqCst);
+        if previous >= self.max {
+            self.count.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(Pending { connections: self })
+    }
+}
+
+/// A pending connection, which stops counting once dropped.
+pub struct Pending<'a> {
+    connections: &'a PendingConnections
+}
+
+impl<'a> Drop for Pending<'a> {
+    fn drop(&mut self) {
+        self.connections.count.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cap() {
+        let connections = PendingConnections::new(2);
+        let first = connections.enter();
+        let second = connections.enter();
+        assert!(first.is_some() && second.is_some());
+        assert!(connections.enter().is_none());
+        assert_eq!(connections.count(), 2);
+        drop(first);
+        assert!(connections.enter().is_some());
+        assert_eq!(connections.count(), 1);
+    }
+}
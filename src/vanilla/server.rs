@@ -1,35 +1,116 @@
 //! Vanilla server implementation.
 
+use std::collections::HashMap;
 use std::fs;
 use std::io::{self, Write};
-use std::net::TcpStream;
+use std::net::{Shutdown, TcpStream};
 use std::path::Path;
+use std::process;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-use packet::{NextState, PacketRead, PacketWrite};
+use crash;
+use crypto::{self, KeyPair, SymmStream};
+use events::EventBus;
+use message;
+use packet::{AnyPacket, NextState, PacketRead, PacketWrite, Protocol};
+use permissions::{OpLevels, PermissionProvider};
+use player::{Player, PlayerHandle};
+use proto::mutes::MuteList;
+use proto::favicon::{self, Favicons};
+use proto::ops;
 use proto::properties::Properties;
+use proto::session::{self, Profile};
 use proto::slp;
-use world::World;
+use types::consts::Dimension;
+use types::offline_uuid;
+use vanilla::connections::{AddressLimit, ConnectionLimit};
+use vanilla::pending::PendingConnections;
+use vanilla::watchdog;
+use world::{Departure, Storage, World, WorldHandle};
 
+use rand::{self, Rng};
 use uuid::Uuid;
 
+/// Seconds between saves of every world, vanilla's 6000 ticks.
+pub const AUTOSAVE_INTERVAL: u64 = 300;
+
 /// TODO(toqueteos): Move this to its own module. Proposal: src/vanilla/mod.rs
 pub struct Server {
     addr: String,
     props: Properties,
     // Dummy player storage, just their username.
     // players: Vec<String>,
-    worlds: Vec<World>
+    worlds: Vec<Arc<World>>,
+    /// Op levels by lowercase player name.
+    ops: HashMap<String, i32>,
+    mutes: Arc<MuteList>,
+    events: Arc<EventBus>,
+    /// Connections which haven't logged in yet.
+    pending: PendingConnections,
+    /// All connections being handled, each on its own thread.
+    connections: ConnectionLimit,
+    /// Status connections by address, which server list scanners flood.
+    status_connections: AddressLimit,
+    /// Icons sent in turn to the server list.
+    favicons: Favicons,
+    /// The key clients encrypt their shared secret with, in online mode.
+    key: Option<KeyPair>
 }
 
-impl Server {
-    pub fn new() -> io::Result<Server> {
-        let properties_path = &Path::new("server.properties");
-        let props = match fs::metadata(properties_path) {
-        // let props = match properties_path.metadata() {
-            Ok(_) => try!(Properties::load(properties_path)),
-            Err(_) => Properties::default(),
-        };
+/// Sets a server up without files: from `Properties` given or made here
+/// instead of server.properties, optionally keeping the world in memory.
+///
+/// ```ignore
+/// let server = ServerBuilder::new().port(25570).motd("Testing").in_memory().build();
+/// ```
+pub struct ServerBuilder {
+    props: Properties
+}
+
+impl ServerBuilder {
+    /// Starts from the default properties.
+    pub fn new() -> ServerBuilder {
+        ServerBuilder::with_properties(Properties::default())
+    }
+
+    /// Starts from `props`, e.g. loaded from a file elsewhere.
+    pub fn with_properties(props: Properties) -> ServerBuilder {
+        ServerBuilder { props: props }
+    }
+
+    pub fn port(mut self, port: u16) -> ServerBuilder {
+        self.props.server_port = port;
+        self
+    }
+
+    /// Message shown in the server list.
+    pub fn motd(mut self, motd: &str) -> ServerBuilder {
+        self.props.motd = motd.to_string();
+        self
+    }
+
+    /// Keeps the world in the directory at `path` on disk.
+    pub fn world_path<P: AsRef<Path>>(mut self, path: P) -> ServerBuilder {
+        self.props.level_name = path.as_ref().to_string_lossy().into_owned();
+        self.props.level_storage = "disk".to_string();
+        self
+    }
+
+    /// Keeps the world, and mutes, in memory: generated on start and gone
+    /// on exit.
+    pub fn in_memory(mut self) -> ServerBuilder {
+        self.props.level_storage = "memory".to_string();
+        self
+    }
+
+    pub fn build(self) -> io::Result<Server> {
+        let props = self.props;
         info!("{:?}", props);
+        // Made on start like vanilla does, for clients to encrypt their
+        // shared secret with
+        let key = if props.online_mode { Some(try!(KeyPair::generate())) } else { None };
 
         // There's no *prettier way* of doing this, if it was an Option then
         // there's .unwrap_or but it's just a String.
@@ -38,20 +119,250 @@ impl Server {
         } else {
             props.server_ip.clone()
         };
+        let ops_path = Path::new("ops.json");
+        let ops = match ops::load(ops_path) {
+            Ok(ops) => ops,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => {
+                warn!("failed reading ops.json: {}", err);
+                HashMap::new()
+            }
+        };
+        let mutes = match Storage::of(&props) {
+            Storage::Disk => try!(MuteList::load(Path::new("muted.json"))),
+            Storage::Memory => MuteList::in_memory()
+        };
+        let mutes = Arc::new(mutes);
+        let events = Arc::new(EventBus::new());
+        // The Overworld comes first, then the Nether unless it's disabled
+        // and the End
+        let mut dimensions = vec![Dimension::Overworld];
+        if props.allow_nether {
+            dimensions.push(Dimension::Nether);
+        }
+        dimensions.push(Dimension::End);
+        let mut worlds = vec![];
+        for dimension in dimensions {
+            let world = try!(World::with_dimension(&props, dimension, events.clone(), mutes.clone()));
+            world.set_permissions(Arc::new(OpLevels::vanilla(!ops.is_empty())));
+            // New worlds get their level.dat right away
+            try!(world.save_config());
+            worlds.push(Arc::new(world));
+        }
+        let pending = PendingConnections::new(props.max_pending_connections.max(1) as usize);
+        // Room for a full server with as many clients still logging in,
+        // unless set
+        let max_connections = if props.max_connections > 0 {
+            props.max_connections
+        } else {
+            props.max_players.max(0) + props.max_pending_connections.max(1)
+        };
+        let connections = ConnectionLimit::new(max_connections as usize);
+        let status_connections = AddressLimit::new(props.max_status_connections_per_ip.max(1) as usize);
         Ok(Server {
             addr: addr,
             props: props,
             // players: vec![],
-            worlds: vec![World::new()]
+            worlds: worlds,
+            ops: ops,
+            mutes: mutes,
+            events: events,
+            pending: pending,
+            connections: connections,
+            status_connections: status_connections,
+            favicons: Favicons::new(favicon::ICONS_DIR, favicon::DEFAULT_ICON),
+            key: key
         })
     }
+}
+
+impl Server {
+    /// The server set up by server.properties in the working directory, or
+    /// the defaults if there's none.
+    pub fn new() -> io::Result<Server> {
+        let properties_path = &Path::new("server.properties");
+        let props = match fs::metadata(properties_path) {
+        // let props = match properties_path.metadata() {
+            Ok(_) => try!(Properties::load(properties_path)),
+            Err(_) => Properties::default(),
+        };
+        ServerBuilder::with_properties(props).build()
+    }
 
     pub fn addr(&self) -> &str { return &self.addr }
     pub fn port(&self) -> u16 { self.props.server_port }
 
+    /// Event bus shared by all worlds, subscribe here to react to events.
+    pub fn events(&self) -> &EventBus { &self.events }
+
+    pub fn worlds(&self) -> &[WorldHandle] { &self.worlds }
+
+    /// The player called `name`, ignoring case, in whichever world they're
+    /// in. Locks each player in turn, so mustn't be called while holding a
+    /// player's lock.
+    pub fn player(&self, name: &str) -> Option<PlayerHandle> {
+        let name = name.to_lowercase();
+        self.worlds.iter()
+            .flat_map(|world| world.players())
+            .find(|player| player.lock().unwrap().name().to_lowercase() == name)
+    }
+
+    /// Has serverbound packets with `id` decoded as `P` and emitted as
+    /// `Event::CustomPacket` in every world.
+    pub fn register_packet<P: Protocol<Clean = P> + AnyPacket + 'static>(&self, id: i32) {
+        for world in &self.worlds {
+            world.register_packet::<P>(id);
+        }
+    }
+
+    /// Has `permissions` decide what players may do in every world.
+    pub fn set_permissions(&self, permissions: Arc<PermissionProvider + Send + Sync>) {
+        for world in &self.worlds {
+            world.set_permissions(permissions.clone());
+        }
+    }
+
+    /// Writes a crash report when any thread panics, and unless it was
+    /// handling a connection saves and exits.
+    pub fn install_crash_handler(&self) {
+        crash::install(self.worlds.clone());
+    }
+
+    /// Starts ticking every world on its own thread.
+    pub fn start_ticking(&self) -> io::Result<Vec<JoinHandle<()>>> {
+        let mut handles = vec![];
+        for world in &self.worlds {
+            let world = world.clone();
+            let name = format!("tick {}", world.config().level_name);
+            handles.push(try!(thread::Builder::new().name(name).spawn(move || world.tick_loop())));
+        }
+        Ok(handles)
+    }
+
+    /// Starts saving every world each `AUTOSAVE_INTERVAL` seconds.
+    pub fn start_autosave(&self) -> io::Result<JoinHandle<()>> {
+        let worlds = self.worlds.clone();
+        thread::Builder::new().name("Autosave".to_string()).spawn(move || {
+            loop {
+                thread::sleep(Duration::from_secs(AUTOSAVE_INTERVAL));
+                for world in &worlds {
+                    if let Err(err) = world.save() {
+                        warn!("autosave failed: {}", err);
+                    }
+                }
+            }
+        })
+    }
+
+    /// Saves every world, their settings and chunks.
+    pub fn save_all(&self) -> io::Result<()> {
+        for world in &self.worlds {
+            let chunks = try!(world.save());
+            info!("saved {}, {} chunks written", world.config().level_name, chunks);
+        }
+        Ok(())
+    }
+
+    /// Saves every world and exits.
+    pub fn stop(&self) -> ! {
+        info!("stopping the server");
+        let code = match self.save_all() {
+            Ok(()) => 0,
+            Err(err) => {
+                error!("failed saving the worlds: {}", err);
+                1
+            }
+        };
+        process::exit(code)
+    }
+
+    /// Starts the watchdog shutting the server down when a world stops
+    /// ticking for longer than max-tick-time, unless that's disabled with a
+    /// negative value.
+    pub fn start_watchdog(&self) -> io::Result<Option<JoinHandle<()>>> {
+        if self.props.max_tick_time <= 0 {
+            return Ok(None);
+        }
+        let max_tick_time = Duration::from_millis(self.props.max_tick_time as u64);
+        watchdog::spawn(self.worlds.clone(), max_tick_time, true).map(Some)
+    }
+
+    /// Starts backing up every world each backup-interval minutes, unless
+    /// that's 0.
+    pub fn start_backups(&self) -> io::Result<Option<JoinHandle<()>>> {
+        if self.props.backup_interval <= 0 {
+            return Ok(None);
+        }
+        let interval = Duration::from_secs(self.props.backup_interval as u64 * 60);
+        let worlds = self.worlds.clone();
+        thread::Builder::new().name("Backups".to_string()).spawn(move || {
+            loop {
+                thread::sleep(interval);
+                for world in &worlds {
+                    if let Err(err) = world.backup() {
+                        warn!("scheduled backup failed: {}", err);
+                    }
+                }
+            }
+        }).map(Some)
+    }
+
+    /// Ticks per second of each world, by level name.
+    pub fn tps(&self) -> Vec<(String, f64)> {
+        self.worlds.iter().map(|world| (world.config().level_name, world.tps())).collect()
+    }
+
+    /// Index into `worlds()` of the world of `dimension`, if the server has
+    /// one.
+    pub fn world_of(&self, dimension: Dimension) -> Option<usize> {
+        self.worlds.iter().position(|world| world.dimension() == dimension)
+    }
+
+    /// Moves the player with `uuid` to world `world`, an index into
+    /// `worlds()`. Returns whether they were found.
+    pub fn transfer(&self, uuid: &Uuid, world: usize) -> bool {
+        if world >= self.worlds.len() {
+            return false;
+        }
+        self.worlds.iter().any(|from| from.transfer(uuid, world))
+    }
+
+    /// Handles a newly accepted connection on a thread of its own, or
+    /// closes it right away when max-connections are being handled already.
+    pub fn accept(server: &Arc<Server>, stream: TcpStream) {
+        let slot = match server.connections.acquire() {
+            Some(slot) => slot,
+            None => {
+                debug!("refused connection from {:?}, {} connections already", stream.peer_addr().ok(), server.connections.max());
+                let _ = stream.shutdown(Shutdown::Both);
+                return;
+            }
+        };
+        let server = server.clone();
+        let spawned = thread::Builder::new().name(crash::CONNECTION_THREAD.to_string()).spawn(move || {
+            let _slot = slot;
+            if let Err(err) = server.handle(stream) {
+                info!("{}", err);
+            }
+        });
+        if let Err(err) = spawned {
+            warn!("failed starting a connection thread: {}", err);
+        }
+    }
+
     #[allow(unreachable_code)]
     pub fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
         use packet::handshake::Packet::{self, Handshake};
+
+        let pending = match self.pending.enter() {
+            Some(pending) => pending,
+            None => return Err(io::Error::new(io::ErrorKind::ConnectionRefused, "too many pending connections"))
+        };
+        // Clients get login-timeout seconds to get through to playing, unless
+        // it's 0 or less
+        if self.props.login_timeout > 0 {
+            try!(stream.set_read_timeout(Some(Duration::from_secs(self.props.login_timeout as u64))));
+        }
         let state = match try!(Packet::read(&mut stream)) {
             Handshake(hs) => {
                 debug!("Handshake proto_version={} server_address={} server_port={} next_state={:?}",
@@ -61,10 +372,31 @@ impl Server {
         };
         match state {
             NextState::Status => {
-                try!(slp::response(&mut stream));
-                try!(slp::pong(&mut stream));
+                let addr = try!(stream.peer_addr()).ip();
+                let _slot = match self.status_connections.enter(addr) {
+                    Some(slot) => slot,
+                    None => return Err(io::Error::new(io::ErrorKind::ConnectionRefused, &format!("too many status connections from {}", addr)[..]))
+                };
+                // Asking for the status takes a moment, unless
+                // status-timeout is 0 or less
+                if self.props.status_timeout > 0 {
+                    let timeout = Some(Duration::from_secs(self.props.status_timeout as u64));
+                    try!(stream.set_read_timeout(timeout));
+                    try!(stream.set_write_timeout(timeout));
+                }
+                let online: Vec<_> = self.worlds.iter().flat_map(|world| world.profiles()).collect();
+                let players = slp::Players {
+                    max: self.props.max_players,
+                    online: online.len() as i32,
+                    sample: slp::SampleMode::of(&self.props).sample(&online, &mut rand::thread_rng())
+                };
+                try!(slp::response(&mut stream, &self.props.motd, players, self.favicons.next()));
+                if !try!(slp::pong(&mut stream)) {
+                    debug!("{} asked for the status without pinging", addr);
+                }
             }
             NextState::Login => {
+                use packet::login::clientbound::Disconnect;
                 use packet::login::serverbound::Packet;
                 use packet::login::serverbound::Packet::{LoginStart, EncryptionResponse};
                 use packet::login::clientbound::{LoginSuccess, SetCompression};
@@ -78,29 +410,230 @@ impl Server {
                 };
                 debug!(">> LoginStart name={}", name);
 
-                // NOTE: threshold of `-1` disables compression
+                // Everything from here on is encrypted in online mode
+                let mut stream = SymmStream::new(stream);
+                let profile = match self.key {
+                    Some(ref key) => match try!(authenticate(&mut stream, key, &name)) {
+                        Some(profile) => profile,
+                        None => {
+                            try!(Disconnect { reason: message::UNVERIFIED.into() }.write(&mut stream));
+                            return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{} couldn't be verified", name)));
+                        }
+                    },
+                    // Without authentication players are told apart by
+                    // name, their UUID following from it so their data is
+                    // found again
+                    None => Profile { id: offline_uuid(&name), name: name, properties: vec![] }
+                };
+
+                // NOTE: threshold of `-1` disables compression, which isn't
+                // supported yet so network-compression-threshold is ignored
                 let threshold = -1;
                 try!(SetCompression { threshold: threshold }.write(&mut stream));
                 debug!("<< LoginSetCompression");
                 // try!(stream.flush());
 
                 // NOTE: UUID *MUST* be sent with hyphens
-                try!(LoginSuccess { uuid: Uuid::new_v4(), username: name }.write(&mut stream));
+                let mut player = Player::new(profile.id, profile.name, Box::new(try!(stream.try_clone())));
+                player.set_properties(profile.properties);
+                player.set_socket(try!(stream.get_ref().try_clone()));
+                if let Some(&level) = self.ops.get(&player.name().to_lowercase()) {
+                    player.set_op_level(level);
+                }
+                player.set_muted(self.mutes.is_muted(player.name()));
+                try!(LoginSuccess { uuid: player.uuid().clone(), username: player.name().to_string() }.write(&mut stream));
                 debug!("<< LoginSuccess");
                 // try!(stream.flush());
 
-                // FIXME(toqueteos): Won't work because `name` is moved at `LoginSuccess`.
-                // info!("Player {} joined.", name);
+                info!("Player {} joined.", player.name());
 
                 // TODO(toqueteos): Add `name` to server's player list and do whatever else stuff is
                 // required.
 
                 try!(stream.flush());
+                try!(stream.set_read_timeout(None));
+                drop(pending);
 
                 // TODO(toqueteos): Determine player world and send `stream` to it.
-                try!(self.worlds[0].handle_player(stream));
+                let mut departure = try!(self.worlds[0].handle_player(stream, player));
+                // Players moving between worlds keep their connection thread
+                while let Departure::Transfer { world, stream, player } = departure {
+                    departure = try!(self.worlds[world].arrive(stream, player));
+                }
             }
         }
         Ok(())
     }
 }
+
+/// Sets encryption up with the player logging in as `name` and asks the
+/// session server who they are, `None` if it doesn't know them.
+fn authenticate(stream: &mut SymmStream, key: &KeyPair, name: &str) -> io::Result<Option<Profile>> {
+    use packet::login::clientbound::EncryptionRequest;
+    use packet::login::serverbound::Packet;
+    use packet::login::serverbound::Packet::{LoginStart, EncryptionResponse};
+
+    let mut verify_token = [0u8; 4];
+    rand::thread_rng().fill(&mut verify_token);
+    // Vanilla's server id has been empty since 1.7
+    let server_id = "";
+    try!(EncryptionRequest { server_id: server_id.to_string(), pubkey: key.public_der().to_vec(), verify_token: verify_token.to_vec() }
+        .write(stream));
+    debug!("<< EncryptionRequest");
+    let response = match try!(Packet::read(stream)) {
+        EncryptionResponse(response) => response,
+        LoginStart(_) => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                       "Expecting login::serverbound::EncryptionResponse packet, got LoginStart"));
+        }
+    };
+    debug!(">> EncryptionResponse");
+    if try!(key.decrypt(&response.verify_token)) != verify_token {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "verify token doesn't match"));
+    }
+    let secret = try!(key.decrypt(&response.shared_secret));
+    try!(stream.encrypt(&secret));
+    session::has_joined(name, &crypto::server_hash(server_id, &secret, key.public_der()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proto::properties::Properties;
+    use world::Storage;
+
+    #[test]
+    fn building() {
+        let server = ServerBuilder::new()
+            .port(25570)
+            .motd("Testing")
+            .in_memory()
+            .build()
+            .unwrap();
+        assert_eq!(server.port(), 25570);
+        assert_eq!(server.props.motd, "Testing");
+        assert_eq!(Storage::of(&server.props), Storage::Memory);
+        assert_eq!(server.worlds().len(), 3);
+        assert_eq!((server.world_of(Dimension::Overworld), server.world_of(Dimension::Nether), server.world_of(Dimension::End)), (Some(0), Some(1), Some(2)));
+        let server = ServerBuilder::with_properties(Properties { allow_nether: false, ..Properties::default() }).in_memory().build().unwrap();
+        assert_eq!((server.worlds().len(), server.world_of(Dimension::Nether)), (2, None));
+
+        let props = ServerBuilder::with_properties(Properties { server_port: 1, ..Properties::default() })
+            .world_path("worlds/test")
+            .props;
+        assert_eq!((props.server_port, &props.level_name[..], Storage::of(&props)), (1, "worlds/test", Storage::Disk));
+    }
+
+    #[test]
+    fn rejoining() {
+        use std::env;
+        use std::io;
+        use std::net::{TcpListener, TcpStream};
+        use std::time::Instant;
+
+        use consts;
+        use packet::handshake::Handshake;
+        use packet::login::serverbound::LoginStart;
+        use packet::play::serverbound::ClientSettings;
+        use player::SpawnPoint;
+        use types::consts::ChatMode;
+
+        // Waits for `joined` to be whether Steve is in the server
+        fn wait(server: &Server, joined: bool) {
+            let start = Instant::now();
+            while server.player("Steve").is_some() != joined {
+                assert!(start.elapsed() < Duration::from_secs(30), "Steve never {}", if joined { "joined" } else { "left" });
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+
+        let dir = env::temp_dir().join(format!("hematite-rejoining-{}", ::std::process::id()));
+        let server = Arc::new(ServerBuilder::with_properties(Properties { view_distance: 1, allow_nether: false, online_mode: false, ..Properties::default() })
+            .world_path(dir.join("world"))
+            .build()
+            .unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let join = || {
+            let mut client = TcpStream::connect(addr).unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            Server::accept(&server, stream);
+            Handshake { proto_version: consts::PROTO_VERSION, server_address: "127.0.0.1".to_string(), server_port: addr.port(), next_state: NextState::Login }
+                .write(&mut client).unwrap();
+            LoginStart { name: "Steve".to_string() }.write(&mut client).unwrap();
+            ClientSettings { locale: "en_US".to_string(), view_distance: 1, chat_mode: ChatMode::Enabled, chat_colors: true, displayed_skin_parts: 0x7f }
+                .write(&mut client).unwrap();
+            // Whatever the server sends is dropped, so it's never stuck
+            // writing chunks
+            let mut incoming = client.try_clone().unwrap();
+            thread::spawn(move || io::copy(&mut incoming, &mut io::sink()));
+            wait(&server, true);
+            client
+        };
+
+        let client = join();
+        let spawn_point = Some(SpawnPoint { location: [12, 70, -3], forced: true });
+        let uuid = {
+            let steve = server.player("Steve").unwrap();
+            let mut steve = steve.lock().unwrap();
+            steve.set_spawn_point(spawn_point);
+            steve.uuid().clone()
+        };
+        assert_eq!(uuid, offline_uuid("Steve"));
+        client.shutdown(Shutdown::Both).unwrap();
+        wait(&server, false);
+
+        let client = join();
+        {
+            let steve = server.player("Steve").unwrap();
+            let steve = steve.lock().unwrap();
+            assert_eq!((steve.uuid(), steve.spawn_point()), (&uuid, spawn_point));
+        }
+        client.shutdown(Shutdown::Both).unwrap();
+        wait(&server, false);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn online_mode() {
+        use std::io::Read;
+        use std::net::{TcpListener, TcpStream};
+
+        use openssl::rsa::Rsa;
+
+        use consts;
+        use packet::handshake::Handshake;
+        use packet::login::clientbound::Packet;
+        use packet::login::serverbound::{EncryptionResponse, LoginStart};
+
+        let server = Arc::new(ServerBuilder::with_properties(Properties { online_mode: true, allow_nether: false, ..Properties::default() })
+            .in_memory()
+            .build()
+            .unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(addr).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        Server::accept(&server, stream);
+        Handshake { proto_version: consts::PROTO_VERSION, server_address: "127.0.0.1".to_string(), server_port: addr.port(), next_state: NextState::Login }
+            .write(&mut client).unwrap();
+        LoginStart { name: "Steve".to_string() }.write(&mut client).unwrap();
+        let request = match Packet::read(&mut client).unwrap() {
+            Packet::EncryptionRequest(request) => request,
+            _ => panic!("expected an encryption request")
+        };
+        assert_eq!(request.server_id, "");
+        assert_eq!(request.verify_token.len(), 4);
+        assert!(Rsa::public_key_from_der(&request.pubkey).is_ok());
+
+        // A token not encrypted with the key gets the client dropped, before
+        // anything is asked of the session server
+        EncryptionResponse { shared_secret: vec![0; 128], verify_token: vec![0; 128] }.write(&mut client).unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+        let mut rest = vec![];
+        client.read_to_end(&mut rest).unwrap();
+        assert!(rest.is_empty());
+        assert!(server.player("Steve").is_none());
+    }
+}
@@ -0,0 +1,61 @@
+//! Watchdog noticing worlds whose tick loop stopped ticking.
+
+use std::backtrace::Backtrace;
+use std::io;
+use std::sync::Arc;
+use std::thread::{self, sleep, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crash::{self, Crash};
+use world::World;
+
+/// How often tick heartbeats are checked.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// Starts a thread checking that every world in `worlds` ticks at least
+/// once every `max_tick_time`. A stalled world gets a crash report, and the
+/// server is shut down if `shutdown` is set, like vanilla does.
+pub fn spawn(worlds: Vec<Arc<World>>, max_tick_time: Duration, shutdown: bool) -> io::Result<JoinHandle<()>> {
+    thread::Builder::new().name("Server Watchdog".to_string()).spawn(move || {
+        let started = Instant::now();
+        // Worlds already reported, so a stall is only reported once
+        let mut reported = vec![false; worlds.len()];
+        loop {
+            sleep(INTERVAL);
+            for (index, world) in worlds.iter().enumerate() {
+                let last = world.last_tick_end().unwrap_or(started);
+                let stalled = last.elapsed();
+                if stalled <= max_tick_time {
+                    reported[index] = false;
+                    continue;
+                }
+                if reported[index] {
+                    continue;
+                }
+                reported[index] = true;
+                let crash = Crash {
+                    message: format!("Watching Server: a single server tick took {:.2} seconds (should be max {:.2})",
+                                     as_secs(stalled), as_secs(max_tick_time)),
+                    location: None,
+                    // Not the world's name, its lock may be what's stuck
+                    thread: format!("tick thread of world {}", index),
+                    // Other threads' stacks can't be had, this is the
+                    // watchdog's own
+                    backtrace: Backtrace::force_capture().to_string()
+                };
+                if shutdown {
+                    crash::crash_and_exit(&crash, &worlds);
+                }
+                let text = crash::report(&crash, &worlds);
+                match crash::write_report(&text) {
+                    Ok(path) => error!("world {} stalled, report saved to {}", index, path.display()),
+                    Err(err) => error!("world {} stalled, couldn't save report ({}):\n{}", index, err, text)
+                }
+            }
+        }
+    })
+}
+
+fn as_secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1e9
+}
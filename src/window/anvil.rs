@@ -0,0 +1,239 @@
+//! Anvils: repairing and renaming items for experience levels.
+
+use item;
+use types::Slot;
+
+/// Window slot of the item being worked on.
+pub const LEFT_SLOT: usize = 0;
+
+/// Window slot of the repair material or sacrificed item.
+pub const RIGHT_SLOT: usize = 1;
+
+pub const OUTPUT_SLOT: usize = 2;
+
+/// Number of anvil slots, the player's inventory comes after them.
+pub const SIZE: usize = 3;
+
+/// Cost, in levels, from which survival players can't use an anvil anymore.
+pub const TOO_EXPENSIVE: i32 = 40;
+
+/// Slots and text field of an anvil window.
+#[derive(Clone, Debug)]
+pub struct Anvil {
+    /// Location of the anvil block, which wears out with use.
+    pub location: [i32; 3],
+    inputs: [Option<Slot>; 2],
+    /// Name typed in the text field.
+    name: Option<String>,
+    /// Name the client filled the text field with when the item was put in.
+    /// Item names aren't known here, so this tells whether the player
+    /// actually typed something new.
+    original_name: Option<String>,
+    output: Option<Slot>,
+    cost: i32,
+    /// Items taken off the right input along with the output.
+    used: u8
+}
+
+impl Anvil {
+    pub fn new(location: [i32; 3]) -> Anvil {
+        Anvil {
+            location: location,
+            inputs: [None, None],
+            name: None,
+            original_name: None,
+            output: None,
+            cost: 0,
+            used: 0
+        }
+    }
+
+    /// Levels taking the output costs, sent as window property 0.
+    pub fn cost(&self) -> i32 { self.cost }
+
+    /// Contents of the anvil's slots.
+    pub fn items(&self) -> Vec<Option<Slot>> {
+        vec![self.inputs[0].clone(), self.inputs[1].clone(), self.output.clone()]
+    }
+
+    /// Input slot `index`, `None` for the output.
+    pub fn input_mut(&mut self, index: usize) -> Option<&mut Option<Slot>> {
+        if index == LEFT_SLOT {
+            // Whatever the client fills the text field with next is the
+            // name of the new item
+            self.name = None;
+            self.original_name = None;
+        }
+        self.inputs.get_mut(index)
+    }
+
+    /// Sets the name typed in the text field, as sent in MC|ItemName.
+    pub fn set_name(&mut self, name: String) {
+        if self.inputs[0].is_some() && self.original_name.is_none() {
+            self.original_name = Some(name);
+        } else {
+            self.name = Some(name);
+        }
+    }
+
+    /// Works out the output and its cost after an input or the name
+    /// changed.
+    pub fn update(&mut self, creative: bool) {
+        let rename = match (&self.name, &self.original_name) {
+            (&Some(ref name), &Some(ref original)) if name != original => Some(&name[..]),
+            _ => None
+        };
+        let result = match self.inputs[0] {
+            Some(ref left) => combine(left, self.inputs[1].as_ref(), rename),
+            None => None
+        };
+        match result {
+            Some((output, cost, used)) => {
+                self.output = if cost >= TOO_EXPENSIVE && !creative { None } else { Some(output) };
+                self.cost = cost;
+                self.used = used;
+            }
+            None => {
+                self.output = None;
+                self.cost = 0;
+                self.used = 0;
+            }
+        }
+    }
+
+    /// Takes the output, using up the inputs. Returns it along with its
+    /// cost in levels.
+    pub fn take_output(&mut self, creative: bool) -> Option<(Slot, i32)> {
+        let output = match self.output.take() {
+            Some(output) => output,
+            None => return None
+        };
+        let cost = self.cost;
+        self.inputs[0] = None;
+        self.inputs[1] = match self.inputs[1].take() {
+            Some(ref right) if self.used > 0 && right.count() > self.used => {
+                let mut left = right.clone();
+                left.set_count(right.count() - self.used);
+                Some(left)
+            }
+            Some(right) => if self.used > 0 { None } else { Some(right) },
+            None => None
+        };
+        self.name = None;
+        self.original_name = None;
+        self.update(creative);
+        Some((output, cost))
+    }
+
+    /// Empties the input slots, when the window is closed.
+    pub fn take_inputs(&mut self) -> Vec<Slot> {
+        self.output = None;
+        self.cost = 0;
+        self.inputs[0].take().into_iter().chain(self.inputs[1].take()).collect()
+    }
+}
+
+/// Works on `left` with `right`, either its repair material or another one
+/// of the same item, then gives it the name `rename`, a blank one removing
+/// its custom name. Returns the resulting item, its cost in levels and how
+/// many items of `right` it uses up, or `None` if nothing would change.
+pub fn combine(left: &Slot, right: Option<&Slot>, rename: Option<&str>) -> Option<(Slot, i32, u8)> {
+    let mut output = left.clone();
+    let mut cost = 0;
+    let mut used = 0;
+    if let Some(right) = right {
+        let max = match item::max_damage(left.id()) {
+            Some(max) => max,
+            None => return None
+        };
+        if item::repair_material(left.id()) == Some(right.id()) {
+            // Each unit of material repairs a quarter of the durability
+            let mut damage = left.damage();
+            if damage <= 0 {
+                return None;
+            }
+            while damage > 0 && used < right.count() {
+                damage -= (max / 4).min(damage);
+                used += 1;
+                cost += 1;
+            }
+            output.set_damage(damage);
+        } else if right.id() == left.id() {
+            // Both durabilities add up, with a 12% bonus
+            let remaining = (max - left.damage()) + (max - right.damage()) + max * 12 / 100;
+            let damage = (max - remaining).max(0);
+            if damage < left.damage() {
+                output.set_damage(damage);
+                cost += 2;
+            }
+            used = 1;
+        } else {
+            return None;
+        }
+    }
+    if let Some(name) = rename {
+        if name.trim().is_empty() {
+            if left.custom_name().is_some() {
+                output.set_custom_name(None);
+                cost += 1;
+            }
+        } else {
+            output.set_custom_name(Some(name.to_string()));
+            cost += 1;
+        }
+    }
+    if cost == 0 {
+        return None;
+    }
+    let prior = left.repair_cost().max(right.map_or(0, |right| right.repair_cost()));
+    let mut total = left.repair_cost() + right.map_or(0, |right| right.repair_cost()) + cost;
+    // Renaming alone is never too expensive
+    if right.is_none() && total >= TOO_EXPENSIVE {
+        total = TOO_EXPENSIVE - 1;
+    }
+    output.set_repair_cost(prior * 2 + 1);
+    Some((output, total, used))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use item;
+    use types::Slot;
+
+    #[test]
+    fn repair_with_material() {
+        let sword = Slot::new(267, 1, 200);
+        let (output, cost, used) = combine(&sword, Some(&Slot::new(item::IRON_INGOT, 5, 0)), None).unwrap();
+        // 250 durability, 62 per ingot
+        assert_eq!((output.damage(), cost, used), (0, 4, 4));
+        assert_eq!(output.repair_cost(), 1);
+        assert!(combine(&Slot::new(267, 1, 0), Some(&Slot::new(item::IRON_INGOT, 1, 0)), None).is_none());
+    }
+
+    #[test]
+    fn combine_and_rename() {
+        let (output, cost, used) = combine(&Slot::new(267, 1, 200), Some(&Slot::new(267, 1, 100)), Some("Sting")).unwrap();
+        assert_eq!(output.damage(), 20);
+        assert_eq!(output.custom_name(), Some("Sting".to_string()));
+        assert_eq!((cost, used), (3, 1));
+        assert!(combine(&Slot::new(1, 1, 0), Some(&Slot::new(1, 1, 0)), None).is_none());
+    }
+
+    #[test]
+    fn name_field() {
+        let mut anvil = Anvil::new([0, 64, 0]);
+        *anvil.input_mut(LEFT_SLOT).unwrap() = Some(Slot::new(1, 64, 0));
+        // The client first fills in the current name
+        anvil.set_name("Stone".to_string());
+        anvil.update(false);
+        assert_eq!(anvil.cost(), 0);
+        anvil.set_name("Rock".to_string());
+        anvil.update(false);
+        assert_eq!(anvil.cost(), 1);
+        let (output, cost) = anvil.take_output(false).unwrap();
+        assert_eq!((output.count(), cost), (64, 1));
+        assert!(anvil.items().iter().all(|slot| slot.is_none()));
+    }
+}
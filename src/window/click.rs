@@ -0,0 +1,655 @@
+//! The ClickWindow modes, worked out on a copy of a window's slots so that
+//! invalid clicks leave the real ones alone.
+
+use block_entity::{furnace, BlockEntity};
+use block_entity::brewing_stand;
+use entity::horse::{self, Horse};
+use item;
+use player::{ARMOR_START, MAIN_START};
+use types::Slot;
+use window::{self, anvil, OUTSIDE};
+
+/// Number of slots the player's main inventory and hotbar take at the
+/// bottom of every window.
+pub const INVENTORY_SLOTS: usize = 27 + 9;
+
+/// Where the hotbar starts among those.
+const HOTBAR_OFFSET: usize = 27;
+
+/// What the slots at the top of a window allow, the player's inventory
+/// below them takes anything.
+pub trait Rules {
+    /// Whether `stack` can be put in slot `index`.
+    fn accepts(&self, index: usize, stack: &Slot) -> bool;
+
+    /// Whether shift-clicking `stack` in the inventory may move it to slot
+    /// `index`.
+    fn shift_accepts(&self, index: usize, stack: &Slot) -> bool {
+        self.accepts(index, stack)
+    }
+
+    /// Whether slot `index` is an output, which can only be taken from as a
+    /// whole.
+    fn output(&self, _index: usize) -> bool { false }
+
+    /// Whether slot `index` can be taken from right now.
+    fn can_take(&self, _index: usize) -> bool { true }
+
+    /// Most items slot `index` holds, whatever the item.
+    fn max_stack(&self, _index: usize) -> u8 { 64 }
+}
+
+/// The crafting and armor slots heading the player's own inventory, window
+/// 0. Nothing can be crafted yet, so the crafting output stays empty.
+pub struct InventoryRules;
+
+impl Rules for InventoryRules {
+    fn accepts(&self, index: usize, stack: &Slot) -> bool {
+        match index {
+            0 => false,
+            _ if index < ARMOR_START => true,
+            _ => index < MAIN_START && item::armor_slot(stack.id()) == Some(index - ARMOR_START)
+        }
+    }
+
+    fn shift_accepts(&self, index: usize, stack: &Slot) -> bool {
+        index >= ARMOR_START && self.accepts(index, stack)
+    }
+
+    fn output(&self, index: usize) -> bool { index == 0 }
+
+    fn can_take(&self, index: usize) -> bool { index != 0 }
+
+    fn max_stack(&self, index: usize) -> u8 {
+        if index >= ARMOR_START { 1 } else { 64 }
+    }
+}
+
+impl Rules for Horse {
+    fn accepts(&self, index: usize, stack: &Slot) -> bool {
+        Horse::accepts(self, index, stack)
+    }
+
+    fn max_stack(&self, index: usize) -> u8 {
+        if index == horse::SADDLE_SLOT || index == horse::ARMOR_SLOT { 1 } else { 64 }
+    }
+}
+
+impl Rules for BlockEntity {
+    fn accepts(&self, index: usize, stack: &Slot) -> bool {
+        BlockEntity::accepts(self, index, stack)
+    }
+
+    /// Only things which smelt are shift-clicked into a furnace's input.
+    fn shift_accepts(&self, index: usize, stack: &Slot) -> bool {
+        match *self {
+            BlockEntity::Furnace(_) if index == furnace::INPUT_SLOT => furnace::smelt(stack.id(), stack.damage()).is_some(),
+            _ => self.accepts(index, stack)
+        }
+    }
+
+    fn output(&self, index: usize) -> bool {
+        match *self {
+            BlockEntity::Furnace(_) => index == furnace::OUTPUT_SLOT,
+            BlockEntity::BrewingStand(_) | BlockEntity::Sign(_) => false
+        }
+    }
+
+    fn max_stack(&self, index: usize) -> u8 {
+        match *self {
+            BlockEntity::BrewingStand(_) if index != brewing_stand::INGREDIENT_SLOT => 1,
+            _ => 64
+        }
+    }
+}
+
+/// An anvil's slots, whose output can only be taken by players who can pay
+/// for it.
+pub struct AnvilRules {
+    pub affordable: bool
+}
+
+impl Rules for AnvilRules {
+    fn accepts(&self, index: usize, _stack: &Slot) -> bool {
+        index != anvil::OUTPUT_SLOT
+    }
+
+    fn output(&self, index: usize) -> bool { index == anvil::OUTPUT_SLOT }
+
+    fn can_take(&self, index: usize) -> bool {
+        index != anvil::OUTPUT_SLOT || self.affordable
+    }
+}
+
+/// How a drag spreads the cursor over the slots it painted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DragKind {
+    /// Left button, splitting the cursor evenly.
+    Split,
+    /// Right button, one item per slot.
+    One,
+    /// Middle button in creative mode, a full stack per slot.
+    Fill
+}
+
+/// A drag in progress. Mode 5 clicks start it, paint slots and end it,
+/// and any other click abandons it.
+#[derive(Clone, Debug)]
+pub struct Drag {
+    pub kind: DragKind,
+    pub slots: Vec<usize>
+}
+
+/// Slots of a window, its own ones followed by the player's main inventory
+/// and hotbar, along with the cursor.
+pub struct View<'a> {
+    slots: Vec<Option<Slot>>,
+    cursor: Option<Slot>,
+    /// Stacks thrown out of the window.
+    dropped: Vec<Slot>,
+    /// Number of the window's own slots.
+    size: usize,
+    rules: &'a Rules,
+    creative: bool
+}
+
+impl<'a> View<'a> {
+    /// A window whose own slots hold `container`, above the main inventory
+    /// and hotbar `inventory`.
+    pub fn new(container: Vec<Option<Slot>>, inventory: Vec<Option<Slot>>, cursor: Option<Slot>,
+               rules: &'a Rules, creative: bool) -> View<'a> {
+        let size = container.len();
+        let mut slots = container;
+        slots.extend(inventory);
+        View { slots: slots, cursor: cursor, dropped: vec![], size: size, rules: rules, creative: creative }
+    }
+
+    pub fn container(&self) -> &[Option<Slot>] { &self.slots[..self.size] }
+    pub fn inventory(&self) -> &[Option<Slot>] { &self.slots[self.size..] }
+    pub fn cursor(&self) -> Option<&Slot> { self.cursor.as_ref() }
+    pub fn dropped(&self) -> &[Slot] { &self.dropped }
+
+    /// Window slot `index`, `None` when it's empty or out of range.
+    pub fn slot(&self, index: usize) -> Option<&Slot> {
+        self.slots.get(index).and_then(|slot| slot.as_ref())
+    }
+
+    /// Splits the view into the window's own slots, the inventory, the
+    /// cursor and the stacks thrown out.
+    pub fn into_parts(mut self) -> (Vec<Option<Slot>>, Vec<Option<Slot>>, Option<Slot>, Vec<Slot>) {
+        let inventory = self.slots.split_off(self.size);
+        (self.slots, inventory, self.cursor, self.dropped)
+    }
+
+    /// Clicks window slot `slot` with `button` in ClickWindow `mode`, moving
+    /// `drag` along. Returns whether the click made sense, clicks which
+    /// don't change anything but could have been done by the client count
+    /// as valid.
+    pub fn click(&mut self, slot: i16, button: i8, mode: i8, drag: &mut Option<Drag>) -> bool {
+        if mode != 5 {
+            *drag = None;
+        }
+        if slot == OUTSIDE {
+            return match (mode, button) {
+                (0, 0) | (0, 1) => {
+                    self.drop_cursor(button == 1);
+                    true
+                }
+                // Clicks outside with nothing held
+                (4, 0) | (4, 1) => true,
+                (5, _) => self.drag(None, button, drag),
+                _ => false
+            };
+        }
+        // The client sends -1 for clicks on the window's border
+        if slot == -1 && mode != 5 {
+            return true;
+        }
+        if slot < 0 || slot as usize >= self.slots.len() {
+            *drag = None;
+            return false;
+        }
+        let index = slot as usize;
+        match (mode, button) {
+            (0, 0) | (0, 1) => self.pickup(index, button == 1),
+            (1, 0) | (1, 1) => self.shift(index),
+            (2, _) if button >= 0 && button < 9 => self.swap(index, button as usize),
+            (3, 2) => self.clone_stack(index),
+            (4, 0) | (4, 1) => self.drop(index, button == 1),
+            (5, _) => self.drag(Some(index), button, drag),
+            (6, 0) => self.gather(index),
+            _ => false
+        }
+    }
+
+    fn accepts(&self, index: usize, stack: &Slot) -> bool {
+        index >= self.size || self.rules.accepts(index, stack)
+    }
+
+    fn output(&self, index: usize) -> bool {
+        index < self.size && self.rules.output(index)
+    }
+
+    fn can_take(&self, index: usize) -> bool {
+        index >= self.size || self.rules.can_take(index)
+    }
+
+    /// Most items of `stack`'s kind slot `index` holds.
+    fn max_stack(&self, index: usize, stack: &Slot) -> u8 {
+        let max = stack.max_stack_size();
+        if index < self.size { max.min(self.rules.max_stack(index)) } else { max }
+    }
+
+    /// Mode 0, a plain left or right click.
+    fn pickup(&mut self, index: usize, right: bool) -> bool {
+        if !self.can_take(index) && self.slots[index].is_some() {
+            return true;
+        }
+        if self.output(index) {
+            window::take(&mut self.slots[index], &mut self.cursor);
+            return true;
+        }
+        let accepted = self.cursor.as_ref().map_or(true, |held| self.accepts(index, held));
+        if accepted {
+            let limit = self.rules_limit(index);
+            window::click_limited(&mut self.slots[index], &mut self.cursor, right, limit);
+        } else if let Some(mut held) = self.cursor.take() {
+            // What can't go in the slot can still pick up more of the same
+            if let Some(mut stack) = self.slots[index].take() {
+                held.merge(&mut stack, 64);
+                self.slots[index] = stack.non_empty();
+            }
+            self.cursor = Some(held);
+        }
+        true
+    }
+
+    fn rules_limit(&self, index: usize) -> u8 {
+        if index < self.size { self.rules.max_stack(index) } else { 64 }
+    }
+
+    /// Mode 1, shift-clicking moves a stack between the window's slots and
+    /// the inventory, or between the main inventory and the hotbar when
+    /// none of the window's slots take it.
+    fn shift(&mut self, index: usize) -> bool {
+        if !self.can_take(index) {
+            return true;
+        }
+        let stack = match self.slots[index].take() {
+            Some(stack) => stack,
+            None => return true
+        };
+        let before = self.slots.clone();
+        let targets: Vec<usize> = if index < self.size {
+            // Into the inventory, hotbar first from its right end
+            (self.size..self.slots.len()).rev().collect()
+        } else {
+            let accepting: Vec<usize> = (0..self.size)
+                .filter(|&target| !self.output(target) && self.rules.shift_accepts(target, &stack))
+                .collect();
+            let hotbar = self.size + HOTBAR_OFFSET;
+            if !accepting.is_empty() {
+                accepting
+            } else if index < hotbar {
+                (hotbar..self.slots.len()).collect()
+            } else {
+                (self.size..hotbar).collect()
+            }
+        };
+        let left = self.merge(stack.clone(), &targets);
+        if left.is_some() && self.output(index) {
+            // Outputs move as a whole or not at all
+            self.slots = before;
+            self.slots[index] = Some(stack);
+        } else {
+            self.slots[index] = left;
+        }
+        true
+    }
+
+    /// Moves `stack` into the `targets` slots, topping up stacks of the
+    /// same item before filling empty slots. Returns what didn't fit.
+    fn merge(&mut self, mut stack: Slot, targets: &[usize]) -> Option<Slot> {
+        for &target in targets {
+            let max = self.max_stack(target, &stack);
+            if let Some(ref mut existing) = self.slots[target] {
+                existing.merge(&mut stack, max);
+            }
+            if stack.count() == 0 {
+                return None;
+            }
+        }
+        for &target in targets {
+            if self.slots[target].is_none() && self.accepts(target, &stack) {
+                let max = self.max_stack(target, &stack);
+                self.slots[target] = Some(stack.split(max));
+            }
+            if stack.count() == 0 {
+                return None;
+            }
+        }
+        Some(stack)
+    }
+
+    /// Mode 2, a number key swaps the slot with that hotbar slot.
+    fn swap(&mut self, index: usize, button: usize) -> bool {
+        let hotbar = self.size + HOTBAR_OFFSET + button;
+        if hotbar == index || !self.can_take(index) {
+            return true;
+        }
+        if self.output(index) {
+            if self.slots[hotbar].is_none() {
+                self.slots[hotbar] = self.slots[index].take();
+            }
+            return true;
+        }
+        if let Some(ref stack) = self.slots[hotbar] {
+            if !self.accepts(index, stack) || stack.count() > self.max_stack(index, stack) {
+                return true;
+            }
+        }
+        self.slots.swap(index, hotbar);
+        true
+    }
+
+    /// Mode 3, a middle click copies a full stack to the cursor in creative
+    /// mode.
+    fn clone_stack(&mut self, index: usize) -> bool {
+        if self.creative && self.cursor.is_none() {
+            if let Some(ref stack) = self.slots[index] {
+                self.cursor = Some(stack.with_count(stack.max_stack_size()));
+            }
+        }
+        true
+    }
+
+    /// Mode 4, throws one item or with `whole` the stack out of the slot.
+    /// Outputs are thrown as a whole.
+    fn drop(&mut self, index: usize, whole: bool) -> bool {
+        if self.cursor.is_some() || !self.can_take(index) {
+            return true;
+        }
+        let whole = whole || self.output(index);
+        if let Some(mut stack) = self.slots[index].take() {
+            if whole || stack.count() == 1 {
+                self.dropped.push(stack);
+            } else {
+                self.dropped.push(stack.split(1));
+                self.slots[index] = Some(stack);
+            }
+        }
+        true
+    }
+
+    /// Clicking outside throws the cursor away, or a single item of it on
+    /// right clicks.
+    fn drop_cursor(&mut self, one: bool) {
+        if let Some(mut held) = self.cursor.take() {
+            if one && held.count() > 1 {
+                self.dropped.push(held.split(1));
+                self.cursor = Some(held);
+            } else {
+                self.dropped.push(held);
+            }
+        }
+    }
+
+    /// Mode 5, a step of a drag: buttons 0, 4 and 8 start a left, right or
+    /// middle drag outside the window, the next ones paint slot `index` and
+    /// the ones after end it outside the window.
+    fn drag(&mut self, index: Option<usize>, button: i8, drag: &mut Option<Drag>) -> bool {
+        let kind = match button / 4 {
+            0 => DragKind::Split,
+            1 => DragKind::One,
+            2 => DragKind::Fill,
+            _ => {
+                *drag = None;
+                return false;
+            }
+        };
+        match (button % 4, index) {
+            (0, None) => {
+                if self.cursor.is_none() || (kind == DragKind::Fill && !self.creative) {
+                    *drag = None;
+                    return false;
+                }
+                *drag = Some(Drag { kind: kind, slots: vec![] });
+                true
+            }
+            (1, Some(index)) => {
+                let paintable = match *drag {
+                    Some(ref painted) if painted.kind == kind => {
+                        !painted.slots.contains(&index) && self.can_paint(index, kind, painted.slots.len())
+                    }
+                    _ => {
+                        *drag = None;
+                        return false;
+                    }
+                };
+                if paintable {
+                    if let Some(ref mut painted) = *drag {
+                        painted.slots.push(index);
+                    }
+                }
+                true
+            }
+            (2, None) => match drag.take() {
+                Some(painted) if painted.kind == kind => {
+                    self.paint(&painted);
+                    true
+                }
+                _ => false
+            },
+            _ => {
+                *drag = None;
+                false
+            }
+        }
+    }
+
+    /// Whether a drag which already painted `painted` slots can paint slot
+    /// `index` too.
+    fn can_paint(&self, index: usize, kind: DragKind, painted: usize) -> bool {
+        let held = match self.cursor {
+            Some(ref held) => held,
+            None => return false
+        };
+        !self.output(index) && self.accepts(index, held) &&
+            self.slots[index].as_ref().map_or(true, |stack| stack.can_stack_with(held)) &&
+            (kind == DragKind::Fill || held.count() as usize > painted)
+    }
+
+    /// Spreads the cursor over the slots of a finished drag.
+    fn paint(&mut self, drag: &Drag) {
+        if drag.slots.len() == 1 && drag.kind != DragKind::Fill {
+            // Dragging over a single slot is a plain click
+            self.pickup(drag.slots[0], drag.kind == DragKind::One);
+            return;
+        }
+        let held = match self.cursor.take() {
+            Some(held) => held,
+            None => return
+        };
+        let mut left = held.count();
+        let per_slot = match drag.kind {
+            DragKind::Split => held.count() / drag.slots.len().max(1) as u8,
+            DragKind::One => 1,
+            DragKind::Fill => held.max_stack_size()
+        };
+        for &index in &drag.slots {
+            let existing = match self.slots[index] {
+                Some(ref stack) if stack.can_stack_with(&held) => stack.count(),
+                Some(_) => continue,
+                None => 0
+            };
+            if !self.accepts(index, &held) {
+                continue;
+            }
+            let mut added = per_slot.min(self.max_stack(index, &held).saturating_sub(existing));
+            if drag.kind != DragKind::Fill {
+                added = added.min(left);
+                left -= added;
+            }
+            if added > 0 {
+                self.slots[index] = Some(held.with_count(existing + added));
+            }
+        }
+        self.cursor = if drag.kind == DragKind::Fill {
+            Some(held)
+        } else if left > 0 {
+            Some(held.with_count(left))
+        } else {
+            None
+        };
+    }
+
+    /// Mode 6, a double click gathers items like the cursor's into it,
+    /// taking from partial stacks before full ones.
+    fn gather(&mut self, index: usize) -> bool {
+        let mut held = match self.cursor.take() {
+            Some(held) => held,
+            None => return true
+        };
+        if self.slots[index].is_none() || !self.can_take(index) {
+            let max = held.max_stack_size();
+            for &full in &[false, true] {
+                for other in 0..self.slots.len() {
+                    if held.count() >= max {
+                        break;
+                    }
+                    if self.output(other) || !self.can_take(other) {
+                        continue;
+                    }
+                    let rest = match self.slots[other].take() {
+                        Some(mut stack) => {
+                            if full || stack.count() < stack.max_stack_size() {
+                                held.merge(&mut stack, max);
+                            }
+                            stack.non_empty()
+                        }
+                        None => None
+                    };
+                    self.slots[other] = rest;
+                }
+            }
+        }
+        self.cursor = Some(held);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use player::MAIN_START;
+    use types::Slot;
+    use window::OUTSIDE;
+
+    /// A chest-like window whose 2 slots take anything.
+    struct Chest;
+
+    impl Rules for Chest {
+        fn accepts(&self, _index: usize, _stack: &Slot) -> bool { true }
+    }
+
+    fn view<'a>(container: Vec<Option<Slot>>, cursor: Option<Slot>, rules: &'a Rules) -> View<'a> {
+        View::new(container, vec![None; INVENTORY_SLOTS], cursor, rules, false)
+    }
+
+    fn count(slot: Option<&Slot>) -> u8 {
+        slot.map_or(0, |stack| stack.count())
+    }
+
+    #[test]
+    fn shift_click() {
+        let mut view = view(vec![Some(Slot::new(1, 64, 0)), None], None, &Chest);
+        assert!(view.click(0, 0, 1, &mut None));
+        // Lands at the right end of the hotbar
+        assert_eq!(count(view.slot(2 + INVENTORY_SLOTS - 1)), 64);
+        assert!(view.click(2 + INVENTORY_SLOTS as i16 - 1, 0, 1, &mut None));
+        assert_eq!(count(view.slot(0)), 64);
+    }
+
+    #[test]
+    fn shift_click_armor() {
+        let mut view = View::new(vec![None; MAIN_START], vec![None; INVENTORY_SLOTS], None, &InventoryRules, false);
+        view.slots[9] = Some(Slot::new(310, 1, 0));
+        view.slots[10] = Some(Slot::new(1, 10, 0));
+        assert!(view.click(9, 0, 1, &mut None));
+        assert_eq!(view.slot(5).map(|stack| stack.id()), Some(310));
+        // Anything else goes between the main inventory and the hotbar
+        assert!(view.click(10, 0, 1, &mut None));
+        assert_eq!(count(view.slot(9 + 27)), 10);
+    }
+
+    #[test]
+    fn number_key() {
+        let mut view = view(vec![Some(Slot::new(1, 3, 0)), None], None, &Chest);
+        assert!(view.click(0, 4, 2, &mut None));
+        assert!(view.slot(0).is_none());
+        assert_eq!(count(view.slot(2 + 27 + 4)), 3);
+        assert!(!view.click(0, 9, 2, &mut None));
+    }
+
+    #[test]
+    fn drop_from_slot() {
+        let mut view = view(vec![Some(Slot::new(1, 3, 0)), None], None, &Chest);
+        assert!(view.click(0, 0, 4, &mut None));
+        assert!(view.click(0, 1, 4, &mut None));
+        assert!(view.slot(0).is_none());
+        assert_eq!(view.dropped().iter().map(|stack| stack.count()).collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn drag_split() {
+        let mut view = view(vec![None, Some(Slot::new(1, 1, 0))], Some(Slot::new(1, 7, 0)), &Chest);
+        let mut drag = None;
+        assert!(view.click(OUTSIDE, 0, 5, &mut drag));
+        for slot in 0..3 {
+            assert!(view.click(slot, 1, 5, &mut drag));
+        }
+        // Right button steps don't belong to a left drag
+        assert!(!view.click(0, 5, 5, &mut drag));
+        assert!(drag.is_none());
+
+        assert!(view.click(OUTSIDE, 0, 5, &mut drag));
+        for slot in 0..3 {
+            assert!(view.click(slot, 1, 5, &mut drag));
+        }
+        assert!(view.click(OUTSIDE, 2, 5, &mut drag));
+        assert_eq!((count(view.slot(0)), count(view.slot(1)), count(view.slot(2))), (2, 3, 2));
+        assert_eq!(count(view.cursor()), 1);
+    }
+
+    #[test]
+    fn drag_one_slot() {
+        let mut view = view(vec![None, None], Some(Slot::new(1, 7, 0)), &Chest);
+        let mut drag = None;
+        assert!(view.click(OUTSIDE, 4, 5, &mut drag));
+        assert!(view.click(1, 5, 5, &mut drag));
+        assert!(view.click(OUTSIDE, 6, 5, &mut drag));
+        assert_eq!((count(view.slot(1)), count(view.cursor())), (1, 6));
+        // Middle drags are for creative mode only
+        assert!(!view.click(OUTSIDE, 8, 5, &mut drag));
+    }
+
+    #[test]
+    fn double_click() {
+        let mut view = view(vec![None, Some(Slot::new(1, 64, 0))], Some(Slot::new(1, 10, 0)), &Chest);
+        view.slots[5] = Some(Slot::new(1, 30, 0));
+        assert!(view.click(0, 0, 6, &mut None));
+        // Partial stacks go first
+        assert_eq!((count(view.cursor()), count(view.slot(5)), count(view.slot(1))), (64, 0, 40));
+    }
+
+    #[test]
+    fn outputs() {
+        let rules = AnvilRules { affordable: false };
+        let mut view = view(vec![None, None, Some(Slot::new(276, 1, 0))], None, &rules);
+        assert!(view.click(2, 0, 0, &mut None));
+        assert!(view.cursor().is_none());
+        assert!(view.click(2, 0, 1, &mut None));
+        assert_eq!(count(view.slot(2)), 1);
+    }
+}
@@ -0,0 +1,183 @@
+//! Inventory windows opened by players.
+
+use types::Slot;
+
+pub use self::anvil::Anvil;
+pub use self::click::{Drag, DragKind, Rules, View};
+
+pub mod anvil;
+pub mod click;
+
+/// Window id the client uses for the item held by the cursor in SetSlot.
+pub const CURSOR_WINDOW: u8 = 255;
+
+/// Slot number of clicks outside the window, which throw the cursor away.
+pub const OUTSIDE: i16 = -999;
+
+/// Farthest a player can be from the middle of the block or the entity
+/// whose window they have open before it's closed, like vanilla's.
+pub const MAX_DISTANCE: f64 = 8.0;
+
+/// A window opened on top of the player's own inventory, which has id 0 and
+/// is always open.
+#[derive(Clone, Debug)]
+pub struct Window {
+    pub id: u8,
+    pub kind: WindowKind
+}
+
+#[derive(Clone, Debug)]
+pub enum WindowKind {
+    /// Inventory of the horse with the given entity id.
+    Horse(i32),
+    /// The furnace at the given location.
+    Furnace([i32; 3]),
+    /// The brewing stand at the given location.
+    BrewingStand([i32; 3]),
+    /// An anvil, whose slots belong to the player using it.
+    Anvil(Anvil)
+}
+
+impl WindowKind {
+    /// Location of the block entity whose slots this window shows.
+    pub fn block_entity(&self) -> Option<[i32; 3]> {
+        match *self {
+            WindowKind::Furnace(location) | WindowKind::BrewingStand(location) => Some(location),
+            WindowKind::Horse(_) | WindowKind::Anvil(_) => None
+        }
+    }
+
+    /// Location of the block the window was opened on, whether or not it
+    /// keeps items.
+    pub fn block(&self) -> Option<[i32; 3]> {
+        match *self {
+            WindowKind::Furnace(location) | WindowKind::BrewingStand(location) => Some(location),
+            WindowKind::Anvil(ref anvil) => Some(anvil.location),
+            WindowKind::Horse(_) => None
+        }
+    }
+}
+
+/// Whether a player at `position` is close enough to `target` to keep using
+/// a window opened on it.
+pub fn in_reach(position: [f64; 3], target: [f64; 3]) -> bool {
+    let (dx, dy, dz) = (position[0] - target[0], position[1] - target[1], position[2] - target[2]);
+    dx * dx + dy * dy + dz * dz <= MAX_DISTANCE * MAX_DISTANCE
+}
+
+/// Left, or when `right` is set right, clicks `slot` while holding `cursor`,
+/// as done by ClickWindow mode 0.
+pub fn click(slot: &mut Option<Slot>, cursor: &mut Option<Slot>, right: bool) {
+    click_limited(slot, cursor, right, 64)
+}
+
+/// Like `click`, for a slot holding at most `limit` items whatever the item.
+pub fn click_limited(slot: &mut Option<Slot>, cursor: &mut Option<Slot>, right: bool, limit: u8) {
+    match (slot.take(), cursor.take()) {
+        (None, None) => {}
+        (Some(mut stack), None) => {
+            if right && stack.count() > 1 {
+                // Pick up the bigger half
+                let taken = (stack.count() + 1) / 2;
+                *cursor = Some(stack.split(taken));
+                *slot = Some(stack);
+            } else {
+                *cursor = Some(stack);
+            }
+        }
+        (None, Some(mut held)) => {
+            let moved = (if right { 1 } else { held.count() }).min(limit);
+            *slot = Some(held.split(moved));
+            *cursor = held.non_empty();
+        }
+        (Some(mut stack), Some(mut held)) => {
+            if stack.can_stack_with(&held) {
+                let limit = if right { stack.count().saturating_add(1).min(limit) } else { limit };
+                stack.merge(&mut held, limit);
+                *slot = Some(stack);
+                *cursor = held.non_empty();
+            } else if held.count() <= limit {
+                *slot = Some(held);
+                *cursor = Some(stack);
+            } else {
+                *slot = Some(stack);
+                *cursor = Some(held);
+            }
+        }
+    }
+}
+
+/// Clicks an output slot, which can only be taken from: the whole of `slot`
+/// goes to `cursor` if it fits. Returns whether it did.
+pub fn take(slot: &mut Option<Slot>, cursor: &mut Option<Slot>) -> bool {
+    match (slot.take(), cursor.take()) {
+        (Some(stack), None) => {
+            *cursor = Some(stack);
+            true
+        }
+        (Some(stack), Some(held)) => {
+            if held.can_stack_with(&stack) && held.count() as u16 + stack.count() as u16 <= held.max_stack_size() as u16 {
+                *cursor = Some(held.with_count(held.count() + stack.count()));
+                true
+            } else {
+                *slot = Some(stack);
+                *cursor = Some(held);
+                false
+            }
+        }
+        (None, held) => {
+            *cursor = held;
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use types::Slot;
+
+    fn count(slot: &Option<Slot>) -> u8 {
+        slot.as_ref().map_or(0, |stack| stack.count())
+    }
+
+    #[test]
+    fn reach() {
+        assert_eq!(WindowKind::Anvil(Anvil::new([1, 2, 3])).block(), Some([1, 2, 3]));
+        assert_eq!(WindowKind::Horse(7).block(), None);
+        assert!(in_reach([0.5, 64.0, 0.5], [0.5, 64.5, 7.5]));
+        assert!(!in_reach([0.5, 64.0, 0.5], [6.5, 64.5, 6.5]));
+    }
+
+    #[test]
+    fn left_click() {
+        let (mut slot, mut cursor) = (Some(Slot::new(1, 40, 0)), Some(Slot::new(1, 40, 0)));
+        click(&mut slot, &mut cursor, false);
+        assert_eq!((count(&slot), count(&cursor)), (64, 16));
+        let mut other = Some(Slot::new(4, 1, 0));
+        click(&mut other, &mut cursor, false);
+        assert_eq!(other.as_ref().map(|stack| stack.id()), Some(1));
+        assert_eq!(cursor.as_ref().map(|stack| stack.id()), Some(4));
+    }
+
+    #[test]
+    fn right_click() {
+        let (mut slot, mut cursor) = (Some(Slot::new(1, 5, 0)), None);
+        click(&mut slot, &mut cursor, true);
+        assert_eq!((count(&slot), count(&cursor)), (2, 3));
+        let mut empty = None;
+        click(&mut empty, &mut cursor, true);
+        assert_eq!((count(&empty), count(&cursor)), (1, 2));
+    }
+
+    #[test]
+    fn take_output() {
+        let (mut output, mut cursor) = (Some(Slot::new(265, 1, 0)), Some(Slot::new(265, 63, 0)));
+        assert!(take(&mut output, &mut cursor));
+        assert_eq!((count(&output), count(&cursor)), (0, 64));
+        output = Some(Slot::new(265, 1, 0));
+        assert!(!take(&mut output, &mut cursor));
+        assert_eq!((count(&output), count(&cursor)), (1, 64));
+    }
+}
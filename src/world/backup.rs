@@ -0,0 +1,183 @@
+//! World backups, gzipped tarballs of the world directory.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use time;
+
+/// Name of a backup of world `level_name` taken at `at`. Backups of a world
+/// sort by when they were taken.
+pub fn name(level_name: &str, at: &time::Tm) -> String {
+    format!("{}-{}.tar.gz", level_name, time::strftime("%Y-%m-%d_%H.%M.%S", at).unwrap())
+}
+
+/// Writes directory `dir` and everything in it to a new gzipped tarball at
+/// `path`. Returns how many files were written.
+pub fn create(dir: &Path, path: &Path) -> io::Result<usize> {
+    if let Some(parent) = path.parent() {
+        try!(fs::create_dir_all(parent));
+    }
+    let root = match dir.file_name().and_then(|name| name.to_str()) {
+        Some(root) => root.to_string(),
+        None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "can't back up a directory without a name"))
+    };
+    let dst = GzEncoder::new(BufWriter::new(try!(File::create(path))), Compression::Default);
+    let mut tar = Tar::new(dst);
+    let count = try!(tar.append_dir_all(dir, &root));
+    try!(try!(try!(tar.finish()).finish()).flush());
+    Ok(count)
+}
+
+/// Deletes the oldest backups of world `level_name` in `dir` until `keep`
+/// are left.
+pub fn prune(dir: &Path, level_name: &str, keep: usize) -> io::Result<()> {
+    let prefix = format!("{}-", level_name);
+    let mut backups: Vec<PathBuf> = vec![];
+    for entry in try!(fs::read_dir(dir)) {
+        let path = try!(entry).path();
+        let ours = path.file_name().and_then(|name| name.to_str())
+            .map_or(false, |name| name.starts_with(&prefix) && name.ends_with(".tar.gz"));
+        if ours {
+            backups.push(path);
+        }
+    }
+    backups.sort();
+    let extra = backups.len().saturating_sub(keep);
+    for path in &backups[..extra] {
+        info!("deleting old backup {}", path.display());
+        try!(fs::remove_file(path));
+    }
+    Ok(())
+}
+
+/// Writes a ustar archive, which is all a backup needs.
+pub struct Tar<W> {
+    dst: W
+}
+
+impl<W: Write> Tar<W> {
+    pub fn new(dst: W) -> Tar<W> {
+        Tar { dst: dst }
+    }
+
+    /// Adds directory `dir` as `name`, then everything in it. Returns how
+    /// many files were added.
+    pub fn append_dir_all(&mut self, dir: &Path, name: &str) -> io::Result<usize> {
+        try!(self.append(&format!("{}/", name), b'5', &[], 0));
+        let mut entries = try!(fs::read_dir(dir).and_then(|entries| entries.collect::<io::Result<Vec<_>>>()));
+        entries.sort_by_key(|entry| entry.file_name());
+        let mut count = 0;
+        for entry in entries {
+            let path = entry.path();
+            let child = match entry.file_name().into_string() {
+                Ok(child) => format!("{}/{}", name, child),
+                Err(_) => {
+                    warn!("not backing up {}, its name isn't UTF-8", path.display());
+                    continue;
+                }
+            };
+            let metadata = try!(entry.metadata());
+            if metadata.is_dir() {
+                count += try!(self.append_dir_all(&path, &child));
+            } else if metadata.is_file() {
+                let mtime = metadata.modified().ok()
+                    .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |since| since.as_secs());
+                let mut data = vec![];
+                try!(try!(File::open(&path)).read_to_end(&mut data));
+                try!(self.append_file(&child, &data, mtime));
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Adds a file called `name` holding `data`, last modified `mtime`
+    /// seconds after the epoch.
+    pub fn append_file(&mut self, name: &str, data: &[u8], mtime: u64) -> io::Result<()> {
+        self.append(name, b'0', data, mtime)
+    }
+
+    /// Ends the archive, returning where it was written.
+    pub fn finish(mut self) -> io::Result<W> {
+        try!(self.dst.write_all(&[0; 1024]));
+        Ok(self.dst)
+    }
+
+    fn append(&mut self, name: &str, kind: u8, data: &[u8], mtime: u64) -> io::Result<()> {
+        let mode = if kind == b'5' { 0o755 } else { 0o644 };
+        try!(self.dst.write_all(&try!(header(name, kind, mode, data.len() as u64, mtime))));
+        try!(self.dst.write_all(data));
+        // Contents are padded to whole blocks
+        let padding = (512 - data.len() % 512) % 512;
+        self.dst.write_all(&vec![0; padding])
+    }
+}
+
+/// A ustar header block. Names longer than 100 bytes are split between the
+/// prefix and name fields.
+fn header(name: &str, kind: u8, mode: u32, size: u64, mtime: u64) -> io::Result<[u8; 512]> {
+    let (prefix, name) = if name.len() <= 100 {
+        ("", name)
+    } else {
+        // Split at a slash leaving at most 100 bytes for the name
+        match name[..name.len().min(156)].rfind('/') {
+            Some(split) if name.len() - split - 1 <= 100 && split <= 155 => (&name[..split], &name[split + 1..]),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("path too long for tar: {}", name)))
+        }
+    };
+    let mut block = [0; 512];
+    block[..name.len()].copy_from_slice(name.as_bytes());
+    octal(&mut block[100..108], mode as u64);
+    octal(&mut block[108..116], 0);
+    octal(&mut block[116..124], 0);
+    octal(&mut block[124..136], size);
+    octal(&mut block[136..148], mtime);
+    block[156] = kind;
+    block[257..263].copy_from_slice(b"ustar\0");
+    block[263..265].copy_from_slice(b"00");
+    block[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+    // The checksum is taken with its own field as spaces
+    for byte in &mut block[148..156] {
+        *byte = b' ';
+    }
+    let checksum = block.iter().fold(0u64, |sum, &byte| sum + byte as u64);
+    octal(&mut block[148..155], checksum);
+    Ok(block)
+}
+
+/// Writes `value` into `field` as zero padded octal, ending with a NUL.
+fn octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:01$o}", value, field.len() - 1);
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[field.len() - 1] = 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tar() {
+        let mut tar = Tar::new(vec![]);
+        tar.append_file("world/level.dat", b"hello", 1445000000).unwrap();
+        let archive = tar.finish().unwrap();
+        // Header, one block of contents, two blocks ending the archive
+        assert_eq!(archive.len(), 4 * 512);
+        assert_eq!(&archive[..15], b"world/level.dat");
+        assert_eq!(&archive[124..136], b"00000000005\0");
+        assert_eq!(&archive[257..263], b"ustar\0");
+        assert_eq!(&archive[512..517], b"hello");
+        let checksum = archive[..512].iter().enumerate()
+            .fold(0, |sum, (i, &byte)| sum + if i >= 148 && i < 156 { b' ' as u64 } else { byte as u64 });
+        assert_eq!(&archive[148..155], format!("{:06o}\0", checksum).as_bytes());
+
+        let long = format!("world/{}/r.0.0.mca", "region".repeat(20));
+        assert!(Tar::new(vec![]).append_file(&long, b"", 0).is_ok());
+        assert!(Tar::new(vec![]).append_file(&"x".repeat(101), b"", 0).is_err());
+    }
+}
@@ -0,0 +1,82 @@
+//! Block changes of a tick, gathered by chunk so each chunk is sent once.
+
+use std::collections::BTreeMap;
+use std::mem;
+
+/// Changes in a chunk from which the whole chunk is resent instead, as
+/// vanilla does.
+pub const CHUNK_RESEND_THRESHOLD: usize = 64;
+
+/// How the changes to a chunk are best sent.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Batch {
+    /// A lone change, sent as BlockChange.
+    Single([i32; 3]),
+    /// A few changes to chunk `chunk`, sent as one MultiBlockChange.
+    Multi { chunk: (i32, i32), locations: Vec<[i32; 3]> },
+    /// So many changes to chunk `chunk` that resending it with ChunkData is
+    /// cheaper.
+    Whole((i32, i32))
+}
+
+/// Blocks changed since the last tick, by chunk.
+#[derive(Clone, Debug, Default)]
+pub struct BlockChanges {
+    chunks: BTreeMap<(i32, i32), Vec<[i32; 3]>>
+}
+
+impl BlockChanges {
+    pub fn new() -> BlockChanges {
+        BlockChanges { chunks: BTreeMap::new() }
+    }
+
+    /// Records that the block at `location` changed, once however often it
+    /// changes.
+    pub fn add(&mut self, location: [i32; 3]) {
+        let changes = self.chunks.entry((location[0] >> 4, location[2] >> 4)).or_insert_with(Vec::new);
+        if !changes.contains(&location) {
+            changes.push(location);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// The changes recorded so far, one batch per chunk, leaving none.
+    pub fn drain(&mut self) -> Vec<Batch> {
+        let chunks = mem::replace(&mut self.chunks, BTreeMap::new());
+        chunks.into_iter().map(|(chunk, mut locations)| {
+            if locations.len() == 1 {
+                Batch::Single(locations.remove(0))
+            } else if locations.len() >= CHUNK_RESEND_THRESHOLD {
+                Batch::Whole(chunk)
+            } else {
+                Batch::Multi { chunk: chunk, locations: locations }
+            }
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches() {
+        let mut changes = BlockChanges::new();
+        changes.add([1, 64, 1]);
+        changes.add([1, 64, 1]);
+        changes.add([-1, 64, 1]);
+        changes.add([-2, 70, 15]);
+        for y in 0..CHUNK_RESEND_THRESHOLD as i32 {
+            changes.add([40, y, 40]);
+        }
+        assert_eq!(changes.drain(), vec![
+            Batch::Multi { chunk: (-1, 0), locations: vec![[-1, 64, 1], [-2, 70, 15]] },
+            Batch::Single([1, 64, 1]),
+            Batch::Whole((2, 2))
+        ]);
+        assert!(changes.is_empty());
+    }
+}
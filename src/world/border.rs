@@ -0,0 +1,215 @@
+//! World border geometry.
+
+use types::WorldBorderAction;
+
+/// Square border centered on `center`, which can grow or shrink over time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorldBorder {
+    center: [f64; 2],
+    old_diameter: f64,
+    new_diameter: f64,
+    /// Length of the current resize and how far into it we are, in ticks.
+    lerp_ticks: i64,
+    lerp_elapsed: i64,
+    /// Seconds left before a shrinking border reaches a player at which the
+    /// client starts showing the warning screen.
+    warning_time: i32,
+    /// Distance to the border at which the client starts showing the
+    /// warning screen.
+    warning_blocks: i32,
+    portal_teleport_boundary: i32
+}
+
+impl WorldBorder {
+    /// Vanilla's default border, as big as the world itself.
+    pub fn new() -> WorldBorder {
+        WorldBorder {
+            center: [0.0, 0.0],
+            old_diameter: 60000000.0,
+            new_diameter: 60000000.0,
+            lerp_ticks: 0,
+            lerp_elapsed: 0,
+            warning_time: 15,
+            warning_blocks: 5,
+            portal_teleport_boundary: 29999984
+        }
+    }
+
+    pub fn center(&self) -> [f64; 2] { self.center }
+    pub fn warning_time(&self) -> i32 { self.warning_time }
+    pub fn warning_blocks(&self) -> i32 { self.warning_blocks }
+
+    pub fn set_center(&mut self, x: f64, z: f64) {
+        self.center = [x, z];
+    }
+
+    pub fn set_warning_time(&mut self, seconds: i32) {
+        self.warning_time = seconds;
+    }
+
+    pub fn set_warning_blocks(&mut self, blocks: i32) {
+        self.warning_blocks = blocks;
+    }
+
+    /// Current diameter, somewhere in between the old and new one while
+    /// resizing.
+    pub fn diameter(&self) -> f64 {
+        if self.lerp_elapsed >= self.lerp_ticks {
+            return self.new_diameter;
+        }
+        let t = self.lerp_elapsed as f64 / self.lerp_ticks as f64;
+        self.old_diameter + (self.new_diameter - self.old_diameter) * t
+    }
+
+    /// Diameter the border is resizing to.
+    pub fn target_diameter(&self) -> f64 {
+        self.new_diameter
+    }
+
+    /// Resizes the border immediately.
+    pub fn set_diameter(&mut self, diameter: f64) {
+        self.old_diameter = diameter;
+        self.new_diameter = diameter;
+        self.lerp_ticks = 0;
+        self.lerp_elapsed = 0;
+    }
+
+    /// Resizes the border from its current diameter to `diameter` over
+    /// `ticks` ticks.
+    pub fn lerp_to(&mut self, diameter: f64, ticks: i64) {
+        if ticks <= 0 {
+            return self.set_diameter(diameter);
+        }
+        self.old_diameter = self.diameter();
+        self.new_diameter = diameter;
+        self.lerp_ticks = ticks;
+        self.lerp_elapsed = 0;
+    }
+
+    /// Ticks left in the current resize.
+    pub fn remaining_ticks(&self) -> i64 {
+        if self.lerp_elapsed < self.lerp_ticks { self.lerp_ticks - self.lerp_elapsed } else { 0 }
+    }
+
+    /// Advances the current resize, if any, by one tick.
+    pub fn tick(&mut self) {
+        if self.lerp_elapsed < self.lerp_ticks {
+            self.lerp_elapsed += 1;
+            if self.lerp_elapsed == self.lerp_ticks {
+                let diameter = self.new_diameter;
+                self.set_diameter(diameter);
+            }
+        }
+    }
+
+    /// Edges of the border as `[min_x, min_z, max_x, max_z]`.
+    pub fn bounds(&self) -> [f64; 4] {
+        let radius = self.diameter() / 2.0;
+        [self.center[0] - radius, self.center[1] - radius,
+         self.center[0] + radius, self.center[1] + radius]
+    }
+
+    pub fn contains(&self, x: f64, z: f64) -> bool {
+        let bounds = self.bounds();
+        x >= bounds[0] && z >= bounds[1] && x <= bounds[2] && z <= bounds[3]
+    }
+
+    /// Distance from `(x, z)` to the closest edge, negative when outside.
+    pub fn distance_to_edge(&self, x: f64, z: f64) -> f64 {
+        let bounds = self.bounds();
+        (x - bounds[0]).min(z - bounds[1]).min(bounds[2] - x).min(bounds[3] - z)
+    }
+
+    /// Moves `position` to the closest point inside the border.
+    pub fn clamp(&self, position: [f64; 3]) -> [f64; 3] {
+        let bounds = self.bounds();
+        [position[0].max(bounds[0]).min(bounds[2]), position[1], position[2].max(bounds[1]).min(bounds[3])]
+    }
+
+    /// Distance at which the client starts tinting the screen red, this
+    /// grows with the speed of a shrinking border so that players get
+    /// `warning_time` seconds of notice.
+    pub fn warning_distance(&self) -> f64 {
+        let blocks = self.warning_blocks as f64;
+        if self.new_diameter >= self.old_diameter || self.remaining_ticks() == 0 {
+            return blocks;
+        }
+        // Both edges move towards the center, hence the halving
+        let speed = (self.old_diameter - self.new_diameter) / 2.0 / self.lerp_ticks as f64 * 20.0;
+        let approaching = (speed * self.warning_time as f64).min((self.diameter() - self.new_diameter) / 2.0);
+        blocks.max(approaching)
+    }
+
+    /// Strength of the warning screen effect, between 0 (none) and 1, for a
+    /// player standing at `(x, z)`.
+    pub fn warning_intensity(&self, x: f64, z: f64) -> f64 {
+        let distance = self.distance_to_edge(x, z);
+        let warning = self.warning_distance();
+        if distance >= warning || warning <= 0.0 {
+            0.0
+        } else {
+            (1.0 - distance / warning).min(1.0)
+        }
+    }
+
+    /// Action which brings a client up to date with this border.
+    pub fn initialize(&self) -> WorldBorderAction {
+        WorldBorderAction::Initialize {
+            x: self.center[0],
+            z: self.center[1],
+            old_diameter: self.diameter(),
+            new_diameter: self.new_diameter,
+            speed: self.remaining_ticks() * 50,
+            portal_teleport_boundary: self.portal_teleport_boundary,
+            warning_time: self.warning_time,
+            warning_blocks: self.warning_blocks
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contains_and_distance() {
+        let mut border = WorldBorder::new();
+        border.set_center(10.0, 0.0);
+        border.set_diameter(20.0);
+        assert!(border.contains(0.0, 10.0));
+        assert!(!border.contains(-0.5, 0.0));
+        assert_eq!(border.distance_to_edge(10.0, 0.0), 10.0);
+        assert_eq!(border.distance_to_edge(18.0, 0.0), 2.0);
+        assert_eq!(border.distance_to_edge(25.0, 0.0), -5.0);
+        assert_eq!(border.clamp([25.0, 64.0, -15.0]), [20.0, 64.0, -10.0]);
+    }
+
+    #[test]
+    fn lerp() {
+        let mut border = WorldBorder::new();
+        border.set_diameter(100.0);
+        border.lerp_to(50.0, 10);
+        assert_eq!(border.diameter(), 100.0);
+        for _ in 0..5 {
+            border.tick();
+        }
+        assert_eq!(border.diameter(), 75.0);
+        assert_eq!(border.remaining_ticks(), 5);
+        for _ in 0..10 {
+            border.tick();
+        }
+        assert_eq!(border.diameter(), 50.0);
+        assert_eq!(border.remaining_ticks(), 0);
+    }
+
+    #[test]
+    fn warning() {
+        let mut border = WorldBorder::new();
+        border.set_diameter(100.0);
+        assert_eq!(border.warning_intensity(0.0, 0.0), 0.0);
+        assert_eq!(border.warning_intensity(47.5, 0.0), 0.5);
+        // 1 block per second for 15 seconds beats the 5 warning blocks
+        border.lerp_to(40.0, 600);
+        assert_eq!(border.warning_distance(), 15.0);
+    }
+}
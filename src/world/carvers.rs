@@ -0,0 +1,251 @@
+//! Caves and ravines carved out of freshly generated terrain, before any
+//! feature is placed on it.
+//!
+//! Like vanilla, tunnels are random walks starting in chunks up to `RANGE`
+//! chunks away, each replayed from its start chunk's seed and carving only
+//! the part that falls in the chunk being generated.
+
+use std::f32::consts::PI;
+
+use types::ChunkColumn;
+use world::random::{JavaRandom, WorldRandom};
+
+/// Chunks away from where it starts a tunnel can reach.
+pub const RANGE: i32 = 8;
+
+/// Height below which carved out space fills with lava.
+pub const LAVA_LEVEL: i32 = 10;
+
+const STONE: u16 = 1;
+const GRASS: u16 = 2;
+const DIRT: u16 = 3;
+const WATER: u16 = 9;
+const LAVA: u16 = 11;
+
+/// Where a tunnel is heading, and how wide it is.
+struct Tunnel {
+    position: [f64; 3],
+    yaw: f32,
+    pitch: f32,
+    width: f32,
+    /// How much taller than wide it is, 1 for caves and more for ravines.
+    height_scale: f64
+}
+
+/// Carves the caves reaching chunk (`x`, `z`) out of `column`, under
+/// `surface`.
+pub fn caves(random: &WorldRandom, column: &mut ChunkColumn, x: i32, z: i32, surface: i32) {
+    for start_x in x - RANGE..x + RANGE + 1 {
+        for start_z in z - RANGE..z + RANGE + 1 {
+            let mut rng = random.feature("caves", start_x, start_z);
+            // Mostly none, sometimes a handful
+            let bound = rng.next_int_bounded(15) + 1;
+            let bound = rng.next_int_bounded(bound) + 1;
+            let mut count = rng.next_int_bounded(bound);
+            if rng.next_int_bounded(7) != 0 {
+                count = 0;
+            }
+            for _ in 0..count {
+                let cave_x = start_x * 16 + rng.next_int_bounded(16);
+                let bound = rng.next_int_bounded(surface - 8) + 8;
+                let cave_y = rng.next_int_bounded(bound);
+                let position = [cave_x as f64, cave_y as f64, (start_z * 16 + rng.next_int_bounded(16)) as f64];
+                let mut tunnels = 1;
+                if rng.next_int_bounded(4) == 0 {
+                    // A wide, flat room the tunnels lead off
+                    let width = 1.0 + rng.next_float() * 6.0;
+                    let room = Tunnel { position: position, yaw: 0.0, pitch: 0.0, width: width, height_scale: 0.5 };
+                    carve_step(column, x, z, &room, 1.5 + width as f64);
+                    tunnels += rng.next_int_bounded(4);
+                }
+                for _ in 0..tunnels {
+                    let mut width = rng.next_float() * 2.0 + rng.next_float();
+                    if rng.next_int_bounded(10) == 0 {
+                        width *= rng.next_float() * rng.next_float() * 3.0 + 1.0;
+                    }
+                    let tunnel = Tunnel {
+                        position: position,
+                        yaw: rng.next_float() * PI * 2.0,
+                        pitch: (rng.next_float() - 0.5) * 2.0 / 8.0,
+                        width: width,
+                        height_scale: 1.0
+                    };
+                    let seed = rng.next_long();
+                    dig(column, x, z, seed, tunnel, 0, 0, false);
+                }
+            }
+        }
+    }
+}
+
+/// Carves the ravines reaching chunk (`x`, `z`) out of `column`, one
+/// starting in a chunk out of 50.
+pub fn ravines(random: &WorldRandom, column: &mut ChunkColumn, x: i32, z: i32, surface: i32) {
+    for start_x in x - RANGE..x + RANGE + 1 {
+        for start_z in z - RANGE..z + RANGE + 1 {
+            let mut rng = random.feature("ravines", start_x, start_z);
+            if rng.next_int_bounded(50) != 0 {
+                continue;
+            }
+            let ravine_x = start_x * 16 + rng.next_int_bounded(16);
+            let bound = rng.next_int_bounded(surface - 28) + 8;
+            let ravine_y = rng.next_int_bounded(bound) + 20;
+            let position = [ravine_x as f64, ravine_y as f64, (start_z * 16 + rng.next_int_bounded(16)) as f64];
+            let tunnel = Tunnel {
+                position: position,
+                yaw: rng.next_float() * PI * 2.0,
+                pitch: (rng.next_float() - 0.5) * 2.0 / 8.0,
+                width: (rng.next_float() * 2.0 + rng.next_float()) * 2.0,
+                height_scale: 3.0
+            };
+            let seed = rng.next_long();
+            dig(column, x, z, seed, tunnel, 0, 0, true);
+        }
+    }
+}
+
+/// Follows `tunnel` from `step` on, `length` steps in all or a random
+/// length if 0, carving wherever it passes through chunk (`x`, `z`).
+/// Cave tunnels split in two once along the way.
+fn dig(column: &mut ChunkColumn, x: i32, z: i32, seed: i64, mut tunnel: Tunnel, step: i32, length: i32, ravine: bool) {
+    let mut rng = JavaRandom::new(seed);
+    let center = [(x * 16 + 8) as f64, (z * 16 + 8) as f64];
+    let length = if length > 0 { length } else { RANGE * 16 - 16 - rng.next_int_bounded((RANGE * 16 - 16) / 4) };
+    let split = if !ravine && tunnel.width > 1.0 { Some(rng.next_int_bounded(length / 2) + length / 4) } else { None };
+    let steep = rng.next_int_bounded(6) == 0;
+    let (mut yaw_change, mut pitch_change) = (0.0f32, 0.0f32);
+    for step in step..length {
+        let radius = 1.5 + ((step as f32 * PI / length as f32).sin() * tunnel.width) as f64;
+        tunnel.position[0] += (tunnel.yaw.cos() * tunnel.pitch.cos()) as f64;
+        tunnel.position[1] += tunnel.pitch.sin() as f64;
+        tunnel.position[2] += (tunnel.yaw.sin() * tunnel.pitch.cos()) as f64;
+        tunnel.pitch *= if steep { 0.92 } else { 0.7 };
+        tunnel.pitch += pitch_change * 0.1;
+        tunnel.yaw += yaw_change * 0.1;
+        pitch_change *= if ravine { 0.8 } else { 0.9 };
+        yaw_change *= if ravine { 0.5 } else { 0.75 };
+        pitch_change += (rng.next_float() - rng.next_float()) * rng.next_float() * 2.0;
+        yaw_change += (rng.next_float() - rng.next_float()) * rng.next_float() * 4.0;
+
+        if Some(step) == split {
+            for &turn in &[-PI / 2.0, PI / 2.0] {
+                let branch = Tunnel {
+                    position: tunnel.position,
+                    yaw: tunnel.yaw + turn,
+                    pitch: tunnel.pitch / 3.0,
+                    width: rng.next_float() * 0.5 + 0.5,
+                    height_scale: tunnel.height_scale
+                };
+                let seed = rng.next_long();
+                dig(column, x, z, seed, branch, step, length, ravine);
+            }
+            return;
+        }
+        if !ravine && rng.next_int_bounded(4) == 0 {
+            continue;
+        }
+        // Stop once the chunk's out of reach for the steps left
+        let (dx, dz) = (tunnel.position[0] - center[0], tunnel.position[2] - center[1]);
+        let (left, reach) = ((length - step) as f64, tunnel.width as f64 + 2.0 + 16.0);
+        if dx * dx + dz * dz - left * left > reach * reach {
+            return;
+        }
+        carve_step(column, x, z, &tunnel, radius);
+    }
+}
+
+/// Carves an ellipsoid `radius` wide around where `tunnel` is, if it
+/// reaches chunk (`x`, `z`) and wouldn't let water in.
+fn carve_step(column: &mut ChunkColumn, x: i32, z: i32, tunnel: &Tunnel, radius: f64) {
+    let height = radius * tunnel.height_scale;
+    let (px, py, pz) = (tunnel.position[0], tunnel.position[1], tunnel.position[2]);
+    let (base_x, base_z) = ((x * 16) as f64, (z * 16) as f64);
+    if px + radius < base_x || px - radius > base_x + 16.0 || pz + radius < base_z || pz - radius > base_z + 16.0 {
+        return;
+    }
+    let clamp = |value: f64, max: i32| (value.floor() as i32).max(0).min(max);
+    let (min_x, max_x) = (clamp(px - radius - base_x, 16), clamp(px + radius - base_x + 1.0, 16));
+    let (min_z, max_z) = (clamp(pz - radius - base_z, 16), clamp(pz + radius - base_z + 1.0, 16));
+    let (min_y, max_y) = (clamp(py - height - 1.0, 255).max(1), clamp(py + height + 1.0, 255));
+    let inside = |bx: i32, by: i32, bz: i32| {
+        let dx = (bx as f64 + base_x + 0.5 - px) / radius;
+        let dy = (by as f64 + 0.5 - py) / height;
+        let dz = (bz as f64 + base_z + 0.5 - pz) / radius;
+        // Flat floors, like vanilla
+        dy > -0.7 && dx * dx + dy * dy + dz * dz < 1.0
+    };
+    for bx in min_x..max_x {
+        for bz in min_z..max_z {
+            for by in min_y..max_y + 1 {
+                if column.block(bx as usize, by as usize, bz as usize) >> 4 == WATER {
+                    return;
+                }
+            }
+        }
+    }
+    for bx in min_x..max_x {
+        for bz in min_z..max_z {
+            for by in min_y..max_y {
+                if !inside(bx, by, bz) {
+                    continue;
+                }
+                let (ux, uy, uz) = (bx as usize, by as usize, bz as usize);
+                match column.block(ux, uy, uz) >> 4 {
+                    STONE | DIRT | GRASS => column.set_block(ux, uy, uz, if by < LAVA_LEVEL { LAVA << 4 } else { 0 }),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use types::{Chunk, ChunkColumn};
+    use world::random::WorldRandom;
+
+    fn ground() -> ChunkColumn {
+        let mut column = ChunkColumn { chunks: (0..4).map(|_| Chunk::new(STONE << 4, 0xff)).collect(), biomes: None };
+        for x in 0..16 {
+            for z in 0..16 {
+                column.set_block(x, 0, z, 7 << 4);
+            }
+        }
+        column
+    }
+
+    fn count(column: &ChunkColumn, id: u16) -> usize {
+        column.chunks.iter().map(|chunk| chunk.blocks.iter().filter(|&&block| block >> 4 == id).count()).sum()
+    }
+
+    #[test]
+    fn carving() {
+        let random = WorldRandom::new(42);
+        let carved: Vec<_> = (0..16).map(|i| {
+            let mut column = ground();
+            caves(&random, &mut column, i, i / 4, 63);
+            column
+        }).collect();
+        assert!(carved.iter().any(|column| count(column, 0) > 0));
+        for column in &carved {
+            // Bedrock's left alone, and lava only lies low
+            assert_eq!(count(column, 7), 256);
+            for y in LAVA_LEVEL as usize..64 {
+                assert!((0..16).all(|x| column.block(x, y, 8) >> 4 != LAVA));
+            }
+        }
+        // Same seed, same caves
+        let mut again = ground();
+        caves(&random, &mut again, 3, 0, 63);
+        assert_eq!(count(&again, 0), count(&carved[3], 0));
+
+        let mut column = ground();
+        let ravine = (0..400).map(|i| (i % 20, i / 20)).find(|&(x, z)| {
+            ravines(&random, &mut column, x, z, 63);
+            count(&column, 0) > 0
+        });
+        assert!(ravine.is_some());
+    }
+}
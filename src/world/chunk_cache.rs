@@ -0,0 +1,127 @@
+//! Chunk columns encoded for clients, shared by every player they're sent
+//! to until their blocks change.
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+
+use packet::{ChunkMeta, Encoded, PacketWrite};
+use packet::play::clientbound::{ChunkData, ChunkDataBulk};
+use types::ChunkColumn;
+
+/// Encoded data of a chunk column, with the mask of the chunks it holds.
+#[derive(Clone, Debug)]
+pub struct EncodedColumn {
+    pub mask: u16,
+    pub data: Arc<Vec<u8>>
+}
+
+impl EncodedColumn {
+    pub fn new(column: &ChunkColumn) -> io::Result<EncodedColumn> {
+        Ok(EncodedColumn { mask: column.mask(), data: Arc::new(try!(column.encode())) })
+    }
+}
+
+pub struct ChunkCache {
+    columns: HashMap<(i32, i32), EncodedColumn>,
+    hits: u64,
+    misses: u64
+}
+
+impl ChunkCache {
+    pub fn new() -> ChunkCache {
+        ChunkCache { columns: HashMap::new(), hits: 0, misses: 0 }
+    }
+
+    /// Encoded column `chunk`, if it hasn't changed since it was encoded.
+    pub fn get(&mut self, chunk: (i32, i32)) -> Option<EncodedColumn> {
+        let cached = self.columns.get(&chunk).cloned();
+        if cached.is_some() { self.hits += 1 } else { self.misses += 1 }
+        cached
+    }
+
+    pub fn insert(&mut self, chunk: (i32, i32), column: EncodedColumn) {
+        self.columns.insert(chunk, column);
+    }
+
+    /// Forgets column `chunk`, after its blocks changed.
+    pub fn invalidate(&mut self, chunk: (i32, i32)) {
+        self.columns.remove(&chunk);
+    }
+
+    /// Number of columns cached.
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Share of the lookups which found their column, 0 before any.
+    pub fn hit_rate(&self) -> f64 {
+        if self.hits + self.misses == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / (self.hits + self.misses) as f64
+    }
+}
+
+/// ChunkData packet sending the whole of column `chunk`.
+pub fn chunk_data(chunk: (i32, i32), column: &EncodedColumn) -> ChunkData {
+    ChunkData { x: chunk.0, z: chunk.1, continuous: true, mask: column.mask, chunk_data: (*column.data).clone() }
+}
+
+/// ChunkDataBulk packet sending `columns`, put together from their encoded
+/// data rather than encoding them again.
+pub fn chunk_data_bulk(sky_light_sent: bool, columns: &[((i32, i32), EncodedColumn)]) -> io::Result<Encoded> {
+    // Without columns the packet is its header, which the data follows
+    let header = ChunkDataBulk {
+        sky_light_sent: sky_light_sent,
+        chunk_meta: columns.iter().map(|&((x, z), ref column)| ChunkMeta { x: x, z: z, mask: column.mask }).collect(),
+        chunk_data: vec![]
+    };
+    let len = header.inner_len() + columns.iter().map(|&(_, ref column)| column.data.len()).fold(0, |acc, len| acc + len);
+    let mut body = Vec::with_capacity(len);
+    try!(header.inner_encode(&mut body));
+    for &(_, ref column) in columns {
+        body.extend_from_slice(&column.data);
+    }
+    Ok(Encoded(Arc::new(body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use packet::PacketWrite;
+    use packet::play::clientbound::ChunkDataBulk;
+    use types::{Chunk, ChunkColumn};
+
+    fn column(block: u16) -> ChunkColumn {
+        ChunkColumn { chunks: vec![Chunk::new(block << 4, 0xff)], biomes: Some([1; 256]) }
+    }
+
+    #[test]
+    fn caching() {
+        let mut cache = ChunkCache::new();
+        assert!(cache.get((0, 0)).is_none());
+        cache.insert((0, 0), EncodedColumn::new(&column(1)).unwrap());
+        assert_eq!(cache.get((0, 0)).map(|column| column.mask), Some(1));
+        assert_eq!(cache.hit_rate(), 0.5);
+        cache.invalidate((0, 0));
+        assert!(cache.get((0, 0)).is_none());
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn bulk_matches_encoding() {
+        let columns = vec![((1, -2), column(1)), ((3, 4), column(2))];
+        let mut expected = Vec::new();
+        ChunkDataBulk {
+            sky_light_sent: true,
+            chunk_meta: columns.iter().map(|&((x, z), ref column)| ChunkMeta { x: x, z: z, mask: column.mask() }).collect(),
+            chunk_data: columns.iter().map(|&(_, ref column)| column.clone()).collect()
+        }.write(&mut expected).unwrap();
+        let encoded: Vec<_> = columns.iter().map(|&(chunk, ref column)| (chunk, EncodedColumn::new(column).unwrap())).collect();
+        let mut bulk = Vec::new();
+        chunk_data_bulk(true, &encoded).unwrap().write(&mut bulk).unwrap();
+        assert_eq!(bulk, expected);
+    }
+}
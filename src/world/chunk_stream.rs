@@ -0,0 +1,101 @@
+//! Chunk streaming: players are sent the chunks coming within view
+//! distance as they move, and told to drop those going out of it.
+
+use std::collections::HashSet;
+
+use packet::play::clientbound::ChunkData;
+use world::tickets;
+
+/// Most chunks sent to a player each tick, so moving fast doesn't hold the
+/// tick up.
+pub const CHUNKS_PER_TICK: usize = 10;
+
+/// Chunks a player's client has.
+#[derive(Clone, Debug)]
+pub struct ChunkView {
+    /// View distance in chunks.
+    radius: i32,
+    sent: HashSet<(i32, i32)>
+}
+
+impl ChunkView {
+    pub fn new(radius: i32) -> ChunkView {
+        ChunkView { radius: radius.max(1), sent: HashSet::new() }
+    }
+
+    pub fn radius(&self) -> i32 { self.radius }
+
+    /// Whether the client has chunk `chunk`.
+    pub fn contains(&self, chunk: (i32, i32)) -> bool {
+        self.sent.contains(&chunk)
+    }
+
+    /// Every chunk the client has.
+    pub fn sent(&self) -> &HashSet<(i32, i32)> { &self.sent }
+
+    pub fn mark_sent(&mut self, chunk: (i32, i32)) {
+        self.sent.insert(chunk);
+    }
+
+    /// Up to `limit` chunks in view of a player in chunk `center` which
+    /// they haven't been sent, nearest first.
+    pub fn missing(&self, center: (i32, i32), limit: usize) -> Vec<(i32, i32)> {
+        let mut missing: Vec<_> = tickets::chunks_within(center, self.radius).into_iter()
+            .filter(|chunk| !self.sent.contains(chunk))
+            .collect();
+        missing.sort_by_key(|&(x, z)| (x - center.0) * (x - center.0) + (z - center.1) * (z - center.1));
+        missing.truncate(limit);
+        missing
+    }
+
+    /// Chunks the client has out of view of chunk `center`, forgetting
+    /// them as it's about to be told to drop them.
+    pub fn take_stale(&mut self, center: (i32, i32)) -> Vec<(i32, i32)> {
+        let radius = self.radius;
+        let stale: Vec<_> = self.sent.iter()
+            .filter(|&&(x, z)| (x - center.0).abs() > radius || (z - center.1).abs() > radius)
+            .cloned()
+            .collect();
+        for chunk in &stale {
+            self.sent.remove(chunk);
+        }
+        stale
+    }
+}
+
+/// Packet telling clients to drop chunk `chunk`: a whole column without
+/// any chunks in it.
+pub fn unload_packet(chunk: (i32, i32)) -> ChunkData {
+    ChunkData { x: chunk.0, z: chunk.1, continuous: true, mask: 0, chunk_data: vec![] }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streaming() {
+        let mut view = ChunkView::new(2);
+        let missing = view.missing((0, 0), 100);
+        assert_eq!(missing.len(), 25);
+        assert_eq!(missing[0], (0, 0));
+        assert_eq!(view.missing((0, 0), 3).len(), 3);
+        for chunk in missing {
+            view.mark_sent(chunk);
+        }
+        assert!(view.missing((0, 0), 100).is_empty());
+        assert!(view.take_stale((0, 0)).is_empty());
+
+        // A chunk east, the next row comes in and the last goes out
+        let mut coming = view.missing((1, 0), 100);
+        coming.sort();
+        assert_eq!(coming, vec![(3, -2), (3, -1), (3, 0), (3, 1), (3, 2)]);
+        let mut stale = view.take_stale((1, 0));
+        stale.sort();
+        assert_eq!(stale, vec![(-2, -2), (-2, -1), (-2, 0), (-2, 1), (-2, 2)]);
+        assert!(!view.contains((-2, 0)) && view.contains((-1, 0)));
+
+        let unload = unload_packet((3, -4));
+        assert_eq!((unload.mask, unload.continuous, unload.chunk_data.len()), (0, true, 0));
+    }
+}
@@ -0,0 +1,504 @@
+//! Chat commands.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::mem;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use message;
+use permissions;
+use player::{Player, SpawnPoint};
+use types::{ChatJson, Format};
+use types::consts::{Color, GameMode};
+use world::{memory, Weather, World};
+
+use rand::{self, Rng};
+use time;
+use uuid::Uuid;
+
+/// A command players can type in chat.
+pub struct Command {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub description: &'static str,
+    /// Op level needed to run the command, unless the world's permission
+    /// provider says otherwise.
+    pub level: i32,
+    run: fn(&World, &mut Player, &[&str]) -> io::Result<()>
+}
+
+/// Every command, in alphabetical order.
+pub const COMMANDS: &'static [Command] = &[
+    Command {
+        name: "backup",
+        usage: "/backup",
+        description: "Saves the world and copies it to the backup directory",
+        level: 4,
+        run: backup
+    },
+    Command {
+        name: "chunks",
+        usage: "/chunks",
+        description: "Shows how many chunks, entities and players the world has",
+        level: 2,
+        run: chunks
+    },
+    Command {
+        name: "debug",
+        usage: "/debug <start|stop>",
+        description: "Profiles ticks, writing a report to the debug directory",
+        level: 3,
+        run: debug
+    },
+    Command {
+        name: "help",
+        usage: "/help [page|command]",
+        description: "Lists the commands you can use, or tells how to use one",
+        level: 0,
+        run: help
+    },
+    Command {
+        name: "mem",
+        usage: "/mem",
+        description: "Shows how much memory the server uses",
+        level: 2,
+        run: mem
+    },
+    Command {
+        name: "msg",
+        usage: "/msg <player> <message ...>",
+        description: "Sends a private message to a player",
+        level: 0,
+        run: msg
+    },
+    Command {
+        name: "r",
+        usage: "/r <message ...>",
+        description: "Replies to the last player you sent or got a private message from",
+        level: 0,
+        run: reply
+    },
+    Command {
+        name: "socialspy",
+        usage: "/socialspy",
+        description: "Toggles seeing everyone's private messages",
+        level: 1,
+        run: social_spy
+    },
+    Command {
+        name: "spawnpoint",
+        usage: "/spawnpoint [player] [<x> <y> <z>]",
+        description: "Sets where a player respawns, where they stand by default",
+        level: 2,
+        run: spawnpoint
+    },
+    Command {
+        name: "time",
+        usage: "/time <set|add> <value>",
+        description: "Sets the time of day, day and night standing for their start",
+        level: 2,
+        run: time
+    },
+    Command {
+        name: "tps",
+        usage: "/tps",
+        description: "Shows how many ticks per second the world runs at",
+        level: 2,
+        run: tps
+    },
+    Command {
+        name: "weather",
+        usage: "/weather <clear|rain|thunder> [seconds]",
+        description: "Changes the weather, for 5 to 15 minutes unless told",
+        level: 2,
+        run: weather
+    }
+];
+
+/// Commands listed on each page of /help, like vanilla.
+pub const HELP_PAGE_SIZE: usize = 7;
+
+/// A private message, delivered on the next tick so the sender and the
+/// recipient are never locked at once.
+pub struct Whisper {
+    pub from: String,
+    pub to: Uuid,
+    pub to_name: String,
+    pub text: String
+}
+
+/// A spawn point set for another player, applied on the next tick for the
+/// same reason as whispers. Without a location, it's where they stand.
+pub struct SpawnPointChange {
+    pub by: Uuid,
+    pub to: Uuid,
+    pub location: Option<[i32; 3]>
+}
+
+/// The command called `name`.
+pub fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+/// Number of /help pages listing `count` commands, at least one.
+pub fn help_pages(count: usize) -> usize {
+    ((count + HELP_PAGE_SIZE - 1) / HELP_PAGE_SIZE).max(1)
+}
+
+/// A red message, for commands which failed.
+pub fn error<T: Into<ChatJson>>(message: T) -> ChatJson {
+    let mut message = message.into();
+    message.color = Some(Color::Red);
+    message
+}
+
+impl World {
+    /// Runs command `line`, as typed by `player` without the leading slash.
+    pub fn run_command(&self, player: &mut Player, line: &str) -> io::Result<()> {
+        let mut args: Vec<&str> = line.split_whitespace().collect();
+        if args.is_empty() {
+            return Ok(());
+        }
+        let name = args.remove(0).to_lowercase();
+        info!("{} issued server command: /{}", player.name(), line);
+        let command = find(&name);
+        match command {
+            Some(command) if self.has_permission(player, &permissions::command_node(command.name)) => {
+                (command.run)(self, player, &args)
+            }
+            Some(_) => player.send_message(error(message::NO_PERMISSION)),
+            None => player.send_message(error(message::UNKNOWN_COMMAND))
+        }
+    }
+
+    /// Sends private message `text` from `player` to the player called
+    /// `to`, on the next tick.
+    pub fn whisper(&self, player: &mut Player, to: &str, text: String) -> io::Result<()> {
+        let target = self.names.lock().unwrap().get(&to.to_lowercase()).cloned();
+        let (uuid, name) = match target {
+            Some(target) => target,
+            None => return player.send_message(error(message::PLAYER_NOT_FOUND))
+        };
+        if uuid == *player.uuid() {
+            return player.send_message(error(message::MESSAGE_SELF));
+        }
+        let mut text = text;
+        if !try!(self.check_chat(player, &mut text)) {
+            return Ok(());
+        }
+        info!("{} whispers to {}: {}", player.name(), name, text);
+        try!(player.send_message(whisper_style(message::MESSAGE_OUTGOING.to_chat(&[&name, &text]))));
+        player.set_reply_to(Some(name.clone()));
+        self.whispers.lock().unwrap().push(Whisper { from: player.name().to_string(), to: uuid, to_name: name, text: text });
+        Ok(())
+    }
+
+    /// Applies the spawn points set for other players since the last tick,
+    /// then tells whoever set them.
+    pub fn deliver_spawn_points(&self) {
+        let changes = mem::replace(&mut *self.spawn_points.lock().unwrap(), vec![]);
+        for change in changes {
+            let target = self.players.lock().unwrap().get(&change.to).cloned();
+            let feedback = match target {
+                Some(target) => {
+                    let mut target = target.lock().unwrap();
+                    let location = change.location.unwrap_or_else(|| block_of(target.position()));
+                    target.set_spawn_point(Some(SpawnPoint { location: location, forced: true }));
+                    spawn_point_set(target.name(), location)
+                }
+                None => error(message::PLAYER_NOT_FOUND)
+            };
+            let by = self.players.lock().unwrap().get(&change.by).cloned();
+            if let Some(by) = by {
+                let mut by = by.lock().unwrap();
+                if let Err(err) = by.send_message(feedback) {
+                    debug!("failed sending command feedback to {}: {}", by.name(), err);
+                }
+            }
+        }
+    }
+
+    /// Hands out the private messages sent since the last tick, with a copy
+    /// for operators spying on them.
+    pub fn deliver_whispers(&self, players: &[(Arc<Mutex<Player>>, i32, [f64; 3], GameMode)]) {
+        let whispers = mem::replace(&mut *self.whispers.lock().unwrap(), vec![]);
+        for whisper in whispers {
+            let target = self.players.lock().unwrap().get(&whisper.to).cloned();
+            if let Some(target) = target {
+                let mut target = target.lock().unwrap();
+                let incoming = whisper_style(message::MESSAGE_INCOMING.to_chat(&[&whisper.from, &whisper.text]));
+                if let Err(err) = target.send_message(incoming) {
+                    debug!("failed sending private message to {}: {}", target.name(), err);
+                }
+                target.set_reply_to(Some(whisper.from.clone()));
+            }
+            let mut spied = ChatJson::from(format!("[Spy] {} -> {}: {}", whisper.from, whisper.to_name, whisper.text));
+            spied.color = Some(Color::DarkGray);
+            for &(ref player, _, _, _) in players {
+                let mut player = player.lock().unwrap();
+                if !player.social_spy() || player.name() == whisper.from || player.name() == whisper.to_name {
+                    continue;
+                }
+                if let Err(err) = player.send_message(spied.clone()) {
+                    debug!("failed sending private message to {}: {}", player.name(), err);
+                }
+            }
+        }
+    }
+}
+
+fn backup(world: &World, player: &mut Player, _args: &[&str]) -> io::Result<()> {
+    try!(player.send_message("Backing up the world..."));
+    match world.backup() {
+        Ok(path) => player.send_message(format!("Saved backup to {}", path.display())),
+        Err(err) => {
+            warn!("backup failed: {}", err);
+            player.send_message(error(format!("Couldn't back up the world: {}", err)))
+        }
+    }
+}
+
+fn chunks(world: &World, player: &mut Player, _: &[&str]) -> io::Result<()> {
+    let (chunks, forced) = world.chunk_counts();
+    let (encoded, reused) = world.chunk_cache_stats();
+    let players = world.players().len();
+    player.send_message(format!("{}: {} chunks loaded ({} forced, {} encoded, {:.0}% reused), {} entities, {} players",
+                                world.config().level_name, chunks, forced, encoded, reused * 100.0, world.entity_count(), players))
+}
+
+fn debug(world: &World, player: &mut Player, args: &[&str]) -> io::Result<()> {
+    match args.first().map(|arg| &arg[..]) {
+        Some("start") => {
+            if world.profiler.lock().unwrap().start() {
+                player.send_message(message::DEBUG_START)
+            } else {
+                player.send_message(error(message::DEBUG_ALREADY_STARTED))
+            }
+        }
+        Some("stop") => {
+            let report = match world.profiler.lock().unwrap().stop() {
+                Some(report) => report,
+                None => return player.send_message(error(message::DEBUG_NOT_STARTED))
+            };
+            let name = format!("profile-results-{}.txt", time::strftime("%Y-%m-%d_%H.%M.%S", &time::now()).unwrap());
+            let path = Path::new("debug").join(name);
+            let written = fs::create_dir_all("debug")
+                .and_then(|_| File::create(&path))
+                .and_then(|mut file| file.write_all(report.to_text().as_bytes()));
+            if let Err(err) = written {
+                warn!("failed writing {}: {}", path.display(), err);
+                return player.send_message(error(format!("Couldn't save profiler results: {}", err)));
+            }
+            info!("profiled {} ticks at {:.2} ticks per second", report.ticks, report.ticks_per_second());
+            player.send_message(message::DEBUG_STOP.to_chat(&[report.elapsed.as_secs().to_string(), report.ticks.to_string()]))
+        }
+        _ => usage(player, "debug")
+    }
+}
+
+fn help(world: &World, player: &mut Player, args: &[&str]) -> io::Result<()> {
+    let commands: Vec<&Command> = COMMANDS.iter()
+        .filter(|command| world.has_permission(player, &permissions::command_node(command.name)))
+        .collect();
+    let pages = help_pages(commands.len());
+    let page = match args.first() {
+        None => 1,
+        Some(arg) => match arg.parse::<i64>() {
+            Ok(page) if page < 1 => return player.send_message(error(message::NUMBER_TOO_SMALL.to_chat(&[*arg, "1"]))),
+            Ok(page) if page > pages as i64 => {
+                return player.send_message(error(message::NUMBER_TOO_BIG.to_chat(&[arg.to_string(), pages.to_string()])));
+            }
+            Ok(page) => page as usize,
+            Err(_) => {
+                let name = arg.trim_start_matches('/').to_lowercase();
+                return match commands.iter().find(|command| command.name == name) {
+                    Some(command) => {
+                        try!(player.send_message(message::USAGE.to_chat(&[command.usage])));
+                        player.send_message(command.description)
+                    }
+                    None => player.send_message(error(message::UNKNOWN_COMMAND))
+                };
+            }
+        }
+    };
+    let mut header = message::HELP_HEADER.to_chat(&[page.to_string(), pages.to_string()]);
+    header.color = Some(Color::DarkGreen);
+    try!(player.send_message(header));
+    for command in commands.iter().skip((page - 1) * HELP_PAGE_SIZE).take(HELP_PAGE_SIZE) {
+        try!(player.send_message(format!("{} - {}", command.usage, command.description)));
+    }
+    Ok(())
+}
+
+/// Tells `player` how command `name` is used, after they got it wrong.
+fn usage(player: &mut Player, name: &str) -> io::Result<()> {
+    let usage = find(name).map_or("", |command| command.usage);
+    player.send_message(error(message::USAGE.to_chat(&[usage])))
+}
+
+fn mem(_: &World, player: &mut Player, _: &[&str]) -> io::Result<()> {
+    match memory::usage() {
+        Some(usage) => player.send_message(format!("Memory used: {} ({} reserved)",
+                                                   memory::format_bytes(usage.resident), memory::format_bytes(usage.reserved))),
+        None => player.send_message(error("Memory use can't be told on this system"))
+    }
+}
+
+fn msg(world: &World, player: &mut Player, args: &[&str]) -> io::Result<()> {
+    if args.len() < 2 {
+        return usage(player, "msg");
+    }
+    world.whisper(player, args[0], args[1..].join(" "))
+}
+
+fn reply(world: &World, player: &mut Player, args: &[&str]) -> io::Result<()> {
+    if args.is_empty() {
+        return usage(player, "r");
+    }
+    match player.reply_to().map(|name| name.to_string()) {
+        Some(name) => world.whisper(player, &name, args.join(" ")),
+        None => player.send_message(error("There's nobody to reply to"))
+    }
+}
+
+fn social_spy(_: &World, player: &mut Player, _: &[&str]) -> io::Result<()> {
+    let spying = !player.social_spy();
+    player.set_social_spy(spying);
+    player.send_message(if spying { "Social spy enabled" } else { "Social spy disabled" })
+}
+
+fn spawnpoint(world: &World, player: &mut Player, args: &[&str]) -> io::Result<()> {
+    let (name, coordinates) = match args.len() {
+        0 => (player.name().to_string(), None),
+        1 => (args[0].to_string(), None),
+        4 => (args[0].to_string(), Some(&args[1..])),
+        _ => return usage(player, "spawnpoint")
+    };
+    let location = match coordinates {
+        Some(coordinates) => {
+            let position = player.position();
+            let mut location = [0; 3];
+            for (axis, arg) in coordinates.iter().enumerate() {
+                location[axis] = match coordinate(arg, position[axis]) {
+                    Some(coordinate) => coordinate,
+                    None => return player.send_message(error(message::INVALID_NUMBER.to_chat(&[arg])))
+                };
+            }
+            Some(location)
+        }
+        None => None
+    };
+    if name.to_lowercase() == player.name().to_lowercase() {
+        let location = location.unwrap_or_else(|| block_of(player.position()));
+        player.set_spawn_point(Some(SpawnPoint { location: location, forced: true }));
+        let feedback = spawn_point_set(player.name(), location);
+        return player.send_message(feedback);
+    }
+    let target = world.names.lock().unwrap().get(&name.to_lowercase()).cloned();
+    match target {
+        Some((uuid, _)) => {
+            let change = SpawnPointChange { by: player.uuid().clone(), to: uuid, location: location };
+            world.spawn_points.lock().unwrap().push(change);
+            Ok(())
+        }
+        None => player.send_message(error(message::PLAYER_NOT_FOUND))
+    }
+}
+
+fn time(world: &World, player: &mut Player, args: &[&str]) -> io::Result<()> {
+    if args.len() != 2 {
+        return usage(player, "time");
+    }
+    let value = match (args[0], args[1]) {
+        ("set", "day") => 1000,
+        ("set", "night") => 13000,
+        (_, arg) => match arg.parse::<i64>() {
+            Ok(value) if value < 0 => return player.send_message(error(message::NUMBER_TOO_SMALL.to_chat(&[arg, "0"]))),
+            Ok(value) => value,
+            Err(_) => return player.send_message(error(message::INVALID_NUMBER.to_chat(&[arg])))
+        }
+    };
+    match args[0] {
+        "set" => {
+            world.set_time(value);
+            player.send_message(message::TIME_SET.to_chat(&[value.to_string()]))
+        }
+        "add" => {
+            world.add_time(value);
+            player.send_message(message::TIME_ADDED.to_chat(&[value.to_string()]))
+        }
+        _ => usage(player, "time")
+    }
+}
+
+fn tps(world: &World, player: &mut Player, _: &[&str]) -> io::Result<()> {
+    let tps = world.tps();
+    let mean = world.mean_tick_time();
+    let millis = mean.as_secs() as f64 * 1000.0 + mean.subsec_nanos() as f64 / 1e6;
+    let mut message = ChatJson::from(format!("{}: {:.2} ticks per second, {:.2} ms per tick", world.config().level_name, tps, millis));
+    // Green at full speed, yellow when a little behind, red when lagging
+    message.color = Some(if tps >= 19.0 { Color::BrightGreen } else if tps >= 15.0 { Color::Yellow } else { Color::Red });
+    player.send_message(message)
+}
+
+fn weather(world: &World, player: &mut Player, args: &[&str]) -> io::Result<()> {
+    let seconds = match args.get(1) {
+        None => rand::thread_rng().gen_range(300, 900),
+        Some(arg) => match arg.parse::<i32>() {
+            Ok(seconds) if seconds < 1 => return player.send_message(error(message::NUMBER_TOO_SMALL.to_chat(&[*arg, "1"]))),
+            Ok(seconds) if seconds > 1000000 => return player.send_message(error(message::NUMBER_TOO_BIG.to_chat(&[*arg, "1000000"]))),
+            Ok(seconds) => seconds,
+            Err(_) => return player.send_message(error(message::INVALID_NUMBER.to_chat(&[*arg])))
+        }
+    };
+    let (raining, thundering, feedback) = match args.first().map(|arg| &arg[..]) {
+        Some("clear") => (false, false, message::WEATHER_CLEAR),
+        Some("rain") => (true, false, message::WEATHER_RAIN),
+        Some("thunder") => (true, true, message::WEATHER_THUNDER),
+        _ => return usage(player, "weather")
+    };
+    if args.len() > 2 {
+        return usage(player, "weather");
+    }
+    world.set_weather(Weather::lasting(raining, thundering, seconds * 20));
+    player.send_message(feedback)
+}
+
+/// Block coordinate `arg`, relative to `base` when it starts with `~`.
+fn coordinate(arg: &str, base: f64) -> Option<i32> {
+    if !arg.starts_with('~') {
+        return arg.parse().ok();
+    }
+    let offset = if arg.len() == 1 { Some(0.0) } else { arg[1..].parse::<f64>().ok() };
+    offset.map(|offset| (base + offset).floor() as i32)
+}
+
+fn block_of(position: [f64; 3]) -> [i32; 3] {
+    [position[0].floor() as i32, position[1].floor() as i32, position[2].floor() as i32]
+}
+
+fn spawn_point_set(name: &str, location: [i32; 3]) -> ChatJson {
+    message::SPAWN_POINT_SET.to_chat(&[name.to_string(), location[0].to_string(), location[1].to_string(), location[2].to_string()])
+}
+
+/// Private messages are gray and in italics, like vanilla's.
+fn whisper_style(mut message: ChatJson) -> ChatJson {
+    message.color = Some(Color::Gray);
+    message.formats.insert(Format::Italic);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn help_listing() {
+        assert!(COMMANDS.windows(2).all(|pair| pair[0].name < pair[1].name));
+        assert_eq!(find("help").map(|command| command.level), Some(0));
+        assert_eq!(help_pages(0), 1);
+        assert_eq!(help_pages(HELP_PAGE_SIZE), 1);
+        assert_eq!(help_pages(HELP_PAGE_SIZE + 1), 2);
+    }
+}
@@ -0,0 +1,383 @@
+//! Per-world settings, kept in the world's level.dat.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use proto::properties::Properties;
+use types::consts::GameMode;
+
+use flate2::read::GzDecoder;
+use nbt;
+use num::FromPrimitive;
+
+/// Where a world is kept, as set by level-storage in server.properties.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Storage {
+    /// In the level-name directory.
+    Disk,
+    /// Nowhere, the world is generated on start and gone on exit. Meant
+    /// for tests and throwaway servers.
+    Memory
+}
+
+impl Storage {
+    pub fn of(props: &Properties) -> Storage {
+        match &props.level_storage[..] {
+            "memory" => Storage::Memory,
+            "disk" => Storage::Disk,
+            other => {
+                warn!("unknown level-storage {}, keeping the world on disk", other);
+                Storage::Disk
+            }
+        }
+    }
+}
+
+/// `version` of worlds stored in the Anvil format, the only one supported.
+pub const ANVIL: i32 = 19133;
+
+/// `version` of worlds stored in the older McRegion format.
+pub const MCREGION: i32 = 19132;
+
+/// Settings of a world, as stored in the `Data` compound of its level.dat.
+///
+/// Entries of level.dat which aren't understood here are kept as they are
+/// and written back on save.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorldConfig {
+    pub level_name: String,
+    pub seed: i64,
+    /// World generator, `"default"`, `"flat"`, `"largeBiomes"`...
+    pub generator: String,
+    pub generator_options: String,
+    /// Whether structures such as villages are generated.
+    pub map_features: bool,
+    /// Where players spawn and compasses point to.
+    pub spawn: [i32; 3],
+    /// Game mode players new to the world start in.
+    pub game_type: GameMode,
+    /// Ticks the world has run for.
+    pub time: i64,
+    /// Ticks of daylight cycle gone by, which commands and sleeping move
+    /// on faster than `time`.
+    pub day_time: i64,
+    pub border_center: [f64; 2],
+    pub border_diameter: f64,
+    pub border_warning_time: i32,
+    pub border_warning_blocks: i32,
+    /// Game rules, which level.dat stores as strings.
+    pub game_rules: BTreeMap<String, String>,
+    /// Storage format of the world's chunks, `ANVIL` unless the world
+    /// predates it.
+    pub version: i32,
+    /// Set by vanilla 1.9 and later, whose worlds hematite can't read.
+    pub data_version: Option<i32>,
+    /// Version of hematite which last saved the world, if any did.
+    pub hematite_version: Option<String>,
+    other: HashMap<String, nbt::Value>
+}
+
+impl WorldConfig {
+    /// Settings of a new world, as configured in server.properties.
+    pub fn new(props: &Properties) -> WorldConfig {
+        let seed = if props.level_seed.is_empty() {
+            0
+        } else {
+            // Seeds which aren't numbers are hashed like vanilla does
+            props.level_seed.parse().unwrap_or_else(|_| {
+                props.level_seed.chars().fold(0i32, |hash, c| hash.wrapping_mul(31).wrapping_add(c as i32)) as i64
+            })
+        };
+        let mut game_rules = BTreeMap::new();
+        for &(name, value) in DEFAULT_GAME_RULES {
+            game_rules.insert(name.to_string(), value.to_string());
+        }
+        WorldConfig {
+            level_name: props.level_name.clone(),
+            seed: seed,
+            generator: props.level_type.to_lowercase(),
+            generator_options: props.generator_settings.clone(),
+            map_features: props.generate_structures,
+            spawn: [10, 65, 10],
+            game_type: GameMode::from_i32(props.gamemode).unwrap_or(GameMode::Survival),
+            time: 0,
+            day_time: 0,
+            border_center: [0.0, 0.0],
+            border_diameter: 60000000.0,
+            border_warning_time: 15,
+            border_warning_blocks: 5,
+            game_rules: game_rules,
+            version: ANVIL,
+            data_version: None,
+            hematite_version: None,
+            other: HashMap::new()
+        }
+    }
+
+    /// Reads the settings from the gzipped level.dat at `path`, falling back
+    /// to `defaults` for missing entries.
+    pub fn load(path: &Path, defaults: &WorldConfig) -> io::Result<WorldConfig> {
+        let file = try!(File::open(path));
+        let mut src = try!(GzDecoder::new(BufReader::new(file)));
+        let root = match try!(nbt::Value::read_header(&mut src).and_then(|(id, _)| nbt::Value::from_reader(id, &mut src))) {
+            nbt::Value::Compound(root) => root,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "level.dat root isn't a compound"))
+        };
+        match root.get("Data") {
+            Some(&nbt::Value::Compound(ref data)) => Ok(WorldConfig::from_data(data.clone(), defaults)),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "level.dat has no Data compound"))
+        }
+    }
+
+    /// Writes the settings to a gzipped level.dat at `path`, creating the
+    /// world directory if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            try!(fs::create_dir_all(dir));
+        }
+        let mut level = nbt::Blob::new("".to_string());
+        try!(level.insert("Data".to_string(), nbt::Value::Compound(self.to_data())));
+        let mut dst = BufWriter::new(try!(File::create(path)));
+        Ok(try!(level.write_gzip(&mut dst)))
+    }
+
+    /// Fails unless hematite can use the world without damaging it: worlds
+    /// converted by newer vanilla versions and those never converted to
+    /// Anvil are refused.
+    pub fn check_version(&self) -> io::Result<()> {
+        let unsupported = |reason: String| Err(io::Error::new(io::ErrorKind::InvalidData, reason));
+        if let Some(data_version) = self.data_version {
+            return unsupported(format!("world was saved by a newer Minecraft (data version {}), only 1.8 worlds are supported", data_version));
+        }
+        match self.version {
+            ANVIL => Ok(()),
+            MCREGION => unsupported("world uses the McRegion format, open it in vanilla 1.8 to convert it".to_string()),
+            version => unsupported(format!("world uses unknown format version {}", version))
+        }
+    }
+
+    /// Value of game rule `name`, if it's set.
+    pub fn game_rule(&self, name: &str) -> Option<&str> {
+        self.game_rules.get(name).map(|value| &value[..])
+    }
+
+    /// Whether boolean game rule `name` is on, rules which aren't set are
+    /// off.
+    pub fn game_rule_enabled(&self, name: &str) -> bool {
+        self.game_rule(name) == Some("true")
+    }
+
+    pub fn set_game_rule(&mut self, name: &str, value: &str) {
+        self.game_rules.insert(name.to_string(), value.to_string());
+    }
+
+    /// Whether anything about the world border differs from `other`.
+    pub fn border_differs(&self, other: &WorldConfig) -> bool {
+        self.border_center != other.border_center ||
+            self.border_diameter != other.border_diameter ||
+            self.border_warning_time != other.border_warning_time ||
+            self.border_warning_blocks != other.border_warning_blocks
+    }
+
+    /// Whether chunks generated now would differ from those generated with
+    /// `other`.
+    pub fn generator_differs(&self, other: &WorldConfig) -> bool {
+        self.seed != other.seed || self.generator != other.generator || self.generator_options != other.generator_options
+            || self.map_features != other.map_features
+    }
+
+    fn from_data(mut data: HashMap<String, nbt::Value>, defaults: &WorldConfig) -> WorldConfig {
+        let mut config = defaults.clone();
+        if let Some(nbt::Value::String(name)) = data.remove("LevelName") {
+            config.level_name = name;
+        }
+        if let Some(nbt::Value::Long(seed)) = data.remove("RandomSeed") {
+            config.seed = seed;
+        }
+        if let Some(nbt::Value::String(generator)) = data.remove("generatorName") {
+            config.generator = generator;
+        }
+        if let Some(nbt::Value::String(options)) = data.remove("generatorOptions") {
+            config.generator_options = options;
+        }
+        if let Some(nbt::Value::Byte(map_features)) = data.remove("MapFeatures") {
+            config.map_features = map_features != 0;
+        }
+        match (data.remove("SpawnX"), data.remove("SpawnY"), data.remove("SpawnZ")) {
+            (Some(nbt::Value::Int(x)), Some(nbt::Value::Int(y)), Some(nbt::Value::Int(z))) => config.spawn = [x, y, z],
+            _ => {}
+        }
+        if let Some(game_type) = data.remove("GameType").and_then(|game_type| match game_type {
+            nbt::Value::Int(game_type) => GameMode::from_i32(game_type),
+            _ => None
+        }) {
+            config.game_type = game_type;
+        }
+        if let Some(nbt::Value::Long(time)) = data.remove("Time") {
+            config.time = time;
+        }
+        if let Some(nbt::Value::Long(day_time)) = data.remove("DayTime") {
+            config.day_time = day_time;
+        }
+        if let Some(nbt::Value::Double(x)) = data.remove("BorderCenterX") {
+            config.border_center[0] = x;
+        }
+        if let Some(nbt::Value::Double(z)) = data.remove("BorderCenterZ") {
+            config.border_center[1] = z;
+        }
+        if let Some(nbt::Value::Double(diameter)) = data.remove("BorderSize") {
+            config.border_diameter = diameter;
+        }
+        if let Some(nbt::Value::Double(time)) = data.remove("BorderWarningTime") {
+            config.border_warning_time = time as i32;
+        }
+        if let Some(nbt::Value::Double(blocks)) = data.remove("BorderWarningBlocks") {
+            config.border_warning_blocks = blocks as i32;
+        }
+        if let Some(nbt::Value::Compound(rules)) = data.remove("GameRules") {
+            for (name, value) in rules {
+                if let nbt::Value::String(value) = value {
+                    config.game_rules.insert(name, value);
+                }
+            }
+        }
+        // Worlds older than McRegion don't say
+        config.version = match data.remove("version") {
+            Some(nbt::Value::Int(version)) => version,
+            _ => 0
+        };
+        config.data_version = match data.remove("DataVersion") {
+            Some(nbt::Value::Int(version)) => Some(version),
+            _ => None
+        };
+        if let Some(nbt::Value::Compound(mut hematite)) = data.remove("Hematite") {
+            if let Some(nbt::Value::String(version)) = hematite.remove("Version") {
+                config.hematite_version = Some(version);
+            }
+        }
+        config.other = data;
+        config
+    }
+
+    fn to_data(&self) -> HashMap<String, nbt::Value> {
+        let mut data = self.other.clone();
+        data.insert("LevelName".to_string(), nbt::Value::String(self.level_name.clone()));
+        data.insert("RandomSeed".to_string(), nbt::Value::Long(self.seed));
+        data.insert("generatorName".to_string(), nbt::Value::String(self.generator.clone()));
+        data.insert("generatorOptions".to_string(), nbt::Value::String(self.generator_options.clone()));
+        data.insert("MapFeatures".to_string(), nbt::Value::Byte(if self.map_features { 1 } else { 0 }));
+        data.insert("SpawnX".to_string(), nbt::Value::Int(self.spawn[0]));
+        data.insert("SpawnY".to_string(), nbt::Value::Int(self.spawn[1]));
+        data.insert("SpawnZ".to_string(), nbt::Value::Int(self.spawn[2]));
+        data.insert("GameType".to_string(), nbt::Value::Int(self.game_type as i32));
+        data.insert("Time".to_string(), nbt::Value::Long(self.time));
+        data.insert("DayTime".to_string(), nbt::Value::Long(self.day_time));
+        data.insert("BorderCenterX".to_string(), nbt::Value::Double(self.border_center[0]));
+        data.insert("BorderCenterZ".to_string(), nbt::Value::Double(self.border_center[1]));
+        data.insert("BorderSize".to_string(), nbt::Value::Double(self.border_diameter));
+        data.insert("BorderWarningTime".to_string(), nbt::Value::Double(self.border_warning_time as f64));
+        data.insert("BorderWarningBlocks".to_string(), nbt::Value::Double(self.border_warning_blocks as f64));
+        let rules = self.game_rules.iter().map(|(name, value)| (name.clone(), nbt::Value::String(value.clone()))).collect();
+        data.insert("GameRules".to_string(), nbt::Value::Compound(rules));
+        data.insert("version".to_string(), nbt::Value::Int(self.version));
+        if let Some(version) = self.data_version {
+            data.insert("DataVersion".to_string(), nbt::Value::Int(version));
+        }
+        let mut hematite = HashMap::new();
+        hematite.insert("Version".to_string(), nbt::Value::String(env!("CARGO_PKG_VERSION").to_string()));
+        data.insert("Hematite".to_string(), nbt::Value::Compound(hematite));
+        data
+    }
+}
+
+/// Game rules new worlds start with.
+const DEFAULT_GAME_RULES: &'static [(&'static str, &'static str)] = &[
+    ("commandBlockOutput", "true"),
+    ("doDaylightCycle", "true"),
+    ("doEntityDrops", "true"),
+    ("doFireTick", "true"),
+    ("doMobLoot", "true"),
+    ("doMobSpawning", "true"),
+    ("doTileDrops", "true"),
+    ("keepInventory", "false"),
+    ("logAdminCommands", "true"),
+    ("mobGriefing", "true"),
+    ("naturalRegeneration", "true"),
+    ("randomTickSpeed", "3"),
+    ("reducedDebugInfo", "false"),
+    ("sendCommandFeedback", "true"),
+    ("showDeathMessages", "true")
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use proto::properties::Properties;
+
+    use nbt;
+
+    #[test]
+    fn data_round_trip() {
+        let defaults = WorldConfig::new(&Properties::default());
+        let mut config = defaults.clone();
+        config.spawn = [100, 70, -20];
+        config.border_diameter = 500.0;
+        config.set_game_rule("keepInventory", "true");
+        config.game_type = GameMode::Creative;
+        config.day_time = 6000;
+        let mut data = config.to_data();
+        data.insert("MapFeatures".to_string(), nbt::Value::Byte(1));
+        let loaded = WorldConfig::from_data(data, &defaults);
+        assert!(loaded.game_rule_enabled("keepInventory"));
+        assert!(loaded.border_differs(&defaults));
+        assert!(!loaded.generator_differs(&defaults));
+        assert_eq!(loaded.to_data().get("MapFeatures"), Some(&nbt::Value::Byte(1)));
+        assert_eq!((loaded.game_type, loaded.day_time), (GameMode::Creative, 6000));
+        assert_eq!(loaded.hematite_version, Some(env!("CARGO_PKG_VERSION").to_string()));
+        assert_eq!(WorldConfig { other: HashMap::new(), hematite_version: None, ..loaded }, config);
+
+        let mut data = config.to_data();
+        data.insert("MapFeatures".to_string(), nbt::Value::Byte(0));
+        let loaded = WorldConfig::from_data(data, &defaults);
+        assert!(!loaded.map_features && loaded.generator_differs(&defaults));
+    }
+
+    #[test]
+    fn missing_entries() {
+        let defaults = WorldConfig::new(&Properties::default());
+        let mut data = HashMap::new();
+        data.insert("SpawnX".to_string(), nbt::Value::Int(5));
+        let loaded = WorldConfig::from_data(data, &defaults);
+        // Spawn is only taken as a whole
+        assert_eq!(loaded.spawn, defaults.spawn);
+        assert_eq!(loaded.game_rule("randomTickSpeed"), Some("3"));
+    }
+
+    #[test]
+    fn storage() {
+        let mut props = Properties::default();
+        assert_eq!(Storage::of(&props), Storage::Disk);
+        props.level_storage = "memory".to_string();
+        assert_eq!(Storage::of(&props), Storage::Memory);
+    }
+
+    #[test]
+    fn versions() {
+        let defaults = WorldConfig::new(&Properties::default());
+        assert!(defaults.check_version().is_ok());
+        let mut data = defaults.to_data();
+        data.insert("DataVersion".to_string(), nbt::Value::Int(169));
+        assert!(WorldConfig::from_data(data, &defaults).check_version().is_err());
+        let mut data = defaults.to_data();
+        data.insert("version".to_string(), nbt::Value::Int(MCREGION));
+        assert!(WorldConfig::from_data(data, &defaults).check_version().is_err());
+        let mut data = defaults.to_data();
+        data.remove("version");
+        assert!(WorldConfig::from_data(data, &defaults).check_version().is_err());
+    }
+}
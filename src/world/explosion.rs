@@ -0,0 +1,195 @@
+//! Explosions.
+
+use std::collections::BTreeSet;
+
+use block;
+use packet::play::clientbound;
+
+use rand;
+
+/// Blocks reached by an explosion, along with what's needed to work out how
+/// hard it hits entities around it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Explosion {
+    pub center: [f64; 3],
+    pub power: f32,
+    /// Every block position the blast reached, air included.
+    pub blocks: Vec<[i32; 3]>
+}
+
+impl Explosion {
+    /// Casts rays from `center` in every direction, like vanilla does, to find
+    /// the blocks `power` is strong enough to reach. `block_at` gives the
+    /// block (`id << 4 | metadata`) at a position.
+    pub fn new<F: Fn([i32; 3]) -> u16>(center: [f64; 3], power: f32, block_at: F) -> Explosion {
+        let mut blocks = BTreeSet::new();
+        for i in 0..16 {
+            for j in 0..16 {
+                for k in 0..16 {
+                    // Only rays going through the surface of a 16x16x16 cube
+                    if i != 0 && i != 15 && j != 0 && j != 15 && k != 0 && k != 15 {
+                        continue;
+                    }
+                    let direction = normalize([i as f64 / 15.0 * 2.0 - 1.0,
+                                               j as f64 / 15.0 * 2.0 - 1.0,
+                                               k as f64 / 15.0 * 2.0 - 1.0]);
+                    let mut intensity = power * (0.7 + rand::random::<f32>() * 0.6);
+                    let mut position = center;
+                    while intensity > 0.0 {
+                        let location = [position[0].floor() as i32, position[1].floor() as i32, position[2].floor() as i32];
+                        let id = block_at(location) >> 4;
+                        if id != block::AIR {
+                            intensity -= (block::blast_resistance(id) / 5.0 + 0.3) * 0.3;
+                        }
+                        if intensity > 0.0 {
+                            blocks.insert(location);
+                        }
+                        for n in 0..3 {
+                            position[n] += direction[n] * 0.3;
+                        }
+                        intensity -= 0.22500001;
+                    }
+                }
+            }
+        }
+        Explosion {
+            center: center,
+            power: power,
+            blocks: blocks.into_iter().collect()
+        }
+    }
+
+    /// Fraction, from 0 to 1, of the box between `min` and `max` which can
+    /// see the center of the explosion.
+    pub fn exposure<F: Fn([i32; 3]) -> u16>(&self, min: [f64; 3], max: [f64; 3], block_at: F) -> f64 {
+        let mut step = [0.0; 3];
+        for n in 0..3 {
+            step[n] = 1.0 / ((max[n] - min[n]) * 2.0 + 1.0);
+        }
+        let (mut seen, mut total) = (0, 0);
+        let mut x = 0.0;
+        while x <= 1.0 {
+            let mut y = 0.0;
+            while y <= 1.0 {
+                let mut z = 0.0;
+                while z <= 1.0 {
+                    let from = [min[0] + (max[0] - min[0]) * x,
+                                min[1] + (max[1] - min[1]) * y,
+                                min[2] + (max[2] - min[2]) * z];
+                    if !blocked(from, self.center, &block_at) {
+                        seen += 1;
+                    }
+                    total += 1;
+                    z += step[2];
+                }
+                y += step[1];
+            }
+            x += step[0];
+        }
+        seen as f64 / total as f64
+    }
+
+    /// How hard the explosion hits something at `position`, from 0 (out of
+    /// range) to 1.
+    pub fn impact(&self, position: [f64; 3], exposure: f64) -> f64 {
+        let reach = self.power as f64 * 2.0;
+        let distance = distance(position, self.center) / reach;
+        if distance > 1.0 { 0.0 } else { (1.0 - distance) * exposure }
+    }
+
+    /// Velocity, in blocks per tick, pushing something at `position` away
+    /// from the center.
+    pub fn knockback(&self, position: [f64; 3], impact: f64) -> [f64; 3] {
+        let direction = normalize([position[0] - self.center[0],
+                                   position[1] - self.center[1],
+                                   position[2] - self.center[2]]);
+        [direction[0] * impact, direction[1] * impact, direction[2] * impact]
+    }
+
+    /// Damage in half hearts dealt for a given `impact`.
+    pub fn damage(&self, impact: f64) -> f32 {
+        if impact <= 0.0 {
+            return 0.0;
+        }
+        let reach = self.power as f64 * 2.0;
+        ((impact * impact + impact) / 2.0 * 8.0 * reach + 1.0) as i32 as f32
+    }
+
+    /// Packet showing the explosion to a player being pushed by
+    /// `player_motion`.
+    pub fn packet(&self, player_motion: [f64; 3]) -> clientbound::Explosion {
+        // The client adds records to the truncated, not floored, center
+        let origin = [self.center[0] as i32, self.center[1] as i32, self.center[2] as i32];
+        clientbound::Explosion {
+            position: [self.center[0] as f32, self.center[1] as f32, self.center[2] as f32],
+            radius: self.power,
+            records: self.blocks.iter().map(|location| {
+                [(location[0] - origin[0]) as i8, (location[1] - origin[1]) as i8, (location[2] - origin[2]) as i8]
+            }).collect(),
+            player_motion: [player_motion[0] as f32, player_motion[1] as f32, player_motion[2] as f32]
+        }
+    }
+}
+
+/// Whether a solid block stands between `from` and `to`.
+fn blocked<F: Fn([i32; 3]) -> u16>(from: [f64; 3], to: [f64; 3], block_at: &F) -> bool {
+    let steps = (distance(from, to) / 0.25).ceil() as i32;
+    for i in 0..steps {
+        let t = i as f64 / steps as f64;
+        let location = [(from[0] + (to[0] - from[0]) * t).floor() as i32,
+                        (from[1] + (to[1] - from[1]) * t).floor() as i32,
+                        (from[2] + (to[2] - from[2]) * t).floor() as i32];
+        if block::is_solid(block_at(location) >> 4) {
+            return true;
+        }
+    }
+    false
+}
+
+fn distance(a: [f64; 3], b: [f64; 3]) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+fn normalize(v: [f64; 3]) -> [f64; 3] {
+    let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if length < 1.0e-4 { [0.0; 3] } else { [v[0] / length, v[1] / length, v[2] / length] }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stone everywhere below y = 64, air above.
+    fn ground(location: [i32; 3]) -> u16 {
+        if location[1] < 64 { 1 << 4 } else { 0 }
+    }
+
+    #[test]
+    fn destroys_nearby_blocks() {
+        let explosion = Explosion::new([0.5, 64.5, 0.5], 4.0, ground);
+        assert!(explosion.blocks.contains(&[0, 63, 0]));
+        assert!(!explosion.blocks.contains(&[0, 50, 0]));
+    }
+
+    #[test]
+    fn obsidian_survives() {
+        let explosion = Explosion::new([0.5, 64.5, 0.5], 4.0, |location: [i32; 3]| {
+            if location[1] < 64 { 49 << 4 } else { 0 }
+        });
+        assert!(explosion.blocks.iter().all(|location| location[1] >= 64));
+    }
+
+    #[test]
+    fn exposure_and_damage() {
+        let explosion = Explosion::new([0.5, 64.5, 0.5], 4.0, |_| 0);
+        let exposure = explosion.exposure([2.2, 64.0, 0.2], [2.8, 65.8, 0.8], |_| 0);
+        assert_eq!(exposure, 1.0);
+        let buried = explosion.exposure([2.2, 60.0, 0.2], [2.8, 61.8, 0.8], ground);
+        assert_eq!(buried, 0.0);
+        assert_eq!(explosion.impact([20.0, 64.5, 0.5], 1.0), 0.0);
+        let impact = explosion.impact([4.5, 64.5, 0.5], 1.0);
+        assert_eq!(impact, 0.5);
+        assert_eq!(explosion.damage(impact), 25.0);
+        assert_eq!(explosion.knockback([4.5, 64.5, 0.5], impact), [0.5, 0.0, 0.0]);
+    }
+}
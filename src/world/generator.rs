@@ -0,0 +1,451 @@
+//! Generation of the chunks a world doesn't have saved: layered terrain,
+//! carved with caves and ravines, then features placed on it, ore veins,
+//! lakes and trees, each of which can be turned off in the generator
+//! settings.
+//!
+//! Features stay within the chunk they're generated for, so chunks can be
+//! generated in any order and still come out the same for a given seed.
+//! Structures, which can span chunks, are placed last, see `structures`.
+//!
+//! The Nether and the End have generators of their own: netherrack
+//! caverns over a lava sea, and an island of end stone around the origin.
+
+use types::{Chunk, ChunkColumn};
+use types::consts::Dimension;
+use world::carvers;
+use world::random::{JavaRandom, WorldRandom};
+use world::structures::{self, Dungeons, Structure, Villages};
+
+/// Blocks generated, as ids.
+const BEDROCK: u16 = 7;
+const STONE: u16 = 1;
+const DIRT: u16 = 3;
+const GRASS: u16 = 2;
+const WATER: u16 = 9;
+const LOG: u16 = 17;
+const LEAVES: u16 = 18;
+const LAVA: u16 = 11;
+const NETHERRACK: u16 = 87;
+const END_STONE: u16 = 121;
+
+/// Height of the top grass layer.
+pub const SURFACE: usize = 63;
+
+/// Biome ids.
+pub const PLAINS: u8 = 1;
+pub const FOREST: u8 = 4;
+pub const HELL: u8 = 8;
+pub const SKY: u8 = 9;
+
+/// Chunks across the areas which have a village each, if they start in
+/// plains.
+pub const VILLAGE_SPACING: i32 = 32;
+
+/// Height of the Nether, between its bedrock floor and ceiling.
+pub const NETHER_HEIGHT: usize = 128;
+
+/// Height of the lava sea filling the bottom of Nether caverns.
+pub const LAVA_SEA: usize = 31;
+
+/// Blocks between the points Nether caverns are shaped from.
+const CELL: usize = 8;
+
+/// Radius of the End island, and the height of its top at the center.
+pub const END_ISLAND_RADIUS: f64 = 80.0;
+pub const END_ISLAND_TOP: f64 = 64.0;
+
+/// An ore: the block, how many blocks a vein has at most, veins per chunk,
+/// and the height veins stay under.
+struct Ore {
+    block: u16,
+    size: i32,
+    veins: i32,
+    max_y: i32
+}
+
+const ORES: [Ore; 6] = [
+    // Coal, iron, gold, redstone, diamond then lapis
+    Ore { block: 16, size: 17, veins: 20, max_y: 128 },
+    Ore { block: 15, size: 9, veins: 20, max_y: 64 },
+    Ore { block: 14, size: 9, veins: 2, max_y: 32 },
+    Ore { block: 73, size: 8, veins: 8, max_y: 16 },
+    Ore { block: 56, size: 8, veins: 1, max_y: 16 },
+    Ore { block: 21, size: 7, veins: 1, max_y: 32 }
+];
+
+/// Which features are placed, from the generator settings: comma separated
+/// `name=false` turn them off, e.g. `trees=false,lakes=false`.
+/// `structures` also follows the world's generate-structures setting.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Features {
+    pub caves: bool,
+    pub ravines: bool,
+    pub ores: bool,
+    pub lakes: bool,
+    pub trees: bool,
+    pub structures: bool
+}
+
+impl Features {
+    pub fn all() -> Features {
+        Features { caves: true, ravines: true, ores: true, lakes: true, trees: true, structures: true }
+    }
+
+    /// Bare terrain, like superflat worlds have.
+    pub fn none() -> Features {
+        Features { caves: false, ravines: false, ores: false, lakes: false, trees: false, structures: false }
+    }
+
+    /// Reads generator settings, ignoring what isn't about features.
+    pub fn parse(settings: &str) -> Features {
+        let mut features = Features::all();
+        for entry in settings.split(',') {
+            let mut parts = entry.splitn(2, '=');
+            let (name, value) = (parts.next().unwrap_or("").trim(), parts.next().unwrap_or("").trim());
+            let enabled = match value {
+                "true" => true,
+                "false" => false,
+                _ => continue
+            };
+            match name {
+                "caves" => features.caves = enabled,
+                "ravines" => features.ravines = enabled,
+                "ores" => features.ores = enabled,
+                "lakes" => features.lakes = enabled,
+                "trees" => features.trees = enabled,
+                "structures" => features.structures = enabled,
+                _ => {}
+            }
+        }
+        features
+    }
+}
+
+pub struct Generator {
+    random: WorldRandom,
+    features: Features,
+    dimension: Dimension
+}
+
+impl Generator {
+    /// An Overworld generator.
+    pub fn new(random: WorldRandom, features: Features) -> Generator {
+        Generator::with_dimension(Dimension::Overworld, random, features)
+    }
+
+    /// A generator for `dimension`, features only apply to the Overworld.
+    pub fn with_dimension(dimension: Dimension, random: WorldRandom, features: Features) -> Generator {
+        Generator { random: random, features: features, dimension: dimension }
+    }
+
+    pub fn features(&self) -> Features { self.features }
+    pub fn dimension(&self) -> Dimension { self.dimension }
+
+    /// Biome of chunk (`x`, `z`), picked for areas of 4x4 chunks in the
+    /// Overworld.
+    pub fn biome(&self, x: i32, z: i32) -> u8 {
+        match self.dimension {
+            Dimension::Nether => HELL,
+            Dimension::End => SKY,
+            Dimension::Overworld => {
+                if self.random.feature("biome", x >> 2, z >> 2).next_int_bounded(3) == 0 { FOREST } else { PLAINS }
+            }
+        }
+    }
+
+    /// Generates chunk column (`x`, `z`).
+    pub fn column(&self, x: i32, z: i32) -> ChunkColumn {
+        match self.dimension {
+            Dimension::Overworld => self.overworld_column(x, z),
+            Dimension::Nether => self.nether_column(x, z),
+            Dimension::End => self.end_column(x, z)
+        }
+    }
+
+    fn overworld_column(&self, x: i32, z: i32) -> ChunkColumn {
+        let biome = self.biome(x, z);
+        let mut column = ChunkColumn {
+            chunks: (0..(SURFACE + 1 + 15) / 16).map(|_| Chunk::new(0, 0xff)).collect(),
+            biomes: Some([biome; 256])
+        };
+        for bx in 0..16 {
+            for bz in 0..16 {
+                column.set_block(bx, 0, bz, BEDROCK << 4);
+                for y in 1..SURFACE - 3 {
+                    column.set_block(bx, y, bz, STONE << 4);
+                }
+                for y in SURFACE - 3..SURFACE {
+                    column.set_block(bx, y, bz, DIRT << 4);
+                }
+                column.set_block(bx, SURFACE, bz, GRASS << 4);
+            }
+        }
+        if self.features.caves {
+            carvers::caves(&self.random, &mut column, x, z, SURFACE as i32);
+        }
+        if self.features.ravines {
+            carvers::ravines(&self.random, &mut column, x, z, SURFACE as i32);
+        }
+        if self.features.ores {
+            place_ores(&mut column, &mut self.random.feature("ores", x, z));
+        }
+        if self.features.lakes {
+            place_lake(&mut column, &mut self.random.feature("lakes", x, z));
+        }
+        if self.features.trees {
+            let count = match biome {
+                FOREST => 6,
+                _ => 1
+            };
+            place_trees(&mut column, &mut self.random.feature("trees", x, z), count);
+        }
+        if self.features.structures {
+            let dungeons = Dungeons { surface: SURFACE as i32 };
+            let villages = Villages { surface: SURFACE as i32, spacing: VILLAGE_SPACING, biomes: &[PLAINS] };
+            let kinds: [&Structure; 2] = [&dungeons, &villages];
+            structures::place(&kinds, &self.random, &|x, z| self.biome(x, z), &mut column, x, z);
+        }
+        column
+    }
+
+    /// Netherrack between bedrock at the bottom and top of the Nether,
+    /// hollowed into caverns whose lower parts are a sea of lava. No
+    /// fortresses yet.
+    fn nether_column(&self, x: i32, z: i32) -> ChunkColumn {
+        let mut column = unlit_column(NETHER_HEIGHT, HELL);
+        // Cavern densities at the corners of the cells covering the chunk,
+        // drawn for each column of corners so neighbouring chunks agree
+        let corners: Vec<Vec<f64>> = (0..3).flat_map(|i| (0..3).map(move |j| (i, j))).map(|(i, j)| {
+            let mut rng = self.random.feature("caverns", x * 2 + i, z * 2 + j);
+            (0..NETHER_HEIGHT / CELL + 1).map(|_| rng.next_double()).collect()
+        }).collect();
+        for bx in 0..16 {
+            for bz in 0..16 {
+                column.set_block(bx, 0, bz, BEDROCK << 4);
+                column.set_block(bx, NETHER_HEIGHT - 1, bz, BEDROCK << 4);
+                for y in 1..NETHER_HEIGHT - 1 {
+                    let density = cavern_density(&corners, bx, y, bz);
+                    let block = if density < 0.5 {
+                        NETHERRACK
+                    } else if y <= LAVA_SEA {
+                        LAVA
+                    } else {
+                        continue
+                    };
+                    column.set_block(bx, y, bz, block << 4);
+                }
+            }
+        }
+        column
+    }
+
+    /// The End island: end stone, flat on top and thinning out towards its
+    /// edge, with nothing else around.
+    fn end_column(&self, x: i32, z: i32) -> ChunkColumn {
+        let top = END_ISLAND_TOP as usize;
+        let mut column = unlit_column(top + 1, SKY);
+        let mut island = false;
+        for bx in 0..16 {
+            for bz in 0..16 {
+                let (wx, wz) = ((x * 16 + bx as i32) as f64, (z * 16 + bz as i32) as f64);
+                let edge = 1.0 - (wx * wx + wz * wz).sqrt() / END_ISLAND_RADIUS;
+                if edge <= 0.0 {
+                    continue;
+                }
+                let bottom = (END_ISLAND_TOP - 4.0 - edge * 40.0) as usize;
+                for y in bottom..top + 1 {
+                    column.set_block(bx, y, bz, END_STONE << 4);
+                }
+                island = true;
+            }
+        }
+        if !island {
+            column.chunks.clear();
+        }
+        column
+    }
+}
+
+/// A column `height` blocks high of air, lit up like vanilla sends
+/// freshly generated chunks, without the sky light dimensions without a
+/// sky leave out.
+fn unlit_column(height: usize, biome: u8) -> ChunkColumn {
+    ChunkColumn {
+        chunks: (0..(height + 15) / 16).map(|_| Chunk { sky_light: None, ..Chunk::new(0, 0xff) }).collect(),
+        biomes: Some([biome; 256])
+    }
+}
+
+/// How solid the Nether is at block (`bx`, `y`, `bz`) of a chunk, from 0
+/// to 1, interpolating `corners` and leaving the floor and ceiling thicker.
+fn cavern_density(corners: &[Vec<f64>], bx: usize, y: usize, bz: usize) -> f64 {
+    let (i, j, k) = (bx / CELL, bz / CELL, y / CELL);
+    let (u, v, w) = ((bx % CELL) as f64 / CELL as f64, (bz % CELL) as f64 / CELL as f64, (y % CELL) as f64 / CELL as f64);
+    let corner = |di: usize, dj: usize, dk: usize| corners[(i + di) * 3 + j + dj][k + dk];
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+    let at = |dk: usize| lerp(lerp(corner(0, 0, dk), corner(1, 0, dk), u), lerp(corner(0, 1, dk), corner(1, 1, dk), u), v);
+    let noise = lerp(at(0), at(1), w);
+    // 0 in the middle of the Nether, 1 at its floor and ceiling
+    let edge = (y as f64 / (NETHER_HEIGHT - 1) as f64 * 2.0 - 1.0).powi(4);
+    (1.0 - noise) * (1.0 - edge) + edge
+}
+
+fn place_ores(column: &mut ChunkColumn, rng: &mut JavaRandom) {
+    for ore in &ORES {
+        for _ in 0..ore.veins {
+            let (mut x, mut y, mut z) = (rng.next_int_bounded(16), rng.next_int_bounded(ore.max_y), rng.next_int_bounded(16));
+            // A random walk, only ever replacing stone
+            for _ in 0..rng.next_int_bounded(ore.size) + 1 {
+                if x >= 0 && x < 16 && z >= 0 && z < 16 && y > 0 {
+                    let (bx, by, bz) = (x as usize, y as usize, z as usize);
+                    if column.block(bx, by, bz) >> 4 == STONE {
+                        column.set_block(bx, by, bz, ore.block << 4);
+                    }
+                }
+                match rng.next_int_bounded(6) {
+                    0 => x += 1,
+                    1 => x -= 1,
+                    2 => y += 1,
+                    3 => y -= 1,
+                    4 => z += 1,
+                    _ => z -= 1
+                }
+            }
+        }
+    }
+}
+
+/// Digs a small pond in one chunk out of 8.
+fn place_lake(column: &mut ChunkColumn, rng: &mut JavaRandom) {
+    if rng.next_int_bounded(8) != 0 {
+        return;
+    }
+    let (cx, cz) = (rng.next_int_bounded(6) + 5, rng.next_int_bounded(6) + 5);
+    let radius = rng.next_int_bounded(2) + 3;
+    let depth = rng.next_int_bounded(2) + 2;
+    for x in cx - radius..cx + radius + 1 {
+        for z in cz - radius..cz + radius + 1 {
+            let (dx, dz) = (x - cx, z - cz);
+            let distance = dx * dx + dz * dz;
+            if distance > radius * radius {
+                continue;
+            }
+            // Deepest in the middle
+            let down = if distance * 2 <= radius * radius { depth } else { 1 };
+            for y in SURFACE as i32 - down + 1..SURFACE as i32 + 1 {
+                column.set_block(x as usize, y as usize, z as usize, WATER << 4);
+            }
+        }
+    }
+}
+
+/// Grows up to `count` oak trees on grass, far enough from the chunk's
+/// edges that their leaves stay in it.
+fn place_trees(column: &mut ChunkColumn, rng: &mut JavaRandom, count: i32) {
+    let count = if count > 1 { count } else { rng.next_int_bounded(2) * count };
+    for _ in 0..count {
+        let (x, z) = (rng.next_int_bounded(12) as usize + 2, rng.next_int_bounded(12) as usize + 2);
+        let height = rng.next_int_bounded(3) as usize + 4;
+        let ground = SURFACE;
+        if column.block(x, ground, z) >> 4 != GRASS || column.block(x, ground + 1, z) >> 4 != 0 {
+            continue;
+        }
+        column.set_block(x, ground, z, DIRT << 4);
+        let top = ground + height;
+        // Two wide layers of leaves, then two narrow ones
+        for y in top - 2..top + 2 {
+            let radius: i32 = if y < top { 2 } else { 1 };
+            for dx in -radius..radius + 1 {
+                for dz in -radius..radius + 1 {
+                    // Corners of the wide layers are sometimes bare
+                    if dx.abs() == radius && dz.abs() == radius && (y >= top || rng.next_bool()) {
+                        continue;
+                    }
+                    let (lx, lz) = ((x as i32 + dx) as usize, (z as i32 + dz) as usize);
+                    if column.block(lx, y, lz) == 0 {
+                        column.set_block(lx, y, lz, LEAVES << 4);
+                    }
+                }
+            }
+        }
+        for y in ground + 1..top {
+            column.set_block(x, y, z, LOG << 4);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use types::consts::Dimension;
+    use world::random::WorldRandom;
+
+    fn count(column: &ChunkColumn, id: u16) -> usize {
+        column.chunks.iter().map(|chunk| chunk.blocks.iter().filter(|&&block| block >> 4 == id).count()).sum()
+    }
+
+    #[test]
+    fn settings() {
+        assert_eq!(Features::parse(""), Features::all());
+        assert_eq!(Features::parse("trees=false, lakes=false,foo=bar"), Features { lakes: false, trees: false, ..Features::all() });
+        assert_eq!(Features::parse("caves=false,ravines=false"), Features { caves: false, ravines: false, ..Features::all() });
+    }
+
+    #[test]
+    fn features() {
+        let generator = Generator::new(WorldRandom::new(42), Features::all());
+        let column = generator.column(3, -7);
+        // Same seed, same chunk
+        let again = generator.column(3, -7);
+        assert!(column.chunks.iter().zip(&again.chunks).all(|(a, b)| &a.blocks[..] == &b.blocks[..]));
+        assert_eq!(column.block(5, 0, 5) >> 4, BEDROCK);
+        let ores: usize = ORES.iter().map(|ore| count(&column, ore.block)).sum();
+        assert!(ores > 0);
+
+        // Forests always get trees
+        let forest = (0..64).map(|i| (i, i * 3)).find(|&(x, z)| generator.biome(x, z) == FOREST).unwrap();
+        assert!(count(&generator.column(forest.0, forest.1), LOG) > 0);
+
+        let bare = Generator::new(WorldRandom::new(42), Features::none());
+        let column = bare.column(forest.0, forest.1);
+        assert_eq!((count(&column, LOG), count(&column, WATER), count(&column, 16)), (0, 0, 0));
+        assert_eq!(count(&column, GRASS), 256);
+    }
+
+    #[test]
+    fn nether() {
+        let generator = Generator::with_dimension(Dimension::Nether, WorldRandom::new(42), Features::all());
+        let columns: Vec<_> = (0..4).map(|x| generator.column(x, -x)).collect();
+        for column in &columns {
+            assert_eq!((column.block(3, 0, 3) >> 4, column.block(3, NETHER_HEIGHT - 1, 3) >> 4), (BEDROCK, BEDROCK));
+            assert!(column.chunks.iter().all(|chunk| chunk.sky_light.is_none()));
+            assert_eq!(column.biomes.unwrap()[0], HELL);
+        }
+        let total = |id| columns.iter().map(|column| count(column, id)).sum::<usize>();
+        assert!(total(NETHERRACK) > 0 && total(LAVA) > 0 && total(0) > 0);
+        // No overworld features, and lava only fills the sea
+        assert_eq!((total(GRASS), total(LOG), total(16)), (0, 0, 0));
+        for column in &columns {
+            for y in LAVA_SEA + 1..NETHER_HEIGHT {
+                assert!(column.block(8, y, 8) >> 4 != LAVA);
+            }
+        }
+
+        // Caverns carry on across chunk edges
+        let (west, east) = (generator.column(0, 0), generator.column(1, 0));
+        let matching = (1..NETHER_HEIGHT - 1).filter(|&y| (west.block(15, y, 4) == 0) == (east.block(0, y, 4) == 0)).count();
+        assert!(matching > NETHER_HEIGHT * 3 / 4);
+    }
+
+    #[test]
+    fn end() {
+        let generator = Generator::with_dimension(Dimension::End, WorldRandom::new(42), Features::all());
+        let center = generator.column(0, 0);
+        assert_eq!(center.block(0, END_ISLAND_TOP as usize, 0) >> 4, END_STONE);
+        assert_eq!(center.block(0, END_ISLAND_TOP as usize + 1, 0), 0);
+        assert_eq!(center.block(0, 0, 0), 0);
+        assert!(center.chunks.iter().all(|chunk| chunk.sky_light.is_none()));
+        assert_eq!(count(&generator.column(20, 0), END_STONE), 0);
+    }
+}
@@ -0,0 +1,86 @@
+//! Keep-alives, which clients answer to show they're still there.
+
+use std::time::{Duration, Instant};
+
+/// What a connection's keep-alive wants done.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Poll {
+    Wait,
+    /// A keep-alive with this id should be sent.
+    Send(i32),
+    /// The last keep-alive went unanswered for too long, the client is
+    /// gone.
+    TimedOut
+}
+
+/// Keep-alives sent to one connection.
+pub struct KeepAlive {
+    interval: Duration,
+    timeout: Duration,
+    last_sent: Option<Instant>,
+    /// Id of the keep-alive awaiting an answer, and when it was sent.
+    pending: Option<(i32, Instant)>,
+    /// How long the client took to answer the last keep-alive.
+    latency: Option<Duration>
+}
+
+impl KeepAlive {
+    /// Sends a keep-alive every `interval`, the client having `timeout` to
+    /// answer each.
+    pub fn new(interval: Duration, timeout: Duration) -> KeepAlive {
+        KeepAlive { interval: interval, timeout: timeout, last_sent: None, pending: None, latency: None }
+    }
+
+    /// What to do at `now`, `id` being the id of the keep-alive to send
+    /// should one be due. The keep-alive is taken as sent.
+    pub fn poll(&mut self, now: Instant, id: i32) -> Poll {
+        if let Some((_, sent)) = self.pending {
+            return if now.duration_since(sent) >= self.timeout { Poll::TimedOut } else { Poll::Wait };
+        }
+        if self.last_sent.map_or(false, |sent| now.duration_since(sent) < self.interval) {
+            return Poll::Wait;
+        }
+        self.last_sent = Some(now);
+        self.pending = Some((id, now));
+        Poll::Send(id)
+    }
+
+    /// Handles the client answering keep-alive `id` at `now`. Returns
+    /// whether it was the one awaited, others are stale or made up.
+    pub fn answer(&mut self, id: i32, now: Instant) -> bool {
+        match self.pending {
+            Some((pending, sent)) if pending == id => {
+                self.latency = Some(now.duration_since(sent));
+                self.pending = None;
+                true
+            }
+            _ => false
+        }
+    }
+
+    pub fn latency(&self) -> Option<Duration> { self.latency }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn answered_and_timed_out() {
+        let mut keep_alive = KeepAlive::new(Duration::from_secs(15), Duration::from_secs(30));
+        let start = Instant::now();
+        assert_eq!(keep_alive.poll(start, 1), Poll::Send(1));
+        assert_eq!(keep_alive.poll(start + Duration::from_secs(5), 2), Poll::Wait);
+        assert!(!keep_alive.answer(2, start + Duration::from_secs(10)));
+        assert!(keep_alive.answer(1, start + Duration::from_secs(10)));
+        assert_eq!(keep_alive.latency(), Some(Duration::from_secs(10)));
+        assert!(!keep_alive.answer(1, start + Duration::from_secs(11)));
+        // Not due until an interval after the last one was sent
+        assert_eq!(keep_alive.poll(start + Duration::from_secs(14), 3), Poll::Wait);
+        assert_eq!(keep_alive.poll(start + Duration::from_secs(15), 3), Poll::Send(3));
+        assert_eq!(keep_alive.poll(start + Duration::from_secs(44), 4), Poll::Wait);
+        assert_eq!(keep_alive.poll(start + Duration::from_secs(45), 4), Poll::TimedOut);
+    }
+}
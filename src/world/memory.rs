@@ -0,0 +1,61 @@
+//! Memory used by the server process, as the operating system reports it.
+
+use std::fs::File;
+use std::io::Read;
+
+/// Memory used by the process, in bytes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MemoryUsage {
+    /// Memory actually in RAM.
+    pub resident: u64,
+    /// Address space reserved, whether in use or not.
+    pub reserved: u64
+}
+
+/// Memory used by the process, `None` where the system doesn't tell, which
+/// is anywhere but Linux for now.
+pub fn usage() -> Option<MemoryUsage> {
+    let mut status = String::new();
+    match File::open("/proc/self/status").and_then(|mut file| file.read_to_string(&mut status)) {
+        Ok(_) => parse_status(&status),
+        Err(_) => None
+    }
+}
+
+/// Reads the memory usage from the content of `/proc/self/status`.
+fn parse_status(status: &str) -> Option<MemoryUsage> {
+    let field = |name: &str| status.lines()
+        .find(|line| line.starts_with(name))
+        .and_then(|line| line[name.len()..].trim().trim_right_matches("kB").trim().parse::<u64>().ok())
+        .map(|kib| kib * 1024);
+    match (field("VmRSS:"), field("VmSize:")) {
+        (Some(resident), Some(reserved)) => Some(MemoryUsage { resident: resident, reserved: reserved }),
+        _ => None
+    }
+}
+
+/// `bytes` in the largest unit it makes at least 1 of, e.g. "1.5 MiB".
+pub fn format_bytes(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < units.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} B", bytes) } else { format!("{:.1} {}", value, units[unit]) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status() {
+        let status = "Name:\thematite\nVmPeak:\t  300000 kB\nVmSize:\t  262144 kB\nVmRSS:\t   51200 kB\n";
+        assert_eq!(parse_status(status), Some(MemoryUsage { resident: 50 * 1024 * 1024, reserved: 256 * 1024 * 1024 }));
+        assert_eq!(parse_status("Name:\thematite\n"), None);
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536 * 1024), "1.5 MiB");
+    }
+}
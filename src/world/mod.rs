@@ -0,0 +1,3848 @@
+//! Worlds (a group of dimensions).
+//!
+//! This module is a WORK IN PROGRESS.
+
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::mem;
+use std::net::Shutdown;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use block;
+use block_entity::{self, brewing_stand, furnace, BlockEntity};
+use crypto::SymmStream;
+use damage::{Attacker, DamageSource, DeathMessage};
+use entity::{self, horse, navigation, segment_hits, Action, ArmorStand, Brain, Entity, EntityKind, Horse, ItemFrame, Monster, MonsterKind, Target};
+use events::{Event, EventBus, FilterAction};
+use item;
+use mca::{self, McaFile, RawChunk};
+use message::{self, Announcement};
+use packet::{self, read_frame, AnyPacket, BlockChangeRecord, PacketRead, PacketWrite, Protocol, Registry};
+use packet::play::serverbound::{ClickWindow, Packet};
+use permissions::{self, OpLevels, PermissionProvider};
+use player::{self, Player, PlayerHandle, SpawnPoint};
+use potion;
+use proto::mutes::MuteList;
+use proto::properties::Properties;
+use types::consts::*;
+use types::{ChatJson, ChunkColumn, CombatEvent, EntityUseAction, PlayerListAction, Slot, Var};
+use window::{self, anvil, Anvil, Window, WindowKind};
+
+use nbt;
+use num::FromPrimitive;
+use rand::{self, Rng};
+use time;
+use uuid::Uuid;
+
+pub use self::block_changes::{Batch, BlockChanges};
+pub use self::border::WorldBorder;
+pub use self::command::{Command, SpawnPointChange, Whisper};
+pub use self::config::{Storage, WorldConfig};
+pub use self::explosion::Explosion;
+pub use self::generator::{Features, Generator};
+pub use self::packet_log::PacketLog;
+pub use self::player_data::PlayerData;
+pub use self::profiler::{PhaseTimer, Profiler, Report, TickTimings};
+pub use self::random::{JavaRandom, WorldRandom};
+pub use self::rate_limit::{Limit, PacketGroup, RateLimiter, Verdict};
+pub use self::teams::{NameTagVisibility, Team, Teams};
+pub use self::tickets::{ChunkTickets, Ticket};
+pub use self::tick_stats::TickStats;
+pub use self::weather::Weather;
+use self::chunk_cache::{ChunkCache, EncodedColumn};
+use self::chunk_stream::ChunkView;
+
+pub mod backup;
+mod block_changes;
+pub mod carvers;
+pub mod chunk_cache;
+pub mod chunk_stream;
+pub mod command;
+mod border;
+mod config;
+mod explosion;
+pub mod generator;
+pub mod keep_alive;
+pub mod memory;
+mod packet_log;
+mod player_data;
+pub mod plugin_message;
+mod profiler;
+pub mod random;
+pub mod random_ticks;
+mod rate_limit;
+pub mod safe_spawn;
+pub mod spawning;
+pub mod structures;
+mod teams;
+pub mod tickets;
+mod tick_stats;
+pub mod weather;
+
+// Temporal, only used within the BLOCK OF SHAME
+const PACKET_NAMES: [&'static str; 26] = [
+    "(c2s) KeepAlive",
+    "(c2s) ChatMessage",
+    "(c2s) UseEntity",
+    "(c2s) Player",
+    "(c2s) PlayerPosition",
+    "(c2s) PlayerLook",
+    "(c2s) PlayerPositionAndLook",
+    "(c2s) PlayerDigging",
+    "(c2s) PlayerBlockPlacement",
+    "(c2s) HeldItemChange",
+    "(c2s) Animation",
+    "(c2s) EntityAction",
+    "(c2s) SteerVehicle",
+    "(c2s) CloseWindow",
+    "(c2s) ClickWindow",
+    "(c2s) ConfirmTransaction",
+    "(c2s) CreativeInventoryAction",
+    "(c2s) EnchantItem",
+    "(c2s) UpdateSign",
+    "(c2s) PlayerAbilities",
+    "(c2s) TabComplete",
+    "(c2s) ClientSettings",
+    "(c2s) ClientStatus",
+    "(c2s) PluginMessage",
+    "(c2s) Spectate",
+    "(c2s) ResourcePackStatus"
+];
+
+/// Name of serverbound packet `id`, for the log.
+fn packet_name(id: i32) -> &'static str {
+    PACKET_NAMES.get(id as usize).cloned().unwrap_or("(c2s) Unknown")
+}
+
+/// Chunk holding `position`.
+fn chunk_of(position: [f64; 3]) -> (i32, i32) {
+    ((position[0].floor() as i32) >> 4, (position[2].floor() as i32) >> 4)
+}
+
+/// Duration of property value `minutes`, `None` for 0 or less.
+fn minutes(minutes: i32) -> Option<Duration> {
+    if minutes > 0 { Some(Duration::from_secs(minutes as u64 * 60)) } else { None }
+}
+
+/// Packet showing the text of the sign at `location`.
+fn sign_update(location: [i32; 3], lines: &[String; block_entity::sign::LINES]) -> packet::play::clientbound::UpdateSign {
+    packet::play::clientbound::UpdateSign {
+        location: location,
+        line0: ChatJson::from(&lines[0][..]),
+        line1: ChatJson::from(&lines[1][..]),
+        line2: ChatJson::from(&lines[2][..]),
+        line3: ChatJson::from(&lines[3][..])
+    }
+}
+
+/// Sends `player` the chunk `columns`, then has them drop the `stale`
+/// chunks, keeping their chunk view up to date.
+fn send_chunks(player: &mut Player, columns: Vec<((i32, i32), EncodedColumn)>, stale: Vec<(i32, i32)>) -> io::Result<()> {
+    for (chunk, column) in columns {
+        try!(player.send(&chunk_cache::chunk_data(chunk, &column)));
+        if let Some(view) = player.chunk_view_mut() {
+            view.mark_sent(chunk);
+        }
+    }
+    for chunk in stale {
+        try!(player.send(&chunk_stream::unload_packet(chunk)));
+    }
+    Ok(())
+}
+
+/// Most chunk columns sent in one ChunkDataBulk packet.
+const CHUNKS_PER_PACKET: usize = 10;
+
+/// Seconds between unloading the chunks nobody needs.
+const UNLOAD_INTERVAL: u64 = 10;
+
+/// Entity ids are unique across worlds, so players keep theirs when moving
+/// from one world to another.
+static NEXT_ENTITY_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// How a player left a world.
+pub enum Departure {
+    /// The connection dropped.
+    Disconnected,
+    /// The player is moving to world `world`, bringing their connection
+    /// along.
+    Transfer { world: usize, stream: SymmStream, player: Arc<Mutex<Player>> }
+}
+
+/// A world as the server, its tick thread and connections share it.
+pub type WorldHandle = Arc<World>;
+
+/// World is a set of dimensions which tick in sync.
+///
+/// Lock order: a player's lock may be taken before any of the world's own
+/// locks, never while holding one of them. Packets are handled with the
+/// sending player locked, so anything everyone needs to know about is queued
+/// and broadcast on the next tick instead.
+///
+/// Everything public takes `&self` and may be called from any thread, so
+/// embedders holding a `WorldHandle` can drive the game like commands do:
+/// `set_time`, `set_weather`, `set_block`, `spawn_entity` and
+/// `queue_broadcast` all leave telling players to the next tick. Only
+/// `broadcast` sends right away, and must not be called while holding a
+/// player's lock.
+pub struct World {
+    start: time::Timespec,
+    /// World age in ticks when the world was loaded.
+    start_age: i64,
+    /// Ticks added to the world age to get the time of day, moved on when
+    /// players sleep through the night.
+    time_offset: Mutex<i64>,
+    weather: Mutex<Weather>,
+    gamemode: GameMode,
+    difficulty: Difficulty,
+    hardcore: bool,
+    dimension: Dimension,
+    /// Shown to joining players, as set by max-players.
+    max_players: u8,
+    resource_pack: Option<(String, String)>,
+    /// Sent to clients on MC|Brand.
+    brand: String,
+    /// Chat and movement packets each connection may send.
+    rate_limits: (Limit, Limit),
+    /// How often players are sent keep-alives, and how long they have to
+    /// answer before being disconnected.
+    keep_alive: (Duration, Duration),
+    /// How long players may stay idle before they're away from keyboard,
+    /// as set by afk-timeout, `None` for ever.
+    afk_timeout: Option<Duration>,
+    /// Tab list name of players away from keyboard, `%s` standing for
+    /// theirs, `None` to leave it alone.
+    afk_list_name: Option<String>,
+    /// How long players may stay idle before they're kicked, as set by
+    /// player-idle-timeout, `None` for ever.
+    idle_timeout: Option<Duration>,
+    /// Whether packets with ids unknown here are skipped, rather than
+    /// dropping the connection.
+    skip_unknown_packets: bool,
+    events: Arc<EventBus>,
+    /// Shared by all worlds.
+    mutes: Arc<MuteList>,
+    teams: RwLock<Teams>,
+    permissions: RwLock<Arc<PermissionProvider + Send + Sync>>,
+    /// Serverbound packets added by extensions, emitted as events.
+    custom_packets: RwLock<Registry>,
+    /// Radius around the spawn only players with
+    /// `permissions::SPAWN_PROTECTION_BYPASS` may dig in, 0 to protect
+    /// nothing.
+    spawn_protection: i32,
+    chunks: RwLock<HashMap<(i32, i32), ChunkColumn>>,
+    /// Columns of `chunks` encoded for clients, taken after `chunks`.
+    chunk_cache: Mutex<ChunkCache>,
+    /// Makes the chunks the world directory doesn't have.
+    generator: Generator,
+    /// What keeps chunks loaded besides players seeing them.
+    tickets: Mutex<ChunkTickets>,
+    /// Chunks of `chunks` generated or changed since they were last saved,
+    /// taken after `chunks`.
+    unsaved: Mutex<HashSet<(i32, i32)>>,
+    /// Held while writing region files, so saves don't overlap.
+    saving: Mutex<()>,
+    /// When chunks were last unloaded.
+    last_unload: Mutex<Instant>,
+    /// Radius in chunks of the area players are sent around them.
+    view_distance: i32,
+    /// Radius in chunks around players in which entities and block
+    /// entities tick. Chunks kept loaded by a ticket always tick.
+    simulation_distance: i32,
+    /// Whether monsters spawn on their own, as set by spawn-monsters.
+    spawn_monsters: bool,
+    /// How players joining and leaving are announced.
+    join_message: Announcement,
+    quit_message: Announcement,
+    entities: Mutex<HashMap<i32, Entity>>,
+    /// Taken before `chunks` when both are needed.
+    block_entities: Mutex<HashMap<[i32; 3], BlockEntity>>,
+    border: RwLock<WorldBorder>,
+    config: RwLock<WorldConfig>,
+    /// Where `config` is saved, `None` for worlds kept in memory.
+    level_dat: Option<PathBuf>,
+    /// Where backups go, and how many are kept there, 0 for all of them.
+    backup_dir: PathBuf,
+    backups_kept: usize,
+    /// Held while backing up, so backups don't overlap.
+    backing_up: Mutex<()>,
+    players: Mutex<HashMap<Uuid, Arc<Mutex<Player>>>>,
+    /// Uuid and name of each player, by lowercase name, so players can be
+    /// found without locking them.
+    names: Mutex<HashMap<String, (Uuid, String)>>,
+    /// Entities spawned since the last tick.
+    spawned: Mutex<Vec<i32>>,
+    /// Blocks changed since the last tick.
+    changed_blocks: Mutex<BlockChanges>,
+    /// Cracks shown on blocks being dug, by entity id of the digger.
+    cracks: Mutex<HashMap<i32, ([i32; 3], i8)>>,
+    /// Packets to broadcast on the next tick.
+    queued: Mutex<Vec<Box<PacketWrite + Send>>>,
+    /// Chat messages to broadcast on the next tick, to players whose chat
+    /// mode shows them.
+    queued_chat: Mutex<Vec<(ChatJson, ChatPosition)>>,
+    /// Private messages to deliver on the next tick.
+    whispers: Mutex<Vec<Whisper>>,
+    /// Spawn points set for other players, to apply on the next tick.
+    spawn_points: Mutex<Vec<SpawnPointChange>>,
+    stats: Mutex<TickStats>,
+    profiler: Mutex<Profiler>,
+    packet_log: Mutex<PacketLog>,
+    /// Players asked to move to another world, by index into the server's
+    /// worlds. They leave on their next packet.
+    transfers: Mutex<HashMap<Uuid, usize>>
+}
+
+/// Sets `chunks` in region file `path`, made if it isn't there.
+fn write_region(path: &Path, chunks: Vec<((i32, i32), RawChunk)>) -> io::Result<()> {
+    let mut data = vec![];
+    match File::open(path) {
+        Ok(mut file) => try!(file.read_to_end(&mut data)),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => 0,
+        Err(err) => return Err(err)
+    };
+    // Chunks which can't be read are dropped, they were generated again
+    // when loaded
+    let mut file = match mca::check(&data) {
+        Ok((file, _)) => file,
+        Err(err) => {
+            warn!("rewriting {}, it was unreadable: {}", path.display(), err);
+            McaFile::new()
+        }
+    };
+    for (chunk, encoded) in chunks {
+        file.set_chunk(chunk.0, chunk.1, Some(encoded));
+    }
+    if let Some(dir) = path.parent() {
+        try!(fs::create_dir_all(dir));
+    }
+    let tmp = path.with_extension("mca.tmp");
+    try!(file.write(&mut try!(File::create(&tmp))));
+    fs::rename(&tmp, path)
+}
+
+/// Directory of the region files of `dimension`, within its world's
+/// directory, laid out like Bukkit does.
+fn region_dir(dimension: Dimension) -> &'static str {
+    match dimension {
+        Dimension::Overworld => "region",
+        Dimension::Nether => "DIM-1/region",
+        Dimension::End => "DIM1/region"
+    }
+}
+
+impl World {
+    /// The Overworld, see `with_dimension`.
+    pub fn new(props: &Properties, events: Arc<EventBus>, mutes: Arc<MuteList>) -> io::Result<World> {
+        World::with_dimension(props, Dimension::Overworld, events, mutes)
+    }
+
+    /// A world of `dimension`, kept in a directory named after the level,
+    /// with `_nether` or `_the_end` appended for the other dimensions.
+    ///
+    /// Fails when the world's level.dat is from a version hematite can't
+    /// use, rather than risk damaging it.
+    pub fn with_dimension(props: &Properties, dimension: Dimension, events: Arc<EventBus>, mutes: Arc<MuteList>) -> io::Result<World> {
+        let level_name = match dimension {
+            Dimension::Overworld => props.level_name.clone(),
+            Dimension::Nether => format!("{}_nether", props.level_name),
+            Dimension::End => format!("{}_the_end", props.level_name)
+        };
+        let level_dat = match Storage::of(props) {
+            Storage::Disk => Some(Path::new(&level_name).join("level.dat")),
+            Storage::Memory => None
+        };
+        let mut defaults = WorldConfig::new(props);
+        defaults.level_name = level_name;
+        let config = match level_dat {
+            Some(ref level_dat) => {
+                let config = match WorldConfig::load(level_dat, &defaults) {
+                    Ok(config) => config,
+                    Err(ref err) if err.kind() == io::ErrorKind::NotFound => defaults,
+                    Err(err) => {
+                        warn!("failed reading {}, using defaults: {}", level_dat.display(), err);
+                        defaults
+                    }
+                };
+                try!(config.check_version().map_err(|err| {
+                    io::Error::new(err.kind(), format!("can't load {}: {}", level_dat.display(), err))
+                }));
+                config
+            }
+            None => defaults
+        };
+        if let Some(ref version) = config.hematite_version {
+            if version != env!("CARGO_PKG_VERSION") {
+                info!("{} was last saved by hematite {}", config.level_name, version);
+            }
+        }
+        let mut border = WorldBorder::new();
+        World::configure_border(&mut border, &config);
+        let spawn_chunk = (config.spawn[0] >> 4, config.spawn[2] >> 4);
+        // Superflat worlds aren't carved
+        let features = match &config.generator[..] {
+            "flat" => Features { caves: false, ravines: false, ..Features::parse(&config.generator_options) },
+            _ => Features::parse(&config.generator_options)
+        };
+        let world = World {
+            start: time::get_time(),
+            start_age: config.time,
+            time_offset: Mutex::new(config.day_time - config.time),
+            weather: Mutex::new(Weather::new()),
+            gamemode: config.game_type,
+            difficulty: Difficulty::from_i32(props.difficulty).unwrap_or(Difficulty::Easy),
+            hardcore: props.hardcore,
+            dimension: dimension,
+            max_players: props.max_players.max(0).min(255) as u8,
+            resource_pack: if props.resource_pack.is_empty() {
+                None
+            } else {
+                Some((props.resource_pack.clone(), props.resource_pack_hash.clone()))
+            },
+            brand: plugin_message::server_brand(&props.server_brand),
+            rate_limits: (Limit::new(props.rate_limit_chat, Duration::from_secs(10)),
+                          Limit::new(props.rate_limit_movement, Duration::from_secs(1))),
+            keep_alive: (Duration::from_secs(props.keep_alive_interval.max(1) as u64),
+                         Duration::from_secs(props.keep_alive_timeout.max(1) as u64)),
+            afk_timeout: minutes(props.afk_timeout),
+            afk_list_name: match props.afk_list_name.trim() {
+                "" => None,
+                format => Some(format.to_string())
+            },
+            idle_timeout: minutes(props.player_idle_timeout),
+            skip_unknown_packets: props.skip_unknown_packets,
+            events: events,
+            mutes: mutes,
+            teams: RwLock::new(Teams::new()),
+            permissions: RwLock::new(Arc::new(OpLevels::vanilla(true))),
+            custom_packets: RwLock::new(Registry::new()),
+            spawn_protection: props.spawn_protection,
+            chunks: RwLock::new(HashMap::new()),
+            chunk_cache: Mutex::new(ChunkCache::new()),
+            generator: Generator::with_dimension(dimension, WorldRandom::new(config.seed), Features {
+                structures: features.structures && config.map_features,
+                ..features
+            }),
+            tickets: Mutex::new(ChunkTickets::new()),
+            unsaved: Mutex::new(HashSet::new()),
+            saving: Mutex::new(()),
+            last_unload: Mutex::new(Instant::now()),
+            view_distance: props.view_distance.max(1),
+            simulation_distance: props.simulation_distance.max(1),
+            spawn_monsters: props.spawn_monsters,
+            join_message: Announcement::parse(&props.join_message, message::JOINED),
+            quit_message: Announcement::parse(&props.quit_message, message::LEFT),
+            entities: Mutex::new(HashMap::new()),
+            block_entities: Mutex::new(HashMap::new()),
+            border: RwLock::new(border),
+            config: RwLock::new(config),
+            level_dat: level_dat,
+            backup_dir: PathBuf::from(&props.backup_directory),
+            backups_kept: props.backups_kept.max(0) as usize,
+            backing_up: Mutex::new(()),
+            players: Mutex::new(HashMap::new()),
+            names: Mutex::new(HashMap::new()),
+            spawned: Mutex::new(vec![]),
+            changed_blocks: Mutex::new(BlockChanges::new()),
+            cracks: Mutex::new(HashMap::new()),
+            queued: Mutex::new(vec![]),
+            queued_chat: Mutex::new(vec![]),
+            whispers: Mutex::new(vec![]),
+            spawn_points: Mutex::new(vec![]),
+            stats: Mutex::new(TickStats::new()),
+            profiler: Mutex::new(Profiler::new()),
+            packet_log: Mutex::new(PacketLog::new()),
+            transfers: Mutex::new(HashMap::new())
+        };
+        world.add_chunk_ticket(Ticket::new(tickets::SPAWN, spawn_chunk, tickets::SPAWN_RADIUS));
+        Ok(world)
+    }
+
+    /// Keeps the chunks `ticket` covers loaded until it's removed, loading
+    /// those which aren't yet.
+    pub fn add_chunk_ticket(&self, ticket: Ticket) {
+        let chunks = ticket.chunks();
+        self.tickets.lock().unwrap().add(ticket);
+        self.load_chunks(chunks);
+    }
+
+    /// Loads those of `chunks` which aren't yet, from the world's region
+    /// files or else generating them.
+    fn load_chunks(&self, chunks: Vec<(i32, i32)>) {
+        let missing: Vec<_> = {
+            let loaded = self.chunks.read().unwrap();
+            chunks.into_iter().filter(|chunk| !loaded.contains_key(chunk)).collect()
+        };
+        // Read and generated with no lock held, another ticket may load the
+        // same chunks meanwhile
+        let mut read = self.read_columns(&missing);
+        let columns: Vec<_> = missing.into_iter().map(|chunk| {
+            match read.remove(&chunk) {
+                Some(column) => (chunk, column, false),
+                None => (chunk, self.generator.column(chunk.0, chunk.1), true)
+            }
+        }).collect();
+        let mut loaded = self.chunks.write().unwrap();
+        let mut unsaved = self.unsaved.lock().unwrap();
+        for (chunk, column, generated) in columns {
+            if !loaded.contains_key(&chunk) {
+                loaded.insert(chunk, column);
+                if generated {
+                    unsaved.insert(chunk);
+                }
+            }
+        }
+    }
+
+    /// Directory of the world's region files, `None` for worlds kept in
+    /// memory.
+    fn region_path(&self) -> Option<PathBuf> {
+        self.level_dat.as_ref().map(|level_dat| level_dat.parent().unwrap_or(Path::new(".")).join(region_dir(self.dimension)))
+    }
+
+    /// Reads `chunks` from the world's region files, each file once. Chunks
+    /// which aren't there or can't be read are left out.
+    fn read_columns(&self, chunks: &[(i32, i32)]) -> HashMap<(i32, i32), ChunkColumn> {
+        let mut columns = HashMap::new();
+        let dir = match self.region_path() {
+            Some(dir) => dir,
+            None => return columns
+        };
+        let mut regions: HashMap<(i32, i32), Vec<(i32, i32)>> = HashMap::new();
+        for &(x, z) in chunks {
+            regions.entry((x >> 5, z >> 5)).or_insert_with(Vec::new).push((x, z));
+        }
+        for ((region_x, region_z), chunks) in regions {
+            let path = dir.join(format!("r.{}.{}.mca", region_x, region_z));
+            let mut data = vec![];
+            match File::open(&path).and_then(|mut file| file.read_to_end(&mut data)) {
+                Ok(_) => {}
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => {
+                    warn!("failed reading {}, generating its chunks: {}", path.display(), err);
+                    continue;
+                }
+            }
+            // Chunks which can't be read are generated again, the others
+            // kept
+            let file = match mca::check(&data) {
+                Ok((file, report)) => {
+                    let lost: Vec<_> = report.lost.into_iter()
+                        .map(|(x, z)| (region_x << 5 | x, region_z << 5 | z))
+                        .filter(|chunk| chunks.contains(chunk))
+                        .collect();
+                    if !lost.is_empty() {
+                        warn!("chunks {:?} of {} are damaged, generating them", lost, path.display());
+                    }
+                    file
+                }
+                Err(err) => {
+                    warn!("failed reading {}, generating its chunks: {}", path.display(), err);
+                    continue;
+                }
+            };
+            for (x, z) in chunks {
+                match file.chunk(x, z).map(|chunk| chunk.column()) {
+                    Some(Ok(mut column)) => {
+                        // Clients of dimensions without a sky don't expect
+                        // sky light, even if it was saved
+                        if self.dimension != Dimension::Overworld {
+                            for chunk in &mut column.chunks {
+                                chunk.sky_light = None;
+                            }
+                        }
+                        columns.insert((x, z), column);
+                    }
+                    Some(Err(err)) => warn!("failed reading chunk {}, {} of {}: {}", x, z, path.display(), err),
+                    None => {}
+                }
+            }
+        }
+        columns
+    }
+
+    /// Removes the tickets of `source` centered on chunk `center`. Returns
+    /// how many there were.
+    pub fn remove_chunk_ticket(&self, source: &str, center: (i32, i32)) -> usize {
+        self.tickets.lock().unwrap().remove(source, center)
+    }
+
+    /// Removes every ticket of `source`. Returns how many there were.
+    pub fn remove_chunk_tickets(&self, source: &str) -> usize {
+        self.tickets.lock().unwrap().remove_source(source)
+    }
+
+    pub fn chunk_tickets(&self) -> Vec<Ticket> {
+        self.tickets.lock().unwrap().tickets().to_vec()
+    }
+
+    /// Chunks where things happen this tick: those within simulation
+    /// distance of a player at one of `positions`, and those a ticket keeps
+    /// loaded.
+    fn simulated_chunks<I: Iterator<Item = [f64; 3]>>(&self, positions: I) -> HashSet<(i32, i32)> {
+        let mut chunks: HashSet<_> = self.tickets.lock().unwrap().forced_chunks().into_iter().collect();
+        for position in positions {
+            chunks.extend(tickets::chunks_within(chunk_of(position), self.simulation_distance));
+        }
+        chunks
+    }
+
+    /// Whether a ticket keeps chunk `chunk` loaded.
+    pub fn is_chunk_forced(&self, chunk: (i32, i32)) -> bool {
+        self.tickets.lock().unwrap().is_forced(chunk)
+    }
+
+    /// Block at `pos` as `id << 4 | metadata`, air if the chunk isn't loaded.
+    pub fn block(&self, pos: [i32; 3]) -> u16 {
+        if pos[1] < 0 || pos[1] > 255 {
+            return 0;
+        }
+        match self.chunks.read().unwrap().get(&(pos[0] >> 4, pos[2] >> 4)) {
+            Some(column) => column.block((pos[0] & 0xf) as usize, pos[1] as usize, (pos[2] & 0xf) as usize),
+            None => 0
+        }
+    }
+
+    /// Sets the block at `pos`, does nothing if the chunk isn't loaded.
+    /// Players are told about the change on the next tick.
+    pub fn set_block(&self, pos: [i32; 3], block: u16) {
+        if self.store_block(pos, block) {
+            self.changed_blocks.lock().unwrap().add(pos);
+        }
+    }
+
+    /// Packet telling players about the block changes of `batch`, `None`
+    /// if their chunk was unloaded since.
+    fn block_change_packet(&self, batch: Batch) -> Option<Box<PacketWrite>> {
+        use packet::play::clientbound::{BlockChange, MultiBlockChange};
+
+        match batch {
+            Batch::Single(location) => {
+                Some(Box::new(BlockChange { location: location, block_id: self.block(location) as i32 }))
+            }
+            Batch::Multi { chunk, locations } => {
+                let records = locations.into_iter().map(|location| BlockChangeRecord {
+                    xz: ((location[0] & 0xf) << 4 | (location[2] & 0xf)) as u8,
+                    y: location[1] as u8,
+                    block_id: self.block(location) as i32
+                }).collect();
+                Some(Box::new(MultiBlockChange { chunk_x: chunk.0, chunk_z: chunk.1, records: records }))
+            }
+            Batch::Whole(chunk) => {
+                let chunks = self.chunks.read().unwrap();
+                self.encoded_column(&chunks, chunk).map(|column| Box::new(chunk_cache::chunk_data(chunk, &column)) as Box<PacketWrite>)
+            }
+        }
+    }
+
+    /// Column `chunk` of `chunks`, the world's own, encoded for clients
+    /// once for as long as its blocks don't change. `None` if it isn't
+    /// loaded or can't be encoded.
+    fn encoded_column(&self, chunks: &HashMap<(i32, i32), ChunkColumn>, chunk: (i32, i32)) -> Option<EncodedColumn> {
+        let column = match chunks.get(&chunk) {
+            Some(column) => column,
+            None => return None
+        };
+        if let Some(encoded) = self.chunk_cache.lock().unwrap().get(chunk) {
+            return Some(encoded);
+        }
+        // Encoded without the cache locked, nobody can change the column
+        // while `chunks` is
+        match EncodedColumn::new(column) {
+            Ok(encoded) => {
+                self.chunk_cache.lock().unwrap().insert(chunk, encoded.clone());
+                Some(encoded)
+            }
+            Err(err) => {
+                warn!("failed encoding chunk {:?}: {}", chunk, err);
+                None
+            }
+        }
+    }
+
+    /// Sets the block at `pos` without telling players, returns whether the
+    /// chunk was loaded.
+    fn store_block(&self, pos: [i32; 3], block: u16) -> bool {
+        if pos[1] < 0 || pos[1] > 255 {
+            return false;
+        }
+        let chunk = (pos[0] >> 4, pos[2] >> 4);
+        match self.chunks.write().unwrap().get_mut(&chunk) {
+            Some(column) => {
+                column.set_block((pos[0] & 0xf) as usize, pos[1] as usize, (pos[2] & 0xf) as usize, block);
+                self.chunk_cache.lock().unwrap().invalidate(chunk);
+                self.unsaved.lock().unwrap().insert(chunk);
+                true
+            }
+            None => false
+        }
+    }
+
+    /// Allocates a new entity id.
+    pub fn next_entity_id(&self) -> i32 {
+        NEXT_ENTITY_ID.fetch_add(1, Ordering::SeqCst) as i32
+    }
+
+    /// Adds `entity` to the world, players see it from the next tick on.
+    pub fn spawn_entity(&self, entity: Entity) {
+        self.spawned.lock().unwrap().push(entity.id);
+        self.entities.lock().unwrap().insert(entity.id, entity);
+    }
+
+    /// Players in this world.
+    pub fn players(&self) -> Vec<PlayerHandle> {
+        self.players.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Names and ids of the players in this world, locking each in turn.
+    pub fn profiles(&self) -> Vec<(String, Uuid)> {
+        self.players().into_iter().map(|player| {
+            let player = player.lock().unwrap();
+            (player.name().to_string(), player.uuid().clone())
+        }).collect()
+    }
+
+    /// Sends `packet` to every player in this world, must not be called
+    /// while holding a player's lock.
+    pub fn broadcast<P: PacketWrite + ?Sized>(&self, packet: &P) {
+        for player in self.players() {
+            let mut player = player.lock().unwrap();
+            if let Err(err) = player.send(packet) {
+                debug!("failed sending to {}: {}", player.name(), err);
+            }
+        }
+    }
+
+    /// Sends `packet` to every player in this world on the next tick, safe
+    /// to call while holding a player's lock.
+    pub fn queue_broadcast<P: PacketWrite + Send + 'static>(&self, packet: P) {
+        self.queued.lock().unwrap().push(Box::new(packet));
+    }
+
+    /// Sends `message` to every player whose chat mode shows it on the
+    /// next tick, safe to call while holding a player's lock.
+    pub fn queue_chat(&self, message: ChatJson, position: ChatPosition) {
+        self.queued_chat.lock().unwrap().push((message, position));
+    }
+
+    /// Whether `message` from `player` may be sent, after going through
+    /// the chat filters. Muted players are told they can't chat.
+    pub fn check_chat(&self, player: &mut Player, message: &mut String) -> io::Result<bool> {
+        if player.muted() {
+            try!(player.send_message("You are muted."));
+            return Ok(false);
+        }
+        match self.events.filter_chat(player, message) {
+            FilterAction::Allow => return Ok(true),
+            FilterAction::Cancel => debug!("chat filters stopped {}: {}", player.name(), message),
+            FilterAction::Mute => {
+                info!("chat filters muted {} for: {}", player.name(), message);
+                try!(self.set_muted(player, true));
+                try!(player.send_message("You are muted."));
+            }
+        }
+        Ok(false)
+    }
+
+    /// Mutes or unmutes `player`, for good.
+    pub fn set_muted(&self, player: &mut Player, muted: bool) -> io::Result<()> {
+        player.set_muted(muted);
+        self.mutes.set_muted(player.name(), muted)
+    }
+
+    /// Throws `stack` out of `player`'s hands.
+    pub fn drop_item(&self, player: &mut Player, stack: Slot) -> io::Result<()> {
+        let mut position = player.eye_position();
+        position[1] -= 0.3;
+        let mut entity = Entity::new(self.next_entity_id(), EntityKind::Item(stack), position);
+        let look = player.look();
+        entity.velocity = [look[0] * 0.3, look[1] * 0.3 + 0.1, look[2] * 0.3];
+        self.spawn_entity(entity);
+        Ok(())
+    }
+
+    /// Hangs an item frame on the `face` side of the block at `location`.
+    fn hang_item_frame(&self, player: &mut Player, location: [i32; 3], face: BlockFace) {
+        let frame = match ItemFrame::new(face) {
+            Some(frame) => frame,
+            None => return
+        };
+        let offset = face.offset();
+        let spot = [location[0] + offset[0], location[1] + offset[1], location[2] + offset[2]];
+        if !block::is_solid(self.block(location) >> 4) || self.block(spot) >> 4 != 0 {
+            return;
+        }
+        let position = [spot[0] as f64 + 0.5, spot[1] as f64 + 0.5, spot[2] as f64 + 0.5];
+        let taken = self.entities.lock().unwrap().values().any(|entity| match entity.kind {
+            EntityKind::ItemFrame(ref other) => other.facing == face && entity.position == position,
+            _ => false
+        });
+        if taken {
+            return;
+        }
+        self.spawn_entity(Entity::new(self.next_entity_id(), EntityKind::ItemFrame(frame), position));
+        if player.gamemode() != GameMode::Creative {
+            player.take_held_item(false);
+        }
+    }
+
+    /// Stands an armor stand at `position`, facing `player` to the nearest
+    /// 45°.
+    fn place_armor_stand(&self, player: &mut Player, position: [f64; 3]) {
+        let mut stand = Entity::new(self.next_entity_id(), EntityKind::ArmorStand(ArmorStand::new()), position);
+        stand.yaw = ((player.yaw() - 180.0 + 22.5) / 45.0).floor() * 45.0;
+        self.spawn_entity(stand);
+        if player.gamemode() != GameMode::Creative {
+            player.take_held_item(false);
+        }
+    }
+
+    /// Puts a boat or a minecart down at `position`.
+    fn place_vehicle(&self, player: &mut Player, kind: ObjectKind, position: [f64; 3]) {
+        let mut vehicle = Entity::new(self.next_entity_id(), EntityKind::Vehicle(kind), position);
+        vehicle.yaw = player.yaw();
+        self.spawn_entity(vehicle);
+        if player.gamemode() != GameMode::Creative {
+            player.take_held_item(false);
+        }
+    }
+
+    /// Lets everyone know about changes to the metadata of entity `id`.
+    fn update_metadata(&self, id: i32) {
+        use packet::play::clientbound::EntityMetadata;
+
+        let metadata = match self.entities.lock().unwrap().get(&id) {
+            Some(entity) => entity.metadata(),
+            None => return
+        };
+        self.queue_broadcast(EntityMetadata { entity_id: id, metadata: metadata });
+    }
+
+    /// Sends everyone NBT of entity `id`, sanitized first since it may hold
+    /// text players wrote.
+    pub fn update_entity_nbt(&self, id: i32, entries: HashMap<String, nbt::Value>) -> io::Result<()> {
+        use packet::play::clientbound::UpdateEntityNbt;
+
+        let tag = try!(entity::sanitize::entity_nbt(entries));
+        self.queue_broadcast(UpdateEntityNbt { entity_id: id, tag: tag });
+        Ok(())
+    }
+
+    /// Reacts to `player` right clicking the entity `target`: tamed horses
+    /// get saddled, or have their inventory opened by sneaking players,
+    /// anything else rideable gets ridden.
+    pub fn interact(&self, player: &mut Player, target: i32) -> io::Result<()> {
+        if self.use_item_frame(player, target) {
+            return Ok(());
+        }
+        let held = player.held_item().cloned();
+        let sneaking = player.sneaking();
+        let (tame, equipped) = match self.entities.lock().unwrap().get_mut(&target) {
+            Some(&mut Entity { kind: EntityKind::Horse(ref mut horse), .. }) => {
+                let tame = horse.tame();
+                (tame, tame && !sneaking && held.as_ref().map_or(false, |stack| horse.equip(stack)))
+            }
+            Some(_) => (false, false),
+            None => return Ok(())
+        };
+        if equipped {
+            if player.gamemode() != GameMode::Creative {
+                player.take_held_item(false);
+            }
+            self.update_metadata(target);
+            Ok(())
+        } else if tame && sneaking {
+            self.open_window(player, WindowKind::Horse(target))
+        } else {
+            self.mount(player, target)
+        }
+    }
+
+    /// Puts the item `player` holds in the item frame `target`, or turns the
+    /// one already in it. Returns false if `target` isn't an item frame.
+    fn use_item_frame(&self, player: &mut Player, target: i32) -> bool {
+        let used = match self.entities.lock().unwrap().get_mut(&target) {
+            Some(&mut Entity { kind: EntityKind::ItemFrame(ref mut frame), .. }) => frame.interact(player.held_item()),
+            _ => return false
+        };
+        if used && player.gamemode() != GameMode::Creative {
+            player.take_held_item(false);
+        }
+        self.update_metadata(target);
+        true
+    }
+
+    /// Swaps the item `player` holds with the matching equipment of the
+    /// armor stand `target`. Empty handed players take whatever is at the
+    /// `height` they clicked instead.
+    pub fn use_armor_stand(&self, player: &mut Player, target: i32, height: f32) -> io::Result<()> {
+        use packet::play::clientbound::EntityEquipment;
+
+        let held = player.held_item().cloned();
+        let (slot, placed, previous) = match self.entities.lock().unwrap().get_mut(&target) {
+            Some(&mut Entity { kind: EntityKind::ArmorStand(ref mut stand), .. }) => {
+                let slot = match held {
+                    Some(ref stack) => stand.slot_for(stack),
+                    None => stand.slot_at(height)
+                };
+                let slot = match slot {
+                    Some(slot) => slot,
+                    None => return Ok(())
+                };
+                let placed = held.as_ref().map(|stack| {
+                    let mut one = stack.clone();
+                    one.set_count(1);
+                    one
+                });
+                (slot, placed.clone(), stand.swap(slot, placed))
+            }
+            _ => return Ok(())
+        };
+        self.queue_broadcast(EntityEquipment { entity_id: target, slot: slot as i16, item: placed });
+        match held {
+            // Creative players keep their item, and get nothing back
+            Some(_) if player.gamemode() == GameMode::Creative => return Ok(()),
+            Some(ref stack) if stack.count() > 1 => {
+                player.take_held_item(false);
+                if let Some(previous) = previous {
+                    if let Some(left) = try!(player.add_item(previous)) {
+                        try!(self.drop_item(player, left));
+                    }
+                }
+            }
+            _ => player.set_held_item(previous)
+        }
+        let index = player::HOTBAR_START + player.held_slot();
+        player.send_slot(index)
+    }
+
+    /// Left click on entity `target`. Hitting an item frame knocks its item
+    /// out, or takes it down when empty, and armor stands break at once.
+    pub fn attack(&self, player: &mut Player, target: i32) -> io::Result<()> {
+        let creative = player.gamemode() == GameMode::Creative;
+        let (position, mut drops, changed) = match self.entities.lock().unwrap().get_mut(&target) {
+            Some(entity) => {
+                let position = entity.position;
+                match entity.kind {
+                    EntityKind::ItemFrame(ref mut frame) => match frame.take_item() {
+                        Some(stack) => (position, vec![stack], true),
+                        None => {
+                            entity.removed = true;
+                            (position, vec![Slot::new(item::ITEM_FRAME, 1, 0)], false)
+                        }
+                    },
+                    EntityKind::ArmorStand(ref mut stand) => {
+                        entity.removed = true;
+                        let mut drops = vec![Slot::new(item::ARMOR_STAND, 1, 0)];
+                        drops.extend(stand.take_equipment());
+                        (position, drops, false)
+                    }
+                    _ => return Ok(())
+                }
+            }
+            None => return Ok(())
+        };
+        player.add_exhaustion(0.3);
+        if changed {
+            self.update_metadata(target);
+        }
+        // Creative players don't need the items
+        if creative {
+            drops.clear();
+        }
+        for stack in drops {
+            let mut item = Entity::new(self.next_entity_id(), EntityKind::Item(stack), position);
+            item.velocity = [(rand::random::<f64>() - 0.5) * 0.1, 0.1, (rand::random::<f64>() - 0.5) * 0.1];
+            self.spawn_entity(item);
+        }
+        Ok(())
+    }
+
+    /// Seats `player` on the entity `vehicle`, if it's free. Wild horses
+    /// only let players on once tamed, which takes a few tries.
+    pub fn mount(&self, player: &mut Player, vehicle: i32) -> io::Result<()> {
+        use packet::play::clientbound::{AttachEntity, EntityStatus};
+
+        if player.vehicle() == Some(vehicle) {
+            return Ok(());
+        }
+        let mut tamed = None;
+        let free = match self.entities.lock().unwrap().get_mut(&vehicle) {
+            Some(entity) => {
+                let free = entity.rideable() && entity.passenger.is_none();
+                if let EntityKind::Horse(ref mut horse) = entity.kind {
+                    if free && !horse.tame() {
+                        tamed = Some(horse.try_tame(player.uuid().clone()));
+                    }
+                }
+                free
+            }
+            None => false
+        };
+        match tamed {
+            Some(true) => {
+                self.queue_broadcast(EntityStatus { entity_id: vehicle, entity_status: EntityStatusKind::TamingSucceeded });
+                self.update_metadata(vehicle);
+            }
+            Some(false) => {
+                self.queue_broadcast(EntityStatus { entity_id: vehicle, entity_status: EntityStatusKind::TamingFailed });
+                return Ok(());
+            }
+            None => {}
+        }
+        if !free {
+            return Ok(());
+        }
+        try!(self.dismount(player));
+        if let Some(entity) = self.entities.lock().unwrap().get_mut(&vehicle) {
+            entity.passenger = Some(player.entity_id());
+        }
+        player.set_vehicle(Some(vehicle));
+        self.queue_broadcast(AttachEntity { riding_eid: player.entity_id(), vehicle_eid: vehicle, leash: false });
+        Ok(())
+    }
+
+    /// Gets `player` off what they're riding and puts them on top of it.
+    fn dismount(&self, player: &mut Player) -> io::Result<()> {
+        use packet::play::clientbound::{AttachEntity, PlayerPositionAndLook};
+
+        let vehicle = match player.take_vehicle() {
+            Some(vehicle) => vehicle,
+            None => return Ok(())
+        };
+        let mut position = player.position();
+        if let Some(entity) = self.entities.lock().unwrap().get_mut(&vehicle) {
+            entity.passenger = None;
+            position = [entity.position[0], entity.position[1] + entity.size()[1], entity.position[2]];
+        }
+        player.set_position(position, false);
+        self.queue_broadcast(AttachEntity { riding_eid: player.entity_id(), vehicle_eid: -1, leash: false });
+        // Only the position is absolute, keep the player's look
+        player.send(&PlayerPositionAndLook { position: position, yaw: 0.0, pitch: 0.0, flags: 0x18 })
+    }
+
+    /// Pushes the vehicle `player` rides according to their input.
+    fn steer(&self, player: &Player, forward: f32, sideways: f32) {
+        let vehicle = match player.vehicle() {
+            Some(vehicle) => vehicle,
+            None => return
+        };
+        if let Some(entity) = self.entities.lock().unwrap().get_mut(&vehicle) {
+            let speed = match entity.kind {
+                EntityKind::Horse(ref horse) if horse.saddled() => horse::SPEED,
+                // There's no steering a horse without a saddle
+                EntityKind::Horse(_) => return,
+                _ => 0.04
+            };
+            let yaw = (player.yaw() as f64).to_radians();
+            let (forward, sideways) = (forward as f64 * speed, sideways as f64 * speed);
+            entity.velocity[0] += -yaw.sin() * forward + yaw.cos() * sideways;
+            entity.velocity[2] += yaw.cos() * forward + yaw.sin() * sideways;
+            entity.yaw = player.yaw();
+        }
+    }
+
+    /// Makes the horse `player` rides jump, `boost` being how long the jump
+    /// was charged, from 0 to 100.
+    fn jump_horse(&self, player: &Player, boost: i32) {
+        let vehicle = match player.vehicle() {
+            Some(vehicle) => vehicle,
+            None => return
+        };
+        if let Some(entity) = self.entities.lock().unwrap().get_mut(&vehicle) {
+            let saddled = match entity.kind {
+                EntityKind::Horse(ref horse) => horse.saddled(),
+                _ => false
+            };
+            if saddled && entity.on_ground {
+                let boost = (boost as f64 / 100.0).max(0.0).min(1.0);
+                entity.velocity[1] = horse::JUMP_VELOCITY * boost;
+            }
+        }
+    }
+
+    /// Opens a window of the given `kind` for `player` and fills it in.
+    /// Horses only have their inventory opened once tamed.
+    fn open_window(&self, player: &mut Player, kind: WindowKind) -> io::Result<()> {
+        use packet::play::clientbound::OpenWindow;
+
+        let (window_type, title, slots, entity_id) = match kind {
+            WindowKind::Horse(horse) => match self.entities.lock().unwrap().get(&horse) {
+                Some(&Entity { kind: EntityKind::Horse(ref state), .. }) if state.tame() => {
+                    ("EntityHorse", state.kind.name(), state.window_size(), Some(horse))
+                }
+                _ => return Ok(())
+            },
+            WindowKind::Furnace(_) => ("minecraft:furnace", "Furnace", furnace::SIZE, None),
+            WindowKind::BrewingStand(_) => ("minecraft:brewing_stand", "Brewing Stand", brewing_stand::SIZE, None),
+            // Windows which don't keep items have no slots as far as the
+            // client is concerned
+            WindowKind::Anvil(_) => ("minecraft:anvil", "Repair & Name", 0, None)
+        };
+        // Whatever was open before goes away, anvil inputs included
+        if player.window().is_some() {
+            try!(self.close_window(player));
+        }
+        let window_id = player.open_window(kind);
+        try!(player.send(&OpenWindow {
+            window_id: window_id,
+            window_type: window_type.to_string(),
+            window_title: ChatJson::from(title),
+            slots: slots as u8,
+            entity_id: entity_id
+        }));
+        self.send_window(player)
+    }
+
+    /// Number of slots in a window of the given `kind` before the player's
+    /// inventory.
+    fn container_size(&self, kind: &WindowKind) -> usize {
+        match *kind {
+            WindowKind::Horse(horse) => match self.entities.lock().unwrap().get(&horse) {
+                Some(&Entity { kind: EntityKind::Horse(ref state), .. }) => state.window_size(),
+                _ => 0
+            },
+            WindowKind::Furnace(_) => furnace::SIZE,
+            WindowKind::BrewingStand(_) => brewing_stand::SIZE,
+            WindowKind::Anvil(_) => anvil::SIZE
+        }
+    }
+
+    /// Sends the whole content of the window `player` has open, or their
+    /// inventory when none is, its properties and what their cursor holds.
+    fn send_window(&self, player: &mut Player) -> io::Result<()> {
+        use packet::play::clientbound::{SetSlot, WindowItems, WindowProperty};
+
+        let (window_id, mut slots, properties) = match player.window() {
+            Some(window) => {
+                let (slots, properties) = match window.kind {
+                    WindowKind::Horse(horse) => match self.entities.lock().unwrap().get(&horse) {
+                        Some(&Entity { kind: EntityKind::Horse(ref state), .. }) => (state.items(), vec![]),
+                        _ => (vec![], vec![])
+                    },
+                    WindowKind::Furnace(location) | WindowKind::BrewingStand(location) => {
+                        match self.block_entities.lock().unwrap().get(&location) {
+                            Some(block_entity) => (block_entity.items(), block_entity.properties()),
+                            None => (vec![None; self.container_size(&window.kind)], vec![])
+                        }
+                    }
+                    WindowKind::Anvil(ref anvil) => (anvil.items(), vec![anvil.cost() as i16])
+                };
+                (window.id, slots, properties)
+            }
+            // Crafting and armor head the player's own inventory
+            None => (0, (0..player::MAIN_START).map(|index| player.slot(index).cloned()).collect(), vec![])
+        };
+        // The player's inventory fills the bottom
+        slots.extend((player::MAIN_START..player::INVENTORY_SIZE).map(|index| player.slot(index).cloned()));
+        try!(player.send(&WindowItems { window_id: window_id, slots: slots }));
+        for (property, value) in properties.into_iter().enumerate() {
+            try!(player.send(&WindowProperty { window_id: window_id, property: property as i16, value: value }));
+        }
+        let cursor = player.cursor().cloned();
+        player.send(&SetSlot { window_id: window::CURSOR_WINDOW, slot: -1, data: cursor })
+    }
+
+    /// Handles a click in the window `player` has open, or in their own
+    /// inventory when none is. Clicks which don't make sense, or which the
+    /// client saw differently, are rejected and the window resent. Like
+    /// vanilla, further clicks in that window are ignored until the client
+    /// sends the rejected action number back.
+    fn click_window(&self, player: &mut Player, click: ClickWindow) -> io::Result<()> {
+        use packet::play::clientbound::ConfirmTransaction;
+
+        if click.window_id != player.window_id() {
+            debug!("ignoring click of {} in window {}, which isn't open", player.name(), click.window_id);
+            return Ok(());
+        }
+        let kind = player.window().map(|window| window.kind.clone());
+        if let Some(ref kind) = kind {
+            if !self.window_usable(player, kind) {
+                return self.force_close_window(player);
+            }
+        }
+        if player.awaiting_apology(click.window_id) {
+            debug!("ignoring click {} of {} until the last rejected one is confirmed", click.action_number, player.name());
+            return Ok(());
+        }
+        let dropped = match kind {
+            None => {
+                let container = (0..player::MAIN_START).map(|index| player.slot(index).cloned()).collect();
+                click_slots(player, container, &window::click::InventoryRules, &click).map(|(container, dropped)| {
+                    for (index, stack) in container.into_iter().enumerate() {
+                        player.set_slot(index, stack);
+                    }
+                    dropped
+                })
+            }
+            Some(WindowKind::Horse(horse)) => self.click_horse(player, horse, &click),
+            Some(WindowKind::Furnace(location)) | Some(WindowKind::BrewingStand(location)) => {
+                try!(self.click_block_entity(player, location, &click))
+            }
+            Some(WindowKind::Anvil(_)) => try!(self.click_anvil(player, &click))
+        };
+        let accepted = dropped.is_some();
+        for stack in dropped.into_iter().flat_map(|dropped| dropped) {
+            try!(self.drop_item(player, stack));
+        }
+        try!(player.send(&ConfirmTransaction {
+            window_id: click.window_id,
+            action_number: click.action_number,
+            accepted: accepted
+        }));
+        if accepted {
+            Ok(())
+        } else {
+            player.reject_click(click.window_id, click.action_number);
+            // Put the client's window back the way it was, so that it
+            // doesn't show ghost items
+            self.send_window(player)
+        }
+    }
+
+    fn click_horse(&self, player: &mut Player, horse: i32, click: &ClickWindow) -> Option<Vec<Slot>> {
+        let (dropped, equipped) = {
+            let mut entities = self.entities.lock().unwrap();
+            let state = match entities.get_mut(&horse) {
+                Some(&mut Entity { kind: EntityKind::Horse(ref mut state), .. }) => state,
+                _ => return None
+            };
+            let before = state.items();
+            let (container, dropped) = match click_slots(player, before.clone(), &*state, click) {
+                Some(clicked) => clicked,
+                None => return None
+            };
+            let equipped = before[horse::SADDLE_SLOT] != container[horse::SADDLE_SLOT] ||
+                before[horse::ARMOR_SLOT] != container[horse::ARMOR_SLOT];
+            for (index, stack) in container.into_iter().enumerate() {
+                if let Some(slot) = state.slot_mut(index) {
+                    *slot = stack;
+                }
+            }
+            (dropped, equipped)
+        };
+        if equipped {
+            self.update_metadata(horse);
+        }
+        Some(dropped)
+    }
+
+    /// Clicks a furnace or brewing stand. Taking furnace output earns the
+    /// experience gathered smelting it.
+    fn click_block_entity(&self, player: &mut Player, location: [i32; 3], click: &ClickWindow) -> io::Result<Option<Vec<Slot>>> {
+        let (dropped, experience) = {
+            let mut block_entities = self.block_entities.lock().unwrap();
+            let block_entity = match block_entities.get_mut(&location) {
+                Some(block_entity) => block_entity,
+                None => return Ok(None)
+            };
+            let before = block_entity.items();
+            let (container, dropped) = match click_slots(player, before.clone(), &*block_entity, click) {
+                Some(clicked) => clicked,
+                None => return Ok(None)
+            };
+            let experience = match *block_entity {
+                BlockEntity::Furnace(ref mut furnace) if container[furnace::OUTPUT_SLOT].is_none() &&
+                                                         before[furnace::OUTPUT_SLOT].is_some() => furnace.take_experience(),
+                _ => 0
+            };
+            for (index, stack) in container.into_iter().enumerate() {
+                if let Some(slot) = block_entity.slot_mut(index) {
+                    *slot = stack;
+                }
+            }
+            (dropped, experience)
+        };
+        try!(player.add_experience(experience));
+        Ok(Some(dropped))
+    }
+
+    /// Anvil output can only be taken with enough levels to pay for it,
+    /// which uses up the inputs.
+    fn click_anvil(&self, player: &mut Player, click: &ClickWindow) -> io::Result<Option<Vec<Slot>>> {
+        let creative = player.gamemode() == GameMode::Creative;
+        let (before, rules) = match player.window() {
+            Some(&Window { kind: WindowKind::Anvil(ref anvil), .. }) => {
+                (anvil.items(), window::click::AnvilRules { affordable: creative || player.level() >= anvil.cost() })
+            }
+            _ => return Ok(None)
+        };
+        let (container, dropped) = match click_slots(player, before.clone(), &rules, click) {
+            Some(clicked) => clicked,
+            None => return Ok(None)
+        };
+        let mut paid = None;
+        if let Some(&mut Window { kind: WindowKind::Anvil(ref mut anvil), .. }) = player.window_mut() {
+            if before[anvil::OUTPUT_SLOT].is_some() && container[anvil::OUTPUT_SLOT].is_none() {
+                let location = anvil.location;
+                paid = anvil.take_output(creative).map(|(_, cost)| (cost, location));
+            } else {
+                for index in 0..anvil::OUTPUT_SLOT {
+                    if before[index] != container[index] {
+                        if let Some(slot) = anvil.input_mut(index) {
+                            *slot = container[index].clone();
+                        }
+                    }
+                }
+                anvil.update(creative);
+            }
+        }
+        if let Some((cost, location)) = paid {
+            if !creative {
+                try!(player.remove_levels(cost));
+            }
+            self.wear_anvil(location);
+        }
+        try!(self.send_anvil_output(player));
+        Ok(Some(dropped))
+    }
+
+    /// Updates the name typed in the anvil `player` is using, as sent in a
+    /// MC|ItemName plugin message.
+    fn rename_in_anvil(&self, player: &mut Player, data: &[u8]) -> io::Result<()> {
+        let name = if data.is_empty() {
+            String::new()
+        } else {
+            try!(<String as Protocol>::proto_decode(&mut io::Cursor::new(data)))
+        };
+        // Longer names are ignored, like vanilla does
+        if name.chars().count() > 30 {
+            return Ok(());
+        }
+        let creative = player.gamemode() == GameMode::Creative;
+        match player.window_mut() {
+            Some(&mut Window { kind: WindowKind::Anvil(ref mut anvil), .. }) => {
+                anvil.set_name(name);
+                anvil.update(creative);
+            }
+            _ => return Ok(())
+        }
+        self.send_anvil_output(player)
+    }
+
+    /// Tells `player` what their anvil would give them and for how many
+    /// levels, the client doesn't work that out itself.
+    fn send_anvil_output(&self, player: &mut Player) -> io::Result<()> {
+        use packet::play::clientbound::{SetSlot, WindowProperty};
+
+        let (window_id, output, cost) = match player.window() {
+            Some(&Window { id, kind: WindowKind::Anvil(ref anvil) }) => {
+                (id, anvil.items()[anvil::OUTPUT_SLOT].clone(), anvil.cost())
+            }
+            _ => return Ok(())
+        };
+        try!(player.send(&SetSlot { window_id: window_id, slot: anvil::OUTPUT_SLOT as i16, data: output }));
+        player.send(&WindowProperty { window_id: window_id, property: 0, value: cost as i16 })
+    }
+
+    /// Anvils have a 12% chance to get more damaged each time they're
+    /// used, breaking after the third time.
+    fn wear_anvil(&self, location: [i32; 3]) {
+        if rand::random::<f32>() >= 0.12 {
+            return;
+        }
+        let block = self.block(location);
+        if block >> 4 != block::ANVIL {
+            return;
+        }
+        let metadata = block & 0xf;
+        if metadata >> 2 >= 2 {
+            self.set_block(location, 0);
+        } else {
+            self.set_block(location, block::ANVIL << 4 | (metadata + 4));
+        }
+    }
+
+    /// Whether `player` can still use a window of the given `kind`: what
+    /// it was opened on is still there, and close enough.
+    fn window_usable(&self, player: &Player, kind: &WindowKind) -> bool {
+        let target = match *kind {
+            WindowKind::Horse(horse) => match self.entities.lock().unwrap().get(&horse) {
+                Some(entity) if !entity.removed => entity.position,
+                _ => return false
+            },
+            WindowKind::Furnace(location) | WindowKind::BrewingStand(location) => {
+                let block = self.block(location) >> 4;
+                if !self.block_entities.lock().unwrap().get(&location).map_or(false, |block_entity| block_entity.belongs_to(block)) {
+                    return false;
+                }
+                [location[0] as f64 + 0.5, location[1] as f64 + 0.5, location[2] as f64 + 0.5]
+            }
+            WindowKind::Anvil(ref anvil) => {
+                if self.block(anvil.location) >> 4 != block::ANVIL {
+                    return false;
+                }
+                let location = anvil.location;
+                [location[0] as f64 + 0.5, location[1] as f64 + 0.5, location[2] as f64 + 0.5]
+            }
+        };
+        window::in_reach(player.position(), target)
+    }
+
+    /// Closes the window `player` has open on the server's side, telling
+    /// their client to close it as well.
+    fn force_close_window(&self, player: &mut Player) -> io::Result<()> {
+        use packet::play::clientbound::CloseWindow;
+
+        let window_id = player.window_id();
+        if window_id == 0 {
+            return Ok(());
+        }
+        debug!("closing window {} of {}", window_id, player.name());
+        try!(player.send(&CloseWindow { window_id: window_id }));
+        self.close_window(player)
+    }
+
+    /// Closes the windows of players who walked away from them, or whose
+    /// block or horse is gone.
+    fn tick_windows(&self, players: &[(Arc<Mutex<Player>>, i32, [f64; 3], GameMode)]) {
+        for &(ref player, _, _, _) in players {
+            let mut player = player.lock().unwrap();
+            let usable = player.window().map_or(true, |window| self.window_usable(&player, &window.kind));
+            if !usable {
+                if let Err(err) = self.force_close_window(&mut player) {
+                    debug!("failed closing the window of {}: {}", player.name(), err);
+                }
+            }
+        }
+    }
+
+    /// Sends players the keep-alives due, and disconnects those who didn't
+    /// answer the last one in time.
+    fn tick_keep_alives(&self, players: &[(Arc<Mutex<Player>>, i32, [f64; 3], GameMode)]) {
+        use packet::play::clientbound::KeepAlive;
+
+        let now = Instant::now();
+        for &(ref player, _, _, _) in players {
+            let mut player = player.lock().unwrap();
+            let poll = match player.keep_alive_mut() {
+                Some(keep_alive) => keep_alive.poll(now, rand::random()),
+                None => continue
+            };
+            let result = match poll {
+                keep_alive::Poll::Wait => Ok(()),
+                keep_alive::Poll::Send(id) => player.send(&KeepAlive { keep_alive_id: id }),
+                keep_alive::Poll::TimedOut => {
+                    warn!("{} didn't answer a keep-alive within {} seconds, disconnecting them", player.name(), self.keep_alive.1.as_secs());
+                    // The client is likely gone already, close the connection anyway
+                    let _ = player.kick(message::TIMEOUT);
+                    player.disconnect()
+                }
+            };
+            if let Err(err) = result {
+                debug!("failed keeping {} alive: {}", player.name(), err);
+            }
+        }
+    }
+
+    /// Marks players idle for afk-timeout away from keyboard, and kicks
+    /// those idle for player-idle-timeout.
+    fn tick_idle(&self, players: &[(Arc<Mutex<Player>>, i32, [f64; 3], GameMode)]) {
+        use packet::play::clientbound::UpdatePlayerList;
+
+        if self.afk_timeout.is_none() && self.idle_timeout.is_none() {
+            return;
+        }
+        let now = Instant::now();
+        for &(ref player, _, _, _) in players {
+            let mut player = player.lock().unwrap();
+            let idle = player.idle_for(now);
+            if self.idle_timeout.map_or(false, |timeout| idle >= timeout) {
+                info!("{} was idle for too long, disconnecting them", player.name());
+                let _ = player.kick(message::IDLE);
+                if let Err(err) = player.disconnect() {
+                    debug!("failed disconnecting {}: {}", player.name(), err);
+                }
+            } else if !player.afk() && self.afk_timeout.map_or(false, |timeout| idle >= timeout) {
+                player.set_afk(true);
+                if let Some(ref format) = self.afk_list_name {
+                    let mut name = ChatJson::from(message::fill(format, &[player.name()]));
+                    name.color = Some(Color::Gray);
+                    player.set_list_name(Some(name.clone()));
+                    let names = vec![(player.uuid().clone(), Some(name))];
+                    self.queue_broadcast(UpdatePlayerList { action: PlayerListAction::UpdateDisplayName(names) });
+                }
+                self.events.emit(&Event::AfkEnter { player: &player });
+            }
+        }
+    }
+
+    /// Records `player` moving or chatting, bringing them back if they were
+    /// away from keyboard.
+    fn mark_active(&self, player: &mut Player) {
+        use packet::play::clientbound::UpdatePlayerList;
+
+        if !player.mark_active(Instant::now()) {
+            return;
+        }
+        if player.list_name().is_some() {
+            player.set_list_name(None);
+            let names = vec![(player.uuid().clone(), None)];
+            self.queue_broadcast(UpdatePlayerList { action: PlayerListAction::UpdateDisplayName(names) });
+        }
+        self.events.emit(&Event::AfkLeave { player: player });
+    }
+
+    /// Closes the window `player` has open. Items left in an anvil go back
+    /// to the player, and whatever their cursor held is thrown away like
+    /// vanilla does.
+    fn close_window(&self, player: &mut Player) -> io::Result<()> {
+        if let Some(mut window) = player.close_window() {
+            if let WindowKind::Anvil(ref mut anvil) = window.kind {
+                for stack in anvil.take_inputs() {
+                    if let Some(left) = try!(player.add_item(stack)) {
+                        try!(self.drop_item(player, left));
+                    }
+                }
+            }
+        }
+        match player.take_cursor() {
+            Some(stack) => self.drop_item(player, stack),
+            None => Ok(())
+        }
+    }
+
+    /// Launches a projectile of the given `kind` where `player` is looking.
+    fn launch(&self, player: &Player, kind: ObjectKind, speed: f64, pickup: bool) {
+        let mut position = player.eye_position();
+        position[1] -= 0.1;
+        let projectile = EntityKind::Projectile { kind: kind, shooter: player.entity_id(), stuck: false, pickup: pickup };
+        let mut entity = Entity::new(self.next_entity_id(), projectile, position);
+        let look = player.look();
+        entity.velocity = [look[0] * speed, look[1] * speed, look[2] * speed];
+        self.spawn_entity(entity);
+    }
+
+    /// Has `player` start using their held item, e.g. eating or drawing a
+    /// bow.
+    fn start_using_item(&self, player: &mut Player) {
+        player.start_using_item(self.world_age());
+        self.update_player_metadata(player);
+    }
+
+    /// Lets others know whether `player` is sneaking, sprinting or using
+    /// their item.
+    fn update_player_metadata(&self, player: &Player) {
+        use packet::play::clientbound::EntityMetadata;
+        use types::EntityMetadata as Metadata;
+
+        let mut metadata = Metadata::new();
+        metadata.set_flags(player.metadata_flags());
+        self.queue_broadcast(EntityMetadata { entity_id: player.entity_id(), metadata: metadata });
+    }
+
+    /// Finishes eating the food or drinking the milk or potion `player`
+    /// holds, once they've been at it long enough.
+    fn tick_item_use(&self, players: &[(Arc<Mutex<Player>>, i32, [f64; 3], GameMode)]) {
+        let age = self.world_age();
+        for &(ref player, _, _, _) in players {
+            let mut player = player.lock().unwrap();
+            let due = player.using_item().map_or(false, |started| age - started >= item::USE_TICKS);
+            let held = player.held_item().map(|stack| (stack.id(), stack.damage()));
+            match held {
+                Some((id, _)) if due && (item::food(id).is_some() || id == item::MILK_BUCKET || id == item::POTION) => {}
+                _ => continue
+            }
+            if let Err(err) = self.finish_using_item(&mut player, age) {
+                debug!("failed finishing {}'s item use: {}", player.name(), err);
+            }
+        }
+    }
+
+    fn finish_using_item(&self, player: &mut Player, age: i64) -> io::Result<()> {
+        use packet::play::clientbound::EntityStatus;
+
+        player.stop_using_item();
+        let (id, damage) = match player.held_item() {
+            Some(stack) => (stack.id(), stack.damage()),
+            None => return Ok(())
+        };
+        if let Some((food, saturation)) = item::food(id) {
+            player.eat(food, saturation);
+            try!(player.send_health());
+        } else if id == item::MILK_BUCKET {
+            player.clear_effects();
+        } else {
+            for effect in potion::effects(damage) {
+                player.add_effect(effect, age);
+            }
+        }
+        if player.gamemode() != GameMode::Creative {
+            player.take_held_item(false);
+            if let Some(leftover) = item::leftover(id) {
+                let stack = Slot::new(leftover, 1, 0);
+                if player.held_item().is_none() {
+                    player.set_held_item(Some(stack));
+                } else if let Some(rest) = try!(player.add_item(stack)) {
+                    try!(self.drop_item(player, rest));
+                }
+            }
+            let held = player::HOTBAR_START + player.held_slot();
+            try!(player.send_slot(held));
+        }
+        try!(player.send(&EntityStatus { entity_id: player.entity_id(), entity_status: EntityStatusKind::EatingAccepted }));
+        self.update_player_metadata(player);
+        Ok(())
+    }
+
+    /// Releases the bow `player` has been drawing for `ticks` ticks.
+    fn shoot_arrow(&self, player: &mut Player, ticks: i64) -> io::Result<()> {
+        let charge = ticks as f64 / 20.0;
+        let force = ((charge * charge + charge * 2.0) / 3.0).min(1.0);
+        if force < 0.1 {
+            return Ok(());
+        }
+        let creative = player.gamemode() == GameMode::Creative;
+        if !creative && !try!(player.take_item(item::ARROW)) {
+            return Ok(());
+        }
+        player.wear_held_item();
+        self.launch(player, ObjectKind::Arrow, force * 3.0, !creative);
+        Ok(())
+    }
+
+    /// Replaces the TNT block at `location` with TNT which explodes in
+    /// `fuse` ticks.
+    pub fn ignite_tnt(&self, location: [i32; 3], fuse: i32) {
+        self.set_block(location, 0);
+        let center = [location[0] as f64 + 0.5, location[1] as f64, location[2] as f64 + 0.5];
+        let mut tnt = Entity::new(self.next_entity_id(), EntityKind::PrimedTnt { fuse: fuse }, center);
+        // A little hop in a random direction
+        let angle = rand::random::<f64>() * PI * 2.0;
+        tnt.velocity = [-angle.sin() * 0.02, 0.2, -angle.cos() * 0.02];
+        self.spawn_entity(tnt);
+    }
+
+    /// Blows up `power` worth of explosives at `center`, destroying blocks
+    /// and pushing entities away. When `fire` is set some of the destroyed
+    /// blocks catch fire. Players are only affected once the explosion is
+    /// applied to them with `apply_explosion`.
+    pub fn create_explosion(&self, center: [f64; 3], power: f32, fire: bool) -> Explosion {
+        let explosion = Explosion::new(center, power, |location| self.block(location));
+        // The Explosion packet clears these blocks on the client already
+        for &location in &explosion.blocks {
+            match self.block(location) >> 4 {
+                block::AIR => {}
+                block::TNT => self.ignite_tnt(location, entity::TNT_FUSE / 8 + rand::thread_rng().gen_range(0, entity::TNT_FUSE / 4)),
+                _ => { self.store_block(location, 0); }
+            }
+        }
+        if fire {
+            for &location in &explosion.blocks {
+                let below = self.block([location[0], location[1] - 1, location[2]]) >> 4;
+                if block::is_solid(below) && rand::random::<u8>() % 3 == 0 {
+                    self.set_block(location, block::FIRE << 4);
+                }
+            }
+        }
+        for entity in self.entities.lock().unwrap().values_mut() {
+            let size = entity.size();
+            let position = entity.position;
+            let min = [position[0] - size[0] / 2.0, position[1], position[2] - size[0] / 2.0];
+            let max = [position[0] + size[0] / 2.0, position[1] + size[1], position[2] + size[0] / 2.0];
+            let exposure = explosion.exposure(min, max, |location| self.block(location));
+            let knockback = explosion.knockback(position, explosion.impact(position, exposure));
+            for n in 0..3 {
+                entity.velocity[n] += knockback[n];
+            }
+        }
+        explosion
+    }
+
+    /// Hurts and pushes `player` according to how close they stand to
+    /// `explosion`, and shows it to them.
+    pub fn apply_explosion(&self, player: &mut Player, explosion: &Explosion) -> io::Result<()> {
+        let position = player.position();
+        let min = [position[0] - 0.3, position[1], position[2] - 0.3];
+        let max = [position[0] + 0.3, position[1] + 1.8, position[2] + 0.3];
+        let exposure = explosion.exposure(min, max, |location| self.block(location));
+        let impact = explosion.impact(position, exposure);
+        let motion = explosion.knockback(player.eye_position(), impact);
+        if impact > 0.0 {
+            try!(self.hurt(player, explosion.damage(impact), DamageSource::Explosion));
+        }
+        try!(player.send(&explosion.packet(motion)));
+        debug!("<< Explosion {:?} to {}", explosion.center, player.name());
+        Ok(())
+    }
+
+    /// Hurts `player` with `amount` half hearts of damage from `source`,
+    /// announcing their death if it kills them.
+    pub fn hurt(&self, player: &mut Player, amount: f32, source: DamageSource) -> io::Result<()> {
+        use packet::play::clientbound::PlayCombatEvent;
+
+        if let Some(attacker) = source.attacker() {
+            if !self.teams.read().unwrap().can_hurt(&attacker.name, player.name()) {
+                return Ok(());
+            }
+        }
+        let age = self.world_age();
+        if !try!(player.damage(amount, source, age)) {
+            return Ok(());
+        }
+        let killer = player.killer(age).cloned();
+        let message = DeathMessage::new(player.name(), player.last_damage().unwrap_or(&DamageSource::Generic), killer.as_ref());
+        info!("{}", message.to_text());
+        player.increment_stat("stat.deaths", 1);
+        let event = CombatEvent::EntityDead {
+            player_id: player.entity_id(),
+            entity_id: killer.map_or(-1, |killer| killer.entity_id),
+            message: message.to_text()
+        };
+        try!(player.send(&PlayCombatEvent { event: event }));
+        try!(self.drop_on_death(player));
+        if self.config.read().unwrap().game_rule_enabled("showDeathMessages") {
+            // The player is locked, so everyone hears about it next tick
+            self.queue_chat(message.to_chat(), ChatPosition::Chat);
+        }
+        Ok(())
+    }
+
+    /// Scatters the items and experience of `player`, who just died, unless
+    /// the keepInventory game rule is on.
+    fn drop_on_death(&self, player: &mut Player) -> io::Result<()> {
+        try!(self.close_window(player));
+        if self.config.read().unwrap().game_rule_enabled("keepInventory") {
+            return Ok(());
+        }
+        let mut rng = rand::thread_rng();
+        let mut position = player.eye_position();
+        position[1] -= 0.3;
+        for stack in player.take_inventory() {
+            let mut item = Entity::new(self.next_entity_id(), EntityKind::Item(stack), position);
+            let (speed, angle) = (rng.gen::<f64>() * 0.5, rng.gen::<f64>() * PI * 2.0);
+            item.velocity = [-angle.sin() * speed, 0.2, angle.cos() * speed];
+            self.spawn_entity(item);
+        }
+        try!(self.send_window(player));
+        let points = try!(player.take_experience());
+        if points > 0 {
+            self.spawn_entity(Entity::new(self.next_entity_id(), EntityKind::ExperienceOrb(points as i16), player.position()));
+        }
+        Ok(())
+    }
+
+    /// Replaces what decides players' permissions, op levels by default.
+    pub fn set_permissions(&self, permissions: Arc<PermissionProvider + Send + Sync>) {
+        *self.permissions.write().unwrap() = permissions;
+    }
+
+    /// Has serverbound packets with `id` decoded as `P` and emitted as
+    /// `Event::CustomPacket`. Ids of packets hematite knows keep being
+    /// handled by it.
+    pub fn register_packet<P: Protocol<Clean = P> + AnyPacket + 'static>(&self, id: i32) {
+        self.custom_packets.write().unwrap().register::<P>(id);
+    }
+
+    pub fn has_permission(&self, player: &Player, node: &str) -> bool {
+        let permissions = self.permissions.read().unwrap().clone();
+        permissions.has_permission(player, node)
+    }
+
+    /// Whether spawn protection keeps `player` from changing the block at
+    /// `location`.
+    pub fn spawn_protected(&self, player: &Player, location: [i32; 3]) -> bool {
+        if self.spawn_protection <= 0 || self.dimension != Dimension::Overworld {
+            return false;
+        }
+        let spawn = self.config.read().unwrap().spawn;
+        let distance = (location[0] - spawn[0]).abs().max((location[2] - spawn[2]).abs());
+        distance <= self.spawn_protection
+            && !self.has_permission(player, permissions::SPAWN_PROTECTION_BYPASS)
+    }
+
+    /// Snapshot of the teams.
+    pub fn teams(&self) -> Teams {
+        self.teams.read().unwrap().clone()
+    }
+
+    /// Changes the teams, e.g. `world.update_teams(|teams| teams.join("red", "Steve"))`.
+    pub fn update_teams<F, T>(&self, update: F) -> T where F: FnOnce(&mut Teams) -> T {
+        update(&mut self.teams.write().unwrap())
+    }
+
+    /// Snapshot of the world border.
+    pub fn border(&self) -> WorldBorder {
+        self.border.read().unwrap().clone()
+    }
+
+    /// Snapshot of the world's settings.
+    pub fn config(&self) -> WorldConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Generators derived from the world's seed, for whatever has to come
+    /// out the same for the same seed.
+    pub fn random(&self) -> WorldRandom {
+        WorldRandom::new(self.config.read().unwrap().seed)
+    }
+
+    /// Changes the world's settings with `update`, brings the border and
+    /// players up to date, tells event listeners and saves level.dat.
+    pub fn update_config<F>(&self, update: F) -> io::Result<()> where F: FnOnce(&mut WorldConfig) {
+        use packet::play::clientbound::{WorldBorder, WorldSpawn};
+
+        let (old, new) = {
+            let mut config = self.config.write().unwrap();
+            let old = config.clone();
+            update(&mut config);
+            (old, config.clone())
+        };
+        if new == old {
+            return Ok(());
+        }
+        if new.border_differs(&old) {
+            let mut border = self.border.write().unwrap();
+            World::configure_border(&mut border, &new);
+            self.queue_broadcast(WorldBorder { action: border.initialize() });
+        }
+        if new.spawn != old.spawn {
+            self.queue_broadcast(WorldSpawn { location: new.spawn });
+        }
+        if new.generator_differs(&old) {
+            // Chunks already there stay as they are
+            info!("world generator is now {:?} (seed {}), used for new chunks", new.generator, new.seed);
+        }
+        self.events.emit(&Event::WorldConfigChanged { old: &old, new: &new });
+        self.save_config()
+    }
+
+    /// Writes the world's settings to its level.dat.
+    pub fn save_config(&self) -> io::Result<()> {
+        match self.level_dat {
+            Some(ref level_dat) => {
+                let mut config = self.config.write().unwrap();
+                self.record_time(&mut config);
+                config.save(level_dat)
+            }
+            None => Ok(())
+        }
+    }
+
+    /// Has `config` hold the current time, for saving.
+    fn record_time(&self, config: &mut WorldConfig) {
+        let age = self.world_age();
+        config.time = age;
+        config.day_time = age + *self.time_offset.lock().unwrap();
+    }
+
+    /// Saves the world, then copies its directory to a new backup. Returns
+    /// where the backup was written.
+    pub fn backup(&self) -> io::Result<PathBuf> {
+        let level_dat = match self.level_dat {
+            Some(ref level_dat) => level_dat,
+            None => return Err(io::Error::new(io::ErrorKind::Other, "worlds kept in memory can't be backed up"))
+        };
+        let _backing_up = self.backing_up.lock().unwrap();
+        // Nothing in the world directory changes until it's copied: chunks
+        // are flushed and no more saved meanwhile, and holding the config
+        // keeps level.dat from being saved
+        let _saving = self.saving.lock().unwrap();
+        try!(self.flush_chunks());
+        self.record_time(&mut self.config.write().unwrap());
+        let config = self.config.read().unwrap();
+        try!(config.save(level_dat));
+        let dir = level_dat.parent().unwrap_or(Path::new("."));
+        // Named after the directory, the level name may be a path
+        let name = dir.file_name().map_or(config.level_name.clone(), |name| name.to_string_lossy().into_owned());
+        let path = self.backup_dir.join(backup::name(&name, &time::now()));
+        let files = try!(backup::create(dir, &path));
+        info!("backed up {} files of {} to {}", files, config.level_name, path.display());
+        if self.backups_kept > 0 {
+            try!(backup::prune(&self.backup_dir, &name, self.backups_kept));
+        }
+        Ok(path)
+    }
+
+    /// Saves like `save_config`, unless that would mean waiting on a lock.
+    /// Returns `None` when nothing was saved.
+    pub fn try_save_config(&self) -> Option<io::Result<()>> {
+        let level_dat = match self.level_dat {
+            Some(ref level_dat) => level_dat,
+            None => return Some(Ok(()))
+        };
+        self.config.try_write().ok().map(|mut config| {
+            self.record_time(&mut config);
+            config.save(level_dat)
+        })
+    }
+
+    /// Saves the world's settings and its chunks.
+    pub fn save(&self) -> io::Result<usize> {
+        try!(self.save_config());
+        self.save_chunks()
+    }
+
+    /// Writes the chunks generated or changed since they were last saved to
+    /// the world's region files. Returns how many were written.
+    pub fn save_chunks(&self) -> io::Result<usize> {
+        let _saving = self.saving.lock().unwrap();
+        self.flush_chunks()
+    }
+
+    /// Writes the unsaved chunks, `saving` being held.
+    fn flush_chunks(&self) -> io::Result<usize> {
+        let raw = {
+            let chunks = self.chunks.read().unwrap();
+            let mut unsaved = self.unsaved.lock().unwrap();
+            self.encode_unsaved(&chunks, &mut unsaved, |_| true)
+        };
+        self.write_regions(raw)
+    }
+
+    /// Saves like `save_chunks`, unless that would mean waiting on a lock.
+    /// Returns `None` when nothing was saved.
+    pub fn try_save_chunks(&self) -> Option<io::Result<usize>> {
+        let _saving = match self.saving.try_lock() {
+            Ok(saving) => saving,
+            Err(_) => return None
+        };
+        let raw = match (self.chunks.try_read(), self.unsaved.try_lock()) {
+            (Ok(chunks), Ok(mut unsaved)) => self.encode_unsaved(&chunks, &mut unsaved, |_| true),
+            _ => return None
+        };
+        Some(self.write_regions(raw))
+    }
+
+    /// Takes the columns of `chunks` which are `unsaved` and `which` picks
+    /// out of it, compressed for region files. Those which can't be stay
+    /// unsaved.
+    fn encode_unsaved<F>(&self, chunks: &HashMap<(i32, i32), ChunkColumn>, unsaved: &mut HashSet<(i32, i32)>, which: F) -> Vec<((i32, i32), RawChunk)>
+        where F: Fn(&(i32, i32)) -> bool
+    {
+        let age = self.world_age();
+        let timestamp = time::get_time().sec as u32;
+        let taken: Vec<_> = unsaved.iter().cloned().filter(|chunk| which(chunk)).collect();
+        let mut raw = vec![];
+        for chunk in taken {
+            unsaved.remove(&chunk);
+            let column = match chunks.get(&chunk) {
+                Some(column) => column,
+                None => continue
+            };
+            match RawChunk::from_column(chunk.0, chunk.1, column, age, timestamp) {
+                Ok(encoded) => raw.push((chunk, encoded)),
+                Err(err) => {
+                    warn!("failed encoding chunk {:?} for saving: {}", chunk, err);
+                    unsaved.insert(chunk);
+                }
+            }
+        }
+        raw
+    }
+
+    /// Unloads the columns out of view of `players` and of the spawn which
+    /// no ticket keeps loaded, saving them first. Returns how many were
+    /// unloaded.
+    fn unload_chunks(&self, players: &[(Arc<Mutex<Player>>, i32, [f64; 3], GameMode)]) -> io::Result<usize> {
+        // Entities near players tick even if simulation-distance is over
+        // view-distance
+        let radius = self.view_distance.max(self.simulation_distance);
+        let spawn = self.config.read().unwrap().spawn;
+        let mut kept: HashSet<_> = tickets::chunks_within((spawn[0] >> 4, spawn[2] >> 4), radius).into_iter().collect();
+        for &(ref player, _, position, _) in players {
+            kept.extend(tickets::chunks_within(chunk_of(position), radius));
+            // Those the client has, which it may still act on
+            if let Some(view) = player.lock().unwrap().chunk_view() {
+                kept.extend(view.sent());
+            }
+        }
+        kept.extend(self.tickets.lock().unwrap().forced_chunks());
+
+        let _saving = self.saving.lock().unwrap();
+        let (unloading, raw) = {
+            let chunks = self.chunks.read().unwrap();
+            let unloading: HashSet<_> = chunks.keys().filter(|chunk| !kept.contains(chunk)).cloned().collect();
+            if unloading.is_empty() {
+                return Ok(0);
+            }
+            let mut unsaved = self.unsaved.lock().unwrap();
+            let raw = self.encode_unsaved(&chunks, &mut unsaved, |chunk| unloading.contains(chunk));
+            (unloading, raw)
+        };
+        try!(self.write_regions(raw));
+        // Those changed meanwhile stay until they're saved
+        let unloaded: Vec<_> = {
+            let mut chunks = self.chunks.write().unwrap();
+            let unsaved = self.unsaved.lock().unwrap();
+            unloading.into_iter().filter(|chunk| !unsaved.contains(chunk) && chunks.remove(chunk).is_some()).collect()
+        };
+        let mut cache = self.chunk_cache.lock().unwrap();
+        for &chunk in &unloaded {
+            cache.invalidate(chunk);
+        }
+        Ok(unloaded.len())
+    }
+
+    /// Writes `raw` chunks to their region files, keeping the other chunks
+    /// there. Each file is written aside and renamed over the old one, so
+    /// a failure leaves it alone. Chunks which couldn't be written are
+    /// unsaved again.
+    fn write_regions(&self, raw: Vec<((i32, i32), RawChunk)>) -> io::Result<usize> {
+        let dir = match self.region_path() {
+            Some(dir) => dir,
+            None => return Ok(0)
+        };
+        let mut regions: HashMap<(i32, i32), Vec<((i32, i32), RawChunk)>> = HashMap::new();
+        for (chunk, encoded) in raw {
+            regions.entry((chunk.0 >> 5, chunk.1 >> 5)).or_insert_with(Vec::new).push((chunk, encoded));
+        }
+        let mut written = 0;
+        let mut failed = None;
+        for ((region_x, region_z), chunks) in regions {
+            if failed.is_some() {
+                self.unsaved.lock().unwrap().extend(chunks.into_iter().map(|(chunk, _)| chunk));
+                continue;
+            }
+            let path = dir.join(format!("r.{}.{}.mca", region_x, region_z));
+            let positions: Vec<_> = chunks.iter().map(|&(chunk, _)| chunk).collect();
+            let count = chunks.len();
+            match write_region(&path, chunks) {
+                Ok(()) => written += count,
+                Err(err) => {
+                    self.unsaved.lock().unwrap().extend(positions);
+                    failed = Some(io::Error::new(err.kind(), format!("{}: {}", path.display(), err)));
+                }
+            }
+        }
+        match failed {
+            Some(err) => Err(err),
+            None => Ok(written)
+        }
+    }
+
+    /// Makes `border` match the border settings of `config`.
+    fn configure_border(border: &mut WorldBorder, config: &WorldConfig) {
+        border.set_center(config.border_center[0], config.border_center[1]);
+        border.set_warning_time(config.border_warning_time);
+        border.set_warning_blocks(config.border_warning_blocks);
+        // Leave a resize in progress alone if it's heading there anyway
+        if border.target_diameter() != config.border_diameter {
+            border.set_diameter(config.border_diameter);
+        }
+    }
+
+    /// Ticks the world 20 times a second, never returns. Each world runs
+    /// this on its own thread.
+    pub fn tick_loop(&self) {
+        let tick = Duration::from_millis(50);
+        loop {
+            let start = Instant::now();
+            self.tick();
+            let elapsed = start.elapsed();
+            self.stats.lock().unwrap().record(start, elapsed);
+            if elapsed < tick {
+                sleep(tick - elapsed);
+            } else {
+                debug!("tick of {} took {:?}, can't keep up", self.config.read().unwrap().level_name, elapsed);
+            }
+        }
+    }
+
+    /// Ticks per second over the last few seconds.
+    pub fn tps(&self) -> f64 {
+        self.stats.lock().unwrap().tps()
+    }
+
+    /// When the last tick finished, `None` before the first one.
+    pub fn last_tick_end(&self) -> Option<Instant> {
+        self.stats.lock().unwrap().last_tick_end()
+    }
+
+    /// Average time the last few ticks took.
+    pub fn mean_tick_time(&self) -> Duration {
+        self.stats.lock().unwrap().mean_tick_time()
+    }
+
+    /// Number of chunk columns loaded, and how many of them tickets force
+    /// loaded.
+    pub fn chunk_counts(&self) -> (usize, usize) {
+        let forced = self.tickets.lock().unwrap().forced_chunks().len();
+        (self.chunks.read().unwrap().len(), forced)
+    }
+
+    /// Number of columns kept encoded for clients, and the share of those
+    /// sent which were encoded already.
+    pub fn chunk_cache_stats(&self) -> (usize, f64) {
+        let cache = self.chunk_cache.lock().unwrap();
+        (cache.len(), cache.hit_rate())
+    }
+
+    /// Number of entities in this world, players aside.
+    pub fn entity_count(&self) -> usize {
+        self.entities.lock().unwrap().len()
+    }
+
+    /// Number of players in this world, `None` if that can't be told
+    /// without waiting, as when crashing.
+    pub fn try_player_count(&self) -> Option<usize> {
+        self.players.try_lock().ok().map(|players| players.len())
+    }
+
+    /// The last few packets players in this world sent, `None` if they
+    /// can't be had without waiting.
+    pub fn try_recent_packets(&self) -> Option<Vec<String>> {
+        self.packet_log.try_lock().ok().map(|log| log.entries())
+    }
+
+    /// Timings of the phases of the last few ticks, oldest first.
+    pub fn recent_timings(&self) -> Vec<TickTimings> {
+        self.profiler.lock().unwrap().recent()
+    }
+
+    /// Advances the world by one tick.
+    pub fn tick(&self) {
+        use packet::play::clientbound::{AttachEntity, CollectItem, DestroyEntities, EntityHeadLook, EntityStatus};
+
+        let mut timer = PhaseTimer::start();
+        self.border.write().unwrap().tick();
+        timer.end_phase("border");
+
+        // Where everyone stands and what mobs see of them, taken before
+        // locking entities
+        let mut targets = vec![];
+        let players: Vec<_> = self.players().into_iter().map(|player| {
+            let (id, position, gamemode) = {
+                let player = player.lock().unwrap();
+                targets.push(Target {
+                    entity_id: player.entity_id(),
+                    position: player.position(),
+                    held_item: player.held_item().map(|stack| stack.id()),
+                    attackable: player.gamemode() == GameMode::Survival || player.gamemode() == GameMode::Adventure
+                });
+                (player.entity_id(), player.position(), player.gamemode())
+            };
+            (player, id, position, gamemode)
+        }).collect();
+        let simulated = self.simulated_chunks(players.iter().map(|&(_, _, position, _)| position));
+
+        let mut packets: Vec<Box<PacketWrite>> = vec![];
+        let mut explosions = vec![];
+        // Players hit by projectiles and arrows they walked over, by index
+        // into `players`
+        let mut hits = vec![];
+        // Players mobs hit, by index into `players`, with the damage and
+        // who hit them
+        let mut attacks = vec![];
+        let mut pickups = vec![];
+        let mut orbs = vec![];
+        // Players riding something, with where they sit, and players whose
+        // vehicle is gone
+        let mut seats = vec![];
+        let mut ejected = vec![];
+        let mut rng = rand::thread_rng();
+        let mut budget = navigation::TICK_BUDGET;
+        // Undead mobs burn under the open sky while the sun is up
+        let day = self.dimension == Dimension::Overworld && spawning::is_daytime(self.time_of_day());
+        {
+            let mut entities = self.entities.lock().unwrap();
+            for id in mem::replace(&mut *self.spawned.lock().unwrap(), vec![]) {
+                if let Some(entity) = entities.get(&id) {
+                    packets.extend(entity.spawn_packets());
+                }
+            }
+            for entity in entities.values_mut() {
+                if !simulated.contains(&chunk_of(entity.position)) {
+                    continue;
+                }
+                let from = entity.position;
+                let yaw = entity.yaw;
+                let block_at = |location| self.block(location);
+                if let Some(Action::Attack { target, damage }) = entity.think(&targets, &block_at, &mut rng, &mut budget) {
+                    if let Some(index) = players.iter().position(|&(_, id, _, _)| id == target) {
+                        attacks.push((index, damage, Attacker { entity_id: entity.id, name: entity.name().to_string() }));
+                    }
+                }
+                if entity.tick(&block_at) || entity.yaw != yaw {
+                    packets.push(Box::new(entity.teleport_packet()));
+                }
+                if entity.yaw != yaw {
+                    packets.push(Box::new(EntityHeadLook { entity_id: entity.id, head_yaw: entity::angle(entity.yaw) }));
+                }
+                if let EntityKind::Monster(_) = entity.kind {
+                    self.tick_monster(entity, day, &players, &mut packets);
+                }
+                if let Some(passenger) = entity.passenger {
+                    if entity.removed {
+                        ejected.push(passenger);
+                    } else {
+                        let seat = [entity.position[0], entity.position[1] + entity.seat_height(), entity.position[2]];
+                        seats.push((passenger, seat));
+                    }
+                }
+                match entity.kind {
+                    EntityKind::PrimedTnt { ref mut fuse } => {
+                        *fuse -= 1;
+                        if *fuse <= 0 {
+                            entity.removed = true;
+                            explosions.push([entity.position[0], entity.position[1] + 0.98 / 16.0, entity.position[2]]);
+                        }
+                    }
+                    EntityKind::Projectile { kind, shooter, stuck: false, .. } if !entity.removed => {
+                        for (index, &(_, id, position, gamemode)) in players.iter().enumerate() {
+                            // Don't shoot yourself on the way out
+                            if gamemode == GameMode::Spectator || (id == shooter && entity.age < 5) {
+                                continue;
+                            }
+                            let min = [position[0] - 0.3, position[1], position[2] - 0.3];
+                            let max = [position[0] + 0.3, position[1] + 1.8, position[2] + 0.3];
+                            if segment_hits(from, entity.position, min, max) {
+                                let v = entity.velocity;
+                                let speed = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+                                let damage = if kind == ObjectKind::Arrow { (speed * 2.0).ceil() as f32 } else { 0.0 };
+                                hits.push((index, damage, shooter));
+                                entity.removed = true;
+                                break;
+                            }
+                        }
+                    }
+                    EntityKind::Projectile { stuck: true, pickup: true, .. } if !entity.removed => {
+                        for (index, &(_, _, position, gamemode)) in players.iter().enumerate() {
+                            let near = (entity.position[0] - position[0]).abs() <= 1.3
+                                && (entity.position[2] - position[2]).abs() <= 1.3
+                                && entity.position[1] >= position[1] - 0.5
+                                && entity.position[1] <= position[1] + 2.3;
+                            if gamemode != GameMode::Spectator && near {
+                                pickups.push((entity.id, index));
+                                break;
+                            }
+                        }
+                    }
+                    EntityKind::ExperienceOrb(points) if !entity.removed => {
+                        for (index, &(_, _, position, gamemode)) in players.iter().enumerate() {
+                            let near = (entity.position[0] - position[0]).abs() <= 1.0
+                                && (entity.position[2] - position[2]).abs() <= 1.0
+                                && entity.position[1] >= position[1] - 0.5
+                                && entity.position[1] <= position[1] + 2.3;
+                            if gamemode != GameMode::Spectator && near {
+                                orbs.push((entity.id, index, points));
+                                break;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            let removed: Vec<i32> = entities.values().filter(|entity| entity.removed).map(|entity| entity.id).collect();
+            for id in &removed {
+                entities.remove(id);
+            }
+            if !removed.is_empty() {
+                packets.push(Box::new(DestroyEntities { entity_ids: removed }));
+            }
+        }
+        timer.end_phase("entities");
+        self.spawn_monsters(&simulated, &players, &mut rng);
+        timer.end_phase("spawning");
+        if self.dimension == Dimension::Overworld && self.weather.lock().unwrap().tick(&mut rng) {
+            packets.extend(self.weather().packets().into_iter().map(|packet| Box::new(packet) as Box<PacketWrite>));
+        }
+        self.tick_sleep(&players, &mut packets);
+        timer.end_phase("weather");
+        let batches = self.changed_blocks.lock().unwrap().drain();
+        for batch in batches {
+            packets.extend(self.block_change_packet(batch));
+        }
+        timer.end_phase("blocks");
+        for packet in mem::replace(&mut *self.queued.lock().unwrap(), vec![]) {
+            packets.push(packet);
+        }
+        for &(ref player, id, _, _) in &players {
+            if let Some(&(_, seat)) = seats.iter().find(|&&(passenger, _)| passenger == id) {
+                player.lock().unwrap().set_position(seat, false);
+            }
+            if ejected.contains(&id) {
+                player.lock().unwrap().set_vehicle(None);
+                packets.push(Box::new(AttachEntity { riding_eid: id, vehicle_eid: -1, leash: false }));
+            }
+        }
+        for packet in &packets {
+            self.broadcast(&**packet);
+        }
+        let chat = mem::replace(&mut *self.queued_chat.lock().unwrap(), vec![]);
+        if !chat.is_empty() {
+            for &(ref player, _, _, _) in &players {
+                let mut player = player.lock().unwrap();
+                for &(ref message, position) in &chat {
+                    if let Err(err) = player.send_chat(message.clone(), position) {
+                        debug!("failed sending chat to {}: {}", player.name(), err);
+                    }
+                }
+            }
+        }
+        self.deliver_whispers(&players);
+        self.deliver_spawn_points();
+        timer.end_phase("packets");
+        self.tick_block_entities(&simulated);
+        timer.end_phase("blockEntities");
+        self.tick_random_blocks(&simulated, &mut rng);
+        timer.end_phase("randomTicks");
+
+        for (index, damage, shooter) in hits {
+            let (ref player, id, _, _) = players[index];
+            if damage > 0.0 {
+                let shooter = players.iter().find(|&&(_, other, _, _)| other == shooter).map(|&(ref shooter, id, _, _)| {
+                    Attacker { entity_id: id, name: shooter.lock().unwrap().name().to_string() }
+                });
+                let result = self.hurt(&mut player.lock().unwrap(), damage, DamageSource::Arrow(shooter));
+                if let Err(err) = result {
+                    debug!("failed sending damage: {}", err);
+                }
+                self.broadcast(&EntityStatus { entity_id: id, entity_status: EntityStatusKind::Hurt });
+            }
+        }
+        for (index, damage, attacker) in attacks {
+            let (ref player, id, _, _) = players[index];
+            if let Err(err) = self.hurt(&mut player.lock().unwrap(), damage, DamageSource::Mob(attacker)) {
+                debug!("failed sending damage: {}", err);
+            }
+            self.broadcast(&EntityStatus { entity_id: id, entity_status: EntityStatusKind::Hurt });
+        }
+        timer.end_phase("hits");
+        for (arrow, index) in pickups {
+            let (ref player, id, _, _) = players[index];
+            let picked = match player.lock().unwrap().add_item(Slot::new(item::ARROW, 1, 0)) {
+                Ok(left) => left.is_none(),
+                Err(_) => false
+            };
+            if picked {
+                // Despawned, and told to everyone, on the next tick
+                if let Some(entity) = self.entities.lock().unwrap().get_mut(&arrow) {
+                    entity.removed = true;
+                }
+                self.broadcast(&CollectItem { collected_eid: arrow, collector_eid: id });
+            }
+        }
+        for (orb, index, points) in orbs {
+            let (ref player, id, _, _) = players[index];
+            {
+                // The dead don't collect the experience they just dropped
+                let mut player = player.lock().unwrap();
+                if player.dead() {
+                    continue;
+                }
+                if let Err(err) = player.add_experience(points as i32) {
+                    debug!("failed sending experience to {}: {}", player.name(), err);
+                }
+            }
+            if let Some(entity) = self.entities.lock().unwrap().get_mut(&orb) {
+                entity.removed = true;
+            }
+            self.broadcast(&CollectItem { collected_eid: orb, collector_eid: id });
+        }
+        timer.end_phase("pickups");
+
+        for center in explosions {
+            let explosion = self.create_explosion(center, entity::TNT_POWER, false);
+            for player in self.players() {
+                let mut player = player.lock().unwrap();
+                if let Err(err) = self.apply_explosion(&mut player, &explosion) {
+                    debug!("failed sending explosion to {}: {}", player.name(), err);
+                }
+            }
+        }
+        timer.end_phase("explosions");
+        let regenerate = self.config.read().unwrap().game_rule_enabled("naturalRegeneration");
+        for &(ref player, _, _, _) in &players {
+            let mut player = player.lock().unwrap();
+            let result = player.tick_food(regenerate, self.difficulty).and_then(|starve| {
+                if starve { self.hurt(&mut player, 1.0, DamageSource::Starve) } else { Ok(()) }
+            });
+            if let Err(err) = result {
+                debug!("failed sending food to {}: {}", player.name(), err);
+            }
+        }
+        timer.end_phase("food");
+        self.tick_player_movement(&players);
+        timer.end_phase("playerMovement");
+        self.tick_chunk_streaming(&players);
+        timer.end_phase("chunkStreaming");
+        let unload = {
+            let mut last_unload = self.last_unload.lock().unwrap();
+            if last_unload.elapsed() >= Duration::from_secs(UNLOAD_INTERVAL) {
+                *last_unload = Instant::now();
+                true
+            } else {
+                false
+            }
+        };
+        if unload {
+            if let Err(err) = self.unload_chunks(&players) {
+                warn!("failed unloading chunks: {}", err);
+            }
+        }
+        timer.end_phase("chunkUnloading");
+        self.tick_item_use(&players);
+        timer.end_phase("itemUse");
+        self.tick_cracks(&players);
+        timer.end_phase("digging");
+        self.tick_windows(&players);
+        timer.end_phase("windows");
+        self.tick_idle(&players);
+        timer.end_phase("idle");
+        self.tick_keep_alives(&players);
+        timer.end_phase("keepAlive");
+        self.profiler.lock().unwrap().record(timer.finish());
+    }
+
+    /// Burns undead `monster` in sunlight, `day` telling whether the sun is
+    /// up, and despawns it once far from every player.
+    fn tick_monster(&self, monster: &mut Entity, day: bool, players: &[(Arc<Mutex<Player>>, i32, [f64; 3], GameMode)], packets: &mut Vec<Box<PacketWrite>>) {
+        use packet::play::clientbound::{EntityMetadata, EntityStatus};
+
+        let far = players.iter().all(|&(_, _, position, _)| {
+            spawning::distance_squared(position, monster.position) > spawning::DESPAWN_DISTANCE.powi(2)
+        });
+        if far {
+            monster.removed = true;
+            return;
+        }
+        let feet = [monster.position[0].floor() as i32, monster.position[1].floor() as i32, monster.position[2].floor() as i32];
+        let sunlight = day && spawning::sees_sky([feet[0], feet[1] + 1, feet[2]], &|location| self.block(location));
+        let in_water = match self.block(feet) >> 4 { 8 | 9 => true, _ => false };
+        let (burning, hurt, dead) = match monster.kind {
+            EntityKind::Monster(ref mut state) => {
+                let was_burning = state.on_fire();
+                let hurt = state.tick_fire(sunlight, in_water) > 0.0;
+                (if state.on_fire() != was_burning { Some(state.on_fire()) } else { None }, hurt, state.dead())
+            }
+            _ => return
+        };
+        if dead {
+            monster.removed = true;
+            packets.push(Box::new(EntityStatus { entity_id: monster.id, entity_status: EntityStatusKind::Dead }));
+            return;
+        }
+        if hurt {
+            packets.push(Box::new(EntityStatus { entity_id: monster.id, entity_status: EntityStatusKind::Hurt }));
+        }
+        if hurt || burning.is_some() {
+            packets.push(Box::new(EntityMetadata { entity_id: monster.id, metadata: monster.metadata() }));
+        }
+    }
+
+    /// Tries spawning a monster in each of the `simulated` chunks, in the
+    /// dark and away from players, until there are as many as the cap
+    /// allows.
+    fn spawn_monsters<R: Rng>(&self, simulated: &HashSet<(i32, i32)>, players: &[(Arc<Mutex<Player>>, i32, [f64; 3], GameMode)], rng: &mut R) {
+        let enabled = self.config.read().unwrap().game_rule_enabled("doMobSpawning");
+        if !enabled || !self.spawn_monsters || self.difficulty == Difficulty::Peaceful || self.dimension != Dimension::Overworld {
+            return;
+        }
+        let mut counts = spawning::MobCounts::count(self.entities.lock().unwrap().values().map(|entity| &entity.kind));
+        let time = self.time_of_day();
+        let block_at = |location| self.block(location);
+        for &(x, z) in simulated {
+            if !counts.below_cap(spawning::MobCategory::Monster, simulated.len()) {
+                break;
+            }
+            let height = match self.chunks.read().unwrap().get(&(x, z)) {
+                Some(column) => column.chunks.len() as i32 * 16,
+                None => continue
+            };
+            if height == 0 {
+                continue;
+            }
+            let location = [x << 4 | rng.gen_range(0, 16), rng.gen_range(1, height), z << 4 | rng.gen_range(0, 16)];
+            let position = [location[0] as f64 + 0.5, location[1] as f64, location[2] as f64 + 0.5];
+            let crowded = players.iter().any(|&(_, _, player, _)| {
+                spawning::distance_squared(player, position) < spawning::MIN_PLAYER_DISTANCE.powi(2)
+            });
+            if crowded || !spawning::monster_spot(location, time, &block_at, rng) {
+                continue;
+            }
+            let kind = entity::monster::SPAWNING[rng.gen_range(0, entity::monster::SPAWNING.len())];
+            let mut monster = Entity::new(self.next_entity_id(), EntityKind::Monster(Monster::new(kind)), position);
+            monster.yaw = rng.gen_range(0.0, 360.0);
+            monster.on_ground = true;
+            monster.brain = Some(Brain::hostile(kind.attack_damage()));
+            self.spawn_entity(monster);
+            counts.add(spawning::MobCategory::Monster);
+        }
+    }
+
+    /// Skips the night once every player but spectators has slept long
+    /// enough, clearing the weather and waking everyone up. The new time,
+    /// weather and wake ups go out with the rest of the tick's `packets`.
+    fn tick_sleep(&self, players: &[(Arc<Mutex<Player>>, i32, [f64; 3], GameMode)], packets: &mut Vec<Box<PacketWrite>>) {
+        use packet::play::clientbound::{Animation, TimeUpdate};
+
+        let age = self.world_age();
+        let awake = players.iter()
+            .filter(|&&(_, _, _, gamemode)| gamemode != GameMode::Spectator)
+            .any(|&(ref player, _, _, _)| !player.lock().unwrap().slept(age));
+        if awake || players.iter().all(|&(_, _, _, gamemode)| gamemode == GameMode::Spectator) {
+            return;
+        }
+        self.skip_to_dawn();
+        packets.push(Box::new(TimeUpdate { world_age: age, time_of_day: self.time_of_day() }));
+        let was_raining = self.weather.lock().unwrap().clear();
+        if was_raining {
+            packets.extend(self.weather().packets().into_iter().map(|packet| Box::new(packet) as Box<PacketWrite>));
+        }
+        // Sleeping through the night makes the bed where players respawn
+        for &(ref player, id, _, _) in players {
+            let mut player = player.lock().unwrap();
+            if let Some(bed) = player.wake() {
+                player.set_spawn_point(Some(SpawnPoint { location: bed, forced: false }));
+                packets.push(Box::new(Animation { entity_id: id, animation: AnimationKind::LeaveBed }));
+            }
+        }
+    }
+
+    /// Puts `player` to sleep in the bed at `location` if it's night or
+    /// storming and no monsters are close, telling them otherwise.
+    fn use_bed(&self, player: &mut Player, location: [i32; 3]) -> io::Result<()> {
+        use packet::play::clientbound::UseBed;
+
+        let block = self.block(location);
+        // The head is where the foot points to
+        let head = if block & 0x8 != 0 {
+            location
+        } else {
+            let (dx, dz) = match block & 0x3 { 0 => (0, 1), 1 => (-1, 0), 2 => (0, -1), _ => (1, 0) };
+            [location[0] + dx, location[1], location[2] + dz]
+        };
+        let position = player.position();
+        let far = (position[0] - head[0] as f64 - 0.5).abs() > 3.0
+            || (position[1] - head[1] as f64).abs() > 2.0
+            || (position[2] - head[2] as f64 - 0.5).abs() > 3.0;
+        if self.dimension != Dimension::Overworld || player.bed().is_some() || far {
+            return Ok(());
+        }
+        if spawning::is_daytime(self.time_of_day()) && !self.weather.lock().unwrap().storming() {
+            return player.send_message(message::NO_SLEEP);
+        }
+        let threatened = self.entities.lock().unwrap().values().any(|entity| {
+            let monster = match entity.kind { EntityKind::Monster(_) => true, _ => false };
+            monster
+                && (entity.position[0] - head[0] as f64).abs() <= 8.0
+                && (entity.position[1] - head[1] as f64).abs() <= 5.0
+                && (entity.position[2] - head[2] as f64).abs() <= 8.0
+        });
+        if threatened {
+            return player.send_message(message::NOT_SAFE);
+        }
+        player.sleep(head, self.world_age());
+        self.queue_broadcast(UseBed { entity_id: player.entity_id(), location: head });
+        Ok(())
+    }
+
+    /// Random ticks `randomTickSpeed` blocks in every section of the
+    /// `simulated` chunks.
+    fn tick_random_blocks<R: Rng>(&self, simulated: &HashSet<(i32, i32)>, rng: &mut R) {
+        let speed = self.config.read().unwrap().game_rule("randomTickSpeed")
+            .and_then(|speed| speed.parse().ok())
+            .unwrap_or(random_ticks::DEFAULT_SPEED);
+        if speed == 0 {
+            return;
+        }
+        // Picked with the chunks locked, ticked once they aren't
+        let mut picked = vec![];
+        {
+            let chunks = self.chunks.read().unwrap();
+            for &(x, z) in simulated {
+                let column = match chunks.get(&(x, z)) {
+                    Some(column) => column,
+                    None => continue
+                };
+                for section in 0..column.chunks.len() {
+                    for _ in 0..speed {
+                        let (dx, y, dz) = (rng.gen_range(0, 16), section * 16 + rng.gen_range(0, 16), rng.gen_range(0, 16));
+                        if random_ticks::ticks_randomly(column.block(dx, y, dz) >> 4) {
+                            picked.push([x << 4 | dx as i32, y as i32, z << 4 | dz as i32]);
+                        }
+                    }
+                }
+            }
+        }
+        let block_at = |location| self.block(location);
+        for location in picked {
+            for (location, block) in random_ticks::random_tick(location, &block_at, rng) {
+                self.set_block(location, block);
+            }
+        }
+    }
+
+    /// Shows players how far along others within 32 blocks are digging, in
+    /// ten stages of cracks, and clears the cracks once they stop.
+    fn tick_cracks(&self, players: &[(Arc<Mutex<Player>>, i32, [f64; 3], GameMode)]) {
+        use packet::play::clientbound::BlockBreakAnimation;
+
+        let age = self.world_age();
+        let mut cracks = HashMap::new();
+        for &(ref player, id, _, _) in players {
+            let digging = player.lock().unwrap().digging().cloned();
+            if let Some(digging) = digging {
+                let stage = (digging.speed * digging.ticks(age) as f32 * 10.0).min(9.0).max(0.0) as i8;
+                cracks.insert(id, (digging.location, stage));
+            }
+        }
+        let changes: Vec<_> = {
+            let mut shown = self.cracks.lock().unwrap();
+            let mut changes: Vec<_> = cracks.iter()
+                .filter(|&(id, crack)| shown.get(id) != Some(crack))
+                .map(|(&id, &(location, stage))| (id, location, stage))
+                .collect();
+            changes.extend(shown.iter()
+                .filter(|&(id, _)| !cracks.contains_key(id))
+                .map(|(&id, &(location, _))| (id, location, -1)));
+            *shown = cracks;
+            changes
+        };
+        for (digger, location, stage) in changes {
+            let packet = BlockBreakAnimation { entity_id: digger, location: location, destroy_stage: stage };
+            for &(ref player, id, position, _) in players {
+                let dx = position[0] - location[0] as f64;
+                let dy = position[1] - location[1] as f64;
+                let dz = position[2] - location[2] as f64;
+                // The digger sees their own cracks already
+                if id == digger || dx * dx + dy * dy + dz * dz > 32.0 * 32.0 {
+                    continue;
+                }
+                let mut player = player.lock().unwrap();
+                if let Err(err) = player.send(&packet) {
+                    debug!("failed sending cracks to {}: {}", player.name(), err);
+                }
+            }
+        }
+    }
+
+    /// How fast `player` digs the block at `location`, see
+    /// `block::dig_speed`.
+    fn dig_speed(&self, player: &Player, location: [i32; 3]) -> f32 {
+        let age = self.world_age();
+        let eyes = player.eye_position();
+        let head = self.block([eyes[0].floor() as i32, eyes[1].floor() as i32, eyes[2].floor() as i32]) >> 4;
+        let conditions = block::DigConditions {
+            haste: player.effect(potion::HASTE, age).map(|effect| effect.amplifier),
+            mining_fatigue: player.effect(potion::MINING_FATIGUE, age).map(|effect| effect.amplifier),
+            underwater: head == 8 || head == 9,
+            aqua_affinity: player.slot(player::ARMOR_START).map_or(false, |helmet| {
+                helmet.enchantment_level(item::AQUA_AFFINITY) > 0
+            }),
+            on_ground: player.on_ground()
+        };
+        block::dig_speed(self.block(location) >> 4, player.held_item(), &conditions)
+    }
+
+    /// Gives the block `id` at `location` its block entity, unless it has
+    /// one already.
+    fn create_block_entity(&self, location: [i32; 3], id: u16) {
+        if let Some(block_entity) = BlockEntity::new(id) {
+            self.block_entities.lock().unwrap().entry(location).or_insert(block_entity);
+        }
+    }
+
+    /// Lets `player` write the sign at `location`, opening the editor for
+    /// them. Returns whether there's a sign there.
+    pub fn open_sign_editor(&self, player: &mut Player, location: [i32; 3]) -> io::Result<bool> {
+        use packet::play::clientbound::SignEditorOpen;
+
+        let id = self.block(location) >> 4;
+        if id != block::STANDING_SIGN && id != block::WALL_SIGN {
+            return Ok(false);
+        }
+        self.create_block_entity(location, id);
+        if let Some(&mut BlockEntity::Sign(ref mut sign)) = self.block_entities.lock().unwrap().get_mut(&location) {
+            sign.set_editor(player.uuid().clone());
+        }
+        try!(player.send(&SignEditorOpen { location: location }));
+        Ok(true)
+    }
+
+    /// Writes the lines `player` sent on the sign at `location`, as plain
+    /// text, if they're its editor, then shows it to everyone.
+    fn write_sign(&self, player: &mut Player, location: [i32; 3], lines: [&ChatJson; block_entity::sign::LINES]) {
+        let written = match self.block_entities.lock().unwrap().get_mut(&location) {
+            Some(&mut BlockEntity::Sign(ref mut sign)) => {
+                if sign.write(player.uuid(), lines) { Some(sign.lines().clone()) } else { None }
+            }
+            _ => None
+        };
+        match written {
+            Some(lines) => self.queue_broadcast(sign_update(location, &lines)),
+            None => debug!("{} tried writing on the sign at {:?} without editing it", player.name(), location)
+        }
+    }
+
+    /// Ticks furnaces and brewing stands, keeping players who look into them
+    /// up to date, and spills the content of block entities whose block is
+    /// gone.
+    fn tick_block_entities(&self, simulated: &HashSet<(i32, i32)>) {
+        let mut dropped = vec![];
+        // Block entities which ticked, with their new slots if they changed
+        // and whether furnaces were lit or put out
+        let mut updates = vec![];
+        {
+            let mut block_entities = self.block_entities.lock().unwrap();
+            let mut gone = vec![];
+            for (&location, block_entity) in block_entities.iter_mut() {
+                if !block_entity.belongs_to(self.block(location) >> 4) {
+                    gone.push(location);
+                    continue;
+                }
+                if !block_entity.active() || !simulated.contains(&(location[0] >> 4, location[2] >> 4)) {
+                    continue;
+                }
+                let burning = |block_entity: &BlockEntity| match *block_entity {
+                    BlockEntity::Furnace(ref furnace) => furnace.burning(),
+                    _ => false
+                };
+                let was_burning = burning(block_entity);
+                let changed = block_entity.tick();
+                let lit = if burning(block_entity) != was_burning { Some(!was_burning) } else { None };
+                let items = if changed { Some(block_entity.items()) } else { None };
+                updates.push((location, block_entity.properties(), items, lit));
+            }
+            for location in gone {
+                if let Some(block_entity) = block_entities.remove(&location) {
+                    dropped.push((location, block_entity.into_items()));
+                }
+            }
+        }
+        for (location, items) in dropped {
+            let position = [location[0] as f64 + 0.5, location[1] as f64 + 0.5, location[2] as f64 + 0.5];
+            for stack in items {
+                let mut entity = Entity::new(self.next_entity_id(), EntityKind::Item(stack), position);
+                entity.velocity = [(rand::random::<f64>() - 0.5) * 0.1, 0.2, (rand::random::<f64>() - 0.5) * 0.1];
+                self.spawn_entity(entity);
+            }
+        }
+        for &(location, _, _, lit) in &updates {
+            if let Some(lit) = lit {
+                let id = if lit { block::LIT_FURNACE } else { block::FURNACE };
+                let metadata = self.block(location) & 0xf;
+                self.set_block(location, id << 4 | metadata);
+            }
+        }
+        if updates.is_empty() {
+            return;
+        }
+        for player in self.players() {
+            let mut player = player.lock().unwrap();
+            let (window_id, location) = match player.window() {
+                Some(window) => match window.kind.block_entity() {
+                    Some(location) => (window.id, location),
+                    None => continue
+                },
+                None => continue
+            };
+            if let Some(&(_, ref properties, ref items, _)) = updates.iter().find(|update| update.0 == location) {
+                if let Err(err) = send_block_entity(&mut player, window_id, properties, items.as_ref()) {
+                    debug!("failed sending block entity to {}: {}", player.name(), err);
+                }
+            }
+        }
+    }
+
+    /// Teleports `player` back inside the world border if they've left it.
+    fn keep_inside_border(&self, player: &mut Player) -> io::Result<()> {
+        use packet::play::clientbound::PlayerPositionAndLook;
+
+        let position = player.position();
+        let border = self.border.read().unwrap();
+        if border.contains(position[0], position[2]) {
+            return Ok(());
+        }
+        let clamped = border.clamp(position);
+        player.set_position(clamped, false);
+        // Only the position is absolute, keep the player's look
+        try!(player.send(&PlayerPositionAndLook { position: clamped, yaw: 0.0, pitch: 0.0, flags: 0x18 }));
+        debug!("<< PlayerPositionAndLook {} outside world border", player.name());
+        Ok(())
+    }
+
+    pub fn gamemode(&self) -> GameMode { self.gamemode }
+    pub fn difficulty(&self) -> Difficulty { self.difficulty }
+    pub fn hardcore(&self) -> bool { self.hardcore }
+    pub fn dimension(&self) -> Dimension { self.dimension }
+
+    /// Whether the player with `uuid` is in this world.
+    pub fn has_player(&self, uuid: &Uuid) -> bool {
+        self.players.lock().unwrap().contains_key(uuid)
+    }
+
+    /// Moves the player with `uuid` to world `world`, an index into the
+    /// server's worlds, as soon as they send their next packet. Returns
+    /// whether they're in this world.
+    pub fn transfer(&self, uuid: &Uuid, world: usize) -> bool {
+        if !self.has_player(uuid) {
+            return false;
+        }
+        self.transfers.lock().unwrap().insert(uuid.clone(), world);
+        true
+    }
+
+    /// Ticks since the world was created, counting from its level.dat.
+    pub fn world_age(&self) -> i64 {
+        let end = time::get_time();
+        let elapsed = (end - self.start).num_seconds();
+        self.start_age + elapsed * 20
+    }
+
+    pub fn time_of_day(&self) -> i64 {
+        (self.world_age() + *self.time_offset.lock().unwrap()) % 24000
+    }
+
+    /// Moves time on to the next dawn, telling no one.
+    fn skip_to_dawn(&self) {
+        let time = self.time_of_day();
+        *self.time_offset.lock().unwrap() += 24000 - time;
+    }
+
+    /// Sets the time of day, in ticks since dawn, players are told on the
+    /// next tick.
+    pub fn set_time(&self, time: i64) {
+        use packet::play::clientbound::TimeUpdate;
+
+        let age = self.world_age();
+        *self.time_offset.lock().unwrap() = (time % 24000 + 24000) % 24000 - age % 24000;
+        self.queue_broadcast(TimeUpdate { world_age: age, time_of_day: self.time_of_day() });
+    }
+
+    /// Moves the time of day `ticks` on, see `set_time`.
+    pub fn add_time(&self, ticks: i64) {
+        let time = self.time_of_day();
+        self.set_time(time + ticks);
+    }
+
+    pub fn weather(&self) -> Weather {
+        self.weather.lock().unwrap().clone()
+    }
+
+    /// Replaces the weather, players are told on the next tick.
+    pub fn set_weather(&self, weather: Weather) {
+        *self.weather.lock().unwrap() = weather.clone();
+        for packet in weather.packets() {
+            self.queue_broadcast(packet);
+        }
+    }
+
+    /// Reacts to a packet sent by `player`, whose lock is held.
+    pub fn handle_packet(&self, player: &mut Player, packet: Packet) -> io::Result<()> {
+        use packet::play::clientbound::{Animation, BlockChange, Statistics};
+
+        // Only moving or looking around counts, clients keep sending
+        // where they are while standing still
+        let active = match packet {
+            Packet::ChatMessage(_) => true,
+            Packet::PlayerPosition(ref pos) => pos.position != player.position(),
+            Packet::PlayerLook(ref look) => (look.yaw, look.pitch) != (player.yaw(), player.pitch()),
+            Packet::PlayerPositionAndLook(ref pos) => pos.position != player.position() || (pos.yaw, pos.pitch) != (player.yaw(), player.pitch()),
+            _ => false
+        };
+        if active {
+            self.mark_active(player);
+        }
+        match packet {
+            Packet::KeepAlive(keep_alive) => {
+                let id = keep_alive.keep_alive_id;
+                let answered = player.keep_alive_mut().map_or(false, |pending| pending.answer(id, Instant::now()));
+                if answered {
+                    use packet::play::clientbound::UpdatePlayerList;
+
+                    let latency = vec![(player.uuid().clone(), player.ping())];
+                    self.queue_broadcast(UpdatePlayerList { action: PlayerListAction::UpdateLatency(latency) });
+                } else {
+                    debug!("ignoring unexpected keep-alive {} from {}", id, player.name());
+                }
+            }
+            Packet::ResourcePackStatus(status) => {
+                debug!(">> ResourcePackStatus {} {:?}", player.name(), status.result);
+                player.set_resource_pack(status.result);
+                self.events.emit(&Event::ResourcePackStatus { player: player, result: status.result });
+            }
+            Packet::ChatMessage(chat) => {
+                debug!(">> ChatMessage {} {:?}", player.name(), chat.message);
+                if chat.message.starts_with('/') {
+                    try!(self.run_command(player, &chat.message[1..]));
+                } else {
+                    let mut message = chat.message;
+                    if try!(self.check_chat(player, &mut message)) {
+                        self.events.emit(&Event::Chat { player: player, message: &message });
+                        let line = format!("<{}> {}", player.name(), message);
+                        info!("{}", line);
+                        self.queue_chat(ChatJson::from(line), ChatPosition::Chat);
+                    }
+                }
+            }
+            Packet::ClientStatus(status) => {
+                debug!(">> ClientStatus {} {:?}", player.name(), status.action);
+                match status.action {
+                    ClientStatusAction::PerformRespawn if player.dead() => try!(self.respawn(player)),
+                    ClientStatusAction::RequestStats => {
+                        let stats = player.stats();
+                        try!(player.send(&Statistics { stats: stats }));
+                        debug!("<< Statistics");
+                    }
+                    _ => {}
+                }
+            }
+            Packet::ClientSettings(settings) => {
+                debug!(">> ClientSettings {:?}", settings);
+                player.set_chat_mode(settings.chat_mode);
+            }
+            // Riders go wherever their vehicle goes
+            Packet::PlayerPosition(pos) => {
+                if player.vehicle().is_none() {
+                    player.walk(pos.position, pos.on_ground);
+                    try!(self.keep_inside_border(player));
+                }
+            }
+            Packet::PlayerLook(look) => player.set_look(look.yaw, look.pitch, look.on_ground),
+            Packet::PlayerPositionAndLook(pos) => {
+                if player.vehicle().is_none() {
+                    player.walk(pos.position, pos.on_ground);
+                    try!(self.keep_inside_border(player));
+                }
+                player.set_look(pos.yaw, pos.pitch, pos.on_ground);
+            }
+            Packet::PlayerAbilities(abilities) => {
+                if !player.set_flying(abilities.flags & player::FLYING != 0) {
+                    try!(player.send_abilities());
+                }
+            }
+            Packet::UseEntity(use_entity) => {
+                match use_entity.use_type {
+                    EntityUseAction::Interact => try!(self.interact(player, use_entity.target_eid)),
+                    // Only armor stands care where they were clicked
+                    EntityUseAction::InteractAt { target } => try!(self.use_armor_stand(player, use_entity.target_eid, target[1])),
+                    EntityUseAction::Attack => try!(self.attack(player, use_entity.target_eid))
+                }
+            }
+            Packet::SteerVehicle(steer) => {
+                // Bit 0x2 is sneak, which gets players off their vehicle
+                if steer.flags & 0x2 != 0 {
+                    try!(self.dismount(player));
+                } else {
+                    self.steer(player, steer.forward, steer.sideways);
+                }
+            }
+            Packet::EntityAction(action) => {
+                match action.action {
+                    EntityActionKind::StartSneaking => {
+                        player.set_sneaking(true);
+                        try!(self.dismount(player));
+                    }
+                    EntityActionKind::StopSneaking => player.set_sneaking(false),
+                    EntityActionKind::LeaveBed => {
+                        if player.wake().is_some() {
+                            self.queue_broadcast(Animation { entity_id: player.entity_id(), animation: AnimationKind::LeaveBed });
+                        }
+                    }
+                    EntityActionKind::StartSprinting => {
+                        // Hungry clients shouldn't sprint, resending the food bar stops them
+                        if !player.start_sprinting() {
+                            try!(player.send_health());
+                        }
+                    }
+                    EntityActionKind::StopSprinting => player.stop_sprinting(),
+                    EntityActionKind::JumpWithHorse => self.jump_horse(player, action.jump_boost),
+                    EntityActionKind::OpenRiddenHorseInventory => {
+                        if let Some(vehicle) = player.vehicle() {
+                            try!(self.open_window(player, WindowKind::Horse(vehicle)));
+                        }
+                    }
+                }
+            }
+            Packet::HeldItemChange(change) => {
+                // Switching items stops eating or drawing a bow
+                if player.stop_using_item().is_some() {
+                    self.update_player_metadata(player);
+                }
+                player.set_held_slot(change.slot as usize);
+            }
+            Packet::PlayerBlockPlacement(ref placement) if placement.direction == BlockFace::Special => {
+                // Right click in the air
+                match player.held_item().map(|stack| stack.id()) {
+                    Some(id) if id == item::SNOWBALL || id == item::EGG => {
+                        let kind = if id == item::EGG { ObjectKind::Egg } else { ObjectKind::Snowball };
+                        self.launch(player, kind, 1.5, false);
+                        if player.gamemode() != GameMode::Creative {
+                            player.take_held_item(false);
+                        }
+                    }
+                    Some(item::BOW) => {
+                        if player.gamemode() == GameMode::Creative || player.has_item(item::ARROW) {
+                            self.start_using_item(player);
+                        }
+                    }
+                    Some(id) if player.can_eat(id) => self.start_using_item(player),
+                    Some(item::MILK_BUCKET) => self.start_using_item(player),
+                    Some(item::POTION) if !potion::is_splash(player.held_item().unwrap().damage()) => {
+                        self.start_using_item(player);
+                    }
+                    _ => {}
+                }
+            }
+            Packet::PlayerBlockPlacement(placement) => {
+                let location = placement.location;
+                let clicked = self.block(location) >> 4;
+                let center = [location[0] as f64 + 0.5, location[1] as f64, location[2] as f64 + 0.5];
+                match player.held_item().map(|stack| stack.id()) {
+                    // Sneaking players use their item on containers instead
+                    _ if (clicked == block::FURNACE || clicked == block::LIT_FURNACE) && !player.sneaking() => {
+                        self.create_block_entity(location, clicked);
+                        try!(self.open_window(player, WindowKind::Furnace(location)));
+                    }
+                    _ if clicked == block::BREWING_STAND && !player.sneaking() => {
+                        self.create_block_entity(location, clicked);
+                        try!(self.open_window(player, WindowKind::BrewingStand(location)));
+                    }
+                    _ if clicked == block::BED && !player.sneaking() => try!(self.use_bed(player, location)),
+                    _ if clicked == block::ANVIL && !player.sneaking() => {
+                        try!(self.open_window(player, WindowKind::Anvil(Anvil::new(location))));
+                    }
+                    Some(item::FLINT_AND_STEEL) | Some(item::FIRE_CHARGE)
+                        if clicked == block::TNT && self.spawn_protected(player, location) => {}
+                    Some(item::FLINT_AND_STEEL) if clicked == block::TNT => {
+                        self.ignite_tnt(location, entity::TNT_FUSE);
+                        player.wear_held_item();
+                    }
+                    Some(item::FIRE_CHARGE) if clicked == block::TNT => {
+                        self.ignite_tnt(location, entity::TNT_FUSE);
+                        if player.gamemode() != GameMode::Creative {
+                            player.take_held_item(false);
+                        }
+                    }
+                    Some(item::BOAT) => {
+                        self.place_vehicle(player, ObjectKind::Boat, [center[0], center[1] + 1.0, center[2]]);
+                    }
+                    Some(item::ITEM_FRAME) => self.hang_item_frame(player, location, placement.direction),
+                    Some(item::ARMOR_STAND) if placement.direction == BlockFace::Up => {
+                        self.place_armor_stand(player, [center[0], center[1] + 1.0, center[2]]);
+                    }
+                    Some(item::MINECART) if block::is_rail(clicked) => {
+                        self.place_vehicle(player, ObjectKind::Minecart, [center[0], center[1] + 0.0625, center[2]]);
+                    }
+                    Some(item::SPAWN_EGG) if player.held_item().map(|stack| stack.damage()) == Some(MobKind::Horse as i16) => {
+                        let horse = EntityKind::Horse(Horse::random());
+                        let mut entity = Entity::new(self.next_entity_id(), horse, [center[0], center[1] + 1.0, center[2]]);
+                        entity.brain = Some(Brain::passive());
+                        self.spawn_entity(entity);
+                        if player.gamemode() != GameMode::Creative {
+                            player.take_held_item(false);
+                        }
+                    }
+                    Some(item::SPAWN_EGG) => {
+                        let kind = player.held_item().and_then(|stack| MobKind::from_i16(stack.damage())).and_then(MonsterKind::from_mob_kind);
+                        if let Some(kind) = kind {
+                            let position = [center[0], center[1] + 1.0, center[2]];
+                            let mut entity = Entity::new(self.next_entity_id(), EntityKind::Monster(Monster::new(kind)), position);
+                            entity.brain = Some(Brain::hostile(kind.attack_damage()));
+                            self.spawn_entity(entity);
+                            if player.gamemode() != GameMode::Creative {
+                                player.take_held_item(false);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Packet::ClickWindow(click) => try!(self.click_window(player, click)),
+            Packet::PluginMessage(ref message) => match &message.channel[..] {
+                plugin_message::BRAND => {
+                    let brand = try!(plugin_message::decode_brand(&message.data));
+                    info!("{} is using client {}", player.name(), brand);
+                    player.set_brand(brand);
+                }
+                plugin_message::ITEM_NAME => try!(self.rename_in_anvil(player, &message.data)),
+                channel => debug!("{} sent a message on unknown channel {}", player.name(), channel)
+            },
+            Packet::CloseWindow(close) => {
+                if close.window_id == player.window_id() {
+                    try!(self.close_window(player));
+                } else {
+                    debug!("ignoring {} closing window {}, which isn't open", player.name(), close.window_id);
+                }
+            }
+            Packet::ConfirmTransaction(confirm) => {
+                if confirm.accepted && !player.apologize(confirm.window_id, confirm.action_number) {
+                    debug!("{} confirmed transaction {} which wasn't rejected", player.name(), confirm.action_number);
+                }
+            }
+            Packet::UpdateSign(ref update) => {
+                self.write_sign(player, update.location, [&update.line0, &update.line1, &update.line2, &update.line3]);
+            }
+            Packet::CreativeInventoryAction(action) => {
+                if player.gamemode() == GameMode::Creative && action.slot >= 0 {
+                    player.set_slot(action.slot as usize, action.clicked_item);
+                }
+            }
+            Packet::PlayerDigging(dig) => {
+                debug!(">> PlayerDigging {} {:?} {:?}", player.name(), dig.status, dig.location);
+                match dig.status {
+                    DiggingStatus::StartedDigging | DiggingStatus::FinishedDigging
+                        if self.spawn_protected(player, dig.location) => {
+                        player.stop_digging();
+                        try!(player.send(&BlockChange { location: dig.location, block_id: self.block(dig.location) as i32 }));
+                    }
+                    DiggingStatus::StartedDigging => {
+                        let speed = self.dig_speed(player, dig.location);
+                        if player.gamemode() == GameMode::Creative || speed >= 1.0 {
+                            self.set_block(dig.location, 0);
+                            player.add_exhaustion(0.025);
+                        } else if speed > 0.0 {
+                            player.start_digging(dig.location, dig.face, self.world_age(), speed);
+                        }
+                    }
+                    DiggingStatus::CancelledDigging => {
+                        player.stop_digging();
+                    }
+                    DiggingStatus::FinishedDigging => {
+                        match player.stop_digging() {
+                            // Vanilla breaks blocks 70% dug, and the client may be a tick ahead
+                            Some(ref digging) if digging.location == dig.location &&
+                                                 digging.speed * (digging.ticks(self.world_age()) + 1) as f32 >= 0.7 => {
+                                debug!("{} dug {:?} in {} ticks", player.name(), dig.location, digging.ticks(self.world_age()));
+                                self.set_block(dig.location, 0);
+                                player.add_exhaustion(0.025);
+                            }
+                            _ => {
+                                // Client finished a block it never started or
+                                // dug too fast, resync it
+                                try!(player.send(&BlockChange { location: dig.location, block_id: self.block(dig.location) as i32 }));
+                            }
+                        }
+                    }
+                    DiggingStatus::DropItemStack | DiggingStatus::DropItem => {
+                        if let Some(stack) = player.take_held_item(dig.status == DiggingStatus::DropItemStack) {
+                            try!(self.drop_item(player, stack));
+                        }
+                    }
+                    DiggingStatus::ShootArrowFinishEating => {
+                        // Eating finishes on the server's tick, clients only
+                        // send this for bows or to give up eating
+                        if let Some(started) = player.stop_using_item() {
+                            self.update_player_metadata(player);
+                            if player.held_item().map(|stack| stack.id()) == Some(item::BOW) {
+                                let ticks = self.world_age() - started;
+                                try!(self.shoot_arrow(player, ticks));
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Joins a player who just logged in, then handles their packets until
+    /// they leave.
+    pub fn handle_player(&self, mut stream: SymmStream, mut player: Player) -> io::Result<Departure> {
+        use packet::play::serverbound::Packet::ClientSettings;
+        use packet::play::clientbound::{JoinGame, KeepAlive};
+        use packet::play::clientbound::{PlayerAbilities, PlayerPositionAndLook, ResourcePackSend};
+        use packet::play::clientbound::{PluginMessage, ServerDifficulty};
+
+        // FIXME(toqueteos): We need:
+        // - An id generator, can't use UUID here
+        // - Read world info from disk
+        // - Read some keypairs from server.properties
+        player.set_entity_id(self.next_entity_id());
+        player.set_gamemode(self.gamemode);
+        let config = self.config();
+        try!(JoinGame {
+            entity_id: player.entity_id(),
+            gamemode: GameModeFlags { gamemode: player.gamemode(), hardcore: self.hardcore },
+            dimension: self.dimension,
+            difficulty: self.difficulty,
+            max_players: self.max_players,
+            level_type: config.generator.clone(),
+            reduced_debug_info: false
+        }.write(&mut stream));
+        debug!("<< JoinGame");
+        // try!(stream.flush());
+
+        try!(ServerDifficulty { difficulty: self.difficulty }.write(&mut stream));
+        debug!("<< ServerDifficulty");
+
+        // WRITE `MC|Brand` plugin
+        try!(PluginMessage {
+            channel: plugin_message::BRAND.to_string(),
+            data: plugin_message::encode_brand(&self.brand)
+        }.write(&mut stream));
+        debug!("<< PluginMessage");
+        // try!(stream.flush());
+
+        // WRITE supported channels
+        try!(PluginMessage {
+            channel: "REGISTER".to_string(),
+            data: plugin_message::register_data()
+        }.write(&mut stream));
+        debug!("<< PluginMessage");
+        // try!(stream.flush());
+
+        let mut view = ChunkView::new(self.view_distance);
+        try!(self.send_world(&mut stream, &config, &mut view));
+        player.set_chunk_view(view);
+
+        // Send Abilities
+        try!(PlayerAbilities {
+            flags: player.abilities(),
+            flying_speed: 0.05,
+            walking_speed: 0.1
+        }.write(&mut stream));
+        debug!("<< PlayerAbilities");
+        try!(stream.flush());
+
+        // // Send Inventory items
+        // let wi = ClientWindowItems {
+        //     window_id: 0,
+        //     slots: repeat(EMPTY_SLOT).take(45).collect()
+        // };
+        // try!(wi.write(&mut stream));
+        debug!("<< WindowItems (not sent)");
+        // try!(stream.flush());
+
+        // Players new to the world start at the spawn
+        let data = self.load_player(player.uuid());
+        let position = data.position.unwrap_or_else(|| self.safe_spawn(&config));
+        let (yaw, pitch) = data.rotation.map_or((0.0, 0.0), |rotation| (rotation[0], rotation[1]));
+        player.set_position(position, false);
+        player.set_look(yaw, pitch, false);
+        player.set_spawn_point(data.spawn_point);
+        try!(PlayerPositionAndLook {
+            position: position,
+            yaw: yaw,
+            pitch: pitch,
+            flags: 0
+        }.write(&mut stream));
+        debug!("<< PlayerPositionAndLook");
+        // try!(stream.flush());
+
+        // Read Client Settings
+        let settings = if self.skip_unknown_packets {
+            Packet::read_skipping_unknown(&mut stream)
+        } else {
+            Packet::read(&mut stream)
+        };
+        match try!(settings) {
+            settings @ ClientSettings(_) => try!(self.handle_packet(&mut player, settings)),
+            wrong_packet => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          &format!("Expecting play::serverbound::ClientSettings packet, got {:?}", wrong_packet)[..]));
+            }
+        }
+
+        try!(player.send_message("Server: Welcome to hematite server!"));
+        debug!("<< ChatMessage");
+
+        if let Some((ref url, ref hash)) = self.resource_pack {
+            try!(ResourcePackSend { url: url.clone(), hash: hash.clone() }.write(&mut stream));
+            debug!("<< ResourcePackSend");
+        }
+
+        // Send first Keep Alive, the next ones are sent on ticks
+        let (interval, timeout) = self.keep_alive;
+        let mut keep_alive = keep_alive::KeepAlive::new(interval, timeout);
+        if let keep_alive::Poll::Send(id) = keep_alive.poll(Instant::now(), rand::random()) {
+            try!(KeepAlive { keep_alive_id: id }.write(&mut stream));
+            debug!("<< KeepAlive");
+        }
+        player.set_keep_alive(keep_alive);
+        try!(stream.flush());
+
+        // From now on everything sent to the player goes through its lock
+        self.enter(stream, Arc::new(Mutex::new(player)), true)
+    }
+
+    /// Takes in a player coming from another world, then handles their
+    /// packets until they leave.
+    pub fn arrive(&self, mut stream: SymmStream, player: Arc<Mutex<Player>>) -> io::Result<Departure> {
+        use packet::play::clientbound::{PlayerPositionAndLook, Respawn};
+
+        let config = self.config();
+        {
+            let mut player = player.lock().unwrap();
+            // Clients ignore respawning in the dimension they're in, going
+            // through another one makes them drop the old world's chunks
+            let other = if self.dimension == Dimension::Overworld { Dimension::Nether } else { Dimension::Overworld };
+            for &dimension in &[other, self.dimension] {
+                try!(player.send(&Respawn {
+                    dimension: dimension,
+                    difficulty: self.difficulty,
+                    gamemode: self.gamemode,
+                    level_type: config.generator.clone()
+                }));
+            }
+            debug!("<< Respawn {} into {}", player.name(), config.level_name);
+            player.set_gamemode(self.gamemode);
+            let mut view = ChunkView::new(self.view_distance);
+            try!(self.send_world(&mut stream, &config, &mut view));
+            player.set_chunk_view(view);
+            try!(player.send_abilities());
+            let position = self.safe_spawn(&config);
+            player.set_position(position, false);
+            // Still looking the same way
+            let (yaw, pitch) = (player.yaw(), player.pitch());
+            try!(player.send(&PlayerPositionAndLook { position: position, yaw: yaw, pitch: pitch, flags: 0 }));
+            try!(stream.flush());
+        }
+        self.enter(stream, player, false)
+    }
+
+    /// Where players without a position of their own appear: on ground near
+    /// the spawn, or on a platform built at the spawn if there's none.
+    fn safe_spawn(&self, config: &WorldConfig) -> [f64; 3] {
+        let spot = match safe_spawn::find(config.spawn, &|location| self.block(location)) {
+            Some(spot) => spot,
+            None => {
+                info!("no ground near the spawn of {}, building a platform", config.level_name);
+                for (location, block) in safe_spawn::platform(config.spawn) {
+                    self.set_block(location, block);
+                }
+                config.spawn
+            }
+        };
+        [spot[0] as f64 + 0.5, spot[1] as f64, spot[2] as f64 + 0.5]
+    }
+
+    /// What this world remembers about player `uuid`, nothing if they're
+    /// new or the world isn't saved.
+    fn load_player(&self, uuid: &Uuid) -> PlayerData {
+        let path = match self.level_dat {
+            Some(ref level_dat) => PlayerData::path(level_dat, uuid),
+            None => return PlayerData::default()
+        };
+        match PlayerData::load(&path) {
+            Ok(data) => data,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => PlayerData::default(),
+            Err(err) => {
+                warn!("failed reading {}, starting afresh: {}", path.display(), err);
+                PlayerData::default()
+            }
+        }
+    }
+
+    /// Saves where `player` is and respawns, keeping the rest of their data
+    /// as it was.
+    fn save_player(&self, player: &Player) -> io::Result<()> {
+        let path = match self.level_dat {
+            Some(ref level_dat) => PlayerData::path(level_dat, player.uuid()),
+            None => return Ok(())
+        };
+        let mut data = self.load_player(player.uuid());
+        data.position = Some(player.position());
+        data.rotation = Some([player.yaw(), player.pitch()]);
+        data.spawn_point = player.spawn_point();
+        data.save(&path)
+    }
+
+    /// Where `player` respawns: next to their bed, at the spawn point they
+    /// were given, or at the world spawn if the bed is gone.
+    fn respawn_position(&self, player: &mut Player, config: &WorldConfig) -> io::Result<[f64; 3]> {
+        let spawn_point = match player.spawn_point() {
+            Some(spawn_point) => spawn_point,
+            None => return Ok(self.safe_spawn(config))
+        };
+        let block_at = |location| self.block(location);
+        let location = spawn_point.location;
+        let spot = match safe_spawn::bed_exit(location, &block_at) {
+            Some(spot) => Some(spot),
+            None if spawn_point.forced && self.block(location) >> 4 != block::BED => {
+                let free = |location: [i32; 3]| !block::is_solid(self.block(location) >> 4);
+                if free(location) && free([location[0], location[1] + 1, location[2]]) { Some(location) } else { None }
+            }
+            None => None
+        };
+        match spot {
+            Some(spot) => Ok([spot[0] as f64 + 0.5, spot[1] as f64, spot[2] as f64 + 0.5]),
+            None => {
+                try!(player.send_message(message::BED_MISSING));
+                Ok(self.safe_spawn(config))
+            }
+        }
+    }
+
+    /// Brings `player` back to life after they died.
+    fn respawn(&self, player: &mut Player) -> io::Result<()> {
+        use packet::play::clientbound::{PlayerPositionAndLook, Respawn};
+
+        let config = self.config();
+        let position = try!(self.respawn_position(player, &config));
+        player.respawn();
+        try!(player.send(&Respawn {
+            dimension: self.dimension,
+            difficulty: self.difficulty,
+            gamemode: player.gamemode(),
+            level_type: config.generator.clone()
+        }));
+        debug!("<< Respawn {}", player.name());
+        player.set_position(position, false);
+        try!(player.send(&PlayerPositionAndLook { position: position, yaw: 0.0, pitch: 0.0, flags: 0 }));
+        try!(player.send_health());
+        player.send_experience()
+    }
+
+    /// Sends the chunks, entities, spawn, border, time and weather of this
+    /// world to a player joining it, recording the chunks sent in `view`.
+    /// The rest of the chunks around them are streamed once they're in.
+    fn send_world(&self, stream: &mut Write, config: &WorldConfig, view: &mut ChunkView) -> io::Result<()> {
+        use packet::play::clientbound::{TimeUpdate, WorldBorder, WorldSpawn};
+
+        // Loaded chunks within view distance of the spawn, a few per packet
+        // like vanilla
+        let center = (config.spawn[0] >> 4, config.spawn[2] >> 4);
+        let columns: Vec<_> = {
+            let chunks = self.chunks.read().unwrap();
+            chunks.keys()
+                .filter(|&&(x, z)| (x - center.0).abs() <= view.radius() && (z - center.1).abs() <= view.radius())
+                .filter_map(|&chunk| self.encoded_column(&chunks, chunk).map(|column| (chunk, column)))
+                .collect()
+        };
+        for batch in columns.chunks(CHUNKS_PER_PACKET) {
+            try!(try!(chunk_cache::chunk_data_bulk(self.dimension == Dimension::Overworld, batch)).write(stream));
+            debug!("<< ChunkDataBulk");
+        }
+        for &(chunk, _) in &columns {
+            view.mark_sent(chunk);
+        }
+
+        for entity in self.entities.lock().unwrap().values() {
+            for packet in entity.spawn_packets() {
+                try!(packet.write(stream));
+            }
+        }
+        for (&location, block_entity) in self.block_entities.lock().unwrap().iter() {
+            if let BlockEntity::Sign(ref sign) = *block_entity {
+                try!(sign_update(location, sign.lines()).write(stream));
+            }
+        }
+        // try!(stream.flush());
+
+        // Send Compass
+        try!(WorldSpawn { location: config.spawn }.write(stream));
+        debug!("<< WorldSpawn");
+        // try!(stream.flush());
+
+        try!(WorldBorder { action: self.border().initialize() }.write(stream));
+        debug!("<< WorldBorder");
+
+        // Send Time
+        try!(TimeUpdate {
+            world_age: self.world_age(),
+            time_of_day: self.time_of_day()
+        }.write(stream));
+        debug!("<< TimeUpdate");
+        // try!(stream.flush());
+
+        // Send Weather
+        for packet in self.weather().packets() {
+            try!(packet.write(stream));
+            debug!("<< ChangeGameState Weather");
+        }
+        // try!(stream.flush());
+
+        Ok(())
+    }
+
+    /// Registers `player`, handles their packets until they leave and
+    /// unregisters them. Players `joining` the server, rather than coming
+    /// from another world, are announced, as are those leaving it.
+    fn enter(&self, stream: SymmStream, player: Arc<Mutex<Player>>, joining: bool) -> io::Result<Departure> {
+        let (uuid, name) = {
+            let player = player.lock().unwrap();
+            (player.uuid().clone(), player.name().to_string())
+        };
+        self.players.lock().unwrap().insert(uuid.clone(), player.clone());
+        self.names.lock().unwrap().insert(name.to_lowercase(), (uuid.clone(), name.clone()));
+        self.list_player(&player, &uuid);
+        self.show_player(&player, &uuid);
+        if joining {
+            self.events.emit(&Event::PlayerJoin { player: &player.lock().unwrap() });
+            self.announce(&self.join_message, &name);
+        }
+        // Whatever they sent that panicked costs them their connection, and
+        // the crash hook leaves the server running
+        let result = match panic::catch_unwind(AssertUnwindSafe(|| self.play(stream, &player, &uuid))) {
+            Ok(result) => result,
+            Err(_) => {
+                player.clear_poison();
+                Err(io::Error::new(io::ErrorKind::Other, format!("handling packets from {} panicked", name)))
+            }
+        };
+        self.depart(&player, &uuid, &name, result.as_ref().map_or(false, |moved| moved.is_some()));
+        match result {
+            Ok(Some((world, stream))) => return Ok(Departure::Transfer { world: world, stream: stream, player: player }),
+            Ok(None) => {}
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => {}
+            Err(err) => {
+                self.leave(&player, &name);
+                return Err(err);
+            }
+        }
+        self.leave(&player, &name);
+        Ok(Departure::Disconnected)
+    }
+
+    /// Undoes `enter` for `player`, however they left: gets them off their
+    /// vehicle and out of their window, saves their data, takes them off
+    /// the world's registries, removes their chunk tickets, and takes them
+    /// off everyone's tab list and out of sight. When `moving` to another
+    /// world, this world's players are taken off their tab list too.
+    fn depart(&self, player: &Mutex<Player>, uuid: &Uuid, name: &str, moving: bool) {
+        {
+            let mut player = player.lock().unwrap();
+            let _ = self.dismount(&mut player);
+            let _ = self.close_window(&mut player);
+            if let Err(err) = self.save_player(&player) {
+                warn!("failed saving the data of {}: {}", name, err);
+            }
+        }
+        self.players.lock().unwrap().remove(uuid);
+        self.names.lock().unwrap().remove(&name.to_lowercase());
+        self.transfers.lock().unwrap().remove(uuid);
+        let released = self.remove_chunk_tickets(&tickets::player_source(uuid));
+        if released > 0 {
+            debug!("released {} chunk tickets of {}", released, name);
+        }
+        self.unlist_player(player, uuid, moving);
+        self.hide_player(player);
+    }
+
+    /// Adds `player` to the tab list of everyone in this world, themselves
+    /// included, and everyone else to theirs.
+    fn list_player(&self, player: &Mutex<Player>, uuid: &Uuid) {
+        use packet::play::clientbound::UpdatePlayerList;
+
+        let entry = player.lock().unwrap().list_entry();
+        self.broadcast(&UpdatePlayerList { action: PlayerListAction::AddPlayer(vec![entry]) });
+        let others: Vec<_> = self.players().into_iter()
+            .map(|other| other.lock().unwrap().list_entry())
+            .filter(|entry| &entry.uuid != uuid)
+            .collect();
+        if !others.is_empty() {
+            let mut player = player.lock().unwrap();
+            if let Err(err) = player.send(&UpdatePlayerList { action: PlayerListAction::AddPlayer(others) }) {
+                debug!("failed sending the player list to {}: {}", player.name(), err);
+            }
+        }
+    }
+
+    /// Takes `player`, who's no longer in this world, off everyone's tab
+    /// list, and when `moving` to another world takes this world's players
+    /// off theirs.
+    fn unlist_player(&self, player: &Mutex<Player>, uuid: &Uuid, moving: bool) {
+        use packet::play::clientbound::UpdatePlayerList;
+
+        self.broadcast(&UpdatePlayerList { action: PlayerListAction::RemovePlayer(vec![uuid.clone()]) });
+        if moving {
+            let others: Vec<_> = self.profiles().into_iter().map(|(_, uuid)| uuid).collect();
+            if !others.is_empty() {
+                let _ = player.lock().unwrap().send(&UpdatePlayerList { action: PlayerListAction::RemovePlayer(others) });
+            }
+        }
+    }
+
+    /// Spawns `player` for everyone else in this world, and everyone else
+    /// for them.
+    fn show_player(&self, player: &Mutex<Player>, uuid: &Uuid) {
+        let spawn = player.lock().unwrap().spawn_packet();
+        let mut others = vec![];
+        for other in self.players() {
+            let mut other = other.lock().unwrap();
+            if other.uuid() == uuid {
+                continue;
+            }
+            others.push(other.spawn_packet());
+            if let Err(err) = other.send(&spawn) {
+                debug!("failed sending to {}: {}", other.name(), err);
+            }
+        }
+        let mut player = player.lock().unwrap();
+        for packet in others {
+            if let Err(err) = player.send(&packet) {
+                debug!("failed spawning players for {}: {}", player.name(), err);
+                break;
+            }
+        }
+    }
+
+    /// Despawns `player`, who's no longer in this world, for everyone in it.
+    fn hide_player(&self, player: &Mutex<Player>) {
+        use packet::play::clientbound::DestroyEntities;
+
+        let id = player.lock().unwrap().entity_id();
+        self.broadcast(&DestroyEntities { entity_ids: vec![id] });
+    }
+
+    /// Sends where players moved or looked to everyone else in the world.
+    fn tick_player_movement(&self, players: &[(Arc<Mutex<Player>>, i32, [f64; 3], GameMode)]) {
+        for &(ref player, id, _, _) in players {
+            let packets = player.lock().unwrap().movement_packets();
+            if packets.is_empty() {
+                continue;
+            }
+            for &(ref other, other_id, _, _) in players {
+                if other_id == id {
+                    continue;
+                }
+                let mut other = other.lock().unwrap();
+                for packet in &packets {
+                    if let Err(err) = other.send(&**packet) {
+                        debug!("failed sending to {}: {}", other.name(), err);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sends players the chunks coming within view distance as they move,
+    /// loading them if need be, and has them drop those going out of it.
+    fn tick_chunk_streaming(&self, players: &[(Arc<Mutex<Player>>, i32, [f64; 3], GameMode)]) {
+        for &(ref player, _, position, _) in players {
+            let center = chunk_of(position);
+            let (missing, stale) = match player.lock().unwrap().chunk_view_mut() {
+                Some(view) => (view.missing(center, chunk_stream::CHUNKS_PER_TICK), view.take_stale(center)),
+                None => continue
+            };
+            if missing.is_empty() && stale.is_empty() {
+                continue;
+            }
+            // Loaded and encoded with no player locked
+            self.load_chunks(missing.clone());
+            let columns: Vec<_> = {
+                let chunks = self.chunks.read().unwrap();
+                missing.into_iter().filter_map(|chunk| self.encoded_column(&chunks, chunk).map(|column| (chunk, column))).collect()
+            };
+            let mut player = player.lock().unwrap();
+            if let Err(err) = send_chunks(&mut player, columns, stale) {
+                debug!("failed streaming chunks to {}: {}", player.name(), err);
+            }
+        }
+    }
+
+    /// Announces player `name` left the server.
+    fn leave(&self, player: &Mutex<Player>, name: &str) {
+        self.events.emit(&Event::PlayerQuit { player: &player.lock().unwrap() });
+        self.announce(&self.quit_message, name);
+    }
+
+    /// Tells everyone about player `name` joining or leaving, as
+    /// `announcement` says.
+    fn announce(&self, announcement: &Announcement, name: &str) {
+        if let Some(message) = announcement.to_chat(name) {
+            self.queue_chat(message, ChatPosition::Chat);
+        }
+    }
+
+    /// Reads and handles packets sent by `player` until the connection
+    /// drops, or until they're moved to another world, whose index is
+    /// returned along with the connection.
+    fn play(&self, mut stream: SymmStream, player: &Mutex<Player>, uuid: &Uuid) -> io::Result<Option<(usize, SymmStream)>> {
+        let name = player.lock().unwrap().name().to_string();
+
+        let (chat, movement) = self.rate_limits;
+        let mut limiter = RateLimiter::new(chat, movement);
+
+        // BLOCK OF SHAME
+        loop {
+            // Read whole frames so packets we can't decode yet are skipped
+            let buf = try!(read_frame(&mut stream));
+            let (id, _) = try!(Var::<i32>::decode_from(&buf));
+            // We could add a filter here, chat messages might be info!, position packets are debug!, etc...
+            debug!("id={} length={} buf={:?}", packet_name(id), buf.len(), buf);
+            self.packet_log.lock().unwrap().record(&name, packet_name(id), buf.len());
+            match PacketGroup::of(id).map_or(Verdict::Allow, |group| limiter.check(group, Instant::now())) {
+                Verdict::Allow => {}
+                Verdict::Drop => {
+                    debug!("dropping {} from {}, over the rate limit", packet_name(id), name);
+                    continue;
+                }
+                Verdict::Kick => {
+                    warn!("{} sent {} too fast, kicking them", name, packet_name(id));
+                    try!(player.lock().unwrap().kick(message::SPAM));
+                    try!(stream.shutdown(Shutdown::Both));
+                    return Ok(None);
+                }
+            }
+            match Packet::inner_decode(&mut io::Cursor::new(&buf[..])) {
+                Ok(packet) => try!(self.handle_packet(&mut player.lock().unwrap(), packet)),
+                Err(ref err) if packet::unknown_id(err).is_some() && self.custom_packets.read().unwrap().contains(id) => {
+                    let custom = self.custom_packets.read().unwrap().inner_decode(&mut io::Cursor::new(&buf[..]));
+                    match custom {
+                        Ok(packet) => self.events.emit(&Event::CustomPacket { player: &player.lock().unwrap(), packet: &*packet }),
+                        Err(err) => debug!("skipping custom packet {:#04x} from {}: {}", id, name, err)
+                    }
+                }
+                Err(err) => {
+                    if packet::unknown_id(&err).is_some() && !self.skip_unknown_packets {
+                        return Err(err);
+                    }
+                    debug!("skipping {} from {}: {}", packet_name(id), name, err);
+                }
+            }
+
+            if let Some(world) = self.transfers.lock().unwrap().remove(uuid) {
+                return Ok(Some((world, stream)));
+            }
+
+            sleep(Duration::from_millis(15));
+        }
+        // /BLOCK OF SHAME
+    }
+}
+
+/// Sends the progress of the furnace or brewing stand `player` is looking
+/// into and, when they changed, its slots.
+fn send_block_entity(player: &mut Player, window_id: u8, properties: &[i16], items: Option<&Vec<Option<Slot>>>) -> io::Result<()> {
+    use packet::play::clientbound::{SetSlot, WindowProperty};
+
+    for (property, &value) in properties.iter().enumerate() {
+        try!(player.send(&WindowProperty { window_id: window_id, property: property as i16, value: value }));
+    }
+    if let Some(items) = items {
+        for (slot, stack) in items.iter().enumerate() {
+            try!(player.send(&SetSlot { window_id: window_id, slot: slot as i16, data: stack.clone() }));
+        }
+    }
+    Ok(())
+}
+
+/// Runs `click` on a copy of a window whose own slots hold `container`,
+/// above `player`'s inventory. When the click is valid and the client
+/// predicted the same, `player`'s inventory and cursor take the result and
+/// the window's own slots are returned along with what was thrown out.
+fn click_slots(player: &mut Player, container: Vec<Option<Slot>>, rules: &window::Rules,
+               click: &ClickWindow) -> Option<(Vec<Option<Slot>>, Vec<Slot>)> {
+    let inventory = (player::MAIN_START..player::INVENTORY_SIZE).map(|index| player.slot(index).cloned()).collect();
+    let creative = player.gamemode() == GameMode::Creative;
+    let mut view = window::View::new(container, inventory, player.cursor().cloned(), rules, creative);
+    // Plain clicks carry what the client saw in the slot
+    let predicted = click.mode != 0 || click.slot < 0 ||
+        view.slot(click.slot as usize) == click.clicked_item.as_ref();
+    let mut drag = player.take_drag();
+    let valid = view.click(click.slot, click.button, click.mode, &mut drag);
+    player.set_drag(drag);
+    if !valid || !predicted {
+        return None;
+    }
+    let (container, inventory, cursor, dropped) = view.into_parts();
+    for (index, stack) in inventory.into_iter().enumerate() {
+        player.set_slot(player::MAIN_START + index, stack);
+    }
+    player.set_cursor(cursor);
+    Some((container, dropped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    use byteorder::{BigEndian, ReadBytesExt};
+
+    use mca::SECTOR_BYTES;
+    use types::Chunk;
+
+    /// An empty world directory in the temporary directory, named after
+    /// `test`.
+    fn world_dir(test: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("hematite-{}-{}", test, ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("region")).unwrap();
+        dir
+    }
+
+    fn world(dir: &Path) -> World {
+        let props = Properties { level_name: dir.to_string_lossy().into_owned(), ..Properties::default() };
+        World::new(&props, Arc::new(EventBus::new()), Arc::new(MuteList::in_memory())).unwrap()
+    }
+
+    #[test]
+    fn damaged_chunks() {
+        const WOOL: u16 = 35 << 4 | 14;
+
+        // Three chunks by the spawn, the last of them damaged
+        let dir = world_dir("damaged-chunks");
+        let column = ChunkColumn { chunks: vec![Chunk::new(WOOL, 0xff)], biomes: Some([1; 256]) };
+        let mut file = McaFile::new();
+        for x in 0..3 {
+            file.set_chunk(x, 0, Some(RawChunk::from_column(x, 0, &column, 0, 0).unwrap()));
+        }
+        let mut data = vec![];
+        file.write(&mut data).unwrap();
+        let offset = (&data[8..12]).read_u32::<BigEndian>().unwrap() as usize >> 8;
+        data[offset * SECTOR_BYTES + 4] = 9;
+        File::create(dir.join("region/r.0.0.mca")).unwrap().write_all(&data).unwrap();
+
+        // The others are still there, only the damaged one is generated
+        let world = world(&dir);
+        assert_eq!(world.block([0, 0, 0]), WOOL);
+        assert_eq!(world.block([16, 0, 0]), WOOL);
+        assert_eq!(world.block([32, 0, 0]) >> 4, 7);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saving() {
+        const WOOL: u16 = 35 << 4 | 14;
+
+        let dir = world_dir("saving");
+        let world = world(&dir);
+        // Everything generated is saved, then only what changes
+        assert!(world.save_chunks().unwrap() > 0);
+        assert_eq!(world.save_chunks().unwrap(), 0);
+        world.set_block([-20, 100, 3], WOOL);
+        assert_eq!(world.save_chunks().unwrap(), 1);
+        assert!(dir.join("region/r.-1.0.mca").exists());
+        assert_eq!(world.try_save_chunks().map(|saved| saved.unwrap()), Some(0));
+
+        let reloaded = self::world(&dir);
+        assert_eq!(reloaded.block([-20, 100, 3]), WOOL);
+        assert_eq!(reloaded.save_chunks().unwrap(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unloading() {
+        const WOOL: u16 = 35 << 4 | 14;
+
+        let dir = world_dir("unloading");
+        let world = world(&dir);
+        world.add_chunk_ticket(Ticket::new("test", (40, 40), 0));
+        world.set_block([640, 100, 640], WOOL);
+        // Kept while the ticket is there, or anywhere near the spawn
+        assert_eq!(world.unload_chunks(&[]).unwrap(), 0);
+        let loaded = world.chunk_counts().0;
+
+        // Saved on the way out
+        world.remove_chunk_tickets("test");
+        assert_eq!(world.unload_chunks(&[]).unwrap(), 1);
+        assert_eq!(world.chunk_counts().0, loaded - 1);
+        assert_eq!(world.block([640, 100, 640]), 0);
+        world.add_chunk_ticket(Ticket::new("test", (40, 40), 0));
+        assert_eq!(world.block([640, 100, 640]), WOOL);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn backups_flush_chunks() {
+        let dir = world_dir("backups");
+        let props = Properties {
+            level_name: dir.join("world").to_string_lossy().into_owned(),
+            backup_directory: dir.join("backups").to_string_lossy().into_owned(),
+            ..Properties::default()
+        };
+        let world = World::new(&props, Arc::new(EventBus::new()), Arc::new(MuteList::in_memory())).unwrap();
+        world.set_block([5, 100, 5], 1 << 4);
+        let backup = world.backup().unwrap();
+        assert!(backup.starts_with(dir.join("backups")) && backup.exists());
+        // The backup has every chunk as it is
+        assert_eq!(world.save_chunks().unwrap(), 0);
+        assert!(dir.join("world/region/r.0.0.mca").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,48 @@
+//! Log of recently received packets, for crash reports.
+
+use std::collections::VecDeque;
+
+use time;
+
+/// Number of packets kept.
+pub const CAPACITY: usize = 50;
+
+/// The last few packets players sent, oldest first.
+pub struct PacketLog {
+    entries: VecDeque<String>
+}
+
+impl PacketLog {
+    pub fn new() -> PacketLog {
+        PacketLog { entries: VecDeque::with_capacity(CAPACITY) }
+    }
+
+    /// Notes that `player` sent packet `name`, `len` bytes long.
+    pub fn record(&mut self, player: &str, name: &str, len: usize) {
+        if self.entries.len() == CAPACITY {
+            self.entries.pop_front();
+        }
+        let now = time::strftime("%H:%M:%S", &time::now()).unwrap_or_default();
+        self.entries.push_back(format!("[{}] {} >> {} ({} bytes)", now, player, name, len));
+    }
+
+    pub fn entries(&self) -> Vec<String> {
+        self.entries.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_last_packets() {
+        let mut log = PacketLog::new();
+        for i in 0..CAPACITY + 3 {
+            log.record("Notch", &format!("packet {}", i), 1);
+        }
+        let entries = log.entries();
+        assert_eq!(entries.len(), CAPACITY);
+        assert!(entries[0].ends_with("Notch >> packet 3 (1 bytes)"));
+    }
+}
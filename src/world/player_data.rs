@@ -0,0 +1,144 @@
+//! What a world remembers about players between visits, kept in
+//! `playerdata/<uuid>.dat` next to level.dat like vanilla.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use player::SpawnPoint;
+use types::hyphenated;
+
+use flate2::read::GzDecoder;
+use nbt;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlayerData {
+    /// Where the player was when they left.
+    pub position: Option<[f64; 3]>,
+    /// Yaw and pitch the player was looking at.
+    pub rotation: Option<[f32; 2]>,
+    /// Where the player respawns, the world spawn if not set.
+    pub spawn_point: Option<SpawnPoint>,
+    /// Entries hematite doesn't use, kept as they were.
+    pub other: HashMap<String, nbt::Value>
+}
+
+impl PlayerData {
+    /// Path of the data of player `uuid` in the world whose level.dat is at
+    /// `level_dat`.
+    pub fn path(level_dat: &Path, uuid: &Uuid) -> PathBuf {
+        level_dat.parent().unwrap_or(Path::new("."))
+            .join("playerdata")
+            .join(format!("{}.dat", hyphenated(uuid)))
+    }
+
+    /// Reads the gzipped player data at `path`.
+    pub fn load(path: &Path) -> io::Result<PlayerData> {
+        let file = try!(File::open(path));
+        let mut src = try!(GzDecoder::new(BufReader::new(file)));
+        match try!(nbt::Value::read_header(&mut src).and_then(|(id, _)| nbt::Value::from_reader(id, &mut src))) {
+            nbt::Value::Compound(root) => Ok(PlayerData::from_data(root)),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "player data root isn't a compound"))
+        }
+    }
+
+    /// Writes the player data gzipped to `path`, creating its directory if
+    /// needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            try!(fs::create_dir_all(dir));
+        }
+        let mut blob = nbt::Blob::new("".to_string());
+        for (name, value) in self.to_data() {
+            try!(blob.insert(name, value));
+        }
+        let mut dst = BufWriter::new(try!(File::create(path)));
+        Ok(try!(blob.write_gzip(&mut dst)))
+    }
+
+    fn from_data(mut data: HashMap<String, nbt::Value>) -> PlayerData {
+        let position = match data.remove("Pos") {
+            Some(nbt::Value::List(ref pos)) if pos.len() == 3 => match (&pos[0], &pos[1], &pos[2]) {
+                (&nbt::Value::Double(x), &nbt::Value::Double(y), &nbt::Value::Double(z)) => Some([x, y, z]),
+                _ => None
+            },
+            _ => None
+        };
+        let rotation = match data.remove("Rotation") {
+            Some(nbt::Value::List(ref rotation)) if rotation.len() == 2 => match (&rotation[0], &rotation[1]) {
+                (&nbt::Value::Float(yaw), &nbt::Value::Float(pitch)) => Some([yaw, pitch]),
+                _ => None
+            },
+            _ => None
+        };
+        let forced = match data.remove("SpawnForced") {
+            Some(nbt::Value::Byte(forced)) => forced != 0,
+            _ => false
+        };
+        let spawn_point = match (data.remove("SpawnX"), data.remove("SpawnY"), data.remove("SpawnZ")) {
+            (Some(nbt::Value::Int(x)), Some(nbt::Value::Int(y)), Some(nbt::Value::Int(z))) => {
+                Some(SpawnPoint { location: [x, y, z], forced: forced })
+            }
+            _ => None
+        };
+        PlayerData { position: position, rotation: rotation, spawn_point: spawn_point, other: data }
+    }
+
+    fn to_data(&self) -> HashMap<String, nbt::Value> {
+        let mut data = self.other.clone();
+        if let Some(position) = self.position {
+            let pos = position.iter().map(|&coordinate| nbt::Value::Double(coordinate)).collect();
+            data.insert("Pos".to_string(), nbt::Value::List(pos));
+        }
+        if let Some(rotation) = self.rotation {
+            let rotation = rotation.iter().map(|&angle| nbt::Value::Float(angle)).collect();
+            data.insert("Rotation".to_string(), nbt::Value::List(rotation));
+        }
+        if let Some(spawn_point) = self.spawn_point {
+            data.insert("SpawnX".to_string(), nbt::Value::Int(spawn_point.location[0]));
+            data.insert("SpawnY".to_string(), nbt::Value::Int(spawn_point.location[1]));
+            data.insert("SpawnZ".to_string(), nbt::Value::Int(spawn_point.location[2]));
+            data.insert("SpawnForced".to_string(), nbt::Value::Byte(spawn_point.forced as i8));
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::fs;
+
+    use player::SpawnPoint;
+
+    use nbt;
+    use uuid::Uuid;
+
+    #[test]
+    fn round_trip() {
+        let level_dat = env::temp_dir().join("hematite_player_data").join("level.dat");
+        let uuid = Uuid::new_v4();
+        let path = PlayerData::path(&level_dat, &uuid);
+        assert!(path.ends_with(format!("playerdata/{}.dat", hyphenated(&uuid))));
+
+        let mut data = PlayerData {
+            position: Some([1.5, 64.0, -3.5]),
+            rotation: Some([90.0, -12.5]),
+            spawn_point: Some(SpawnPoint { location: [10, 70, 10], forced: true }),
+            other: HashMap::new()
+        };
+        data.other.insert("XpLevel".to_string(), nbt::Value::Int(5));
+        data.save(&path).unwrap();
+        assert_eq!(PlayerData::load(&path).unwrap(), data);
+        // A spawn point is only taken as a whole
+        let mut partial = data.to_data();
+        partial.remove("SpawnZ");
+        assert_eq!(PlayerData::from_data(partial).spawn_point, None);
+
+        fs::remove_dir_all(level_dat.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,53 @@
+//! Plugin channels the server speaks, and their payloads.
+
+use std::io;
+
+use packet::Protocol;
+
+pub const BRAND: &'static str = "MC|Brand";
+pub const ITEM_NAME: &'static str = "MC|ItemName";
+
+/// Channels the server understands, announced to clients on REGISTER.
+pub const CHANNELS: &'static [&'static str] = &[BRAND, ITEM_NAME];
+
+/// Payload of REGISTER, the channel names each followed by a NUL.
+pub fn register_data() -> Vec<u8> {
+    let mut data = vec![];
+    for channel in CHANNELS {
+        data.extend(channel.as_bytes());
+        data.push(0);
+    }
+    data
+}
+
+/// Brand the server goes by in the client's debug screen, `brand`
+/// followed by the hematite version.
+pub fn server_brand(brand: &str) -> String {
+    format!("{} {}", brand, env!("CARGO_PKG_VERSION"))
+}
+
+/// Payload of MC|Brand, which is sent as a string.
+pub fn encode_brand(brand: &str) -> Vec<u8> {
+    let mut data = vec![];
+    // Writing to a Vec can't fail
+    <String as Protocol>::proto_encode(&brand.to_string(), &mut data).unwrap();
+    data
+}
+
+pub fn decode_brand(data: &[u8]) -> io::Result<String> {
+    <String as Protocol>::proto_decode(&mut io::Cursor::new(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brand_roundtrip() {
+        let data = encode_brand("vanilla");
+        assert_eq!(data[0], 7);
+        assert_eq!(decode_brand(&data).unwrap(), "vanilla");
+        assert!(decode_brand(&[]).is_err());
+        assert_eq!(register_data(), b"MC|Brand\0MC|ItemName\0".to_vec());
+    }
+}
@@ -0,0 +1,184 @@
+//! Tick phase timings, and the reports `/debug` makes out of them.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Number of recent ticks whose timings are kept.
+pub const HISTORY: usize = 100;
+
+/// How long each phase of a tick took, in the order they ran.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TickTimings {
+    pub phases: Vec<(&'static str, Duration)>
+}
+
+impl TickTimings {
+    /// Time spent in the whole tick.
+    pub fn total(&self) -> Duration {
+        self.phases.iter().fold(Duration::from_millis(0), |total, &(_, duration)| total + duration)
+    }
+}
+
+/// Times the phases of one tick as it runs.
+pub struct PhaseTimer {
+    phase_start: Instant,
+    phases: Vec<(&'static str, Duration)>
+}
+
+impl PhaseTimer {
+    pub fn start() -> PhaseTimer {
+        PhaseTimer { phase_start: Instant::now(), phases: vec![] }
+    }
+
+    /// Ends phase `name`, which started when the last one ended.
+    pub fn end_phase(&mut self, name: &'static str) {
+        let now = Instant::now();
+        self.phases.push((name, now.duration_since(self.phase_start)));
+        self.phase_start = now;
+    }
+
+    pub fn finish(self) -> TickTimings {
+        TickTimings { phases: self.phases }
+    }
+}
+
+/// Keeps the timings of recent ticks, and sums them up while a profiling
+/// session is running.
+pub struct Profiler {
+    recent: VecDeque<TickTimings>,
+    session: Option<Session>
+}
+
+struct Session {
+    started: Instant,
+    ticks: u64,
+    /// Total time spent in each phase, in the order phases first ran.
+    phases: Vec<(&'static str, Duration)>
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler { recent: VecDeque::with_capacity(HISTORY), session: None }
+    }
+
+    pub fn record(&mut self, timings: TickTimings) {
+        if let Some(ref mut session) = self.session {
+            session.ticks += 1;
+            for &(name, duration) in &timings.phases {
+                match session.phases.iter_mut().find(|phase| phase.0 == name) {
+                    Some(phase) => phase.1 += duration,
+                    None => session.phases.push((name, duration))
+                }
+            }
+        }
+        if self.recent.len() == HISTORY {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(timings);
+    }
+
+    /// Timings of the last few ticks, oldest first.
+    pub fn recent(&self) -> Vec<TickTimings> {
+        self.recent.iter().cloned().collect()
+    }
+
+    pub fn running(&self) -> bool { self.session.is_some() }
+
+    /// Starts a profiling session, returns false if one is already running.
+    pub fn start(&mut self) -> bool {
+        if self.session.is_some() {
+            return false;
+        }
+        self.session = Some(Session { started: Instant::now(), ticks: 0, phases: vec![] });
+        true
+    }
+
+    /// Stops the running profiling session, and reports on it.
+    pub fn stop(&mut self) -> Option<Report> {
+        self.session.take().map(|session| Report {
+            elapsed: session.started.elapsed(),
+            ticks: session.ticks,
+            phases: session.phases
+        })
+    }
+}
+
+/// What a profiling session measured.
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub elapsed: Duration,
+    pub ticks: u64,
+    pub phases: Vec<(&'static str, Duration)>
+}
+
+impl Report {
+    pub fn ticks_per_second(&self) -> f64 {
+        let seconds = as_secs(self.elapsed);
+        if seconds > 0.0 { self.ticks as f64 / seconds } else { 0.0 }
+    }
+
+    /// The report laid out like vanilla's profiler results.
+    pub fn to_text(&self) -> String {
+        let total = self.phases.iter().fold(0.0, |total, &(_, duration)| total + as_secs(duration));
+        let percent = |duration: Duration| if total > 0.0 { as_secs(duration) / total * 100.0 } else { 0.0 };
+        let mut text = String::new();
+        text.push_str("---- Minecraft Profiler Results ----\n\n");
+        text.push_str(&format!("Time span: {} ms\n", self.elapsed.as_secs() * 1000 + self.elapsed.subsec_nanos() as u64 / 1000000));
+        text.push_str(&format!("Tick span: {} ticks\n", self.ticks));
+        text.push_str(&format!("// This is approximately {:.2} ticks per second. It should be 20 ticks per second\n\n",
+                               self.ticks_per_second()));
+        text.push_str("--- BEGIN PROFILE DUMP ---\n\n");
+        text.push_str(&format!("[00] tick({}/1) - 100.00%/100.00%\n", self.ticks));
+        for &(name, duration) in &self.phases {
+            let share = percent(duration);
+            text.push_str(&format!("[01] |   {}({}/1) - {:.2}%/{:.2}%\n", name, self.ticks, share, share));
+        }
+        text.push_str("\n--- END PROFILE DUMP ---\n");
+        text
+    }
+}
+
+fn as_secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1e9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    fn timings(entities: u64, blocks: u64) -> TickTimings {
+        TickTimings { phases: vec![("entities", Duration::from_millis(entities)), ("blocks", Duration::from_millis(blocks))] }
+    }
+
+    #[test]
+    fn session() {
+        let mut profiler = Profiler::new();
+        profiler.record(timings(10, 10));
+        assert!(profiler.stop().is_none());
+        assert!(profiler.start());
+        assert!(!profiler.start());
+        profiler.record(timings(30, 10));
+        profiler.record(timings(30, 10));
+        let report = profiler.stop().unwrap();
+        assert!(!profiler.running());
+        assert_eq!(report.ticks, 2);
+        assert_eq!(report.phases, vec![("entities", Duration::from_millis(60)), ("blocks", Duration::from_millis(20))]);
+        let text = report.to_text();
+        assert!(text.contains("[01] |   entities(2/1) - 75.00%/75.00%"));
+        assert!(text.contains("[01] |   blocks(2/1) - 25.00%/25.00%"));
+    }
+
+    #[test]
+    fn keeps_recent_ticks() {
+        let mut profiler = Profiler::new();
+        for i in 0..(HISTORY as u64 + 5) {
+            profiler.record(timings(i, 0));
+        }
+        let recent = profiler.recent();
+        assert_eq!(recent.len(), HISTORY);
+        assert_eq!(recent[0], timings(5, 0));
+        assert_eq!(recent[0].total(), Duration::from_millis(5));
+    }
+}
@@ -0,0 +1,167 @@
+//! Randomness derived from the world seed, so that generating a world with
+//! the same seed gives the same world every time. Randomness which doesn't
+//! shape the world, like keep-alive ids, keeps using `rand::thread_rng`.
+
+use rand::{self, RngCore};
+
+const MULTIPLIER: i64 = 0x5DEECE66D;
+const MASK: i64 = (1 << 48) - 1;
+
+/// Java's `java.util.Random`, which vanilla's world generation is built on,
+/// so that seeds give worlds like vanilla's.
+#[derive(Clone, Debug)]
+pub struct JavaRandom {
+    seed: i64
+}
+
+impl JavaRandom {
+    pub fn new(seed: i64) -> JavaRandom {
+        JavaRandom { seed: (seed ^ MULTIPLIER) & MASK }
+    }
+
+    /// The next `bits` random bits, at most 32.
+    fn next(&mut self, bits: u32) -> i32 {
+        self.seed = self.seed.wrapping_mul(MULTIPLIER).wrapping_add(0xB) & MASK;
+        (self.seed >> (48 - bits)) as i32
+    }
+
+    pub fn next_int(&mut self) -> i32 {
+        self.next(32)
+    }
+
+    /// A number from 0 up to `bound` excluded, which must be positive.
+    pub fn next_int_bounded(&mut self, bound: i32) -> i32 {
+        assert!(bound > 0, "bound must be positive");
+        if bound & -bound == bound {
+            return ((bound as i64 * self.next(31) as i64) >> 31) as i32;
+        }
+        loop {
+            let bits = self.next(31);
+            let value = bits % bound;
+            // Retry the few values which would make low numbers likelier
+            if bits.wrapping_sub(value).wrapping_add(bound - 1) >= 0 {
+                return value;
+            }
+        }
+    }
+
+    pub fn next_long(&mut self) -> i64 {
+        ((self.next(32) as i64) << 32).wrapping_add(self.next(32) as i64)
+    }
+
+    pub fn next_bool(&mut self) -> bool {
+        self.next(1) != 0
+    }
+
+    /// A number from 0 up to 1 excluded.
+    pub fn next_float(&mut self) -> f32 {
+        self.next(24) as f32 / (1 << 24) as f32
+    }
+
+    /// A number from 0 up to 1 excluded.
+    pub fn next_double(&mut self) -> f64 {
+        (((self.next(26) as i64) << 27) + self.next(27) as i64) as f64 / (1i64 << 53) as f64
+    }
+}
+
+impl RngCore for JavaRandom {
+    fn next_u32(&mut self) -> u32 {
+        self.next_int() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.next_long() as u64
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let value = self.next_u32();
+            for (index, byte) in chunk.iter_mut().enumerate() {
+                *byte = (value >> (index * 8)) as u8;
+            }
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Hands out generators derived from a world seed, each part of the world
+/// getting its own so that the order things are generated in doesn't
+/// matter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldRandom {
+    seed: i64
+}
+
+impl WorldRandom {
+    pub fn new(seed: i64) -> WorldRandom {
+        WorldRandom { seed: seed }
+    }
+
+    pub fn seed(&self) -> i64 { self.seed }
+
+    /// A generator seeded with the world seed, like the one vanilla shapes
+    /// terrain with.
+    pub fn world(&self) -> JavaRandom {
+        JavaRandom::new(self.seed)
+    }
+
+    /// Seed of chunk (`x`, `z`), derived from the world seed like vanilla
+    /// does when populating chunks.
+    pub fn chunk_seed(&self, x: i32, z: i32) -> i64 {
+        let mut rng = self.world();
+        let a = rng.next_long() / 2 * 2 + 1;
+        let b = rng.next_long() / 2 * 2 + 1;
+        (x as i64).wrapping_mul(a).wrapping_add((z as i64).wrapping_mul(b)) ^ self.seed
+    }
+
+    /// A generator for chunk (`x`, `z`).
+    pub fn chunk(&self, x: i32, z: i32) -> JavaRandom {
+        JavaRandom::new(self.chunk_seed(x, z))
+    }
+
+    /// A generator for chunk (`x`, `z`) to use for one `feature`, say
+    /// caves, which doesn't follow the others' draws.
+    pub fn feature(&self, feature: &str, x: i32, z: i32) -> JavaRandom {
+        let salt = feature.bytes().fold(0i64, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as i64));
+        JavaRandom::new(self.chunk_seed(x, z) ^ salt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+
+    #[test]
+    fn java_random() {
+        // As given by java.util.Random
+        assert_eq!(JavaRandom::new(0).next_int(), -1155484576);
+        assert_eq!(JavaRandom::new(42).next_int(), -1170105035);
+        assert_eq!(JavaRandom::new(0).next_long(), -4962768465676381896);
+        assert_eq!(JavaRandom::new(42).next_int_bounded(10), 0);
+        let mut rng = JavaRandom::new(7);
+        for _ in 0..1000 {
+            let value = rng.next_int_bounded(13);
+            assert!(value >= 0 && value < 13);
+            let value = rng.next_double();
+            assert!(value >= 0.0 && value < 1.0);
+        }
+        // Usable wherever an Rng is
+        let value: u8 = JavaRandom::new(1).gen_range(0, 5);
+        assert!(value < 5);
+    }
+
+    #[test]
+    fn derived_generators() {
+        let random = WorldRandom::new(1234);
+        assert_eq!(random.chunk(3, -4).next_long(), WorldRandom::new(1234).chunk(3, -4).next_long());
+        assert!(random.chunk_seed(3, -4) != random.chunk_seed(-4, 3));
+        assert!(random.chunk_seed(0, 0) != WorldRandom::new(1235).chunk_seed(0, 0));
+        assert!(random.feature("caves", 1, 1).next_long() != random.chunk(1, 1).next_long());
+    }
+}
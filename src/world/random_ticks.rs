@@ -0,0 +1,289 @@
+//! Random ticks, which make grass spread, crops and saplings grow and ice
+//! melt.
+//!
+//! Each tick, `randomTickSpeed` blocks are picked at random in every section
+//! of the chunks being simulated, and those which do something on random
+//! ticks get to. Chunks don't hold light yet, so grass and plants grow
+//! whatever the light, and ice melts by what light nearby blocks give off.
+
+use block;
+
+use rand::Rng;
+
+/// Blocks ticked per section per tick when the `randomTickSpeed` game rule
+/// is missing or not a number.
+pub const DEFAULT_SPEED: u32 = 3;
+
+pub const GRASS: u16 = 2;
+pub const DIRT: u16 = 3;
+pub const SAPLING: u16 = 6;
+pub const LOG: u16 = 17;
+pub const LEAVES: u16 = 18;
+pub const WHEAT: u16 = 59;
+pub const FARMLAND: u16 = 60;
+pub const ICE: u16 = 79;
+pub const CARROTS: u16 = 141;
+pub const POTATOES: u16 = 142;
+pub const LEAVES2: u16 = 161;
+pub const LOG2: u16 = 162;
+
+/// Most block light ice stands without melting.
+const ICE_LIGHT: u8 = 8;
+
+/// Whether block `id` does something on random ticks.
+pub fn ticks_randomly(id: u16) -> bool {
+    match id {
+        GRASS | SAPLING | WHEAT | ICE | CARROTS | POTATOES => true,
+        _ => false
+    }
+}
+
+/// Random ticks the block at `location`. Returns the blocks to change, as
+/// `id << 4 | metadata`.
+pub fn random_tick<F, R>(location: [i32; 3], block_at: &F, rng: &mut R) -> Vec<([i32; 3], u16)>
+    where F: Fn([i32; 3]) -> u16, R: Rng
+{
+    let block = block_at(location);
+    match block >> 4 {
+        GRASS => grass(location, block_at, rng),
+        SAPLING => sapling(location, block & 0xf, block_at, rng),
+        WHEAT | CARROTS | POTATOES => crop(location, block, block_at, rng),
+        ICE => ice(location, block_at),
+        _ => vec![]
+    }
+}
+
+fn above(location: [i32; 3], by: i32) -> [i32; 3] {
+    [location[0], location[1] + by, location[2]]
+}
+
+/// Whether grass can grow under block `id`.
+fn lets_grass_grow(id: u16) -> bool {
+    !block::is_solid(id) && !(8 <= id && id <= 11)
+}
+
+/// Grass dies under solid blocks, and spreads to dirt nearby otherwise.
+fn grass<F, R>(location: [i32; 3], block_at: &F, rng: &mut R) -> Vec<([i32; 3], u16)>
+    where F: Fn([i32; 3]) -> u16, R: Rng
+{
+    if !lets_grass_grow(block_at(above(location, 1)) >> 4) {
+        return vec![(location, DIRT << 4)];
+    }
+    let mut changes = vec![];
+    for _ in 0..4 {
+        let spot = [
+            location[0] + rng.gen_range(-1, 2),
+            location[1] + rng.gen_range(-3, 2),
+            location[2] + rng.gen_range(-1, 2)
+        ];
+        if block_at(spot) == DIRT << 4 && lets_grass_grow(block_at(above(spot, 1)) >> 4) && !changes.iter().any(|&(changed, _)| changed == spot) {
+            changes.push((spot, GRASS << 4));
+        }
+    }
+    changes
+}
+
+/// Crops on farmland grow a stage now and then, faster when it's wet.
+fn crop<F, R>(location: [i32; 3], block: u16, block_at: &F, rng: &mut R) -> Vec<([i32; 3], u16)>
+    where F: Fn([i32; 3]) -> u16, R: Rng
+{
+    let soil = block_at(above(location, -1));
+    let age = block & 0xf;
+    if soil >> 4 != FARMLAND || age >= 7 {
+        return vec![];
+    }
+    // As vanilla works it out for a lone crop
+    let growth = if soil & 0xf > 0 { 4 } else { 2 };
+    if rng.gen_range(0, 25 / growth + 1) != 0 {
+        return vec![];
+    }
+    vec![(location, block & !0xf | age + 1)]
+}
+
+/// Saplings grow in two stages, the second one a tree if there's room.
+fn sapling<F, R>(location: [i32; 3], metadata: u16, block_at: &F, rng: &mut R) -> Vec<([i32; 3], u16)>
+    where F: Fn([i32; 3]) -> u16, R: Rng
+{
+    if rng.gen_range(0, 7) != 0 {
+        return vec![];
+    }
+    if metadata & 0x8 == 0 {
+        return vec![(location, SAPLING << 4 | metadata | 0x8)];
+    }
+    tree(location, metadata & 0x7, block_at, rng)
+}
+
+/// A small tree of wood `kind` growing from `location`, the way oaks grow,
+/// no blocks if there's no room for it.
+pub fn tree<F, R>(location: [i32; 3], kind: u16, block_at: &F, rng: &mut R) -> Vec<([i32; 3], u16)>
+    where F: Fn([i32; 3]) -> u16, R: Rng
+{
+    let (log, leaves) = if kind < 4 {
+        (LOG << 4 | kind, LEAVES << 4 | kind)
+    } else {
+        (LOG2 << 4 | (kind - 4), LEAVES2 << 4 | (kind - 4))
+    };
+    let soil = block_at(above(location, -1)) >> 4;
+    if soil != GRASS && soil != DIRT && soil != FARMLAND {
+        return vec![];
+    }
+    let height = rng.gen_range(4, 7);
+    if location[1] + height + 1 > 255 {
+        return vec![];
+    }
+    let replaceable = |id: u16| id == block::AIR || id == LEAVES || id == LEAVES2 || id == SAPLING;
+    if (1..height + 1).any(|y| !replaceable(block_at(above(location, y)) >> 4)) {
+        return vec![];
+    }
+    let mut changes = vec![(above(location, -1), DIRT << 4)];
+    let top = location[1] + height - 1;
+    for y in top - 3..top + 2 {
+        let radius: i32 = if y >= top { 1 } else { 2 };
+        for dx in -radius..radius + 1 {
+            for dz in -radius..radius + 1 {
+                // Corners are cut at random, and always at the very top
+                let corner = dx.abs() == radius && dz.abs() == radius;
+                if corner && (y == top + 1 || rng.gen_range(0, 2) == 0) {
+                    continue;
+                }
+                if y == top + 1 && dx != 0 && dz != 0 {
+                    continue;
+                }
+                let spot = [location[0] + dx, y, location[2] + dz];
+                if block_at(spot) >> 4 == block::AIR {
+                    changes.push((spot, leaves));
+                }
+            }
+        }
+    }
+    for y in 0..height {
+        changes.push((above(location, y), log));
+    }
+    changes
+}
+
+/// Rough block light at `location`, from what blocks nearby give off less
+/// a level per block away.
+pub fn block_light<F: Fn([i32; 3]) -> u16>(location: [i32; 3], block_at: &F) -> u8 {
+    light_within(location, 14, block_at)
+}
+
+/// Whether the block light at `location` is above `level`, looking no
+/// further than blocks bright enough for that.
+pub fn is_lit<F: Fn([i32; 3]) -> u16>(location: [i32; 3], level: u8, block_at: &F) -> bool {
+    level < 15 && light_within(location, 14 - level as i32, block_at) > level
+}
+
+/// Block light at `location` from blocks at most `reach` blocks away.
+fn light_within<F: Fn([i32; 3]) -> u16>(location: [i32; 3], reach: i32, block_at: &F) -> u8 {
+    let mut light = 0;
+    for dx in -reach..reach + 1 {
+        for dy in -reach..reach + 1 {
+            for dz in -reach..reach + 1 {
+                let away = (dx.abs() + dy.abs() + dz.abs()) as u8;
+                if away > reach as u8 || away >= 15 - light {
+                    continue;
+                }
+                let emitted = block::light_emitted(block_at([location[0] + dx, location[1] + dy, location[2] + dz]) >> 4);
+                if emitted > away {
+                    light = light.max(emitted - away);
+                }
+            }
+        }
+    }
+    light
+}
+
+/// Ice melts in bright block light, to water over solid ground or water,
+/// and to nothing over air.
+fn ice<F: Fn([i32; 3]) -> u16>(location: [i32; 3], block_at: &F) -> Vec<([i32; 3], u16)> {
+    if !is_lit(location, ICE_LIGHT, block_at) {
+        return vec![];
+    }
+    let below = block_at(above(location, -1)) >> 4;
+    if block::is_solid(below) || (8 <= below && below <= 11) {
+        vec![(location, 8 << 4)]
+    } else {
+        vec![(location, block::AIR)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    /// Stone below y = 62, dirt at 62 and 63, air above, with `blocks` on
+    /// top of that.
+    fn world(blocks: &[([i32; 3], u16)]) -> RefCell<HashMap<[i32; 3], u16>> {
+        RefCell::new(blocks.iter().cloned().collect())
+    }
+
+    fn block_at(blocks: &RefCell<HashMap<[i32; 3], u16>>, location: [i32; 3]) -> u16 {
+        match blocks.borrow().get(&location) {
+            Some(&block) => block,
+            None if location[1] < 62 => 1 << 4,
+            None if location[1] < 64 => DIRT << 4,
+            None => 0
+        }
+    }
+
+    /// Random ticks the block at `location` until something changes,
+    /// returning the changes.
+    fn tick_until_change(blocks: &RefCell<HashMap<[i32; 3], u16>>, location: [i32; 3], rng: &mut StdRng) -> Vec<([i32; 3], u16)> {
+        for _ in 0..1000 {
+            let changes = random_tick(location, &|location| block_at(blocks, location), rng);
+            if !changes.is_empty() {
+                blocks.borrow_mut().extend(changes.iter().cloned());
+                return changes;
+            }
+        }
+        vec![]
+    }
+
+    #[test]
+    fn grass_and_crops() {
+        let mut rng = StdRng::from_seed([3; 32]);
+        let blocks = world(&[([0, 63, 0], GRASS << 4)]);
+        let changes = tick_until_change(&blocks, [0, 63, 0], &mut rng);
+        assert!(changes.iter().all(|&(_, block)| block == GRASS << 4));
+        // Covered grass dies
+        blocks.borrow_mut().insert([0, 64, 0], 1 << 4);
+        assert_eq!(random_tick([0, 63, 0], &|location| block_at(&blocks, location), &mut rng), vec![([0, 63, 0], DIRT << 4)]);
+
+        let blocks = world(&[([0, 63, 0], FARMLAND << 4 | 7), ([0, 64, 0], WHEAT << 4)]);
+        for age in 1..8 {
+            assert_eq!(tick_until_change(&blocks, [0, 64, 0], &mut rng), vec![([0, 64, 0], WHEAT << 4 | age)]);
+        }
+        // Ripe
+        assert!(tick_until_change(&blocks, [0, 64, 0], &mut rng).is_empty());
+        // Crops don't grow off farmland
+        let blocks = world(&[([0, 64, 0], CARROTS << 4)]);
+        assert!(tick_until_change(&blocks, [0, 64, 0], &mut rng).is_empty());
+    }
+
+    #[test]
+    fn saplings_and_ice() {
+        let mut rng = StdRng::from_seed([3; 32]);
+        let blocks = world(&[([0, 63, 0], GRASS << 4), ([0, 64, 0], SAPLING << 4 | 2)]);
+        assert_eq!(tick_until_change(&blocks, [0, 64, 0], &mut rng), vec![([0, 64, 0], SAPLING << 4 | 0xa)]);
+        let changes = tick_until_change(&blocks, [0, 64, 0], &mut rng);
+        assert!(changes.contains(&([0, 64, 0], LOG << 4 | 2)));
+        assert!(changes.iter().any(|&(_, block)| block == LEAVES << 4 | 2));
+        assert_eq!(block_at(&blocks, [0, 63, 0]), DIRT << 4);
+        // No room under a ceiling
+        let blocks = world(&[([0, 64, 0], SAPLING << 4 | 0x8), ([0, 66, 0], 1 << 4)]);
+        assert!(tick_until_change(&blocks, [0, 64, 0], &mut rng).is_empty());
+
+        let blocks = world(&[([0, 64, 0], ICE << 4)]);
+        assert!(tick_until_change(&blocks, [0, 64, 0], &mut rng).is_empty());
+        blocks.borrow_mut().insert([2, 64, 0], 50 << 4);
+        assert_eq!(block_light([0, 64, 0], &|location| block_at(&blocks, location)), 12);
+        assert_eq!(tick_until_change(&blocks, [0, 64, 0], &mut rng), vec![([0, 64, 0], 8 << 4)]);
+    }
+}
@@ -0,0 +1,120 @@
+//! Limits on how fast a client may send some kinds of packets, so floods
+//! of chat or movement can't bog down the server.
+
+use std::time::{Duration, Instant};
+
+/// Kinds of packets limited together.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PacketGroup {
+    Chat,
+    Movement
+}
+
+impl PacketGroup {
+    /// Group of the serverbound play packet `id`, `None` if it isn't
+    /// limited.
+    pub fn of(id: i32) -> Option<PacketGroup> {
+        match id {
+            0x01 => Some(PacketGroup::Chat),
+            0x03 | 0x04 | 0x05 | 0x06 => Some(PacketGroup::Movement),
+            _ => None
+        }
+    }
+}
+
+/// At most `max` packets every `window`, 0 being no limit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limit {
+    pub max: u32,
+    pub window: Duration
+}
+
+impl Limit {
+    pub fn new(max: i32, window: Duration) -> Limit {
+        Limit { max: if max > 0 { max as u32 } else { 0 }, window: window }
+    }
+}
+
+/// What to do with a packet.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Verdict {
+    Allow,
+    /// The client is over the limit, the packet is ignored.
+    Drop,
+    /// The client sent twice the limit, it's disconnected.
+    Kick
+}
+
+/// Packets counted in the current window of a group.
+struct Counter {
+    limit: Limit,
+    start: Option<Instant>,
+    count: u32
+}
+
+impl Counter {
+    fn check(&mut self, now: Instant) -> Verdict {
+        if self.limit.max == 0 {
+            return Verdict::Allow;
+        }
+        match self.start {
+            Some(start) if now.duration_since(start) < self.limit.window => {}
+            _ => {
+                self.start = Some(now);
+                self.count = 0;
+            }
+        }
+        self.count += 1;
+        if self.count > self.limit.max * 2 {
+            Verdict::Kick
+        } else if self.count > self.limit.max {
+            Verdict::Drop
+        } else {
+            Verdict::Allow
+        }
+    }
+}
+
+/// Rate limits of one connection.
+pub struct RateLimiter {
+    chat: Counter,
+    movement: Counter
+}
+
+impl RateLimiter {
+    pub fn new(chat: Limit, movement: Limit) -> RateLimiter {
+        RateLimiter {
+            chat: Counter { limit: chat, start: None, count: 0 },
+            movement: Counter { limit: movement, start: None, count: 0 }
+        }
+    }
+
+    /// Counts a packet of `group` received at `now`.
+    pub fn check(&mut self, group: PacketGroup, now: Instant) -> Verdict {
+        match group {
+            PacketGroup::Chat => self.chat.check(now),
+            PacketGroup::Movement => self.movement.check(now)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn chat_flood() {
+        let mut limiter = RateLimiter::new(Limit::new(2, Duration::from_secs(10)), Limit::new(0, Duration::from_secs(1)));
+        let now = Instant::now();
+        let verdicts: Vec<_> = (0..5).map(|_| limiter.check(PacketGroup::Chat, now)).collect();
+        assert_eq!(verdicts, vec![Verdict::Allow, Verdict::Allow, Verdict::Drop, Verdict::Drop, Verdict::Kick]);
+        // The next window starts over
+        assert_eq!(limiter.check(PacketGroup::Chat, now + Duration::from_secs(10)), Verdict::Allow);
+        // Movement isn't limited
+        assert!((0..100).all(|_| limiter.check(PacketGroup::Movement, now) == Verdict::Allow));
+        assert_eq!(PacketGroup::of(0x04), Some(PacketGroup::Movement));
+        assert_eq!(PacketGroup::of(0x07), None);
+    }
+}
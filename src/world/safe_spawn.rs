@@ -0,0 +1,132 @@
+//! Where players appear when they've got nowhere saved to go back to: on
+//! solid ground near the world spawn, or on a platform built for them when
+//! there's none, so they don't fall forever in empty worlds.
+
+use block;
+
+/// How far from the world spawn, in blocks, ground is looked for.
+pub const SEARCH_RADIUS: i32 = 8;
+
+/// Blocks a platform reaches out from its center, making it 5x5.
+pub const PLATFORM_RADIUS: i32 = 2;
+
+/// What platforms are made of, as `id << 4 | metadata`.
+pub const PLATFORM_BLOCK: u16 = block::STONE << 4;
+
+fn is_liquid(id: u16) -> bool {
+    8 <= id && id <= 11
+}
+
+/// Whether a player can stand with their feet at `feet`: on solid ground,
+/// with room for their body and head, and nothing hurting them.
+pub fn is_safe<F: Fn([i32; 3]) -> u16>(feet: [i32; 3], block_at: &F) -> bool {
+    let id = |location: [i32; 3]| block_at(location) >> 4;
+    let (below, body, head) = (id([feet[0], feet[1] - 1, feet[2]]), id(feet), id([feet[0], feet[1] + 1, feet[2]]));
+    block::is_solid(below) && !block::is_harmful(below) &&
+        [body, head].iter().all(|&id| !block::is_solid(id) && !block::is_harmful(id) && !is_liquid(id))
+}
+
+/// The safe spot closest to `spawn` within `SEARCH_RADIUS` blocks, the
+/// highest one of its column, as feet position. `None` if there's nowhere
+/// to stand.
+pub fn find<F: Fn([i32; 3]) -> u16>(spawn: [i32; 3], block_at: &F) -> Option<[i32; 3]> {
+    for radius in 0..SEARCH_RADIUS + 1 {
+        let mut ring: Vec<_> = (-radius..radius + 1)
+            .flat_map(|dx| (-radius..radius + 1).map(move |dz| (dx, dz)))
+            .filter(|&(dx, dz)| dx.abs() == radius || dz.abs() == radius)
+            .collect();
+        ring.sort_by_key(|&(dx, dz)| dx * dx + dz * dz);
+        for (dx, dz) in ring {
+            let (x, z) = (spawn[0] + dx, spawn[2] + dz);
+            if let Some(y) = (1..255).rev().find(|&y| is_safe([x, y, z], block_at)) {
+                return Some([x, y, z]);
+            }
+        }
+    }
+    None
+}
+
+/// Where someone getting out of the bed whose head is at `head` stands,
+/// next to it. `None` if the bed is gone or there's no room around it.
+pub fn bed_exit<F: Fn([i32; 3]) -> u16>(head: [i32; 3], block_at: &F) -> Option<[i32; 3]> {
+    if block_at(head) >> 4 != block::BED {
+        return None;
+    }
+    let mut spots = vec![];
+    for dy in &[0, 1, -1] {
+        for dx in -2..3 {
+            for dz in -2..3 {
+                spots.push([head[0] + dx, head[1] + dy, head[2] + dz]);
+            }
+        }
+    }
+    spots.sort_by_key(|spot| (spot[0] - head[0]).pow(2) + (spot[2] - head[2]).pow(2));
+    // Beside the bed, not on it
+    let on_bed = |spot: [i32; 3]| block_at([spot[0], spot[1] - 1, spot[2]]) >> 4 == block::BED;
+    spots.into_iter().find(|&spot| is_safe(spot, block_at) && !on_bed(spot))
+}
+
+/// Blocks making a platform for players to stand on at `spawn`, with room
+/// above it.
+pub fn platform(spawn: [i32; 3]) -> Vec<([i32; 3], u16)> {
+    let mut blocks = vec![];
+    for dx in -PLATFORM_RADIUS..PLATFORM_RADIUS + 1 {
+        for dz in -PLATFORM_RADIUS..PLATFORM_RADIUS + 1 {
+            let (x, z) = (spawn[0] + dx, spawn[2] + dz);
+            blocks.push(([x, spawn[1] - 1, z], PLATFORM_BLOCK));
+            blocks.push(([x, spawn[1], z], block::AIR));
+            blocks.push(([x, spawn[1] + 1, z], block::AIR));
+        }
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    #[test]
+    fn finding_ground() {
+        // Void but for a pillar at x = 3 with lava on top of the one at x = -3
+        let ground = |location: [i32; 3]| -> u16 {
+            match (location[0], location[1], location[2]) {
+                (3, y, 0) if y <= 40 => 1 << 4,
+                (-3, y, 0) if y <= 50 => 1 << 4,
+                (-3, 51, 0) => 10 << 4,
+                _ => 0
+            }
+        };
+        assert!(is_safe([3, 41, 0], &ground));
+        assert!(!is_safe([-3, 51, 0], &ground));
+        assert_eq!(find([0, 64, 0], &ground), Some([3, 41, 0]));
+        assert_eq!(find([20, 64, 0], &ground), None);
+
+        let blocks: HashMap<_, _> = platform([20, 64, 0]).into_iter().collect();
+        let built = |location: [i32; 3]| blocks.get(&location).cloned().unwrap_or(0);
+        assert_eq!(blocks.len(), 75);
+        assert_eq!(find([20, 64, 0], &built), Some([20, 64, 0]));
+    }
+
+    #[test]
+    fn leaving_bed() {
+        // A bed on flat ground along x, boxed in by a wall at x = 3
+        let ground = |location: [i32; 3]| -> u16 {
+            match (location[0], location[1], location[2]) {
+                (_, y, _) if y < 64 => 1 << 4,
+                (0, 64, 0) => block::BED << 4 | 0x8,
+                (1, 64, 0) => block::BED << 4,
+                (3, _, _) => 1 << 4,
+                _ => 0
+            }
+        };
+        let exit = bed_exit([0, 64, 0], &ground).unwrap();
+        assert_eq!(exit[1], 64);
+        assert_eq!(exit[0].abs() + exit[2].abs(), 1);
+        assert_eq!(bed_exit([5, 64, 0], &ground), None);
+        // Buried beds can't be left
+        let buried = |location: [i32; 3]| if location == [0, 64, 0] { ground(location) } else { 1 << 4 };
+        assert_eq!(bed_exit([0, 64, 0], &buried), None);
+    }
+}
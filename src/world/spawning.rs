@@ -0,0 +1,186 @@
+//! Natural mob spawning and the day/night cycle mobs go by.
+//!
+//! Mobs are counted by category, and each category has a cap which grows
+//! with the chunks simulated around players, as in vanilla. Monsters only
+//! spawn in the dark, away from players.
+
+use block;
+use entity::EntityKind;
+use world::random_ticks;
+
+use rand::Rng;
+
+/// Chunks the caps are given for, those within 8 chunks of a lone player.
+pub const CAP_CHUNKS: usize = 289;
+
+/// Monsters don't spawn closer than this to a player, in blocks.
+pub const MIN_PLAYER_DISTANCE: f64 = 24.0;
+
+/// Monsters further than this from every player are despawned, in blocks.
+pub const DESPAWN_DISTANCE: f64 = 128.0;
+
+/// Sky light at night, under the open sky.
+pub const NIGHT_SKY_LIGHT: u8 = 4;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MobCategory {
+    Monster,
+    Creature,
+    Ambient,
+    WaterCreature
+}
+
+impl MobCategory {
+    /// Category of entities of kind `kind`, `None` for those which aren't
+    /// mobs.
+    pub fn of(kind: &EntityKind) -> Option<MobCategory> {
+        match *kind {
+            EntityKind::Monster(_) => Some(MobCategory::Monster),
+            EntityKind::Horse(_) => Some(MobCategory::Creature),
+            _ => None
+        }
+    }
+
+    /// Most mobs of the category with `CAP_CHUNKS` chunks simulated.
+    pub fn cap(&self) -> usize {
+        match *self {
+            MobCategory::Monster => 70,
+            MobCategory::Creature => 10,
+            MobCategory::Ambient => 15,
+            MobCategory::WaterCreature => 5
+        }
+    }
+
+    /// Most mobs of the category with `chunks` chunks simulated.
+    pub fn scaled_cap(&self, chunks: usize) -> usize {
+        self.cap() * chunks / CAP_CHUNKS
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Mobs in a world, by category.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MobCounts {
+    counts: [usize; 4]
+}
+
+impl MobCounts {
+    pub fn new() -> MobCounts {
+        MobCounts { counts: [0; 4] }
+    }
+
+    /// Counts the mobs among entities of kinds `kinds`.
+    pub fn count<'a, I: Iterator<Item = &'a EntityKind>>(kinds: I) -> MobCounts {
+        let mut counts = MobCounts::new();
+        for category in kinds.filter_map(MobCategory::of) {
+            counts.add(category);
+        }
+        counts
+    }
+
+    pub fn add(&mut self, category: MobCategory) {
+        self.counts[category.index()] += 1;
+    }
+
+    pub fn get(&self, category: MobCategory) -> usize {
+        self.counts[category.index()]
+    }
+
+    /// Whether another mob of `category` may spawn with `chunks` chunks
+    /// simulated.
+    pub fn below_cap(&self, category: MobCategory, chunks: usize) -> bool {
+        self.get(category) < category.scaled_cap(chunks)
+    }
+}
+
+/// Whether the sun is up at time of day `time`, in ticks.
+pub fn is_daytime(time: i64) -> bool {
+    let time = (time % 24000 + 24000) % 24000;
+    time < 12542 || time >= 23460
+}
+
+/// Whether nothing but air and see-through blocks are above `location`.
+pub fn sees_sky<F: Fn([i32; 3]) -> u16>(location: [i32; 3], block_at: &F) -> bool {
+    (location[1] + 1..256).all(|y| {
+        let id = block_at([location[0], y, location[2]]) >> 4;
+        !block::is_solid(id) && !(8 <= id && id <= 11)
+    })
+}
+
+/// Whether a monster could spawn with its feet at `location` at time of day
+/// `time`: on solid ground, with room to stand, and dark enough, which at
+/// light levels 1 to 7 is down to chance.
+pub fn monster_spot<F, R>(location: [i32; 3], time: i64, block_at: &F, rng: &mut R) -> bool
+    where F: Fn([i32; 3]) -> u16, R: Rng
+{
+    let id = |location: [i32; 3]| block_at(location) >> 4;
+    let free = |id: u16| !block::is_solid(id) && !(8 <= id && id <= 11);
+    let (below, head) = ([location[0], location[1] - 1, location[2]], [location[0], location[1] + 1, location[2]]);
+    if !block::is_solid(id(below)) || !free(id(location)) || !free(id(head)) {
+        return false;
+    }
+    let darkness = rng.gen_range(0, 8);
+    let sky = if !sees_sky(location, block_at) {
+        0
+    } else if is_daytime(time) {
+        15
+    } else {
+        NIGHT_SKY_LIGHT
+    };
+    sky <= darkness && !random_ticks::is_lit(location, darkness, block_at)
+}
+
+/// Squared distance from `a` to `b`.
+pub fn distance_squared(a: [f64; 3], b: [f64; 3]) -> f64 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use entity::{Horse, Monster, MonsterKind};
+
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn caps() {
+        let kinds = vec![
+            EntityKind::Monster(Monster::new(MonsterKind::Zombie)),
+            EntityKind::Monster(Monster::new(MonsterKind::Spider)),
+            EntityKind::Horse(Horse::random()),
+            EntityKind::PrimedTnt { fuse: 10 }
+        ];
+        let counts = MobCounts::count(kinds.iter());
+        assert_eq!(counts.get(MobCategory::Monster), 2);
+        assert_eq!(counts.get(MobCategory::Creature), 1);
+        assert_eq!(counts.get(MobCategory::Ambient), 0);
+        assert_eq!(MobCategory::Monster.scaled_cap(CAP_CHUNKS * 2), 140);
+        assert!(counts.below_cap(MobCategory::Monster, 20));
+        // A chunk alone allows no animals
+        assert!(!counts.below_cap(MobCategory::Creature, 1));
+    }
+
+    #[test]
+    fn darkness() {
+        let mut rng = StdRng::from_seed([5; 32]);
+        // A floor at 63 and a roof at 70 from x = 10 on
+        let ground = |location: [i32; 3]| -> u16 {
+            let solid = location[1] < 64 || location[0] >= 10 && location[1] == 70;
+            if solid { 1 << 4 } else { 0 }
+        };
+        assert!(is_daytime(1000) && !is_daytime(18000) && is_daytime(-500));
+        assert!(sees_sky([0, 64, 0], &ground) && !sees_sky([10, 64, 0], &ground));
+        assert!((0..20).all(|_| !monster_spot([0, 64, 0], 6000, &ground, &mut rng)));
+        assert!((0..20).any(|_| monster_spot([0, 64, 0], 18000, &ground, &mut rng)));
+        assert!(monster_spot([10, 64, 0], 6000, &ground, &mut rng));
+        assert!(!monster_spot([10, 65, 0], 6000, &ground, &mut rng));
+        // Torches keep them away
+        let lit = |location: [i32; 3]| if location == [12, 64, 0] { 50 << 4 } else { ground(location) };
+        assert!((0..20).all(|_| !monster_spot([10, 64, 0], 6000, &lit, &mut rng)));
+    }
+}
@@ -0,0 +1,435 @@
+//! Structures generated over the terrain, dungeons and villages so far.
+//!
+//! A structure starts in one chunk, picked from that chunk's seed, and is
+//! made of pieces whose bounding boxes may reach into the chunks around.
+//! Generating a chunk looks for the starts near enough to reach it and
+//! places the part of each piece inside it, so a structure comes out whole
+//! whichever order its chunks are generated in.
+
+use std::cmp;
+
+use types::ChunkColumn;
+use world::random::WorldRandom;
+
+/// Blocks structures are built of, as ids.
+const COBBLESTONE: u16 = 4;
+const PLANKS: u16 = 5;
+const GRAVEL: u16 = 13;
+const LOG: u16 = 17;
+const GLASS: u16 = 20;
+const MOSSY_COBBLESTONE: u16 = 48;
+const SPAWNER: u16 = 52;
+const FENCE: u16 = 85;
+const WATER: u16 = 9;
+
+/// Blocks within both corners, inclusive.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BoundingBox {
+    pub min: [i32; 3],
+    pub max: [i32; 3]
+}
+
+impl BoundingBox {
+    pub fn new(min: [i32; 3], max: [i32; 3]) -> BoundingBox {
+        BoundingBox { min: min, max: max }
+    }
+
+    /// Blocks of chunk column (`x`, `z`), all the way up.
+    pub fn chunk(x: i32, z: i32) -> BoundingBox {
+        BoundingBox::new([x * 16, 0, z * 16], [x * 16 + 15, 255, z * 16 + 15])
+    }
+
+    pub fn contains(&self, pos: [i32; 3]) -> bool {
+        (0..3).all(|i| pos[i] >= self.min[i] && pos[i] <= self.max[i])
+    }
+
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        (0..3).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+
+    /// Smallest box holding both.
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox::new(
+            [cmp::min(self.min[0], other.min[0]), cmp::min(self.min[1], other.min[1]), cmp::min(self.min[2], other.min[2])],
+            [cmp::max(self.max[0], other.max[0]), cmp::max(self.max[1], other.max[1]), cmp::max(self.max[2], other.max[2])]
+        )
+    }
+}
+
+/// The chunk column being generated, written to in world coordinates,
+/// leaving out whatever falls outside it.
+pub struct ChunkArea<'a> {
+    column: &'a mut ChunkColumn,
+    bounds: BoundingBox
+}
+
+impl<'a> ChunkArea<'a> {
+    pub fn new(column: &'a mut ChunkColumn, x: i32, z: i32) -> ChunkArea<'a> {
+        ChunkArea { column: column, bounds: BoundingBox::chunk(x, z) }
+    }
+
+    /// Block at `pos`, as `id << 4 | metadata`, air outside the chunk.
+    pub fn block(&self, pos: [i32; 3]) -> u16 {
+        if !self.bounds.contains(pos) {
+            return 0;
+        }
+        self.column.block((pos[0] & 15) as usize, pos[1] as usize, (pos[2] & 15) as usize)
+    }
+
+    /// Sets the block at `pos` to block id `id`, if it's in the chunk.
+    pub fn set(&mut self, pos: [i32; 3], id: u16) {
+        if self.bounds.contains(pos) {
+            self.column.set_block((pos[0] & 15) as usize, pos[1] as usize, (pos[2] & 15) as usize, id << 4);
+        }
+    }
+
+    /// Fills the blocks of `area` with block id `id`.
+    pub fn fill(&mut self, area: BoundingBox, id: u16) {
+        for x in cmp::max(area.min[0], self.bounds.min[0])..cmp::min(area.max[0], self.bounds.max[0]) + 1 {
+            for y in cmp::max(area.min[1], 0)..cmp::min(area.max[1], 255) + 1 {
+                for z in cmp::max(area.min[2], self.bounds.min[2])..cmp::min(area.max[2], self.bounds.max[2]) + 1 {
+                    self.set([x, y, z], id);
+                }
+            }
+        }
+    }
+}
+
+/// Part of a structure, placed chunk by chunk.
+pub trait Piece {
+    fn bounds(&self) -> BoundingBox;
+
+    /// Places the blocks of the piece that are in `area`, the same whichever
+    /// chunk it is.
+    fn place(&self, area: &mut ChunkArea);
+}
+
+/// A structure starting in some chunk, as the pieces it's made of.
+pub struct Start {
+    pub pieces: Vec<Box<Piece>>
+}
+
+impl Start {
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        self.pieces.iter().map(|piece| piece.bounds()).fold(None, |bounds, piece| {
+            Some(bounds.map_or(piece, |bounds: BoundingBox| bounds.union(&piece)))
+        })
+    }
+}
+
+/// A kind of structure.
+pub trait Structure {
+    /// Chunks away from its start a structure can reach.
+    fn range(&self) -> i32;
+
+    /// The structure starting in chunk (`x`, `z`), if one does, `biome`
+    /// giving the biome of any chunk.
+    fn start(&self, random: &WorldRandom, biome: &Fn(i32, i32) -> u8, x: i32, z: i32) -> Option<Start>;
+}
+
+/// Places the parts of `structures` which reach chunk (`x`, `z`) in
+/// `column`.
+pub fn place(structures: &[&Structure], random: &WorldRandom, biome: &Fn(i32, i32) -> u8, column: &mut ChunkColumn, x: i32, z: i32) {
+    let chunk = BoundingBox::chunk(x, z);
+    let mut area = ChunkArea::new(column, x, z);
+    for structure in structures {
+        let range = structure.range();
+        for start_x in x - range..x + range + 1 {
+            for start_z in z - range..z + range + 1 {
+                let start = match structure.start(random, biome, start_x, start_z) {
+                    Some(start) => start,
+                    None => continue
+                };
+                for piece in &start.pieces {
+                    if piece.bounds().intersects(&chunk) {
+                        piece.place(&mut area);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Small cobblestone rooms underground, with a spawner in the middle, in
+/// one chunk out of 4.
+pub struct Dungeons {
+    /// Height of the ground they're dug under.
+    pub surface: i32
+}
+
+impl Structure for Dungeons {
+    fn range(&self) -> i32 { 1 }
+
+    fn start(&self, random: &WorldRandom, _: &Fn(i32, i32) -> u8, x: i32, z: i32) -> Option<Start> {
+        let mut rng = random.feature("dungeon", x, z);
+        if rng.next_int_bounded(4) != 0 || self.surface < 20 {
+            return None;
+        }
+        let center = [x * 16 + rng.next_int_bounded(16), 10 + rng.next_int_bounded(self.surface - 20), z * 16 + rng.next_int_bounded(16)];
+        let radius = [rng.next_int_bounded(2) + 2, rng.next_int_bounded(2) + 2];
+        Some(Start { pieces: vec![Box::new(DungeonRoom { center: center, radius: radius, moss_seed: rng.next_int() })] })
+    }
+}
+
+/// A dungeon room, `radius` blocks across on either side of its center
+/// along x and z, inside its walls.
+struct DungeonRoom {
+    center: [i32; 3],
+    radius: [i32; 2],
+    moss_seed: i32
+}
+
+impl DungeonRoom {
+    /// Whether the wall or floor block at `pos` is mossy, picked by hashing
+    /// its position so every chunk agrees.
+    fn mossy(&self, pos: [i32; 3]) -> bool {
+        let hash = pos[0].wrapping_mul(73856093) ^ pos[1].wrapping_mul(19349663) ^ pos[2].wrapping_mul(83492791) ^ self.moss_seed;
+        hash & 3 == 0
+    }
+}
+
+impl Piece for DungeonRoom {
+    fn bounds(&self) -> BoundingBox {
+        let (x, y, z) = (self.center[0], self.center[1], self.center[2]);
+        BoundingBox::new([x - self.radius[0] - 1, y - 1, z - self.radius[1] - 1], [x + self.radius[0] + 1, y + 4, z + self.radius[1] + 1])
+    }
+
+    fn place(&self, area: &mut ChunkArea) {
+        let bounds = self.bounds();
+        for x in bounds.min[0]..bounds.max[0] + 1 {
+            for y in bounds.min[1]..bounds.max[1] + 1 {
+                for z in bounds.min[2]..bounds.max[2] + 1 {
+                    let wall = x == bounds.min[0] || x == bounds.max[0] || z == bounds.min[2] || z == bounds.max[2];
+                    let id = if y == bounds.max[1] {
+                        COBBLESTONE
+                    } else if y == bounds.min[1] || wall {
+                        if self.mossy([x, y, z]) { MOSSY_COBBLESTONE } else { COBBLESTONE }
+                    } else {
+                        0
+                    };
+                    area.set([x, y, z], id);
+                }
+            }
+        }
+        area.set(self.center, SPAWNER);
+    }
+}
+
+/// A village around a well, with paths leading off it lined by houses, in
+/// one plains chunk of each area of `spacing` by `spacing` chunks.
+pub struct Villages {
+    /// Height of the ground they're built on.
+    pub surface: i32,
+    pub spacing: i32,
+    pub biomes: &'static [u8]
+}
+
+/// Longest path leading off a village well, in blocks.
+const VILLAGE_PATH: i32 = 20;
+
+impl Villages {
+    /// The chunk the village of area (`area_x`, `area_z`) may start in,
+    /// away from the area's far edges so villages don't meet.
+    fn start_chunk(&self, random: &WorldRandom, area_x: i32, area_z: i32) -> (i32, i32) {
+        let mut rng = random.feature("village", area_x, area_z);
+        let room = cmp::max(self.spacing - 8, 1);
+        (area_x * self.spacing + rng.next_int_bounded(room), area_z * self.spacing + rng.next_int_bounded(room))
+    }
+}
+
+impl Structure for Villages {
+    fn range(&self) -> i32 { (VILLAGE_PATH + 16 + 15) / 16 }
+
+    fn start(&self, random: &WorldRandom, biome: &Fn(i32, i32) -> u8, x: i32, z: i32) -> Option<Start> {
+        let area = (floor_div(x, self.spacing), floor_div(z, self.spacing));
+        if self.start_chunk(random, area.0, area.1) != (x, z) || !self.biomes.contains(&biome(x, z)) {
+            return None;
+        }
+        let mut rng = random.chunk(x, z);
+        let (cx, cz, ground) = (x * 16 + 8, z * 16 + 8, self.surface);
+        let mut pieces: Vec<Box<Piece>> = vec![Box::new(Well { center: [cx, ground, cz] })];
+        for &(dx, dz) in &[(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let length = 8 + rng.next_int_bounded(VILLAGE_PATH - 7);
+            // From the edge of the well out, 3 wide
+            let (near, far) = ([cx + dx * 3, ground, cz + dz * 3], [cx + dx * (2 + length), ground, cz + dz * (2 + length)]);
+            let (side_x, side_z) = (dz.abs(), dx.abs());
+            pieces.push(Box::new(Path {
+                bounds: BoundingBox::new(
+                    [cmp::min(near[0], far[0]) - side_x, ground, cmp::min(near[2], far[2]) - side_z],
+                    [cmp::max(near[0], far[0]) + side_x, ground, cmp::max(near[2], far[2]) + side_z]
+                )
+            }));
+            // Houses along either side of the path, facing it
+            let mut along = 4;
+            while along + 5 <= length {
+                for &side in &[-1, 1] {
+                    if rng.next_int_bounded(3) == 0 {
+                        continue;
+                    }
+                    let (door_x, door_z) = (cx + dx * (2 + along + 2) + side * side_x * 2, cz + dz * (2 + along + 2) + side * side_z * 2);
+                    // The house's near wall is 2 blocks from the path's
+                    // middle, the house 5 blocks deep beyond it
+                    let (min_x, max_x) = if side_x == 0 {
+                        (door_x - 2, door_x + 2)
+                    } else if side > 0 {
+                        (door_x, door_x + 4)
+                    } else {
+                        (door_x - 4, door_x)
+                    };
+                    let (min_z, max_z) = if side_z == 0 {
+                        (door_z - 2, door_z + 2)
+                    } else if side > 0 {
+                        (door_z, door_z + 4)
+                    } else {
+                        (door_z - 4, door_z)
+                    };
+                    pieces.push(Box::new(House {
+                        bounds: BoundingBox::new([min_x, ground, min_z], [max_x, ground + 4, max_z]),
+                        door: [door_x, ground + 1, door_z]
+                    }));
+                }
+                along += 7;
+            }
+        }
+        Some(Start { pieces: pieces })
+    }
+}
+
+/// `a / b` rounded down rather than towards 0.
+fn floor_div(a: i32, b: i32) -> i32 {
+    if a >= 0 { a / b } else { (a + 1) / b - 1 }
+}
+
+/// A well in the middle of a village, 4 by 4 around `center`.
+struct Well {
+    center: [i32; 3]
+}
+
+impl Piece for Well {
+    fn bounds(&self) -> BoundingBox {
+        let (x, y, z) = (self.center[0], self.center[1], self.center[2]);
+        BoundingBox::new([x - 2, y - 3, z - 2], [x + 1, y + 3, z + 1])
+    }
+
+    fn place(&self, area: &mut ChunkArea) {
+        let bounds = self.bounds();
+        let (x, y, z) = (self.center[0], self.center[1], self.center[2]);
+        area.fill(BoundingBox::new(bounds.min, [bounds.max[0], y, bounds.max[2]]), COBBLESTONE);
+        area.fill(BoundingBox::new([x - 1, y - 2, z - 1], [x, y, z]), WATER);
+        area.fill(BoundingBox::new([bounds.min[0], y + 1, bounds.min[2]], [bounds.max[0], y + 2, bounds.max[2]]), 0);
+        for &(post_x, post_z) in &[(bounds.min[0], bounds.min[2]), (bounds.min[0], bounds.max[2]), (bounds.max[0], bounds.min[2]), (bounds.max[0], bounds.max[2])] {
+            area.fill(BoundingBox::new([post_x, y + 1, post_z], [post_x, y + 2, post_z]), FENCE);
+        }
+        area.fill(BoundingBox::new([bounds.min[0], y + 3, bounds.min[2]], bounds.max), COBBLESTONE);
+    }
+}
+
+/// A gravel path.
+struct Path {
+    bounds: BoundingBox
+}
+
+impl Piece for Path {
+    fn bounds(&self) -> BoundingBox { self.bounds }
+
+    fn place(&self, area: &mut ChunkArea) {
+        area.fill(self.bounds, GRAVEL);
+    }
+}
+
+/// A one room house of planks on a cobblestone floor, with logs at its
+/// corners, glass windows, and an opening for a door at `door`.
+struct House {
+    bounds: BoundingBox,
+    door: [i32; 3]
+}
+
+impl Piece for House {
+    fn bounds(&self) -> BoundingBox { self.bounds }
+
+    fn place(&self, area: &mut ChunkArea) {
+        let BoundingBox { min, max } = self.bounds;
+        let floor = min[1];
+        area.fill(BoundingBox::new(min, [max[0], floor, max[2]]), COBBLESTONE);
+        area.fill(BoundingBox::new([min[0], floor + 1, min[2]], [max[0], max[1] - 1, max[2]]), PLANKS);
+        area.fill(BoundingBox::new([min[0] + 1, floor + 1, min[2] + 1], [max[0] - 1, max[1] - 1, max[2] - 1]), 0);
+        area.fill(BoundingBox::new([min[0], max[1], min[2]], max), PLANKS);
+        for &(x, z) in &[(min[0], min[2]), (min[0], max[2]), (max[0], min[2]), (max[0], max[2])] {
+            area.fill(BoundingBox::new([x, floor + 1, z], [x, max[1] - 1, z]), LOG);
+        }
+        // Windows in the middle of each wall, then the doorway over one
+        let (mid_x, mid_z) = ((min[0] + max[0]) / 2, (min[2] + max[2]) / 2);
+        for &pos in &[[mid_x, floor + 2, min[2]], [mid_x, floor + 2, max[2]], [min[0], floor + 2, mid_z], [max[0], floor + 2, mid_z]] {
+            area.set(pos, GLASS);
+        }
+        area.fill(BoundingBox::new(self.door, [self.door[0], self.door[1] + 1, self.door[2]]), 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use types::{Chunk, ChunkColumn};
+    use world::random::WorldRandom;
+
+    fn stone() -> ChunkColumn {
+        ChunkColumn { chunks: (0..5).map(|_| Chunk::new(1 << 4, 0xff)).collect(), biomes: None }
+    }
+
+    fn count(column: &ChunkColumn, id: u16) -> usize {
+        column.chunks.iter().map(|chunk| chunk.blocks.iter().filter(|&&block| block >> 4 == id).count()).sum()
+    }
+
+    #[test]
+    fn bounding_boxes() {
+        let a = BoundingBox::new([0, 0, 0], [15, 10, 15]);
+        let b = BoundingBox::new([15, 10, 15], [20, 12, 20]);
+        assert!(a.intersects(&b) && b.intersects(&a));
+        assert!(!a.intersects(&BoundingBox::new([16, 0, 0], [20, 10, 15])));
+        assert_eq!(a.union(&b), BoundingBox::new([0, 0, 0], [20, 12, 20]));
+        assert!(BoundingBox::chunk(-1, 0).contains([-1, 64, 15]));
+    }
+
+    #[test]
+    fn dungeons_across_chunks() {
+        let random = WorldRandom::new(42);
+        let dungeons = Dungeons { surface: 63 };
+        let plains = |_: i32, _: i32| 1u8;
+        // A room reaching into the chunk east of its own
+        let (x, start) = (0..200).filter_map(|x| dungeons.start(&random, &plains, x, 0).map(|start| (x, start)))
+            .find(|&(x, ref start)| start.bounds().unwrap().max[0] >= (x + 1) * 16).unwrap();
+        let room = start.bounds().unwrap();
+        let structures: [&Structure; 1] = [&dungeons];
+        let (mut own, mut east) = (stone(), stone());
+        place(&structures, &random, &plains, &mut own, x, 0);
+        place(&structures, &random, &plains, &mut east, x + 1, 0);
+        let (y, z) = (room.max[1] - 2, room.min[2] + 1);
+        // Walls on either side, air inside, in whichever chunk it falls
+        let at = |pos: [i32; 3]| {
+            let column = if pos[0] >= (x + 1) * 16 { &east } else { &own };
+            column.block((pos[0] & 15) as usize, pos[1] as usize, (pos[2] & 15) as usize) >> 4
+        };
+        assert!(at([room.max[0], y, z]) == COBBLESTONE || at([room.max[0], y, z]) == MOSSY_COBBLESTONE);
+        assert_eq!(at([room.max[0] - 1, y, z]), 0);
+        assert_eq!(count(&own, SPAWNER) + count(&east, SPAWNER), 1);
+    }
+
+    #[test]
+    fn villages() {
+        let random = WorldRandom::new(42);
+        let villages = Villages { surface: 63, spacing: 16, biomes: &[1] };
+        let structures: [&Structure; 1] = [&villages];
+        let (x, z) = villages.start_chunk(&random, 2, -1);
+        assert!(villages.start(&random, &|_, _| 1, x, z).is_some());
+        assert!(villages.start(&random, &|_, _| 4, x, z).is_none());
+        assert!(villages.start(&random, &|_, _| 1, x + 1, z).is_none());
+        let mut center = stone();
+        place(&structures, &random, &|_, _| 1, &mut center, x, z);
+        assert!(count(&center, WATER) > 0 && count(&center, GRAVEL) > 0);
+        // Paths carry on into the chunks around
+        let mut east = stone();
+        place(&structures, &random, &|_, _| 1, &mut east, x + 1, z);
+        assert!(count(&east, GRAVEL) > 0);
+    }
+}
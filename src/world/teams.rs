@@ -0,0 +1,143 @@
+//! Teams of players, which may be kept from hurting each other.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Who sees the name tags of a team's members, as sent in UpdateTeam.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NameTagVisibility {
+    Always,
+    Never,
+    HideForOtherTeams,
+    HideForOwnTeam
+}
+
+impl NameTagVisibility {
+    /// Name used in UpdateTeam.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            NameTagVisibility::Always => "always",
+            NameTagVisibility::Never => "never",
+            NameTagVisibility::HideForOtherTeams => "hideForOtherTeams",
+            NameTagVisibility::HideForOwnTeam => "hideForOwnTeam"
+        }
+    }
+
+    /// Whether a member's name tag shows to a player, `same_team` telling
+    /// whether they're teammates.
+    pub fn shows_to(&self, same_team: bool) -> bool {
+        match *self {
+            NameTagVisibility::Always => true,
+            NameTagVisibility::Never => false,
+            NameTagVisibility::HideForOtherTeams => same_team,
+            NameTagVisibility::HideForOwnTeam => !same_team
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Team {
+    pub name: String,
+    /// Lowercase player names.
+    members: BTreeSet<String>,
+    /// Whether members can hurt each other.
+    pub friendly_fire: bool,
+    pub name_tag_visibility: NameTagVisibility
+}
+
+impl Team {
+    pub fn new(name: &str) -> Team {
+        Team {
+            name: name.to_string(),
+            members: BTreeSet::new(),
+            friendly_fire: true,
+            name_tag_visibility: NameTagVisibility::Always
+        }
+    }
+
+    pub fn has_member(&self, player: &str) -> bool {
+        self.members.contains(&player.to_lowercase())
+    }
+}
+
+/// Every team, players being in at most one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Teams {
+    teams: BTreeMap<String, Team>
+}
+
+impl Teams {
+    pub fn new() -> Teams {
+        Teams { teams: BTreeMap::new() }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Team> { self.teams.get(name) }
+
+    /// Adds `team`, replacing any team of the same name.
+    pub fn add(&mut self, team: Team) {
+        self.teams.insert(team.name.clone(), team);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Team> {
+        self.teams.remove(name)
+    }
+
+    /// Puts `player` in team `team`, leaving their old one. Returns whether
+    /// the team exists.
+    pub fn join(&mut self, team: &str, player: &str) -> bool {
+        if !self.teams.contains_key(team) {
+            return false;
+        }
+        self.leave(player);
+        if let Some(team) = self.teams.get_mut(team) {
+            team.members.insert(player.to_lowercase());
+        }
+        true
+    }
+
+    pub fn leave(&mut self, player: &str) {
+        let player = player.to_lowercase();
+        for team in self.teams.values_mut() {
+            team.members.remove(&player);
+        }
+    }
+
+    /// Team `player` is in.
+    pub fn team_of(&self, player: &str) -> Option<&Team> {
+        self.teams.values().find(|team| team.has_member(player))
+    }
+
+    /// Whether `attacker` may hurt `victim`, which teammates can't do
+    /// unless their team allows friendly fire.
+    pub fn can_hurt(&self, attacker: &str, victim: &str) -> bool {
+        if attacker.to_lowercase() == victim.to_lowercase() {
+            return true;
+        }
+        match self.team_of(attacker) {
+            Some(team) if team.has_member(victim) => team.friendly_fire,
+            _ => true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn friendly_fire() {
+        let mut teams = Teams::new();
+        let mut red = Team::new("red");
+        red.friendly_fire = false;
+        teams.add(red);
+        teams.add(Team::new("blue"));
+        assert!(teams.join("red", "Steve"));
+        assert!(teams.join("red", "Alex"));
+        assert!(!teams.join("green", "Alex"));
+        assert!(!teams.can_hurt("Steve", "alex"));
+        assert!(teams.can_hurt("Steve", "Steve"));
+        teams.join("blue", "Alex");
+        assert!(teams.can_hurt("Steve", "Alex"));
+        assert_eq!(teams.team_of("alex").map(|team| &team.name[..]), Some("blue"));
+        assert!(!NameTagVisibility::HideForOtherTeams.shows_to(false));
+    }
+}
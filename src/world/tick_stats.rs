@@ -0,0 +1,101 @@
+//! Tick timings, for TPS metrics.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Number of recent ticks metrics are computed over.
+pub const SAMPLES: usize = 100;
+
+/// Ticks per second the tick loop aims for.
+pub const TARGET_TPS: f64 = 20.0;
+
+/// When recent ticks started and how long they took.
+#[derive(Clone, Debug)]
+pub struct TickStats {
+    starts: VecDeque<Instant>,
+    durations: VecDeque<Duration>
+}
+
+impl TickStats {
+    pub fn new() -> TickStats {
+        TickStats {
+            starts: VecDeque::with_capacity(SAMPLES),
+            durations: VecDeque::with_capacity(SAMPLES)
+        }
+    }
+
+    /// Records a tick which started at `start` and took `duration`.
+    pub fn record(&mut self, start: Instant, duration: Duration) {
+        if self.starts.len() == SAMPLES {
+            self.starts.pop_front();
+            self.durations.pop_front();
+        }
+        self.starts.push_back(start);
+        self.durations.push_back(duration);
+    }
+
+    /// When the last recorded tick finished, `None` before the first one.
+    pub fn last_tick_end(&self) -> Option<Instant> {
+        match (self.starts.back(), self.durations.back()) {
+            (Some(&start), Some(&duration)) => Some(start + duration),
+            _ => None
+        }
+    }
+
+    /// Ticks per second over the recent ticks, the target until there are
+    /// enough of them to tell.
+    pub fn tps(&self) -> f64 {
+        let (first, last) = match (self.starts.front(), self.starts.back()) {
+            (Some(&first), Some(&last)) if self.starts.len() > 1 => (first, last),
+            _ => return TARGET_TPS
+        };
+        let elapsed = last.duration_since(first);
+        let seconds = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+        if seconds <= 0.0 {
+            return TARGET_TPS;
+        }
+        ((self.starts.len() - 1) as f64 / seconds).min(TARGET_TPS)
+    }
+
+    /// Average time spent ticking over the recent ticks.
+    pub fn mean_tick_time(&self) -> Duration {
+        if self.durations.is_empty() {
+            return Duration::from_millis(0);
+        }
+        let total = self.durations.iter().fold(Duration::from_millis(0), |total, &duration| total + duration);
+        total / self.durations.len() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn slow_ticks() {
+        let mut stats = TickStats::new();
+        assert_eq!(stats.tps(), TARGET_TPS);
+        let start = Instant::now();
+        // Ticks taking 100ms each, running late
+        for i in 0..11 {
+            stats.record(start + Duration::from_millis(i * 100), Duration::from_millis(100));
+        }
+        assert!((stats.tps() - 10.0).abs() < 1e-6);
+        assert_eq!(stats.mean_tick_time(), Duration::from_millis(100));
+        assert_eq!(stats.last_tick_end(), Some(start + Duration::from_millis(1100)));
+    }
+
+    #[test]
+    fn keeps_recent_ticks() {
+        let mut stats = TickStats::new();
+        let start = Instant::now();
+        for i in 0..(SAMPLES as u64 * 2) {
+            let duration = if i < SAMPLES as u64 { 40 } else { 10 };
+            stats.record(start + Duration::from_millis(i * 50), Duration::from_millis(duration));
+        }
+        assert_eq!(stats.mean_tick_time(), Duration::from_millis(10));
+        assert_eq!(stats.tps(), TARGET_TPS);
+    }
+}
@@ -0,0 +1,129 @@
+//! Chunk tickets, keeping chunks loaded whether or not players are near.
+
+use std::collections::BTreeSet;
+
+use types::hyphenated;
+
+use uuid::Uuid;
+
+/// Source of the ticket keeping the spawn chunks loaded.
+pub const SPAWN: &'static str = "spawn";
+
+/// Radius in chunks of the spawn area kept loaded, as in vanilla.
+pub const SPAWN_RADIUS: i32 = 8;
+
+/// Source of the tickets taken out for player `uuid`, which are removed
+/// when they leave the world.
+pub fn player_source(uuid: &Uuid) -> String {
+    format!("player/{}", hyphenated(uuid))
+}
+
+/// A request to keep the chunks within `radius` of chunk `center` loaded.
+/// `source` tells who asked, so they can take their tickets back.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ticket {
+    pub source: String,
+    pub center: (i32, i32),
+    pub radius: i32
+}
+
+impl Ticket {
+    pub fn new<S: Into<String>>(source: S, center: (i32, i32), radius: i32) -> Ticket {
+        Ticket { source: source.into(), center: center, radius: radius.max(0) }
+    }
+
+    /// Whether the ticket keeps chunk `chunk` loaded.
+    pub fn covers(&self, chunk: (i32, i32)) -> bool {
+        (chunk.0 - self.center.0).abs() <= self.radius && (chunk.1 - self.center.1).abs() <= self.radius
+    }
+
+    /// Chunks the ticket keeps loaded.
+    pub fn chunks(&self) -> Vec<(i32, i32)> {
+        chunks_within(self.center, self.radius)
+    }
+}
+
+/// Chunks at most `radius` chunks away from chunk `center` on either axis.
+pub fn chunks_within(center: (i32, i32), radius: i32) -> Vec<(i32, i32)> {
+    let mut chunks = vec![];
+    for z in center.1 - radius..center.1 + radius + 1 {
+        for x in center.0 - radius..center.0 + radius + 1 {
+            chunks.push((x, z));
+        }
+    }
+    chunks
+}
+
+/// Tickets of a world.
+#[derive(Clone, Debug, Default)]
+pub struct ChunkTickets {
+    tickets: Vec<Ticket>
+}
+
+impl ChunkTickets {
+    pub fn new() -> ChunkTickets {
+        ChunkTickets { tickets: vec![] }
+    }
+
+    pub fn add(&mut self, ticket: Ticket) {
+        self.tickets.push(ticket);
+    }
+
+    /// Takes back the tickets of `source` centered on `center`. Returns
+    /// how many there were.
+    pub fn remove(&mut self, source: &str, center: (i32, i32)) -> usize {
+        let before = self.tickets.len();
+        self.tickets.retain(|ticket| ticket.source != source || ticket.center != center);
+        before - self.tickets.len()
+    }
+
+    /// Takes back every ticket of `source`. Returns how many there were.
+    pub fn remove_source(&mut self, source: &str) -> usize {
+        let before = self.tickets.len();
+        self.tickets.retain(|ticket| ticket.source != source);
+        before - self.tickets.len()
+    }
+
+    pub fn tickets(&self) -> &[Ticket] {
+        &self.tickets
+    }
+
+    /// Whether any ticket keeps chunk `chunk` loaded.
+    pub fn is_forced(&self, chunk: (i32, i32)) -> bool {
+        self.tickets.iter().any(|ticket| ticket.covers(chunk))
+    }
+
+    /// Every chunk some ticket keeps loaded.
+    pub fn forced_chunks(&self) -> BTreeSet<(i32, i32)> {
+        self.tickets.iter().flat_map(|ticket| ticket.chunks()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use types::hyphenated;
+
+    use uuid::Uuid;
+
+    #[test]
+    fn tickets() {
+        let mut tickets = ChunkTickets::new();
+        tickets.add(Ticket::new(SPAWN, (0, 0), 1));
+        tickets.add(Ticket::new("farm", (5, 5), 0));
+        tickets.add(Ticket::new("farm", (-5, 0), 0));
+        assert_eq!(tickets.forced_chunks().len(), 11);
+        assert!(tickets.is_forced((1, -1)));
+        assert!(!tickets.is_forced((2, 0)));
+        assert_eq!(tickets.remove("farm", (5, 5)), 1);
+        assert!(!tickets.is_forced((5, 5)));
+        assert!(tickets.is_forced((-5, 0)));
+        assert_eq!(tickets.remove_source("farm"), 1);
+        assert_eq!(tickets.tickets(), &[Ticket::new(SPAWN, (0, 0), 1)][..]);
+        assert_eq!(chunks_within((3, -3), 0), vec![(3, -3)]);
+        let uuid = Uuid::new_v4();
+        assert_eq!(player_source(&uuid), format!("player/{}", hyphenated(&uuid)));
+        assert_eq!(chunks_within((0, 0), 2).len(), 25);
+    }
+}
@@ -0,0 +1,122 @@
+//! Rain and thunder, which come and go at random like in vanilla.
+
+use packet::play::clientbound::ChangeGameState;
+
+use rand::Rng;
+
+/// ChangeGameState reasons for the weather.
+pub const BEGIN_RAINING: u8 = 1;
+pub const END_RAINING: u8 = 2;
+pub const RAIN_LEVEL: u8 = 7;
+pub const THUNDER_LEVEL: u8 = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Weather {
+    pub raining: bool,
+    pub thundering: bool,
+    /// Ticks until it starts or stops raining, 0 to pick anew.
+    pub rain_time: i32,
+    /// Ticks until thunder starts or stops, 0 to pick anew. It only thunders
+    /// while it rains.
+    pub thunder_time: i32
+}
+
+impl Weather {
+    /// Clear weather, for however long the next tick picks.
+    pub fn new() -> Weather {
+        Weather { raining: false, thundering: false, rain_time: 0, thunder_time: 0 }
+    }
+
+    /// Rain with thunder, rain alone or neither for `ticks`, then whatever
+    /// the next tick picks.
+    pub fn lasting(raining: bool, thundering: bool, ticks: i32) -> Weather {
+        let ticks = ticks.max(1);
+        Weather { raining: raining, thundering: raining && thundering, rain_time: ticks, thunder_time: ticks }
+    }
+
+    /// Whether there's a thunderstorm, which players may sleep through.
+    pub fn storming(&self) -> bool {
+        self.raining && self.thundering
+    }
+
+    /// Moves the weather one tick forward. Returns whether rain or thunder
+    /// started or stopped.
+    pub fn tick<R: Rng>(&mut self, rng: &mut R) -> bool {
+        let (raining, storming) = (self.raining, self.storming());
+        if self.thunder_time <= 0 {
+            self.thunder_time = if self.thundering { rng.gen_range(3600, 15600) } else { rng.gen_range(12000, 180000) };
+        } else {
+            self.thunder_time -= 1;
+            if self.thunder_time == 0 {
+                self.thundering = !self.thundering;
+            }
+        }
+        if self.rain_time <= 0 {
+            self.rain_time = if self.raining { rng.gen_range(12000, 24000) } else { rng.gen_range(12000, 180000) };
+        } else {
+            self.rain_time -= 1;
+            if self.rain_time == 0 {
+                self.raining = !self.raining;
+            }
+        }
+        self.raining != raining || self.storming() != storming
+    }
+
+    /// Stops rain and thunder, as a night's sleep does, timers start over.
+    /// Returns whether it was raining.
+    pub fn clear(&mut self) -> bool {
+        let raining = self.raining;
+        *self = Weather::new();
+        raining
+    }
+
+    /// Packets telling clients what the weather is like.
+    pub fn packets(&self) -> Vec<ChangeGameState> {
+        if !self.raining {
+            return vec![ChangeGameState { reason: END_RAINING, value: 0.0 }];
+        }
+        vec![
+            ChangeGameState { reason: BEGIN_RAINING, value: 0.0 },
+            ChangeGameState { reason: RAIN_LEVEL, value: 1.0 },
+            ChangeGameState { reason: THUNDER_LEVEL, value: if self.thundering { 1.0 } else { 0.0 } }
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn cycle() {
+        let mut rng = StdRng::from_seed([1; 32]);
+        let mut weather = Weather::new();
+        assert!(!weather.tick(&mut rng));
+        assert!(weather.rain_time >= 12000);
+        // Rain comes once its time is up, and lasts
+        weather.rain_time = 1;
+        assert!(weather.tick(&mut rng));
+        assert!(weather.raining);
+        assert!(!weather.tick(&mut rng));
+        assert!(weather.rain_time >= 12000 && weather.rain_time < 24000);
+        assert_eq!(weather.packets()[0].reason, BEGIN_RAINING);
+
+        weather.thundering = true;
+        assert!(weather.storming());
+        assert!(weather.clear());
+        assert!(!weather.storming());
+        assert_eq!(weather.packets().len(), 1);
+        assert!(!weather.clear());
+
+        // Set weather holds until its time is up
+        let mut weather = Weather::lasting(true, true, 2);
+        assert!(weather.storming());
+        assert!(!weather.tick(&mut rng));
+        assert!(weather.tick(&mut rng));
+        assert!(!weather.raining && !weather.thundering);
+        assert!(!Weather::lasting(false, true, 100).thundering);
+    }
+}
@@ -0,0 +1,93 @@
+//! Decodes every packet in `tests/fixtures` and, where encoding is
+//! deterministic, checks it's encoded back to the very same bytes.
+
+extern crate hematite_server;
+
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use hematite_server::packet::{self, PacketRead, PacketWrite};
+
+/// A fixture: the bytes of one packet, length included, and whether
+/// encoding the decoded packet must give them back.
+struct Fixture {
+    path: PathBuf,
+    bytes: Vec<u8>,
+    deterministic: bool
+}
+
+impl Fixture {
+    /// Reads a `.hex` fixture: hex bytes, with `#` starting comments and
+    /// `# deterministic: false` marking packets which encode differently,
+    /// e.g. JSON chat, whose key order isn't kept.
+    fn load(path: &Path) -> Fixture {
+        let mut text = String::new();
+        File::open(path).and_then(|mut file| file.read_to_string(&mut text)).unwrap();
+        let mut bytes = vec![];
+        let mut deterministic = true;
+        for line in text.lines() {
+            let (data, comment) = match line.find('#') {
+                Some(start) => (&line[..start], &line[start + 1..]),
+                None => (line, "")
+            };
+            if comment.trim() == "deterministic: false" {
+                deterministic = false;
+            }
+            for byte in data.split_whitespace() {
+                bytes.push(u8::from_str_radix(byte, 16).unwrap_or_else(|_| panic!("{}: bad byte {:?}", path.display(), byte)));
+            }
+        }
+        Fixture { path: path.to_path_buf(), bytes: bytes, deterministic: deterministic }
+    }
+}
+
+fn fixtures(dir: &Path, found: &mut Vec<Fixture>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            fixtures(&path, found);
+        } else if path.extension().map_or(false, |ext| ext == "hex") {
+            found.push(Fixture::load(&path));
+        }
+    }
+}
+
+/// Decodes the packet in `bytes` as a `P`, which must use all of them, and
+/// encodes it again.
+fn round_trip<P: PacketRead + PacketWrite>(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut src = Cursor::new(bytes);
+    let packet = try!(P::read(&mut src));
+    if src.position() as usize != bytes.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} bytes left over", bytes.len() - src.position() as usize)));
+    }
+    let mut dst = vec![];
+    try!(packet.write(&mut dst));
+    Ok(dst)
+}
+
+#[test]
+fn fixtures_round_trip() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures");
+    let mut found = vec![];
+    fixtures(&root, &mut found);
+    assert!(!found.is_empty(), "no fixtures in {}", root.display());
+    for fixture in found {
+        let kind = fixture.path.strip_prefix(&root).unwrap().parent().unwrap().to_path_buf();
+        let kind: Vec<_> = kind.iter().map(|part| part.to_str().unwrap()).collect();
+        let encoded = match &kind[..] {
+            ["handshake", "serverbound"] => round_trip::<packet::handshake::Packet>(&fixture.bytes),
+            ["status", "clientbound"] => round_trip::<packet::status::clientbound::Packet>(&fixture.bytes),
+            ["status", "serverbound"] => round_trip::<packet::status::serverbound::Packet>(&fixture.bytes),
+            ["login", "clientbound"] => round_trip::<packet::login::clientbound::Packet>(&fixture.bytes),
+            ["login", "serverbound"] => round_trip::<packet::login::serverbound::Packet>(&fixture.bytes),
+            ["play", "clientbound"] => round_trip::<packet::play::clientbound::Packet>(&fixture.bytes),
+            ["play", "serverbound"] => round_trip::<packet::play::serverbound::Packet>(&fixture.bytes),
+            _ => panic!("{}: fixtures go in <state>/<direction>/", fixture.path.display())
+        };
+        let encoded = encoded.unwrap_or_else(|err| panic!("{}: {}", fixture.path.display(), err));
+        if fixture.deterministic {
+            assert!(encoded == fixture.bytes, "{}: encoded as {:?}", fixture.path.display(), encoded);
+        }
+    }
+}